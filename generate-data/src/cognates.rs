@@ -0,0 +1,174 @@
+//! Pairs words that look alike and mean the same thing in two target languages (French "nation"
+//! and Spanish "nación"), so a deck in one course can start from what the user knows in another.
+//! Both dictionaries have to be for the same native language: lemmas are paired when they have the
+//! same part of speech, are spelled alike once accents are dropped, and share a translation. The
+//! shared translation keeps out false friends like "embarazada" and "embarrassé".
+
+use std::collections::BTreeMap;
+
+use language_utils::{DictionaryEntry, Heteronym};
+use unicode_normalization::UnicodeNormalization;
+use unicode_normalization::char::is_combining_mark;
+
+/// Shorter lemmas look alike by chance too often
+const MIN_LEMMA_CHARS: usize = 4;
+/// The most edits between two cognates, as a fraction of the longer lemma
+const MAX_EDIT_FRACTION: f64 = 0.3;
+
+/// Lowercase, without accents
+fn normalize(lemma: &str) -> String {
+    lemma
+        .nfd()
+        .filter(|c| !is_combining_mark(*c))
+        .collect::<String>()
+        .to_lowercase()
+}
+
+fn edit_distance(a: &[char], b: &[char]) -> usize {
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, a_char) in a.iter().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, b_char) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(a_char != b_char);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+/// The entry's translations, lowercased. Definitions like "to eat, to dine" count as two.
+fn translations(entry: &DictionaryEntry) -> Vec<String> {
+    entry
+        .definitions
+        .iter()
+        .flat_map(|definition| definition.native.split([',', ';']))
+        .map(|translation| translation.trim().to_lowercase())
+        .filter(|translation| !translation.is_empty())
+        .collect()
+}
+
+struct Lemma<'a> {
+    heteronym: &'a Heteronym<String>,
+    chars: Vec<char>,
+    translations: Vec<String>,
+}
+
+/// The lemmas worth comparing, by part of speech and first letter. Cognates almost always start
+/// with the same letter, so only lemmas in the same bucket are compared.
+fn buckets(
+    dictionary: &BTreeMap<Heteronym<String>, DictionaryEntry>,
+) -> BTreeMap<(language_utils::PartOfSpeech, char), Vec<Lemma<'_>>> {
+    let mut buckets: BTreeMap<_, Vec<_>> = BTreeMap::new();
+    for (heteronym, entry) in dictionary {
+        if heteronym.word != heteronym.lemma {
+            continue;
+        }
+        let chars = normalize(&heteronym.lemma).chars().collect::<Vec<_>>();
+        if chars.len() < MIN_LEMMA_CHARS {
+            continue;
+        }
+        buckets
+            .entry((heteronym.pos, chars[0]))
+            .or_default()
+            .push(Lemma {
+                heteronym,
+                chars,
+                translations: translations(entry),
+            });
+    }
+    buckets
+}
+
+/// Each lemma in `other_dictionary` with its closest cognate in `dictionary`, as
+/// `(other heteronym, heteronym)`
+pub fn cognates(
+    dictionary: &BTreeMap<Heteronym<String>, DictionaryEntry>,
+    other_dictionary: &BTreeMap<Heteronym<String>, DictionaryEntry>,
+) -> Vec<(Heteronym<String>, Heteronym<String>)> {
+    let ours = buckets(dictionary);
+    let mut cognates = Vec::new();
+    for (key, others) in buckets(other_dictionary) {
+        let Some(candidates) = ours.get(&key) else {
+            continue;
+        };
+        for other in others {
+            let closest = candidates
+                .iter()
+                .filter(|lemma| {
+                    lemma
+                        .translations
+                        .iter()
+                        .any(|translation| other.translations.contains(translation))
+                })
+                .map(|lemma| (edit_distance(&other.chars, &lemma.chars), lemma))
+                .filter(|(distance, lemma)| {
+                    let longer = other.chars.len().max(lemma.chars.len());
+                    *distance as f64 <= longer as f64 * MAX_EDIT_FRACTION
+                })
+                .min_by_key(|(distance, lemma)| (*distance, lemma.heteronym));
+            if let Some((_, lemma)) = closest {
+                cognates.push((other.heteronym.clone(), lemma.heteronym.clone()));
+            }
+        }
+    }
+    cognates
+}
+
+#[cfg(test)]
+mod tests {
+    use language_utils::{PartOfSpeech, TargetToNativeWord};
+
+    use super::*;
+
+    fn entry(lemma: &str, pos: PartOfSpeech, native: &str) -> (Heteronym<String>, DictionaryEntry) {
+        (
+            Heteronym {
+                word: lemma.to_string(),
+                lemma: lemma.to_string(),
+                pos,
+            },
+            DictionaryEntry {
+                target_language_word: lemma.to_string(),
+                definitions: vec![TargetToNativeWord {
+                    native: native.to_string(),
+                    note: None,
+                    example_sentence_target_language: String::new(),
+                    example_sentence_native_language: String::new(),
+                }],
+                morphology: Vec::new(),
+                notes: None,
+            },
+        )
+    }
+
+    #[test]
+    fn test_pairs_lemmas_that_look_and_mean_alike() {
+        let spanish = BTreeMap::from([
+            entry("nación", PartOfSpeech::Noun, "nation"),
+            entry("importante", PartOfSpeech::Adj, "important, significant"),
+            entry("embarazada", PartOfSpeech::Adj, "pregnant"),
+            entry("comer", PartOfSpeech::Verb, "to eat"),
+        ]);
+        let french = BTreeMap::from([
+            entry("nation", PartOfSpeech::Noun, "nation"),
+            entry("important", PartOfSpeech::Adj, "important"),
+            entry("embarrassé", PartOfSpeech::Adj, "embarrassed"),
+            entry("manger", PartOfSpeech::Verb, "to eat"),
+            // Spelled like the Spanish adjective, but a noun
+            entry("importance", PartOfSpeech::Noun, "important"),
+        ]);
+
+        let pairs = cognates(&spanish, &french)
+            .into_iter()
+            .map(|(french, spanish)| (french.lemma, spanish.lemma))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            pairs,
+            vec![
+                ("important".to_string(), "importante".to_string()),
+                ("nation".to_string(), "nación".to_string()),
+            ]
+        );
+    }
+}
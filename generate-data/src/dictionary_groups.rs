@@ -0,0 +1,82 @@
+//! Groups the dictionary's heteronyms by how they're written, so the app's dictionary can show one
+//! entry for "est" with a section for each part of speech instead of a near-duplicate row per
+//! heteronym. Words that only differ by case ("Est" at the start of a sentence) are grouped too.
+
+use std::collections::BTreeMap;
+
+use language_utils::{FrequencyEntry, Heteronym, Lexeme};
+
+/// The heteronyms grouped by their lowercased word. The most common group comes first, and within
+/// a group the most common heteronym comes first, so its word is the one to show.
+pub fn dictionary_groups<'a>(
+    heteronyms: impl IntoIterator<Item = &'a Heteronym<String>>,
+    frequencies: &[FrequencyEntry<String>],
+) -> Vec<Vec<Heteronym<String>>> {
+    let counts: BTreeMap<&Heteronym<String>, u32> = frequencies
+        .iter()
+        .filter_map(|frequency| match &frequency.lexeme {
+            Lexeme::Heteronym(heteronym) => Some((heteronym, frequency.count)),
+            Lexeme::Multiword(_) => None,
+        })
+        .collect();
+    let count = |heteronym: &Heteronym<String>| counts.get(heteronym).copied().unwrap_or(0);
+
+    let mut groups: BTreeMap<String, Vec<Heteronym<String>>> = BTreeMap::new();
+    for heteronym in heteronyms {
+        groups
+            .entry(heteronym.word.to_lowercase())
+            .or_default()
+            .push(heteronym.clone());
+    }
+
+    let mut groups: Vec<_> = groups.into_values().collect();
+    for group in &mut groups {
+        group.sort_by(|a, b| count(b).cmp(&count(a)).then(a.cmp(b)));
+    }
+    // Stable, so groups that are equally common stay in alphabetical order
+    groups.sort_by_key(|group| std::cmp::Reverse(count(&group[0])));
+    groups
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use language_utils::PartOfSpeech;
+
+    fn heteronym(word: &str, lemma: &str, pos: PartOfSpeech) -> Heteronym<String> {
+        Heteronym {
+            word: word.to_string(),
+            lemma: lemma.to_string(),
+            pos,
+        }
+    }
+
+    fn frequency(heteronym: &Heteronym<String>, count: u32) -> FrequencyEntry<String> {
+        FrequencyEntry {
+            lexeme: Lexeme::Heteronym(heteronym.clone()),
+            count,
+        }
+    }
+
+    #[test]
+    fn test_groups_heteronyms_by_written_word() {
+        let est_verb = heteronym("est", "être", PartOfSpeech::Verb);
+        let est_noun = heteronym("est", "est", PartOfSpeech::Noun);
+        let est_capitalized = heteronym("Est", "est", PartOfSpeech::Propn);
+        let maison = heteronym("maison", "maison", PartOfSpeech::Noun);
+        let frequencies = [
+            frequency(&est_verb, 500),
+            frequency(&maison, 40),
+            frequency(&est_noun, 10),
+        ];
+
+        let groups = dictionary_groups(
+            [&est_noun, &maison, &est_capitalized, &est_verb],
+            &frequencies,
+        );
+        assert_eq!(
+            groups,
+            vec![vec![est_verb, est_noun, est_capitalized], vec![maison]]
+        );
+    }
+}
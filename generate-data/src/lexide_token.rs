@@ -302,6 +302,7 @@ pub(crate) fn lexide_token_to_literal(
             text: "".to_string(),
             whitespace,
             heteronym: None,
+            morph: None,
         };
     }
 
@@ -319,6 +320,7 @@ pub(crate) fn lexide_token_to_literal(
         text: token.text.text.clone(),
         whitespace: token.whitespace.clone(),
         heteronym,
+        morph: None,
     }
 }
 
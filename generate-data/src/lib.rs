@@ -1,17 +1,23 @@
 #[cfg(test)]
 mod db_info;
 
+pub mod cognates;
 pub mod dict;
+pub mod dictionary_groups;
 pub mod disambiguation_practice;
 pub mod frequencies;
 pub mod lexide_token;
 pub mod morphology_analysis;
 pub mod nlp;
+pub mod posters;
 pub mod pronunciation_patterns;
 pub mod pronunciations;
 pub mod proper_noun_filter;
 pub mod read_anki;
+pub mod songs;
 pub mod target_sentences;
 pub mod tatoeba;
 pub mod wiktionary_conjugations;
 pub mod wiktionary_terms;
+pub mod word_families;
+pub mod word_notes;
@@ -271,27 +271,34 @@ async fn main() -> anyhow::Result<()> {
         let combined_freq_dir = target_language_dir.join("frequency_lists/combined");
         std::fs::create_dir_all(&combined_freq_dir)?;
         let frequencies_file = combined_freq_dir.join("frequencies.jsonl");
-        {
-            let frequencies = generate_data::frequencies::compute_frequencies(
-                &nlp_sentences,
-                course.target_language,
-                &banned_words,
-            );
-
-            generate_data::frequencies::write_frequencies_file(frequencies, &frequencies_file)?;
-        }
-        let frequencies = {
+        let read_frequencies = || -> anyhow::Result<Vec<language_utils::FrequencyEntry<String>>> {
             let file = File::open(&frequencies_file)?;
             let reader = BufReader::new(file);
             let frequencies = reader
                 .lines()
                 .map(|line| serde_json::from_str(&line.unwrap()))
                 .collect::<Result<Vec<language_utils::FrequencyEntry<String>>, _>>()?;
-            frequencies
+            Ok(frequencies
                 .into_iter()
                 .filter(|entry| entry.count > 3)
-                .collect::<Vec<_>>()
+                .collect::<Vec<_>>())
+        };
+        // The previous build's frequencies, to see how the ranks moved (see `frequency_drift`)
+        let previous_frequencies = if frequencies_file.exists() {
+            read_frequencies()?
+        } else {
+            Vec::new()
         };
+        {
+            let frequencies = generate_data::frequencies::compute_frequencies(
+                &nlp_sentences,
+                course.target_language,
+                &banned_words,
+            );
+
+            generate_data::frequencies::write_frequencies_file(frequencies, &frequencies_file)?;
+        }
+        let frequencies = read_frequencies()?;
 
         // create and write dictionary
         let dict_file = native_specific_dir.join("dictionary.jsonl");
@@ -348,6 +355,29 @@ async fn main() -> anyhow::Result<()> {
                 .collect()
         };
 
+        // Etymology and usage notes for the lemmas, written separately since the dictionary file
+        // is read back as `DictionaryEntryThoughts`
+        let dictionary = {
+            let word_notes = generate_data::word_notes::create_word_notes(
+                *course,
+                &dictionary,
+                Path::new("./.cache/wiktionary"),
+            )
+            .await?;
+            let mut file = File::create(native_specific_dir.join("word_notes.jsonl"))?;
+            for entry in &word_notes {
+                let json = serde_json::to_string(&entry)?;
+                writeln!(file, "{json}")?;
+            }
+            dictionary
+                .into_iter()
+                .map(|(heteronym, mut entry)| {
+                    entry.notes = word_notes.get(&heteronym).cloned();
+                    (heteronym, entry)
+                })
+                .collect::<BTreeMap<_, _>>()
+        };
+
         // Generate conjugations/declensions JSONL
         {
             let morphology_groups = morphology_analysis::analyze_morphology(&dictionary);
@@ -838,6 +868,8 @@ async fn main() -> anyhow::Result<()> {
             if metadata_file.exists() {
                 let metadata_content = std::fs::read_to_string(&metadata_file)?;
                 let posters_dir = movies_dir.join("posters");
+                // Shared by every course, since images are named by their hash
+                let images_dir = PathBuf::from("./out/images");
                 let mut movies = FxHashMap::default();
 
                 for line in metadata_content.lines() {
@@ -846,12 +878,20 @@ async fn main() -> anyhow::Result<()> {
                     }
                     let basic: language_utils::MovieMetadataBasic = serde_json::from_str(line)?;
 
-                    // Convert to full MovieMetadata and load poster bytes from separate file
+                    // Convert to full MovieMetadata and load the poster from a separate file
                     let mut movie: language_utils::MovieMetadata = basic.into();
                     let poster_path = posters_dir.join(format!("{}.jpg", movie.id));
                     if poster_path.exists() {
                         if let Ok(bytes) = std::fs::read(&poster_path) {
-                            movie.poster_bytes = Some(bytes);
+                            match generate_data::posters::process_poster(&bytes, &images_dir) {
+                                Ok(poster) => {
+                                    movie.poster_thumbnail = Some(poster.thumbnail);
+                                    movie.posters = poster.assets;
+                                }
+                                Err(e) => {
+                                    eprintln!("Skipping poster {}: {e:?}", poster_path.display())
+                                }
+                            }
                         }
                     }
 
@@ -895,12 +935,221 @@ async fn main() -> anyhow::Result<()> {
             FxHashMap::default()
         };
 
+        // UI phrases for both languages of the course: the built-in ones, overridden or added to by
+        // the language's ui_strings.json
+        let ui_strings = {
+            let mut ui_strings = BTreeMap::new();
+            for language in [course.target_language, course.native_language] {
+                let mut strings = language_utils::ui_strings::builtins(language);
+                let ui_strings_file = PathBuf::from(format!(
+                    "./generate-data/data/{}/ui_strings.json",
+                    language.iso_639_3()
+                ));
+                if ui_strings_file.exists() {
+                    let content = std::fs::read_to_string(&ui_strings_file)
+                        .context("Failed to read UI strings file")?;
+                    let overrides: BTreeMap<String, String> = serde_json::from_str(&content)
+                        .context("Failed to parse UI strings file")?;
+                    strings.extend(overrides);
+                }
+                ui_strings.insert(language.iso_639_3().to_string(), strings);
+            }
+            ui_strings
+        };
+
+        // Spellings that differ between standards of the course's languages (US and UK English,
+        // German before and after the spelling reform...), from the language's
+        // spelling_variants.json, so grading accepts every standard's spelling
+        let spelling_variants = {
+            let mut spelling_variants = BTreeMap::new();
+            for language in [course.target_language, course.native_language] {
+                let spelling_variants_file = PathBuf::from(format!(
+                    "./generate-data/data/{}/spelling_variants.json",
+                    language.iso_639_3()
+                ));
+                if spelling_variants_file.exists() {
+                    let content = std::fs::read_to_string(&spelling_variants_file)
+                        .context("Failed to read spelling variants file")?;
+                    let table: language_utils::spelling_variants::SpellingVariantTable =
+                        serde_json::from_str(&content)
+                            .context("Failed to parse spelling variants file")?;
+                    let variants = language_utils::spelling_variants::SpellingVariants::new(table)
+                        .map_err(|e| anyhow::anyhow!("Invalid spelling variants file: {e}"))?;
+                    spelling_variants.insert(language.iso_639_3().to_string(), variants);
+                }
+            }
+            spelling_variants
+        };
+
+        // Sentences containing a word from the profanity list, so users can choose not to see them
+        let profane_sentences = {
+            let profane_words_file = source_data_path.join("profane_words.jsonl");
+            let profane_words = if profane_words_file.exists() {
+                let content = std::fs::read_to_string(profane_words_file)
+                    .context("Failed to read profane words file")?;
+                content
+                    .lines()
+                    .map(|line| line.trim())
+                    .filter(|line| !line.is_empty())
+                    .map(|line| serde_json::from_str::<language_utils::Heteronym<String>>(line))
+                    .collect::<Result<std::collections::HashSet<_>, _>>()
+                    .context("Failed to parse profane words file")?
+            } else {
+                std::collections::HashSet::new()
+            };
+            nlp_sentences
+                .iter()
+                .filter(|(_, info)| {
+                    info.words.iter().any(|word| {
+                        word.heteronym
+                            .as_ref()
+                            .is_some_and(|heteronym| profane_words.contains(heteronym))
+                    })
+                })
+                .map(|(sentence, _)| sentence.clone())
+                .collect::<Vec<_>>()
+        };
+
+        // Recommended FSRS parameters, for languages where the defaults don't fit well
+        let fsrs_preset = {
+            let fsrs_preset_file = source_data_path.join("fsrs_preset.json");
+            if fsrs_preset_file.exists() {
+                let content = std::fs::read_to_string(&fsrs_preset_file)
+                    .context("Failed to read FSRS preset file")?;
+                Some(
+                    serde_json::from_str::<language_utils::fsrs_parameters::FsrsParameters>(
+                        &content,
+                    )
+                    .context("Failed to parse FSRS preset file")?,
+                )
+            } else {
+                None
+            }
+        };
+
+        // What it takes to finish the course, for courses where the default doesn't fit
+        let completion_criteria = {
+            let completion_criteria_file = source_data_path.join("completion_criteria.json");
+            if completion_criteria_file.exists() {
+                let content = std::fs::read_to_string(&completion_criteria_file)
+                    .context("Failed to read completion criteria file")?;
+                Some(
+                    serde_json::from_str::<language_utils::course_completion::CompletionCriteria>(
+                        &content,
+                    )
+                    .context("Failed to parse completion criteria file")?,
+                )
+            } else {
+                None
+            }
+        };
+
+        // One dictionary entry per written word, rather than one per heteronym
+        let dictionary_groups =
+            generate_data::dictionary_groups::dictionary_groups(dictionary.keys(), &frequencies);
+
+        // Related lemmas, so flashcards can show a word's family
+        let word_families = generate_data::word_families::word_families(dictionary.keys());
+
+        // Cognates with the other courses for the same native language, so a deck in this course
+        // can start from what the user knows in theirs. A course whose dictionary hasn't been
+        // written yet (because it comes later in `COURSES`) is paired on the next run.
+        let cognates = {
+            let mut cognates = BTreeMap::new();
+            for other in COURSES.iter().filter(|other| {
+                other.native_language == course.native_language
+                    && other.target_language != course.target_language
+                    && other.target_language.writing_system()
+                        == course.target_language.writing_system()
+            }) {
+                let other_dict_file = PathBuf::from(format!(
+                    "./out/{}_for_{}/dictionary.jsonl",
+                    other.target_language.iso_639_3(),
+                    other.native_language.iso_639_3()
+                ));
+                if !other_dict_file.exists() {
+                    continue;
+                }
+                let other_dictionary = BufReader::new(File::open(&other_dict_file)?)
+                    .lines()
+                    .map(|line| line.unwrap())
+                    .filter(|line| !line.is_empty())
+                    .map(|line| {
+                        serde_json::from_str::<(
+                            language_utils::Heteronym<String>,
+                            (
+                                language_utils::DictionaryEntryThoughts,
+                                Vec<language_utils::features::Morphology>,
+                            ),
+                        )>(&line)
+                        .map(|(heteronym, entry)| (heteronym, entry.into()))
+                    })
+                    .collect::<Result<BTreeMap<_, _>, _>>()
+                    .context("Failed to parse another course's dictionary")?;
+                let pairs = generate_data::cognates::cognates(&dictionary, &other_dictionary);
+                println!(
+                    "Found {} cognates with {}",
+                    pairs.len(),
+                    other.target_language
+                );
+                cognates.insert(other.target_language.iso_639_3().to_string(), pairs);
+            }
+            cognates
+        };
+
+        // Stable ids for the lexemes, carried over from earlier builds so decks can follow lexemes
+        // whose strings changed. The table is source data: commit it along with the pack.
+        let lexeme_ids = {
+            let lexeme_ids_file = source_data_path.join("lexeme_ids.jsonl");
+            let mut table = if lexeme_ids_file.exists() {
+                let content = std::fs::read_to_string(&lexeme_ids_file)
+                    .context("Failed to read lexeme ids file")?;
+                language_utils::lexeme_ids::LexemeIdTable::from_entries(
+                    content
+                        .lines()
+                        .filter(|line| !line.trim().is_empty())
+                        .map(serde_json::from_str)
+                        .collect::<Result<Vec<_>, _>>()
+                        .context("Failed to parse lexeme ids file")?,
+                )
+            } else {
+                language_utils::lexeme_ids::LexemeIdTable::default()
+            };
+            table.assign(frequencies.iter().map(|freq| &freq.lexeme));
+            let entries = table.entries();
+            let mut writer = BufWriter::new(File::create(&lexeme_ids_file)?);
+            for entry in &entries {
+                writeln!(writer, "{}", serde_json::to_string(entry)?)?;
+            }
+            writer.flush()?;
+            entries
+        };
+
+        let frequency_drift = language_utils::frequency_drift::rank_drift(
+            &previous_frequencies,
+            &frequencies,
+            &language_utils::lexeme_ids::LexemeIdTable::from_entries(lexeme_ids.iter().cloned()),
+        );
+        println!(
+            "{} lexemes changed frequency rank by at least {}x since the previous build",
+            frequency_drift.len(),
+            language_utils::frequency_drift::REPORTED_FACTOR
+        );
+
+        // Timed song lyrics, for singing along
+        let songs = generate_data::songs::load_songs(
+            source_data_path,
+            course.target_language,
+            &nlp_sentences,
+        )?;
+
         // Create consolidated data structure
         let consolidated_data = language_utils::ConsolidatedLanguageData {
             target_language_sentences,
             translations,
             nlp_sentences,
             dictionary,
+            dictionary_groups,
             phrasebook,
             frequencies,
             movie_frequencies,
@@ -910,6 +1159,16 @@ async fn main() -> anyhow::Result<()> {
             homophone_practice,
             movies,
             sentence_sources,
+            ui_strings,
+            profane_sentences,
+            fsrs_preset,
+            completion_criteria,
+            spelling_variants,
+            word_families,
+            lexeme_ids,
+            songs,
+            cognates,
+            frequency_drift,
         };
 
         let language_pack = language_utils::language_pack::LanguagePack::new(consolidated_data);
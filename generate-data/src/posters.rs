@@ -0,0 +1,107 @@
+//! Movie posters. The originals in sentence-sources/movies/posters/ are too big to put in every
+//! user's language pack, so the pack only gets a small thumbnail. The original and a few smaller
+//! copies are written to `out/images/`, named by their hash, to be uploaded next to the packs and
+//! fetched when the app shows them.
+
+use std::io::Cursor;
+use std::path::Path;
+
+use anyhow::Context;
+use image::codecs::jpeg::JpegEncoder;
+use image::imageops::FilterType;
+use language_utils::{ImageAsset, POSTER_THUMBNAIL_WIDTH, POSTER_WIDTHS};
+
+const JPEG_QUALITY: u8 = 80;
+
+/// A movie's poster, ready to go in its `MovieMetadata`
+pub struct Poster {
+    pub thumbnail: Vec<u8>,
+    pub assets: Vec<ImageAsset>,
+}
+
+/// Scales `image` down to `width`, keeping its aspect ratio
+fn resize(image: &image::DynamicImage, width: u32) -> anyhow::Result<(Vec<u8>, u32, u32)> {
+    let resized = image.resize(width, u32::MAX, FilterType::Lanczos3);
+    let mut bytes = Vec::new();
+    JpegEncoder::new_with_quality(&mut Cursor::new(&mut bytes), JPEG_QUALITY)
+        .encode_image(&resized.to_rgb8())
+        .context("Failed to encode poster")?;
+    Ok((bytes, resized.width(), resized.height()))
+}
+
+/// Makes the thumbnail and the separately fetched sizes of `original`, writing the latter to
+/// `images_dir`. Sizes at least as wide as the original are skipped.
+pub fn process_poster(original: &[u8], images_dir: &Path) -> anyhow::Result<Poster> {
+    let image = image::load_from_memory(original).context("Failed to decode poster")?;
+    let (thumbnail, _, _) = resize(&image, POSTER_THUMBNAIL_WIDTH.min(image.width()))?;
+
+    let mut sized = POSTER_WIDTHS
+        .iter()
+        .filter(|width| **width < image.width())
+        .map(|width| resize(&image, *width))
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    sized.push((original.to_vec(), image.width(), image.height()));
+
+    std::fs::create_dir_all(images_dir)?;
+    let assets = sized
+        .into_iter()
+        .map(|(bytes, width, height)| {
+            let asset = ImageAsset::new(&bytes, width, height);
+            let path = images_dir.join(asset.file_name());
+            // Named by hash, so an existing file already has these contents
+            if !path.exists() {
+                std::fs::write(&path, &bytes)
+                    .with_context(|| format!("Failed to write {}", path.display()))?;
+            }
+            Ok(asset)
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
+
+    Ok(Poster { thumbnail, assets })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn jpeg(width: u32, height: u32) -> Vec<u8> {
+        let image = image::DynamicImage::new_rgb8(width, height);
+        let mut bytes = Vec::new();
+        JpegEncoder::new_with_quality(&mut Cursor::new(&mut bytes), JPEG_QUALITY)
+            .encode_image(&image.to_rgb8())
+            .unwrap();
+        bytes
+    }
+
+    #[test]
+    fn test_process_poster_writes_each_size_by_hash() {
+        let images_dir = tempfile::tempdir().unwrap();
+        let original = jpeg(370, 740);
+        let poster = process_poster(&original, images_dir.path()).unwrap();
+
+        let thumbnail = image::load_from_memory(&poster.thumbnail).unwrap();
+        assert_eq!(
+            (thumbnail.width(), thumbnail.height()),
+            (POSTER_THUMBNAIL_WIDTH, 184)
+        );
+        let sizes = poster
+            .assets
+            .iter()
+            .map(|asset| (asset.width, asset.height))
+            .collect::<Vec<_>>();
+        assert_eq!(sizes, vec![(185, 370), (370, 740)]);
+        for asset in &poster.assets {
+            let written = std::fs::read(images_dir.path().join(asset.file_name())).unwrap();
+            assert!(asset.matches(&written));
+        }
+        assert!(poster.assets[1].matches(&original));
+
+        // A poster smaller than every size only has its original
+        let small = process_poster(&jpeg(60, 90), images_dir.path()).unwrap();
+        assert_eq!(small.assets.len(), 1);
+        assert_eq!(
+            image::load_from_memory(&small.thumbnail).unwrap().width(),
+            60
+        );
+    }
+}
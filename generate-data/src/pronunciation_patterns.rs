@@ -1,12 +1,13 @@
 use futures::StreamExt;
 use language_utils::Lexeme;
-use language_utils::{Course, Language, PatternPosition, PronunciationGuideThoughts};
+use language_utils::{
+    Course, Language, PatternPosition, PronunciationGuideThoughts, pronunciation_patterns,
+};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::hash::Hash;
 use std::sync::LazyLock;
 use tysm::chat_completions::ChatClient;
-use unicode_normalization::UnicodeNormalization;
 
 static CHAT_CLIENT: LazyLock<ChatClient> = LazyLock::new(|| {
     ChatClient::from_env("o3")
@@ -201,18 +202,9 @@ where
         frequencies.insert((pattern.clone(), *position), 0);
     }
 
-    // Check if we're dealing with Korean by looking at the patterns
-    let is_korean = sounds.iter().any(|(pattern, _)| {
-        pattern.chars().any(|c| {
-            // Check if character is in Hangul Jamo ranges (including compatibility jamo)
-            ('\u{1100}'..='\u{11FF}').contains(&c) || // Hangul Jamo
-            ('\u{3130}'..='\u{318F}').contains(&c) || // Hangul Compatibility Jamo
-            ('\u{A960}'..='\u{A97F}').contains(&c) || // Hangul Jamo Extended-A
-            ('\u{D7B0}'..='\u{D7FF}').contains(&c) || // Hangul Jamo Extended-B
-            ('\u{3131}'..='\u{314E}').contains(&c) || // Hangul compatibility consonants
-            ('\u{314F}'..='\u{3163}').contains(&c) // Hangul compatibility vowels
-        })
-    });
+    // Korean patterns are written in jamo, so words need to be decomposed to match them
+    let jamo =
+        pronunciation_patterns::uses_jamo(sounds.iter().map(|(pattern, _)| pattern.as_str()));
 
     // Sum up frequencies for each pattern based on word occurrences
     for freq_entry in word_frequencies {
@@ -221,146 +213,16 @@ where
             language_utils::Lexeme::Heteronym(h) => h.word.as_ref(),
             language_utils::Lexeme::Multiword(s) => s.as_ref(),
         };
-
-        // For Korean, use NFKD (compatibility decomposition) to handle jamo
-        // For other languages, just use lowercase
-        let word_normalized = if is_korean {
-            // NFKD performs compatibility decomposition followed by canonical composition
-            // This properly handles Korean text decomposition
-            word.nfkd().collect::<String>()
-        } else {
-            word.to_lowercase()
-        };
+        let word_normalized = pronunciation_patterns::normalize_word(word, jamo);
 
         for (pattern, position) in sounds {
-            // For Korean patterns, use position-aware jamo conversion
-            // For others, lowercase
-            let pattern_normalized = if is_korean {
-                // Korean compatibility jamo need position-based conversion
-                // since they map to different combining jamo based on syllable position
-                let mut normalized = String::new();
-                for ch in pattern.chars() {
-                    // Check if this is a compatibility jamo (U+3131..U+318E)
-                    if ('\u{3131}'..='\u{318E}').contains(&ch) {
-                        // Convert based on position in syllable
-                        if *position == PatternPosition::End {
-                            // Convert to final jamo for End position
-                            let final_jamo = match ch {
-                                'ㄱ' => 'ᆨ',
-                                'ㄲ' => 'ᆩ',
-                                'ㄳ' => 'ᆪ',
-                                'ㄴ' => 'ᆫ',
-                                'ㄵ' => 'ᆬ',
-                                'ㄶ' => 'ᆭ',
-                                'ㄷ' => 'ᆮ',
-                                'ㄹ' => 'ᆯ',
-                                'ㄺ' => 'ᆰ',
-                                'ㄻ' => 'ᆱ',
-                                'ㄼ' => 'ᆲ',
-                                'ㄽ' => 'ᆳ',
-                                'ㄾ' => 'ᆴ',
-                                'ㄿ' => 'ᆵ',
-                                'ㅀ' => 'ᆶ',
-                                'ㅁ' => 'ᆷ',
-                                'ㅂ' => 'ᆸ',
-                                'ㅄ' => 'ᆹ',
-                                'ㅅ' => 'ᆺ',
-                                'ㅆ' => 'ᆻ',
-                                'ㅇ' => 'ᆼ',
-                                'ㅈ' => 'ᆽ',
-                                'ㅊ' => 'ᆾ',
-                                'ㅋ' => 'ᆿ',
-                                'ㅌ' => 'ᇀ',
-                                'ㅍ' => 'ᇁ',
-                                'ㅎ' => 'ᇂ',
-                                // Vowels (medial jamo) - same in all positions
-                                'ㅏ' => 'ᅡ',
-                                'ㅐ' => 'ᅢ',
-                                'ㅑ' => 'ᅣ',
-                                'ㅒ' => 'ᅤ',
-                                'ㅓ' => 'ᅥ',
-                                'ㅔ' => 'ᅦ',
-                                'ㅕ' => 'ᅧ',
-                                'ㅖ' => 'ᅨ',
-                                'ㅗ' => 'ᅩ',
-                                'ㅘ' => 'ᅪ',
-                                'ㅙ' => 'ᅫ',
-                                'ㅚ' => 'ᅬ',
-                                'ㅛ' => 'ᅭ',
-                                'ㅜ' => 'ᅮ',
-                                'ㅝ' => 'ᅯ',
-                                'ㅞ' => 'ᅰ',
-                                'ㅟ' => 'ᅱ',
-                                'ㅠ' => 'ᅲ',
-                                'ㅡ' => 'ᅳ',
-                                'ㅢ' => 'ᅴ',
-                                'ㅣ' => 'ᅵ',
-                                _ => ch,
-                            };
-                            normalized.push(final_jamo);
-                        } else {
-                            // Convert to initial jamo for Beginning/Anywhere
-                            let initial_jamo = match ch {
-                                'ㄱ' => 'ᄀ',
-                                'ㄲ' => 'ᄁ',
-                                'ㄴ' => 'ᄂ',
-                                'ㄷ' => 'ᄃ',
-                                'ㄸ' => 'ᄄ',
-                                'ㄹ' => 'ᄅ',
-                                'ㅁ' => 'ᄆ',
-                                'ㅂ' => 'ᄇ',
-                                'ㅃ' => 'ᄈ',
-                                'ㅅ' => 'ᄉ',
-                                'ㅆ' => 'ᄊ',
-                                'ㅇ' => 'ᄋ',
-                                'ㅈ' => 'ᄌ',
-                                'ㅉ' => 'ᄍ',
-                                'ㅊ' => 'ᄎ',
-                                'ㅋ' => 'ᄏ',
-                                'ㅌ' => 'ᄐ',
-                                'ㅍ' => 'ᄑ',
-                                'ㅎ' => 'ᄒ',
-                                // Vowels (medial jamo) - same in all positions
-                                'ㅏ' => 'ᅡ',
-                                'ㅐ' => 'ᅢ',
-                                'ㅑ' => 'ᅣ',
-                                'ㅒ' => 'ᅤ',
-                                'ㅓ' => 'ᅥ',
-                                'ㅔ' => 'ᅦ',
-                                'ㅕ' => 'ᅧ',
-                                'ㅖ' => 'ᅨ',
-                                'ㅗ' => 'ᅩ',
-                                'ㅘ' => 'ᅪ',
-                                'ㅙ' => 'ᅫ',
-                                'ㅚ' => 'ᅬ',
-                                'ㅛ' => 'ᅭ',
-                                'ㅜ' => 'ᅮ',
-                                'ㅝ' => 'ᅯ',
-                                'ㅞ' => 'ᅰ',
-                                'ㅟ' => 'ᅱ',
-                                'ㅠ' => 'ᅲ',
-                                'ㅡ' => 'ᅳ',
-                                'ㅢ' => 'ᅴ',
-                                'ㅣ' => 'ᅵ',
-                                _ => ch,
-                            };
-                            normalized.push(initial_jamo);
-                        }
-                    } else {
-                        // Not a compatibility jamo, keep as-is
-                        normalized.push(ch);
-                    }
-                }
-                normalized
-            } else {
-                pattern.to_lowercase()
-            };
-
-            let contains_pattern = match position {
-                PatternPosition::Beginning => word_normalized.starts_with(&pattern_normalized),
-                PatternPosition::End => word_normalized.ends_with(&pattern_normalized),
-                PatternPosition::Anywhere => word_normalized.contains(&pattern_normalized),
-            };
+            let pattern_normalized =
+                pronunciation_patterns::normalize_pattern(pattern, *position, jamo);
+            let contains_pattern = pronunciation_patterns::contains_pattern(
+                &word_normalized,
+                &pattern_normalized,
+                *position,
+            );
 
             if contains_pattern {
                 // Add the word's frequency count to the pattern's total
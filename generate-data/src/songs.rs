@@ -0,0 +1,263 @@
+//! Songs in sentence-sources/songs/. Plain `.txt` files are just lyrics, one sentence per line.
+//! `.lrc` files are timed lyrics, which are also turned into `SongLyrics` for singing along.
+//!
+//! LRC lines look like `[01:02.50]J'y crois pas comment chuis bo`, optionally with word timings
+//! (`<01:03.10>crois`) in the "enhanced" format, and the file can set the title (`[ti:...]`) and
+//! artist (`[ar:...]`).
+
+use std::path::Path;
+
+use anyhow::Context;
+use language_utils::{Language, LyricLine, SentenceInfo, SentenceSource, SongLyrics};
+
+/// How long the last line of a song lasts, since nothing comes after it to end it
+const LAST_LINE_MS: u32 = 4000;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct LrcLine {
+    pub start_ms: u32,
+    pub text: String,
+    /// Where each word timing starts in `text` (in chars), and when it's sung
+    pub word_times: Vec<(usize, u32)>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Lrc {
+    pub title: Option<String>,
+    pub artist: Option<String>,
+    pub lines: Vec<LrcLine>,
+}
+
+/// Parses `mm:ss.xx` (or `mm:ss`) into milliseconds
+fn parse_timestamp(timestamp: &str) -> Option<u32> {
+    let (minutes, seconds) = timestamp.split_once(':')?;
+    let minutes: u32 = minutes.trim().parse().ok()?;
+    let seconds: f64 = seconds.trim().parse().ok()?;
+    Some(minutes * 60_000 + (seconds * 1000.0).round() as u32)
+}
+
+/// Removes the word timings from an enhanced LRC line, returning the text and where each timing was
+fn strip_word_times(line: &str) -> (String, Vec<(usize, u32)>) {
+    let mut text = String::new();
+    let mut word_times = Vec::new();
+    let mut rest = line;
+    while let Some(open) = rest.find('<') {
+        let Some(close) = rest[open..].find('>') else {
+            break;
+        };
+        text.push_str(&rest[..open]);
+        match parse_timestamp(&rest[open + 1..open + close]) {
+            Some(time) => word_times.push((text.chars().count(), time)),
+            // Not a timing, so it's part of the lyrics
+            None => text.push_str(&rest[open..open + close + 1]),
+        }
+        rest = &rest[open + close + 1..];
+    }
+    text.push_str(rest);
+    (text, word_times)
+}
+
+pub fn parse_lrc(content: &str) -> Lrc {
+    let mut lrc = Lrc::default();
+    for line in content.lines() {
+        let mut rest = line.trim();
+        // A line can have several timestamps when it's repeated, e.g. a chorus
+        let mut start_times = Vec::new();
+        while let Some(tag) = rest.strip_prefix('[') {
+            let Some(close) = tag.find(']') else {
+                break;
+            };
+            let tag_content = &tag[..close];
+            rest = tag[close + 1..].trim_start();
+            if let Some(time) = parse_timestamp(tag_content) {
+                start_times.push(time);
+            } else if let Some((key, value)) = tag_content.split_once(':') {
+                let value = value.trim().to_string();
+                match key.trim() {
+                    "ti" => lrc.title = Some(value),
+                    "ar" => lrc.artist = Some(value),
+                    _ => {}
+                }
+            }
+        }
+
+        let (text, word_times) = strip_word_times(rest);
+        let text = text.trim();
+        if text.is_empty() {
+            continue;
+        }
+        for start_ms in start_times {
+            lrc.lines.push(LrcLine {
+                start_ms,
+                text: text.to_string(),
+                word_times: word_times.clone(),
+            });
+        }
+    }
+    lrc.lines.sort_by_key(|line| line.start_ms);
+    lrc
+}
+
+fn song_files(source_data_path: &Path) -> anyhow::Result<Vec<std::path::PathBuf>> {
+    let songs_dir = source_data_path.join("sentence-sources/songs");
+    if !songs_dir.exists() {
+        return Ok(vec![]);
+    }
+    let mut files = std::fs::read_dir(&songs_dir)
+        .context("Failed to read songs directory")?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<Result<Vec<_>, _>>()?;
+    files.sort();
+    Ok(files)
+}
+
+fn has_extension(path: &Path, extension: &str) -> bool {
+    path.extension().is_some_and(|ext| ext == extension)
+}
+
+/// Every line of every song, to be added to the sentence list
+pub fn load_song_sentences(
+    source_data_path: &Path,
+) -> anyhow::Result<Vec<(String, Option<String>, SentenceSource)>> {
+    let mut sentences = Vec::new();
+    for path in song_files(source_data_path)? {
+        let content = std::fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read song file: {}", path.display()))?;
+        let lines = if has_extension(&path, "lrc") {
+            parse_lrc(&content)
+                .lines
+                .into_iter()
+                .map(|line| line.text)
+                .collect()
+        } else if has_extension(&path, "txt") {
+            content
+                .lines()
+                .map(|line| line.trim().to_string())
+                .filter(|line| !line.is_empty())
+                .collect::<Vec<_>>()
+        } else {
+            continue;
+        };
+        for line in lines {
+            let mut source = SentenceSource::none();
+            source.from_song = true;
+            sentences.push((line, None, source));
+        }
+    }
+    Ok(sentences)
+}
+
+/// When each literal of a line is sung. Literals get the time of the last word timing at or before
+/// them, or, without word timings, are spread over the line by how far into it they are.
+fn align_literals(line: &LrcLine, end_ms: u32, sentence: &SentenceInfo) -> Vec<u32> {
+    let total_chars = sentence
+        .words
+        .iter()
+        .map(|literal| literal.text.chars().count() + literal.whitespace.chars().count())
+        .sum::<usize>()
+        .max(1);
+    let duration = end_ms.saturating_sub(line.start_ms);
+    let mut offset = 0;
+    sentence
+        .words
+        .iter()
+        .map(|literal| {
+            let start = match line
+                .word_times
+                .iter()
+                .take_while(|(word_offset, _)| *word_offset <= offset)
+                .last()
+            {
+                Some((_, time)) => *time,
+                None if line.word_times.is_empty() => {
+                    line.start_ms + (duration as u64 * offset as u64 / total_chars as u64) as u32
+                }
+                None => line.start_ms,
+            };
+            offset += literal.text.chars().count() + literal.whitespace.chars().count();
+            start
+        })
+        .collect()
+}
+
+/// The timed lyrics of every `.lrc` song, with their lines aligned to the analyzed sentences
+pub fn load_songs(
+    source_data_path: &Path,
+    language: Language,
+    nlp_sentences: &[(String, SentenceInfo)],
+) -> anyhow::Result<Vec<SongLyrics<String>>> {
+    let analyzed = nlp_sentences
+        .iter()
+        .map(|(sentence, info)| (sentence.as_str(), info))
+        .collect::<std::collections::HashMap<_, _>>();
+
+    let mut songs = Vec::new();
+    for path in song_files(source_data_path)? {
+        if !has_extension(&path, "lrc") {
+            continue;
+        }
+        let content = std::fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read song file: {}", path.display()))?;
+        let lrc = parse_lrc(&content);
+        let id = path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().to_string())
+            .unwrap_or_default();
+
+        let lines = lrc
+            .lines
+            .iter()
+            .enumerate()
+            .map(|(index, line)| {
+                let end_ms = lrc
+                    .lines
+                    .get(index + 1)
+                    .map(|next| next.start_ms)
+                    .unwrap_or(line.start_ms + LAST_LINE_MS);
+                // Sentences are cleaned up before analysis, so the lines have to be too
+                let sentence =
+                    language_utils::text_cleanup::cleanup_sentence(line.text.clone(), language);
+                let literal_start_ms = analyzed
+                    .get(sentence.as_str())
+                    .map(|info| align_literals(line, end_ms, info))
+                    .unwrap_or_default();
+                LyricLine {
+                    sentence,
+                    start_ms: line.start_ms,
+                    end_ms,
+                    literal_start_ms,
+                }
+            })
+            .collect();
+
+        songs.push(SongLyrics {
+            title: lrc.title.unwrap_or_else(|| id.clone()),
+            artist: lrc.artist,
+            id,
+            lines,
+        });
+    }
+    Ok(songs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parses_enhanced_lrc() {
+        let lrc = parse_lrc(
+            "[ti:Chuis bo]\n[ar:Les Twins]\n\n[00:12.50][01:02.00]Chuis bo\n[00:15.00]<00:15.00>J'y <00:15.40>crois pas\n",
+        );
+        assert_eq!(lrc.title.as_deref(), Some("Chuis bo"));
+        assert_eq!(lrc.artist.as_deref(), Some("Les Twins"));
+        let starts = lrc
+            .lines
+            .iter()
+            .map(|line| line.start_ms)
+            .collect::<Vec<_>>();
+        assert_eq!(starts, vec![12_500, 15_000, 62_000]);
+        assert_eq!(lrc.lines[1].text, "J'y crois pas");
+        assert_eq!(lrc.lines[1].word_times, vec![(0, 15_000), (4, 15_400)]);
+    }
+}
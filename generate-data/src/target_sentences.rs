@@ -88,11 +88,15 @@ pub fn get_target_sentences(
     // Load movie sentences
     let movie_sentences = load_movie_sentences(&source_data_path, course.target_language)?;
 
+    // Load song lyrics
+    let song_sentences = crate::songs::load_song_sentences(&source_data_path)?;
+
     println!(
-        "  Loaded sentences: Anki: {}, Tatoeba: {}, Movies: {}, Manual: {}",
+        "  Loaded sentences: Anki: {}, Tatoeba: {}, Movies: {}, Songs: {}, Manual: {}",
         anki_sentences.len(),
         tatoeba_sentences.len(),
         movie_sentences.len(),
+        song_sentences.len(),
         manual_sentences.len(),
     );
 
@@ -109,6 +113,7 @@ pub fn get_target_sentences(
         .into_iter()
         .chain(tatoeba_sentences)
         .chain(movie_sentences) // Add movie sentences
+        .chain(song_sentences)
         .map(|(sentence, native, source)| {
             (
                 language_utils::text_cleanup::cleanup_sentence(sentence, course.target_language),
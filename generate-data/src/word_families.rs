@@ -0,0 +1,115 @@
+//! Groups lemmas into families of related words (e.g. "heureux", "heureuse" and "heureusement")
+//! so flashcards can point learners at words they already know. There's no derivation data for
+//! most languages, so lemmas are grouped by a long shared prefix, which finds most derivations
+//! without pulling in too many unrelated words.
+
+use std::collections::BTreeMap;
+
+use language_utils::Heteronym;
+
+/// Lemmas have to share at least this many characters to be related...
+const MIN_STEM_CHARS: usize = 5;
+/// ...and at least this much of the shorter lemma
+const MIN_STEM_FRACTION: f64 = 0.75;
+/// Families bigger than this are mostly noise (and too long to show), so they're cut down to the
+/// lemmas with the longest shared stem
+const MAX_FAMILY_SIZE: usize = 8;
+
+fn shared_prefix_chars(a: &str, b: &str) -> usize {
+    a.chars().zip(b.chars()).take_while(|(a, b)| a == b).count()
+}
+
+fn is_related(a: &str, b: &str) -> bool {
+    let shorter = a.chars().count().min(b.chars().count());
+    let shared = shared_prefix_chars(a, b);
+    shared >= MIN_STEM_CHARS && shared as f64 >= shorter as f64 * MIN_STEM_FRACTION
+}
+
+/// Maps each lemma to one heteronym for each of the other lemmas in its family. The heteronym
+/// chosen for a lemma is the lemma itself where possible, so it can be shown as the word.
+pub fn word_families<'a>(
+    heteronyms: impl IntoIterator<Item = &'a Heteronym<String>>,
+) -> Vec<(String, Vec<Heteronym<String>>)> {
+    let mut representatives: BTreeMap<String, Heteronym<String>> = BTreeMap::new();
+    for heteronym in heteronyms {
+        let lemma = heteronym.lemma.to_lowercase();
+        match representatives.get(&lemma) {
+            Some(existing) if existing.word == existing.lemma => {}
+            _ => {
+                representatives.insert(lemma, heteronym.clone());
+            }
+        }
+    }
+
+    // Related lemmas always share their first `MIN_STEM_CHARS` characters, so only lemmas in the
+    // same bucket have to be compared
+    let mut buckets: BTreeMap<String, Vec<&String>> = BTreeMap::new();
+    for lemma in representatives.keys() {
+        if lemma.chars().count() >= MIN_STEM_CHARS {
+            let stem: String = lemma.chars().take(MIN_STEM_CHARS).collect();
+            buckets.entry(stem).or_default().push(lemma);
+        }
+    }
+
+    let mut families = Vec::new();
+    for lemmas in buckets.values() {
+        for lemma in lemmas {
+            let mut family: Vec<&String> = lemmas
+                .iter()
+                .filter(|other| *other != lemma && is_related(lemma, other))
+                .copied()
+                .collect();
+            if family.is_empty() {
+                continue;
+            }
+            family.sort_by_key(|other| std::cmp::Reverse(shared_prefix_chars(lemma, other)));
+            family.truncate(MAX_FAMILY_SIZE);
+            families.push((
+                representatives[*lemma].lemma.clone(),
+                family
+                    .into_iter()
+                    .map(|other| representatives[other].clone())
+                    .collect(),
+            ));
+        }
+    }
+    families
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use language_utils::PartOfSpeech;
+
+    fn heteronym(word: &str, lemma: &str, pos: PartOfSpeech) -> Heteronym<String> {
+        Heteronym {
+            word: word.to_string(),
+            lemma: lemma.to_string(),
+            pos,
+        }
+    }
+
+    #[test]
+    fn test_groups_derivations_by_stem() {
+        let heteronyms = [
+            heteronym("heureuse", "heureux", PartOfSpeech::Adj),
+            heteronym("heureux", "heureux", PartOfSpeech::Adj),
+            heteronym("heureusement", "heureusement", PartOfSpeech::Adv),
+            heteronym("heurter", "heurter", PartOfSpeech::Verb),
+            heteronym("maison", "maison", PartOfSpeech::Noun),
+        ];
+        let families: BTreeMap<_, _> = word_families(&heteronyms).into_iter().collect();
+
+        assert_eq!(
+            families["heureux"],
+            vec![heteronym("heureusement", "heureusement", PartOfSpeech::Adv)]
+        );
+        assert_eq!(
+            families["heureusement"],
+            vec![heteronym("heureux", "heureux", PartOfSpeech::Adj)]
+        );
+        // "heurter" only shares 4 characters with "heureux"
+        assert!(!families.contains_key("heurter"));
+        assert!(!families.contains_key("maison"));
+    }
+}
@@ -0,0 +1,194 @@
+use futures::StreamExt;
+use indicatif::{ProgressBar, ProgressStyle};
+use language_utils::{Course, DictionaryEntry, Heteronym, Language, WordNotes};
+use scraper::{ElementRef, Html, Selector};
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::sync::LazyLock;
+use tysm::chat_completions::ChatClient;
+
+use crate::wiktionary_conjugations::get_wiktionary_html;
+
+static CHAT_CLIENT: LazyLock<ChatClient> = LazyLock::new(|| {
+    ChatClient::from_env("gpt-4o")
+        .unwrap()
+        .with_cache_directory("./.cache")
+});
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+struct WordNotesThoughts {
+    thoughts: String,
+    etymology: Option<String>,
+    usage: Option<String>,
+}
+
+/// Etymology and usage notes for each lemma in `dictionary`. The etymology is summarized from
+/// the word's Wiktionary entry when it has one, so the model isn't making the history up.
+pub async fn create_word_notes(
+    course: Course,
+    dictionary: &BTreeMap<Heteronym<String>, DictionaryEntry>,
+    wiktionary_cache_dir: &Path,
+) -> anyhow::Result<BTreeMap<Heteronym<String>, WordNotes>> {
+    let Course {
+        native_language,
+        target_language,
+    } = course;
+
+    let lemmas = dictionary
+        .iter()
+        .filter(|(heteronym, _)| heteronym.word == heteronym.lemma)
+        .collect::<Vec<_>>();
+
+    let pb = ProgressBar::new(lemmas.len() as u64);
+    pb.set_style(
+        ProgressStyle::default_bar()
+            .template("{spinner:.green} [{elapsed_precise}] [{bar:40.cyan/blue}] {pos}/{len} word notes ({per_sec}, ${msg}, {eta})")
+            .unwrap()
+            .progress_chars("#>-"),
+    );
+    pb.enable_steady_tick(std::time::Duration::from_millis(100));
+
+    let notes = futures::stream::iter(lemmas)
+        .map(async |(heteronym, entry)| {
+            pb.set_message(format!(
+                "{:.2} ({})",
+                CHAT_CLIENT.cost().unwrap_or(0.0),
+                heteronym.lemma
+            ));
+
+            let etymology = match get_wiktionary_html(&heteronym.lemma, wiktionary_cache_dir).await
+            {
+                Ok(html) => etymology_section(&html, target_language),
+                Err(e) => {
+                    eprintln!("Failed to fetch Wiktionary entry for {}: {e}", heteronym.lemma);
+                    None
+                }
+            };
+            let definitions = entry
+                .definitions
+                .iter()
+                .map(|definition| definition.native.as_str())
+                .collect::<Vec<_>>()
+                .join("; ");
+
+            let response: Result<WordNotesThoughts, _> = CHAT_CLIENT.chat_with_system_prompt(
+                format!(r#"The input is a {target_language} word from an app for {target_language} learners (whose native language is {native_language}), with its definitions and (if there is one) the etymology section of its Wiktionary entry. Write short notes for curious learners. First, think about what would be interesting or useful to them. Your thoughts will not be shown to the user.
+
+- "etymology": One or two sentences on where the word comes from, focusing on what helps remember it, like a related {native_language} word. Only use the Wiktionary etymology; if there isn't one, or it says nothing useful, use null. Leave out Wiktionary's abbreviations and reconstructed forms.
+- "usage": One or two sentences on how the word is used that the definitions don't already say, like whether it's formal or slang, a false friend, or a very common expression it's in. If there's nothing worth saying, use null.
+
+Write both in {native_language}, and don't start with the word itself (e.g. not "The word X comes from..." but "From...")."#),
+                format!(
+                    "word: `{lemma}`\npos: {pos}\ndefinitions: {definitions}\nWiktionary etymology: {etymology}",
+                    lemma = heteronym.lemma,
+                    pos = heteronym.pos,
+                    etymology = etymology.as_deref().unwrap_or("(none)"),
+                ),
+            ).await.inspect_err(|e| {
+                println!("error: {e:#?}");
+            });
+
+            pb.inc(1);
+
+            (heteronym, response)
+        })
+        .buffer_unordered(50)
+        .collect::<Vec<_>>()
+        .await
+        .into_iter()
+        .filter_map(|(heteronym, response)| {
+            let response = response.ok()?;
+            (response.etymology.is_some() || response.usage.is_some()).then(|| {
+                (
+                    heteronym.clone(),
+                    WordNotes {
+                        etymology: response.etymology,
+                        usage: response.usage,
+                    },
+                )
+            })
+        })
+        .collect::<BTreeMap<_, _>>();
+
+    pb.finish_with_message(format!("{:.2}", CHAT_CLIENT.cost().unwrap_or(0.0)));
+
+    Ok(notes)
+}
+
+/// The text of the first etymology in `language`'s section of a Wiktionary page. Words with
+/// several etymologies (e.g. "avocat") have numbered sections, and the first is usually the most
+/// common meaning.
+fn etymology_section(html: &str, language: Language) -> Option<String> {
+    let document = Html::parse_document(html);
+    let heading_selector = Selector::parse("h2, h3, h4, h5").unwrap();
+    let language_heading = document
+        .select(&Selector::parse(&format!("h2#{language}")).ok()?)
+        .next()?;
+
+    // Headings are wrapped in `div.mw-heading`s, and a language's section is the siblings after
+    // its heading's div, up to the next language's
+    let mut paragraphs = Vec::new();
+    let mut in_etymology = false;
+    let mut current = language_heading.parent()?.next_sibling();
+    while let Some(node) = current {
+        current = node.next_sibling();
+        let Some(elem) = ElementRef::wrap(node) else {
+            continue;
+        };
+        if let Some(heading) = elem.select(&heading_selector).next()
+            && elem
+                .value()
+                .classes()
+                .any(|class| class.starts_with("mw-heading"))
+        {
+            if heading.value().name() == "h2" || !paragraphs.is_empty() {
+                break;
+            }
+            in_etymology = heading
+                .value()
+                .id()
+                .is_some_and(|id| id.starts_with("Etymology"));
+        } else if in_etymology && elem.value().name() == "p" {
+            let text = elem.text().collect::<String>().trim().to_string();
+            if !text.is_empty() {
+                paragraphs.push(text);
+            }
+        }
+    }
+
+    (!paragraphs.is_empty()).then(|| paragraphs.join("\n"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_finds_the_etymology_in_the_right_language() {
+        let html = r#"<html><body>
+            <div class="mw-heading mw-heading2"><h2 id="English">English</h2></div>
+            <div class="mw-heading mw-heading3"><h3 id="Etymology">Etymology</h3></div>
+            <p>From Old French <i>fenestre</i>.</p>
+            <div class="mw-heading mw-heading2"><h2 id="French">French</h2></div>
+            <div class="mw-heading mw-heading3"><h3 id="Pronunciation">Pronunciation</h3></div>
+            <p>/fə.nɛtʁ/</p>
+            <div class="mw-heading mw-heading3"><h3 id="Etymology_1">Etymology 1</h3></div>
+            <p>Inherited from Latin <i>fenestra</i>.</p>
+            <p>Doublet of <i>fenestre</i>.</p>
+            <div class="mw-heading mw-heading4"><h4 id="Noun">Noun</h4></div>
+            <p><b>fenêtre</b> f (plural fenêtres)</p>
+            <div class="mw-heading mw-heading3"><h3 id="Etymology_2">Etymology 2</h3></div>
+            <p>Something else.</p>
+        </body></html>"#;
+
+        assert_eq!(
+            etymology_section(html, Language::French).as_deref(),
+            Some("Inherited from Latin fenestra.\nDoublet of fenestre.")
+        );
+        assert_eq!(
+            etymology_section(html, Language::English).as_deref(),
+            Some("From Old French fenestre.")
+        );
+        assert_eq!(etymology_section(html, Language::Spanish), None);
+    }
+}
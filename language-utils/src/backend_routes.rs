@@ -0,0 +1,271 @@
+//! The yap-ai-backend routes clients call, with the types they send and get back. The backend
+//! registers its handlers at these paths and `yap_frontend_rs` calls them through `Route`, so a
+//! path or a body can't change on one side without the other noticing.
+
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+
+use crate::autograde::{
+    AutoGradeTranscriptionRequest, AutoGradeTranslationRequest, AutoGradeTranslationResponse,
+};
+use crate::content_report::{ContentReportStatus, SubmitContentReportRequest};
+use crate::crash_report::{SubmitCrashReportRequest, SubmitCrashReportResponse};
+use crate::data_export::DataExportStatus;
+use crate::profile::{
+    CreateShareTokenRequest, FollowRequest, FollowResponse, FollowStatus, GetProfileQuery, Profile,
+    RevokeShareTokenRequest, RevokeShareTokenResponse, ShareToken, SharedDeck, SharedEvents,
+    SharedEventsQuery, UpdateLanguageStatsRequest, UpdateLanguageStatsResponse,
+    UpdateProfileRequest, UpdateProfileResponse, UpdateSharedDeckResponse, UserLanguageStats,
+};
+use crate::public_stats::{
+    GetPublicStatsQuery, PublicStats, PublicStatsSettings, SetPublicStatsRequest,
+};
+use crate::scheduler_telemetry::SchedulerTelemetry;
+use crate::sentence_generation::{GenerateSentenceRequest, GenerateSentenceResponse};
+use crate::shared_list::{GetSharedListQuery, ShareListRequest, ShareListResponse, SharedList};
+use crate::transcription_challenge::Grade;
+use crate::webhook::{SetWebhookRequest, Webhook};
+use crate::{AvailableCourse, TtsProvider};
+
+/// These respond with the base64 encoded audio as plain text, so they aren't `Route`s
+pub const TTS_PATH: &str = "/tts";
+pub const GOOGLE_TTS_PATH: &str = "/tts/google";
+
+pub fn tts_path(provider: &TtsProvider) -> &'static str {
+    match provider {
+        TtsProvider::Google => GOOGLE_TTS_PATH,
+        TtsProvider::ElevenLabs => TTS_PATH,
+    }
+}
+
+/// The language pack routes respond with raw bytes and take a `pack_manifest::PackQuery` in the
+/// query string, see `language_data_server::router`
+pub const LANGUAGE_DATA_PATH: &str = "/language-data";
+pub const LANGUAGE_DATA_MANIFEST_PATH: &str = "/language-data/manifest";
+pub const LANGUAGE_DATA_SEGMENT_PATH: &str = "/language-data/segment";
+
+/// `GET {IMAGES_PATH}/{sha256}.jpg` responds with the image, see `ImageAsset`
+pub const IMAGES_PATH: &str = "/images";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RouteMethod {
+    Get,
+    Post,
+    Patch,
+}
+
+/// A JSON route. `Request` is the body, or for `GET` routes the query string (see
+/// `query_string`).
+pub trait Route {
+    const METHOD: RouteMethod;
+    const PATH: &'static str;
+    type Request: Serialize;
+    type Response: DeserializeOwned;
+}
+
+macro_rules! routes {
+    ($(
+        $(#[$meta:meta])*
+        $name:ident: $method:ident $path:literal, $request:ty => $response:ty;
+    )*) => {
+        $(
+            $(#[$meta])*
+            pub struct $name;
+
+            impl Route for $name {
+                const METHOD: RouteMethod = RouteMethod::$method;
+                const PATH: &'static str = $path;
+                type Request = $request;
+                type Response = $response;
+            }
+        )*
+    };
+}
+
+routes! {
+    /// The courses the backend has packs for, and which of its features they support
+    Courses: Get "/courses", () => Vec<AvailableCourse>;
+    AutogradeTranslation: Post "/autograde-translation",
+        AutoGradeTranslationRequest => AutoGradeTranslationResponse;
+    AutogradeTranscription: Post "/autograde-transcription",
+        AutoGradeTranscriptionRequest => Grade;
+    GenerateSentence: Post "/generate-sentence",
+        GenerateSentenceRequest => GenerateSentenceResponse;
+    GetProfile: Get "/profile", GetProfileQuery => Profile;
+    UpdateProfile: Patch "/profile", UpdateProfileRequest => UpdateProfileResponse;
+    UpdateLanguageStats: Post "/language-stats",
+        UpdateLanguageStatsRequest => UpdateLanguageStatsResponse;
+    GetLanguageStats: Get "/user-language-stats", GetProfileQuery => Vec<UserLanguageStats>;
+    Follow: Post "/follow", FollowRequest => FollowResponse;
+    Unfollow: Post "/unfollow", FollowRequest => FollowResponse;
+    GetFollowStatus: Get "/follow-status", GetProfileQuery => FollowStatus;
+    /// Tells the backend a sync changed the user's progress, so it refreshes what it keeps
+    /// derived from it (like their public progress page) within a few seconds
+    SummariesDirty: Post "/summaries/dirty", () => ();
+    ShareList: Post "/shared-lists", ShareListRequest => ShareListResponse;
+    GetSharedList: Get "/shared-lists", GetSharedListQuery => SharedList;
+    /// Only called for users who opted in to sharing crash reports. Logged-out reports are stored
+    /// without a user.
+    SubmitCrashReport: Post "/crash-reports",
+        SubmitCrashReportRequest => SubmitCrashReportResponse;
+    /// Only called for users who opted in to sharing scheduler telemetry. Always sent without the
+    /// user's token, so histograms can't be tied to anyone.
+    SubmitSchedulerTelemetry: Post "/telemetry/scheduler", SchedulerTelemetry => ();
+    /// `None` if the user hasn't set one up
+    GetWebhook: Get "/webhook", () => Option<Webhook>;
+    SetWebhook: Post "/webhook", SetWebhookRequest => Option<Webhook>;
+    /// Starts an export of everything stored about the user. An export that's still pending is
+    /// returned instead of starting another.
+    RequestDataExport: Post "/data-export", () => DataExportStatus;
+    /// The user's latest export, `None` if they never asked for one
+    GetDataExport: Get "/data-export", () => Option<DataExportStatus>;
+    /// Reports from logged-out users are stored without a user, so their status can't be looked up
+    SubmitContentReport: Post "/content-reports",
+        SubmitContentReportRequest => ContentReportStatus;
+    /// The status of every report the user submitted
+    GetContentReports: Get "/content-reports", () => Vec<ContentReportStatus>;
+    /// Whether the user's progress page is public, and its share id
+    GetPublicStatsSettings: Get "/public-stats/settings", () => PublicStatsSettings;
+    SetPublicStats: Post "/public-stats/settings", SetPublicStatsRequest => PublicStatsSettings;
+    /// Doesn't need a login. Not found unless the user made their page public.
+    GetPublicStats: Get "/public-stats", GetPublicStatsQuery => PublicStats;
+    /// The user's share tokens, newest first
+    GetShareTokens: Get "/share-tokens", () => Vec<ShareToken>;
+    CreateShareToken: Post "/share-tokens", CreateShareTokenRequest => ShareToken;
+    /// The token stops working right away
+    RevokeShareToken: Post "/share-tokens/revoke",
+        RevokeShareTokenRequest => RevokeShareTokenResponse;
+    /// Replaces what `ShareScope::Deck` tokens can browse. Needs a login, unlike `GetSharedDeck`.
+    UpdateSharedDeck: Post "/shared/deck", SharedDeck => UpdateSharedDeckResponse;
+    /// The `Shared` routes don't need a login, only a share token sent as the bearer token in
+    /// place of one. A revoked token is unauthorized, and one with too narrow a scope is forbidden.
+    GetSharedStats: Get "/shared/stats", () => Vec<UserLanguageStats>;
+    /// Needs `ShareScope::Deck`. Not found if the user never uploaded one.
+    GetSharedDeck: Get "/shared/deck", () => SharedDeck;
+    /// Needs `ShareScope::FullHistory`
+    GetSharedEvents: Get "/shared/events", SharedEventsQuery => SharedEvents;
+}
+
+/// Why a request failed, so clients can tell whether to retry without matching on statuses
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize, tsify::Tsify)]
+#[tsify(into_wasm_abi, from_wasm_abi)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorCode {
+    BadRequest,
+    Unauthorized,
+    Forbidden,
+    NotFound,
+    RateLimited,
+    /// The course or language isn't supported by the route
+    NotImplemented,
+    /// A service the backend relies on (an LLM or TTS provider) failed
+    UpstreamFailed,
+    Unavailable,
+    Internal,
+    /// From a newer backend
+    #[serde(other)]
+    Unknown,
+}
+
+/// The body of every error response from the backend
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, tsify::Tsify)]
+#[tsify(into_wasm_abi, from_wasm_abi)]
+pub struct ErrorEnvelope {
+    pub code: ErrorCode,
+    pub message: String,
+    /// Whether the same request may succeed later
+    pub retryable: bool,
+    /// How many seconds to wait before retrying, if the backend knows
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub retry_after: Option<u64>,
+}
+
+impl ErrorEnvelope {
+    /// What an error with this status means, for errors that didn't come with an envelope (e.g.
+    /// from a proxy in front of the backend)
+    pub fn from_status(status: u16) -> Self {
+        let (code, retryable) = match status {
+            401 => (ErrorCode::Unauthorized, false),
+            403 => (ErrorCode::Forbidden, false),
+            404 => (ErrorCode::NotFound, false),
+            429 => (ErrorCode::RateLimited, true),
+            501 => (ErrorCode::NotImplemented, false),
+            502 | 504 => (ErrorCode::UpstreamFailed, true),
+            503 => (ErrorCode::Unavailable, true),
+            400..500 => (ErrorCode::BadRequest, false),
+            _ => (ErrorCode::Internal, true),
+        };
+        Self {
+            code,
+            message: format!("The server responded with {status}"),
+            retryable,
+            retry_after: None,
+        }
+    }
+}
+
+/// The query string for a `GET` route's request, including the `?` (or empty if there are no
+/// parameters). Requests are flat structs, so each field becomes a parameter and `None`s are left
+/// out.
+pub fn query_string(request: &impl Serialize) -> String {
+    let Ok(serde_json::Value::Object(fields)) = serde_json::to_value(request) else {
+        return String::new();
+    };
+    let parameters = fields
+        .into_iter()
+        .filter_map(|(name, value)| {
+            let value = match value {
+                serde_json::Value::Null => return None,
+                serde_json::Value::String(value) => value,
+                value => value.to_string(),
+            };
+            Some(format!(
+                "{}={}",
+                percent_encode(&name),
+                percent_encode(&value)
+            ))
+        })
+        .collect::<Vec<_>>();
+    if parameters.is_empty() {
+        String::new()
+    } else {
+        format!("?{}", parameters.join("&"))
+    }
+}
+
+fn percent_encode(s: &str) -> String {
+    s.bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                (byte as char).to_string()
+            }
+            _ => format!("%{byte:02X}"),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_query_strings_leave_out_missing_parameters() {
+        let query = GetProfileQuery {
+            id: None,
+            slug: Some("jean dupont&co".to_string()),
+        };
+        assert_eq!(query_string(&query), "?slug=jean%20dupont%26co");
+        assert_eq!(query_string(&()), "");
+    }
+
+    #[test]
+    fn test_unknown_error_codes_are_parsed() {
+        let envelope: ErrorEnvelope = serde_json::from_str(
+            r#"{"code":"quota_exceeded","message":"Out of credits","retryable":false}"#,
+        )
+        .unwrap();
+        assert_eq!(envelope.code, ErrorCode::Unknown);
+        assert_eq!(envelope.retry_after, None);
+        assert!(ErrorEnvelope::from_status(503).retryable);
+        assert!(!ErrorEnvelope::from_status(422).retryable);
+    }
+}
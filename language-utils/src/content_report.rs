@@ -0,0 +1,73 @@
+//! Corrections users report for a language pack's content, like a wrong translation or
+//! definition. The app records each report as an event, so it's kept with the rest of the deck,
+//! and uploads it with `POST /content-reports`. Once someone has reviewed a report,
+//! `GET /content-reports` says whether the correction was taken.
+
+use serde::{Deserialize, Serialize};
+
+use crate::{Course, Lexeme};
+
+/// Longer comments and suggestions are rejected by the backend
+pub const MAX_CONTENT_REPORT_TEXT_LENGTH: usize = 2000;
+
+#[derive(
+    Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, tsify::Tsify,
+)]
+#[tsify(into_wasm_abi, from_wasm_abi)]
+#[serde(rename_all = "snake_case")]
+pub enum ContentReportKind {
+    /// A sentence's translation is wrong
+    WrongTranslation,
+    /// A word's dictionary entry is wrong
+    WrongDefinition,
+    Other,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, PartialOrd, Ord, tsify::Tsify)]
+#[tsify(into_wasm_abi, from_wasm_abi)]
+pub struct ContentReport {
+    /// Chosen by the client, so a report uploaded twice is only stored once
+    pub id: String,
+    pub kind: ContentReportKind,
+    pub course: Course,
+    /// The hash of the language pack the content was in, since a later pack may have fixed it
+    pub pack_version: String,
+    /// The sentence the content was shown with, if any
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sentence: Option<String>,
+    /// The word the report is about, if any
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub lexeme: Option<Lexeme<String>>,
+    /// What the user thinks it should say
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub suggestion: Option<String>,
+    pub comment: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, tsify::Tsify)]
+#[tsify(into_wasm_abi, from_wasm_abi)]
+#[serde(rename_all = "snake_case")]
+pub enum ContentReportState {
+    /// Nobody has reviewed it yet
+    Open,
+    /// The correction was taken, and will be in a later pack
+    Accepted,
+    /// The content was right, or the report couldn't be acted on
+    Rejected,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, tsify::Tsify)]
+#[tsify(into_wasm_abi, from_wasm_abi)]
+pub struct ContentReportStatus {
+    pub id: String,
+    pub state: ContentReportState,
+    /// From whoever reviewed the report
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reviewer_note: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, tsify::Tsify)]
+#[tsify(into_wasm_abi, from_wasm_abi)]
+pub struct SubmitContentReportRequest {
+    pub report: ContentReport,
+}
@@ -0,0 +1,36 @@
+//! What it takes to finish a course, shipped in its language pack as
+//! `LanguagePack::completion_criteria`. A user who meets the criteria can graduate, which puts
+//! their deck in a maintenance mode that's about keeping what they know rather than learning more.
+
+/// Courses whose pack doesn't set any criteria use `CompletionCriteria::default`
+#[derive(
+    Clone,
+    Debug,
+    PartialEq,
+    Eq,
+    serde::Serialize,
+    serde::Deserialize,
+    rkyv::Archive,
+    rkyv::Serialize,
+    rkyv::Deserialize,
+    tsify::Tsify,
+)]
+#[rkyv(derive(Debug))]
+#[tsify(into_wasm_abi, from_wasm_abi)]
+pub struct CompletionCriteria {
+    /// How many words (including multiword terms) have to be in FSRS's review state
+    pub words_in_review: u32,
+    /// Whether the card of every pronunciation pattern in the pack has to be in the review state
+    /// too. Courses without pronunciation patterns ignore this.
+    #[serde(default)]
+    pub pronunciation_patterns: bool,
+}
+
+impl Default for CompletionCriteria {
+    fn default() -> Self {
+        Self {
+            words_in_review: 5000,
+            pronunciation_patterns: true,
+        }
+    }
+}
@@ -0,0 +1,52 @@
+//! Reports of the app crashing, which `yap_frontend_rs` records when it panics and, if the user
+//! opted in, uploads with `POST /crash-reports` the next time it starts.
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+/// Reports with more log entries than this are rejected by the backend
+pub const MAX_CRASH_REPORT_LOGS: usize = 200;
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, tsify::Tsify)]
+#[tsify(into_wasm_abi, from_wasm_abi)]
+pub struct CrashReport {
+    /// Chosen by the client, so a report uploaded twice is only stored once
+    pub id: String,
+    /// RFC 3339
+    pub occurred_at: String,
+    pub app_version: String,
+    /// The panic message
+    pub message: String,
+    /// Where in the source the panic happened, e.g. `src/lib.rs:12:5`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub location: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub backtrace: Option<String>,
+    /// The last entries logged before the panic, oldest first
+    pub logs: Vec<CrashLogEntry>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, tsify::Tsify)]
+#[tsify(into_wasm_abi, from_wasm_abi)]
+pub struct CrashLogEntry {
+    /// RFC 3339
+    pub timestamp: String,
+    pub level: String,
+    pub target: String,
+    pub message: String,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub fields: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, tsify::Tsify)]
+#[tsify(into_wasm_abi, from_wasm_abi)]
+pub struct SubmitCrashReportRequest {
+    pub report: CrashReport,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, tsify::Tsify)]
+#[tsify(into_wasm_abi, from_wasm_abi)]
+pub struct SubmitCrashReportResponse {
+    pub id: String,
+}
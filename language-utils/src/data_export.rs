@@ -0,0 +1,33 @@
+//! Exports of everything the backend stores about a user, for data access requests. The app can
+//! already export the local event log on its own; this covers the server side too (synced event
+//! rows, profile, stats, usage records, ...). `POST /data-export` starts building the archive in
+//! the background and `GET /data-export` reports how it's going, with a download link once it's
+//! ready.
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, tsify::Tsify)]
+#[tsify(into_wasm_abi, from_wasm_abi)]
+#[serde(rename_all = "snake_case")]
+pub enum DataExportState {
+    Pending,
+    Ready,
+    Failed,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, tsify::Tsify)]
+#[tsify(into_wasm_abi, from_wasm_abi)]
+pub struct DataExportStatus {
+    pub id: String,
+    pub state: DataExportState,
+    pub requested_at: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub completed_at: Option<String>,
+    /// A zip archive, for `DataExportState::Ready` exports. The link expires after an hour, so
+    /// get the status again for a new one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub download_url: Option<String>,
+    /// Why a `DataExportState::Failed` export failed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
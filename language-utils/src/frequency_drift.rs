@@ -0,0 +1,113 @@
+//! How lexemes' frequency ranks moved since the previous build of a course's pack. Rebuilding a
+//! pack with new sentences or a new NLP model changes the frequencies, and with them which words
+//! decks consider worth learning, without anything saying so. generate-data compares the new
+//! frequencies with the ones from the previous build and ships the lexemes that moved a lot in the
+//! pack as `LanguagePack::frequency_drift`, so those changes can be explained.
+
+use std::collections::BTreeMap;
+
+use crate::lexeme_ids::{LexemeId, LexemeIdTable};
+use crate::{FrequencyEntry, Lexeme};
+
+/// Lexemes whose rank changed by at least this factor (either way) are in the report
+pub const REPORTED_FACTOR: f64 = 1.5;
+
+/// A lexeme's rank in the previous build and in this one, 1 being the most frequent
+#[derive(Copy, Clone, Debug, PartialEq, Eq, rkyv::Archive, rkyv::Serialize, rkyv::Deserialize)]
+#[rkyv(derive(Debug))]
+pub struct RankDrift {
+    pub previous_rank: u32,
+    pub rank: u32,
+}
+
+impl RankDrift {
+    /// How many times more or less frequent the lexeme's rank says it is now, always at least 1
+    pub fn factor(&self) -> f64 {
+        let (previous, current) = (f64::from(self.previous_rank), f64::from(self.rank));
+        (previous / current).max(current / previous)
+    }
+}
+
+/// The lexemes of `current` whose rank moved by at least `REPORTED_FACTOR` since `previous`, both
+/// most frequent first. Lexemes are matched by their id in `lexeme_ids`, so renamed ones are
+/// compared with what they used to be called, and lexemes that are new to the pack are left out.
+pub fn rank_drift(
+    previous: &[FrequencyEntry<String>],
+    current: &[FrequencyEntry<String>],
+    lexeme_ids: &LexemeIdTable,
+) -> Vec<(Lexeme<String>, RankDrift)> {
+    let previous_ranks: BTreeMap<LexemeId, u32> = previous
+        .iter()
+        .zip(1..)
+        .filter_map(|(entry, rank)| Some((lexeme_ids.get(&entry.lexeme)?, rank)))
+        .collect();
+    current
+        .iter()
+        .zip(1..)
+        .filter_map(|(entry, rank)| {
+            let previous_rank = *previous_ranks.get(&lexeme_ids.get(&entry.lexeme)?)?;
+            let drift = RankDrift {
+                previous_rank,
+                rank,
+            };
+            (drift.factor() >= REPORTED_FACTOR).then(|| (entry.lexeme.clone(), drift))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Heteronym, PartOfSpeech};
+
+    fn noun(word: &str) -> Lexeme<String> {
+        Lexeme::Heteronym(Heteronym {
+            word: word.to_string(),
+            lemma: word.to_string(),
+            pos: PartOfSpeech::Noun,
+        })
+    }
+
+    fn entries(lexemes: &[&Lexeme<String>]) -> Vec<FrequencyEntry<String>> {
+        lexemes
+            .iter()
+            .map(|lexeme| FrequencyEntry {
+                lexeme: (*lexeme).clone(),
+                count: 10,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_reports_lexemes_that_moved_a_lot() {
+        let [chat, chien, maison, pain, vin, lait, eau] =
+            ["chat", "chien", "maison", "pain", "vin", "lait", "eau"].map(noun);
+        let mut lexeme_ids = LexemeIdTable::default();
+        lexeme_ids.assign([&chat, &chien, &maison, &pain, &vin, &lait]);
+        let previous = entries(&[&chat, &chien, &maison, &pain, &vin, &lait]);
+
+        // "vin" only went from 5th to 7th, and "eau" is new
+        lexeme_ids.assign([&chat, &chien, &maison, &lait, &eau, &pain, &vin]);
+        let current = entries(&[&chat, &chien, &maison, &lait, &eau, &pain, &vin]);
+
+        assert_eq!(
+            rank_drift(&previous, &current, &lexeme_ids),
+            vec![
+                (
+                    lait,
+                    RankDrift {
+                        previous_rank: 6,
+                        rank: 4,
+                    }
+                ),
+                (
+                    pain,
+                    RankDrift {
+                        previous_rank: 4,
+                        rank: 6,
+                    }
+                ),
+            ]
+        );
+    }
+}
@@ -0,0 +1,56 @@
+//! FSRS parameters, either recommended for a course (shipped in its language pack as
+//! `LanguagePack::fsrs_preset`) or the user's own.
+
+use std::cmp::Ordering;
+
+#[derive(
+    Clone,
+    Debug,
+    serde::Serialize,
+    serde::Deserialize,
+    rkyv::Archive,
+    rkyv::Serialize,
+    rkyv::Deserialize,
+    tsify::Tsify,
+)]
+#[rkyv(derive(Debug))]
+#[tsify(into_wasm_abi, from_wasm_abi)]
+pub struct FsrsParameters {
+    /// The probability of recalling a card that reviews are scheduled for
+    pub request_retention: f64,
+    /// The FSRS model weights. Empty keeps FSRS's defaults, and so does a list of the wrong
+    /// length, since it was probably made for another FSRS version.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub weights: Vec<f64>,
+}
+
+// Parameters are compared by their exact values, so they can be part of deck events (which are
+// ordered)
+impl Ord for FsrsParameters {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.request_retention
+            .total_cmp(&other.request_retention)
+            .then_with(|| {
+                self.weights
+                    .iter()
+                    .zip(&other.weights)
+                    .map(|(a, b)| a.total_cmp(b))
+                    .find(|ordering| ordering.is_ne())
+                    .unwrap_or_else(|| self.weights.len().cmp(&other.weights.len()))
+            })
+    }
+}
+
+impl PartialOrd for FsrsParameters {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for FsrsParameters {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other).is_eq()
+    }
+}
+
+impl Eq for FsrsParameters {}
@@ -74,6 +74,11 @@ impl<K: Clone + Hash + Eq, V: Clone> IndexMap<K, V> {
         self.indices.contains_key(key)
     }
 
+    /// Returns the position of the key in insertion order
+    pub fn get_index_of(&self, key: &K) -> Option<usize> {
+        self.indices.get(key).copied()
+    }
+
     /// Gets the key-value pair at the given index
     pub fn get_index(&self, index: usize) -> Option<(&K, &V)> {
         self.order.get(index).map(|(k, v)| (k, v))
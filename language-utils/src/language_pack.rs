@@ -1,17 +1,28 @@
+use crate::course_completion::CompletionCriteria;
+use crate::frequency_drift::RankDrift;
+use crate::fsrs_parameters::FsrsParameters;
 use crate::indexmap::IndexMap;
+use crate::lexeme_ids::LexemeId;
+use crate::spelling_variants::SpellingVariants;
 use crate::{
     ConsolidatedLanguageData, DictionaryEntry, Frequency, Heteronym, HomophonePractice,
-    HomophoneWordPair, Lexeme, Literal, MovieMetadata, PatternPosition, PhrasebookEntry,
-    PronunciationData, SentenceSource,
+    HomophoneWordPair, Language, Lexeme, Literal, LyricLine, MovieMetadata, PatternPosition,
+    PhrasebookEntry, PronunciationData, SentenceSource, SongLyrics, WordNotes, native_strings,
+    ui_strings,
 };
 use lasso::Spur;
+use rkyv::with::{Identity, Map, MapKV};
 use rustc_hash::FxHashMap;
 use std::collections::{BTreeMap, BTreeSet};
+use std::sync::Arc;
 
 #[derive(Debug, rkyv::Archive, rkyv::Serialize, rkyv::Deserialize)]
 pub struct LanguagePack {
     pub rodeo: lasso::RodeoReader,
-    pub translations: FxHashMap<Spur, Vec<Spur>>,
+    /// The native translations of each sentence. They're in `native_strings` rather than `rodeo`,
+    /// so packs with the same native language share them.
+    #[rkyv(with = MapKV<Identity, Map<native_strings::Shared>>)]
+    pub translations: FxHashMap<Spur, Vec<Arc<str>>>,
     pub words_to_heteronyms: FxHashMap<Spur, BTreeSet<Heteronym<Spur>>>,
     pub sentences_containing_lexeme_index: FxHashMap<Lexeme<Spur>, Vec<Spur>>,
     pub sentences_to_literals: FxHashMap<Spur, Vec<Literal<Spur>>>,
@@ -22,6 +33,9 @@ pub struct LanguagePack {
     /// Per-movie word frequencies indexed by movie ID
     pub movie_word_frequencies: FxHashMap<String, IndexMap<Lexeme<Spur>, Frequency>>,
     pub dictionary: BTreeMap<Heteronym<Spur>, DictionaryEntry>,
+    /// The dictionary's heteronyms grouped by their written word, most common first. The first
+    /// heteronym's word is the one to show for the group.
+    pub dictionary_groups: Vec<Vec<Heteronym<Spur>>>,
     pub phrasebook: BTreeMap<Spur, PhrasebookEntry>,
     pub word_to_pronunciation: FxHashMap<Spur, Spur>,
     pub pronunciation_to_words: FxHashMap<Spur, Vec<Spur>>,
@@ -34,6 +48,33 @@ pub struct LanguagePack {
     pub movies: FxHashMap<String, MovieMetadata>,
     /// Sentence source provenance tracking (maps sentence to its sources)
     pub sentence_sources: FxHashMap<Spur, SentenceSource>,
+    /// UI phrases by ISO 639-3 language code, then key. Look them up with `ui_string`.
+    pub ui_strings: BTreeMap<String, BTreeMap<String, String>>,
+    /// Sentences flagged as containing profanity when the pack was built
+    pub profane_sentences: BTreeSet<Spur>,
+    /// FSRS parameters for decks of this course whose user hasn't set their own. `None` uses
+    /// the app's defaults.
+    pub fsrs_preset: Option<FsrsParameters>,
+    /// What it takes to finish the course. `None` uses `CompletionCriteria::default`.
+    pub completion_criteria: Option<CompletionCriteria>,
+    /// Spellings that differ between standards of the same language, by ISO 639-3 language code.
+    /// Look them up with `spelling_variants`.
+    pub spelling_variants: BTreeMap<String, SpellingVariants>,
+    /// Lemmas related to each lemma (same word family), as dictionary heteronyms
+    pub word_families: FxHashMap<Spur, Vec<Heteronym<Spur>>>,
+    /// The stable id of each lexeme in `word_frequencies`, see `lexeme_ids`
+    pub lexeme_ids: FxHashMap<Lexeme<Spur>, LexemeId>,
+    /// Lexemes from earlier versions of the pack that are now called something else. Look them up
+    /// with `renamed_lexeme`.
+    pub renamed_lexemes: BTreeMap<Lexeme<String>, Lexeme<Spur>>,
+    /// Songs with timed lyrics indexed by song ID
+    pub songs: FxHashMap<String, SongLyrics<Spur>>,
+    /// By the ISO 639-3 code of another target language, its lemmas paired with their cognates in
+    /// this pack. Look them up with `cognate`.
+    pub cognates: BTreeMap<String, BTreeMap<Heteronym<String>, Heteronym<Spur>>>,
+    /// The lexemes whose frequency rank moved a lot since the previous build of the pack, see
+    /// `frequency_drift`
+    pub frequency_drift: FxHashMap<Lexeme<Spur>, RankDrift>,
 }
 
 impl LanguagePack {
@@ -63,6 +104,52 @@ impl LanguagePack {
             .copied()
     }
 
+    /// What `lexeme`, from an earlier version of the pack, is called in this one. `None` if it
+    /// wasn't renamed.
+    pub fn renamed_lexeme(&self, lexeme: &Lexeme<String>) -> Option<Lexeme<Spur>> {
+        self.renamed_lexemes.get(lexeme).copied()
+    }
+
+    /// The cognate in this pack of `heteronym`, a lemma from the pack for `language`
+    pub fn cognate(
+        &self,
+        language: Language,
+        heteronym: &Heteronym<String>,
+    ) -> Option<Heteronym<Spur>> {
+        self.cognates
+            .get(language.iso_639_3())?
+            .get(heteronym)
+            .copied()
+    }
+
+    /// The notes on `heteronym`, which are its lemma's for forms other than the lemma
+    pub fn word_notes(&self, heteronym: &Heteronym<Spur>) -> Option<&WordNotes> {
+        let lemma = Heteronym {
+            word: heteronym.lemma,
+            lemma: heteronym.lemma,
+            pos: heteronym.pos,
+        };
+        self.dictionary
+            .get(heteronym)
+            .and_then(|entry| entry.notes.as_ref())
+            .or_else(|| self.dictionary.get(&lemma)?.notes.as_ref())
+    }
+
+    /// The UI phrase for `key` in `language`, falling back to the one built into the app for
+    /// packs that don't have it
+    pub fn ui_string(&self, language: Language, key: &str) -> Option<&str> {
+        self.ui_strings
+            .get(language.iso_639_3())
+            .and_then(|strings| strings.get(key))
+            .map(String::as_str)
+            .or_else(|| ui_strings::builtin(language, key))
+    }
+
+    /// `None` for languages with a single spelling standard
+    pub fn spelling_variants(&self, language: Language) -> Option<&SpellingVariants> {
+        self.spelling_variants.get(language.iso_639_3())
+    }
+
     pub fn new(language_data: ConsolidatedLanguageData) -> Self {
         let rodeo = {
             let mut rodeo = lasso::Rodeo::new();
@@ -87,7 +174,7 @@ impl LanguagePack {
                         rodeo.get(target_language).unwrap(),
                         native_languages
                             .iter()
-                            .map(|n| rodeo.get(n).unwrap())
+                            .map(|n| native_strings::intern(n))
                             .collect(),
                     )
                 })
@@ -327,6 +414,102 @@ impl LanguagePack {
                 .collect()
         };
 
+        let profane_sentences = language_data
+            .profane_sentences
+            .iter()
+            .filter_map(|sentence| rodeo.get(sentence))
+            .collect();
+
+        let dictionary_groups = language_data
+            .dictionary_groups
+            .iter()
+            .map(|group| {
+                group
+                    .iter()
+                    .filter_map(|heteronym| heteronym.get_interned(&rodeo))
+                    .collect::<Vec<_>>()
+            })
+            .filter(|group| !group.is_empty())
+            .collect();
+
+        let word_families = language_data
+            .word_families
+            .iter()
+            .filter_map(|(lemma, related)| {
+                let related: Vec<_> = related
+                    .iter()
+                    .filter_map(|heteronym| heteronym.get_interned(&rodeo))
+                    .collect();
+                Some((rodeo.get(lemma)?, related))
+            })
+            .collect();
+
+        let (lexeme_ids, renamed_lexemes) = {
+            let mut current: FxHashMap<Lexeme<Spur>, LexemeId> = FxHashMap::default();
+            let mut retired = Vec::new();
+            for (lexeme, id) in &language_data.lexeme_ids {
+                match lexeme.get_interned(&rodeo) {
+                    Some(interned) if word_frequencies.contains_key(&interned) => {
+                        current.insert(interned, *id);
+                    }
+                    _ => retired.push((lexeme, *id)),
+                }
+            }
+            let by_id: FxHashMap<LexemeId, Lexeme<Spur>> =
+                current.iter().map(|(lexeme, id)| (*id, *lexeme)).collect();
+            let renamed = retired
+                .into_iter()
+                .filter_map(|(lexeme, id)| Some((lexeme.clone(), *by_id.get(&id)?)))
+                .collect();
+            (current, renamed)
+        };
+
+        let songs = language_data
+            .songs
+            .iter()
+            .map(|song| {
+                let lines = song
+                    .lines
+                    .iter()
+                    .filter_map(|line| {
+                        Some(LyricLine {
+                            sentence: rodeo.get(&line.sentence)?,
+                            start_ms: line.start_ms,
+                            end_ms: line.end_ms,
+                            literal_start_ms: line.literal_start_ms.clone(),
+                        })
+                    })
+                    .collect();
+                (
+                    song.id.clone(),
+                    SongLyrics {
+                        id: song.id.clone(),
+                        title: song.title.clone(),
+                        artist: song.artist.clone(),
+                        lines,
+                    },
+                )
+            })
+            .collect();
+
+        let cognates = language_data
+            .cognates
+            .iter()
+            .map(|(language, pairs)| {
+                let pairs = pairs
+                    .iter()
+                    .filter_map(|(other, ours)| Some((other.clone(), ours.get_interned(&rodeo)?)))
+                    .collect();
+                (language.clone(), pairs)
+            })
+            .collect();
+
+        let frequency_drift = language_data
+            .frequency_drift
+            .iter()
+            .filter_map(|(lexeme, drift)| Some((lexeme.get_interned(&rodeo)?, *drift)))
+            .collect();
+
         Self {
             rodeo,
             translations,
@@ -348,6 +531,26 @@ impl LanguagePack {
             pronunciation_max_freq_cache,
             movies,
             sentence_sources,
+            ui_strings: language_data.ui_strings,
+            profane_sentences,
+            fsrs_preset: language_data.fsrs_preset,
+            completion_criteria: language_data.completion_criteria,
+            spelling_variants: language_data.spelling_variants,
+            dictionary_groups,
+            word_families,
+            lexeme_ids,
+            renamed_lexemes,
+            songs,
+            cognates,
+            frequency_drift,
         }
     }
 }
+
+impl Drop for LanguagePack {
+    fn drop(&mut self) {
+        // Drop our references first, so the strings only this pack used are released
+        self.translations.clear();
+        native_strings::release_unused();
+    }
+}
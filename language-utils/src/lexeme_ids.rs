@@ -0,0 +1,156 @@
+//! Ids for lexemes that stay the same across versions of a course's language pack. Cards refer to
+//! lexemes by their strings, and those change when the NLP model's lemmas or parts of speech do,
+//! which would orphan the cards' review history. generate-data keeps a `LexemeIdTable` for each
+//! language next to its source data and extends it every build, and the pack carries what the
+//! table says was renamed (see `LanguagePack::renamed_lexeme`), so decks can follow their cards to
+//! the new strings.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::{Heteronym, Lexeme};
+
+#[derive(
+    Copy,
+    Clone,
+    Debug,
+    serde::Serialize,
+    serde::Deserialize,
+    Hash,
+    Eq,
+    PartialEq,
+    Ord,
+    PartialOrd,
+    tsify::Tsify,
+    rkyv::Archive,
+    rkyv::Serialize,
+    rkyv::Deserialize,
+)]
+#[rkyv(compare(PartialEq), derive(PartialEq, PartialOrd, Eq, Ord, Hash))]
+#[serde(transparent)]
+#[tsify(into_wasm_abi, from_wasm_abi)]
+pub struct LexemeId(pub u32);
+
+/// Every lexeme that's been in a course's packs, including the ones that aren't anymore, and its
+/// id. Once a lexeme has an id it keeps it.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct LexemeIdTable {
+    ids: BTreeMap<Lexeme<String>, LexemeId>,
+}
+
+impl LexemeIdTable {
+    pub fn from_entries(entries: impl IntoIterator<Item = (Lexeme<String>, LexemeId)>) -> Self {
+        Self {
+            ids: entries.into_iter().collect(),
+        }
+    }
+
+    pub fn entries(&self) -> Vec<(Lexeme<String>, LexemeId)> {
+        self.ids
+            .iter()
+            .map(|(lexeme, id)| (lexeme.clone(), *id))
+            .collect()
+    }
+
+    pub fn get(&self, lexeme: &Lexeme<String>) -> Option<LexemeId> {
+        self.ids.get(lexeme).copied()
+    }
+
+    /// Gives every lexeme in `current` (the lexemes of the pack being built) an id. A new
+    /// heteronym takes over the id of a heteronym that's no longer current if it's the same word
+    /// with only its lemma or only its part of speech changed, as long as neither of them could
+    /// be paired up with another heteronym that way. Anything else new gets a new id.
+    pub fn assign<'a>(&mut self, current: impl IntoIterator<Item = &'a Lexeme<String>>) {
+        let current: BTreeSet<&Lexeme<String>> = current.into_iter().collect();
+        let mut claimed: BTreeSet<LexemeId> = current
+            .iter()
+            .filter_map(|lexeme| self.ids.get(*lexeme))
+            .copied()
+            .collect();
+        let mut next_id = self.ids.values().map(|id| id.0 + 1).max().unwrap_or(0);
+
+        let new: Vec<&Lexeme<String>> = current
+            .iter()
+            .filter(|lexeme| !self.ids.contains_key(**lexeme))
+            .copied()
+            .collect();
+        for lexeme in new.iter().copied() {
+            let renamed_from = match lexeme {
+                Lexeme::Heteronym(heteronym) => {
+                    let renames_of = |old: &Heteronym<String>| {
+                        new.iter()
+                            .filter(
+                                |new| matches!(new, Lexeme::Heteronym(new) if is_renamed(old, new)),
+                            )
+                            .count()
+                    };
+                    let mut candidates = self.ids.iter().filter_map(|(old, id)| match old {
+                        Lexeme::Heteronym(old)
+                            if !claimed.contains(id) && is_renamed(old, heteronym) =>
+                        {
+                            Some((*id, renames_of(old)))
+                        }
+                        _ => None,
+                    });
+                    match (candidates.next(), candidates.next()) {
+                        (Some((id, 1)), None) => Some(id),
+                        _ => None,
+                    }
+                }
+                Lexeme::Multiword(_) => None,
+            };
+            let id = renamed_from.unwrap_or_else(|| {
+                next_id += 1;
+                LexemeId(next_id - 1)
+            });
+            claimed.insert(id);
+            self.ids.insert(lexeme.clone(), id);
+        }
+    }
+}
+
+fn is_renamed(old: &Heteronym<String>, new: &Heteronym<String>) -> bool {
+    old.word == new.word && (old.lemma == new.lemma) != (old.pos == new.pos)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::PartOfSpeech;
+
+    fn heteronym(word: &str, lemma: &str, pos: PartOfSpeech) -> Lexeme<String> {
+        Lexeme::Heteronym(Heteronym {
+            word: word.to_string(),
+            lemma: lemma.to_string(),
+            pos,
+        })
+    }
+
+    #[test]
+    fn test_renamed_heteronyms_keep_their_ids() {
+        let fait = heteronym("fait", "faire", PartOfSpeech::Verb);
+        let chat = heteronym("chat", "chat", PartOfSpeech::Noun);
+        let mut table = LexemeIdTable::default();
+        table.assign([&fait, &chat]);
+        let (fait_id, chat_id) = (table.get(&fait).unwrap(), table.get(&chat).unwrap());
+        assert_ne!(fait_id, chat_id);
+
+        // The lemma changed, and a word that's new altogether was added
+        let fait_renamed = heteronym("fait", "fait", PartOfSpeech::Verb);
+        let chien = heteronym("chien", "chien", PartOfSpeech::Noun);
+        table.assign([&fait_renamed, &chat, &chien]);
+        assert_eq!(table.get(&fait_renamed), Some(fait_id));
+        assert_eq!(table.get(&fait), Some(fait_id));
+        assert_eq!(table.get(&chat), Some(chat_id));
+        let chien_id = table.get(&chien).unwrap();
+        assert!(chien_id != fait_id && chien_id != chat_id);
+
+        // It's ambiguous which of these "fait" became, so neither takes its id
+        let mut table = LexemeIdTable::default();
+        table.assign([&fait]);
+        let fait_id = table.get(&fait).unwrap();
+        let fait_aux = heteronym("fait", "faire", PartOfSpeech::Aux);
+        table.assign([&fait_renamed, &fait_aux]);
+        assert_ne!(table.get(&fait_renamed), Some(fait_id));
+        assert_ne!(table.get(&fait_aux), Some(fait_id));
+    }
+}
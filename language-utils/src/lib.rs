@@ -1,8 +1,27 @@
+pub mod backend_routes;
+pub mod content_report;
+pub mod course_completion;
+pub mod crash_report;
+pub mod data_export;
 pub mod features;
+pub mod frequency_drift;
+pub mod fsrs_parameters;
 pub mod indexmap;
 pub mod language_pack;
+pub mod lexeme_ids;
+pub mod local_grading;
+pub mod morph_tag;
+pub mod native_strings;
+pub mod pack_manifest;
 pub mod profile;
+pub mod pronunciation_patterns;
+pub mod public_stats;
+pub mod scheduler_telemetry;
+pub mod shared_list;
+pub mod spelling_variants;
 pub mod text_cleanup;
+pub mod ui_strings;
+pub mod webhook;
 
 use rustc_hash::FxHashMap;
 use std::collections::BTreeMap;
@@ -12,6 +31,7 @@ use std::hash::Hash;
 use wasm_bindgen::prelude::*;
 
 use crate::features::Morphology;
+use crate::morph_tag::MorphTag;
 
 #[derive(
     Clone,
@@ -231,6 +251,9 @@ pub struct DictionaryEntry {
     pub target_language_word: String,
     pub definitions: Vec<TargetToNativeWord>,
     pub morphology: Vec<Morphology>,
+    /// Only on lemmas' entries, since the other forms of a word share its history
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub notes: Option<WordNotes>,
 }
 
 impl From<(DictionaryEntryThoughts, Vec<Morphology>)> for DictionaryEntry {
@@ -240,10 +263,37 @@ impl From<(DictionaryEntryThoughts, Vec<Morphology>)> for DictionaryEntry {
             target_language_word: entry.target_language_word,
             definitions: entry.definitions,
             morphology,
+            notes: None,
         }
     }
 }
 
+/// Background on a word for curious learners, in the native language. Either can be missing
+/// when there's nothing worth saying.
+#[derive(
+    Clone,
+    Debug,
+    serde::Deserialize,
+    schemars::JsonSchema,
+    serde::Serialize,
+    tsify::Tsify,
+    Eq,
+    PartialEq,
+    Ord,
+    PartialOrd,
+    rkyv::Archive,
+    rkyv::Serialize,
+    rkyv::Deserialize,
+)]
+#[rkyv(compare(PartialEq), derive(Debug))]
+#[tsify(into_wasm_abi, from_wasm_abi)]
+pub struct WordNotes {
+    /// Where the word comes from, e.g. that "fenêtre" and "fenestration" share a Latin root
+    pub etymology: Option<String>,
+    /// How the word is used beyond its definitions, e.g. register or false friends
+    pub usage: Option<String>,
+}
+
 /// Tracks the source(s) of a sentence. Since a sentence can appear in multiple sources,
 /// we use boolean fields for each source type.
 #[derive(
@@ -258,8 +308,10 @@ impl From<(DictionaryEntryThoughts, Vec<Morphology>)> for DictionaryEntry {
     rkyv::Archive,
     rkyv::Serialize,
     rkyv::Deserialize,
+    tsify::Tsify,
 )]
 #[rkyv(compare(PartialEq), derive(Debug))]
+#[tsify(into_wasm_abi, from_wasm_abi)]
 pub struct SentenceSource {
     /// Sentence came from an Anki deck
     pub from_anki: bool,
@@ -305,6 +357,52 @@ impl SentenceSource {
     }
 }
 
+/// A movie's US (MPAA) content rating
+#[derive(
+    Copy,
+    Clone,
+    Debug,
+    serde::Serialize,
+    serde::Deserialize,
+    Eq,
+    PartialEq,
+    Ord,
+    PartialOrd,
+    Hash,
+    rkyv::Archive,
+    rkyv::Serialize,
+    rkyv::Deserialize,
+)]
+#[cfg_attr(target_arch = "wasm32", derive(tsify::Tsify))]
+#[cfg_attr(target_arch = "wasm32", tsify(into_wasm_abi, from_wasm_abi))]
+#[rkyv(compare(PartialEq), derive(Debug))]
+pub enum MaturityRating {
+    G,
+    PG,
+    PG13,
+    R,
+    NC17,
+}
+
+impl MaturityRating {
+    /// A US certification as TMDB lists it, e.g. "PG-13". `None` for unrated movies ("NR").
+    pub fn from_us_certification(certification: &str) -> Option<Self> {
+        match certification.trim() {
+            "G" => Some(MaturityRating::G),
+            "PG" => Some(MaturityRating::PG),
+            "PG-13" => Some(MaturityRating::PG13),
+            "R" => Some(MaturityRating::R),
+            "NC-17" => Some(MaturityRating::NC17),
+            _ => None,
+        }
+    }
+
+    /// Whether children shouldn't watch it without a parent (R and above)
+    pub fn is_mature(self) -> bool {
+        self >= MaturityRating::R
+    }
+}
+
 /// Basic movie metadata without poster bytes, for serialization to files
 #[derive(Clone, Debug, serde::Serialize, serde::Deserialize, Eq, PartialEq, Ord, PartialOrd)]
 pub struct MovieMetadataBasic {
@@ -314,9 +412,69 @@ pub struct MovieMetadataBasic {
     pub title: String,
     /// Release year
     pub year: Option<u16>,
+    /// `None` if the movie is unrated, or its metadata was fetched before ratings were
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub maturity: Option<MaturityRating>,
+}
+
+/// Posters kept in the language pack are scaled down to this width
+pub const POSTER_THUMBNAIL_WIDTH: u32 = 92;
+/// The widths of the posters that are fetched separately (see `ImageAsset`), besides the original
+pub const POSTER_WIDTHS: [u32; 1] = [185];
+
+/// An image that's fetched on its own rather than stored in the language pack, so the pack stays
+/// small. Images are named by their hash, so a path never changes what it points to and the image
+/// can be cached forever.
+#[derive(
+    Clone,
+    Debug,
+    serde::Serialize,
+    serde::Deserialize,
+    Eq,
+    PartialEq,
+    Ord,
+    PartialOrd,
+    rkyv::Archive,
+    rkyv::Serialize,
+    rkyv::Deserialize,
+)]
+#[cfg_attr(target_arch = "wasm32", derive(tsify::Tsify))]
+#[cfg_attr(target_arch = "wasm32", tsify(into_wasm_abi, from_wasm_abi))]
+#[rkyv(compare(PartialEq), derive(Debug))]
+pub struct ImageAsset {
+    /// The hex SHA-256 of the image (JPEG format)
+    pub sha256: String,
+    pub width: u32,
+    pub height: u32,
+    pub size_bytes: u32,
 }
 
-/// Full movie metadata including poster bytes, for runtime use
+impl ImageAsset {
+    pub fn new(bytes: &[u8], width: u32, height: u32) -> Self {
+        Self {
+            sha256: pack_manifest::sha256_hex(bytes),
+            width,
+            height,
+            size_bytes: bytes.len() as u32,
+        }
+    }
+
+    /// Where the backend serves the image, see `backend_routes::IMAGES_PATH`
+    pub fn path(&self) -> String {
+        format!("{}/{}", backend_routes::IMAGES_PATH, self.file_name())
+    }
+
+    pub fn file_name(&self) -> String {
+        format!("{}.jpg", self.sha256)
+    }
+
+    /// Whether `bytes` is this image, e.g. rather than a truncated download of it
+    pub fn matches(&self, bytes: &[u8]) -> bool {
+        bytes.len() == self.size_bytes as usize && pack_manifest::sha256_hex(bytes) == self.sha256
+    }
+}
+
+/// Full movie metadata including posters, for runtime use
 #[derive(
     Clone,
     Debug,
@@ -340,8 +498,14 @@ pub struct MovieMetadata {
     pub title: String,
     /// Release year
     pub year: Option<u16>,
-    /// Poster image bytes (JPEG format)
-    pub poster_bytes: Option<Vec<u8>>,
+    /// A small poster (`POSTER_THUMBNAIL_WIDTH` wide, JPEG format), kept in the pack so there's
+    /// something to show before `posters` are fetched, or when they can't be
+    pub poster_thumbnail: Option<Vec<u8>>,
+    /// Larger posters, smallest first, see `poster_for_width`
+    #[serde(default)]
+    pub posters: Vec<ImageAsset>,
+    #[serde(default)]
+    pub maturity: Option<MaturityRating>,
 }
 
 impl From<MovieMetadataBasic> for MovieMetadata {
@@ -350,11 +514,23 @@ impl From<MovieMetadataBasic> for MovieMetadata {
             id: basic.id,
             title: basic.title,
             year: basic.year,
-            poster_bytes: None,
+            poster_thumbnail: None,
+            posters: Vec::new(),
+            maturity: basic.maturity,
         }
     }
 }
 
+impl MovieMetadata {
+    /// The smallest poster at least `width` wide, or the largest there is if none are
+    pub fn poster_for_width(&self, width: u32) -> Option<&ImageAsset> {
+        self.posters
+            .iter()
+            .find(|poster| poster.width >= width)
+            .or(self.posters.last())
+    }
+}
+
 #[derive(
     Clone,
     Debug,
@@ -378,6 +554,55 @@ where
     pub end_ms: u32,
 }
 
+/// A song from sentence-sources/songs/, with its lyrics timed for singing along
+#[derive(
+    Clone,
+    Debug,
+    serde::Serialize,
+    serde::Deserialize,
+    rkyv::Archive,
+    rkyv::Serialize,
+    rkyv::Deserialize,
+)]
+#[rkyv(derive(Debug))]
+pub struct SongLyrics<S>
+where
+    S: rkyv::Archive,
+    <S as rkyv::Archive>::Archived: std::fmt::Debug,
+{
+    /// The LRC file's name without the extension (e.g. "chuis-bo")
+    pub id: String,
+    pub title: String,
+    pub artist: Option<String>,
+    pub lines: Vec<LyricLine<S>>,
+}
+
+#[derive(
+    Clone,
+    Debug,
+    serde::Serialize,
+    serde::Deserialize,
+    rkyv::Archive,
+    rkyv::Serialize,
+    rkyv::Deserialize,
+)]
+#[rkyv(derive(Debug))]
+pub struct LyricLine<S>
+where
+    S: rkyv::Archive,
+    <S as rkyv::Archive>::Archived: std::fmt::Debug,
+{
+    /// The sentence text (Spur reference to sentence)
+    pub sentence: S,
+    /// Start timestamp in milliseconds
+    pub start_ms: u32,
+    /// End timestamp in milliseconds
+    pub end_ms: u32,
+    /// When each of the sentence's literals is sung, in milliseconds. Empty if the sentence
+    /// wasn't analyzed, e.g. because it has words that aren't in the dictionary.
+    pub literal_start_ms: Vec<u32>,
+}
+
 #[derive(
     Clone,
     Debug,
@@ -539,6 +764,13 @@ pub struct DocToken {
     pub morph: BTreeMap<String, String>,
 }
 
+impl DocToken {
+    /// The features of `morph` that `Literal`s keep
+    pub fn morph_tag(&self) -> Option<MorphTag> {
+        MorphTag::from_ud_features(&self.morph)
+    }
+}
+
 #[derive(
     Copy,
     Clone,
@@ -598,6 +830,9 @@ where
     pub text: S,
     pub whitespace: S,
     pub heteronym: Option<Heteronym<S>>,
+    /// The word's morphological features, where the tokenizer gave any (see `MorphTag::describe`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub morph: Option<MorphTag>,
 }
 
 impl Literal<String> {
@@ -606,6 +841,7 @@ impl Literal<String> {
             text: rodeo.get_or_intern(&self.text),
             whitespace: rodeo.get_or_intern(&self.whitespace),
             heteronym: self.heteronym.as_ref().map(|h| h.get_or_intern(rodeo)),
+            morph: self.morph,
         }
     }
 
@@ -618,6 +854,7 @@ impl Literal<String> {
             text: rodeo.get(&self.text)?,
             whitespace: rodeo.get(&self.whitespace)?,
             heteronym,
+            morph: self.morph,
         })
     }
 }
@@ -628,6 +865,7 @@ impl Literal<lasso::Spur> {
             text: rodeo.resolve(&self.text).to_string(),
             whitespace: rodeo.resolve(&self.whitespace).to_string(),
             heteronym: self.heteronym.as_ref().map(|h| h.resolve(rodeo)),
+            morph: self.morph,
         }
     }
 }
@@ -834,6 +1072,16 @@ pub mod autograde {
         pub user_sentence: String,
         pub primary_expression: Lexeme<String>,
         pub lexemes: Vec<Lexeme<String>>,
+        /// Language to write the encouragement and explanation in. Defaults to the course's native language.
+        #[serde(default)]
+        pub explanation_language: Option<Language>,
+        /// What the user has got wrong before. `None` if they've turned off sharing it.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pub mistake_history: Option<MistakeDigest>,
+        /// Other languages the user speaks, which the translation may be in instead of the
+        /// course's native language
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        pub other_native_languages: Vec<Language>,
     }
     #[derive(
         Clone, Debug, serde::Serialize, serde::Deserialize, schemars::JsonSchema, tsify::Tsify,
@@ -845,6 +1093,10 @@ pub mod autograde {
         pub primary_expression_status: Remembered,
         pub expressions_remembered: Vec<Lexeme<String>>,
         pub expressions_forgot: Vec<Lexeme<String>>,
+        /// Filled in by the server after checking the grade, not asked of the LLM
+        #[serde(default)]
+        #[schemars(skip)]
+        pub validation: transcription_challenge::GradeValidation,
     }
 
     #[derive(Clone, Debug, serde::Serialize, serde::Deserialize, tsify::Tsify)]
@@ -852,12 +1104,145 @@ pub mod autograde {
     pub struct AutoGradeTranscriptionRequest {
         pub course: Course,
         pub submission: Vec<transcription_challenge::PartSubmitted>,
+        /// Language to write the encouragement and explanation in. Defaults to the course's native language.
+        #[serde(default)]
+        pub explanation_language: Option<Language>,
+        /// What the user has got wrong before. `None` if they've turned off sharing it.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pub mistake_history: Option<MistakeDigest>,
+    }
+
+    /// The words a user habitually mixes up, most frequent first, so the autograder can point out
+    /// a recurring mistake rather than treating it as a one-off
+    #[derive(
+        Clone, Debug, Default, serde::Serialize, serde::Deserialize, tsify::Tsify, PartialEq, Eq,
+    )]
+    #[tsify(into_wasm_abi, from_wasm_abi)]
+    pub struct MistakeDigest {
+        pub confusions: Vec<Confusion>,
+    }
+
+    #[derive(Clone, Debug, serde::Serialize, serde::Deserialize, tsify::Tsify, PartialEq, Eq)]
+    #[tsify(into_wasm_abi, from_wasm_abi)]
+    pub struct Confusion {
+        /// The word the user should have written
+        pub expected: String,
+        pub wrote: String,
+        /// How many times they wrote `wrote` instead
+        pub count: u32,
+    }
+
+    impl MistakeDigest {
+        /// The digest goes into the grading prompt, so it's kept short
+        pub const MAX_CONFUSIONS: usize = 8;
+        pub const MAX_WORD_CHARS: usize = 32;
+
+        /// Cuts the digest down to size. The backend does this too, since it can't trust clients
+        /// to.
+        pub fn capped(mut self) -> Self {
+            self.confusions.truncate(Self::MAX_CONFUSIONS);
+            for confusion in &mut self.confusions {
+                for word in [&mut confusion.expected, &mut confusion.wrote] {
+                    if let Some((end, _)) = word.char_indices().nth(Self::MAX_WORD_CHARS) {
+                        word.truncate(end);
+                    }
+                }
+            }
+            self
+        }
+
+        pub fn is_empty(&self) -> bool {
+            self.confusions.is_empty()
+        }
+    }
+}
+
+pub mod sentence_generation {
+    use super::*;
+
+    /// The LLM gets the user's known vocabulary in the prompt, so requests can list at most this
+    /// many known lexemes. The backend rejects requests with more.
+    pub const MAX_KNOWN_LEXEMES: usize = 600;
+
+    /// Ask the backend for a new example sentence for a lexeme that has no comprehensible
+    /// sentence in the language pack.
+    #[derive(Clone, Debug, serde::Serialize, serde::Deserialize, tsify::Tsify)]
+    #[tsify(into_wasm_abi, from_wasm_abi)]
+    pub struct GenerateSentenceRequest {
+        pub course: Course,
+        /// The lexeme the sentence must contain
+        pub target_lexeme: Lexeme<String>,
+        /// Lexemes the user already knows. Every other word in the sentence must come from this list.
+        pub known_lexemes: Vec<Lexeme<String>>,
+    }
+
+    /// A sentence that was generated by the LLM and then tokenized to check that it only
+    /// uses vocabulary the user knows.
+    #[derive(Clone, Debug, serde::Serialize, serde::Deserialize, tsify::Tsify, PartialEq, Eq)]
+    #[tsify(into_wasm_abi, from_wasm_abi)]
+    pub struct GeneratedSentence {
+        pub target_language: String,
+        pub native_translations: Vec<String>,
+        pub literals: Vec<Literal<String>>,
+        /// The unique lexemes found in the sentence by the tokenizer, in order of appearance
+        pub lexemes: Vec<Lexeme<String>>,
+    }
+
+    #[derive(Clone, Debug, serde::Serialize, serde::Deserialize, tsify::Tsify)]
+    #[tsify(into_wasm_abi, from_wasm_abi)]
+    pub struct GenerateSentenceResponse {
+        /// None if no candidate sentence passed validation
+        pub sentence: Option<GeneratedSentence>,
     }
 }
 
 pub mod transcription_challenge {
     use super::*;
 
+    /// How much of the sentence the user is asked to transcribe
+    #[derive(
+        Clone,
+        Copy,
+        Debug,
+        serde::Serialize,
+        serde::Deserialize,
+        tsify::Tsify,
+        PartialEq,
+        Eq,
+        PartialOrd,
+        Ord,
+        Hash,
+    )]
+    #[tsify(into_wasm_abi, from_wasm_abi)]
+    pub enum DictationLevel {
+        /// Only the word being tested
+        SingleWord,
+        /// A few words around the word being tested
+        PhraseChunk,
+        FullSentence,
+    }
+
+    /// How the user put their answer together
+    #[derive(
+        Clone,
+        Copy,
+        Debug,
+        serde::Serialize,
+        serde::Deserialize,
+        tsify::Tsify,
+        PartialEq,
+        Eq,
+        PartialOrd,
+        Ord,
+        Hash,
+    )]
+    #[tsify(into_wasm_abi, from_wasm_abi)]
+    pub enum InputMode {
+        Typed,
+        /// Tapped words from the challenge's word bank
+        WordBank,
+    }
+
     #[derive(
         Clone,
         Debug,
@@ -997,6 +1382,37 @@ pub mod transcription_challenge {
         pub results: Vec<PartGraded>,
         pub compare: Vec<String>,
         pub autograding_error: Option<String>,
+        #[serde(default)]
+        pub validation: GradeValidation,
+    }
+
+    /// Whether the LLM's grades lined up with what was asked to be graded, or whether the LLM
+    /// wasn't used at all
+    #[derive(
+        Clone,
+        Copy,
+        Debug,
+        Default,
+        serde::Serialize,
+        serde::Deserialize,
+        tsify::Tsify,
+        PartialEq,
+        Eq,
+        PartialOrd,
+        Ord,
+        Hash,
+    )]
+    #[tsify(into_wasm_abi, from_wasm_abi)]
+    pub enum GradeValidation {
+        #[default]
+        Valid,
+        /// The first response was malformed, but asking again fixed it
+        Repaired,
+        /// The response was still malformed after asking again, so the grades shouldn't be trusted
+        Invalid,
+        /// Graded on the device by comparing words, because the LLM couldn't be reached or its
+        /// grades couldn't be trusted
+        Heuristic,
     }
 }
 
@@ -1011,6 +1427,9 @@ pub struct ConsolidatedLanguageData {
     pub nlp_sentences: Vec<(String, SentenceInfo)>,
     /// Dictionary entries for individual words
     pub dictionary: BTreeMap<Heteronym<String>, DictionaryEntry>,
+    /// The dictionary's heteronyms grouped by their written word, most common group first and
+    /// most common heteronym first within each group
+    pub dictionary_groups: Vec<Vec<Heteronym<String>>>,
     /// Phrasebook entries for multiword terms
     pub phrasebook: BTreeMap<String, PhrasebookEntry>,
     /// Frequency data for words and phrases
@@ -1029,6 +1448,30 @@ pub struct ConsolidatedLanguageData {
     pub movies: FxHashMap<String, MovieMetadata>,
     /// Sentence source provenance tracking (including movie_ids)
     pub sentence_sources: Vec<(String, SentenceSource)>,
+    /// UI phrases by ISO 639-3 language code, then key (see `ui_strings`)
+    pub ui_strings: BTreeMap<String, BTreeMap<String, String>>,
+    /// Target language sentences containing a word from the language's profanity list
+    pub profane_sentences: Vec<String>,
+    /// The recommended FSRS parameters for the course, if it has any
+    pub fsrs_preset: Option<fsrs_parameters::FsrsParameters>,
+    /// What it takes to finish the course, if it differs from the default
+    pub completion_criteria: Option<course_completion::CompletionCriteria>,
+    /// By ISO 639-3 language code, the spelling variants of the course's languages that have
+    /// several spelling standards
+    pub spelling_variants: BTreeMap<String, spelling_variants::SpellingVariants>,
+    /// For each lemma with related lemmas (derivations like "heureux" and "heureusement"), one
+    /// dictionary heteronym per related lemma
+    pub word_families: Vec<(String, Vec<Heteronym<String>>)>,
+    /// The course's `lexeme_ids::LexemeIdTable`, including lexemes that are no longer in the pack
+    pub lexeme_ids: Vec<(Lexeme<String>, lexeme_ids::LexemeId)>,
+    /// Songs with timed lyrics, for singing along
+    pub songs: Vec<SongLyrics<String>>,
+    /// By the ISO 639-3 code of another course's target language (with the same native language),
+    /// its lemmas paired with their cognates in this one, see `generate_data::cognates`
+    pub cognates: BTreeMap<String, Vec<(Heteronym<String>, Heteronym<String>)>>,
+    /// The lexemes whose frequency rank moved a lot since the previous build, see
+    /// `frequency_drift`
+    pub frequency_drift: Vec<(Lexeme<String>, frequency_drift::RankDrift)>,
 }
 
 impl ConsolidatedLanguageData {
@@ -1042,12 +1485,10 @@ impl ConsolidatedLanguageData {
             rodeo.get_or_intern(sentence);
         }
 
-        // Intern translations
-        for (french, englishes) in &self.translations {
+        // Intern translated sentences. The translations themselves are shared between packs, see
+        // `native_strings`.
+        for (french, _) in &self.translations {
             rodeo.get_or_intern(french);
-            for english in englishes {
-                rodeo.get_or_intern(english);
-            }
         }
 
         // Intern words from frequency list
@@ -1131,6 +1572,13 @@ impl ConsolidatedLanguageData {
                 rodeo.get_or_intern(movie_id);
             }
         }
+
+        // intern song lyrics, which aren't all in the sentence list
+        for song in &self.songs {
+            for line in &song.lines {
+                rodeo.get_or_intern(&line.sentence);
+            }
+        }
     }
 }
 
@@ -1156,6 +1604,25 @@ pub enum TtsProvider {
     Google,
 }
 
+impl TtsProvider {
+    /// Which voice the backend uses for `language`. Bump it when the voice or model changes, so
+    /// clients replace the audio they cached from the old one (see the frontend's `AudioCache`).
+    pub fn voice_version(&self, language: Language) -> u32 {
+        match (self, language) {
+            (TtsProvider::ElevenLabs, _) => 1,
+            (TtsProvider::Google, _) => 1,
+        }
+    }
+
+    /// A short name for the provider, for file names and logs
+    pub fn slug(&self) -> &'static str {
+        match self {
+            TtsProvider::ElevenLabs => "elevenlabs",
+            TtsProvider::Google => "google",
+        }
+    }
+}
+
 pub type Pronunciation = String;
 
 #[derive(
@@ -1546,6 +2013,22 @@ impl Course {
     }
 }
 
+/// A course as advertised by the backend's `GET /courses`, so new courses can show up without
+/// a client release
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize, tsify::Tsify)]
+#[tsify(into_wasm_abi, from_wasm_abi)]
+pub struct AvailableCourse {
+    pub course: Course,
+    /// The xxh3 hash of the language pack, in the same format as `language_data.hash`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pack_version: Option<String>,
+    /// The hash of the course's beta pack (see `pack_manifest::PackChannel`), if there is one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub beta_pack_version: Option<String>,
+    pub tts_available: bool,
+    pub autograde_available: bool,
+}
+
 pub const COURSES: &[Course] = &[
     Course {
         native_language: Language::English,
@@ -1769,7 +2252,9 @@ impl HomophonePractice<lasso::Spur> {
         }
     }
 }
-#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, tsify::Tsify)]
+#[derive(
+    serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq, Eq, PartialOrd, Ord, tsify::Tsify,
+)]
 #[tsify(into_wasm_abi, from_wasm_abi)]
 pub struct TtsRequest {
     pub text: String,
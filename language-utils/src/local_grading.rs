@@ -0,0 +1,163 @@
+//! Grading translations on the device, for users without an account. Their answers are never sent
+//! to the LLM, so the grade can only say how close the answer is to one of the sentence's known
+//! translations, not which words were understood.
+
+use std::collections::BTreeMap;
+
+use crate::autograde::{AutoGradeTranslationResponse, Remembered};
+use crate::spelling_variants::SpellingVariants;
+use crate::text_cleanup::normalize_for_grading;
+use crate::transcription_challenge::GradeValidation;
+use crate::{Language, Lexeme};
+
+/// Answers sharing at least this fraction of their words with a known translation count as
+/// remembered, which forgives a typo or a different word order in a longer sentence
+pub const CLOSE_ENOUGH: f64 = 0.8;
+
+/// A sentence's known translations into one of the languages the user may answer in
+pub struct AcceptedAnswers<'a> {
+    pub language: Language,
+    pub spelling_variants: Option<&'a SpellingVariants>,
+    pub translations: &'a [String],
+}
+
+/// Grades `user_sentence` against the closest of `accepted` translations. Close enough answers
+/// remember every lexeme. Otherwise only `primary_expression` is marked as forgotten, since
+/// there's no telling which of the other words the user got.
+pub fn grade_translation_locally(
+    user_sentence: &str,
+    accepted: &[AcceptedAnswers<'_>],
+    primary_expression: Lexeme<String>,
+    lexemes: Vec<Lexeme<String>>,
+) -> AutoGradeTranslationResponse {
+    let closest = accepted
+        .iter()
+        .flat_map(|answers| {
+            let normalize = |text: &str| {
+                normalize_for_grading(text, answers.language, answers.spelling_variants)
+            };
+            let user_sentence = normalize(user_sentence);
+            answers.translations.iter().map(move |translation| {
+                (
+                    word_overlap(&user_sentence, &normalize(translation)),
+                    translation,
+                )
+            })
+        })
+        .max_by(|(a, _), (b, _)| a.total_cmp(b));
+
+    match closest {
+        Some((overlap, translation)) if overlap >= CLOSE_ENOUGH => {
+            let encouragement = if overlap == 1.0 {
+                "Perfect! You translated it correctly!".to_string()
+            } else {
+                format!("Close enough! One way to say it: \"{translation}\"")
+            };
+            AutoGradeTranslationResponse {
+                encouragement: Some(encouragement),
+                explanation: None,
+                primary_expression_status: Remembered::Remembered,
+                expressions_remembered: lexemes,
+                expressions_forgot: Vec::new(),
+                validation: GradeValidation::Heuristic,
+            }
+        }
+        closest => AutoGradeTranslationResponse {
+            encouragement: None,
+            explanation: closest
+                .map(|(_, translation)| format!("One way to say it: \"{translation}\"")),
+            primary_expression_status: Remembered::Forgot,
+            expressions_remembered: Vec::new(),
+            expressions_forgot: vec![primary_expression],
+            validation: GradeValidation::Heuristic,
+        },
+    }
+}
+
+/// The fraction of words the two (normalized) sentences have in common, counting repeated words
+/// as many times as they appear in both. 1 when they have the same words.
+fn word_overlap(a: &str, b: &str) -> f64 {
+    let count = |sentence: &str| {
+        let mut counts = BTreeMap::<String, usize>::new();
+        for word in sentence.split_whitespace() {
+            *counts.entry(word.to_string()).or_default() += 1;
+        }
+        counts
+    };
+    let (a, b) = (count(a), count(b));
+    let total = a.values().chain(b.values()).sum::<usize>();
+    if total == 0 {
+        return 0.0;
+    }
+    let common = a
+        .iter()
+        .map(|(word, count)| (*count).min(b.get(word).copied().unwrap_or(0)))
+        .sum::<usize>();
+    (2 * common) as f64 / total as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Heteronym, PartOfSpeech};
+
+    fn noun(word: &str) -> Lexeme<String> {
+        Lexeme::Heteronym(Heteronym {
+            word: word.to_string(),
+            lemma: word.to_string(),
+            pos: PartOfSpeech::Noun,
+        })
+    }
+
+    #[test]
+    fn test_grades_by_the_closest_translation() {
+        let (chat, maison) = (noun("chat"), noun("maison"));
+        let english = ["The cat is sleeping in the big house.".to_string()];
+        let spanish = ["El gato duerme en la casa grande.".to_string()];
+        let accepted = [
+            AcceptedAnswers {
+                language: Language::English,
+                spelling_variants: None,
+                translations: &english,
+            },
+            AcceptedAnswers {
+                language: Language::Spanish,
+                spelling_variants: None,
+                translations: &spanish,
+            },
+        ];
+        let grade = |answer: &str| {
+            grade_translation_locally(
+                answer,
+                &accepted,
+                chat.clone(),
+                vec![chat.clone(), maison.clone()],
+            )
+        };
+
+        // One word off out of eight
+        let close = grade("The cat is sleeping in the large house");
+        assert_eq!(close.primary_expression_status, Remembered::Remembered);
+        assert_eq!(
+            close.expressions_remembered,
+            vec![chat.clone(), maison.clone()]
+        );
+
+        let spanish = grade("el gato duerme en la casa grande");
+        assert_eq!(spanish.primary_expression_status, Remembered::Remembered);
+        assert_eq!(
+            spanish.encouragement.as_deref(),
+            Some("Perfect! You translated it correctly!")
+        );
+
+        let wrong = grade("The dog is eating in the garden");
+        assert_eq!(wrong.primary_expression_status, Remembered::Forgot);
+        assert!(wrong.expressions_remembered.is_empty());
+        assert_eq!(wrong.expressions_forgot, vec![chat.clone()]);
+        assert_eq!(wrong.validation, GradeValidation::Heuristic);
+        assert_eq!(
+            wrong.explanation.as_deref(),
+            Some("One way to say it: \"The cat is sleeping in the big house.\"")
+        );
+    }
+}
@@ -0,0 +1,470 @@
+//! A `Morphology` packed into a single number, so every `Literal` in a language pack can carry
+//! its word's morphological features without making the pack noticeably bigger, and a way to
+//! describe them to the user ("3rd person plural, imparfait").
+
+use std::collections::BTreeMap;
+
+use crate::Language;
+use crate::features::{Case, Gender, Mood, Morphology, Number, Person, Polite, Tense};
+
+/// A `Morphology`, packed. Each feature gets a few bits holding 0 if it isn't set, or one more
+/// than the value's position in its list below. Use `Morphology::from` to unpack it.
+#[derive(
+    Copy,
+    Clone,
+    Debug,
+    serde::Serialize,
+    serde::Deserialize,
+    Hash,
+    Eq,
+    PartialEq,
+    Ord,
+    PartialOrd,
+    tsify::Tsify,
+    rkyv::Archive,
+    rkyv::Serialize,
+    rkyv::Deserialize,
+)]
+#[rkyv(compare(PartialEq), derive(PartialEq, PartialOrd, Eq, Ord, Hash))]
+#[serde(transparent)]
+#[tsify(into_wasm_abi, from_wasm_abi)]
+pub struct MorphTag(u32);
+
+struct Field {
+    shift: u32,
+    bits: u32,
+}
+
+const GENDER: Field = Field { shift: 0, bits: 3 };
+const NUMBER: Field = Field { shift: 3, bits: 4 };
+const PERSON: Field = Field { shift: 7, bits: 3 };
+const TENSE: Field = Field { shift: 10, bits: 3 };
+const MOOD: Field = Field { shift: 13, bits: 4 };
+const CASE: Field = Field { shift: 17, bits: 6 };
+const POLITENESS: Field = Field { shift: 23, bits: 3 };
+
+const GENDERS: &[Gender] = &[
+    Gender::Masculine,
+    Gender::Feminine,
+    Gender::Neuter,
+    Gender::Common,
+];
+const NUMBERS: &[Number] = &[
+    Number::Singular,
+    Number::Plural,
+    Number::Dual,
+    Number::Trial,
+    Number::Paucal,
+    Number::GreaterPaucal,
+    Number::GreaterPlural,
+    Number::Inverse,
+    Number::Count,
+    Number::PluraleTantum,
+    Number::Collective,
+];
+const PERSONS: &[Person] = &[
+    Person::Zeroth,
+    Person::First,
+    Person::Second,
+    Person::Third,
+    Person::Fourth,
+];
+const TENSES: &[Tense] = &[
+    Tense::Past,
+    Tense::Present,
+    Tense::Future,
+    Tense::Imperfect,
+    Tense::Pluperfect,
+];
+const MOODS: &[Mood] = &[
+    Mood::Indicative,
+    Mood::Imperative,
+    Mood::Conditional,
+    Mood::Potential,
+    Mood::Subjunctive,
+    Mood::Jussive,
+    Mood::Purposive,
+    Mood::Quotative,
+    Mood::Optative,
+    Mood::Desiderative,
+    Mood::Necessitative,
+    Mood::Interrogative,
+    Mood::Irrealis,
+    Mood::Admirative,
+];
+const CASES: &[Case] = &[
+    Case::Nominative,
+    Case::Accusative,
+    Case::Absolutive,
+    Case::Ergative,
+    Case::Dative,
+    Case::Genitive,
+    Case::Vocative,
+    Case::Instrumental,
+    Case::Partitive,
+    Case::Distributive,
+    Case::Essive,
+    Case::Translative,
+    Case::Comitative,
+    Case::Abessive,
+    Case::Causative,
+    Case::Benefactive,
+    Case::Considerative,
+    Case::Comparative,
+    Case::Equative,
+    Case::Locative,
+    Case::Lative,
+    Case::Terminative,
+    Case::Inessive,
+    Case::Illative,
+    Case::Elative,
+    Case::Additive,
+    Case::Adessive,
+    Case::Allative,
+    Case::Ablative,
+    Case::Superessive,
+    Case::Superlative,
+    Case::Delative,
+    Case::Subessive,
+    Case::Sublative,
+    Case::Subelative,
+    Case::Perlative,
+    Case::Temporal,
+];
+const POLITENESSES: &[Polite] = &[Polite::Informal, Polite::Formal, Polite::Elev, Polite::Humb];
+
+impl Field {
+    fn pack<T: PartialEq>(&self, values: &[T], value: Option<&T>) -> u32 {
+        let code = value
+            .and_then(|value| values.iter().position(|v| v == value))
+            .map_or(0, |position| position as u32 + 1);
+        code << self.shift
+    }
+
+    fn unpack<T: Copy>(&self, values: &[T], tag: MorphTag) -> Option<T> {
+        let code = (tag.0 >> self.shift) & ((1 << self.bits) - 1);
+        values.get((code as usize).checked_sub(1)?).copied()
+    }
+}
+
+impl From<&Morphology> for MorphTag {
+    fn from(morphology: &Morphology) -> Self {
+        MorphTag(
+            GENDER.pack(GENDERS, morphology.gender.as_ref())
+                | NUMBER.pack(NUMBERS, morphology.number.as_ref())
+                | PERSON.pack(PERSONS, morphology.person.as_ref())
+                | TENSE.pack(TENSES, morphology.tense.as_ref())
+                | MOOD.pack(MOODS, morphology.mood.as_ref())
+                | CASE.pack(CASES, morphology.case.as_ref())
+                | POLITENESS.pack(POLITENESSES, morphology.politeness.as_ref()),
+        )
+    }
+}
+
+impl From<MorphTag> for Morphology {
+    fn from(tag: MorphTag) -> Self {
+        Morphology {
+            gender: GENDER.unpack(GENDERS, tag),
+            number: NUMBER.unpack(NUMBERS, tag),
+            politeness: POLITENESS.unpack(POLITENESSES, tag),
+            tense: TENSE.unpack(TENSES, tag),
+            person: PERSON.unpack(PERSONS, tag),
+            case: CASE.unpack(CASES, tag),
+            mood: MOOD.unpack(MOODS, tag),
+        }
+    }
+}
+
+impl MorphTag {
+    /// The features we keep from a tokenizer's Universal Dependencies features (like
+    /// `{"Number": "Plur", "Person": "3"}`), or `None` if there are none of them
+    pub fn from_ud_features(features: &BTreeMap<String, String>) -> Option<MorphTag> {
+        let feature = |name: &str| features.get(name).map(String::as_str);
+        let morphology = Morphology {
+            gender: feature("Gender").and_then(|value| match value {
+                "Masc" => Some(Gender::Masculine),
+                "Fem" => Some(Gender::Feminine),
+                "Neut" => Some(Gender::Neuter),
+                "Com" => Some(Gender::Common),
+                _ => None,
+            }),
+            number: feature("Number").and_then(|value| match value {
+                "Sing" => Some(Number::Singular),
+                "Plur" => Some(Number::Plural),
+                "Dual" => Some(Number::Dual),
+                "Tri" => Some(Number::Trial),
+                "Pauc" => Some(Number::Paucal),
+                "Grpa" => Some(Number::GreaterPaucal),
+                "Grpl" => Some(Number::GreaterPlural),
+                "Inv" => Some(Number::Inverse),
+                "Count" => Some(Number::Count),
+                "Ptan" => Some(Number::PluraleTantum),
+                "Coll" => Some(Number::Collective),
+                _ => None,
+            }),
+            politeness: feature("Polite").and_then(|value| match value {
+                "Infm" => Some(Polite::Informal),
+                "Form" => Some(Polite::Formal),
+                "Elev" => Some(Polite::Elev),
+                "Humb" => Some(Polite::Humb),
+                _ => None,
+            }),
+            tense: feature("Tense").and_then(|value| match value {
+                "Past" => Some(Tense::Past),
+                "Pres" => Some(Tense::Present),
+                "Fut" => Some(Tense::Future),
+                "Imp" => Some(Tense::Imperfect),
+                "Pqp" => Some(Tense::Pluperfect),
+                _ => None,
+            }),
+            person: feature("Person").and_then(|value| match value {
+                "0" => Some(Person::Zeroth),
+                "1" => Some(Person::First),
+                "2" => Some(Person::Second),
+                "3" => Some(Person::Third),
+                "4" => Some(Person::Fourth),
+                _ => None,
+            }),
+            // Only the cases the courses' languages have
+            case: feature("Case").and_then(|value| match value {
+                "Nom" => Some(Case::Nominative),
+                "Acc" => Some(Case::Accusative),
+                "Dat" => Some(Case::Dative),
+                "Gen" => Some(Case::Genitive),
+                "Voc" => Some(Case::Vocative),
+                "Ins" => Some(Case::Instrumental),
+                "Loc" => Some(Case::Locative),
+                "Abl" => Some(Case::Ablative),
+                "Com" => Some(Case::Comitative),
+                _ => None,
+            }),
+            mood: feature("Mood").and_then(|value| match value {
+                "Ind" => Some(Mood::Indicative),
+                "Imp" => Some(Mood::Imperative),
+                "Cnd" => Some(Mood::Conditional),
+                "Sub" => Some(Mood::Subjunctive),
+                "Pot" => Some(Mood::Potential),
+                "Opt" => Some(Mood::Optative),
+                "Int" => Some(Mood::Interrogative),
+                _ => None,
+            }),
+        };
+        let tag = MorphTag::from(&morphology);
+        (tag.0 != 0).then_some(tag)
+    }
+
+    /// See `Morphology::describe`
+    pub fn describe(self, language: Language) -> Option<String> {
+        Morphology::from(self).describe(language)
+    }
+}
+
+impl Morphology {
+    /// The features, for showing to the user, like "3rd person plural, imparfait". Tenses and
+    /// moods go by the names learners of `language` will see in their grammar books, everything
+    /// else is in English. The indicative mood goes without saying. `None` if there's nothing to
+    /// describe.
+    pub fn describe(&self, language: Language) -> Option<String> {
+        let person = self.person.map(|person| match person {
+            Person::Zeroth => "impersonal",
+            Person::First => "1st person",
+            Person::Second => "2nd person",
+            Person::Third => "3rd person",
+            Person::Fourth => "4th person",
+        });
+        let number = self.number.map(|number| match number {
+            Number::Singular => "singular",
+            Number::Plural => "plural",
+            Number::Dual => "dual",
+            Number::Trial => "trial",
+            Number::Paucal => "paucal",
+            Number::GreaterPaucal => "greater paucal",
+            Number::GreaterPlural => "greater plural",
+            Number::Inverse => "inverse",
+            Number::Count => "count",
+            Number::PluraleTantum => "plurale tantum",
+            Number::Collective => "collective",
+        });
+        let person_and_number = match (person, number) {
+            (Some(person), Some(number)) => Some(format!("{person} {number}")),
+            (person, number) => person.or(number).map(str::to_string),
+        };
+        let gender = self.gender.map(|gender| match gender {
+            Gender::Masculine => "masculine",
+            Gender::Feminine => "feminine",
+            Gender::Neuter => "neuter",
+            Gender::Common => "common gender",
+        });
+        let case = self
+            .case
+            .map(|case| format!("{case:?}").to_lowercase())
+            .map(|case| format!("{case} case"));
+        let politeness = self.politeness.map(|politeness| match politeness {
+            Polite::Informal => "informal",
+            Polite::Formal => "formal",
+            Polite::Elev => "elevated",
+            Polite::Humb => "humble",
+        });
+
+        let parts: Vec<String> = [
+            person_and_number,
+            gender.map(str::to_string),
+            case,
+            self.tense
+                .map(|tense| tense_name(tense, language).to_string()),
+            self.mood
+                .and_then(|mood| mood_name(mood, language))
+                .map(str::to_string),
+            politeness.map(str::to_string),
+        ]
+        .into_iter()
+        .flatten()
+        .collect();
+        (!parts.is_empty()).then(|| parts.join(", "))
+    }
+}
+
+fn tense_name(tense: Tense, language: Language) -> &'static str {
+    match (language, tense) {
+        (Language::French, Tense::Present) => "présent",
+        (Language::French, Tense::Past) => "passé simple",
+        (Language::French, Tense::Imperfect) => "imparfait",
+        (Language::French, Tense::Future) => "futur",
+        (Language::French, Tense::Pluperfect) => "plus-que-parfait",
+        (Language::Spanish, Tense::Present) => "presente",
+        (Language::Spanish, Tense::Past) => "pretérito",
+        (Language::Spanish, Tense::Imperfect) => "imperfecto",
+        (Language::Spanish, Tense::Future) => "futuro",
+        (Language::Spanish, Tense::Pluperfect) => "pluscuamperfecto",
+        (Language::Portuguese, Tense::Present) => "presente",
+        (Language::Portuguese, Tense::Past) => "pretérito perfeito",
+        (Language::Portuguese, Tense::Imperfect) => "pretérito imperfeito",
+        (Language::Portuguese, Tense::Future) => "futuro",
+        (Language::Portuguese, Tense::Pluperfect) => "mais-que-perfeito",
+        (Language::Italian, Tense::Present) => "presente",
+        (Language::Italian, Tense::Past) => "passato remoto",
+        (Language::Italian, Tense::Imperfect) => "imperfetto",
+        (Language::Italian, Tense::Future) => "futuro",
+        (Language::Italian, Tense::Pluperfect) => "trapassato",
+        (Language::German, Tense::Present) => "Präsens",
+        (Language::German, Tense::Past | Tense::Imperfect) => "Präteritum",
+        (Language::German, Tense::Future) => "Futur",
+        (Language::German, Tense::Pluperfect) => "Plusquamperfekt",
+        (_, Tense::Present) => "present",
+        (_, Tense::Past) => "past",
+        (_, Tense::Imperfect) => "imperfect",
+        (_, Tense::Future) => "future",
+        (_, Tense::Pluperfect) => "pluperfect",
+    }
+}
+
+fn mood_name(mood: Mood, language: Language) -> Option<&'static str> {
+    Some(match (language, mood) {
+        (_, Mood::Indicative) => return None,
+        (Language::French, Mood::Subjunctive) => "subjonctif",
+        (Language::French, Mood::Conditional) => "conditionnel",
+        (Language::French, Mood::Imperative) => "impératif",
+        (Language::Spanish | Language::Portuguese, Mood::Subjunctive) => "subjuntivo",
+        (Language::Spanish | Language::Portuguese, Mood::Conditional) => "condicional",
+        (Language::Spanish | Language::Portuguese, Mood::Imperative) => "imperativo",
+        (Language::Italian, Mood::Subjunctive) => "congiuntivo",
+        (Language::Italian, Mood::Conditional) => "condizionale",
+        (Language::Italian, Mood::Imperative) => "imperativo",
+        (Language::German, Mood::Subjunctive) => "Konjunktiv",
+        (Language::German, Mood::Conditional) => "Konjunktiv II",
+        (Language::German, Mood::Imperative) => "Imperativ",
+        (_, Mood::Imperative) => "imperative",
+        (_, Mood::Conditional) => "conditional",
+        (_, Mood::Potential) => "potential",
+        (_, Mood::Subjunctive) => "subjunctive",
+        (_, Mood::Jussive) => "jussive",
+        (_, Mood::Purposive) => "purposive",
+        (_, Mood::Quotative) => "quotative",
+        (_, Mood::Optative) => "optative",
+        (_, Mood::Desiderative) => "desiderative",
+        (_, Mood::Necessitative) => "necessitative",
+        (_, Mood::Interrogative) => "interrogative",
+        (_, Mood::Irrealis) => "irrealis",
+        (_, Mood::Admirative) => "admirative",
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_every_value_fits_in_its_field() {
+        for (field, values) in [
+            (GENDER, GENDERS.len()),
+            (NUMBER, NUMBERS.len()),
+            (PERSON, PERSONS.len()),
+            (TENSE, TENSES.len()),
+            (MOOD, MOODS.len()),
+            (CASE, CASES.len()),
+            (POLITENESS, POLITENESSES.len()),
+        ] {
+            assert!(values < 1 << field.bits);
+        }
+        assert!(POLITENESS.shift + POLITENESS.bits <= 32);
+
+        let morphology = Morphology {
+            gender: Some(Gender::Common),
+            number: Some(Number::Collective),
+            politeness: Some(Polite::Humb),
+            tense: Some(Tense::Pluperfect),
+            person: Some(Person::Fourth),
+            case: Some(Case::Temporal),
+            mood: Some(Mood::Admirative),
+        };
+        assert_eq!(Morphology::from(MorphTag::from(&morphology)), morphology);
+        assert_eq!(
+            Morphology::from(MorphTag::from(&Morphology::default())),
+            Morphology::default()
+        );
+    }
+
+    #[test]
+    fn test_ud_features_are_described() {
+        let features = |features: &[(&str, &str)]| {
+            features
+                .iter()
+                .map(|(name, value)| (name.to_string(), value.to_string()))
+                .collect::<BTreeMap<_, _>>()
+        };
+
+        let tag = MorphTag::from_ud_features(&features(&[
+            ("Mood", "Ind"),
+            ("Number", "Plur"),
+            ("Person", "3"),
+            ("Tense", "Imp"),
+            ("VerbForm", "Fin"),
+        ]))
+        .unwrap();
+        assert_eq!(
+            tag.describe(Language::French).as_deref(),
+            Some("3rd person plural, imparfait")
+        );
+        assert_eq!(
+            tag.describe(Language::English).as_deref(),
+            Some("3rd person plural, imperfect")
+        );
+
+        let tag = MorphTag::from_ud_features(&features(&[
+            ("Mood", "Sub"),
+            ("Number", "Sing"),
+            ("Person", "1"),
+            ("Tense", "Pres"),
+        ]))
+        .unwrap();
+        assert_eq!(
+            tag.describe(Language::Spanish).as_deref(),
+            Some("1st person singular, presente, subjuntivo")
+        );
+
+        assert_eq!(
+            MorphTag::from_ud_features(&features(&[("VerbForm", "Inf")])),
+            None
+        );
+    }
+}
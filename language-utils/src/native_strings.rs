@@ -0,0 +1,78 @@
+//! Native-language strings shared between language packs. French and Spanish packs for English
+//! speakers have a lot of the same English translations, so instead of each pack interning them
+//! into its own rodeo, they're interned here and every pack that has a string points at the same
+//! allocation.
+
+use std::sync::{Arc, LazyLock, Mutex};
+
+use rkyv::Place;
+use rkyv::rancor::{Fallible, Source};
+use rkyv::ser::Writer;
+use rkyv::string::{ArchivedString, StringResolver};
+use rkyv::with::{ArchiveWith, DeserializeWith, SerializeWith};
+use rustc_hash::FxHashSet;
+
+static NATIVE_STRINGS: LazyLock<Mutex<FxHashSet<Arc<str>>>> = LazyLock::new(Default::default);
+
+/// The shared copy of `string`, adding it if no loaded pack has it yet
+pub fn intern(string: &str) -> Arc<str> {
+    let mut strings = NATIVE_STRINGS.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some(shared) = strings.get(string) {
+        return shared.clone();
+    }
+    let shared: Arc<str> = Arc::from(string);
+    strings.insert(shared.clone());
+    shared
+}
+
+/// Forgets the strings no pack uses anymore. Called when a pack is dropped.
+pub fn release_unused() {
+    let mut strings = NATIVE_STRINGS.lock().unwrap_or_else(|e| e.into_inner());
+    strings.retain(|string| Arc::strong_count(string) > 1);
+}
+
+/// Archives an `Arc<str>` as a plain string, which goes through `intern` when the pack is loaded.
+/// Use it with `#[rkyv(with = ...)]`.
+pub struct Shared;
+
+impl ArchiveWith<Arc<str>> for Shared {
+    type Archived = ArchivedString;
+    type Resolver = StringResolver;
+
+    fn resolve_with(field: &Arc<str>, resolver: Self::Resolver, out: Place<Self::Archived>) {
+        ArchivedString::resolve_from_str(field, resolver, out);
+    }
+}
+
+impl<S> SerializeWith<Arc<str>, S> for Shared
+where
+    S: Fallible + Writer + ?Sized,
+    S::Error: Source,
+{
+    fn serialize_with(field: &Arc<str>, serializer: &mut S) -> Result<Self::Resolver, S::Error> {
+        ArchivedString::serialize_from_str(field, serializer)
+    }
+}
+
+impl<D: Fallible + ?Sized> DeserializeWith<ArchivedString, Arc<str>, D> for Shared {
+    fn deserialize_with(field: &ArchivedString, _: &mut D) -> Result<Arc<str>, D::Error> {
+        Ok(intern(field.as_str()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_packs_share_strings() {
+        let first = intern("I don't believe it");
+        let second = intern("I don't believe it");
+        assert!(Arc::ptr_eq(&first, &second));
+
+        drop((first, second));
+        release_unused();
+        let third = intern("I don't believe it");
+        assert_eq!(Arc::strong_count(&third), 2);
+    }
+}
@@ -0,0 +1,137 @@
+//! Per-segment hashes of a language pack, so a download that was truncated or corrupted on the way
+//! can be detected and repaired without fetching the whole pack again.
+
+use std::ops::Range;
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::Course;
+
+/// Packs are hashed in segments of this many bytes (the last one may be shorter)
+pub const PACK_SEGMENT_BYTES: usize = 4 * 1024 * 1024;
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, tsify::Tsify)]
+#[tsify(into_wasm_abi, from_wasm_abi)]
+pub struct PackManifest {
+    pub total_bytes: usize,
+    pub segment_bytes: usize,
+    /// The hex SHA-256 of each segment, in order
+    pub segment_sha256: Vec<String>,
+}
+
+/// Asks for one segment of a course's pack, as described by its `PackManifest`
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, tsify::Tsify)]
+#[tsify(into_wasm_abi, from_wasm_abi)]
+pub struct PackSegmentRequest {
+    pub course: Course,
+    pub segment: usize,
+}
+
+/// Which build of a course's pack to serve. A new build is published to `Beta` first, so it can
+/// be tried on some users before it replaces the `Stable` one.
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    Default,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+    Serialize,
+    Deserialize,
+    tsify::Tsify,
+)]
+#[tsify(into_wasm_abi, from_wasm_abi)]
+#[serde(rename_all = "lowercase")]
+pub enum PackChannel {
+    #[default]
+    Stable,
+    Beta,
+}
+
+/// The query parameters of the `/language-data` routes. Without any, the current stable pack is
+/// served, which is what clients from before channels existed get.
+#[derive(Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct PackQuery {
+    #[serde(default)]
+    pub channel: PackChannel,
+    /// Asks for the pack with this hash rather than the channel's current one, for users pinned
+    /// to a build. `channel` is ignored when this is set.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+}
+
+impl PackQuery {
+    /// The query string to append to a `/language-data` path, including the `?`
+    pub fn to_query_string(&self) -> String {
+        match (&self.version, self.channel) {
+            (Some(version), _) => format!("?version={version}"),
+            (None, PackChannel::Stable) => String::new(),
+            (None, PackChannel::Beta) => "?channel=beta".to_string(),
+        }
+    }
+}
+
+pub(crate) fn sha256_hex(bytes: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(bytes))
+}
+
+impl PackManifest {
+    pub fn for_pack(bytes: &[u8]) -> Self {
+        Self {
+            total_bytes: bytes.len(),
+            segment_bytes: PACK_SEGMENT_BYTES,
+            segment_sha256: bytes.chunks(PACK_SEGMENT_BYTES).map(sha256_hex).collect(),
+        }
+    }
+
+    /// The byte range of `segment` within the pack
+    pub fn segment_range(&self, segment: usize) -> Range<usize> {
+        let start = (segment * self.segment_bytes).min(self.total_bytes);
+        start..(start + self.segment_bytes).min(self.total_bytes)
+    }
+
+    /// Whether `bytes` is the contents of `segment`
+    pub fn segment_matches(&self, segment: usize, bytes: &[u8]) -> bool {
+        self.segment_sha256
+            .get(segment)
+            .is_some_and(|expected| *expected == sha256_hex(bytes))
+    }
+
+    /// The segments of `pack` that don't match the manifest. Segments missing because the pack
+    /// was truncated count as corrupted.
+    pub fn corrupted_segments(&self, pack: &[u8]) -> Vec<usize> {
+        (0..self.segment_sha256.len())
+            .filter(|&segment| {
+                let range = self.segment_range(segment);
+                pack.get(range)
+                    .is_none_or(|bytes| !self.segment_matches(segment, bytes))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detects_truncated_and_corrupted_segments() {
+        let pack: Vec<u8> = (0..PACK_SEGMENT_BYTES * 2 + 10)
+            .map(|i| (i % 251) as u8)
+            .collect();
+        let manifest = PackManifest::for_pack(&pack);
+        assert_eq!(manifest.segment_sha256.len(), 3);
+        assert!(manifest.corrupted_segments(&pack).is_empty());
+
+        let mut corrupted = pack.clone();
+        corrupted[PACK_SEGMENT_BYTES + 1] ^= 1;
+        assert_eq!(manifest.corrupted_segments(&corrupted), vec![1]);
+
+        let truncated = &pack[..PACK_SEGMENT_BYTES + 5];
+        assert_eq!(manifest.corrupted_segments(truncated), vec![1, 2]);
+    }
+}
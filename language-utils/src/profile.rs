@@ -1,4 +1,4 @@
-use crate::Language;
+use crate::{Language, Lexeme};
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Serialize, Deserialize, Clone, schemars::JsonSchema, tsify::Tsify)]
@@ -67,6 +67,9 @@ pub struct UpdateLanguageStatsRequest {
     pub xp: f64,
     pub percent_known: f64,
     pub start_time: Option<String>,
+    /// Not shown on the profile, only used for `webhook::WebhookEvent::CardsDue`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub due_count: Option<i64>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, schemars::JsonSchema, tsify::Tsify)]
@@ -94,3 +97,124 @@ pub struct FollowStatus {
     pub follower_count: i64,
     pub following_count: i64,
 }
+
+/// What a share token lets whoever has it read, e.g. a tutor following the user's progress. Each
+/// scope can read everything the ones before it can.
+#[derive(
+    Debug,
+    Serialize,
+    Deserialize,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    schemars::JsonSchema,
+    tsify::Tsify,
+)]
+#[tsify(into_wasm_abi, from_wasm_abi)]
+#[serde(rename_all = "snake_case")]
+pub enum ShareScope {
+    /// The user's language stats, like their profile shows
+    Stats,
+    /// The words in the user's deck and how well they know them (see `SharedDeck`), without the
+    /// reviews that led there
+    Deck,
+    /// Every event in the user's reviews stream
+    FullHistory,
+}
+
+impl ShareScope {
+    pub fn allows(self, needed: ShareScope) -> bool {
+        self >= needed
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, schemars::JsonSchema, tsify::Tsify)]
+#[tsify(into_wasm_abi, from_wasm_abi)]
+pub struct CreateShareTokenRequest {
+    pub scope: ShareScope,
+    /// Who the token is for, so the user can tell their tokens apart
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, schemars::JsonSchema, tsify::Tsify)]
+#[tsify(into_wasm_abi, from_wasm_abi)]
+pub struct ShareToken {
+    pub token: String,
+    pub scope: ShareScope,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+    pub created_at: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, schemars::JsonSchema, tsify::Tsify)]
+#[tsify(into_wasm_abi, from_wasm_abi)]
+pub struct RevokeShareTokenRequest {
+    pub token: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, schemars::JsonSchema, tsify::Tsify)]
+#[tsify(into_wasm_abi, from_wasm_abi)]
+pub struct RevokeShareTokenResponse {
+    pub success: bool,
+}
+
+/// The share token goes in the `Authorization` header rather than here, so it doesn't end up in
+/// URLs and the logs that record them
+#[derive(Debug, Serialize, Deserialize, Clone, schemars::JsonSchema, tsify::Tsify)]
+#[tsify(into_wasm_abi, from_wasm_abi)]
+pub struct SharedEventsQuery {
+    /// `SharedEvents::next_after_id` from the previous page, `None` for the first one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub after_id: Option<i64>,
+}
+
+/// An event from the user's reviews stream, as it was synced
+#[derive(Debug, Serialize, Deserialize, Clone, schemars::JsonSchema, tsify::Tsify)]
+#[tsify(into_wasm_abi, from_wasm_abi)]
+pub struct SharedEvent {
+    pub id: i64,
+    pub device_id: String,
+    pub within_device_events_index: i64,
+    pub event: serde_json::Value,
+    pub created_at: String,
+}
+
+/// What `ShareScope::Deck` tokens can browse. The app uploads it, since the deck itself is only
+/// ever uploaded encrypted. It has no review times, scheduling or mistakes.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, schemars::JsonSchema, tsify::Tsify)]
+#[tsify(into_wasm_abi, from_wasm_abi)]
+pub struct SharedDeck {
+    pub language: Language,
+    /// Most frequent first
+    pub words: Vec<SharedWord>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, schemars::JsonSchema, tsify::Tsify)]
+#[tsify(into_wasm_abi, from_wasm_abi)]
+pub struct SharedWord {
+    pub lexeme: Lexeme<String>,
+    /// "new", "learning", "review" or "relearning"
+    pub state: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub frequency_rank: Option<u32>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, schemars::JsonSchema, tsify::Tsify)]
+#[tsify(into_wasm_abi, from_wasm_abi)]
+pub struct UpdateSharedDeckResponse {
+    pub success: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, schemars::JsonSchema, tsify::Tsify)]
+#[tsify(into_wasm_abi, from_wasm_abi)]
+pub struct SharedEvents {
+    /// Oldest first
+    pub events: Vec<SharedEvent>,
+    /// Pass as `SharedEventsQuery::after_id` for the next page. `None` once there are no more.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub next_after_id: Option<i64>,
+}
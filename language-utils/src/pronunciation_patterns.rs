@@ -0,0 +1,192 @@
+//! Matching words against the letter/sound patterns in `PronunciationData`. The data generator
+//! uses this to count how common each pattern is, and the frontend to find which patterns a word
+//! contains.
+
+use unicode_normalization::UnicodeNormalization;
+
+use crate::PatternPosition;
+
+/// Whether any of `patterns` is written in Hangul jamo, in which case words need to be decomposed
+/// into jamo before they can be matched
+pub fn uses_jamo<'a>(patterns: impl IntoIterator<Item = &'a str>) -> bool {
+    patterns.into_iter().any(|pattern| {
+        pattern.chars().any(|c| {
+            ('\u{1100}'..='\u{11FF}').contains(&c) || // Hangul Jamo
+            ('\u{3130}'..='\u{318F}').contains(&c) || // Hangul Compatibility Jamo
+            ('\u{A960}'..='\u{A97F}').contains(&c) || // Hangul Jamo Extended-A
+            ('\u{D7B0}'..='\u{D7FF}').contains(&c) || // Hangul Jamo Extended-B
+            ('\u{3131}'..='\u{314E}').contains(&c) || // Hangul compatibility consonants
+            ('\u{314F}'..='\u{3163}').contains(&c) // Hangul compatibility vowels
+        })
+    })
+}
+
+pub fn normalize_word(word: &str, jamo: bool) -> String {
+    if jamo {
+        // NFKD (compatibility decomposition) splits Hangul syllables into jamo
+        word.nfkd().collect::<String>()
+    } else {
+        word.to_lowercase()
+    }
+}
+
+pub fn normalize_pattern(pattern: &str, position: PatternPosition, jamo: bool) -> String {
+    if !jamo {
+        return pattern.to_lowercase();
+    }
+
+    // Korean compatibility jamo need position-based conversion
+    // since they map to different combining jamo based on syllable position
+    let mut normalized = String::new();
+    for ch in pattern.chars() {
+        // Check if this is a compatibility jamo (U+3131..U+318E)
+        if ('\u{3131}'..='\u{318E}').contains(&ch) {
+            // Convert based on position in syllable
+            if position == PatternPosition::End {
+                // Convert to final jamo for End position
+                let final_jamo = match ch {
+                    'ㄱ' => 'ᆨ',
+                    'ㄲ' => 'ᆩ',
+                    'ㄳ' => 'ᆪ',
+                    'ㄴ' => 'ᆫ',
+                    'ㄵ' => 'ᆬ',
+                    'ㄶ' => 'ᆭ',
+                    'ㄷ' => 'ᆮ',
+                    'ㄹ' => 'ᆯ',
+                    'ㄺ' => 'ᆰ',
+                    'ㄻ' => 'ᆱ',
+                    'ㄼ' => 'ᆲ',
+                    'ㄽ' => 'ᆳ',
+                    'ㄾ' => 'ᆴ',
+                    'ㄿ' => 'ᆵ',
+                    'ㅀ' => 'ᆶ',
+                    'ㅁ' => 'ᆷ',
+                    'ㅂ' => 'ᆸ',
+                    'ㅄ' => 'ᆹ',
+                    'ㅅ' => 'ᆺ',
+                    'ㅆ' => 'ᆻ',
+                    'ㅇ' => 'ᆼ',
+                    'ㅈ' => 'ᆽ',
+                    'ㅊ' => 'ᆾ',
+                    'ㅋ' => 'ᆿ',
+                    'ㅌ' => 'ᇀ',
+                    'ㅍ' => 'ᇁ',
+                    'ㅎ' => 'ᇂ',
+                    // Vowels (medial jamo) - same in all positions
+                    'ㅏ' => 'ᅡ',
+                    'ㅐ' => 'ᅢ',
+                    'ㅑ' => 'ᅣ',
+                    'ㅒ' => 'ᅤ',
+                    'ㅓ' => 'ᅥ',
+                    'ㅔ' => 'ᅦ',
+                    'ㅕ' => 'ᅧ',
+                    'ㅖ' => 'ᅨ',
+                    'ㅗ' => 'ᅩ',
+                    'ㅘ' => 'ᅪ',
+                    'ㅙ' => 'ᅫ',
+                    'ㅚ' => 'ᅬ',
+                    'ㅛ' => 'ᅭ',
+                    'ㅜ' => 'ᅮ',
+                    'ㅝ' => 'ᅯ',
+                    'ㅞ' => 'ᅰ',
+                    'ㅟ' => 'ᅱ',
+                    'ㅠ' => 'ᅲ',
+                    'ㅡ' => 'ᅳ',
+                    'ㅢ' => 'ᅴ',
+                    'ㅣ' => 'ᅵ',
+                    _ => ch,
+                };
+                normalized.push(final_jamo);
+            } else {
+                // Convert to initial jamo for Beginning/Anywhere
+                let initial_jamo = match ch {
+                    'ㄱ' => 'ᄀ',
+                    'ㄲ' => 'ᄁ',
+                    'ㄴ' => 'ᄂ',
+                    'ㄷ' => 'ᄃ',
+                    'ㄸ' => 'ᄄ',
+                    'ㄹ' => 'ᄅ',
+                    'ㅁ' => 'ᄆ',
+                    'ㅂ' => 'ᄇ',
+                    'ㅃ' => 'ᄈ',
+                    'ㅅ' => 'ᄉ',
+                    'ㅆ' => 'ᄊ',
+                    'ㅇ' => 'ᄋ',
+                    'ㅈ' => 'ᄌ',
+                    'ㅉ' => 'ᄍ',
+                    'ㅊ' => 'ᄎ',
+                    'ㅋ' => 'ᄏ',
+                    'ㅌ' => 'ᄐ',
+                    'ㅍ' => 'ᄑ',
+                    'ㅎ' => 'ᄒ',
+                    // Vowels (medial jamo) - same in all positions
+                    'ㅏ' => 'ᅡ',
+                    'ㅐ' => 'ᅢ',
+                    'ㅑ' => 'ᅣ',
+                    'ㅒ' => 'ᅤ',
+                    'ㅓ' => 'ᅥ',
+                    'ㅔ' => 'ᅦ',
+                    'ㅕ' => 'ᅧ',
+                    'ㅖ' => 'ᅨ',
+                    'ㅗ' => 'ᅩ',
+                    'ㅘ' => 'ᅪ',
+                    'ㅙ' => 'ᅫ',
+                    'ㅚ' => 'ᅬ',
+                    'ㅛ' => 'ᅭ',
+                    'ㅜ' => 'ᅮ',
+                    'ㅝ' => 'ᅯ',
+                    'ㅞ' => 'ᅰ',
+                    'ㅟ' => 'ᅱ',
+                    'ㅠ' => 'ᅲ',
+                    'ㅡ' => 'ᅳ',
+                    'ㅢ' => 'ᅴ',
+                    'ㅣ' => 'ᅵ',
+                    _ => ch,
+                };
+                normalized.push(initial_jamo);
+            }
+        } else {
+            // Not a compatibility jamo, keep as-is
+            normalized.push(ch);
+        }
+    }
+    normalized
+}
+
+/// Whether a word contains a pattern, both normalized with the functions above
+pub fn contains_pattern(word: &str, pattern: &str, position: PatternPosition) -> bool {
+    match position {
+        PatternPosition::Beginning => word.starts_with(pattern),
+        PatternPosition::End => word.ends_with(pattern),
+        PatternPosition::Anywhere => word.contains(pattern),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_matches_patterns_by_position() {
+        let word = normalize_word("Château", false);
+        assert!(contains_pattern(
+            &word,
+            &normalize_pattern("CH", PatternPosition::Beginning, false),
+            PatternPosition::Beginning
+        ));
+        assert!(contains_pattern(&word, "eau", PatternPosition::End));
+        assert!(!contains_pattern(&word, "eau", PatternPosition::Beginning));
+
+        let word = normalize_word("한국", true);
+        assert!(contains_pattern(
+            &word,
+            &normalize_pattern("ㄱ", PatternPosition::End, true),
+            PatternPosition::End
+        ));
+        assert!(contains_pattern(
+            &word,
+            &normalize_pattern("ㅎ", PatternPosition::Beginning, true),
+            PatternPosition::Beginning
+        ));
+    }
+}
@@ -0,0 +1,51 @@
+//! A public page of a user's progress. A user who opts in with `POST /public-stats/settings` gets
+//! a share id, and anyone with it can get their stats from `GET /public-stats` without logging
+//! in. The stats are a copy the backend refreshes whenever the app uploads its stats during sync
+//! (see `profile::UpdateLanguageStatsRequest`), and leave out anything that identifies the user.
+
+use serde::{Deserialize, Serialize};
+
+use crate::Language;
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, tsify::Tsify)]
+#[tsify(into_wasm_abi, from_wasm_abi)]
+pub struct SetPublicStatsRequest {
+    pub enabled: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, tsify::Tsify)]
+#[tsify(into_wasm_abi, from_wasm_abi)]
+pub struct PublicStatsSettings {
+    pub enabled: bool,
+    /// What goes in the share link, for `GetPublicStatsQuery`. Turning the page off and on again
+    /// gives it a new one, so old links stop working.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub share_id: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, tsify::Tsify)]
+#[tsify(into_wasm_abi, from_wasm_abi)]
+pub struct GetPublicStatsQuery {
+    pub share_id: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, tsify::Tsify)]
+#[tsify(into_wasm_abi, from_wasm_abi)]
+pub struct PublicStats {
+    /// Most XP first
+    pub languages: Vec<PublicLanguageStats>,
+    /// RFC 3339, when the stats were last refreshed
+    pub updated_at: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, tsify::Tsify)]
+#[tsify(into_wasm_abi, from_wasm_abi)]
+pub struct PublicLanguageStats {
+    pub language: Language,
+    pub total_reviews: i64,
+    pub daily_streak: i64,
+    pub xp: f64,
+    pub percent_known: f64,
+    /// RFC 3339, only the date is meant to be shown
+    pub started: String,
+}
@@ -0,0 +1,32 @@
+//! Population-level data on how often cards are remembered after each length of interval, for
+//! tuning the scheduler's defaults. Users who opt in have `yap_frontend_rs` upload a coarse
+//! histogram of their due reviews with `POST /telemetry/scheduler`. The counts are noised on the
+//! device (see its `scheduler_telemetry` module), so the backend never sees anyone's real history.
+
+use serde::{Deserialize, Serialize};
+
+/// Histograms with more buckets than this are rejected by the backend
+pub const MAX_TELEMETRY_BUCKETS: usize = 8;
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, tsify::Tsify)]
+#[tsify(into_wasm_abi, from_wasm_abi)]
+pub struct SchedulerTelemetry {
+    pub app_version: String,
+    /// The privacy budget the noise was calibrated for. Smaller is noisier.
+    pub epsilon: f64,
+    /// How many days of reviews the histogram covers
+    pub window_days: u32,
+    /// Shortest intervals first
+    pub buckets: Vec<NoisedIntervalCounts>,
+}
+
+/// How many due reviews after an interval in this bucket were remembered and forgotten, give or
+/// take the noise
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, tsify::Tsify)]
+#[tsify(into_wasm_abi, from_wasm_abi)]
+pub struct NoisedIntervalCounts {
+    /// The longest interval in the bucket, `None` for the open-ended last one
+    pub max_interval_days: Option<u32>,
+    pub remembered: u32,
+    pub forgot: u32,
+}
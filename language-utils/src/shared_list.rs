@@ -0,0 +1,40 @@
+//! Vocabulary lists that users share with each other. A list can travel as a self-contained code
+//! (see `yap_frontend_rs::encode_shared_list`), or be uploaded with `POST /shared-lists` so the
+//! short code that comes back can be put in a link, and `GET /shared-lists?code=...` fetches it.
+
+use serde::{Deserialize, Serialize};
+
+use crate::{Course, Lexeme};
+
+/// Lists longer than this are rejected by the backend
+pub const MAX_SHARED_LIST_LEXEMES: usize = 5000;
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, tsify::Tsify)]
+#[tsify(into_wasm_abi, from_wasm_abi)]
+pub struct SharedList {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// The course the words are from. Importing it into a deck for another target language does
+    /// nothing.
+    pub course: Course,
+    pub lexemes: Vec<Lexeme<String>>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, tsify::Tsify)]
+#[tsify(into_wasm_abi, from_wasm_abi)]
+pub struct ShareListRequest {
+    pub list: SharedList,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, tsify::Tsify)]
+#[tsify(into_wasm_abi, from_wasm_abi)]
+pub struct ShareListResponse {
+    pub code: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, tsify::Tsify)]
+#[tsify(into_wasm_abi, from_wasm_abi)]
+pub struct GetSharedListQuery {
+    pub code: String,
+}
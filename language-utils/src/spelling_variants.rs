@@ -0,0 +1,185 @@
+//! Words spelled differently in different standards of the same language, like American and
+//! British English, German before and after the 1996 spelling reform, or Brazilian and European
+//! Portuguese. A pack has a table for each of its languages that has several standards (see
+//! `LanguagePack::spelling_variants`), which generate-data builds from the language's
+//! `spelling_variants.json`.
+//!
+//! Grading treats every spelling of a word as the same word (see
+//! `text_cleanup::normalize_for_grading`), so writing "colour" for "color" isn't a mistake. The app
+//! can also show and speak sentences in the standard the user prefers, with `respell`.
+
+use std::collections::BTreeMap;
+
+/// The contents of a language's `spelling_variants.json`
+#[derive(Clone, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct SpellingVariantTable {
+    /// The names of the language's standards, e.g. `["US", "UK"]`
+    pub variants: Vec<String>,
+    /// Each word's spelling in every standard, in the order of `variants`, e.g.
+    /// `["color", "colour"]`
+    pub words: Vec<Vec<String>>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, rkyv::Archive, rkyv::Serialize, rkyv::Deserialize)]
+#[rkyv(derive(Debug))]
+pub struct SpellingVariants {
+    pub variants: Vec<String>,
+    pub words: Vec<Vec<String>>,
+    /// Every spelling in `words`, lowercased, and the index of its word
+    spellings: BTreeMap<String, u32>,
+}
+
+impl SpellingVariants {
+    pub fn new(table: SpellingVariantTable) -> Result<Self, String> {
+        let mut spellings = BTreeMap::new();
+        for (index, word) in table.words.iter().enumerate() {
+            if word.len() != table.variants.len() {
+                return Err(format!(
+                    "{word:?} has {} spellings, but there are {} variants",
+                    word.len(),
+                    table.variants.len()
+                ));
+            }
+            for spelling in word {
+                // A spelling shared by two words stays with the first one
+                spellings
+                    .entry(spelling.to_lowercase())
+                    .or_insert(index as u32);
+            }
+        }
+        Ok(Self {
+            variants: table.variants,
+            words: table.words,
+            spellings,
+        })
+    }
+
+    fn word_of(&self, spelling: &str) -> Option<&[String]> {
+        let index = *self.spellings.get(&spelling.to_lowercase())?;
+        self.words.get(index as usize).map(Vec::as_slice)
+    }
+
+    /// Spells every word of `text` the way the first standard does, so spellings of the same word
+    /// compare equal. `text` should already be normalized, i.e. lowercase words separated by single
+    /// spaces.
+    pub fn canonicalize(&self, text: &str) -> String {
+        text.split(' ')
+            .map(|word| match self.word_of(word) {
+                Some(spellings) => spellings[0].to_lowercase(),
+                None => word.to_string(),
+            })
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    /// `text` with each word that's spelled differently in the standard `variant` spelled that
+    /// way, keeping its capitalization and everything around it. `text` is returned unchanged if
+    /// `variant` isn't one of `variants`.
+    pub fn respell(&self, text: &str, variant: &str) -> String {
+        let Some(variant) = self.variants.iter().position(|v| v == variant) else {
+            return text.to_string();
+        };
+
+        let mut respelled = String::with_capacity(text.len());
+        let mut rest = text;
+        while let Some(start) = rest.find(char::is_alphabetic) {
+            respelled.push_str(&rest[..start]);
+            rest = &rest[start..];
+            let end = rest
+                .find(|c: char| !c.is_alphabetic())
+                .unwrap_or(rest.len());
+            let word = &rest[..end];
+            match self.word_of(word) {
+                Some(spellings) => respelled.push_str(&with_case_of(word, &spellings[variant])),
+                None => respelled.push_str(word),
+            }
+            rest = &rest[end..];
+        }
+        respelled.push_str(rest);
+        respelled
+    }
+}
+
+/// `spelling`, capitalized or in capitals like `word` is
+fn with_case_of(word: &str, spelling: &str) -> String {
+    let mut chars = word.chars();
+    let first_is_upper = chars.next().is_some_and(char::is_uppercase);
+    if first_is_upper && chars.clone().next().is_some() && chars.all(char::is_uppercase) {
+        spelling.to_uppercase()
+    } else if first_is_upper {
+        let mut spelling_chars = spelling.chars();
+        spelling_chars
+            .next()
+            .map(|first| first.to_uppercase().chain(spelling_chars).collect())
+            .unwrap_or_default()
+    } else {
+        spelling.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Language;
+    use crate::text_cleanup::normalize_for_grading;
+
+    fn german() -> SpellingVariants {
+        SpellingVariants::new(SpellingVariantTable {
+            variants: vec!["reformed".to_string(), "traditional".to_string()],
+            words: vec![
+                vec!["dass".to_string(), "daß".to_string()],
+                vec!["Fotografie".to_string(), "Photographie".to_string()],
+            ],
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn test_variants_are_graded_as_the_same_word() {
+        let variants = german();
+        assert_eq!(
+            normalize_for_grading(
+                "Ich weiß, daß er es ist.",
+                Language::German,
+                Some(&variants)
+            ),
+            normalize_for_grading(
+                "Ich weiß, dass er es ist",
+                Language::German,
+                Some(&variants)
+            ),
+        );
+        assert_ne!(
+            normalize_for_grading("daß", Language::German, None),
+            normalize_for_grading("dass", Language::German, None),
+        );
+    }
+
+    #[test]
+    fn test_respelling_keeps_capitalization_and_punctuation() {
+        let variants = german();
+        assert_eq!(
+            variants.respell("Dass die Fotografie, FOTOGRAFIE!", "traditional"),
+            "Daß die Photographie, PHOTOGRAPHIE!"
+        );
+        assert_eq!(
+            variants.respell("Die Photographie", "reformed"),
+            "Die Fotografie"
+        );
+        assert_eq!(
+            variants.respell("Die Photographie", "Swiss"),
+            "Die Photographie"
+        );
+    }
+
+    #[test]
+    fn test_rows_need_a_spelling_per_variant() {
+        assert!(
+            SpellingVariants::new(SpellingVariantTable {
+                variants: vec!["US".to_string(), "UK".to_string()],
+                words: vec![vec!["color".to_string()]],
+            })
+            .is_err()
+        );
+    }
+}
@@ -4,6 +4,7 @@
 //! according to language-specific typographic rules.
 
 use crate::Language;
+use crate::spelling_variants::SpellingVariants;
 
 /// Normalize text for grading purposes
 ///
@@ -12,7 +13,13 @@ use crate::Language;
 /// - For English: expands contractions (e.g., "it's" → "it is")
 /// - Converts to lowercase
 /// - Removes punctuation (except apostrophes and hyphens) and normalizes whitespace
-pub fn normalize_for_grading(text: &str, language: Language) -> String {
+/// - With `spelling_variants` (the language's table from the pack), spells each word the way the
+///   table's first standard does, so e.g. "colour" and "color" are the same
+pub fn normalize_for_grading(
+    text: &str,
+    language: Language,
+    spelling_variants: Option<&SpellingVariants>,
+) -> String {
     // First normalize special characters
     let normalized_chars = text
         .chars()
@@ -62,6 +69,10 @@ pub fn normalize_for_grading(text: &str, language: Language) -> String {
         .collect::<Vec<_>>()
         .join(" ");
 
+    if let Some(spelling_variants) = spelling_variants {
+        result = spelling_variants.canonicalize(&result);
+    }
+
     result
 }
 
@@ -128,18 +139,19 @@ pub fn find_closest_match(
     input: &str,
     candidates: &[String],
     language: Language,
+    spelling_variants: Option<&SpellingVariants>,
 ) -> Option<String> {
     if candidates.is_empty() {
         return None;
     }
 
-    let normalized_input = normalize_for_grading(input, language);
+    let normalized_input = normalize_for_grading(input, language, spelling_variants);
 
     candidates
         .iter()
         .min_by_key(|candidate| {
             levenshtein_distance(
-                &normalize_for_grading(candidate, language),
+                &normalize_for_grading(candidate, language, spelling_variants),
                 &normalized_input,
             )
         })
@@ -322,14 +334,14 @@ mod tests {
         // Test the general cleanup_sentence function with English (no changes)
         let input = "Hello!".to_string();
         let expected = "Hello!";
-        assert_eq!(cleanup_sentence(input, Language::English), expected);
+        assert_eq!(cleanup_sentence(input, Language::English, None), expected);
     }
 
     #[test]
     fn test_normalize_for_grading_french() {
         // French text should normalize quotes and hyphens but not expand contractions
         let input = "\u{2018}Bonjour\u{2019}, c\u{2019}est bien!";
-        let result = normalize_for_grading(input, Language::French);
+        let result = normalize_for_grading(input, Language::French, None);
         assert!(result.contains("bonjour"));
         assert!(result.contains("est bien"));
     }
@@ -338,15 +350,15 @@ mod tests {
     fn test_normalize_for_grading_english_contractions() {
         // English should expand contractions
         assert_eq!(
-            normalize_for_grading("It's a test", Language::English),
+            normalize_for_grading("It's a test", Language::English, None),
             "it is a test"
         );
         assert_eq!(
-            normalize_for_grading("I'm happy", Language::English),
+            normalize_for_grading("I'm happy", Language::English, None),
             "i am happy"
         );
         assert_eq!(
-            normalize_for_grading("won't do it", Language::English),
+            normalize_for_grading("won't do it", Language::English, None),
             "will not do it"
         );
     }
@@ -355,11 +367,11 @@ mod tests {
     fn test_normalize_for_grading_punctuation() {
         // Should remove punctuation
         assert_eq!(
-            normalize_for_grading("Hello, world!", Language::English),
+            normalize_for_grading("Hello, world!", Language::English, None),
             "hello world"
         );
         assert_eq!(
-            normalize_for_grading("What's up?", Language::English),
+            normalize_for_grading("What's up?", Language::English, None),
             "what is up"
         );
     }
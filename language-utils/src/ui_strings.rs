@@ -0,0 +1,41 @@
+//! Short phrases the app shows in a course's languages, like the prefix read before a word in
+//! listening challenges. Language packs carry a table of them (see `LanguagePack::ui_string`),
+//! which generate-data builds from `builtin` plus the language's `ui_strings.json`, so a new course
+//! can fill in its phrases without code changes. `builtin` is also the fallback for packs built
+//! before a phrase existed.
+
+use std::collections::BTreeMap;
+
+use crate::Language;
+
+/// Read before a word in listening challenges, in the target language ("The word is ...")
+pub const LISTENING_PREFIX: &str = "listening_prefix";
+
+/// Every phrase `builtin` knows about
+pub const KEYS: &[&str] = &[LISTENING_PREFIX];
+
+/// The phrase for `key` that ships with the app, if there is one
+pub fn builtin(language: Language, key: &str) -> Option<&'static str> {
+    match key {
+        LISTENING_PREFIX => Some(match language {
+            Language::French => "Le mot est",
+            Language::Spanish => "La palabra es",
+            Language::English => "The word is",
+            Language::Korean => "단어는",
+            Language::German => "Das Wort ist",
+            Language::Chinese => "单词是",
+            Language::Japanese => "単語は",
+            Language::Russian => "слово",
+            Language::Portuguese => "A palavra é",
+            Language::Italian => "La parola è",
+        }),
+        _ => None,
+    }
+}
+
+/// All of `builtin`'s phrases for `language`, in the form packs store them
+pub fn builtins(language: Language) -> BTreeMap<String, String> {
+    KEYS.iter()
+        .filter_map(|key| Some((key.to_string(), builtin(language, key)?.to_string())))
+        .collect()
+}
@@ -0,0 +1,72 @@
+//! Webhooks for external automations (n8n, Zapier, ...). A user registers a URL with
+//! `POST /webhook`, and the backend posts to it when the stats uploaded during sync (see
+//! `profile::UpdateLanguageStatsRequest`) cross a milestone. Every delivery is signed, see
+//! `SIGNATURE_HEADER`.
+
+use serde::{Deserialize, Serialize};
+
+use crate::Language;
+
+/// The header carrying a delivery's signature, as `t=<unix seconds>,v1=<hex>`. `v1` is the
+/// HMAC-SHA256 of `<unix seconds>.<body>`, keyed with `Webhook::secret`.
+pub const SIGNATURE_HEADER: &str = "Yap-Signature";
+
+/// How many due cards trigger `WebhookEvent::CardsDue` if the user doesn't pick a number
+pub const DEFAULT_DUE_THRESHOLD: i64 = 100;
+
+#[derive(
+    Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, tsify::Tsify,
+)]
+#[tsify(into_wasm_abi, from_wasm_abi)]
+#[serde(rename_all = "snake_case")]
+pub enum WebhookEvent {
+    /// The streak ends within a few hours unless the user reviews
+    StreakAtRisk,
+    /// At least `Webhook::due_threshold` cards are due
+    CardsDue,
+    /// The stats for a language, at most once a week
+    WeeklySummary,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, tsify::Tsify)]
+#[tsify(into_wasm_abi, from_wasm_abi)]
+pub struct Webhook {
+    pub url: String,
+    pub events: Vec<WebhookEvent>,
+    pub due_threshold: i64,
+    /// For checking `SIGNATURE_HEADER`. Generated by the backend.
+    pub secret: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, tsify::Tsify)]
+#[tsify(into_wasm_abi, from_wasm_abi)]
+pub struct SetWebhookRequest {
+    /// An `https` URL, or `None` to remove the webhook
+    pub url: Option<String>,
+    pub events: Vec<WebhookEvent>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub due_threshold: Option<i64>,
+    /// Replaces the secret, e.g. after it leaked. A new webhook always gets a new one.
+    #[serde(default)]
+    pub rotate_secret: bool,
+}
+
+/// The body of a delivery
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, tsify::Tsify)]
+#[tsify(into_wasm_abi, from_wasm_abi)]
+pub struct WebhookPayload {
+    pub event: WebhookEvent,
+    /// Unique per delivery, and the same across its retries
+    pub id: String,
+    pub user_id: String,
+    pub language: Language,
+    pub sent_at: String,
+    pub daily_streak: i64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub daily_streak_expiry: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub due_count: Option<i64>,
+    pub total_count: i64,
+    pub xp: f64,
+    pub percent_known: f64,
+}
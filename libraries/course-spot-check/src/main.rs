@@ -10,8 +10,9 @@ use std::sync::LazyLock;
 use tysm::chat_completions::ChatClient;
 use weapon::AppState;
 use weapon::data_model::Timestamped;
-use yap_frontend_rs::{
-    Challenge, Deck, DeckState, TranscribeComprehensibleSentence, TranslateComprehensibleSentence,
+use yap_core::{
+    Challenge, DataMismatchReport, Deck, DeckState, TranscribeComprehensibleSentence,
+    TranslateComprehensibleSentence,
 };
 
 static CHAT_CLIENT: LazyLock<ChatClient> = LazyLock::new(|| {
@@ -66,6 +67,8 @@ struct CourseAnalysis {
     sentences_with_missing_multiword_terms: usize,
     sample_issues: Vec<SentenceAnalysis>,
     all_issues: Vec<SentenceAnalysis>,
+    /// Strings in the simulated events that didn't round-trip through the pack
+    data_mismatches: DataMismatchReport,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -240,6 +243,7 @@ async fn analyze_course(course: Course) -> Result<CourseAnalysis> {
             break;
         }
     }
+    let data_mismatches = simulator.deck().get_data_mismatch_report();
 
     println!(
         "Collected {} unique sentences for analysis",
@@ -340,6 +344,7 @@ async fn analyze_course(course: Course) -> Result<CourseAnalysis> {
         sentences_with_missing_multiword_terms,
         sample_issues,
         all_issues,
+        data_mismatches,
     })
 }
 
@@ -556,6 +561,21 @@ fn print_summary(analyses: Vec<CourseAnalysis>) {
                 * 100.0
         );
 
+        if !analysis.data_mismatches.is_empty() {
+            println!(
+                "  Strings missing from the pack: {} distinct, {} untracked misses, {} orphaned cards",
+                analysis.data_mismatches.mismatches.len(),
+                analysis.data_mismatches.untracked_misses,
+                analysis.data_mismatches.orphaned_cards
+            );
+            for mismatch in analysis.data_mismatches.mismatches.iter().take(10) {
+                println!(
+                    "    {:?} {:?} ({} times)",
+                    mismatch.kind, mismatch.value, mismatch.count
+                );
+            }
+        }
+
         if !analysis.sample_issues.is_empty() {
             println!("\n  Sample problematic sentences:");
             for (i, issue) in analysis.sample_issues.iter().take(5).enumerate() {
@@ -0,0 +1,615 @@
+//! A small made-up French course for tests. The real packs in `out/` are generated from data that
+//! isn't in the repo, so tests that need a `LanguagePack` build this one instead.
+//!
+//! The sentences come from a few dozen words put through some templates ("Le chat est rouge.",
+//! "Le chat voit une pomme."), which makes a few hundred of them. They're nonsense as often as not,
+//! but the pack has one of everything the app looks at: words spelled alike (the noun and the verb
+//! "ferme"), homophones with practice sentences ("mer" and "mère"), a multiword term whose words
+//! are in the pack too ("bien sûr"), pronunciation patterns, and a movie.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use language_utils::language_pack::LanguagePack;
+use language_utils::lexeme_ids::LexemeIdTable;
+use language_utils::pronunciation_patterns::{contains_pattern, normalize_pattern, normalize_word};
+use language_utils::{
+    ConsolidatedLanguageData, DictionaryEntry, FrequencyEntry, Heteronym, HomophonePractice,
+    HomophoneSentencePair, HomophoneWordPair, Language, Lexeme, Literal, MaturityRating,
+    MovieMetadata, MultiwordTerms, PartOfSpeech, PatternPosition, PhrasebookEntry,
+    PronunciationData, PronunciationDifficulty, PronunciationFamiliarity, PronunciationGuide,
+    SentenceInfo, SentenceSource, SoundPosition, TargetToNativeWord, WordPair,
+};
+use rustc_hash::FxHashMap;
+
+pub const TARGET_LANGUAGE: Language = Language::French;
+pub const NATIVE_LANGUAGE: Language = Language::English;
+
+/// The one movie, which every fourth sentence is from
+pub const MOVIE_ID: &str = "tt0000001";
+
+struct Word {
+    text: &'static str,
+    lemma: &'static str,
+    pos: PartOfSpeech,
+    ipa: &'static str,
+    english: &'static str,
+}
+
+const fn word(
+    text: &'static str,
+    lemma: &'static str,
+    pos: PartOfSpeech,
+    ipa: &'static str,
+    english: &'static str,
+) -> Word {
+    Word {
+        text,
+        lemma,
+        pos,
+        ipa,
+        english,
+    }
+}
+
+const fn noun(text: &'static str, ipa: &'static str, english: &'static str) -> Word {
+    word(text, text, PartOfSpeech::Noun, ipa, english)
+}
+
+const fn adjective(text: &'static str, ipa: &'static str, english: &'static str) -> Word {
+    word(text, text, PartOfSpeech::Adj, ipa, english)
+}
+
+static MASCULINE_NOUNS: [Word; 10] = [
+    noun("chat", "ʃa", "cat"),
+    noun("chien", "ʃjɛ̃", "dog"),
+    noun("livre", "livʁ", "book"),
+    noun("garçon", "ɡaʁsɔ̃", "boy"),
+    noun("pain", "pɛ̃", "bread"),
+    noun("train", "tʁɛ̃", "train"),
+    noun("jardin", "ʒaʁdɛ̃", "garden"),
+    noun("café", "kafe", "café"),
+    noun("film", "film", "movie"),
+    noun("bateau", "bato", "boat"),
+];
+
+static FEMININE_NOUNS: [Word; 10] = [
+    noun("maison", "mɛzɔ̃", "house"),
+    noun("voiture", "vwatyʁ", "car"),
+    noun("fille", "fij", "girl"),
+    noun("pomme", "pɔm", "apple"),
+    noun("porte", "pɔʁt", "door"),
+    noun("fleur", "flœʁ", "flower"),
+    noun("mer", "mɛʁ", "sea"),
+    noun("mère", "mɛʁ", "mother"),
+    noun("ferme", "fɛʁm", "farm"),
+    noun("chanson", "ʃɑ̃sɔ̃", "song"),
+];
+
+/// All the same in the masculine and the feminine, so they go with any noun
+static ADJECTIVES: [Word; 8] = [
+    adjective("rouge", "ʁuʒ", "red"),
+    adjective("jaune", "ʒon", "yellow"),
+    adjective("calme", "kalm", "calm"),
+    adjective("triste", "tʁist", "sad"),
+    adjective("rapide", "ʁapid", "fast"),
+    adjective("facile", "fasil", "easy"),
+    adjective("jeune", "ʒœn", "young"),
+    adjective("célèbre", "selɛbʁ", "famous"),
+];
+
+static TRANSITIVE_VERBS: [Word; 5] = [
+    word("voit", "voir", PartOfSpeech::Verb, "vwa", "sees"),
+    word("mange", "manger", PartOfSpeech::Verb, "mɑ̃ʒ", "eats"),
+    word(
+        "cherche",
+        "chercher",
+        PartOfSpeech::Verb,
+        "ʃɛʁʃ",
+        "looks for",
+    ),
+    word("aime", "aimer", PartOfSpeech::Verb, "ɛm", "likes"),
+    word("ferme", "fermer", PartOfSpeech::Verb, "fɛʁm", "closes"),
+];
+
+static EST: Word = word("est", "être", PartOfSpeech::Aux, "ɛ", "is");
+static LE: Word = word("le", "le", PartOfSpeech::Det, "lə", "the");
+static LA: Word = word("la", "le", PartOfSpeech::Det, "la", "the");
+static UN: Word = word("un", "un", PartOfSpeech::Det, "œ̃", "a");
+static UNE: Word = word("une", "un", PartOfSpeech::Det, "yn", "a");
+static BIEN: Word = word("bien", "bien", PartOfSpeech::Adv, "bjɛ̃", "well");
+static SUR: Word = word("sûr", "sûr", PartOfSpeech::Adj, "syʁ", "sure");
+static BONJOUR: Word = word("bonjour", "bonjour", PartOfSpeech::Intj, "bɔ̃ʒuʁ", "hello");
+
+const BIEN_SUR: &str = "bien sûr";
+
+fn heteronym(word: &Word) -> Heteronym<String> {
+    Heteronym {
+        word: word.text.to_string(),
+        lemma: word.lemma.to_string(),
+        pos: word.pos,
+    }
+}
+
+/// A word of a sentence, or punctuation if `word` is `None`
+struct Token<'a> {
+    text: String,
+    word: Option<&'a Word>,
+}
+
+impl<'a> Token<'a> {
+    fn word(word: &'a Word) -> Self {
+        Token {
+            text: word.text.to_string(),
+            word: Some(word),
+        }
+    }
+
+    fn punctuation(text: &str) -> Self {
+        Token {
+            text: text.to_string(),
+            word: None,
+        }
+    }
+}
+
+struct Sentence {
+    text: String,
+    info: SentenceInfo,
+    translation: String,
+}
+
+fn capitalize(text: &str) -> String {
+    let mut chars = text.chars();
+    chars
+        .next()
+        .map(|first| first.to_uppercase().chain(chars).collect())
+        .unwrap_or_default()
+}
+
+fn sentence(mut tokens: Vec<Token>, translation: String) -> Sentence {
+    tokens[0].text = capitalize(&tokens[0].text);
+    let whitespace = (0..tokens.len())
+        .map(|index| match tokens.get(index + 1) {
+            Some(next) if next.word.is_some() => " ",
+            _ => "",
+        })
+        .collect::<Vec<_>>();
+    let text = tokens
+        .iter()
+        .zip(&whitespace)
+        .map(|(token, whitespace)| format!("{}{whitespace}", token.text))
+        .collect();
+    let multiword_terms = MultiwordTerms {
+        high_confidence: tokens
+            .windows(2)
+            .filter(|pair| pair[0].word.is_some_and(|word| word.text == BIEN.text))
+            .filter(|pair| pair[1].word.is_some_and(|word| word.text == SUR.text))
+            .map(|_| BIEN_SUR.to_string())
+            .collect(),
+        low_confidence: Vec::new(),
+    };
+    let words = tokens
+        .iter()
+        .zip(whitespace)
+        .map(|(token, whitespace)| Literal {
+            text: token.text.clone(),
+            whitespace: whitespace.to_string(),
+            heteronym: token.word.map(heteronym),
+            morph: None,
+        })
+        .collect();
+    Sentence {
+        text,
+        info: SentenceInfo {
+            words,
+            multiword_terms,
+        },
+        translation,
+    }
+}
+
+/// "the cat" or "une pomme", with its translation
+fn noun_phrase(
+    noun: &'static Word,
+    feminine: bool,
+    definite: bool,
+) -> (Vec<Token<'static>>, String) {
+    let determiner = match (feminine, definite) {
+        (false, true) => &LE,
+        (true, true) => &LA,
+        (false, false) => &UN,
+        (true, false) => &UNE,
+    };
+    let article = match definite {
+        true => "the",
+        false if noun.english.starts_with(['a', 'e', 'i', 'o', 'u']) => "an",
+        false => "a",
+    };
+    (
+        vec![Token::word(determiner), Token::word(noun)],
+        format!("{article} {}", noun.english),
+    )
+}
+
+fn nouns() -> impl Iterator<Item = (&'static Word, bool)> {
+    MASCULINE_NOUNS
+        .iter()
+        .map(|noun| (noun, false))
+        .chain(FEMININE_NOUNS.iter().map(|noun| (noun, true)))
+}
+
+fn sentences() -> Vec<Sentence> {
+    let nouns = nouns().collect::<Vec<_>>();
+    let mut sentences = Vec::new();
+
+    // "Le chat est rouge."
+    for &(noun, feminine) in &nouns {
+        for adjective in &ADJECTIVES {
+            let (mut tokens, subject) = noun_phrase(noun, feminine, true);
+            tokens.extend([
+                Token::word(&EST),
+                Token::word(adjective),
+                Token::punctuation("."),
+            ]);
+            let translation = capitalize(&format!("{subject} is {}.", adjective.english));
+            sentences.push(sentence(tokens, translation));
+        }
+    }
+
+    // "Le chat voit une pomme."
+    for (verb_index, verb) in TRANSITIVE_VERBS.iter().enumerate() {
+        for (subject_index, &(noun, feminine)) in nouns.iter().enumerate() {
+            let (object, object_feminine) =
+                nouns[(subject_index * 7 + verb_index + 3) % nouns.len()];
+            let (mut tokens, subject) = noun_phrase(noun, feminine, true);
+            let (object_tokens, object) = noun_phrase(object, object_feminine, false);
+            tokens.push(Token::word(verb));
+            tokens.extend(object_tokens);
+            tokens.push(Token::punctuation("."));
+            let translation = capitalize(&format!("{subject} {} {object}.", verb.english));
+            sentences.push(sentence(tokens, translation));
+        }
+    }
+
+    // "Bien sûr, le chat est calme."
+    for (index, &(noun, feminine)) in nouns.iter().enumerate() {
+        let adjective = &ADJECTIVES[(index * 3) % ADJECTIVES.len()];
+        let (noun_tokens, subject) = noun_phrase(noun, feminine, true);
+        let mut tokens = vec![
+            Token::word(&BIEN),
+            Token::word(&SUR),
+            Token::punctuation(","),
+        ];
+        tokens.extend(noun_tokens);
+        tokens.extend([
+            Token::word(&EST),
+            Token::word(adjective),
+            Token::punctuation("."),
+        ]);
+        let translation = format!("Of course, {subject} is {}.", adjective.english);
+        sentences.push(sentence(tokens, translation));
+    }
+
+    sentences.push(sentence(
+        vec![Token::word(&BONJOUR), Token::punctuation(".")],
+        "Hello.".to_string(),
+    ));
+
+    sentences
+}
+
+fn all_words() -> impl Iterator<Item = &'static Word> {
+    MASCULINE_NOUNS
+        .iter()
+        .chain(&FEMININE_NOUNS)
+        .chain(&ADJECTIVES)
+        .chain(&TRANSITIVE_VERBS)
+        .chain([&EST, &LE, &LA, &UN, &UNE, &BIEN, &SUR, &BONJOUR])
+}
+
+/// How often each lexeme is in `sentences`, most frequent first
+fn count_lexemes<'a>(sentences: impl Iterator<Item = &'a Sentence>) -> Vec<FrequencyEntry<String>> {
+    let mut counts: BTreeMap<Lexeme<String>, u32> = BTreeMap::new();
+    for sentence in sentences {
+        for lexeme in sentence.info.lexemes() {
+            *counts.entry(lexeme).or_default() += 1;
+        }
+    }
+    let mut frequencies = counts
+        .into_iter()
+        .map(|(lexeme, count)| FrequencyEntry { lexeme, count })
+        .collect::<Vec<_>>();
+    frequencies.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.lexeme.cmp(&b.lexeme)));
+    frequencies
+}
+
+fn pronunciation_data() -> PronunciationData {
+    let guide = |pattern: &str,
+                 position: PatternPosition,
+                 description: &str,
+                 examples: &[(&str, &str)]| PronunciationGuide {
+        pattern: pattern.to_string(),
+        position,
+        description: description.to_string(),
+        familiarity: PronunciationFamiliarity::ProbablyDoesNotKnow,
+        difficulty: PronunciationDifficulty::Medium,
+        example_words: examples
+            .iter()
+            .map(|(target, native)| WordPair {
+                target: target.to_string(),
+                native: native.to_string(),
+                position: match position {
+                    PatternPosition::Beginning => SoundPosition::Beginning,
+                    PatternPosition::End => SoundPosition::End,
+                    PatternPosition::Anywhere => SoundPosition::Multiple,
+                },
+                cultural_context: String::new(),
+            })
+            .collect(),
+    };
+    let guides = vec![
+        guide(
+            "ch",
+            PatternPosition::Anywhere,
+            "Like the \"sh\" in \"shoe\"",
+            &[("chat", "cat"), ("chien", "dog")],
+        ),
+        guide(
+            "on",
+            PatternPosition::End,
+            "A nasal \"o\", with no \"n\" at the end",
+            &[("garçon", "boy"), ("chanson", "song")],
+        ),
+        guide(
+            "eau",
+            PatternPosition::End,
+            "Like the \"o\" in \"go\", but shorter",
+            &[("bateau", "boat")],
+        ),
+        guide(
+            "r",
+            PatternPosition::Anywhere,
+            "Made at the back of the throat",
+            &[("rouge", "red"), ("fleur", "flower")],
+        ),
+    ];
+
+    let words = all_words()
+        .map(|word| normalize_word(word.text, false))
+        .collect::<BTreeSet<_>>();
+    let mut pattern_frequencies = guides
+        .iter()
+        .map(|guide| {
+            let pattern = normalize_pattern(&guide.pattern, guide.position, false);
+            let count = words
+                .iter()
+                .filter(|word| contains_pattern(word, &pattern, guide.position))
+                .count() as u32;
+            ((guide.pattern.clone(), guide.position), count)
+        })
+        .collect::<Vec<_>>();
+    pattern_frequencies.sort_by(|a, b| b.1.cmp(&a.1));
+
+    PronunciationData {
+        sounds: guides
+            .iter()
+            .map(|guide| (guide.pattern.clone(), guide.position))
+            .collect(),
+        guides,
+        pattern_frequencies,
+    }
+}
+
+/// The pack's data, before it's interned into a `LanguagePack`
+pub fn language_data() -> ConsolidatedLanguageData {
+    let sentences = sentences();
+    let in_movie = |index: usize| index % 4 == 0;
+
+    let frequencies = count_lexemes(sentences.iter());
+    let movie_frequencies = FxHashMap::from_iter([(
+        MOVIE_ID.to_string(),
+        count_lexemes(
+            sentences
+                .iter()
+                .enumerate()
+                .filter(|(index, _)| in_movie(*index))
+                .map(|(_, sentence)| sentence),
+        ),
+    )]);
+
+    // Each word's definition is illustrated with the first sentence it's in
+    let example = |lexeme: &Lexeme<String>| {
+        sentences
+            .iter()
+            .find(|sentence| sentence.info.lexemes().any(|other| other == *lexeme))
+            .map(|sentence| (sentence.text.clone(), sentence.translation.clone()))
+            .unwrap_or_default()
+    };
+    let dictionary = all_words()
+        .map(|word| {
+            let heteronym = heteronym(word);
+            let (target, native) = example(&Lexeme::Heteronym(heteronym.clone()));
+            let entry = DictionaryEntry {
+                target_language_word: word.text.to_string(),
+                definitions: vec![TargetToNativeWord {
+                    native: word.english.to_string(),
+                    note: None,
+                    example_sentence_target_language: target,
+                    example_sentence_native_language: native,
+                }],
+                morphology: Vec::new(),
+                notes: None,
+            };
+            (heteronym, entry)
+        })
+        .collect::<BTreeMap<_, _>>();
+    let mut dictionary_groups: Vec<Vec<Heteronym<String>>> = Vec::new();
+    for heteronym in frequencies
+        .iter()
+        .filter_map(|frequency| frequency.lexeme.heteronym())
+    {
+        match dictionary_groups
+            .iter_mut()
+            .find(|group| group[0].word == heteronym.word)
+        {
+            Some(group) => group.push(heteronym.clone()),
+            None => dictionary_groups.push(vec![heteronym.clone()]),
+        }
+    }
+
+    let (bien_sur_example, bien_sur_translation) =
+        example(&Lexeme::Multiword(BIEN_SUR.to_string()));
+    let phrasebook = BTreeMap::from([(
+        BIEN_SUR.to_string(),
+        PhrasebookEntry {
+            target_language_multi_word_term: BIEN_SUR.to_string(),
+            meaning: "of course".to_string(),
+            additional_notes: String::new(),
+            target_language_example: bien_sur_example,
+            native_language_example: bien_sur_translation,
+        },
+    )]);
+
+    let word_to_pronunciation = all_words()
+        .map(|word| (word.text.to_string(), word.ipa.to_string()))
+        .collect::<BTreeMap<_, _>>();
+    let mut pronunciation_to_words: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    for (word, pronunciation) in &word_to_pronunciation {
+        pronunciation_to_words
+            .entry(pronunciation.clone())
+            .or_default()
+            .push(word.clone());
+    }
+
+    let homophone_pair = HomophoneWordPair::new("mer".to_string(), "mère".to_string())
+        .expect("the words are different");
+    let homophone_practice = BTreeMap::from([(
+        homophone_pair.clone(),
+        HomophonePractice {
+            sentence_pairs: ADJECTIVES
+                .iter()
+                .map(|adjective| {
+                    let sentence = |noun: &str| format!("La {noun} est {}.", adjective.text);
+                    HomophoneSentencePair {
+                        sentence1: sentence(&homophone_pair.word1),
+                        sentence2: sentence(&homophone_pair.word2),
+                    }
+                })
+                .collect(),
+        },
+    )]);
+
+    let movies = FxHashMap::from_iter([(
+        MOVIE_ID.to_string(),
+        MovieMetadata {
+            id: MOVIE_ID.to_string(),
+            title: "Le Jardin".to_string(),
+            year: Some(2024),
+            poster_thumbnail: None,
+            posters: Vec::new(),
+            maturity: Some(MaturityRating::G),
+        },
+    )]);
+    let sentence_sources = sentences
+        .iter()
+        .enumerate()
+        .map(|(index, sentence)| {
+            let source = SentenceSource {
+                from_tatoeba: true,
+                movie_ids: if in_movie(index) {
+                    vec![MOVIE_ID.to_string()]
+                } else {
+                    Vec::new()
+                },
+                ..SentenceSource::none()
+            };
+            (sentence.text.clone(), source)
+        })
+        .collect();
+
+    let mut lexeme_ids = LexemeIdTable::default();
+    lexeme_ids.assign(frequencies.iter().map(|frequency| &frequency.lexeme));
+
+    ConsolidatedLanguageData {
+        target_language_sentences: sentences
+            .iter()
+            .map(|sentence| sentence.text.clone())
+            .collect(),
+        translations: sentences
+            .iter()
+            .map(|sentence| (sentence.text.clone(), vec![sentence.translation.clone()]))
+            .collect(),
+        nlp_sentences: sentences
+            .iter()
+            .map(|sentence| (sentence.text.clone(), sentence.info.clone()))
+            .collect(),
+        dictionary,
+        dictionary_groups,
+        phrasebook,
+        frequencies,
+        movie_frequencies,
+        word_to_pronunciation: word_to_pronunciation.into_iter().collect(),
+        pronunciation_to_words: pronunciation_to_words.into_iter().collect(),
+        pronunciation_data: pronunciation_data(),
+        homophone_practice,
+        movies,
+        sentence_sources,
+        ui_strings: BTreeMap::new(),
+        profane_sentences: Vec::new(),
+        fsrs_preset: None,
+        completion_criteria: None,
+        spelling_variants: BTreeMap::new(),
+        word_families: Vec::new(),
+        lexeme_ids: lexeme_ids.entries(),
+        songs: Vec::new(),
+        cognates: BTreeMap::new(),
+        frequency_drift: Vec::new(),
+    }
+}
+
+/// The pack, for `TARGET_LANGUAGE` learners who speak `NATIVE_LANGUAGE`
+pub fn language_pack() -> LanguagePack {
+    LanguagePack::new(language_data())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pack_has_one_of_everything() {
+        let pack = language_pack();
+        assert!(pack.sentences_to_lexemes.len() > 250);
+        assert!(pack.word_frequencies.len() > 40);
+
+        let ferme = pack.rodeo.get("ferme").unwrap();
+        assert_eq!(pack.words_to_heteronyms[&ferme].len(), 2);
+        let mer = pack.rodeo.get("mer").unwrap();
+        assert_eq!(
+            pack.pronunciation_to_words[&pack.word_to_pronunciation[&mer]].len(),
+            2
+        );
+        let bien_sur = pack.rodeo.get(BIEN_SUR).unwrap();
+        assert!(
+            pack.word_frequencies
+                .contains_key(&Lexeme::Multiword(bien_sur))
+        );
+        assert!(!pack.homophone_practice.is_empty());
+        assert!(!pack.pattern_frequency_map.is_empty());
+        assert!(
+            pack.sentence_sources
+                .values()
+                .any(|source| source.movie_ids == [MOVIE_ID])
+        );
+
+        // It survives being archived like the real packs are
+        let bytes = rkyv::to_bytes::<rkyv::rancor::Error>(&pack).unwrap();
+        let archived = rkyv::access::<
+            language_utils::language_pack::ArchivedLanguagePack,
+            rkyv::rancor::Error,
+        >(&bytes)
+        .unwrap();
+        let restored = rkyv::deserialize::<LanguagePack, rkyv::rancor::Error>(archived).unwrap();
+        assert_eq!(restored.word_frequencies.len(), pack.word_frequencies.len());
+    }
+}
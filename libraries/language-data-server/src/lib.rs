@@ -0,0 +1,415 @@
+//! The language-pack routes of the backend, split out so they can run without the packs being
+//! compiled into the binary.
+//!
+//! Packs either come from memory (for the classic single-binary deploy, which `include_bytes!`s
+//! them) or are fetched from object storage the first time a course is requested and kept
+//! around afterwards. Only the packs that are actually requested end up in memory, which keeps
+//! cold starts fast on small serverless machines.
+//!
+//! Images the packs point to (see `language_utils::ImageAsset`) are only in object storage, and are
+//! passed through rather than kept in memory, since clients cache them themselves.
+
+use std::{
+    collections::BTreeMap,
+    sync::{Arc, RwLock},
+};
+
+use axum::{
+    Router,
+    body::{Body, Bytes},
+    extract::{Json, Path, Query, State},
+    http::{StatusCode, header},
+    response::{IntoResponse as _, Response},
+    routing::{get, post},
+};
+use language_utils::{
+    Course, backend_routes,
+    pack_manifest::{PackChannel, PackManifest, PackQuery, PackSegmentRequest},
+};
+
+/// A pack that's already in memory, usually from `include_bytes!`. Embedded packs are always the
+/// stable ones.
+#[derive(Clone, Copy)]
+pub struct EmbeddedPack {
+    pub data: &'static [u8],
+    /// The contents of the pack's `language_data.hash`
+    pub hash: &'static str,
+}
+
+/// Where language packs are loaded from
+pub enum PackSource {
+    Embedded(BTreeMap<Course, EmbeddedPack>),
+    /// Packs are fetched from `{base_url}/{target}_for_{native}/language_data.rkyv` (and
+    /// `language_data.hash`) and images from `{base_url}/images/`, which is the same layout
+    /// `generate-data` writes to `out/`. Beta packs are under `{base_url}/beta/`, and every
+    /// published pack should also be uploaded under `{base_url}/versions/{hash}/` so users can be
+    /// pinned to it.
+    ObjectStorage {
+        base_url: String,
+        courses: Vec<Course>,
+    },
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum PackStoreError {
+    #[error("Error fetching language pack from object storage")]
+    Fetch(#[source] reqwest::Error),
+
+    #[error("Object storage returned {0} for {1}")]
+    Status(reqwest::StatusCode, String),
+}
+
+/// Which pack of a course is stored where. This is a `PackQuery` with the channel dropped when a
+/// version is asked for, so both spellings of a pinned pack share a cache entry.
+type PackKey = (Course, PackQuery);
+
+fn pack_key(course: Course, query: &PackQuery) -> PackKey {
+    let query = match &query.version {
+        Some(version) => PackQuery {
+            channel: PackChannel::Stable,
+            version: Some(version.clone()),
+        },
+        None => query.clone(),
+    };
+    (course, query)
+}
+
+pub struct PackStore {
+    source: PackSource,
+    client: reqwest::Client,
+    loaded: RwLock<BTreeMap<PackKey, Bytes>>,
+    hashes: RwLock<BTreeMap<PackKey, String>>,
+    manifests: RwLock<BTreeMap<PackKey, Arc<PackManifest>>>,
+}
+
+pub fn course_directory_slug(course: Course) -> String {
+    format!(
+        "{}_for_{}",
+        course.target_language.iso_639_3(),
+        course.native_language.iso_639_3()
+    )
+}
+
+impl PackStore {
+    pub fn new(source: PackSource) -> Self {
+        Self {
+            source,
+            client: reqwest::Client::new(),
+            loaded: RwLock::new(BTreeMap::new()),
+            hashes: RwLock::new(BTreeMap::new()),
+            manifests: RwLock::new(BTreeMap::new()),
+        }
+    }
+
+    /// The courses this store can serve, whether or not they've been loaded yet
+    pub fn courses(&self) -> Vec<Course> {
+        match &self.source {
+            PackSource::Embedded(packs) => packs.keys().copied().collect(),
+            PackSource::ObjectStorage { courses, .. } => courses.clone(),
+        }
+    }
+
+    /// Whether the stable pack for `course` is in memory, i.e. can be served without hitting
+    /// object storage
+    pub fn is_loaded(&self, course: Course) -> bool {
+        match &self.source {
+            PackSource::Embedded(packs) => packs.contains_key(&course),
+            PackSource::ObjectStorage { .. } => self
+                .loaded
+                .read()
+                .unwrap()
+                .contains_key(&pack_key(course, &PackQuery::default())),
+        }
+    }
+
+    /// The embedded pack `query` asks for, if there is one
+    fn embedded(
+        packs: &BTreeMap<Course, EmbeddedPack>,
+        course: Course,
+        query: &PackQuery,
+    ) -> Option<EmbeddedPack> {
+        let pack = packs.get(&course)?;
+        match &query.version {
+            Some(version) => (version == pack.hash.trim()).then_some(*pack),
+            None => (query.channel == PackChannel::Stable).then_some(*pack),
+        }
+    }
+
+    /// The object storage directory `query` points to for `course`, or `None` if it asks for a
+    /// version that isn't a pack hash (which also keeps it from escaping `base_url`)
+    fn object_storage_directory(
+        base_url: &str,
+        course: Course,
+        query: &PackQuery,
+    ) -> Option<String> {
+        let prefix = match (&query.version, query.channel) {
+            (Some(version), _) => {
+                version.parse::<u64>().ok()?;
+                format!("versions/{version}/")
+            }
+            (None, PackChannel::Stable) => String::new(),
+            (None, PackChannel::Beta) => "beta/".to_string(),
+        };
+        Some(format!(
+            "{}/{prefix}{}",
+            base_url.trim_end_matches('/'),
+            course_directory_slug(course)
+        ))
+    }
+
+    /// Returns the pack for `course` that `query` asks for, fetching it from object storage if
+    /// needed. Returns `Ok(None)` if the course (or that pack of it) isn't served by this store.
+    pub async fn get(
+        &self,
+        course: Course,
+        query: &PackQuery,
+    ) -> Result<Option<Bytes>, PackStoreError> {
+        let (base_url, courses) = match &self.source {
+            PackSource::Embedded(packs) => {
+                return Ok(
+                    Self::embedded(packs, course, query).map(|pack| Bytes::from_static(pack.data))
+                );
+            }
+            PackSource::ObjectStorage { base_url, courses } => (base_url, courses),
+        };
+        if !courses.contains(&course) {
+            return Ok(None);
+        }
+        let Some(directory) = Self::object_storage_directory(base_url, course, query) else {
+            return Ok(None);
+        };
+
+        let key = pack_key(course, query);
+        if let Some(pack) = self.loaded.read().unwrap().get(&key) {
+            return Ok(Some(pack.clone()));
+        }
+
+        // Two concurrent first requests for the same course may both fetch it. That's wasteful
+        // but harmless, and much simpler than holding a lock across the download.
+        let Some(pack) = self.fetch(&directory, "language_data.rkyv").await? else {
+            return Ok(None);
+        };
+
+        self.loaded.write().unwrap().insert(key, pack.clone());
+        Ok(Some(pack))
+    }
+
+    /// The hash of the pack for `course` that `query` asks for, which changes whenever the pack
+    /// does. Returns `Ok(None)` if the course (or that pack of it) isn't served by this store.
+    pub async fn pack_version(
+        &self,
+        course: Course,
+        query: &PackQuery,
+    ) -> Result<Option<String>, PackStoreError> {
+        let (base_url, courses) = match &self.source {
+            PackSource::Embedded(packs) => {
+                return Ok(
+                    Self::embedded(packs, course, query).map(|pack| pack.hash.trim().to_string())
+                );
+            }
+            PackSource::ObjectStorage { base_url, courses } => (base_url, courses),
+        };
+        if !courses.contains(&course) {
+            return Ok(None);
+        }
+        let Some(directory) = Self::object_storage_directory(base_url, course, query) else {
+            return Ok(None);
+        };
+
+        let key = pack_key(course, query);
+        if let Some(hash) = self.hashes.read().unwrap().get(&key) {
+            return Ok(Some(hash.clone()));
+        }
+
+        let Some(hash) = self.fetch(&directory, "language_data.hash").await? else {
+            return Ok(None);
+        };
+        let hash = String::from_utf8_lossy(&hash).trim().to_string();
+
+        self.hashes.write().unwrap().insert(key, hash.clone());
+        Ok(Some(hash))
+    }
+
+    /// The per-segment hashes of the pack for `course` that `query` asks for, which clients use
+    /// to repair broken downloads. Returns `Ok(None)` if the course (or that pack of it) isn't
+    /// served by this store.
+    pub async fn manifest(
+        &self,
+        course: Course,
+        query: &PackQuery,
+    ) -> Result<Option<Arc<PackManifest>>, PackStoreError> {
+        let key = pack_key(course, query);
+        if let Some(manifest) = self.manifests.read().unwrap().get(&key) {
+            return Ok(Some(manifest.clone()));
+        }
+        let Some(pack) = self.get(course, query).await? else {
+            return Ok(None);
+        };
+
+        let manifest = Arc::new(PackManifest::for_pack(&pack));
+        self.manifests
+            .write()
+            .unwrap()
+            .insert(key, manifest.clone());
+        Ok(Some(manifest))
+    }
+
+    /// The image named `file_name` (`{sha256}.jpg`). Returns `Ok(None)` if there's no such image,
+    /// which is always the case for embedded packs.
+    pub async fn image(&self, file_name: &str) -> Result<Option<Bytes>, PackStoreError> {
+        let PackSource::ObjectStorage { base_url, .. } = &self.source else {
+            return Ok(None);
+        };
+        // Also keeps the name from escaping `base_url`
+        let is_hash = |hash: &str| hash.len() == 64 && hash.bytes().all(|b| b.is_ascii_hexdigit());
+        if !file_name.strip_suffix(".jpg").is_some_and(is_hash) {
+            return Ok(None);
+        }
+        let directory = format!("{}/images", base_url.trim_end_matches('/'));
+        self.fetch(&directory, file_name).await
+    }
+
+    /// Fetches `file_name` from `directory`. Only stable packs have to exist for every course, so
+    /// a missing file is `Ok(None)` rather than an error.
+    async fn fetch(
+        &self,
+        directory: &str,
+        file_name: &str,
+    ) -> Result<Option<Bytes>, PackStoreError> {
+        let url = format!("{directory}/{file_name}");
+        let response = self
+            .client
+            .get(&url)
+            .send()
+            .await
+            .map_err(PackStoreError::Fetch)?;
+        if response.status() == reqwest::StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        if !response.status().is_success() {
+            return Err(PackStoreError::Status(response.status(), url));
+        }
+        response
+            .bytes()
+            .await
+            .map(Some)
+            .map_err(PackStoreError::Fetch)
+    }
+}
+
+/// `POST /language-data` with a `Course` body responds with the raw rkyv pack.
+/// `POST /language-data/manifest` responds with the pack's `PackManifest`, and
+/// `POST /language-data/segment` with a `PackSegmentRequest` responds with just that segment.
+/// All three take a `PackQuery` in the query string to pick a channel or pinned version.
+/// `GET /images/{sha256}.jpg` responds with an image.
+pub fn router(store: Arc<PackStore>) -> Router {
+    Router::new()
+        .route(
+            backend_routes::LANGUAGE_DATA_PATH,
+            post(serve_language_data),
+        )
+        .route(
+            backend_routes::LANGUAGE_DATA_MANIFEST_PATH,
+            post(serve_manifest),
+        )
+        .route(
+            backend_routes::LANGUAGE_DATA_SEGMENT_PATH,
+            post(serve_segment),
+        )
+        .route(
+            &format!("{}/{{file_name}}", backend_routes::IMAGES_PATH),
+            get(serve_image),
+        )
+        .with_state(store)
+}
+
+fn octet_stream(bytes: Bytes) -> Response {
+    Response::builder()
+        .status(StatusCode::OK)
+        .header(header::CONTENT_TYPE, "application/octet-stream")
+        .header(header::CONTENT_LENGTH, bytes.len())
+        .body(Body::from(bytes))
+        .unwrap()
+}
+
+fn not_found() -> Response {
+    Response::builder()
+        .status(StatusCode::NOT_FOUND)
+        .body(Body::from("Not found"))
+        .unwrap()
+}
+
+fn unavailable(course: Course, e: PackStoreError) -> Response {
+    eprintln!("Error loading language data for {course:?}: {e:?}");
+    Response::builder()
+        .status(StatusCode::SERVICE_UNAVAILABLE)
+        .body(Body::from("Language data unavailable"))
+        .unwrap()
+}
+
+async fn serve_language_data(
+    State(store): State<Arc<PackStore>>,
+    Query(query): Query<PackQuery>,
+    Json(course): Json<Course>,
+) -> Response {
+    match store.get(course, &query).await {
+        Ok(Some(language_data)) => octet_stream(language_data),
+        Ok(None) => not_found(),
+        Err(e) => unavailable(course, e),
+    }
+}
+
+async fn serve_manifest(
+    State(store): State<Arc<PackStore>>,
+    Query(query): Query<PackQuery>,
+    Json(course): Json<Course>,
+) -> Response {
+    match store.manifest(course, &query).await {
+        Ok(Some(manifest)) => Json(manifest.as_ref().clone()).into_response(),
+        Ok(None) => not_found(),
+        Err(e) => unavailable(course, e),
+    }
+}
+
+async fn serve_segment(
+    State(store): State<Arc<PackStore>>,
+    Query(query): Query<PackQuery>,
+    Json(PackSegmentRequest { course, segment }): Json<PackSegmentRequest>,
+) -> Response {
+    let (language_data, manifest) = match (
+        store.get(course, &query).await,
+        store.manifest(course, &query).await,
+    ) {
+        (Ok(Some(language_data)), Ok(Some(manifest))) => (language_data, manifest),
+        (Err(e), _) | (_, Err(e)) => return unavailable(course, e),
+        _ => return not_found(),
+    };
+    if segment >= manifest.segment_sha256.len() {
+        return not_found();
+    }
+    octet_stream(language_data.slice(manifest.segment_range(segment)))
+}
+
+async fn serve_image(
+    State(store): State<Arc<PackStore>>,
+    Path(file_name): Path<String>,
+) -> Response {
+    match store.image(&file_name).await {
+        Ok(Some(image)) => Response::builder()
+            .status(StatusCode::OK)
+            .header(header::CONTENT_TYPE, "image/jpeg")
+            .header(header::CONTENT_LENGTH, image.len())
+            // Images are named by their hash, so they never change
+            .header(header::CACHE_CONTROL, "public, max-age=31536000, immutable")
+            .body(Body::from(image))
+            .unwrap(),
+        Ok(None) => not_found(),
+        Err(e) => {
+            eprintln!("Error loading image {file_name}: {e:?}");
+            Response::builder()
+                .status(StatusCode::SERVICE_UNAVAILABLE)
+                .body(Body::from("Image unavailable"))
+                .unwrap()
+        }
+    }
+}
@@ -0,0 +1,307 @@
+//! Bindings for native (iOS and Android) shells, generated with uniffi. They use the same
+//! `EventStore` as the wasm app, and the same sync requests and responses (see
+//! `weapon::sync_protocol`), so events written on any platform are the same JSON.
+//!
+//! The store holds every stream as JSON events (see `weapon::json_stream`), so the shells don't
+//! need Rust types for them. Network and storage are the shell's job: it sends the bodies this
+//! crate builds with its own HTTP client, and hands the responses back.
+//!
+//! Generate the bindings with
+//! `cargo run -p weapon-ffi --bin uniffi-bindgen -- generate --library <path to libweapon_ffi> --language swift --out-dir <dir>`
+//! (or `--language kotlin`).
+
+use std::ops::{Deref, DerefMut};
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use weapon::data_model::{Clock, EventStore, EventType, RejectedEvent, SyncTarget, Timestamped};
+use weapon::json_stream::{JsonEvent, JsonFold, JsonState};
+use weapon::sync_protocol;
+
+uniffi::setup_scaffolding!();
+
+#[derive(Debug, thiserror::Error, uniffi::Error)]
+pub enum WeaponError {
+    #[error("Invalid JSON: {message}")]
+    InvalidJson { message: String },
+
+    #[error("Event was not added: {message}")]
+    Append { message: String },
+
+    #[error("Unknown fold `{fold}`, expected `log` or `merge`")]
+    UnknownFold { fold: String },
+
+    #[error("Stream {stream} doesn't exist")]
+    UnknownStream { stream: String },
+}
+
+impl From<serde_json::Error> for WeaponError {
+    fn from(e: serde_json::Error) -> Self {
+        WeaponError::InvalidJson {
+            message: e.to_string(),
+        }
+    }
+}
+
+/// An event the server refused to store, see `weapon::data_model::RejectedEvent`
+#[derive(Debug, Clone, uniffi::Record)]
+pub struct Rejection {
+    pub stream: String,
+    pub device: String,
+    pub within_device_events_index: u64,
+    /// A `weapon::data_model::RejectionReason`, as JSON
+    pub reason: String,
+}
+
+impl TryFrom<RejectedEvent<String, String>> for Rejection {
+    type Error = WeaponError;
+
+    fn try_from(rejected: RejectedEvent<String, String>) -> Result<Self, Self::Error> {
+        Ok(Rejection {
+            reason: serde_json::to_string(&rejected.reason)?,
+            stream: rejected.stream,
+            device: rejected.device,
+            within_device_events_index: rejected.within_device_events_index as u64,
+        })
+    }
+}
+
+impl TryFrom<Rejection> for RejectedEvent<String, String> {
+    type Error = WeaponError;
+
+    fn try_from(rejection: Rejection) -> Result<Self, Self::Error> {
+        Ok(RejectedEvent {
+            reason: serde_json::from_str(&rejection.reason)?,
+            stream: rejection.stream,
+            device: rejection.device,
+            within_device_events_index: rejection.within_device_events_index as usize,
+        })
+    }
+}
+
+/// The `EventStore` itself isn't `Send`, because of its listeners and type-erased streams.
+struct Store(EventStore<String, String>);
+
+// SAFETY: the store is only reachable through `WeaponStore`'s mutex. The only parts of it that
+// aren't `Send` are listeners, which are never registered here, and `dyn StreamStore`s, which are
+// all JSON streams (strings all the way down), as that's the only schema that's registered.
+unsafe impl Send for Store {}
+
+impl Deref for Store {
+    type Target = EventStore<String, String>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl DerefMut for Store {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
+#[derive(uniffi::Object)]
+pub struct WeaponStore {
+    store: Mutex<Store>,
+}
+
+#[uniffi::export]
+impl WeaponStore {
+    #[uniffi::constructor]
+    pub fn new() -> Arc<Self> {
+        let mut store = EventStore::default();
+        // Every stream holds JSON events
+        store.register_schema::<EventType<JsonEvent>>("");
+        Arc::new(Self {
+            store: Mutex::new(Store(store)),
+        })
+    }
+
+    /// Adds an event from this device. `event` is the event's JSON, without the timestamp and
+    /// index, which are added here.
+    pub fn add_event(
+        &self,
+        stream: String,
+        device: String,
+        event: String,
+    ) -> Result<(), WeaponError> {
+        let event = JsonEvent::new(&serde_json::from_str(&event)?);
+        self.lock()
+            .add_raw_event(stream, device, event, None)
+            .map_err(|e| WeaponError::Append {
+                message: e.to_string(),
+            })
+    }
+
+    /// Adds events as saved by the shell (see `events`), e.g. when loading them from disk.
+    /// Returns how many were new.
+    pub fn add_saved_events(
+        &self,
+        stream: String,
+        device: String,
+        events: Vec<String>,
+    ) -> Result<u64, WeaponError> {
+        let events = events
+            .iter()
+            .map(|event| serde_json::from_str::<Timestamped<serde_json::Value>>(event))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(self
+            .lock()
+            .add_device_events_jsons(stream, device, events, None) as u64)
+    }
+
+    /// The device's events in `stream` after the first `skip`, each as the JSON of a
+    /// `Timestamped` event. This is what shells should save.
+    pub fn events(&self, stream: String, device: String, skip: u64) -> Vec<String> {
+        let store = self.lock();
+        let Some(stream) = store.get_raw(stream) else {
+            return Vec::new();
+        };
+        stream
+            .jsons(&device, skip as usize)
+            .iter()
+            .filter_map(|event| serde_json::to_string(event).ok())
+            .collect()
+    }
+
+    /// How many events each device has in each stream, as JSON
+    pub fn vector_clock(&self) -> Result<String, WeaponError> {
+        Ok(serde_json::to_string(&self.lock().vector_clock())?)
+    }
+
+    /// The state of `stream`, as JSON. `fold` is `log` or `merge`, see `JsonFold`.
+    pub fn state(&self, stream: String, fold: String) -> Result<String, WeaponError> {
+        let fold = fold
+            .parse::<JsonFold>()
+            .map_err(|_| WeaponError::UnknownFold { fold })?;
+        let store = self.lock();
+        let Some(events) = store.get::<EventType<JsonEvent>>(stream.clone()) else {
+            return Err(WeaponError::UnknownStream { stream });
+        };
+        let state: JsonState = events.state(JsonState::new(fold));
+        Ok(serde_json::to_string(&state.value)?)
+    }
+
+    /// The body to call the sync RPC with
+    pub fn sync_request(&self) -> Result<String, WeaponError> {
+        Ok(serde_json::to_string(&sync_protocol::sync_request(
+            &self.lock().vector_clock(),
+        ))?)
+    }
+
+    /// Adds the events from the sync RPC's response. Returns how many were new.
+    pub fn add_sync_response(&self, body: String) -> Result<u64, WeaponError> {
+        let response = sync_protocol::parse_sync_response(&body)?;
+        Ok(self.lock().add_sync_response(response, None) as u64)
+    }
+
+    /// The body to upload the events the server doesn't have with, given the clock RPC's
+    /// response. Events from a stream and device with a rejection in `rejected` are left out,
+    /// since the server can't store them (or anything after them).
+    pub fn upload_request(
+        &self,
+        user_id: String,
+        remote_clock: String,
+        rejected: Vec<Rejection>,
+    ) -> Result<String, WeaponError> {
+        let remote_clock: Clock<String, String> = serde_json::from_str(&remote_clock)?;
+        let mut events = self.lock().events_missing_from(&remote_clock, &user_id);
+        events.retain(|event| {
+            !rejected.iter().any(|rejection| {
+                rejection.stream == event.stream_id && rejection.device == event.device_id
+            })
+        });
+        Ok(serde_json::to_string(&events)?)
+    }
+
+    /// Records the clock RPC's response after a sync, and the events the server rejected
+    pub fn finish_sync(
+        &self,
+        remote_clock: String,
+        rejected: Vec<Rejection>,
+    ) -> Result<(), WeaponError> {
+        let remote_clock: Clock<String, String> = serde_json::from_str(&remote_clock)?;
+        let rejected = rejected
+            .into_iter()
+            .map(RejectedEvent::try_from)
+            .collect::<Result<Vec<_>, _>>()?;
+        let mut store = self.lock();
+        store.update_sync_clock(SyncTarget::Supabase, remote_clock);
+        store.record_rejected_events(SyncTarget::Supabase, rejected);
+        Ok(())
+    }
+
+    /// How many of each device's events the server had at the last sync, by stream, as JSON
+    pub fn remote_clock(&self) -> Result<String, WeaponError> {
+        let store = self.lock();
+        let clock = store
+            .sync_state(SyncTarget::Supabase)
+            .map(|state| state.remote_clock.clone())
+            .unwrap_or_default();
+        Ok(serde_json::to_string(&clock)?)
+    }
+}
+
+impl WeaponStore {
+    fn lock(&self) -> MutexGuard<'_, Store> {
+        // Nothing here leaves the store half-changed if it panics
+        self.store
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+/// The event the server rejected, if that's why an upload failed. `error_body` is the body of
+/// the failed upload's response.
+#[uniffi::export]
+pub fn parse_rejection(error_body: String) -> Result<Option<Rejection>, WeaponError> {
+    sync_protocol::parse_rejection(&error_body)
+        .map(Rejection::try_from)
+        .transpose()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_events_round_trip_through_the_shell() {
+        let phone = WeaponStore::new();
+        phone
+            .add_event(
+                "journal".to_string(),
+                "phone".to_string(),
+                r#"{"text": "bonjour"}"#.to_string(),
+            )
+            .unwrap();
+
+        let upload: Vec<serde_json::Value> = serde_json::from_str(
+            &phone
+                .upload_request("user".to_string(), "{}".to_string(), Vec::new())
+                .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(upload.len(), 1);
+        assert_eq!(upload[0]["event"]["event"]["User"]["text"], "bonjour");
+
+        // What the shell saves loads back into the same state
+        let saved = phone.events("journal".to_string(), "phone".to_string(), 0);
+        let reopened = WeaponStore::new();
+        let added = reopened
+            .add_saved_events("journal".to_string(), "phone".to_string(), saved)
+            .unwrap();
+        assert_eq!(added, 1);
+        assert_eq!(
+            reopened
+                .state("journal".to_string(), "log".to_string())
+                .unwrap(),
+            phone
+                .state("journal".to_string(), "log".to_string())
+                .unwrap()
+        );
+        assert!(matches!(
+            reopened.state("journal".to_string(), "sum".to_string()),
+            Err(WeaponError::UnknownFold { .. })
+        ));
+    }
+}
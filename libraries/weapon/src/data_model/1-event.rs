@@ -6,4 +6,32 @@
 pub trait Event: Sized + PartialOrd + Ord + Clone + Eq + PartialEq {
     fn to_json(&self) -> Result<serde_json::Value, serde_json::Error>;
     fn from_json(json: &serde_json::Value) -> Result<Self, serde_json::Error>;
+
+    /// Whether this is an event this version of the app doesn't understand (see `EventType::Unknown`)
+    fn is_unknown(&self) -> bool {
+        false
+    }
+
+    /// The meta event this is, if it is one (see `EventType::Meta`)
+    fn meta_event(&self) -> Option<&crate::data_model::MetaEvent> {
+        None
+    }
+
+    /// Checks the app's own invariants before the event is added on this device, so an event
+    /// that can never make sense is rejected instead of being persisted and synced everywhere.
+    /// Events from other devices aren't checked, since they're already persisted and a device's
+    /// events must be added in order.
+    fn validate(&self) -> Validation {
+        Validation::Accept
+    }
+}
+
+/// What `Event::validate` decided about an event
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Validation {
+    Accept,
+    /// The event is added, but looks like a mistake, so `note` is logged
+    Warn(String),
+    /// The event isn't added, and adding it fails with `AppendError::Rejected`
+    Reject(String),
 }
@@ -0,0 +1,110 @@
+//! # Quarantine
+//! A single event that can't be applied would make a stream's state impossible to compute, locking the user out of everything in it.
+//! Apps can refuse such events with `PartialAppState::check_event`, and they're skipped whenever events are replayed.
+//! `state_with_quarantine` also catches events whose `process_event` panics, and reports every skipped event so it can be repaired.
+//! Panics can only be caught where they unwind. Where they abort (like wasm, by default), `check_event` is the only protection.
+
+use std::any::Any;
+use std::collections::BTreeSet;
+use std::hash::Hash;
+use std::panic::{AssertUnwindSafe, catch_unwind};
+
+use crate::PartialAppState;
+use crate::data_model::{EventStreamStore, EventType, Timestamped};
+
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(target_arch = "wasm32", derive(tsify::Tsify))]
+#[cfg_attr(target_arch = "wasm32", tsify(into_wasm_abi, from_wasm_abi))]
+#[serde(rename_all = "camelCase")]
+pub struct QuarantinedEvent<Device> {
+    pub device: Device,
+    pub within_device_events_index: usize,
+    pub timestamp: chrono::DateTime<chrono::Utc>,
+    /// `check_event`'s error, or what `process_event` panicked with
+    pub reason: String,
+}
+
+impl<Device: Eq + Hash + Clone + Ord, Event: Ord + Clone + crate::Event>
+    EventStreamStore<Device, Timestamped<EventType<Event>>>
+{
+    /// Like `state`, but events that can't be applied are skipped rather than taking the state
+    /// down with them, and are returned in the order they'd have been applied in. When an event
+    /// panics, the events before it are replayed again from `initial_state`.
+    pub fn state_with_quarantine<A>(
+        &self,
+        initial_state: A::Partial,
+    ) -> (A, Vec<QuarantinedEvent<Device>>)
+    where
+        A: PartialAppState<Event = Event>,
+        A::Partial: Clone,
+    {
+        let mut events: Vec<(&Device, Timestamped<Event>)> = self
+            .events()
+            .iter()
+            .flat_map(|(device, events)| {
+                events.iter().filter_map(move |event| match &event.event {
+                    EventType::User(user_event) => Some((
+                        device,
+                        Timestamped {
+                            event: user_event.clone(),
+                            timestamp: event.timestamp,
+                            within_device_events_index: event.within_device_events_index,
+                        },
+                    )),
+                    EventType::Meta(_) | EventType::Unknown(_) => None,
+                })
+            })
+            .collect();
+        events.sort_by(|(device_a, a), (device_b, b)| a.cmp(b).then(device_a.cmp(device_b)));
+
+        let quarantine = |device: &Device, event: &Timestamped<Event>, reason| QuarantinedEvent {
+            device: device.clone(),
+            within_device_events_index: event.within_device_events_index,
+            timestamp: event.timestamp,
+            reason,
+        };
+        let mut quarantined = Vec::new();
+        // Positions in `events`, so they're skipped when replaying again
+        let mut skipped = BTreeSet::new();
+        'replay: loop {
+            let mut partial = initial_state.clone();
+            for (position, (device, event)) in events.iter().enumerate() {
+                if skipped.contains(&position) {
+                    continue;
+                }
+                if let Err(reason) = A::check_event(&partial, event) {
+                    log::error!("Quarantining event that can't be applied: {reason}");
+                    quarantined.push(quarantine(device, event, reason));
+                    skipped.insert(position);
+                    continue;
+                }
+                match catch_unwind(AssertUnwindSafe(|| A::process_event(partial, event))) {
+                    Ok(next) => partial = next,
+                    Err(payload) => {
+                        let reason = panic_message(&*payload);
+                        log::error!("Quarantining event that panicked: {reason}");
+                        quarantined.push(quarantine(device, event, reason));
+                        skipped.insert(position);
+                        continue 'replay;
+                    }
+                }
+            }
+            quarantined.sort_by_key(|event| {
+                (
+                    event.timestamp,
+                    event.within_device_events_index,
+                    event.device.clone(),
+                )
+            });
+            return (A::finalize(partial), quarantined);
+        }
+    }
+}
+
+fn panic_message(payload: &(dyn Any + Send)) -> String {
+    payload
+        .downcast_ref::<&str>()
+        .map(|message| message.to_string())
+        .or_else(|| payload.downcast_ref::<String>().cloned())
+        .unwrap_or_else(|| "process_event panicked".to_string())
+}
@@ -0,0 +1,151 @@
+//! # Duplicates
+//! A sync bug once uploaded some events twice, so they were counted twice whenever the stream was replayed (e.g. one review counting as two).
+//! A duplicate is an event with the same device, timestamp and payload as an earlier event from that device, or an event whose index the device already used.
+//! `EventStore::remove_duplicates` repairs a stream in memory, and `EventStore::rewrite_local_storage` writes the repaired stream over its event log.
+//! Each device's indices have to stay contiguous (see `Timestamped`), so a duplicate with an index of its own is replaced with a `MetaEvent::Duplicate` instead of being removed.
+//! Only an event whose index was already taken is dropped.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::hash::Hash;
+
+use crate::data_model::{
+    EventStore, EventStreamStore, EventType, ListenerKey, MetaEvent, Timestamped,
+};
+
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(target_arch = "wasm32", derive(tsify::Tsify))]
+#[cfg_attr(target_arch = "wasm32", tsify(into_wasm_abi, from_wasm_abi))]
+#[serde(rename_all = "camelCase")]
+pub struct DuplicateEvent<Device> {
+    pub device: Device,
+    pub within_device_events_index: usize,
+    pub timestamp: chrono::DateTime<chrono::Utc>,
+    /// The index of the event this one duplicates. Equal to `within_device_events_index` if the
+    /// index was already used by another event.
+    pub duplicate_of: usize,
+}
+
+impl<Device: Eq + Hash + Clone + Ord, Event: Ord + Clone + crate::Event>
+    EventStreamStore<Device, Timestamped<Event>>
+{
+    /// The loaded events that duplicate another, by device and then index. Meta events are never
+    /// duplicates, so a repaired stream has none.
+    pub fn duplicates(&self) -> Vec<DuplicateEvent<Device>> {
+        let mut duplicates = Vec::new();
+        for (device, events) in self.events() {
+            let mut used_indices = BTreeSet::new();
+            let mut first_with_payload = HashMap::new();
+            for event in by_index(events) {
+                let index = event.within_device_events_index;
+                let duplicate_of = if !used_indices.insert(index) {
+                    Some(index)
+                } else if event.event.meta_event().is_some() {
+                    None
+                } else {
+                    let Ok(payload) = event.event.to_json() else {
+                        continue;
+                    };
+                    let first = *first_with_payload
+                        .entry((event.timestamp, payload.to_string()))
+                        .or_insert(index);
+                    (first != index).then_some(first)
+                };
+                if let Some(duplicate_of) = duplicate_of {
+                    duplicates.push(DuplicateEvent {
+                        device: device.clone(),
+                        within_device_events_index: index,
+                        timestamp: event.timestamp,
+                        duplicate_of,
+                    });
+                }
+            }
+        }
+        duplicates.sort_by(|a, b| {
+            (&a.device, a.within_device_events_index)
+                .cmp(&(&b.device, b.within_device_events_index))
+        });
+        duplicates
+    }
+
+    /// Drops the events whose index was already used, replaces the other duplicates with a
+    /// `MetaEvent::Duplicate`, and returns what was removed. `None` (with nothing changed) if the
+    /// stream's event type can't hold meta events.
+    pub fn remove_duplicates(&mut self) -> Option<Vec<DuplicateEvent<Device>>> {
+        let duplicates = self.duplicates();
+        let mut replacements = BTreeMap::new();
+        for duplicate in &duplicates {
+            if duplicate.duplicate_of == duplicate.within_device_events_index {
+                continue;
+            }
+            let json =
+                serde_json::to_value(EventType::<serde_json::Value>::Meta(MetaEvent::Duplicate {
+                    of: duplicate.duplicate_of,
+                }))
+                .ok()?;
+            let replacement = Event::from_json(&json)
+                .ok()
+                .filter(|event| event.meta_event().is_some())?;
+            replacements.insert(
+                (
+                    duplicate.device.clone(),
+                    duplicate.within_device_events_index,
+                ),
+                replacement,
+            );
+        }
+
+        for (device, events) in self.events_mut() {
+            let mut used_indices = BTreeSet::new();
+            let old_events = by_index(events).cloned().collect::<Vec<_>>();
+            events.clear();
+            for mut event in old_events {
+                let index = event.within_device_events_index;
+                if !used_indices.insert(index) {
+                    continue;
+                }
+                if let Some(replacement) = replacements.remove(&(device.clone(), index)) {
+                    event.event = replacement;
+                }
+                events.insert(event);
+            }
+        }
+        Some(duplicates)
+    }
+}
+
+/// A device's events by index. Events with the same index stay in the order the set has them,
+/// so the one kept is always the same.
+fn by_index<Event: Ord>(
+    events: &BTreeSet<Timestamped<Event>>,
+) -> impl Iterator<Item = &Timestamped<Event>> {
+    let mut events = events.iter().collect::<Vec<_>>();
+    events.sort_by_key(|event| event.within_device_events_index);
+    events.into_iter()
+}
+
+impl<Stream, Device> EventStore<Stream, Device>
+where
+    Stream: Eq + Hash + Clone + Ord,
+    Device: Eq + Hash + Clone + Ord + 'static,
+{
+    /// The loaded events in `stream` that duplicate another, see `EventStreamStore::duplicates`
+    pub fn duplicates(&self, stream: Stream) -> Vec<DuplicateEvent<Device>> {
+        self.get_raw(stream)
+            .map(|store| store.duplicates())
+            .unwrap_or_default()
+    }
+
+    /// Repairs `stream`'s duplicates in memory and returns them, see
+    /// `EventStreamStore::remove_duplicates`. Duplicates among archived events that aren't loaded
+    /// are missed. `None` if the stream doesn't exist or can't hold meta events.
+    pub fn remove_duplicates(
+        &mut self,
+        stream: &Stream,
+        modifier: Option<ListenerKey>,
+    ) -> Option<Vec<DuplicateEvent<Device>>> {
+        if self.get_raw(stream.clone())?.duplicates().is_empty() {
+            return Some(Vec::new());
+        }
+        self.get_mut_raw(stream, modifier)?.remove_duplicates()
+    }
+}
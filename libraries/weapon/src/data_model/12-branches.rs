@@ -0,0 +1,250 @@
+//! # Branches
+//! A branch is a local copy of a stream to experiment in (e.g. trying a different scheduler for a week) without changing the real history.
+//! `EventStore::fork_stream` copies the stream's events into a new stream, named with `branch_stream_id`, and marks where the copy ends with a `MetaEvent::Fork`. New events can then be added to the branch like to any other stream.
+//! Branches are saved locally like any other stream, but are never uploaded, so other devices never see them.
+//! When the experiment is over, `compare_branch` computes both states, and the branch is either thrown away with `discard_branch` or kept with `merge_branch`.
+//! Merging adds the branch's new events to the original stream as new events from the merging device, so to other devices they're just normal events.
+
+use std::collections::BTreeMap;
+use std::hash::Hash;
+
+use crate::data_model::{AppendError, EventStore, EventType, ListenerKey, MetaEvent, Timestamped};
+
+/// Separates a branch's name from the ID of the stream it was forked from. Schemas are matched by
+/// prefix, so a branch holds the same events as the original stream.
+pub const BRANCH_SEPARATOR: &str = "~";
+
+/// The ID of `stream`'s branch called `name`
+pub fn branch_stream_id(stream: &str, name: &str) -> String {
+    format!("{stream}{BRANCH_SEPARATOR}{name}")
+}
+
+/// The stream `branch` was forked from, or `None` if it isn't a branch
+pub fn branch_base(branch: &str) -> Option<&str> {
+    branch
+        .split_once(BRANCH_SEPARATOR)
+        .map(|(stream, _)| stream)
+}
+
+/// Branches are local, so sync targets other than local storage should skip them
+pub fn is_branch_stream(stream: &str) -> bool {
+    branch_base(stream).is_some()
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum BranchError {
+    #[error("Stream {stream} doesn't exist")]
+    UnknownStream { stream: String },
+
+    #[error("{stream} is a branch, and branches can't be forked")]
+    AlreadyABranch { stream: String },
+
+    #[error("{stream} isn't a branch")]
+    NotABranch { stream: String },
+
+    #[error("Branch names can't be empty or contain `{BRANCH_SEPARATOR}`, but got `{name}`")]
+    InvalidName { name: String },
+
+    #[error("Branch {branch} already exists")]
+    AlreadyExists { branch: String },
+
+    /// Copying archived events that aren't loaded isn't possible, see `StreamArchive`
+    #[error("Stream {stream} has archived events that aren't loaded")]
+    NotFullyLoaded { stream: String },
+
+    #[error(transparent)]
+    Append(#[from] AppendError),
+}
+
+/// The states of a branch and of the stream it was forked from, see `EventStore::compare_branch`
+pub struct BranchStates<A> {
+    pub base: A,
+    pub branch: A,
+}
+
+impl<Stream, Device> EventStore<Stream, Device>
+where
+    Stream: Eq + Hash + Clone + Ord + AsRef<str> + From<String>,
+    Device: Eq + Hash + Clone + Ord + AsRef<str> + From<String> + 'static,
+{
+    /// Copies `stream` into a new branch called `name`, and returns the branch's ID. `author` is
+    /// the device recording the fork.
+    pub fn fork_stream(
+        &mut self,
+        author: Device,
+        stream: &Stream,
+        name: &str,
+        modifier: Option<ListenerKey>,
+    ) -> Result<Stream, BranchError> {
+        if is_branch_stream(stream.as_ref()) {
+            return Err(BranchError::AlreadyABranch {
+                stream: stream.as_ref().to_string(),
+            });
+        }
+        if name.is_empty() || name.contains(BRANCH_SEPARATOR) {
+            return Err(BranchError::InvalidName {
+                name: name.to_string(),
+            });
+        }
+        let branch = Stream::from(branch_stream_id(stream.as_ref(), name));
+        if self.get_raw(branch.clone()).is_some() {
+            return Err(BranchError::AlreadyExists {
+                branch: branch.as_ref().to_string(),
+            });
+        }
+        let Some(base) = self.get_raw(stream.clone()) else {
+            return Err(BranchError::UnknownStream {
+                stream: stream.as_ref().to_string(),
+            });
+        };
+        if !base.archived_per_device().is_empty() {
+            return Err(BranchError::NotFullyLoaded {
+                stream: stream.as_ref().to_string(),
+            });
+        }
+
+        let clock = base
+            .num_events_per_device()
+            .into_iter()
+            .map(|(device, count)| (device.clone(), count))
+            .collect::<BTreeMap<_, _>>();
+        let mut copy = base.new_empty();
+        for device in clock.keys() {
+            let Some(valid_to_add) = copy.valid_to_add_event_jsons(device, base.jsons(device, 0))
+            else {
+                continue;
+            };
+            copy.add_device_event_jsons(device.clone(), valid_to_add)
+                .map_err(AppendError::Deserialize)?;
+        }
+
+        let fork = Timestamped {
+            timestamp: chrono::Utc::now(),
+            within_device_events_index: clock.get(&author).copied().unwrap_or(0),
+            event: serde_json::to_value(EventType::<serde_json::Value>::Meta(MetaEvent::Fork {
+                from: stream.as_ref().to_string(),
+                clock: clock
+                    .iter()
+                    .map(|(device, count)| (device.as_ref().to_string(), *count))
+                    .collect(),
+            }))
+            .map_err(AppendError::Serialize)?,
+        };
+        if let Some(valid_to_add) = copy.valid_to_add_event_jsons(&author, vec![fork]) {
+            copy.add_device_event_jsons(author, valid_to_add)
+                .map_err(AppendError::Deserialize)?;
+        }
+
+        self.insert_stream(branch.clone(), copy, modifier);
+        Ok(branch)
+    }
+
+    /// The branches forked from `stream`
+    pub fn branches(&self, stream: &Stream) -> Vec<Stream> {
+        let mut branches = self
+            .iter()
+            .filter(|(branch, _)| branch_base(branch.as_ref()) == Some(stream.as_ref()))
+            .map(|(branch, _)| branch.clone())
+            .collect::<Vec<_>>();
+        branches.sort();
+        branches
+    }
+
+    /// The state of `branch` and of the stream it was forked from, each replayed from
+    /// `initial_state`. `None` if either doesn't exist.
+    pub fn compare_branch<Event, A>(
+        &self,
+        branch: &Stream,
+        initial_state: A::Partial,
+    ) -> Option<BranchStates<A>>
+    where
+        Event: Ord + Clone + crate::Event + 'static,
+        A: crate::PartialAppState<Event = Event>,
+        A::Partial: Clone,
+    {
+        let base = Stream::from(branch_base(branch.as_ref())?.to_string());
+        Some(BranchStates {
+            base: self
+                .get::<EventType<Event>>(base)?
+                .state(initial_state.clone()),
+            branch: self
+                .get::<EventType<Event>>(branch.clone())?
+                .state(initial_state),
+        })
+    }
+
+    /// Throws `branch` away. Returns false if there was no such branch. Local storage still has
+    /// its events until they're deleted there too (e.g. with `delete_from_local_storage`).
+    pub fn discard_branch(&mut self, branch: &Stream) -> bool {
+        is_branch_stream(branch.as_ref()) && self.remove_stream(branch)
+    }
+
+    /// Adds the events `branch` got after it was forked to the stream it was forked from, as new
+    /// events from `author`, and then discards the branch. The events keep their timestamps, so
+    /// they're replayed in the same order as on the branch, interleaved with anything the original
+    /// stream got in the meantime. Returns how many were added.
+    pub fn merge_branch(
+        &mut self,
+        author: Device,
+        branch: &Stream,
+        modifier: Option<ListenerKey>,
+    ) -> Result<usize, BranchError> {
+        let not_a_branch = || BranchError::NotABranch {
+            stream: branch.as_ref().to_string(),
+        };
+        let base = Stream::from(
+            branch_base(branch.as_ref())
+                .ok_or_else(not_a_branch)?
+                .to_string(),
+        );
+        let Some(branch_store) = self.get_raw(branch.clone()) else {
+            return Err(not_a_branch());
+        };
+        let fork_clock = branch_store.fork_clock().ok_or_else(not_a_branch)?;
+
+        let mut new_events = branch_store
+            .num_events_per_device()
+            .into_keys()
+            .flat_map(|device| {
+                let forked_with = fork_clock.get(device.as_ref()).copied().unwrap_or(0);
+                branch_store.jsons(device, forked_with)
+            })
+            .filter(|event| {
+                !matches!(
+                    serde_json::from_value::<EventType<serde_json::Value>>(event.event.clone()),
+                    Ok(EventType::Meta(_))
+                )
+            })
+            .collect::<Vec<_>>();
+        new_events.sort_by_key(|event| event.timestamp);
+
+        let Some(store) = self.get_mut_raw(&base, modifier) else {
+            return Err(BranchError::UnknownStream {
+                stream: base.as_ref().to_string(),
+            });
+        };
+        let first_index = store
+            .num_events_per_device()
+            .get(&author)
+            .copied()
+            .unwrap_or(0);
+        let new_events = new_events
+            .into_iter()
+            .enumerate()
+            .map(|(offset, event)| Timestamped {
+                within_device_events_index: first_index + offset,
+                ..event
+            })
+            .collect::<Vec<_>>();
+        let mut merged = 0;
+        if let Some(valid_to_add) = store.valid_to_add_event_jsons(&author, new_events) {
+            let mut store = store;
+            merged = store
+                .add_device_event_jsons(author, valid_to_add)
+                .map_err(AppendError::Deserialize)?;
+        }
+
+        self.remove_stream(branch);
+        Ok(merged)
+    }
+}
@@ -0,0 +1,74 @@
+//! # Stream lifecycle
+//! A stream is created as soon as anything touches it (events from a sync, a listener asking for it, `get_or_insert_default`), usually before its events have been read from local storage.
+//! Until the app calls `EventStore::mark_loaded`, its state may be missing most of its events, so the app shouldn't show it, or save it over what's on disk.
+//! Marking it loaded notifies the listeners once, so they can compute the state from the complete stream, and the stream is ready once they've been told.
+//!
+//! `StreamLifecycle` is the state machine, and `StreamStatus` is what the app sees of it (with `Missing` for streams that don't exist).
+
+/// Something that happens to a stream, see `StreamLifecycle::next`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LifecycleEvent {
+    /// The app finished reading the stream from local storage (`EventStore::mark_loaded`)
+    Loaded,
+    /// The stream's changes were handed to the listeners (`EventStore::drain_due_notifications`)
+    Notified,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum StreamLifecycle {
+    /// Created, but not loaded from local storage yet
+    #[default]
+    Created,
+    /// Loaded, but the listeners haven't been told yet
+    Loaded,
+    /// Loaded, and the listeners have been told
+    Ready,
+}
+
+impl StreamLifecycle {
+    pub fn next(self, event: LifecycleEvent) -> Self {
+        use LifecycleEvent as E;
+        use StreamLifecycle as S;
+        match (self, event) {
+            (S::Created, E::Loaded) => S::Loaded,
+            // Listeners told about events that arrived before the load still have to hear about it
+            (S::Created, E::Notified) => S::Created,
+            (S::Loaded, E::Loaded) => S::Loaded,
+            (S::Loaded, E::Notified) => S::Ready,
+            // Loading is only announced once
+            (S::Ready, E::Loaded) => S::Ready,
+            (S::Ready, E::Notified) => S::Ready,
+        }
+    }
+
+    pub fn is_loaded(self) -> bool {
+        match self {
+            StreamLifecycle::Created => false,
+            StreamLifecycle::Loaded | StreamLifecycle::Ready => true,
+        }
+    }
+
+    pub fn status(self) -> StreamStatus {
+        match self {
+            StreamLifecycle::Created => StreamStatus::Loading,
+            StreamLifecycle::Loaded => StreamStatus::Loaded,
+            StreamLifecycle::Ready => StreamStatus::Ready,
+        }
+    }
+}
+
+/// What the app should show for a stream, see `EventStore::stream_status`
+#[derive(Clone, Copy, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(target_arch = "wasm32", derive(tsify::Tsify))]
+#[cfg_attr(target_arch = "wasm32", tsify(into_wasm_abi, from_wasm_abi))]
+#[serde(rename_all = "camelCase")]
+pub enum StreamStatus {
+    /// Nothing has created the stream yet
+    Missing,
+    /// The stream exists, but may be missing events that are still being loaded
+    Loading,
+    /// Loaded, and the listeners are about to hear about it
+    Loaded,
+    /// Loaded, and the listeners have been told
+    Ready,
+}
@@ -0,0 +1,96 @@
+//! # Sync health
+//! Whether a sync target is up to date, so every part of the app that shows it (the sync indicator, settings, diagnostics) agrees on what "out of date" means.
+//!
+//! A target is green when nothing has been waiting to sync for long, yellow when something has or the last sync failed, and red once events have waited long enough that losing the device would lose real progress, or the target refused events outright.
+
+use std::hash::Hash;
+
+use crate::data_model::{EventStore, SyncTarget};
+
+/// Events waiting longer than this make a target yellow
+pub const SYNC_STALE_AFTER: chrono::Duration = chrono::Duration::minutes(10);
+/// Events waiting longer than this make a target red
+pub const SYNC_OUT_OF_DATE_AFTER: chrono::Duration = chrono::Duration::days(1);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(target_arch = "wasm32", derive(tsify::Tsify))]
+#[cfg_attr(target_arch = "wasm32", tsify(into_wasm_abi, from_wasm_abi))]
+#[serde(rename_all = "camelCase")]
+pub enum SyncHealthStatus {
+    /// Nothing has been waiting to sync for longer than `SYNC_STALE_AFTER`
+    Green,
+    /// Something has, or the last sync failed
+    Yellow,
+    /// Something has been waiting for longer than `SYNC_OUT_OF_DATE_AFTER`, or the target refused
+    /// events (see `SyncState::rejected_events`)
+    Red,
+}
+
+/// How up to date a sync target is, see `EventStore::sync_health`
+#[derive(Clone, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(target_arch = "wasm32", derive(tsify::Tsify))]
+#[cfg_attr(target_arch = "wasm32", tsify(into_wasm_abi, from_wasm_abi))]
+#[serde(rename_all = "camelCase")]
+pub struct SyncHealth {
+    pub target: SyncTarget,
+    pub status: SyncHealthStatus,
+    /// Events the target doesn't have yet, as of the last sync with it
+    pub pending_events: usize,
+    /// When the oldest of `pending_events` was created
+    pub earliest_unsynced_event: Option<chrono::DateTime<chrono::Utc>>,
+    /// How long the oldest of `pending_events` has been waiting. 0 if there are none.
+    pub staleness_seconds: i64,
+    pub last_successful_sync: Option<chrono::DateTime<chrono::Utc>>,
+    /// `None` if the target was never synced with successfully
+    pub seconds_since_last_successful_sync: Option<i64>,
+    pub syncing: bool,
+    pub last_sync_error: Option<String>,
+    pub rejected_events: usize,
+}
+
+impl<Stream: Eq + Hash + Clone + Ord + AsRef<str>, Device: Eq + Hash + Clone + Ord + 'static>
+    EventStore<Stream, Device>
+{
+    pub fn sync_health(
+        &self,
+        target: SyncTarget,
+        now: chrono::DateTime<chrono::Utc>,
+    ) -> SyncHealth {
+        let sync_state = self.sync_state(target);
+        let earliest_unsynced_event = self.get_timestamp_of_earliest_unsynced_event(target);
+        let staleness = earliest_unsynced_event
+            .map(|earliest| (now - earliest).max(chrono::Duration::zero()))
+            .unwrap_or_else(chrono::Duration::zero);
+        let last_successful_sync = sync_state.and_then(|state| state.last_successful_sync);
+        let last_sync_error = sync_state.and_then(|state| state.last_sync_error.clone());
+        let rejected_events = sync_state.map_or(0, |state| state.rejected_events.len());
+        let syncing = sync_state.is_some_and(|state| match state.last_sync_started {
+            Some(started) => state
+                .last_sync_finished
+                .is_none_or(|finished| started > finished),
+            None => false,
+        });
+
+        let status = if staleness > SYNC_OUT_OF_DATE_AFTER || rejected_events > 0 {
+            SyncHealthStatus::Red
+        } else if staleness > SYNC_STALE_AFTER || last_sync_error.is_some() {
+            SyncHealthStatus::Yellow
+        } else {
+            SyncHealthStatus::Green
+        };
+
+        SyncHealth {
+            target,
+            status,
+            pending_events: self.num_unsynced_events(target),
+            earliest_unsynced_event,
+            staleness_seconds: staleness.num_seconds(),
+            last_successful_sync,
+            seconds_since_last_successful_sync: last_successful_sync
+                .map(|last| (now - last).num_seconds()),
+            syncing,
+            last_sync_error,
+            rejected_events,
+        }
+    }
+}
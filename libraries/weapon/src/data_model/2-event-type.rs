@@ -1,11 +1,33 @@
 //! # EventType
 //! For more flexibility, we split events into "User events" and "Meta events".
 //! User events are determined by application developer, and will typically be created by user actions.
-//! Meta events are reserved for internal use. Currently, there are no meta events.
-//! But they will be used for things like naming the device and storing other metadata.
+//! Meta events are reserved for internal use, for metadata about the event log itself (like which
+//! devices are really the same one). They don't affect the app's state.
+//!
+//! Events written by a newer version of the app may not be understood by an older one. Rather than
+//! failing (which would halt syncing, since events from a device must be added in order), those are
+//! kept as `Unknown`. They're skipped when computing state, but are stored and synced unchanged, so
+//! nothing is lost once the app is updated.
+
+use std::collections::BTreeMap;
 
 #[derive(Clone, Debug, Eq, PartialEq, Ord, PartialOrd, serde::Serialize, serde::Deserialize)]
-pub enum MetaEvent {}
+pub enum MetaEvent {
+    /// `from`'s events are part of `into`'s history, e.g. because the app was reinstalled and got
+    /// a new device ID. Nothing is rewritten (`from`'s events keep their device and indices, so
+    /// syncing is unaffected), they're just reported as `into`'s. See `EventStore::merge_device`.
+    MergeDevice { from: String, into: String },
+    /// Stands in for an event that duplicated the same device's event at index `of` (see
+    /// `EventStreamStore::duplicates`). The duplicate can't just be removed, since the device's
+    /// indices have to stay contiguous.
+    Duplicate { of: usize },
+    /// Starts a branch (see `EventStore::fork_stream`): the events before it are a copy of
+    /// `from`'s, `clock` of each device's. Only written to branch streams, which aren't synced.
+    Fork {
+        from: String,
+        clock: BTreeMap<String, usize>,
+    },
+}
 
 #[derive(Clone, Debug, Eq, PartialEq, Ord, PartialOrd, serde::Serialize, serde::Deserialize)]
 #[cfg_attr(target_arch = "wasm32", derive(tsify::Tsify))]
@@ -13,6 +35,8 @@ pub enum MetaEvent {}
 pub enum EventType<E> {
     User(E),
     Meta(MetaEvent),
+    /// The original JSON of an event that couldn't be deserialized
+    Unknown(String),
 }
 
 impl<E> EventType<E> {
@@ -20,6 +44,7 @@ impl<E> EventType<E> {
         match self {
             EventType::User(e) => EventType::User(f(e)),
             EventType::Meta(e) => EventType::Meta(e),
+            EventType::Unknown(json) => EventType::Unknown(json),
         }
     }
 }
@@ -29,18 +54,48 @@ impl<E, Error> EventType<Result<E, Error>> {
         match self {
             EventType::User(e) => e.map(EventType::User),
             EventType::Meta(e) => Ok(EventType::Meta(e)),
+            EventType::Unknown(json) => Ok(EventType::Unknown(json)),
         }
     }
 }
 
 impl<E: crate::Event> crate::Event for EventType<E> {
     fn to_json(&self) -> Result<serde_json::Value, serde_json::Error> {
+        if let EventType::Unknown(json) = self {
+            return serde_json::from_str(json);
+        }
         let s = self.clone().map(|e| e.to_json()).transpose()?;
         serde_json::to_value(&s)
     }
 
     fn from_json(json: &serde_json::Value) -> Result<Self, serde_json::Error> {
-        let s = serde_json::from_value::<EventType<serde_json::Value>>(json.clone())?;
-        s.map(|e| E::from_json(&e)).transpose()
+        let parsed = serde_json::from_value::<EventType<serde_json::Value>>(json.clone())
+            .and_then(|s| s.map(|e| E::from_json(&e)).transpose());
+        match parsed {
+            // An `Unknown` wrapper is never written, so seeing one means the JSON is from elsewhere
+            Ok(EventType::Unknown(_)) | Err(_) => {
+                log::warn!("Keeping event that this version doesn't understand: `{json}`");
+                Ok(EventType::Unknown(json.to_string()))
+            }
+            Ok(event) => Ok(event),
+        }
+    }
+
+    fn is_unknown(&self) -> bool {
+        matches!(self, EventType::Unknown(_))
+    }
+
+    fn meta_event(&self) -> Option<&MetaEvent> {
+        match self {
+            EventType::Meta(event) => Some(event),
+            EventType::User(_) | EventType::Unknown(_) => None,
+        }
+    }
+
+    fn validate(&self) -> crate::data_model::Validation {
+        match self {
+            EventType::User(event) => event.validate(),
+            EventType::Meta(_) | EventType::Unknown(_) => crate::data_model::Validation::Accept,
+        }
     }
 }
@@ -11,25 +11,46 @@ use crate::data_model::{EventType, Timestamped};
 #[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
 pub struct EventStreamStore<Device: Eq + Clone + Hash, Event: Ord + Clone> {
     events: HashMap<Device, BTreeSet<Event>>,
+    /// Per device, how many of its first events are in an archive and aren't loaded (see
+    /// `StreamArchive`). They still count as the device's events, so indices and sync clocks are
+    /// the same as if they were loaded.
+    #[serde(default)]
+    archived: HashMap<Device, usize>,
 }
 
 impl<Device: Eq + Clone + Hash, Event: Ord + Clone> EventStreamStore<Device, Event> {
     pub fn events(&self) -> &HashMap<Device, BTreeSet<Event>> {
         &self.events
     }
+
+    pub(crate) fn events_mut(&mut self) -> &mut HashMap<Device, BTreeSet<Event>> {
+        &mut self.events
+    }
+
+    /// Per device, how many of its first events are archived and not loaded
+    pub fn archived(&self) -> &HashMap<Device, usize> {
+        &self.archived
+    }
+
+    /// Whether no events are archived without being loaded, so `state` covers the whole history
+    pub fn is_fully_loaded(&self) -> bool {
+        self.archived.values().all(|count| *count == 0)
+    }
 }
 
 impl<Device: Eq + Hash + Clone, Event: Ord + Clone> Default for EventStreamStore<Device, Event> {
     fn default() -> Self {
         Self {
             events: HashMap::new(),
+            archived: HashMap::new(),
         }
     }
 }
 
 impl<Device: Eq + Hash + Clone, Event: Ord + Clone> EventStreamStore<Device, Timestamped<Event>> {
     pub fn len_device(&self, device: &Device) -> usize {
-        self.events.get(device).map(|set| set.len()).unwrap_or(0)
+        self.archived.get(device).copied().unwrap_or(0)
+            + self.events.get(device).map(|set| set.len()).unwrap_or(0)
     }
 
     pub(crate) fn valid_to_add_events<A>(
@@ -55,7 +76,7 @@ impl<Device: Eq + Hash + Clone, Event: Ord + Clone> EventStreamStore<Device, Tim
         }
 
         // Check that the lowest event has the index of the current length
-        let expected_index = self.events().get(key).map(BTreeSet::len).unwrap_or(0);
+        let expected_index = self.len_device(key);
         if events[0].within_device_events_index != expected_index {
             log::warn!(
                 "Event out of order - expected index {}, got {}",
@@ -89,6 +110,47 @@ impl<Device: Eq + Hash + Clone, Event: Ord + Clone> EventStreamStore<Device, Tim
 
         events_added
     }
+
+    /// Drops the first `count` events of each device from memory, because they're in an archive.
+    /// Events that are already unloaded stay unloaded.
+    pub(crate) fn unload_archived(&mut self, counts: impl IntoIterator<Item = (Device, usize)>) {
+        for (device, count) in counts {
+            let archived = self.archived.entry(device.clone()).or_insert(0);
+            if count <= *archived {
+                continue;
+            }
+            *archived = count;
+            if let Some(events) = self.events.get_mut(&device) {
+                events.retain(|event| event.within_device_events_index >= count);
+            }
+        }
+    }
+
+    /// Puts a device's archived events back. `events` has to have all of them (extra events are
+    /// ignored), or none are loaded. Returns how many were loaded.
+    pub(crate) fn load_archived(
+        &mut self,
+        device: Device,
+        mut events: Vec<Timestamped<Event>>,
+    ) -> usize {
+        let Some(&archived) = self.archived.get(&device) else {
+            return 0;
+        };
+        events.retain(|event| event.within_device_events_index < archived);
+        events.sort_by_key(|event| event.within_device_events_index);
+        events.dedup_by_key(|event| event.within_device_events_index);
+        if events.len() != archived {
+            log::warn!(
+                "Archive has {} of {archived} archived events, so it can't be loaded",
+                events.len()
+            );
+            return 0;
+        }
+
+        self.archived.remove(&device);
+        self.events.entry(device).or_default().extend(events);
+        archived
+    }
 }
 
 impl<K: Eq + Hash + Clone, T: Ord + Clone> EventStreamStore<K, T> {
@@ -130,8 +192,9 @@ impl<K: Eq + Hash + Clone, T: Ord + Clone> EventStreamStore<K, T> {
         })
     }
 
+    /// Including archived events that aren't loaded
     pub fn num_events(&self) -> usize {
-        self.events.values().flatten().count()
+        self.events.values().flatten().count() + self.archived.values().sum::<usize>()
     }
 
     #[allow(unused)]
@@ -142,6 +205,7 @@ impl<K: Eq + Hash + Clone, T: Ord + Clone> EventStreamStore<K, T> {
                 .into_iter()
                 .map(|(k, vs)| (k, vs.into_iter().map(f.clone()).collect::<BTreeSet<U>>()))
                 .collect(),
+            archived: self.archived,
         }
     }
 }
@@ -149,18 +213,25 @@ impl<K: Eq + Hash + Clone, T: Ord + Clone> EventStreamStore<K, T> {
 impl<Device: Eq + Hash + Clone, Event: Ord + Clone + crate::Event>
     EventStreamStore<Device, Timestamped<EventType<Event>>>
 {
+    /// Replays the loaded events. If some are archived and not loaded (see `is_fully_loaded`),
+    /// that's only the tail of the history.
     pub fn state<A>(&self, initial_state: A::Partial) -> A
     where
         A: crate::PartialAppState<Event = Event>,
     {
-        apply_events_and_metaevents(self.iter(), initial_state)
+        // Finalize once at the end
+        A::finalize(process_events_and_metaevents::<Event, A>(
+            self.iter(),
+            initial_state,
+        ))
     }
 }
 
-pub(crate) fn apply_events_and_metaevents<'a, E: crate::data_model::Event + 'a, A>(
+/// Processes the events without finalizing the state, skipping the ones that aren't the app's
+pub(crate) fn process_events_and_metaevents<'a, E: crate::data_model::Event + 'a, A>(
     events: impl Iterator<Item = &'a Timestamped<EventType<E>>>,
-    initial_state: A::Partial,
-) -> A
+    partial: A::Partial,
+) -> A::Partial
 where
     A: crate::PartialAppState<Event = E>,
 {
@@ -176,28 +247,39 @@ where
                 timestamp,
                 within_device_events_index,
             }),
-            _ => unimplemented!(), // todo: remove?
+            // Written by a newer version of the app, so we can't apply it
+            Timestamped {
+                event: EventType::Unknown(_),
+                ..
+            } => None,
+            // Meta events are about the event log, not the app's state
+            Timestamped {
+                event: EventType::Meta(_),
+                ..
+            } => None,
         })
         .collect::<Vec<_>>();
 
-    apply_events(events.iter(), initial_state)
+    process_events::<E, A>(events.iter(), partial)
 }
 
-pub(crate) fn apply_events<'a, E: crate::data_model::Event + 'a, A>(
+pub(crate) fn process_events<'a, E: crate::data_model::Event + 'a, A>(
     events: impl Iterator<Item = &'a Timestamped<E>>,
-    initial_state: A::Partial,
-) -> A
+    partial: A::Partial,
+) -> A::Partial
 where
     A: crate::PartialAppState<Event = E>,
 {
-    let mut state = initial_state;
     // Process all events efficiently without finalizing
-    for event in events {
-        state = A::process_event(state, event);
-    }
-
-    // Finalize once at the end
-    A::finalize(state)
+    events.fold(partial, |state, event| {
+        match A::check_event(&state, event) {
+            Ok(()) => A::process_event(state, event),
+            Err(reason) => {
+                log::error!("Skipping event that can't be applied: {reason}");
+                state
+            }
+        }
+    })
 }
 
 pub struct ValidToAddEvents<Event> {
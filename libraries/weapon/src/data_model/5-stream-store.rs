@@ -5,7 +5,9 @@ use std::{
     collections::{BTreeMap, HashMap},
 };
 
-use crate::data_model::{EventStreamStore, Timestamped, ValidToAddEvents};
+use crate::data_model::{
+    DuplicateEvent, EventStreamStore, MetaEvent, Timestamped, ValidToAddEvents,
+};
 use std::hash::Hash;
 
 pub trait StreamStore<Device>: Any {
@@ -15,6 +17,8 @@ pub trait StreamStore<Device>: Any {
         self.num_events_per_device().values().sum()
     }
 
+    /// The device's events after the first `skip`. Empty if some of those are archived and not
+    /// loaded, since the rest couldn't be added anywhere without them.
     fn jsons(&self, device: &Device, skip: usize) -> Vec<Timestamped<serde_json::Value>>;
 
     fn valid_to_add_event_jsons(
@@ -33,25 +37,79 @@ pub trait StreamStore<Device>: Any {
         &self,
         sync_state: &BTreeMap<Device, usize>,
     ) -> Option<chrono::DateTime<chrono::Utc>>;
+
+    /// Events that this version of the app couldn't deserialize, probably because they were
+    /// written by a newer version
+    fn num_unknown_events(&self) -> usize;
+
+    /// The timestamp of the latest event from each device
+    fn latest_timestamp_per_device(&self) -> HashMap<&Device, chrono::DateTime<chrono::Utc>>;
+
+    /// The device merges recorded in this stream (see `MetaEvent::MergeDevice`), as
+    /// `(when, from, into)`
+    fn device_merges(&self) -> Vec<(chrono::DateTime<chrono::Utc>, String, String)>;
+
+    /// How many of each device's events a branch stream was forked with (see
+    /// `MetaEvent::Fork`). `None` if this stream isn't a branch.
+    fn fork_clock(&self) -> Option<BTreeMap<String, usize>>;
+
+    /// An empty stream that holds the same events as this one
+    fn new_empty(&self) -> Box<dyn StreamStore<Device>>;
+
+    /// Per device, how many of its first events are archived and not loaded (see
+    /// `StreamArchive`)
+    fn archived_per_device(&self) -> HashMap<&Device, usize>;
+
+    /// Each device's first events from before `cutoff`, as JSON, but no more than `max_counts`
+    /// allows (e.g. only what's been synced). Stops at the first meta event, since meta events
+    /// describe the log and have to stay loaded. `None` if the stream isn't fully loaded.
+    fn archivable_jsons(
+        &self,
+        cutoff: chrono::DateTime<chrono::Utc>,
+        max_counts: &BTreeMap<Device, usize>,
+    ) -> Option<BTreeMap<Device, Vec<Timestamped<serde_json::Value>>>>;
+
+    /// Drops the first `count` events of each device from memory, see
+    /// `EventStreamStore::unload_archived`
+    fn unload_archived(&mut self, counts: &BTreeMap<Device, usize>);
+
+    /// Puts archived events back, see `EventStreamStore::load_archived`. Returns how many were
+    /// loaded.
+    fn load_archived_jsons(
+        &mut self,
+        events: BTreeMap<Device, Vec<Timestamped<serde_json::Value>>>,
+    ) -> Result<usize, serde_json::Error>;
+
+    /// The loaded events that duplicate another, see `EventStreamStore::duplicates`
+    fn duplicates(&self) -> Vec<DuplicateEvent<Device>>;
+
+    /// Repairs the duplicates and returns them, see `EventStreamStore::remove_duplicates`
+    fn remove_duplicates(&mut self) -> Option<Vec<DuplicateEvent<Device>>>;
 }
 
 impl<Device: Ord + Eq + Clone + Hash + 'static, Event: crate::Event + 'static> StreamStore<Device>
     for EventStreamStore<Device, Timestamped<Event>>
 {
     fn num_events_per_device(&self) -> HashMap<&Device, usize> {
-        self.events()
-            .iter()
-            .map(|(device, events)| (device, events.len()))
-            .collect::<HashMap<&Device, usize>>()
+        let mut counts = self.archived_per_device();
+        for (device, events) in self.events() {
+            *counts.entry(device).or_insert(0) += events.len();
+        }
+        counts
     }
 
     fn jsons(&self, device: &Device, skip: usize) -> Vec<Timestamped<serde_json::Value>> {
+        let archived = self.archived().get(device).copied().unwrap_or(0);
+        if skip < archived {
+            log::warn!("Events {skip} to {archived} are archived, so they can't be read");
+            return Vec::new();
+        }
         self.events()
             .get(device)
             .map(|events| {
                 events
                     .iter()
-                    .skip(skip)
+                    .skip(skip - archived)
                     .map(|event| event.as_ref().map(|event| event.to_json().unwrap()))
                     .collect()
             })
@@ -87,7 +145,7 @@ impl<Device: Ord + Eq + Clone + Hash + 'static, Event: crate::Event + 'static> S
         for (device_id, events_set) in self.events() {
             let synced_count = sync_state.get(device_id).copied().unwrap_or(0);
 
-            if events_set.len() > synced_count {
+            if self.len_device(device_id) > synced_count {
                 if let Some(ev) = events_set
                     .iter()
                     .find(|e| e.within_device_events_index == synced_count)
@@ -102,4 +160,116 @@ impl<Device: Ord + Eq + Clone + Hash + 'static, Event: crate::Event + 'static> S
         }
         earliest
     }
+
+    fn num_unknown_events(&self) -> usize {
+        self.events()
+            .values()
+            .flatten()
+            .filter(|event| event.event.is_unknown())
+            .count()
+    }
+
+    fn latest_timestamp_per_device(&self) -> HashMap<&Device, chrono::DateTime<chrono::Utc>> {
+        self.events()
+            .iter()
+            .filter_map(|(device, events)| Some((device, events.last()?.timestamp)))
+            .collect()
+    }
+
+    fn device_merges(&self) -> Vec<(chrono::DateTime<chrono::Utc>, String, String)> {
+        self.events()
+            .values()
+            .flatten()
+            .filter_map(|event| match event.event.meta_event()? {
+                MetaEvent::MergeDevice { from, into } => {
+                    Some((event.timestamp, from.clone(), into.clone()))
+                }
+                MetaEvent::Duplicate { .. } | MetaEvent::Fork { .. } => None,
+            })
+            .collect()
+    }
+
+    fn fork_clock(&self) -> Option<BTreeMap<String, usize>> {
+        self.events()
+            .values()
+            .flatten()
+            .find_map(|event| match event.event.meta_event()? {
+                MetaEvent::Fork { clock, .. } => Some(clock.clone()),
+                MetaEvent::MergeDevice { .. } | MetaEvent::Duplicate { .. } => None,
+            })
+    }
+
+    fn new_empty(&self) -> Box<dyn StreamStore<Device>> {
+        Box::new(EventStreamStore::<Device, Timestamped<Event>>::default())
+    }
+
+    fn archived_per_device(&self) -> HashMap<&Device, usize> {
+        self.archived()
+            .iter()
+            .filter(|(_, count)| **count > 0)
+            .map(|(device, count)| (device, *count))
+            .collect()
+    }
+
+    fn archivable_jsons(
+        &self,
+        cutoff: chrono::DateTime<chrono::Utc>,
+        max_counts: &BTreeMap<Device, usize>,
+    ) -> Option<BTreeMap<Device, Vec<Timestamped<serde_json::Value>>>> {
+        if !self.is_fully_loaded() {
+            return None;
+        }
+        Some(
+            self.events()
+                .iter()
+                .map(|(device, events)| {
+                    let max_count = max_counts.get(device).copied().unwrap_or(0);
+                    // Events are ordered by timestamp, but the archive is a prefix by index
+                    let mut events: Vec<_> = events.iter().collect();
+                    events.sort_by_key(|event| event.within_device_events_index);
+                    let archivable = events
+                        .into_iter()
+                        .take(max_count)
+                        .take_while(|event| {
+                            event.timestamp < cutoff && event.event.meta_event().is_none()
+                        })
+                        .map(|event| event.as_ref().map(|event| event.to_json().unwrap()))
+                        .collect();
+                    (device.clone(), archivable)
+                })
+                .collect(),
+        )
+    }
+
+    fn unload_archived(&mut self, counts: &BTreeMap<Device, usize>) {
+        EventStreamStore::unload_archived(
+            self,
+            counts
+                .iter()
+                .map(|(device, count)| (device.clone(), *count)),
+        );
+    }
+
+    fn load_archived_jsons(
+        &mut self,
+        events: BTreeMap<Device, Vec<Timestamped<serde_json::Value>>>,
+    ) -> Result<usize, serde_json::Error> {
+        let mut loaded = 0;
+        for (device, events) in events {
+            let events = events
+                .into_iter()
+                .map(|event| event.map(|event| Event::from_json(&event)).transpose())
+                .collect::<Result<Vec<_>, _>>()?;
+            loaded += self.load_archived(device, events);
+        }
+        Ok(loaded)
+    }
+
+    fn duplicates(&self) -> Vec<DuplicateEvent<Device>> {
+        EventStreamStore::duplicates(self)
+    }
+
+    fn remove_duplicates(&mut self) -> Option<Vec<DuplicateEvent<Device>>> {
+        EventStreamStore::remove_duplicates(self)
+    }
 }
@@ -4,7 +4,7 @@
 
 use std::ops::{Deref, DerefMut};
 
-use crate::data_model::ListenerKey;
+use crate::data_model::{LifecycleEvent, ListenerKey, StreamLifecycle};
 
 #[derive(Clone, Debug)]
 pub enum DirtyState {
@@ -21,17 +21,23 @@ pub struct DirtyTracker<Store> {
     store: Store,
     /// Tracks whether there are pending notifications and who should be notified
     pub dirty_state: DirtyState,
-    loaded_at_least_once: bool,
+    lifecycle: StreamLifecycle,
 }
 
 impl<Store: Default> Default for DirtyTracker<Store> {
     fn default() -> Self {
+        Self::new(Default::default())
+    }
+}
+
+impl<Store> DirtyTracker<Store> {
+    pub fn new(store: Store) -> Self {
         Self {
-            store: Default::default(),
+            store,
 
             // Creating a stream is an action that warrants a notification.
             dirty_state: DirtyState::DirtyAll,
-            loaded_at_least_once: false,
+            lifecycle: StreamLifecycle::Created,
         }
     }
 }
@@ -82,15 +88,19 @@ impl<'a, Store> DirtyOnDerefMut<'a, Store> {
 }
 
 impl<Store> DirtyTracker<Store> {
-    /// Returns true if the `loaded` marker was changed
+    /// Returns true if the stream wasn't loaded before
     pub(crate) fn mark_loaded(&mut self, modifier: Option<ListenerKey>) -> bool {
-        if !self.loaded_at_least_once {
-            self.loaded_at_least_once = true;
+        let was_loaded = self.lifecycle.is_loaded();
+        self.lifecycle = self.lifecycle.next(LifecycleEvent::Loaded);
+        if !was_loaded {
             self.store_mut(modifier).mark_dirty();
-            true
-        } else {
-            false
         }
+        !was_loaded
+    }
+
+    /// Records that the listeners were handed the stream's changes
+    pub(crate) fn mark_notified(&mut self) {
+        self.lifecycle = self.lifecycle.next(LifecycleEvent::Notified);
     }
 
     pub fn store(&self) -> &Store {
@@ -106,14 +116,18 @@ impl<Store> DirtyTracker<Store> {
     }
 
     pub fn loaded_at_least_once(&self) -> bool {
-        self.loaded_at_least_once
+        self.lifecycle.is_loaded()
+    }
+
+    pub fn lifecycle(&self) -> StreamLifecycle {
+        self.lifecycle
     }
 
     pub fn map<NewStore>(self, f: impl FnOnce(Store) -> NewStore) -> DirtyTracker<NewStore> {
         DirtyTracker {
             store: f(self.store),
             dirty_state: self.dirty_state,
-            loaded_at_least_once: self.loaded_at_least_once,
+            lifecycle: self.lifecycle,
         }
     }
 }
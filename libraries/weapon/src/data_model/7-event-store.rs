@@ -1,17 +1,125 @@
 use std::any::Any;
-use std::collections::{BTreeMap, HashMap};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::hash::Hash;
 use std::sync::Arc;
 
 use crate::data_model::{
-    DirtyState, DirtyTracker, EventStreamStore, EventType, ListenerKey, StreamStore, Timestamped,
+    DirtyState, DirtyTracker, EventStreamStore, EventType, ListenerKey, MetaEvent, StreamStatus,
+    StreamStore, Timestamped, Validation, is_branch_stream,
 };
 
 use super::DirtyOnDerefMut;
 
+/// Events bigger than this (as JSON) are rejected when appended. Every event is synced to every
+/// device, so a single huge one can break syncing for everyone.
+pub const MAX_EVENT_BYTES: usize = 64 * 1024;
+
+#[derive(Debug, thiserror::Error)]
+pub enum AppendError {
+    #[error("Event is {size} bytes, which is over the limit of {MAX_EVENT_BYTES} bytes")]
+    TooLarge { size: usize },
+
+    #[error("Event could not be serialized")]
+    Serialize(#[source] serde_json::Error),
+
+    #[error("Event does not deserialize back to itself, so it would be corrupted when synced")]
+    RoundTrip,
+
+    #[error("No schema is registered for stream {stream}, so its events can't be decoded")]
+    UnknownStream { stream: String },
+
+    #[error("Event could not be decoded")]
+    Deserialize(#[source] serde_json::Error),
+
+    /// The event's own `Event::validate` hook rejected it
+    #[error("Event was rejected: {reason}")]
+    Rejected { reason: String },
+}
+
+/// Checks that an event is allowed by the app (see `Event::validate`), is small enough to sync
+/// and survives being written and read back
+fn validate_event<Event: crate::Event>(event: &Timestamped<Event>) -> Result<(), AppendError> {
+    match event.event.validate() {
+        Validation::Accept => {}
+        Validation::Warn(note) => log::warn!("Adding suspicious event: {note}"),
+        Validation::Reject(reason) => return Err(AppendError::Rejected { reason }),
+    }
+    let json = crate::Event::to_json(event).map_err(AppendError::Serialize)?;
+    let size = serde_json::to_vec(&json)
+        .map_err(AppendError::Serialize)?
+        .len();
+    if size > MAX_EVENT_BYTES {
+        return Err(AppendError::TooLarge { size });
+    }
+    match <Timestamped<Event> as crate::Event>::from_json(&json) {
+        Ok(parsed) if parsed == *event => Ok(()),
+        _ => Err(AppendError::RoundTrip),
+    }
+}
+
+/// How often a listener wants to hear about changes
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(target_arch = "wasm32", derive(tsify::Tsify))]
+#[cfg_attr(target_arch = "wasm32", tsify(into_wasm_abi, from_wasm_abi))]
+#[serde(rename_all = "camelCase", tag = "type")]
+pub enum NotifyPolicy {
+    /// Notified every time notifications are drained and a stream has changed
+    #[default]
+    Immediate,
+    /// Notified at most once every `ms` milliseconds. Changes in between are coalesced into one
+    /// notification, which is due at `next_notification_due`.
+    Throttle { ms: u64 },
+    /// Not notified while a batch is open (see `begin_batch`), then notified once it closes
+    OncePerBatch,
+}
+
+struct Listener<Stream> {
+    callback: Arc<dyn Fn(ListenerKey, Stream)>,
+    policy: NotifyPolicy,
+    last_notified: Option<chrono::DateTime<chrono::Utc>>,
+    /// Streams that changed since this listener was last notified about them
+    pending: HashSet<Stream>,
+}
+
+impl<Stream> Listener<Stream> {
+    fn due_at(&self, batch_open: bool) -> Option<chrono::DateTime<chrono::Utc>> {
+        if self.pending.is_empty() {
+            return None;
+        }
+        match self.policy {
+            NotifyPolicy::Immediate => Some(chrono::DateTime::<chrono::Utc>::MIN_UTC),
+            NotifyPolicy::Throttle { ms } => Some(
+                self.last_notified
+                    .map(|last| last + chrono::Duration::milliseconds(ms as i64))
+                    .unwrap_or(chrono::DateTime::<chrono::Utc>::MIN_UTC),
+            ),
+            NotifyPolicy::OncePerBatch if batch_open => None,
+            NotifyPolicy::OncePerBatch => Some(chrono::DateTime::<chrono::Utc>::MIN_UTC),
+        }
+    }
+}
+
+/// Creates an empty stream for one event type, see `EventStore::register_schema`
+type StreamFactory<Device> = fn() -> DirtyTracker<Box<dyn StreamStore<Device>>>;
+
+fn new_stream<Device, Event>() -> DirtyTracker<Box<dyn StreamStore<Device>>>
+where
+    Device: Eq + Hash + Clone + Ord + 'static,
+    Event: Ord + Clone + crate::Event + 'static,
+{
+    DirtyTracker::<EventStreamStore<Device, Timestamped<Event>>>::default()
+        .map(|s| Box::new(s) as Box<dyn StreamStore<Device>>)
+}
+
 pub struct EventStore<Stream: Eq + Hash + Clone, Device: Eq + Hash + Clone> {
     streams: HashMap<Stream, DirtyTracker<Box<dyn StreamStore<Device>>>>,
-    listeners: slotmap::SlotMap<slotmap::DefaultKey, Arc<dyn Fn(ListenerKey, Stream)>>,
+    /// Which event type streams are decoded as, by stream ID prefix. Lets events for streams that
+    /// haven't been created yet (e.g. pushed from another device) be added without knowing their
+    /// type at the call site.
+    schemas: Vec<(String, StreamFactory<Device>)>,
+    listeners: slotmap::SlotMap<slotmap::DefaultKey, Listener<Stream>>,
+    /// How many batches are open, see `begin_batch`
+    open_batches: usize,
 
     /// Updated whenever a sync target is updated.
     sync_states: SyncStates<Stream, Device>,
@@ -21,7 +129,9 @@ impl<Stream: Eq + Hash + Clone, Device: Eq + Hash + Clone> Default for EventStor
     fn default() -> Self {
         Self {
             streams: HashMap::new(),
+            schemas: Vec::new(),
             listeners: Default::default(),
+            open_batches: 0,
 
             sync_states: Default::default(),
         }
@@ -32,7 +142,6 @@ impl<Stream: Eq + Hash + Clone + 'static, Device: Eq + Hash + Clone + 'static>
     EventStore<Stream, Device>
 {
     pub fn drain_due_notifications(&mut self) -> Vec<Box<dyn FnOnce()>> {
-        let mut notifications: Vec<Box<dyn FnOnce()>> = Vec::new();
         for (stream_id, event_stream) in self.streams.iter_mut() {
             let exclude_key = match &event_stream.dirty_state {
                 DirtyState::Clean => continue,
@@ -42,19 +151,53 @@ impl<Stream: Eq + Hash + Clone + 'static, Device: Eq + Hash + Clone + 'static>
 
             // Reset to clean after draining
             event_stream.dirty_state = DirtyState::Clean;
+            event_stream.mark_notified();
 
-            for (key, listener) in self.listeners.iter() {
-                let listener_key = ListenerKey(key);
-                if exclude_key == Some(listener_key) {
+            for (key, listener) in self.listeners.iter_mut() {
+                if exclude_key == Some(ListenerKey(key)) {
                     continue;
                 }
-                let listener = listener.clone();
-                let stream_id = stream_id.clone();
-                notifications.push(Box::new(move || listener(listener_key, stream_id)));
+                listener.pending.insert(stream_id.clone());
+            }
+        }
+
+        let now = chrono::Utc::now();
+        let batch_open = self.open_batches > 0;
+        let mut notifications: Vec<Box<dyn FnOnce()>> = Vec::new();
+        for (key, listener) in self.listeners.iter_mut() {
+            if !listener.due_at(batch_open).is_some_and(|due| due <= now) {
+                continue;
+            }
+            listener.last_notified = Some(now);
+
+            let listener_key = ListenerKey(key);
+            for stream_id in listener.pending.drain() {
+                let callback = listener.callback.clone();
+                notifications.push(Box::new(move || callback(listener_key, stream_id)));
             }
         }
         notifications
     }
+
+    /// When notifications that are being held back (e.g. by `NotifyPolicy::Throttle`) will be
+    /// due. Nothing is notified on its own, so the caller should drain notifications again then.
+    pub fn next_notification_due(&self) -> Option<chrono::DateTime<chrono::Utc>> {
+        let batch_open = self.open_batches > 0;
+        self.listeners
+            .values()
+            .filter_map(|listener| listener.due_at(batch_open))
+            .min()
+    }
+
+    /// Holds back notifications for `OncePerBatch` listeners until the matching `end_batch`.
+    /// Batches can be nested.
+    pub fn begin_batch(&mut self) {
+        self.open_batches += 1;
+    }
+
+    pub fn end_batch(&mut self) {
+        self.open_batches = self.open_batches.saturating_sub(1);
+    }
 }
 
 impl<Stream: Eq + Hash + Clone + Ord, Device: Eq + Hash + Clone + Ord + 'static>
@@ -141,23 +284,60 @@ impl<Stream: Eq + Hash + Clone + Ord, Device: Eq + Hash + Clone + Ord + 'static>
         modifier: Option<ListenerKey>,
     ) -> DirtyOnDerefMut<'_, EventStreamStore<Device, Timestamped<Event>>> {
         if !self.streams.contains_key(&stream) {
-            let store = DirtyTracker::<EventStreamStore<Device, Timestamped<Event>>>::default();
-            let store = store.map(|s| Box::new(s) as Box<dyn StreamStore<Device>>);
-            self.streams.insert(stream.clone(), store);
+            self.streams
+                .insert(stream.clone(), new_stream::<Device, Event>());
         }
         self.get_mut::<Event>(&stream, modifier)
             .expect("stream must exist at this point")
     }
 
+    /// Adds a stream that was built elsewhere, e.g. a branch's copy (see `fork_stream`)
+    pub(super) fn insert_stream(
+        &mut self,
+        stream: Stream,
+        store: Box<dyn StreamStore<Device>>,
+        modifier: Option<ListenerKey>,
+    ) {
+        let mut tracker = DirtyTracker::new(store);
+        tracker.mark_loaded(modifier);
+        self.streams.insert(stream, tracker);
+    }
+
+    /// Forgets a stream and what the sync targets had of it. Listeners are told it changed, and
+    /// will find it gone. Returns false if there was no such stream.
+    pub(super) fn remove_stream(&mut self, stream: &Stream) -> bool {
+        if self.streams.remove(stream).is_none() {
+            return false;
+        }
+        for state in self.sync_states.values_mut() {
+            state.remote_clock.remove(stream);
+        }
+        for listener in self.listeners.values_mut() {
+            listener.pending.insert(stream.clone());
+        }
+        true
+    }
+
     /// The listener is invoked whenever a new stream is added.
     pub fn register_listener(
         &mut self,
         listener: impl Fn(ListenerKey, Stream) + 'static,
     ) -> ListenerKey {
-        let key = self.listeners.insert(Arc::new(listener));
+        let key = self.listeners.insert(Listener {
+            callback: Arc::new(listener),
+            policy: NotifyPolicy::Immediate,
+            last_notified: None,
+            pending: HashSet::new(),
+        });
         ListenerKey(key)
     }
 
+    pub fn set_notify_policy(&mut self, token: ListenerKey, policy: NotifyPolicy) {
+        if let Some(listener) = self.listeners.get_mut(token.0) {
+            listener.policy = policy;
+        }
+    }
+
     /// Unregister a previously registered store-level listener.
     pub fn unregister_listener(&mut self, token: ListenerKey) {
         self.listeners.remove(token.0);
@@ -174,13 +354,19 @@ impl<Stream: Eq + Hash + Clone + Ord, Device: Eq + Hash + Clone + Ord + 'static>
             .unwrap_or(false)
     }
 
-    /// returns true if the `loaded` marker was changed
-    pub fn mark_loaded(&mut self, stream: Stream, modifier: Option<ListenerKey>) -> bool {
-        let Some(stream) = self.streams.get_mut(&stream) else {
-            return false;
-        };
+    /// Whether `stream` exists, has been loaded, and its listeners have heard about it, see
+    /// `StreamLifecycle`
+    pub fn stream_status(&self, stream: &Stream) -> StreamStatus {
+        self.streams
+            .get(stream)
+            .map_or(StreamStatus::Missing, |s| s.lifecycle().status())
+    }
 
-        stream.mark_loaded(modifier)
+    /// True if any stream has events this version of the app doesn't understand, in which case the
+    /// state is missing those events and the app should be updated
+    pub fn app_update_required(&self) -> bool {
+        self.iter()
+            .any(|(_, stream)| stream.num_unknown_events() > 0)
     }
 }
 
@@ -225,6 +411,87 @@ impl<Stream: Eq + Hash + Clone + Ord, Device: Eq + Hash + Clone + Ord + 'static>
         store.add_device_events(device, valid_to_add)
     }
 
+    /// Like `add_device_events`, but validates the event first (see `MAX_EVENT_BYTES`)
+    pub fn add_device_event<Event>(
+        &mut self,
+        stream: Stream,
+        device: Device,
+        event: Timestamped<Event>,
+        modifier: Option<ListenerKey>,
+    ) -> Result<usize, AppendError>
+    where
+        Event: Ord + Clone + crate::Event + 'static,
+    {
+        validate_event(&event).inspect_err(|e| log::error!("Rejected event: {e}"))?;
+        Ok(self.add_device_events(stream, device, vec![event], modifier))
+    }
+}
+
+impl<Stream, Device> EventStore<Stream, Device>
+where
+    Stream: Eq + Hash + Clone + Ord + AsRef<str>,
+    Device: Eq + Hash + Clone + Ord + 'static,
+{
+    /// Streams whose ID starts with `stream_prefix` hold `Timestamped<Event>`s. When several
+    /// prefixes match a stream, the longest one wins.
+    pub fn register_schema<Event>(&mut self, stream_prefix: impl Into<String>)
+    where
+        Event: Ord + Clone + crate::Event + 'static,
+    {
+        let stream_prefix = stream_prefix.into();
+        self.schemas.retain(|(prefix, _)| *prefix != stream_prefix);
+        self.schemas
+            .push((stream_prefix, new_stream::<Device, Event>));
+    }
+
+    fn schema_entry(&self, stream: &Stream) -> Option<&(String, StreamFactory<Device>)> {
+        self.schemas
+            .iter()
+            .filter(|(prefix, _)| stream.as_ref().starts_with(prefix.as_str()))
+            .max_by_key(|(prefix, _)| prefix.len())
+    }
+
+    fn schema(&self, stream: &Stream) -> Option<StreamFactory<Device>> {
+        self.schema_entry(stream).map(|(_, factory)| *factory)
+    }
+
+    /// The registered prefix that decides what events `stream` holds, if there is one (see
+    /// `register_schema`)
+    pub fn schema_prefix(&self, stream: &Stream) -> Option<&str> {
+        self.schema_entry(stream).map(|(prefix, _)| prefix.as_str())
+    }
+
+    /// Records that `stream` has been read from local storage (see `StreamLifecycle`). A stream
+    /// nothing has created yet (e.g. local storage had no events for it) is created from the
+    /// registered schemas, so it doesn't stay `Missing`. Returns true if it wasn't loaded before.
+    pub fn mark_loaded(&mut self, stream: Stream, modifier: Option<ListenerKey>) -> bool {
+        if !self.streams.contains_key(&stream) {
+            let Some(factory) = self.schema(&stream) else {
+                return false;
+            };
+            self.streams.insert(stream.clone(), factory());
+        }
+        self.streams
+            .get_mut(&stream)
+            .is_some_and(|stream| stream.mark_loaded(modifier))
+    }
+
+    /// Like `get_mut_raw`, but creates the stream if it doesn't exist and a schema is registered
+    /// for it
+    pub fn get_or_insert_from_schema(
+        &mut self,
+        stream: Stream,
+        modifier: Option<ListenerKey>,
+    ) -> Option<DirtyOnDerefMut<'_, Box<dyn StreamStore<Device>>>> {
+        if !self.streams.contains_key(&stream) {
+            let factory = self.schema(&stream)?;
+            self.streams.insert(stream.clone(), factory());
+        }
+        self.get_mut_raw(&stream, modifier)
+    }
+
+    /// Adds events as JSON. If the stream doesn't exist yet, it's created from the registered
+    /// schemas (see `register_schema`).
     pub fn add_device_events_jsons(
         &mut self,
         stream: Stream,
@@ -232,8 +499,8 @@ impl<Stream: Eq + Hash + Clone + Ord, Device: Eq + Hash + Clone + Ord + 'static>
         events: Vec<Timestamped<serde_json::Value>>,
         modifier: Option<ListenerKey>,
     ) -> usize {
-        let Some(store) = self.get_mut_raw(&stream, modifier) else {
-            log::error!("Cannot insert events for stream as it does not exist");
+        let Some(store) = self.get_or_insert_from_schema(stream, modifier) else {
+            log::error!("Cannot insert events for stream as it does not exist and has no schema");
             return 0;
         };
 
@@ -251,17 +518,36 @@ impl<Stream: Eq + Hash + Clone + Ord, Device: Eq + Hash + Clone + Ord + 'static>
             .unwrap_or(0)
     }
 
-    pub fn add_device_event<Event>(
+    /// Adds an event from another device, as the JSON of a `Timestamped` event. The event type is
+    /// the stream's, or comes from the registered schemas if the stream doesn't exist yet.
+    pub fn add_remote_event_json(
         &mut self,
         stream: Stream,
         device: Device,
-        event: Timestamped<Event>,
+        event: serde_json::Value,
         modifier: Option<ListenerKey>,
-    ) -> usize
-    where
-        Event: Ord + Clone + crate::Event + 'static,
-    {
-        self.add_device_events(stream, device, vec![event], modifier)
+    ) -> Result<usize, AppendError> {
+        let size = serde_json::to_vec(&event)
+            .map_err(AppendError::Serialize)?
+            .len();
+        if size > MAX_EVENT_BYTES {
+            return Err(AppendError::TooLarge { size });
+        }
+        let event = serde_json::from_value::<Timestamped<serde_json::Value>>(event)
+            .map_err(AppendError::Deserialize)?;
+
+        let Some(store) = self.get_or_insert_from_schema(stream.clone(), modifier) else {
+            return Err(AppendError::UnknownStream {
+                stream: stream.as_ref().to_string(),
+            });
+        };
+        let Some(valid_to_add) = store.valid_to_add_event_jsons(&device, vec![event]) else {
+            return Ok(0);
+        };
+        let mut store = store;
+        store
+            .add_device_event_jsons(device, valid_to_add)
+            .map_err(AppendError::Deserialize)
     }
 }
 
@@ -274,7 +560,8 @@ impl<Stream: Eq + Hash + Clone + Ord, Device: Eq + Hash + Clone + Ord + 'static>
         device: Device,
         event: Event,
         modifier: Option<ListenerKey>,
-    ) where
+    ) -> Result<(), AppendError>
+    where
         Event: Ord + Clone + crate::Event + 'static,
     {
         let event = Timestamped {
@@ -285,14 +572,50 @@ impl<Stream: Eq + Hash + Clone + Ord, Device: Eq + Hash + Clone + Ord + 'static>
                 .len_device(&device),
         };
 
-        self.add_device_event(stream, device, event, modifier);
+        self.add_device_event(stream, device, event, modifier)?;
+        Ok(())
+    }
+
+    /// Like `add_raw_event` for several events at once, keeping the times they happened at (e.g.
+    /// events recorded while offline). They're added in timestamp order as a single change, so
+    /// listeners are only notified once. If any of them is invalid, none are added.
+    pub fn add_raw_events<Event>(
+        &mut self,
+        stream: Stream,
+        device: Device,
+        mut events: Vec<(chrono::DateTime<chrono::Utc>, Event)>,
+        modifier: Option<ListenerKey>,
+    ) -> Result<usize, AppendError>
+    where
+        Event: Ord + Clone + crate::Event + 'static,
+    {
+        events.sort_by_key(|(timestamp, _)| *timestamp);
+        let first_index = self
+            .get_or_insert_default::<EventType<Event>>(stream.clone(), modifier)
+            .len_device(&device);
+        let events = events
+            .into_iter()
+            .enumerate()
+            .map(|(offset, (timestamp, event))| Timestamped {
+                event: EventType::User(event),
+                timestamp,
+                within_device_events_index: first_index + offset,
+            })
+            .collect::<Vec<_>>();
+        for event in &events {
+            validate_event(event).inspect_err(|e| log::error!("Rejected event: {e}"))?;
+        }
+        Ok(self.add_device_events(stream, device, events, modifier))
     }
 
     /// Returns None if there are no unsynced events
     pub fn get_timestamp_of_earliest_unsynced_event(
         &self,
         target: SyncTarget,
-    ) -> Option<chrono::DateTime<chrono::Utc>> {
+    ) -> Option<chrono::DateTime<chrono::Utc>>
+    where
+        Stream: AsRef<str>,
+    {
         let remote_clock = self
             .sync_states
             .get(&target)
@@ -301,6 +624,10 @@ impl<Stream: Eq + Hash + Clone + Ord, Device: Eq + Hash + Clone + Ord + 'static>
         let mut earliest: Option<chrono::DateTime<chrono::Utc>> = None;
 
         for (stream_id, event_stream) in &self.streams {
+            // Branches are only ever saved locally
+            if target != SyncTarget::Opfs && is_branch_stream(stream_id.as_ref()) {
+                continue;
+            }
             let device_sync_map = remote_clock.get(stream_id).cloned().unwrap_or_default();
 
             let candidate = event_stream
@@ -317,6 +644,36 @@ impl<Stream: Eq + Hash + Clone + Ord, Device: Eq + Hash + Clone + Ord + 'static>
 
         earliest
     }
+
+    /// How many events `target` doesn't have yet, as of the last sync with it
+    pub fn num_unsynced_events(&self, target: SyncTarget) -> usize
+    where
+        Stream: AsRef<str>,
+    {
+        let remote_clock = self.sync_states.get(&target).map(|s| &s.remote_clock);
+        self.streams
+            .iter()
+            // Branches are only ever saved locally
+            .filter(|(stream_id, _)| {
+                target == SyncTarget::Opfs || !is_branch_stream(stream_id.as_ref())
+            })
+            .map(|(stream_id, event_stream)| {
+                let synced = remote_clock.and_then(|clock| clock.get(stream_id));
+                event_stream
+                    .store()
+                    .num_events_per_device()
+                    .into_iter()
+                    .map(|(device, count)| {
+                        let synced_count = synced
+                            .and_then(|synced| synced.get(device))
+                            .copied()
+                            .unwrap_or(0);
+                        count.saturating_sub(synced_count)
+                    })
+                    .sum::<usize>()
+            })
+            .sum()
+    }
 }
 
 pub type Clock<Stream, Device> = BTreeMap<Stream, BTreeMap<Device, usize>>;
@@ -374,9 +731,198 @@ impl<Stream: Eq + Hash + Clone + Ord, Device: Eq + Hash + Clone + Ord> EventStor
 
     pub fn mark_sync_finished(&mut self, target: SyncTarget, error: Option<String>) {
         let state = self.sync_states.entry(target).or_default();
-        state.last_sync_finished = Some(chrono::Utc::now());
+        let now = chrono::Utc::now();
+        state.last_sync_finished = Some(now);
+        if error.is_none() {
+            state.last_successful_sync = Some(now);
+        }
         state.last_sync_error = error;
     }
+
+    /// Replaces the events `target` refused in the last upload
+    pub fn record_rejected_events(
+        &mut self,
+        target: SyncTarget,
+        rejected_events: Vec<RejectedEvent<Stream, Device>>,
+    ) {
+        self.sync_states.entry(target).or_default().rejected_events = rejected_events;
+    }
+
+    /// Replaces the clock the next sync with `target` is sent relative to. `None` makes it send
+    /// the whole clock.
+    pub fn set_clock_baseline(
+        &mut self,
+        target: SyncTarget,
+        baseline: Option<ClockBaseline<Stream, Device>>,
+    ) {
+        self.sync_states.entry(target).or_default().clock_baseline = baseline;
+    }
+}
+
+impl<Stream, Device> EventStore<Stream, Device>
+where
+    Stream: Eq + Hash + Clone + Ord,
+    Device: Eq + Hash + Clone + Ord + AsRef<str> + From<String> + 'static,
+{
+    /// Records that `from`'s events belong to `into`'s history (e.g. after a reinstall gave the
+    /// app a new device ID), so they're reported as `into`'s from now on. `author` is the device
+    /// recording it.
+    ///
+    /// `from`'s events aren't rewritten, so their indices stay valid here, on the server and on
+    /// every other device. Instead, the merge is added as a meta event to each stream `from` has
+    /// events in, which syncs like any other event. Returns how many streams it was added to.
+    pub fn merge_device(
+        &mut self,
+        author: Device,
+        from: Device,
+        into: Device,
+        modifier: Option<ListenerKey>,
+    ) -> Result<usize, AppendError> {
+        if from == into || self.device_aliases().get(&from) == Some(&into) {
+            return Ok(0);
+        }
+
+        let event = serde_json::to_value(EventType::<serde_json::Value>::Meta(
+            MetaEvent::MergeDevice {
+                from: from.as_ref().to_string(),
+                into: into.as_ref().to_string(),
+            },
+        ))
+        .map_err(AppendError::Serialize)?;
+        let streams = self
+            .iter()
+            .filter(|(_, stream)| stream.num_events_per_device().contains_key(&from))
+            .map(|(stream, _)| stream.clone())
+            .collect::<Vec<_>>();
+
+        let mut merged = 0;
+        for stream in streams {
+            let Some(store) = self.get_mut_raw(&stream, modifier) else {
+                continue;
+            };
+            let event = Timestamped {
+                timestamp: chrono::Utc::now(),
+                within_device_events_index: store
+                    .num_events_per_device()
+                    .get(&author)
+                    .copied()
+                    .unwrap_or(0),
+                event: event.clone(),
+            };
+            let Some(valid_to_add) = store.valid_to_add_event_jsons(&author, vec![event]) else {
+                continue;
+            };
+            let mut store = store;
+            merged += store
+                .add_device_event_jsons(author.clone(), valid_to_add)
+                .map_err(AppendError::Deserialize)?
+                .min(1);
+        }
+        Ok(merged)
+    }
+
+    /// Which device each merged device's events count as, see `merge_device`. Merges are
+    /// followed, so if `a` was merged into `b` and `b` into `c`, `a`'s events count as `c`'s. If
+    /// a device was merged more than once, the latest merge wins.
+    pub fn device_aliases(&self) -> BTreeMap<Device, Device> {
+        let mut recorded = self
+            .iter()
+            .flat_map(|(_, stream)| stream.device_merges())
+            .collect::<Vec<_>>();
+        recorded.sort();
+        let mut merges: BTreeMap<Device, Device> = BTreeMap::new();
+        for (_, from, into) in recorded {
+            let (from, into) = (Device::from(from), Device::from(into));
+            // Merging back the other way undoes the earlier merge
+            if merges.get(&into) == Some(&from) {
+                merges.remove(&into);
+            }
+            merges.insert(from, into);
+        }
+
+        merges
+            .keys()
+            .filter_map(|from| {
+                let mut into = &merges[from];
+                // Bounded, in case the merges form a cycle
+                for _ in 0..merges.len() {
+                    match merges.get(into) {
+                        Some(next) if next != from => into = next,
+                        _ => break,
+                    }
+                }
+                (into != from).then(|| (from.clone(), into.clone()))
+            })
+            .collect()
+    }
+
+    /// How far along each device is, summed over all streams. Includes devices we only know about
+    /// from `target`'s clock. Merged devices (see `merge_device`) are counted as the device they
+    /// were merged into.
+    pub fn device_progress(&self, target: SyncTarget) -> Vec<DeviceProgress<Device>> {
+        let aliases = self.device_aliases();
+        let mut progress: BTreeMap<Device, DeviceProgress<Device>> = BTreeMap::new();
+        fn entry<'a, Device: Ord + Clone>(
+            progress: &'a mut BTreeMap<Device, DeviceProgress<Device>>,
+            aliases: &BTreeMap<Device, Device>,
+            device: &Device,
+        ) -> &'a mut DeviceProgress<Device> {
+            let device = aliases.get(device).unwrap_or(device);
+            progress
+                .entry(device.clone())
+                .or_insert_with(|| DeviceProgress {
+                    device: device.clone(),
+                    merged_from: Vec::new(),
+                    local_events: 0,
+                    remote_events: 0,
+                    last_event_at: None,
+                })
+        }
+
+        for (_, stream) in self.iter() {
+            let latest = stream.latest_timestamp_per_device();
+            for (device, count) in stream.num_events_per_device() {
+                let device_progress = entry(&mut progress, &aliases, device);
+                device_progress.local_events += count;
+                device_progress.last_event_at = device_progress
+                    .last_event_at
+                    .max(latest.get(device).copied());
+            }
+        }
+
+        if let Some(state) = self.sync_states.get(&target) {
+            for device_counts in state.remote_clock.values() {
+                for (device, count) in device_counts {
+                    entry(&mut progress, &aliases, device).remote_events += count;
+                }
+            }
+        }
+
+        for from in aliases.keys() {
+            entry(&mut progress, &aliases, from)
+                .merged_from
+                .push(from.clone());
+        }
+
+        progress.into_values().collect()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(target_arch = "wasm32", derive(tsify::Tsify))]
+#[cfg_attr(target_arch = "wasm32", tsify(into_wasm_abi, from_wasm_abi))]
+#[serde(rename_all = "camelCase")]
+pub struct DeviceProgress<Device> {
+    pub device: Device,
+    /// Devices whose events are counted as this one's, see `EventStore::merge_device`
+    pub merged_from: Vec<Device>,
+    /// Events from this device that are stored locally
+    pub local_events: usize,
+    /// Events from this device that the sync target had as of the last sync. If this is more than
+    /// `local_events`, we're behind on that device's events; if it's less, we haven't uploaded them.
+    pub remote_events: usize,
+    /// When the latest locally stored event from this device was created
+    pub last_event_at: Option<chrono::DateTime<chrono::Utc>>,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
@@ -396,6 +942,66 @@ pub struct SyncState<Stream, Device> {
 
     /// If last_sync_error is Some, then the last sync failed. Gets reset to None when the next sync succeeds.
     pub last_sync_error: Option<String>,
+    /// When a sync last finished without an error
+    #[serde(default)]
+    pub last_successful_sync: Option<chrono::DateTime<chrono::Utc>>,
+
+    /// Events the last upload couldn't store, because the target refused them. They're tried
+    /// again on every sync, so they stay here until a fixed version of the app replaces them.
+    #[serde(default)]
+    pub rejected_events: Vec<RejectedEvent<Stream, Device>>,
+
+    /// The last clock the target acknowledged and kept a copy of. Syncs only send how the local
+    /// clock differs from it, see `weapon::sync_protocol::clock_delta`.
+    #[serde(default)]
+    pub clock_baseline: Option<ClockBaseline<Stream, Device>>,
+}
+
+/// A clock the sync target keeps under `id`, so clocks can be sent as changes from it. Only the
+/// target can create one, so if it forgets it (or never had it), the next sync sends the whole clock.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(target_arch = "wasm32", derive(tsify::Tsify))]
+#[cfg_attr(target_arch = "wasm32", tsify(into_wasm_abi, from_wasm_abi))]
+#[serde(rename_all = "camelCase")]
+#[serde(bound(
+    serialize = "Stream: serde::Serialize + Eq + Hash + Ord, Device: serde::Serialize + Eq + Hash + Ord",
+    deserialize = "Stream: serde::Deserialize<'de> + Eq + Hash + Ord, Device: serde::Deserialize<'de> + Eq + Hash + Ord"
+))]
+pub struct ClockBaseline<Stream, Device> {
+    pub id: String,
+    pub clock: Clock<Stream, Device>,
+}
+
+/// An event a sync target refused to store, e.g. one the Supabase validation trigger rejected
+/// (see `weapon::supabase::MIGRATIONS`)
+#[derive(Debug, Clone, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(target_arch = "wasm32", derive(tsify::Tsify))]
+#[cfg_attr(target_arch = "wasm32", tsify(into_wasm_abi, from_wasm_abi))]
+#[serde(rename_all = "camelCase")]
+pub struct RejectedEvent<Stream, Device> {
+    pub stream: Stream,
+    pub device: Device,
+    pub within_device_events_index: usize,
+    pub reason: RejectionReason,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(target_arch = "wasm32", derive(tsify::Tsify))]
+#[cfg_attr(target_arch = "wasm32", tsify(into_wasm_abi, from_wasm_abi))]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum RejectionReason {
+    /// The event isn't a `Timestamped` event, e.g. it has no timestamp
+    MalformedEnvelope {
+        problem: String,
+    },
+    /// Storing it would leave a gap in the device's indices
+    OutOfOrder {
+        expected_index: usize,
+    },
+    TooLarge {
+        bytes: usize,
+        limit: usize,
+    },
 }
 
 impl<Stream, Device> Default for SyncState<Stream, Device> {
@@ -405,6 +1011,133 @@ impl<Stream, Device> Default for SyncState<Stream, Device> {
             last_sync_started: None,
             last_sync_finished: None,
             last_sync_error: None,
+            last_successful_sync: None,
+            rejected_events: Vec::new(),
+            clock_baseline: None,
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::json_stream::JsonEvent;
+
+    /// Loses `dropped` when it's written out, like a field marked `#[serde(skip)]` by mistake
+    #[derive(
+        Clone, Debug, PartialEq, Eq, PartialOrd, Ord, serde::Serialize, serde::Deserialize,
+    )]
+    struct Lossy {
+        kept: String,
+        #[serde(skip)]
+        dropped: u32,
+    }
+
+    impl crate::Event for Lossy {
+        fn to_json(&self) -> Result<serde_json::Value, serde_json::Error> {
+            serde_json::to_value(self)
+        }
+
+        fn from_json(json: &serde_json::Value) -> Result<Self, serde_json::Error> {
+            serde_json::from_value(json.clone())
+        }
+    }
+
+    fn at<Event>(event: Event) -> Timestamped<EventType<Event>> {
+        Timestamped {
+            timestamp: chrono::DateTime::from_timestamp(10, 0).unwrap(),
+            within_device_events_index: 0,
+            event: EventType::User(event),
+        }
+    }
+
+    fn num_events(store: &EventStore<String, String>, stream: &str) -> usize {
+        store
+            .get_raw(stream.to_string())
+            .map_or(0, |stream| stream.num_events())
+    }
+
+    #[test]
+    fn test_events_over_the_size_limit_are_too_large() {
+        let event = |payload_len| at(JsonEvent::new(&serde_json::json!("x".repeat(payload_len))));
+        let size = |event: &Timestamped<EventType<JsonEvent>>| {
+            serde_json::to_vec(&crate::Event::to_json(event).unwrap())
+                .unwrap()
+                .len()
+        };
+        let overhead = size(&event(0));
+
+        let largest = event(MAX_EVENT_BYTES - overhead);
+        assert_eq!(size(&largest), MAX_EVENT_BYTES);
+        assert!(validate_event(&largest).is_ok());
+
+        let too_large = event(MAX_EVENT_BYTES - overhead + 1);
+        assert!(matches!(
+            validate_event(&too_large),
+            Err(AppendError::TooLarge { size }) if size == MAX_EVENT_BYTES + 1
+        ));
+    }
+
+    #[test]
+    fn test_events_that_do_not_round_trip_are_rejected() {
+        let kept = Lossy {
+            kept: "kept".to_string(),
+            dropped: 0,
+        };
+        assert!(validate_event(&at(kept.clone())).is_ok());
+
+        let lossy = Lossy { dropped: 1, ..kept };
+        assert!(matches!(
+            validate_event(&at(lossy)),
+            Err(AppendError::RoundTrip)
+        ));
+    }
+
+    #[test]
+    fn test_both_append_paths_return_invalid_events_as_errors() {
+        let mut store: EventStore<String, String> = EventStore::default();
+        let too_large = JsonEvent::new(&serde_json::json!("x".repeat(MAX_EVENT_BYTES)));
+        let lossy = Lossy {
+            kept: "kept".to_string(),
+            dropped: 1,
+        };
+
+        let result = store.add_raw_event(
+            "journal".to_string(),
+            "phone".to_string(),
+            too_large.clone(),
+            None,
+        );
+        assert!(matches!(result, Err(AppendError::TooLarge { .. })));
+        let result = store.add_raw_event(
+            "lossy".to_string(),
+            "phone".to_string(),
+            lossy.clone(),
+            None,
+        );
+        assert!(matches!(result, Err(AppendError::RoundTrip)));
+
+        // A valid event alongside doesn't get the invalid one through
+        let now = chrono::Utc::now();
+        let result = store.add_raw_events(
+            "journal".to_string(),
+            "phone".to_string(),
+            vec![
+                (now, JsonEvent::new(&serde_json::json!("small"))),
+                (now, too_large),
+            ],
+            None,
+        );
+        assert!(matches!(result, Err(AppendError::TooLarge { .. })));
+        let result = store.add_raw_events(
+            "lossy".to_string(),
+            "phone".to_string(),
+            vec![(now, lossy)],
+            None,
+        );
+        assert!(matches!(result, Err(AppendError::RoundTrip)));
+
+        assert_eq!(num_events(&store, "journal"), 0);
+        assert_eq!(num_events(&store, "lossy"), 0);
+    }
+}
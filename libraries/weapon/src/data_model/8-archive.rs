@@ -0,0 +1,115 @@
+//! # Archive
+//! Old events are rarely needed, but every device keeps them in memory and every new device downloads them.
+//! A stream's oldest events can be rolled into an archive: an immutable blob that's stored next to the stream (locally and on the server), while the events themselves are dropped from memory.
+//! Archived events still count as their device's events, so indices and sync clocks are unaffected, but the stream's state only covers the events after the archive until it's loaded back with `EventStore::load_archive`.
+
+use std::collections::BTreeMap;
+use std::hash::Hash;
+
+use crate::data_model::{EventStore, ListenerKey, Timestamped};
+
+/// What an archive holds. Streams reference their archive by its manifest.
+#[derive(Clone, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+#[serde(bound(
+    serialize = "Device: serde::Serialize + Ord",
+    deserialize = "Device: serde::Deserialize<'de> + Ord"
+))]
+pub struct ArchiveManifest<Device> {
+    /// Every archived event is from before this
+    pub cutoff: chrono::DateTime<chrono::Utc>,
+    /// How many of each device's first events are archived
+    pub counts: BTreeMap<Device, usize>,
+}
+
+impl<Device> ArchiveManifest<Device> {
+    /// Archives with the same cutoff and number of events hold the same events, so this
+    /// identifies the blob
+    pub fn id(&self) -> String {
+        format!("{}-{}", self.cutoff.timestamp_millis(), self.num_events())
+    }
+
+    pub fn num_events(&self) -> usize {
+        self.counts.values().sum()
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+#[serde(bound(
+    serialize = "Device: serde::Serialize + Ord",
+    deserialize = "Device: serde::Deserialize<'de> + Ord"
+))]
+pub struct StreamArchive<Device> {
+    pub manifest: ArchiveManifest<Device>,
+    pub events: BTreeMap<Device, Vec<Timestamped<serde_json::Value>>>,
+}
+
+impl<Stream, Device> EventStore<Stream, Device>
+where
+    Stream: Eq + Hash + Clone + Ord,
+    Device: Eq + Hash + Clone + Ord + 'static,
+{
+    /// Rolls `stream`'s events from before `cutoff` into an archive. Only events counted in
+    /// `synced` are archived (e.g. the server's clock for the stream), since once they're unloaded
+    /// they can't be uploaded anymore. The archive always starts from each device's first event,
+    /// so it replaces any older archive.
+    ///
+    /// The events stay loaded: store the archive, then drop them with `unload_archived`. Returns
+    /// `None` if there's nothing to archive, or if the stream isn't fully loaded.
+    pub fn create_archive(
+        &self,
+        stream: Stream,
+        cutoff: chrono::DateTime<chrono::Utc>,
+        synced: &BTreeMap<Device, usize>,
+    ) -> Option<StreamArchive<Device>> {
+        let mut events = self.get_raw(stream)?.archivable_jsons(cutoff, synced)?;
+        events.retain(|_, events| !events.is_empty());
+        if events.is_empty() {
+            return None;
+        }
+        let counts = events
+            .iter()
+            .map(|(device, events)| (device.clone(), events.len()))
+            .collect();
+        Some(StreamArchive {
+            manifest: ArchiveManifest { cutoff, counts },
+            events,
+        })
+    }
+
+    /// Drops the events in `manifest` from memory. Returns false if the stream doesn't exist.
+    pub fn unload_archived(
+        &mut self,
+        stream: &Stream,
+        manifest: &ArchiveManifest<Device>,
+        modifier: Option<ListenerKey>,
+    ) -> bool {
+        let Some(mut store) = self.get_mut_raw(stream, modifier) else {
+            return false;
+        };
+        store.unload_archived(&manifest.counts);
+        true
+    }
+
+    /// Puts archived events back into memory, e.g. before a full replay or an export. Devices
+    /// whose archived events aren't all in `events` stay archived. Returns how many events were
+    /// loaded.
+    pub fn load_archive(
+        &mut self,
+        stream: &Stream,
+        events: BTreeMap<Device, Vec<Timestamped<serde_json::Value>>>,
+        modifier: Option<ListenerKey>,
+    ) -> Result<usize, serde_json::Error> {
+        let Some(mut store) = self.get_mut_raw(stream, modifier) else {
+            return Ok(0);
+        };
+        store.load_archived_jsons(events)
+    }
+
+    /// False if some of `stream`'s events are archived and not loaded
+    pub fn is_fully_loaded(&self, stream: Stream) -> bool {
+        self.get_raw(stream)
+            .is_none_or(|store| store.archived_per_device().is_empty())
+    }
+}
@@ -0,0 +1,300 @@
+//! # Snapshot
+//! Replaying years of events is what makes a fresh device slow to become usable. If the app can save its partial state (see `SnapshotState`), a stream's state can be snapshotted along with the vector clock it was taken at, and a device that has the snapshot only replays the events after it.
+//! Events are applied in timestamp order, so a snapshot only holds while every event after it is newer than every event in it. If a device that was offline syncs older events later on, the snapshot is stale and the state is replayed from the start again.
+//! A snapshot that leaves the device is an `EncryptedSnapshot`, sealed with a `SnapshotKey` that only the user's devices have, so whoever stores it can't read the state.
+
+use std::collections::BTreeMap;
+use std::hash::Hash;
+
+use base64::Engine as _;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+
+use crate::PartialAppState;
+use crate::data_model::{EventStreamStore, EventType, Timestamped, process_events_and_metaevents};
+
+/// App states whose partial state can be saved and restored
+pub trait SnapshotState: PartialAppState {
+    /// `None` if this partial state can't be saved
+    fn save_partial(partial: &Self::Partial) -> Option<serde_json::Value>;
+
+    /// Restores a saved partial state. `initial_state` is what replaying would have started from,
+    /// so it has anything that isn't saved (like reference data). `None` if the snapshot doesn't
+    /// fit it, in which case every event is replayed instead.
+    fn restore_partial(
+        snapshot: &serde_json::Value,
+        initial_state: &Self::Partial,
+    ) -> Option<Self::Partial>;
+}
+
+#[derive(Clone, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+#[serde(bound(
+    serialize = "Device: serde::Serialize + Ord",
+    deserialize = "Device: serde::Deserialize<'de> + Ord"
+))]
+pub struct StateSnapshot<Device> {
+    /// How many of each device's events the snapshot includes
+    pub clock: BTreeMap<Device, usize>,
+    /// When the newest included event happened
+    pub as_of: Option<chrono::DateTime<chrono::Utc>>,
+    /// From `SnapshotState::save_partial`
+    pub state: serde_json::Value,
+}
+
+impl<Device> StateSnapshot<Device> {
+    pub fn num_events(&self) -> usize {
+        self.clock.values().sum()
+    }
+}
+
+/// The key snapshots are encrypted with. It has to be the same on each of the user's devices, and
+/// never be sent to the server.
+#[derive(Clone)]
+pub struct SnapshotKey(Key);
+
+impl SnapshotKey {
+    pub const LEN: usize = 32;
+
+    /// `None` unless `bytes` is `SnapshotKey::LEN` long
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        (bytes.len() == Self::LEN).then(|| SnapshotKey(*Key::from_slice(bytes)))
+    }
+}
+
+impl std::fmt::Debug for SnapshotKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("SnapshotKey(..)")
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum SnapshotCryptoError {
+    #[error("Snapshot could not be serialized")]
+    Serialize(#[source] serde_json::Error),
+
+    #[error("Snapshot could not be encrypted")]
+    Encrypt,
+
+    #[error("Snapshot could not be decrypted: it is corrupted or was encrypted with another key")]
+    Decrypt,
+
+    #[error("Decrypted snapshot could not be parsed")]
+    Deserialize(#[source] serde_json::Error),
+}
+
+/// A `StateSnapshot` encrypted with ChaCha20-Poly1305, as it's uploaded. Both fields are base64.
+#[derive(Clone, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EncryptedSnapshot {
+    nonce: String,
+    ciphertext: String,
+}
+
+impl EncryptedSnapshot {
+    /// Encrypts `snapshot` with a fresh random nonce
+    pub fn seal<Device: serde::Serialize + Ord>(
+        snapshot: &StateSnapshot<Device>,
+        key: &SnapshotKey,
+    ) -> Result<Self, SnapshotCryptoError> {
+        let plaintext = serde_json::to_vec(snapshot).map_err(SnapshotCryptoError::Serialize)?;
+        let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let ciphertext = ChaCha20Poly1305::new(&key.0)
+            .encrypt(&nonce, plaintext.as_slice())
+            .map_err(|_| SnapshotCryptoError::Encrypt)?;
+        Ok(EncryptedSnapshot {
+            nonce: BASE64.encode(nonce),
+            ciphertext: BASE64.encode(ciphertext),
+        })
+    }
+
+    /// Fails if the snapshot was tampered with or encrypted with another key
+    pub fn open<Device: serde::de::DeserializeOwned + Ord>(
+        &self,
+        key: &SnapshotKey,
+    ) -> Result<StateSnapshot<Device>, SnapshotCryptoError> {
+        let nonce = BASE64
+            .decode(&self.nonce)
+            .ok()
+            .filter(|nonce| nonce.len() == 12)
+            .ok_or(SnapshotCryptoError::Decrypt)?;
+        let ciphertext = BASE64
+            .decode(&self.ciphertext)
+            .map_err(|_| SnapshotCryptoError::Decrypt)?;
+        let plaintext = ChaCha20Poly1305::new(&key.0)
+            .decrypt(Nonce::from_slice(&nonce), ciphertext.as_slice())
+            .map_err(|_| SnapshotCryptoError::Decrypt)?;
+        serde_json::from_slice(&plaintext).map_err(SnapshotCryptoError::Deserialize)
+    }
+}
+
+impl<Device: Eq + Hash + Clone + Ord, Event: Ord + Clone + crate::Event>
+    EventStreamStore<Device, Timestamped<EventType<Event>>>
+{
+    /// Replays every event and saves the partial state. `None` if the stream isn't fully loaded,
+    /// or the state can't be saved.
+    pub fn snapshot<A>(&self, initial_state: A::Partial) -> Option<StateSnapshot<Device>>
+    where
+        A: SnapshotState<Event = Event>,
+    {
+        if !self.is_fully_loaded() {
+            return None;
+        }
+        let partial = process_events_and_metaevents::<Event, A>(self.iter(), initial_state);
+        Some(StateSnapshot {
+            clock: self
+                .events()
+                .iter()
+                .map(|(device, events)| (device.clone(), events.len()))
+                .collect(),
+            as_of: self.iter().map(|event| event.timestamp).max(),
+            state: A::save_partial(&partial)?,
+        })
+    }
+
+    /// The events after `snapshot`, if it's still valid: they all have to be loaded, and newer
+    /// than the snapshot
+    fn events_after<'a>(
+        &'a self,
+        snapshot: &StateSnapshot<Device>,
+    ) -> Option<Vec<&'a Timestamped<EventType<Event>>>> {
+        let seen = |device: &Device| snapshot.clock.get(device).copied().unwrap_or(0);
+        // A device can't have fewer events than a snapshot that includes them
+        if snapshot
+            .clock
+            .iter()
+            .any(|(device, count)| self.len_device(device) < *count)
+        {
+            return None;
+        }
+        if self
+            .archived()
+            .iter()
+            .any(|(device, archived)| *archived > seen(device))
+        {
+            return None;
+        }
+
+        let mut events: Vec<_> = self
+            .events()
+            .iter()
+            .flat_map(|(device, events)| {
+                let seen = seen(device);
+                events
+                    .iter()
+                    .filter(move |event| event.within_device_events_index >= seen)
+            })
+            .collect();
+        events.sort();
+        let is_newer = |event: &&Timestamped<EventType<Event>>| {
+            snapshot.as_of.is_none_or(|as_of| event.timestamp > as_of)
+        };
+        events.iter().all(is_newer).then_some(events)
+    }
+
+    /// The state, starting from `snapshot` and only replaying the events after it. `None` if the
+    /// snapshot is stale or doesn't fit `initial_state`; use `state` then, once every event is
+    /// loaded.
+    pub fn state_from_snapshot<A>(
+        &self,
+        snapshot: &StateSnapshot<Device>,
+        initial_state: &A::Partial,
+    ) -> Option<A>
+    where
+        A: SnapshotState<Event = Event>,
+    {
+        let events = self.events_after(snapshot)?;
+        let partial = A::restore_partial(&snapshot.state, initial_state)?;
+        Some(A::finalize(process_events_and_metaevents::<Event, A>(
+            events.into_iter(),
+            partial,
+        )))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data_model::EventStore;
+    use crate::json_stream::{JsonEvent, JsonFold, JsonState};
+
+    fn add(store: &mut EventStore<String, String>, device: &str, seconds: i64, value: &str) {
+        store
+            .add_raw_events(
+                "journal".to_string(),
+                device.to_string(),
+                vec![(
+                    chrono::DateTime::from_timestamp(seconds, 0).unwrap(),
+                    JsonEvent::new(&serde_json::json!(value)),
+                )],
+                None,
+            )
+            .unwrap();
+    }
+
+    #[test]
+    fn test_snapshot_and_events_after_it_match_a_full_replay() {
+        let mut store: EventStore<String, String> = EventStore::default();
+        add(&mut store, "phone", 10, "first");
+        add(&mut store, "laptop", 20, "second");
+        add(&mut store, "phone", 30, "third");
+
+        let initial = JsonState::new(JsonFold::Log);
+        let snapshot = store
+            .get::<EventType<JsonEvent>>("journal".to_string())
+            .unwrap()
+            .snapshot::<JsonState>(initial.clone())
+            .unwrap();
+        assert_eq!(snapshot.num_events(), 3);
+
+        add(&mut store, "laptop", 40, "fourth");
+        add(&mut store, "tablet", 50, "fifth");
+        add(&mut store, "phone", 60, "sixth");
+        let stream = store
+            .get::<EventType<JsonEvent>>("journal".to_string())
+            .unwrap();
+
+        let events_after: Vec<_> = stream
+            .events_after(&snapshot)
+            .unwrap()
+            .into_iter()
+            .map(|event| event.timestamp.timestamp())
+            .collect();
+        assert_eq!(events_after, vec![40, 50, 60]);
+
+        let from_snapshot: JsonState = stream.state_from_snapshot(&snapshot, &initial).unwrap();
+        let replayed: JsonState = stream.state(initial);
+        assert_eq!(from_snapshot.value, replayed.value);
+        assert_eq!(
+            replayed.value,
+            serde_json::json!(["first", "second", "third", "fourth", "fifth", "sixth"])
+        );
+    }
+
+    #[test]
+    fn test_encrypted_snapshot_only_opens_with_its_key() {
+        let snapshot = StateSnapshot {
+            clock: BTreeMap::from([("phone".to_string(), 2)]),
+            as_of: chrono::DateTime::from_timestamp(20, 0),
+            state: serde_json::json!(["first", "second"]),
+        };
+        let key = SnapshotKey::from_bytes(&[7; SnapshotKey::LEN]).unwrap();
+        let sealed = EncryptedSnapshot::seal(&snapshot, &key).unwrap();
+
+        let uploaded = serde_json::to_string(&sealed).unwrap();
+        assert!(!uploaded.contains("second"));
+        let downloaded: EncryptedSnapshot = serde_json::from_str(&uploaded).unwrap();
+        assert_eq!(downloaded.open::<String>(&key).unwrap(), snapshot);
+
+        // Each upload has its own nonce
+        assert_ne!(EncryptedSnapshot::seal(&snapshot, &key).unwrap(), sealed);
+
+        let other_key = SnapshotKey::from_bytes(&[8; SnapshotKey::LEN]).unwrap();
+        assert!(matches!(
+            sealed.open::<String>(&other_key),
+            Err(SnapshotCryptoError::Decrypt)
+        ));
+        assert!(SnapshotKey::from_bytes(&[7; 16]).is_none());
+    }
+}
@@ -19,12 +19,40 @@ mod dirty_tracker;
 #[path = "7-event-store.rs"]
 mod event_store;
 
+#[path = "8-archive.rs"]
+mod archive;
+
+#[path = "9-snapshot.rs"]
+mod snapshot;
+
+#[path = "10-quarantine.rs"]
+mod quarantine;
+
+#[path = "11-duplicates.rs"]
+mod duplicates;
+
+#[path = "12-branches.rs"]
+mod branches;
+
+#[path = "13-stream-lifecycle.rs"]
+mod stream_lifecycle;
+
+#[path = "14-sync-health.rs"]
+mod sync_health;
+
+pub use archive::*;
+pub use branches::*;
 pub use dirty_tracker::*;
+pub use duplicates::*;
 pub use event::*;
 pub use event_store::*;
 pub use event_stream_store::*;
 pub use event_type::*;
+pub use quarantine::*;
+pub use snapshot::*;
+pub use stream_lifecycle::*;
 pub use stream_store::*;
+pub use sync_health::*;
 pub use timestamped::*;
 
 #[cfg_attr(target_arch = "wasm32", wasm_bindgen::prelude::wasm_bindgen)]
@@ -92,6 +120,689 @@ mod tests {
         assert_eq!(collected, vec!["apple", "apricot", "banana", "cherry"]);
     }
 
+    #[derive(
+        Clone, Debug, PartialEq, Eq, PartialOrd, Ord, serde::Serialize, serde::Deserialize,
+    )]
+    enum OldEvent {
+        Known,
+    }
+
+    impl Event for OldEvent {
+        fn to_json(&self) -> Result<serde_json::Value, serde_json::Error> {
+            serde_json::to_value(self)
+        }
+
+        fn from_json(json: &serde_json::Value) -> Result<Self, serde_json::Error> {
+            serde_json::from_value(json.clone())
+        }
+    }
+
+    #[test]
+    fn test_unknown_events_are_kept() {
+        let known = serde_json::json!({ "User": "Known" });
+        let event = EventType::<OldEvent>::from_json(&known).unwrap();
+        assert_eq!(event, EventType::User(OldEvent::Known));
+        assert!(!event.is_unknown());
+
+        let from_the_future = serde_json::json!({ "User": "AddedLater" });
+        let event = EventType::<OldEvent>::from_json(&from_the_future).unwrap();
+        assert!(event.is_unknown());
+        assert_eq!(event.to_json().unwrap(), from_the_future);
+    }
+
+    #[derive(
+        Clone, Debug, PartialEq, Eq, PartialOrd, Ord, serde::Serialize, serde::Deserialize,
+    )]
+    struct Score(i32);
+
+    impl Event for Score {
+        fn to_json(&self) -> Result<serde_json::Value, serde_json::Error> {
+            serde_json::to_value(self)
+        }
+
+        fn from_json(json: &serde_json::Value) -> Result<Self, serde_json::Error> {
+            serde_json::from_value(json.clone())
+        }
+
+        fn validate(&self) -> Validation {
+            match self.0 {
+                ..0 => Validation::Reject(format!("{} is negative", self.0)),
+                0 => Validation::Warn("zero".to_string()),
+                _ => Validation::Accept,
+            }
+        }
+    }
+
+    #[test]
+    fn test_rejected_events_are_not_added() {
+        let mut store: EventStore<String, String> = EventStore::default();
+        let add = |store: &mut EventStore<String, String>, score| {
+            store.add_raw_event(
+                "scores".to_string(),
+                "device".to_string(),
+                Score(score),
+                None,
+            )
+        };
+        add(&mut store, 3).unwrap();
+        add(&mut store, 0).unwrap();
+        let result = add(&mut store, -1);
+        assert!(
+            matches!(result, Err(AppendError::Rejected { reason }) if reason == "-1 is negative")
+        );
+
+        // A rejected batch adds nothing
+        let at = |seconds| chrono::DateTime::from_timestamp(seconds, 0).unwrap();
+        let result = store.add_raw_events(
+            "scores".to_string(),
+            "device".to_string(),
+            vec![(at(10), Score(1)), (at(20), Score(-2))],
+            None,
+        );
+        assert!(matches!(result, Err(AppendError::Rejected { .. })));
+
+        let stream = store.get::<EventType<Score>>("scores".to_string()).unwrap();
+        assert_eq!(stream.num_events(), 2);
+    }
+
+    #[derive(Clone, Debug, Default)]
+    struct Scores(Vec<i32>);
+
+    impl crate::PartialAppState for Scores {
+        type Event = Score;
+        type Partial = Scores;
+
+        fn process_event(mut partial: Scores, event: &Timestamped<Score>) -> Scores {
+            if event.event.0 == 13 {
+                panic!("unlucky");
+            }
+            partial.0.push(event.event.0);
+            partial
+        }
+
+        fn check_event(_partial: &Scores, event: &Timestamped<Score>) -> Result<(), String> {
+            if event.event.0 > 100 {
+                return Err(format!("{} is too high", event.event.0));
+            }
+            Ok(())
+        }
+
+        fn finalize(partial: Scores) -> Scores {
+            partial
+        }
+    }
+
+    #[test]
+    fn test_events_that_cant_be_applied_are_quarantined() {
+        let mut store: EventStore<String, String> = EventStore::default();
+        let at = |seconds| chrono::DateTime::from_timestamp(seconds, 0).unwrap();
+        store
+            .add_raw_events(
+                "scores".to_string(),
+                "device".to_string(),
+                vec![
+                    (at(10), Score(1)),
+                    (at(20), Score(13)),
+                    (at(30), Score(200)),
+                    (at(40), Score(2)),
+                ],
+                None,
+            )
+            .unwrap();
+
+        let stream = store.get::<EventType<Score>>("scores".to_string()).unwrap();
+        let (state, quarantined) = stream.state_with_quarantine::<Scores>(Scores::default());
+        assert_eq!(state.0, vec![1, 2]);
+        let quarantined: Vec<_> = quarantined
+            .into_iter()
+            .map(|event| (event.within_device_events_index, event.reason))
+            .collect();
+        assert_eq!(
+            quarantined,
+            vec![
+                (1, "unlucky".to_string()),
+                (2, "200 is too high".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_duplicates_are_removed() {
+        let at = |seconds| chrono::DateTime::from_timestamp(seconds, 0).unwrap();
+        let event = |index, seconds, score| Timestamped {
+            timestamp: at(seconds),
+            within_device_events_index: index,
+            event: EventType::User(Score(score)),
+        };
+        let device = "device".to_string();
+        let mut stream = EventStreamStore::default();
+        stream.add_event_unchecked(device.clone(), event(0, 10, 1));
+        // Uploaded again under its own index
+        stream.add_event_unchecked(device.clone(), event(1, 10, 1));
+        stream.add_event_unchecked(device.clone(), event(2, 20, 2));
+        // Under an index that's already used
+        stream.add_event_unchecked(device.clone(), event(2, 30, 3));
+        // The same payload at another time isn't a duplicate
+        stream.add_event_unchecked(device.clone(), event(3, 40, 1));
+
+        let removed: Vec<_> = stream
+            .remove_duplicates()
+            .unwrap()
+            .into_iter()
+            .map(|duplicate| (duplicate.within_device_events_index, duplicate.duplicate_of))
+            .collect();
+        assert_eq!(removed, vec![(1, 0), (2, 2)]);
+
+        let (state, _) = stream.state_with_quarantine::<Scores>(Scores::default());
+        assert_eq!(state.0, vec![1, 2, 1]);
+        assert_eq!(stream.len_device(&device), 4);
+        assert!(stream.duplicates().is_empty());
+    }
+
+    #[test]
+    fn test_remote_events_are_routed_by_schema() {
+        let mut store: EventStore<String, String> = EventStore::default();
+        store.register_schema::<EventType<OldEvent>>("old");
+
+        let event = serde_json::json!({
+            "timestamp": "2025-01-01T00:00:00Z",
+            "within_device_events_index": 0,
+            "event": { "User": "Known" },
+        });
+        let added = store
+            .add_remote_event_json(
+                "old:1".to_string(),
+                "device".to_string(),
+                event.clone(),
+                None,
+            )
+            .unwrap();
+        assert_eq!(added, 1);
+        let stream = store
+            .get::<EventType<OldEvent>>("old:1".to_string())
+            .unwrap();
+        assert_eq!(stream.num_events(), 1);
+
+        let result =
+            store.add_remote_event_json("new".to_string(), "device".to_string(), event, None);
+        assert!(matches!(result, Err(AppendError::UnknownStream { .. })));
+    }
+
+    #[test]
+    fn test_raw_events_are_added_in_timestamp_order() {
+        use crate::json_stream::{JsonEvent, JsonFold, JsonState};
+
+        let mut store: EventStore<String, String> = EventStore::default();
+        let at = |seconds| chrono::DateTime::from_timestamp(seconds, 0).unwrap();
+        let added = store
+            .add_raw_events(
+                "journal".to_string(),
+                "device".to_string(),
+                vec![
+                    (at(20), JsonEvent::new(&serde_json::json!("second"))),
+                    (at(10), JsonEvent::new(&serde_json::json!("first"))),
+                ],
+                None,
+            )
+            .unwrap();
+        assert_eq!(added, 2);
+
+        let stream = store
+            .get::<EventType<JsonEvent>>("journal".to_string())
+            .unwrap();
+        let state: JsonState = stream.state(JsonState::new(JsonFold::Log));
+        assert_eq!(state.value, serde_json::json!(["first", "second"]));
+    }
+
+    #[test]
+    fn test_merged_devices_are_reported_together() {
+        let mut store: EventStore<String, String> = EventStore::default();
+        let at = |seconds| chrono::DateTime::from_timestamp(seconds, 0).unwrap();
+        for (device, seconds) in [("old", 10), ("old", 20), ("new", 30)] {
+            store
+                .add_raw_events(
+                    "reviews".to_string(),
+                    device.to_string(),
+                    vec![(at(seconds), OldEvent::Known)],
+                    None,
+                )
+                .unwrap();
+        }
+
+        let merged = store
+            .merge_device(
+                "new".to_string(),
+                "old".to_string(),
+                "new".to_string(),
+                None,
+            )
+            .unwrap();
+        assert_eq!(merged, 1);
+        // Merging again changes nothing
+        let merged = store
+            .merge_device(
+                "new".to_string(),
+                "old".to_string(),
+                "new".to_string(),
+                None,
+            )
+            .unwrap();
+        assert_eq!(merged, 0);
+
+        let progress = store.device_progress(SyncTarget::Supabase);
+        assert_eq!(progress.len(), 1);
+        assert_eq!(progress[0].device, "new");
+        assert_eq!(progress[0].merged_from, vec!["old".to_string()]);
+        // Two events from each device, counting the merge itself
+        assert_eq!(progress[0].local_events, 4);
+
+        // The old device's events keep their indices, and the merge doesn't change the state
+        let stream = store
+            .get::<EventType<OldEvent>>("reviews".to_string())
+            .unwrap();
+        assert_eq!(stream.events()["old"].len(), 2);
+        let user_events = stream
+            .iter()
+            .filter(|event| matches!(event.event, EventType::User(_)))
+            .count();
+        assert_eq!(user_events, 3);
+    }
+
+    #[test]
+    fn test_archived_events_are_unloaded_and_loaded_back() {
+        use crate::json_stream::{JsonEvent, JsonFold, JsonState};
+        use std::collections::BTreeMap;
+
+        let mut store: EventStore<String, String> = EventStore::default();
+        let at = |seconds| chrono::DateTime::from_timestamp(seconds, 0).unwrap();
+        store
+            .add_raw_events(
+                "journal".to_string(),
+                "device".to_string(),
+                vec![
+                    (at(10), JsonEvent::new(&serde_json::json!("old"))),
+                    (at(20), JsonEvent::new(&serde_json::json!("older"))),
+                    (at(30), JsonEvent::new(&serde_json::json!("new"))),
+                ],
+                None,
+            )
+            .unwrap();
+
+        // Only synced events are archived
+        let synced = BTreeMap::from([("device".to_string(), 1)]);
+        let archive = store
+            .create_archive("journal".to_string(), at(25), &synced)
+            .unwrap();
+        assert_eq!(archive.manifest.counts["device"], 1);
+        let synced = BTreeMap::from([("device".to_string(), 3)]);
+        let archive = store
+            .create_archive("journal".to_string(), at(25), &synced)
+            .unwrap();
+        assert_eq!(archive.manifest.counts["device"], 2);
+
+        assert!(store.unload_archived(&"journal".to_string(), &archive.manifest, None));
+        assert!(!store.is_fully_loaded("journal".to_string()));
+        // Archived events still count, so the next event gets the next index
+        assert_eq!(store.vector_clock()["journal"]["device"], 3);
+        let stream = store
+            .get::<EventType<JsonEvent>>("journal".to_string())
+            .unwrap();
+        let state: JsonState = stream.state(JsonState::new(JsonFold::Log));
+        assert_eq!(state.value, serde_json::json!(["new"]));
+        assert!(
+            store
+                .create_archive("journal".to_string(), at(25), &synced)
+                .is_none()
+        );
+
+        let loaded = store
+            .load_archive(&"journal".to_string(), archive.events, None)
+            .unwrap();
+        assert_eq!(loaded, 2);
+        assert!(store.is_fully_loaded("journal".to_string()));
+        let stream = store
+            .get::<EventType<JsonEvent>>("journal".to_string())
+            .unwrap();
+        let state: JsonState = stream.state(JsonState::new(JsonFold::Log));
+        assert_eq!(state.value, serde_json::json!(["old", "older", "new"]));
+    }
+
+    #[test]
+    fn test_state_is_replayed_from_snapshot() {
+        use crate::json_stream::{JsonEvent, JsonFold, JsonState};
+
+        let mut store: EventStore<String, String> = EventStore::default();
+        let at = |seconds| chrono::DateTime::from_timestamp(seconds, 0).unwrap();
+        let add = |store: &mut EventStore<String, String>, device: &str, seconds, value: &str| {
+            store
+                .add_raw_events(
+                    "journal".to_string(),
+                    device.to_string(),
+                    vec![(at(seconds), JsonEvent::new(&serde_json::json!(value)))],
+                    None,
+                )
+                .unwrap();
+        };
+        add(&mut store, "phone", 10, "first");
+        add(&mut store, "phone", 20, "second");
+
+        let initial = JsonState::new(JsonFold::Log);
+        let stream = store
+            .get::<EventType<JsonEvent>>("journal".to_string())
+            .unwrap();
+        let mut snapshot = stream.snapshot::<JsonState>(initial.clone()).unwrap();
+        assert_eq!(snapshot.num_events(), 2);
+        // Only the snapshot's state is used for the events it includes
+        snapshot.state = serde_json::json!(["from snapshot"]);
+
+        add(&mut store, "phone", 30, "third");
+        let stream = store
+            .get::<EventType<JsonEvent>>("journal".to_string())
+            .unwrap();
+        let state: JsonState = stream.state_from_snapshot(&snapshot, &initial).unwrap();
+        assert_eq!(state.value, serde_json::json!(["from snapshot", "third"]));
+
+        // An event from before the snapshot means it's stale
+        add(&mut store, "laptop", 15, "late");
+        let stream = store
+            .get::<EventType<JsonEvent>>("journal".to_string())
+            .unwrap();
+        assert!(
+            stream
+                .state_from_snapshot::<JsonState>(&snapshot, &initial)
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn test_branches_are_merged_as_new_events() {
+        use crate::json_stream::{JsonEvent, JsonFold, JsonState};
+
+        let mut store: EventStore<String, String> = EventStore::default();
+        let at = |seconds| chrono::DateTime::from_timestamp(seconds, 0).unwrap();
+        let add = |store: &mut EventStore<String, String>, stream: &str, seconds, value: &str| {
+            store
+                .add_raw_events(
+                    stream.to_string(),
+                    "phone".to_string(),
+                    vec![(at(seconds), JsonEvent::new(&serde_json::json!(value)))],
+                    None,
+                )
+                .unwrap();
+        };
+        let state = |store: &EventStore<String, String>, stream: &str| {
+            let stream = store
+                .get::<EventType<JsonEvent>>(stream.to_string())
+                .unwrap();
+            stream
+                .state::<JsonState>(JsonState::new(JsonFold::Log))
+                .value
+        };
+        add(&mut store, "journal", 10, "before");
+
+        let journal = "journal".to_string();
+        let branch = store
+            .fork_stream("phone".to_string(), &journal, "sandbox", None)
+            .unwrap();
+        assert_eq!(branch, "journal~sandbox");
+        assert_eq!(store.branches(&journal), vec![branch.clone()]);
+        assert!(matches!(
+            store.fork_stream("phone".to_string(), &journal, "sandbox", None),
+            Err(BranchError::AlreadyExists { .. })
+        ));
+
+        add(&mut store, &branch, 20, "experiment");
+        // Branches are never uploaded
+        store.update_sync_clock(
+            SyncTarget::Supabase,
+            std::collections::BTreeMap::from([(
+                journal.clone(),
+                std::collections::BTreeMap::from([("phone".to_string(), 1)]),
+            )]),
+        );
+        assert_eq!(
+            store.get_timestamp_of_earliest_unsynced_event(SyncTarget::Supabase),
+            None
+        );
+        assert_eq!(
+            store.get_timestamp_of_earliest_unsynced_event(SyncTarget::Opfs),
+            Some(at(10))
+        );
+
+        add(&mut store, "journal", 30, "meanwhile");
+        let states = store
+            .compare_branch::<JsonEvent, JsonState>(&branch, JsonState::new(JsonFold::Log))
+            .unwrap();
+        assert_eq!(
+            states.base.value,
+            serde_json::json!(["before", "meanwhile"])
+        );
+        assert_eq!(
+            states.branch.value,
+            serde_json::json!(["before", "experiment"])
+        );
+
+        let merged = store
+            .merge_branch("phone".to_string(), &branch, None)
+            .unwrap();
+        assert_eq!(merged, 1);
+        assert!(store.branches(&journal).is_empty());
+        assert_eq!(
+            state(&store, "journal"),
+            serde_json::json!(["before", "experiment", "meanwhile"])
+        );
+        assert_eq!(store.vector_clock()["journal"]["phone"], 3);
+
+        let branch = store
+            .fork_stream("phone".to_string(), &journal, "another", None)
+            .unwrap();
+        add(&mut store, &branch, 40, "thrown away");
+        assert!(store.discard_branch(&branch));
+        assert!(!store.discard_branch(&journal));
+        assert_eq!(
+            state(&store, "journal"),
+            serde_json::json!(["before", "experiment", "meanwhile"])
+        );
+    }
+
+    #[test]
+    fn test_stream_lifecycle_transitions() {
+        use LifecycleEvent as E;
+        use StreamLifecycle as S;
+
+        // Every state and event, so a new one can't be added without deciding how it behaves
+        let transitions = [
+            (S::Created, E::Loaded, S::Loaded),
+            (S::Created, E::Notified, S::Created),
+            (S::Loaded, E::Loaded, S::Loaded),
+            (S::Loaded, E::Notified, S::Ready),
+            (S::Ready, E::Loaded, S::Ready),
+            (S::Ready, E::Notified, S::Ready),
+        ];
+        for (state, event, expected) in transitions {
+            assert_eq!(state.next(event), expected, "{state:?} after {event:?}");
+        }
+
+        assert_eq!(S::Created.status(), StreamStatus::Loading);
+        assert_eq!(S::Loaded.status(), StreamStatus::Loaded);
+        assert_eq!(S::Ready.status(), StreamStatus::Ready);
+        assert!(!S::Created.is_loaded());
+        assert!(S::Loaded.is_loaded() && S::Ready.is_loaded());
+    }
+
+    #[test]
+    fn test_streams_are_ready_once_loaded_and_notified() {
+        use crate::json_stream::JsonEvent;
+        use std::cell::RefCell;
+        use std::rc::Rc;
+
+        let mut store: EventStore<String, String> = EventStore::default();
+        let journal = "journal".to_string();
+        let notified = Rc::new(RefCell::new(Vec::new()));
+        let notified_clone = notified.clone();
+        store.register_listener(move |_, stream: String| notified_clone.borrow_mut().push(stream));
+        assert_eq!(store.stream_status(&journal), StreamStatus::Missing);
+
+        // Events arriving before the load don't make the stream loaded
+        store
+            .add_raw_events(
+                journal.clone(),
+                "phone".to_string(),
+                vec![(
+                    chrono::DateTime::from_timestamp(10, 0).unwrap(),
+                    JsonEvent::new(&serde_json::json!("synced")),
+                )],
+                None,
+            )
+            .unwrap();
+        for notification in store.drain_due_notifications() {
+            notification();
+        }
+        assert_eq!(store.stream_status(&journal), StreamStatus::Loading);
+        assert!(!store.loaded_at_least_once(&journal));
+
+        // Loading is announced to the listeners exactly once
+        notified.borrow_mut().clear();
+        assert!(store.mark_loaded(journal.clone(), None));
+        assert_eq!(store.stream_status(&journal), StreamStatus::Loaded);
+        for notification in store.drain_due_notifications() {
+            notification();
+        }
+        assert_eq!(*notified.borrow(), vec![journal.clone()]);
+        assert_eq!(store.stream_status(&journal), StreamStatus::Ready);
+
+        assert!(!store.mark_loaded(journal.clone(), None));
+        assert!(store.drain_due_notifications().is_empty());
+        assert_eq!(store.stream_status(&journal), StreamStatus::Ready);
+        assert!(!store.mark_loaded("unknown".to_string(), None));
+
+        // A stream with nothing in local storage is still loaded
+        store.register_schema::<EventType<JsonEvent>>("");
+        assert!(store.mark_loaded("settings".to_string(), None));
+        assert_eq!(
+            store.stream_status(&"settings".to_string()),
+            StreamStatus::Loaded
+        );
+    }
+
+    #[test]
+    fn test_sync_health() {
+        use crate::json_stream::JsonEvent;
+
+        let mut store: EventStore<String, String> = EventStore::default();
+        let at = |seconds| chrono::DateTime::from_timestamp(seconds, 0).unwrap();
+        let add = |store: &mut EventStore<String, String>, seconds| {
+            store
+                .add_raw_events(
+                    "journal".to_string(),
+                    "phone".to_string(),
+                    vec![(at(seconds), JsonEvent::new(&serde_json::json!(seconds)))],
+                    None,
+                )
+                .unwrap();
+        };
+        add(&mut store, 0);
+        add(&mut store, 60);
+
+        let health = store.sync_health(SyncTarget::Supabase, at(120));
+        assert_eq!(health.status, SyncHealthStatus::Green);
+        assert_eq!(health.pending_events, 2);
+        assert_eq!(health.staleness_seconds, 120);
+        assert_eq!(health.last_successful_sync, None);
+
+        let an_hour_later = at(60 * 60);
+        assert_eq!(
+            store
+                .sync_health(SyncTarget::Supabase, an_hour_later)
+                .status,
+            SyncHealthStatus::Yellow
+        );
+        let two_days_later = at(2 * 24 * 60 * 60);
+        assert_eq!(
+            store
+                .sync_health(SyncTarget::Supabase, two_days_later)
+                .status,
+            SyncHealthStatus::Red
+        );
+
+        // Only the second event is left once the first is synced
+        store.mark_sync_started(SyncTarget::Supabase);
+        assert!(store.sync_health(SyncTarget::Supabase, at(120)).syncing);
+        store.update_sync_clock(
+            SyncTarget::Supabase,
+            std::collections::BTreeMap::from([(
+                "journal".to_string(),
+                std::collections::BTreeMap::from([("phone".to_string(), 1)]),
+            )]),
+        );
+        store.mark_sync_finished(SyncTarget::Supabase, None);
+        let health = store.sync_health(SyncTarget::Supabase, an_hour_later);
+        assert!(!health.syncing);
+        assert_eq!(health.pending_events, 1);
+        assert_eq!(health.earliest_unsynced_event, Some(at(60)));
+        assert!(health.last_successful_sync.is_some());
+
+        // A failed sync keeps the last successful one
+        store.mark_sync_finished(SyncTarget::Supabase, Some("offline".to_string()));
+        let failed = store.sync_health(SyncTarget::Supabase, at(120));
+        assert_eq!(failed.status, SyncHealthStatus::Yellow);
+        assert_eq!(failed.last_successful_sync, health.last_successful_sync);
+    }
+
+    #[test]
+    fn test_clock_deltas_round_trip() {
+        use crate::sync_protocol::{apply_clock_delta, clock_delta};
+        use std::collections::BTreeMap;
+
+        let clock = |streams: &[(&str, &[(&str, usize)])]| -> Clock<String, String> {
+            streams
+                .iter()
+                .map(|(stream, devices)| {
+                    let devices = devices
+                        .iter()
+                        .map(|(device, count)| (device.to_string(), *count))
+                        .collect();
+                    (stream.to_string(), devices)
+                })
+                .collect()
+        };
+        let baseline = clock(&[
+            ("reviews", &[("phone", 10), ("laptop", 4), ("tablet", 7)]),
+            ("settings", &[("phone", 2)]),
+        ]);
+        let local = clock(&[
+            ("reviews", &[("phone", 12), ("tablet", 7), ("watch", 1)]),
+            ("journal", &[("phone", 3)]),
+            ("journal~sandbox", &[("phone", 1)]),
+        ]);
+
+        // Only what changed is sent, and branches aren't sent at all
+        let delta = clock_delta(&local, &baseline);
+        assert_eq!(
+            delta,
+            BTreeMap::from([
+                (
+                    "journal".to_string(),
+                    Some(BTreeMap::from([("phone".to_string(), Some(3))]))
+                ),
+                (
+                    "reviews".to_string(),
+                    Some(BTreeMap::from([
+                        ("laptop".to_string(), None),
+                        ("phone".to_string(), Some(12)),
+                        ("watch".to_string(), Some(1)),
+                    ]))
+                ),
+                ("settings".to_string(), None),
+            ])
+        );
+
+        let mut synced = local.clone();
+        synced.remove("journal~sandbox");
+        assert_eq!(apply_clock_delta(&baseline, &delta), synced);
+        assert!(clock_delta(&baseline, &baseline).is_empty());
+    }
+
     #[test]
     fn test_interleaved_ordering() {
         let mut events = EventStreamStore::default();
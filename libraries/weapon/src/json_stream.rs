@@ -0,0 +1,180 @@
+//! # JSON streams
+//! Streams whose events are arbitrary JSON, for apps that want their own streams (journal entries,
+//! settings, ...) without defining a Rust event type for each of them. How a stream's state is
+//! computed from its events is picked with a `JsonFold` when the stream is created.
+
+use crate::data_model::{Event, Timestamped};
+
+/// An event whose contents are up to the app. It's kept as serialized JSON so it can be ordered.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct JsonEvent(String);
+
+impl JsonEvent {
+    pub fn new(value: &serde_json::Value) -> Self {
+        Self(value.to_string())
+    }
+
+    pub fn value(&self) -> serde_json::Value {
+        serde_json::from_str(&self.0).expect("JsonEvent always holds valid JSON")
+    }
+}
+
+impl Event for JsonEvent {
+    fn to_json(&self) -> Result<serde_json::Value, serde_json::Error> {
+        serde_json::from_str(&self.0)
+    }
+
+    fn from_json(json: &serde_json::Value) -> Result<Self, serde_json::Error> {
+        Ok(Self::new(json))
+    }
+}
+
+/// How the events of a JSON stream are folded into its state
+#[derive(Clone, Copy, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum JsonFold {
+    /// The state is an array of every event, in order. Good for things like journal entries.
+    Log,
+    /// The state starts as `{}` and each event is applied to it as a JSON merge patch (RFC 7386).
+    /// Good for things like settings.
+    Merge,
+}
+
+#[derive(Debug, thiserror::Error)]
+#[error("Unknown schema tag `{0}`, expected `log` or `merge`")]
+pub struct UnknownSchemaTag(pub String);
+
+impl std::str::FromStr for JsonFold {
+    type Err = UnknownSchemaTag;
+
+    fn from_str(tag: &str) -> Result<Self, Self::Err> {
+        serde_json::from_value(serde_json::Value::String(tag.to_string()))
+            .map_err(|_| UnknownSchemaTag(tag.to_string()))
+    }
+}
+
+/// The state of a JSON stream
+#[derive(Clone, Debug, PartialEq)]
+pub struct JsonState {
+    pub fold: JsonFold,
+    pub value: serde_json::Value,
+}
+
+impl JsonState {
+    /// The state of a stream with no events
+    pub fn new(fold: JsonFold) -> Self {
+        let value = match fold {
+            JsonFold::Log => serde_json::Value::Array(Vec::new()),
+            JsonFold::Merge => serde_json::Value::Object(serde_json::Map::new()),
+        };
+        Self { fold, value }
+    }
+}
+
+impl crate::PartialAppState for JsonState {
+    type Event = JsonEvent;
+    type Partial = Self;
+
+    fn process_event(mut state: Self::Partial, event: &Timestamped<Self::Event>) -> Self::Partial {
+        let event = event.event.value();
+        match state.fold {
+            JsonFold::Log => {
+                if let serde_json::Value::Array(events) = &mut state.value {
+                    events.push(event);
+                }
+            }
+            JsonFold::Merge => merge_patch(&mut state.value, event),
+        }
+        state
+    }
+
+    fn finalize(state: Self::Partial) -> Self {
+        state
+    }
+}
+
+impl crate::data_model::SnapshotState for JsonState {
+    fn save_partial(partial: &Self::Partial) -> Option<serde_json::Value> {
+        Some(partial.value.clone())
+    }
+
+    fn restore_partial(
+        snapshot: &serde_json::Value,
+        initial_state: &Self::Partial,
+    ) -> Option<Self::Partial> {
+        Some(Self {
+            fold: initial_state.fold,
+            value: snapshot.clone(),
+        })
+    }
+}
+
+fn merge_patch(target: &mut serde_json::Value, patch: serde_json::Value) {
+    let serde_json::Value::Object(patch) = patch else {
+        *target = patch;
+        return;
+    };
+    if !target.is_object() {
+        *target = serde_json::Value::Object(serde_json::Map::new());
+    }
+    let serde_json::Value::Object(target) = target else {
+        unreachable!("target was just made an object");
+    };
+    for (key, value) in patch {
+        if value.is_null() {
+            target.remove(&key);
+        } else {
+            merge_patch(target.entry(key).or_insert(serde_json::Value::Null), value);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::PartialAppState as _;
+    use serde_json::json;
+
+    fn fold(fold: JsonFold, events: &[serde_json::Value]) -> serde_json::Value {
+        let state =
+            events
+                .iter()
+                .enumerate()
+                .fold(JsonState::new(fold), |state, (index, event)| {
+                    JsonState::process_event(
+                        state,
+                        &Timestamped {
+                            timestamp: chrono::Utc::now(),
+                            within_device_events_index: index,
+                            event: JsonEvent::new(event),
+                        },
+                    )
+                });
+        JsonState::finalize(state).value
+    }
+
+    #[test]
+    fn test_log_keeps_every_event() {
+        let events = [json!({ "entry": "one" }), json!({ "entry": "two" })];
+        assert_eq!(fold(JsonFold::Log, &events), json!(events));
+    }
+
+    #[test]
+    fn test_merge_applies_patches() {
+        let events = [
+            json!({ "theme": "dark", "audio": { "speed": 1.0, "voice": "a" } }),
+            json!({ "audio": { "speed": 1.5, "voice": null } }),
+        ];
+        assert_eq!(
+            fold(JsonFold::Merge, &events),
+            json!({ "theme": "dark", "audio": { "speed": 1.5 } })
+        );
+    }
+
+    #[test]
+    fn test_schema_tags() {
+        assert_eq!("log".parse::<JsonFold>().unwrap(), JsonFold::Log);
+        assert_eq!("merge".parse::<JsonFold>().unwrap(), JsonFold::Merge);
+        assert!("Merge".parse::<JsonFold>().is_err());
+    }
+}
@@ -22,6 +22,9 @@ pub mod opfs;
 pub mod indexeddb;
 
 pub mod data_model;
+pub mod json_stream;
+pub mod settings;
+pub mod sync_protocol;
 
 use crate::data_model::{Event, Timestamped};
 
@@ -37,6 +40,16 @@ pub trait PartialAppState: Sized {
     /// This is called for each event when applying multiple events.
     fn process_event(partial: Self::Partial, event: &Timestamped<Self::Event>) -> Self::Partial;
 
+    /// Called before each event is processed. An event it returns an error for can't be applied
+    /// (e.g. `process_event` would panic on it), so it's skipped instead. See
+    /// `EventStreamStore::state_with_quarantine`.
+    fn check_event(
+        _partial: &Self::Partial,
+        _event: &Timestamped<Self::Event>,
+    ) -> Result<(), String> {
+        Ok(())
+    }
+
     /// Finalize the state by computing any derived state (e.g., statistical models).
     /// This is called once after all events have been processed.
     fn finalize(partial: Self::Partial) -> Self;
@@ -14,10 +14,20 @@ use opfs::{
     persistent::{self, DirectoryHandle, FileHandle},
 };
 
-use crate::data_model::{Clock, EventStore, IndexedEvent, ListenerKey, SyncTarget, Timestamped};
+use crate::data_model::{
+    ArchiveManifest, Clock, ClockBaseline, EventStore, IndexedEvent, ListenerKey, StateSnapshot,
+    StreamArchive, SyncTarget, Timestamped, is_branch_stream,
+};
 use futures::{Stream, StreamExt};
 
 const EVENTS_FILE_NAME: &str = "events.blob";
+/// Says which of the stream's events are archived (see `StreamArchive`). The archived events stay
+/// in the event log, but aren't loaded until they're asked for.
+const ARCHIVE_MANIFEST_FILE_NAME: &str = "archive.json";
+/// The stream's latest `StateSnapshot`, so the state can be computed without replaying every event
+const SNAPSHOT_FILE_NAME: &str = "snapshot.json";
+/// The Supabase `ClockBaseline`, kept in the user's directory since it covers every stream
+const CLOCK_BASELINE_FILE_NAME: &str = "clock_baseline.json";
 const EVENT_LOG_MAGIC: &[u8] = b"WEAPONLG";
 const EVENT_LOG_VERSION: u32 = 1;
 const EVENT_LOG_HEADER_LEN: usize = EVENT_LOG_MAGIC.len() + 4;
@@ -99,6 +109,21 @@ impl EventStore<String, String> {
         let stream_directory = user_directory.get_stream_directory(&stream_id).await?;
         let event_log_file = stream_directory.get_event_log_file().await?;
 
+        // Skipping the archived events below means they're never parsed
+        let nothing_loaded = store
+            .borrow()
+            .get_raw(stream_id.clone())
+            .is_none_or(|s| s.num_events() == 0);
+        if nothing_loaded && let Some(manifest) = stream_directory.read_archive_manifest().await? {
+            let mut store_mut = store.borrow_mut();
+            if store_mut
+                .get_or_insert_from_schema(stream_id.clone(), modifier)
+                .is_some()
+            {
+                store_mut.unload_archived(&stream_id, &manifest, modifier);
+            }
+        }
+
         let mut counts: BTreeMap<String, usize> = {
             let store_ref = store.borrow();
             store_ref
@@ -243,6 +268,319 @@ impl EventStore<String, String> {
         Ok(total_written)
     }
 
+    /// Replaces `stream_id`'s event log with the events in memory, e.g. after
+    /// `EventStore::remove_duplicates` changed ones that were already saved. Nothing is written
+    /// unless the stream is fully loaded, since archived events would be lost. Returns how many
+    /// events were written.
+    pub async fn rewrite_local_storage(
+        store: &RefCell<EventStore<String, String>>,
+        user_directory: &UserDirectory,
+        stream_id: String,
+    ) -> Result<usize, persistent::Error> {
+        let _guard = weblocks::acquire(
+            &format!("opfs-save-to-local-storage-{stream_id}"),
+            weblocks::AcquireOptions::exclusive(),
+        )
+        .await
+        .unwrap();
+
+        let records: Vec<EventLogRecord> = {
+            let store_ref = store.borrow();
+            let Some(stream) = store_ref.get_raw(stream_id.clone()) else {
+                log::warn!("Stream {stream_id} not found in store, skipping rewrite");
+                return Ok(0);
+            };
+            if !stream.archived_per_device().is_empty() {
+                log::error!(
+                    "Stream {stream_id} has archived events that aren't loaded, so it can't be rewritten"
+                );
+                return Ok(0);
+            }
+            let mut devices: Vec<&String> = stream.num_events_per_device().into_keys().collect();
+            devices.sort();
+            devices
+                .into_iter()
+                .flat_map(|device_id| {
+                    stream
+                        .jsons(device_id, 0)
+                        .into_iter()
+                        .map(|event| EventLogRecord {
+                            device_id: device_id.clone(),
+                            within_device_events_index: event.within_device_events_index(),
+                            event,
+                        })
+                })
+                .collect()
+        };
+
+        user_directory
+            .get_stream_directory(&stream_id)
+            .await?
+            .get_event_log_file()
+            .await?
+            .rewrite_records(&records)
+            .await?;
+        Ok(records.len())
+    }
+
+    /// Deletes everything saved for `stream_id`, e.g. after `EventStore::discard_branch`. Only
+    /// branches can be deleted, since other streams' events might not be on the server yet.
+    pub async fn delete_from_local_storage(
+        user_directory: &UserDirectory,
+        stream_id: String,
+    ) -> Result<(), persistent::Error> {
+        if !is_branch_stream(&stream_id) {
+            log::error!("Stream {stream_id} isn't a branch, so it can't be deleted");
+            return Ok(());
+        }
+        let _guard = weblocks::acquire(
+            &format!("opfs-save-to-local-storage-{stream_id}"),
+            weblocks::AcquireOptions::exclusive(),
+        )
+        .await
+        .unwrap();
+
+        user_directory
+            .directory_handle
+            .remove_entry_with_options(
+                &format!("stream__{stream_id}"),
+                &opfs::FileSystemRemoveOptions { recursive: true },
+            )
+            .await
+    }
+
+    /// Saves `stream_id`'s events, records `archive` as its archive and drops the archived events
+    /// from memory. They stay in the event log, which `load_archive_from_local_storage` reads them
+    /// back from.
+    pub async fn archive_to_local_storage(
+        store: &RefCell<EventStore<String, String>>,
+        user_directory: &UserDirectory,
+        stream_id: String,
+        archive: &StreamArchive<String>,
+        modifier: Option<ListenerKey>,
+    ) -> Result<(), persistent::Error> {
+        // The archived events have to be on disk before they're dropped from memory
+        Self::save_to_local_storage(store, user_directory, stream_id.clone()).await?;
+        user_directory
+            .get_stream_directory(&stream_id)
+            .await?
+            .write_archive_manifest(&archive.manifest)
+            .await?;
+        store
+            .borrow_mut()
+            .unload_archived(&stream_id, &archive.manifest, modifier);
+        Ok(())
+    }
+
+    /// Writes an archive that was downloaded from the server (e.g. on a new device) to the event
+    /// log, and records it as the stream's archive, so the next `load_from_local_storage` starts
+    /// after it. Only events the event log doesn't have yet are written. Returns how many were.
+    pub async fn write_archive_to_local_storage(
+        user_directory: &UserDirectory,
+        stream_id: &str,
+        archive: &StreamArchive<String>,
+    ) -> Result<usize, persistent::Error> {
+        let stream_directory = user_directory.get_stream_directory(stream_id).await?;
+        let event_log_file = stream_directory.get_event_log_file().await?;
+        let device_counts_on_disk = event_log_file.device_counts().await?;
+
+        let mut records_to_append = Vec::new();
+        for (device_id, events) in &archive.events {
+            let on_disk = device_counts_on_disk.get(device_id).copied().unwrap_or(0);
+            let mut events: Vec<_> = events
+                .iter()
+                .filter(|event| event.within_device_events_index >= on_disk)
+                .collect();
+            events.sort_by_key(|event| event.within_device_events_index);
+            // Anything else would leave a gap in the event log
+            let contiguous = events
+                .iter()
+                .enumerate()
+                .all(|(offset, event)| event.within_device_events_index == on_disk + offset);
+            let complete = on_disk + events.len()
+                >= archive.manifest.counts.get(device_id).copied().unwrap_or(0);
+            if !contiguous || !complete {
+                log::error!("Archive for stream {stream_id} is missing events of {device_id}");
+                return Ok(0);
+            }
+            records_to_append.extend(events.into_iter().map(|event| EventLogRecord {
+                device_id: device_id.clone(),
+                within_device_events_index: event.within_device_events_index,
+                event: event.clone(),
+            }));
+        }
+
+        event_log_file.append_records(&records_to_append).await?;
+        stream_directory
+            .write_archive_manifest(&archive.manifest)
+            .await?;
+        Ok(records_to_append.len())
+    }
+
+    /// Keeps `snapshot` (e.g. one downloaded from the server) next to `stream_id`'s event log,
+    /// replacing the previous one
+    pub async fn write_snapshot_to_local_storage(
+        user_directory: &UserDirectory,
+        stream_id: &str,
+        snapshot: &StateSnapshot<String>,
+    ) -> Result<(), persistent::Error> {
+        user_directory
+            .get_stream_directory(stream_id)
+            .await?
+            .write_json_file(SNAPSHOT_FILE_NAME, snapshot)
+            .await
+    }
+
+    /// The snapshot last written with `write_snapshot_to_local_storage`, if any
+    pub async fn read_snapshot_from_local_storage(
+        user_directory: &UserDirectory,
+        stream_id: &str,
+    ) -> Result<Option<StateSnapshot<String>>, persistent::Error> {
+        user_directory
+            .get_stream_directory(stream_id)
+            .await?
+            .read_json_file(SNAPSHOT_FILE_NAME)
+            .await
+    }
+
+    /// Saves the Supabase `ClockBaseline`, so the first sync after a reload can also send its
+    /// clock as changes from it
+    pub async fn save_clock_baseline_to_local_storage(
+        store: &RefCell<EventStore<String, String>>,
+        user_directory: &UserDirectory,
+    ) -> Result<(), persistent::Error> {
+        let baseline = store
+            .borrow()
+            .sync_state(SyncTarget::Supabase)
+            .and_then(|state| state.clock_baseline.clone());
+        write_json_file(
+            &user_directory.directory_handle,
+            CLOCK_BASELINE_FILE_NAME,
+            &baseline,
+        )
+        .await
+    }
+
+    /// Restores the baseline saved with `save_clock_baseline_to_local_storage`, unless a sync has
+    /// already replaced it
+    pub async fn load_clock_baseline_from_local_storage(
+        store: &RefCell<EventStore<String, String>>,
+        user_directory: &UserDirectory,
+    ) -> Result<(), persistent::Error> {
+        let has_baseline = |store: &EventStore<String, String>| {
+            store
+                .sync_state(SyncTarget::Supabase)
+                .is_some_and(|state| state.clock_baseline.is_some())
+        };
+        if has_baseline(&store.borrow()) {
+            return Ok(());
+        }
+        let baseline: Option<Option<ClockBaseline<String, String>>> =
+            read_json_file(&user_directory.directory_handle, CLOCK_BASELINE_FILE_NAME).await?;
+        let mut store = store.borrow_mut();
+        if !has_baseline(&store) {
+            store.set_clock_baseline(SyncTarget::Supabase, baseline.flatten());
+        }
+        Ok(())
+    }
+
+    /// Loads `stream_id`'s archived events back from the event log, e.g. before a full replay or
+    /// an export. Returns how many were loaded.
+    pub async fn load_archive_from_local_storage(
+        store: &RefCell<EventStore<String, String>>,
+        user_directory: &UserDirectory,
+        stream_id: String,
+        modifier: Option<ListenerKey>,
+    ) -> Result<usize, persistent::Error> {
+        let archived: BTreeMap<String, usize> = store
+            .borrow()
+            .get_raw(stream_id.clone())
+            .map(|s| {
+                s.archived_per_device()
+                    .into_iter()
+                    .map(|(device, count)| (device.clone(), count))
+                    .collect()
+            })
+            .unwrap_or_default();
+        if archived.is_empty() {
+            return Ok(0);
+        }
+
+        let records = user_directory
+            .get_stream_directory(&stream_id)
+            .await?
+            .get_event_log_file()
+            .await?
+            .read_records(&BTreeMap::new())
+            .await?;
+        let mut events: BTreeMap<String, Vec<Timestamped<serde_json::Value>>> = BTreeMap::new();
+        for record in records {
+            if archived
+                .get(&record.device_id)
+                .is_some_and(|count| record.within_device_events_index < *count)
+            {
+                events
+                    .entry(record.device_id)
+                    .or_default()
+                    .push(record.event);
+            }
+        }
+
+        Ok(store
+            .borrow_mut()
+            .load_archive(&stream_id, events, modifier)
+            .inspect_err(|e| log::error!("Error deserializing archived events: {e:?}"))
+            .unwrap_or(0))
+    }
+
+    /// Loads every stream in `user_directory` into `store` without writing anything to OPFS, so
+    /// another namespace (e.g. the logged-out one) can be inspected without disturbing it.
+    /// Streams that `store` has neither created nor registered a schema for are skipped.
+    pub async fn load_read_only(
+        store: &RefCell<EventStore<String, String>>,
+        user_directory: &UserDirectory,
+    ) -> Result<(), persistent::Error> {
+        let mut streams = user_directory.event_stream_directories().await?;
+        while let Some((stream_id, stream_directory)) = streams.next().await {
+            if store
+                .borrow_mut()
+                .get_or_insert_from_schema(stream_id.clone(), None)
+                .is_none()
+            {
+                continue;
+            }
+            let Ok(file_handle) = stream_directory
+                .directory_handle
+                .get_file_handle_with_options(
+                    EVENTS_FILE_NAME,
+                    &opfs::GetFileHandleOptions { create: false },
+                )
+                .await
+            else {
+                continue;
+            };
+
+            let records = EventLogFile { file_handle }
+                .read_records(&BTreeMap::new())
+                .await?;
+            let mut events_to_add: BTreeMap<String, Vec<Timestamped<serde_json::Value>>> =
+                BTreeMap::new();
+            for record in records {
+                events_to_add
+                    .entry(record.device_id)
+                    .or_default()
+                    .push(record.event);
+            }
+
+            let mut store_mut = store.borrow_mut();
+            for (device_id, events) in events_to_add {
+                store_mut.add_device_events_jsons(stream_id.clone(), device_id, events, None);
+            }
+        }
+
+        Ok(())
+    }
+
     /// Import events from the logged-out user directory into the current user's directory.
     /// This is used when a user first logs in so their offline data is preserved.
     pub async fn import_logged_out_user_data(
@@ -338,6 +676,19 @@ impl UserDirectory {
         })
     }
 
+    /// Like `new`, but doesn't create the directory. Returns `None` if it doesn't exist.
+    pub async fn open_existing(parent: &DirectoryHandle, user_id: &str) -> Option<Self> {
+        Some(Self {
+            directory_handle: parent
+                .get_directory_handle_with_options(
+                    &format!("user__{user_id}"),
+                    &opfs::GetDirectoryHandleOptions { create: false },
+                )
+                .await
+                .ok()?,
+        })
+    }
+
     #[allow(dead_code)]
     async fn event_stream_directories(
         &self,
@@ -393,6 +744,73 @@ impl StreamDirectory {
     }
 }
 
+impl StreamDirectory {
+    async fn read_archive_manifest(
+        &self,
+    ) -> Result<Option<ArchiveManifest<String>>, persistent::Error> {
+        self.read_json_file(ARCHIVE_MANIFEST_FILE_NAME).await
+    }
+
+    async fn write_archive_manifest(
+        &self,
+        manifest: &ArchiveManifest<String>,
+    ) -> Result<(), persistent::Error> {
+        self.write_json_file(ARCHIVE_MANIFEST_FILE_NAME, manifest)
+            .await
+    }
+
+    async fn read_json_file<T: serde::de::DeserializeOwned>(
+        &self,
+        file_name: &str,
+    ) -> Result<Option<T>, persistent::Error> {
+        read_json_file(&self.directory_handle, file_name).await
+    }
+
+    async fn write_json_file(
+        &self,
+        file_name: &str,
+        value: &impl serde::Serialize,
+    ) -> Result<(), persistent::Error> {
+        write_json_file(&self.directory_handle, file_name, value).await
+    }
+}
+
+/// `None` if the file doesn't exist, or can't be parsed (which is logged)
+async fn read_json_file<T: serde::de::DeserializeOwned>(
+    directory_handle: &DirectoryHandle,
+    file_name: &str,
+) -> Result<Option<T>, persistent::Error> {
+    let Ok(file_handle) = directory_handle
+        .get_file_handle_with_options(file_name, &opfs::GetFileHandleOptions { create: false })
+        .await
+    else {
+        return Ok(None);
+    };
+    let bytes = file_handle.read().await?;
+    Ok(serde_json::from_slice(&bytes)
+        .inspect_err(|e| log::error!("Failed to parse {file_name}: {e:?}"))
+        .ok())
+}
+
+async fn write_json_file(
+    directory_handle: &DirectoryHandle,
+    file_name: &str,
+    value: &impl serde::Serialize,
+) -> Result<(), persistent::Error> {
+    let bytes = serde_json::to_vec(value).expect("JSON files can always be serialized");
+    let mut file_handle = directory_handle
+        .get_file_handle_with_options(file_name, &opfs::GetFileHandleOptions { create: true })
+        .await?;
+    let mut writable = file_handle
+        .create_writable_with_options(&opfs::CreateWritableOptions {
+            keep_existing_data: false,
+        })
+        .await?;
+    writable.write_at_cursor_pos(bytes).await?;
+    writable.close().await?;
+    Ok(())
+}
+
 impl EventLogFile {
     async fn read_records(
         &self,
@@ -435,6 +853,25 @@ impl EventLogFile {
         Ok(())
     }
 
+    /// Replaces everything in the log with `records`
+    async fn rewrite_records(&self, records: &[EventLogRecord]) -> Result<(), persistent::Error> {
+        let mut file_handle = self.file_handle.clone();
+        let mut writable = file_handle
+            .create_writable_with_options(&opfs::CreateWritableOptions {
+                keep_existing_data: false,
+            })
+            .await?;
+        let mut bytes = event_log_header_bytes();
+        for record in records {
+            if let Some(record_bytes) = encode_event_log_record(record) {
+                bytes.extend(record_bytes);
+            }
+        }
+        writable.write_at_cursor_pos(bytes).await?;
+        writable.close().await?;
+        Ok(())
+    }
+
     async fn device_counts(&self) -> Result<BTreeMap<String, usize>, persistent::Error> {
         let bytes = self.file_handle.read().await?;
         Ok(parse_device_counts(&bytes))
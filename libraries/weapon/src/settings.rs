@@ -0,0 +1,197 @@
+//! # Settings
+//! A stream of the user's preferences, so they follow the user across devices. Each setting is a
+//! key with a JSON value, and each key is last-writer-wins: its value is the one from the latest
+//! event that set it, so settings changed on two devices while offline settle on whichever change
+//! was made last, without one device's change to another key being lost.
+//!
+//! Apps declare their settings as `Setting<T>` constants, which read and write `T` instead of raw
+//! JSON.
+
+use std::collections::BTreeMap;
+use std::marker::PhantomData;
+
+use crate::data_model::{Event, Timestamped};
+
+/// The stream settings are stored in
+pub const SETTINGS_STREAM: &str = "settings";
+
+/// A setting whose values are `T`
+pub struct Setting<T> {
+    pub key: &'static str,
+    value: PhantomData<fn() -> T>,
+}
+
+impl<T> Setting<T> {
+    pub const fn new(key: &'static str) -> Self {
+        Self {
+            key,
+            value: PhantomData,
+        }
+    }
+}
+
+/// Sets `key` to a value, or resets it to its default if the value is `None`. The value is kept as
+/// serialized JSON so the event can be ordered.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct SettingEvent {
+    key: String,
+    value: Option<String>,
+}
+
+impl SettingEvent {
+    pub fn new(key: String, value: Option<&serde_json::Value>) -> Self {
+        Self {
+            key,
+            value: value.map(serde_json::Value::to_string),
+        }
+    }
+
+    pub fn set<T: serde::Serialize>(
+        setting: &Setting<T>,
+        value: &T,
+    ) -> Result<Self, serde_json::Error> {
+        Ok(Self::new(
+            setting.key.to_string(),
+            Some(&serde_json::to_value(value)?),
+        ))
+    }
+
+    pub fn reset<T>(setting: &Setting<T>) -> Self {
+        Self::new(setting.key.to_string(), None)
+    }
+
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    pub fn value(&self) -> Option<serde_json::Value> {
+        self.value
+            .as_ref()
+            .map(|value| serde_json::from_str(value).expect("SettingEvent always holds valid JSON"))
+    }
+}
+
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(tag = "version")]
+enum VersionedSettingEvent {
+    V1 {
+        key: String,
+        value: Option<serde_json::Value>,
+    },
+}
+
+impl Event for SettingEvent {
+    fn to_json(&self) -> Result<serde_json::Value, serde_json::Error> {
+        serde_json::to_value(VersionedSettingEvent::V1 {
+            key: self.key.clone(),
+            value: self.value(),
+        })
+    }
+
+    fn from_json(json: &serde_json::Value) -> Result<Self, serde_json::Error> {
+        serde_json::from_value::<VersionedSettingEvent>(json.clone()).map(|versioned| {
+            match versioned {
+                VersionedSettingEvent::V1 { key, value } => Self::new(key, value.as_ref()),
+            }
+        })
+    }
+}
+
+/// Every setting that has been set, with when it was last set
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Settings {
+    values: BTreeMap<String, (chrono::DateTime<chrono::Utc>, serde_json::Value)>,
+}
+
+impl Settings {
+    /// The raw value of `key`, or `None` if it hasn't been set
+    pub fn get_raw(&self, key: &str) -> Option<&serde_json::Value> {
+        self.values.get(key).map(|(_, value)| value)
+    }
+
+    /// The value of `setting`, or `None` if it hasn't been set or the value doesn't fit `T` (e.g.
+    /// it was set by a newer version of the app)
+    pub fn get<T: serde::de::DeserializeOwned>(&self, setting: &Setting<T>) -> Option<T> {
+        serde_json::from_value(self.get_raw(setting.key)?.clone()).ok()
+    }
+}
+
+impl crate::PartialAppState for Settings {
+    type Event = SettingEvent;
+    type Partial = Self;
+
+    fn process_event(mut state: Self::Partial, event: &Timestamped<Self::Event>) -> Self::Partial {
+        // Events are applied in order, so this is only for events with the same timestamp
+        if state
+            .values
+            .get(&event.event.key)
+            .is_some_and(|(set_at, _)| *set_at > event.timestamp)
+        {
+            return state;
+        }
+        match event.event.value() {
+            Some(value) => {
+                state
+                    .values
+                    .insert(event.event.key.clone(), (event.timestamp, value));
+            }
+            None => {
+                state.values.remove(&event.event.key);
+            }
+        }
+        state
+    }
+
+    fn finalize(state: Self::Partial) -> Self {
+        state
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::PartialAppState as _;
+
+    const AUDIO_SPEED: Setting<f64> = Setting::new("audio_speed");
+    const THEME: Setting<String> = Setting::new("theme");
+
+    #[test]
+    fn test_last_writer_wins_per_key() {
+        let start = chrono::Utc::now();
+        let events = [
+            SettingEvent::set(&AUDIO_SPEED, &1.5).unwrap(),
+            SettingEvent::set(&THEME, &"dark".to_string()).unwrap(),
+            SettingEvent::set(&AUDIO_SPEED, &0.75).unwrap(),
+            SettingEvent::reset(&THEME),
+        ];
+        let settings =
+            events
+                .iter()
+                .enumerate()
+                .fold(Settings::default(), |state, (index, event)| {
+                    Settings::process_event(
+                        state,
+                        &Timestamped {
+                            timestamp: start + chrono::Duration::seconds(index as i64),
+                            within_device_events_index: index,
+                            event: event.clone(),
+                        },
+                    )
+                });
+        assert_eq!(settings.get(&AUDIO_SPEED), Some(0.75));
+        assert_eq!(settings.get(&THEME), None);
+        // A value of the wrong type reads as unset
+        assert_eq!(settings.get(&Setting::<String>::new("audio_speed")), None);
+    }
+
+    #[test]
+    fn test_events_round_trip_through_json() {
+        let event = SettingEvent::set(&AUDIO_SPEED, &1.25).unwrap();
+        let json = event.to_json().unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({ "version": "V1", "key": "audio_speed", "value": 1.25 })
+        );
+        assert_eq!(SettingEvent::from_json(&json).unwrap(), event);
+    }
+}
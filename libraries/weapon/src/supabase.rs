@@ -1,16 +1,196 @@
 //! Utilities for syncing against a Supabase database.
 use std::{cell::RefCell, collections::BTreeMap};
 
-use crate::data_model::{Clock, EventStore, ListenerKey, SyncTarget, Timestamped};
+use crate::data_model::{
+    ArchiveManifest, Clock, ClockBaseline, EncryptedSnapshot, EventStore, ListenerKey, SnapshotKey,
+    StateSnapshot, StreamArchive, SyncTarget,
+};
+use crate::sync_protocol::{
+    SyncResponse, clock_delta_request, delta_sync_request, parse_clock_delta_response,
+    parse_delta_sync_response, parse_rejection, parse_sync_response, sync_request,
+};
 use wasm_bindgen::{JsValue, prelude::wasm_bindgen};
 
-#[derive(serde::Serialize, serde::Deserialize, tsify::Tsify)]
+/// Which Supabase project to sync with, and what the sync tables and functions are called there.
+/// Only the URL and key are required; everything else defaults to the names Yap uses.
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize, tsify::Tsify)]
 #[tsify(into_wasm_abi, from_wasm_abi)]
 pub struct SupabaseConfig {
     pub supabase_url: String,
     pub supabase_anon_key: String,
+    /// The table events are uploaded to
+    #[serde(default = "default_events_table")]
+    pub events_table: String,
+    /// The function that returns the events we don't have yet
+    #[serde(default = "default_sync_rpc")]
+    pub sync_rpc: String,
+    /// The function that returns the server's event counts per stream and device
+    #[serde(default = "default_clock_rpc")]
+    pub clock_rpc: String,
+    /// Like `sync_rpc`, but takes only the changes from a `ClockBaseline`
+    #[serde(default = "default_sync_delta_rpc")]
+    pub sync_delta_rpc: String,
+    /// Like `clock_rpc`, but returns only the changes from a `ClockBaseline`, and keeps the clock
+    /// as the next one
+    #[serde(default = "default_clock_delta_rpc")]
+    pub clock_delta_rpc: String,
+    /// The Postgres schema the table and functions live in. `None` means the API's default schema
+    /// (usually `public`).
+    #[serde(default)]
+    pub schema: Option<String>,
+    /// The Storage bucket stream archives are uploaded to (see `StreamArchive`)
+    #[serde(default = "default_archive_bucket")]
+    pub archive_bucket: String,
+    /// The Storage bucket state snapshots are uploaded to (see `StateSnapshot`)
+    #[serde(default = "default_snapshot_bucket")]
+    pub snapshot_bucket: String,
+    /// The function `verify_remote_schema` asks about the schema (see `MIGRATIONS`)
+    #[serde(default = "default_schema_status_rpc")]
+    pub schema_status_rpc: String,
 }
 
+fn default_events_table() -> String {
+    "events".to_string()
+}
+
+fn default_sync_rpc() -> String {
+    "sync_events".to_string()
+}
+
+fn default_clock_rpc() -> String {
+    "get_clock".to_string()
+}
+
+fn default_sync_delta_rpc() -> String {
+    "sync_events_delta".to_string()
+}
+
+fn default_clock_delta_rpc() -> String {
+    "get_clock_delta".to_string()
+}
+
+fn default_archive_bucket() -> String {
+    "event-archives".to_string()
+}
+
+fn default_snapshot_bucket() -> String {
+    "state-snapshots".to_string()
+}
+
+fn default_schema_status_rpc() -> String {
+    "weapon_schema_status".to_string()
+}
+
+impl SupabaseConfig {
+    pub fn new(supabase_url: impl Into<String>, supabase_anon_key: impl Into<String>) -> Self {
+        Self {
+            supabase_url: supabase_url.into(),
+            supabase_anon_key: supabase_anon_key.into(),
+            events_table: default_events_table(),
+            sync_rpc: default_sync_rpc(),
+            clock_rpc: default_clock_rpc(),
+            sync_delta_rpc: default_sync_delta_rpc(),
+            clock_delta_rpc: default_clock_delta_rpc(),
+            schema: None,
+            archive_bucket: default_archive_bucket(),
+            snapshot_bucket: default_snapshot_bucket(),
+            schema_status_rpc: default_schema_status_rpc(),
+        }
+    }
+
+    pub fn with_events_table(mut self, events_table: impl Into<String>) -> Self {
+        self.events_table = events_table.into();
+        self
+    }
+
+    pub fn with_sync_rpc(mut self, sync_rpc: impl Into<String>) -> Self {
+        self.sync_rpc = sync_rpc.into();
+        self
+    }
+
+    pub fn with_clock_rpc(mut self, clock_rpc: impl Into<String>) -> Self {
+        self.clock_rpc = clock_rpc.into();
+        self
+    }
+
+    pub fn with_sync_delta_rpc(mut self, sync_delta_rpc: impl Into<String>) -> Self {
+        self.sync_delta_rpc = sync_delta_rpc.into();
+        self
+    }
+
+    pub fn with_clock_delta_rpc(mut self, clock_delta_rpc: impl Into<String>) -> Self {
+        self.clock_delta_rpc = clock_delta_rpc.into();
+        self
+    }
+
+    pub fn with_schema(mut self, schema: impl Into<String>) -> Self {
+        self.schema = Some(schema.into());
+        self
+    }
+
+    pub fn with_archive_bucket(mut self, archive_bucket: impl Into<String>) -> Self {
+        self.archive_bucket = archive_bucket.into();
+        self
+    }
+
+    pub fn with_snapshot_bucket(mut self, snapshot_bucket: impl Into<String>) -> Self {
+        self.snapshot_bucket = snapshot_bucket.into();
+        self
+    }
+
+    pub fn with_schema_status_rpc(mut self, schema_status_rpc: impl Into<String>) -> Self {
+        self.schema_status_rpc = schema_status_rpc.into();
+        self
+    }
+
+    fn rest_url(&self, path: &str) -> String {
+        format!("{}/rest/v1/{path}", self.supabase_url.trim_end_matches('/'))
+    }
+
+    /// Archives are stored per user and stream, next to a `manifest.json` that says which archive
+    /// is the stream's current one
+    fn archive_url(&self, user_id: &str, stream_id: &str, file_name: &str) -> String {
+        self.storage_url(&self.archive_bucket, user_id, stream_id, file_name)
+    }
+
+    /// Each stream has at most one snapshot, which is replaced by newer ones
+    fn snapshot_url(&self, user_id: &str, stream_id: &str) -> String {
+        self.storage_url(&self.snapshot_bucket, user_id, stream_id, "snapshot.json")
+    }
+
+    fn storage_url(&self, bucket: &str, user_id: &str, stream_id: &str, file_name: &str) -> String {
+        format!(
+            "{}/storage/v1/object/{bucket}/{user_id}/{stream_id}/{file_name}",
+            self.supabase_url.trim_end_matches('/'),
+        )
+    }
+
+    /// Storage doesn't know about Postgres schemas, so it only gets the auth headers
+    fn storage_headers(&self, access_token: &str) -> Vec<(&'static str, String)> {
+        vec![
+            ("apikey", self.supabase_anon_key.clone()),
+            ("Authorization", format!("Bearer {access_token}")),
+        ]
+    }
+
+    /// PostgREST picks the schema for reads from `Accept-Profile` and for writes (including RPC
+    /// calls, which are POSTs) from `Content-Profile`
+    fn headers(&self, access_token: &str) -> Vec<(&'static str, String)> {
+        let mut headers = vec![
+            ("apikey", self.supabase_anon_key.clone()),
+            ("Authorization", format!("Bearer {access_token}")),
+        ];
+        if let Some(schema) = &self.schema {
+            headers.push(("Content-Profile", schema.clone()));
+            headers.push(("Accept-Profile", schema.clone()));
+        }
+        headers
+    }
+}
+
+/// What an event is assumed to weigh when there's nothing to go on
+const DEFAULT_EVENT_BYTES: usize = 200;
+
 impl EventStore<String, String> {
     /// Sync with the server
     /// Return Ok(Some(new_events)) if we got new events from the server.
@@ -36,13 +216,11 @@ impl EventStore<String, String> {
         )
         .await
         {
-            Ok((res, final_remote_clock)) => {
-                store
-                    .borrow_mut()
-                    .mark_sync_finished(SyncTarget::Supabase, None);
-                store
-                    .borrow_mut()
-                    .update_sync_clock(SyncTarget::Supabase, final_remote_clock);
+            Ok((res, final_remote_clock, baseline)) => {
+                let mut store = store.borrow_mut();
+                store.mark_sync_finished(SyncTarget::Supabase, None);
+                store.update_sync_clock(SyncTarget::Supabase, final_remote_clock);
+                store.set_clock_baseline(SyncTarget::Supabase, baseline);
                 Ok(res)
             }
             Err(e) => {
@@ -62,20 +240,20 @@ impl EventStore<String, String> {
         user_id: &str,
         stream_id_to_sync: Option<String>,
         modifier: Option<ListenerKey>,
-    ) -> Result<(SupabaseSyncResult, Clock<String, String>), JsValue> {
+    ) -> Result<
+        (
+            SupabaseSyncResult,
+            Clock<String, String>,
+            Option<ClockBaseline<String, String>>,
+        ),
+        JsValue,
+    > {
         let mut sync_result = SupabaseSyncResult {
             uploaded_to_supabase: 0,
             downloaded_from_supabase: 0,
         };
 
         use fetch_happen::Client;
-        use serde_json::json;
-        use std::collections::HashMap;
-
-        let SupabaseConfig {
-            supabase_url,
-            supabase_anon_key,
-        } = &supabase_config;
 
         let vector_clock = store.borrow_mut().vector_clock();
         // If a stream_id_to_sync is provided, narrow the vector clock to just that stream.
@@ -88,107 +266,187 @@ impl EventStore<String, String> {
         } else {
             vector_clock
         };
+        let baseline = store
+            .borrow()
+            .sync_state(SyncTarget::Supabase)
+            .and_then(|state| state.clock_baseline.clone());
 
         // Download new events from server
-        let sync_url = format!("{supabase_url}/rest/v1/rpc/sync_events");
-        // Create multi-stream request format - wrapped in sync_request parameter
-        let payload = json!({
-            "sync_request": vector_clock.iter().map(|(stream_id, device_events)| {
-                (stream_id, json!({
-                    "last_synced_ids": device_events
-                }))
-            }).collect::<HashMap<_, _>>()
-        });
-
         let client = Client;
-        let response = client
-            .post(&sync_url)
-            .header("apikey", supabase_anon_key)
-            .header("Authorization", format!("Bearer {access_token}"))
-            .json(&payload)
-            .map_err(|e| JsValue::from_str(&format!("{e:?}")))?
-            .send()
-            .await
-            .map_err(|e| JsValue::from_str(&format!("{e:?}")))?;
-
-        if !response.ok() {
-            return Err(JsValue::from_str(&format!(
-                "Sync failed with status: {}",
-                response.status()
-            )));
-        }
-
-        let body = response
-            .text()
-            .await
-            .map_err(|e| JsValue::from_str(&format!("{e:?}")))?;
-
-        // Parse the multi-stream response format
-        #[allow(clippy::type_complexity)]
-        let sync_response: HashMap<
-            String,
-            HashMap<String, Vec<SyncEventResponse<Timestamped<serde_json::Value>>>>,
-        > = serde_json::from_str(&body).map_err(|e| {
-            JsValue::from_str(&format!(
-                "Failed to parse sync response: {e}\nResponse body: {body}"
-            ))
-        })?;
-
-        for (stream, device_events) in sync_response {
-            for (device, events) in device_events {
-                let events = events.into_iter().map(|event| event.event).collect();
-                sync_result.downloaded_from_supabase += store.borrow_mut().add_device_events_jsons(
-                    stream.clone(),
-                    device,
-                    events,
-                    modifier,
-                );
+        let sync_response = match &baseline {
+            Some(baseline) => {
+                download_delta(
+                    &client,
+                    &supabase_config,
+                    access_token,
+                    &vector_clock,
+                    baseline,
+                )
+                .await?
             }
-        }
+            None => None,
+        };
+        let sync_response = match sync_response {
+            Some(sync_response) => sync_response,
+            None => download_full(&client, &supabase_config, access_token, &vector_clock).await?,
+        };
+        sync_result.downloaded_from_supabase += store
+            .borrow_mut()
+            .add_sync_response(sync_response, modifier);
+
+        let (remote_clock, baseline) = get_clock_delta(
+            &client,
+            &supabase_config,
+            access_token,
+            user_id,
+            baseline.as_ref(),
+        )
+        .await?;
+        sync_result.uploaded_to_supabase += Self::upload_missing_events(
+            store,
+            &client,
+            &supabase_config,
+            access_token,
+            user_id,
+            &remote_clock,
+        )
+        .await?;
+
+        // Refresh the remote clock after potential uploads and record it.
+        // This captures the authoritative counts on the server post-sync.
+        let (final_remote_clock, baseline) = get_clock_delta(
+            &client,
+            &supabase_config,
+            access_token,
+            user_id,
+            baseline.as_ref(),
+        )
+        .await?;
+
+        log::info!("Sync complete");
+
+        Ok((sync_result, final_remote_clock, baseline))
+    }
+
+    /// Upload-only sync: pushes the local events the server doesn't have yet without downloading
+    /// anything. Meant for contexts like a service worker that only load the event logs from
+    /// OPFS, so it doesn't touch the sync state.
+    /// Returns the number of events uploaded.
+    pub async fn push_to_supabase(
+        store: &RefCell<EventStore<String, String>>,
+        access_token: &str,
+        supabase_config: SupabaseConfig,
+        user_id: &str,
+    ) -> Result<usize, JsValue> {
+        let client = fetch_happen::Client;
+        let remote_clock = get_clock(&client, &supabase_config, access_token, user_id).await?;
+        Self::upload_missing_events(
+            store,
+            &client,
+            &supabase_config,
+            access_token,
+            user_id,
+            &remote_clock,
+        )
+        .await
+    }
 
-        // Fetch remote event counts for all streams/devices in one RPC
+    /// Dry run of `sync_with_supabase`: only exchanges clocks, and returns how many events each
+    /// direction would transfer and roughly how many bytes that is. Nothing is uploaded,
+    /// downloaded or recorded in the sync state.
+    pub async fn preview_sync_with_supabase(
+        store: &RefCell<EventStore<String, String>>,
+        access_token: &str,
+        supabase_config: SupabaseConfig,
+        user_id: &str,
+    ) -> Result<SupabaseSyncPreview, JsValue> {
+        let client = fetch_happen::Client;
         let remote_clock = get_clock(&client, &supabase_config, access_token, user_id).await?;
+        Ok(store.borrow().sync_preview(&remote_clock, user_id))
+    }
 
-        // upload local events if needed
-        // first, collect them into a vector to avoid holding the lock across an .await
-        let events_to_upload = store
-            .borrow()
-            .iter()
-            .flat_map(|(stream_id, stream_events)| {
-                // Get all devices with events in this stream
-                let device_event_counts = stream_events.num_events_per_device();
-
-                // For each device, upload any events not yet on the server
-                device_event_counts
-                    .into_iter()
-                    .flat_map(|(local_device_id, _local_count)| {
-                        let device_events_on_db: usize = remote_clock
-                            .get(stream_id)
-                            .and_then(|device_map| {
-                                device_map.get(&local_device_id.to_string()).copied()
-                            })
-                            .unwrap_or(0);
-
-                        let events_to_upload =
-                            stream_events.jsons(local_device_id, device_events_on_db);
-
-                        events_to_upload
-                            .into_iter()
-                            .map(|event| SyncableEvent {
-                                user_id: user_id.to_string(),
-                                device_id: local_device_id.to_string(),
-                                created_at: event.timestamp.to_string(),
-                                within_device_events_index: event.within_device_events_index,
-                                event: serde_json::to_value(&event).unwrap(),
-                                stream_id: stream_id.clone(),
-                            })
-                            .collect::<Vec<_>>()
-                    })
-                    .collect::<Vec<_>>()
-            })
-            .collect::<Vec<_>>();
+    /// What syncing with a server whose clock is `remote_clock` would transfer, see
+    /// `preview_sync_with_supabase`
+    fn sync_preview(
+        &self,
+        remote_clock: &Clock<String, String>,
+        user_id: &str,
+    ) -> SupabaseSyncPreview {
+        let events_to_upload = self.events_missing_from(remote_clock, user_id);
+        let bytes_to_upload = serde_json::to_vec(&events_to_upload)
+            .map(|json| json.len())
+            .unwrap_or_default();
+
+        // We don't have the events we'd download, so assume they're the size of the ones we have
+        // in the same stream
+        let average_event_bytes = |stream_id: &String| {
+            let Some(stream_events) = self.get_raw(stream_id.clone()) else {
+                return DEFAULT_EVENT_BYTES;
+            };
+            let (count, bytes) = stream_events
+                .num_events_per_device()
+                .into_keys()
+                .flat_map(|device| stream_events.jsons(device, 0))
+                .fold((0, 0), |(count, bytes), event| {
+                    let event_bytes = serde_json::to_vec(&event)
+                        .map(|json| json.len())
+                        .unwrap_or_default();
+                    (count + 1, bytes + event_bytes)
+                });
+            if count == 0 {
+                DEFAULT_EVENT_BYTES
+            } else {
+                bytes / count
+            }
+        };
+
+        let local_clock = self.vector_clock();
+        let mut preview = SupabaseSyncPreview {
+            events_to_upload: events_to_upload.len(),
+            bytes_to_upload,
+            events_to_download: 0,
+            estimated_bytes_to_download: 0,
+        };
+        for (stream_id, remote_devices) in remote_clock {
+            let local_devices = local_clock.get(stream_id);
+            let missing: usize = remote_devices
+                .iter()
+                .map(|(device_id, remote_count)| {
+                    let local_count = local_devices
+                        .and_then(|devices| devices.get(device_id))
+                        .copied()
+                        .unwrap_or(0);
+                    remote_count.saturating_sub(local_count)
+                })
+                .sum();
+            if missing > 0 {
+                preview.events_to_download += missing;
+                preview.estimated_bytes_to_download += missing * average_event_bytes(stream_id);
+            }
+        }
+        preview
+    }
+
+    /// Uploads the local events that aren't on the server according to `remote_clock`. Returns
+    /// how many were uploaded.
+    ///
+    /// A rejected event fails the whole request, so when the server rejects one, the rest of its
+    /// device's events in its stream (which couldn't be stored after it anyway) are left out and
+    /// the others are sent again. The rejections are recorded in the sync state.
+    async fn upload_missing_events(
+        store: &RefCell<EventStore<String, String>>,
+        client: &fetch_happen::Client,
+        supabase_config: &SupabaseConfig,
+        access_token: &str,
+        user_id: &str,
+        remote_clock: &Clock<String, String>,
+    ) -> Result<usize, JsValue> {
+        // collect the events first to avoid holding the lock across an .await
+        let mut events_to_upload = store.borrow().events_missing_from(remote_clock, user_id);
 
-        if !events_to_upload.is_empty() {
+        let mut uploaded = 0;
+        let mut rejected_events = Vec::new();
+        while !events_to_upload.is_empty() {
             // Count unique devices we're uploading from
             let unique_devices: std::collections::HashSet<_> = events_to_upload
                 .iter()
@@ -200,90 +458,199 @@ impl EventStore<String, String> {
                 unique_devices.len()
             );
 
-            let upload_url = format!("{supabase_url}/rest/v1/events");
+            let upload_url = supabase_config.rest_url(&supabase_config.events_table);
 
-            let upload_response = client
-                .post(&upload_url)
-                .header("apikey", supabase_anon_key)
-                .header("Authorization", format!("Bearer {access_token}"))
+            let mut request = client.post(&upload_url);
+            for (name, value) in supabase_config.headers(access_token) {
+                request = request.header(name, value);
+            }
+            let upload_response = request
                 .json(&events_to_upload)
                 .map_err(|e| JsValue::from_str(&format!("{e:?}")))?
                 .send()
                 .await
                 .map_err(|e| JsValue::from_str(&format!("{e:?}")))?;
 
-            if !upload_response.ok() {
-                let status = upload_response.status();
-                let error_body = upload_response
-                    .text()
-                    .await
-                    .unwrap_or_else(|_| "Unknown error".to_string());
-                log::error!("Failed to upload events: {status} - {error_body}");
-            } else {
+            if upload_response.ok() {
                 log::info!("Successfully uploaded events");
-                sync_result.uploaded_to_supabase += events_to_upload.len();
+                uploaded = events_to_upload.len();
+                break;
             }
-        }
 
-        // Refresh the remote clock after potential uploads and record it.
-        // This captures the authoritative counts on the server post-sync.
-        let final_remote_clock =
-            get_clock(&client, &supabase_config, access_token, user_id).await?;
-
-        log::info!("Sync complete");
+            let status = upload_response.status();
+            let error_body = upload_response
+                .text()
+                .await
+                .unwrap_or_else(|_| "Unknown error".to_string());
+            let Some(rejected) = parse_rejection(&error_body) else {
+                log::error!("Failed to upload events: {status} - {error_body}");
+                break;
+            };
+            log::error!("The server rejected an event: {rejected:?}");
+            let count_before = events_to_upload.len();
+            events_to_upload.retain(|event| {
+                event.stream_id != rejected.stream || event.device_id != rejected.device
+            });
+            rejected_events.push(rejected);
+            if events_to_upload.len() == count_before {
+                break;
+            }
+        }
 
-        Ok((sync_result, final_remote_clock))
+        store
+            .borrow_mut()
+            .record_rejected_events(SyncTarget::Supabase, rejected_events);
+        Ok(uploaded)
     }
 }
 
-fn deserialize_event<'de, E, D>(deserializer: D) -> Result<E, D::Error>
-where
-    D: serde::de::Deserializer<'de>,
-    E: serde::de::DeserializeOwned,
-{
-    use serde::Deserialize;
-    use serde::de::Error;
+/// Asks the sync RPC for the events `vector_clock` doesn't have
+async fn download_full(
+    client: &fetch_happen::Client,
+    supabase_config: &SupabaseConfig,
+    access_token: &str,
+    vector_clock: &Clock<String, String>,
+) -> Result<SyncResponse, JsValue> {
+    let sync_url = supabase_config.rest_url(&format!("rpc/{}", supabase_config.sync_rpc));
+    // Create multi-stream request format - wrapped in sync_request parameter
+    let payload = sync_request(vector_clock);
 
-    // First try to deserialize directly
-    let value = serde_json::Value::deserialize(deserializer)?;
+    let mut request = client.post(&sync_url);
+    for (name, value) in supabase_config.headers(access_token) {
+        request = request.header(name, value);
+    }
+    let response = request
+        .json(&payload)
+        .map_err(|e| JsValue::from_str(&format!("{e:?}")))?
+        .send()
+        .await
+        .map_err(|e| JsValue::from_str(&format!("{e:?}")))?;
 
-    // If it's already an object, try to deserialize it directly
-    if value.is_object() {
-        return serde_json::from_value(value).map_err(D::Error::custom);
+    if !response.ok() {
+        return Err(JsValue::from_str(&format!(
+            "Sync failed with status: {}",
+            response.status()
+        )));
     }
 
-    // If it's a string, parse it as JSON
-    if let Some(s) = value.as_str() {
-        return serde_json::from_str(s).map_err(D::Error::custom);
+    let body = response
+        .text()
+        .await
+        .map_err(|e| JsValue::from_str(&format!("{e:?}")))?;
+
+    // Parse the multi-stream response format
+    parse_sync_response(&body).map_err(|e| {
+        JsValue::from_str(&format!(
+            "Failed to parse sync response: {e}\nResponse body: {body}"
+        ))
+    })
+}
+
+/// Like `download_full`, but only sends how `vector_clock` differs from `baseline`. `None` if the
+/// server can't fill in the rest, because it doesn't have the baseline or the delta sync RPC.
+async fn download_delta(
+    client: &fetch_happen::Client,
+    supabase_config: &SupabaseConfig,
+    access_token: &str,
+    vector_clock: &Clock<String, String>,
+    baseline: &ClockBaseline<String, String>,
+) -> Result<Option<SyncResponse>, JsValue> {
+    let payload = delta_sync_request(vector_clock, baseline);
+    let Some(body) = call_rpc(
+        client,
+        supabase_config,
+        access_token,
+        &supabase_config.sync_delta_rpc,
+        &payload,
+    )
+    .await?
+    else {
+        log::warn!(
+            "The server has no {} function, sending the whole clock",
+            supabase_config.sync_delta_rpc
+        );
+        return Ok(None);
+    };
+    let sync_response = parse_delta_sync_response(&body).map_err(|e| {
+        JsValue::from_str(&format!(
+            "Failed to parse delta sync response: {e}\nResponse body: {body}"
+        ))
+    })?;
+    if sync_response.is_none() {
+        log::info!("The server doesn't have our clock baseline, sending the whole clock");
     }
+    Ok(sync_response)
+}
 
-    // Otherwise, fail with an appropriate error
-    Err(D::Error::custom(
-        "Expected either a JSON object or a JSON string",
-    ))
+/// The server's clock, and the baseline the next sync can send its clock relative to. Only the
+/// changes from `baseline` are downloaded if the server still has it. Falls back to `get_clock`
+/// (and no baseline) if the server doesn't have the delta clock RPC.
+async fn get_clock_delta(
+    client: &fetch_happen::Client,
+    supabase_config: &SupabaseConfig,
+    access_token: &str,
+    user_id: &str,
+    baseline: Option<&ClockBaseline<String, String>>,
+) -> Result<(Clock<String, String>, Option<ClockBaseline<String, String>>), JsValue> {
+    let body = clock_delta_request(user_id, baseline);
+    let Some(text) = call_rpc(
+        client,
+        supabase_config,
+        access_token,
+        &supabase_config.clock_delta_rpc,
+        &body,
+    )
+    .await?
+    else {
+        let clock = get_clock(client, supabase_config, access_token, user_id).await?;
+        return Ok((clock, None));
+    };
+    let baseline = parse_clock_delta_response(&text, baseline).map_err(|e| {
+        JsValue::from_str(&format!(
+            "Failed to parse {} response: {e}. Body: {text}",
+            supabase_config.clock_delta_rpc
+        ))
+    })?;
+    Ok((baseline.clock.clone(), Some(baseline)))
 }
 
-#[derive(Debug, serde::Deserialize)]
-#[serde(bound(deserialize = "Event: serde::de::DeserializeOwned"))]
-struct SyncEventResponse<Event> {
-    #[expect(unused)]
-    id: u64,
-    #[serde(deserialize_with = "deserialize_event")]
-    event: Event,
-    #[expect(unused)]
-    // we will use this later, once we stop duplicating the within_device_events_index in Event itself
-    within_device_events_index: u32,
-}
-
-#[derive(serde::Serialize, serde::Deserialize, tsify::Tsify, Debug)]
-#[tsify(into_wasm_abi, from_wasm_abi)]
-pub struct SyncableEvent {
-    pub user_id: String,
-    pub device_id: String,
-    pub event: serde_json::Value,
-    pub created_at: String,
-    pub within_device_events_index: usize,
-    pub stream_id: String,
+/// Calls `rpc` with `body` and returns the response's body. `None` if PostgREST doesn't know the
+/// function, e.g. because the project's schema is older than this version of weapon.
+async fn call_rpc(
+    client: &fetch_happen::Client,
+    supabase_config: &SupabaseConfig,
+    access_token: &str,
+    rpc: &str,
+    body: &serde_json::Value,
+) -> Result<Option<String>, JsValue> {
+    let url = supabase_config.rest_url(&format!("rpc/{rpc}"));
+    let mut request = client.post(&url);
+    for (name, value) in supabase_config.headers(access_token) {
+        request = request.header(name, value);
+    }
+    let response = request
+        .json(body)
+        .map_err(|e| JsValue::from_str(&format!("{e:?}")))?
+        .send()
+        .await
+        .map_err(|e| JsValue::from_str(&format!("{e:?}")))?;
+
+    // PostgREST answers 404 for functions it doesn't know
+    if response.status() == 404 {
+        return Ok(None);
+    }
+    if !response.ok() {
+        return Err(JsValue::from_str(&format!(
+            "{rpc} RPC failed with status: {}",
+            response.status()
+        )));
+    }
+
+    response
+        .text()
+        .await
+        .map(Some)
+        .map_err(|e| JsValue::from_str(&format!("{e:?}")))
 }
 
 // Returns: { "<stream_id>": { "<device_id>": <event_count> } }
@@ -295,18 +662,14 @@ async fn get_clock(
 ) -> Result<Clock<String, String>, JsValue> {
     use serde_json::json;
 
-    let SupabaseConfig {
-        supabase_url,
-        supabase_anon_key,
-    } = supabase_config;
-
-    let url = format!("{supabase_url}/rest/v1/rpc/get_clock");
+    let url = supabase_config.rest_url(&format!("rpc/{}", supabase_config.clock_rpc));
     let body = json!({ "p_user_id": user_id });
 
-    let resp = client
-        .post(&url)
-        .header("apikey", supabase_anon_key)
-        .header("Authorization", format!("Bearer {access_token}"))
+    let mut request = client.post(&url);
+    for (name, value) in supabase_config.headers(access_token) {
+        request = request.header(name, value);
+    }
+    let resp = request
         .json(&body)
         .map_err(|e| JsValue::from_str(&format!("{e:?}")))?
         .send()
@@ -315,7 +678,8 @@ async fn get_clock(
 
     if !resp.ok() {
         return Err(JsValue::from_str(&format!(
-            "get_clock RPC failed with status: {}",
+            "{} RPC failed with status: {}",
+            supabase_config.clock_rpc,
             resp.status()
         )));
     }
@@ -327,16 +691,612 @@ async fn get_clock(
 
     let m: Clock<String, String> = serde_json::from_str(&text).map_err(|e| {
         JsValue::from_str(&format!(
-            "Failed to parse get_clock response: {e}. Body: {text}"
+            "Failed to parse {} response: {e}. Body: {text}",
+            supabase_config.clock_rpc
         ))
     })?;
 
     Ok(m)
 }
 
+/// Uploads `archive` and makes it the stream's current archive. Archives are immutable, so an
+/// archive that's already there is kept as is. The events stay in the events table; a device that
+/// starts from the archive just asks the sync function for the events after it.
+pub async fn upload_archive(
+    access_token: &str,
+    supabase_config: &SupabaseConfig,
+    user_id: &str,
+    stream_id: &str,
+    archive: &StreamArchive<String>,
+) -> Result<(), JsValue> {
+    let client = fetch_happen::Client;
+    let file_name = format!("{}.json", archive.manifest.id());
+
+    let mut request = client.post(&supabase_config.archive_url(user_id, stream_id, &file_name));
+    for (name, value) in supabase_config.storage_headers(access_token) {
+        request = request.header(name, value);
+    }
+    let response = request
+        .json(archive)
+        .map_err(|e| JsValue::from_str(&format!("{e:?}")))?
+        .send()
+        .await
+        .map_err(|e| JsValue::from_str(&format!("{e:?}")))?;
+    // 409 means the archive was already uploaded
+    if !response.ok() && response.status() != 409 {
+        return Err(JsValue::from_str(&format!(
+            "Archive upload failed with status: {}",
+            response.status()
+        )));
+    }
+
+    // The manifest points at the current archive, so it's the one thing that's overwritten
+    let mut request = client
+        .post(&supabase_config.archive_url(user_id, stream_id, "manifest.json"))
+        .header("x-upsert", "true".to_string());
+    for (name, value) in supabase_config.storage_headers(access_token) {
+        request = request.header(name, value);
+    }
+    let response = request
+        .json(&archive.manifest)
+        .map_err(|e| JsValue::from_str(&format!("{e:?}")))?
+        .send()
+        .await
+        .map_err(|e| JsValue::from_str(&format!("{e:?}")))?;
+    if !response.ok() {
+        return Err(JsValue::from_str(&format!(
+            "Archive manifest upload failed with status: {}",
+            response.status()
+        )));
+    }
+
+    Ok(())
+}
+
+/// The stream's current archive, or `None` if it hasn't been archived
+pub async fn fetch_archive_manifest(
+    access_token: &str,
+    supabase_config: &SupabaseConfig,
+    user_id: &str,
+    stream_id: &str,
+) -> Result<Option<ArchiveManifest<String>>, JsValue> {
+    let body = download_storage_file(
+        access_token,
+        supabase_config,
+        &supabase_config.archive_url(user_id, stream_id, "manifest.json"),
+    )
+    .await?;
+    body.map(|body| {
+        serde_json::from_str(&body).map_err(|e| {
+            JsValue::from_str(&format!(
+                "Failed to parse archive manifest: {e}. Body: {body}"
+            ))
+        })
+    })
+    .transpose()
+}
+
+/// Downloads the archive `manifest` describes, e.g. to seed a new device's event log
+pub async fn download_archive(
+    access_token: &str,
+    supabase_config: &SupabaseConfig,
+    user_id: &str,
+    stream_id: &str,
+    manifest: &ArchiveManifest<String>,
+) -> Result<StreamArchive<String>, JsValue> {
+    let file_name = format!("{}.json", manifest.id());
+    let body = download_storage_file(
+        access_token,
+        supabase_config,
+        &supabase_config.archive_url(user_id, stream_id, &file_name),
+    )
+    .await?
+    .ok_or_else(|| JsValue::from_str(&format!("Archive {file_name} not found")))?;
+    serde_json::from_str(&body)
+        .map_err(|e| JsValue::from_str(&format!("Failed to parse archive {file_name}: {e}")))
+}
+
+/// Replaces the stream's snapshot. It's encrypted with `key` before it leaves the device, so the
+/// server only ever stores an `EncryptedSnapshot` it can't read.
+pub async fn upload_snapshot(
+    access_token: &str,
+    supabase_config: &SupabaseConfig,
+    user_id: &str,
+    stream_id: &str,
+    snapshot: &StateSnapshot<String>,
+    key: &SnapshotKey,
+) -> Result<(), JsValue> {
+    let encrypted = EncryptedSnapshot::seal(snapshot, key)
+        .map_err(|e| JsValue::from_str(&format!("Failed to encrypt snapshot: {e}")))?;
+    let client = fetch_happen::Client;
+    let mut request = client
+        .post(&supabase_config.snapshot_url(user_id, stream_id))
+        .header("x-upsert", "true".to_string());
+    for (name, value) in supabase_config.storage_headers(access_token) {
+        request = request.header(name, value);
+    }
+    let response = request
+        .json(&encrypted)
+        .map_err(|e| JsValue::from_str(&format!("{e:?}")))?
+        .send()
+        .await
+        .map_err(|e| JsValue::from_str(&format!("{e:?}")))?;
+    if !response.ok() {
+        return Err(JsValue::from_str(&format!(
+            "Snapshot upload failed with status: {}",
+            response.status()
+        )));
+    }
+    Ok(())
+}
+
+/// The stream's snapshot, decrypted with `key`, or `None` if it doesn't have one. An error if it
+/// was encrypted with another key.
+pub async fn download_snapshot(
+    access_token: &str,
+    supabase_config: &SupabaseConfig,
+    user_id: &str,
+    stream_id: &str,
+    key: &SnapshotKey,
+) -> Result<Option<StateSnapshot<String>>, JsValue> {
+    let body = download_storage_file(
+        access_token,
+        supabase_config,
+        &supabase_config.snapshot_url(user_id, stream_id),
+    )
+    .await?;
+    body.map(|body| {
+        serde_json::from_str::<EncryptedSnapshot>(&body)
+            .map_err(|e| JsValue::from_str(&format!("Failed to parse snapshot: {e}")))?
+            .open(key)
+            .map_err(|e| JsValue::from_str(&format!("Failed to decrypt snapshot: {e}")))
+    })
+    .transpose()
+}
+
+/// `None` if the file doesn't exist. Storage reports missing objects as 400 or 404, depending on
+/// the version.
+async fn download_storage_file(
+    access_token: &str,
+    supabase_config: &SupabaseConfig,
+    url: &str,
+) -> Result<Option<String>, JsValue> {
+    let client = fetch_happen::Client;
+    let mut request = client
+        .get(url)
+        .header("Cache-Control", "no-cache".to_string());
+    for (name, value) in supabase_config.storage_headers(access_token) {
+        request = request.header(name, value);
+    }
+    let response = request
+        .send()
+        .await
+        .map_err(|e| JsValue::from_str(&format!("{e:?}")))?;
+    if response.status() == 400 || response.status() == 404 {
+        return Ok(None);
+    }
+    if !response.ok() {
+        return Err(JsValue::from_str(&format!(
+            "Downloading {url} failed with status: {}",
+            response.status()
+        )));
+    }
+    response
+        .text()
+        .await
+        .map(Some)
+        .map_err(|e| JsValue::from_str(&format!("{e:?}")))
+}
+
+/// A step in setting up the Supabase project weapon syncs with. Apply them in order, e.g. by
+/// copying them into the project's `supabase/migrations` or pasting them into the SQL editor.
+/// They use the default names from `SupabaseConfig::new`.
+#[derive(Clone, Copy, Debug)]
+pub struct Migration {
+    pub version: u32,
+    pub name: &'static str,
+    pub sql: &'static str,
+}
+
+pub const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        name: "events",
+        sql: include_str!("../supabase/migrations/0001_events.sql"),
+    },
+    Migration {
+        version: 2,
+        name: "validate_events",
+        sql: include_str!("../supabase/migrations/0002_validate_events.sql"),
+    },
+    Migration {
+        version: 3,
+        name: "clock_deltas",
+        sql: include_str!("../supabase/migrations/0003_clock_deltas.sql"),
+    },
+];
+
+/// The schema version this version of weapon syncs with
+pub const SCHEMA_VERSION: u32 = MIGRATIONS[MIGRATIONS.len() - 1].version;
+
+/// The migrations a project at `version` still needs, in order. `0` for a new project.
+pub fn migrations_after(version: u32) -> impl Iterator<Item = &'static Migration> {
+    MIGRATIONS
+        .iter()
+        .filter(move |migration| migration.version > version)
+}
+
+/// The indexes the events table needs: which columns, in order, and whether they're unique
+const EXPECTED_INDEXES: [(&[&str], bool); 3] = [
+    (&["device_id", "id"], false),
+    (&["stream_id", "device_id", "id"], false),
+    (
+        &[
+            "user_id",
+            "stream_id",
+            "device_id",
+            "within_device_events_index",
+        ],
+        true,
+    ),
+];
+
+/// The commands the events table needs RLS policies for, as `pg_policies` names them
+const EXPECTED_POLICY_COMMANDS: [&str; 3] = ["SELECT", "INSERT", "UPDATE"];
+
+/// The triggers the events table needs
+const EXPECTED_TRIGGERS: [&str; 1] = ["weapon_validate_event"];
+
+/// What `weapon_schema_status` returns
+#[derive(Debug, serde::Deserialize)]
+struct SchemaStatus {
+    version: u32,
+    table_exists: bool,
+    rls_enabled: bool,
+    indexes: Vec<IndexStatus>,
+    policy_commands: Vec<String>,
+    /// Only reported from version 2
+    #[serde(default)]
+    triggers: Vec<String>,
+    functions: Vec<String>,
+    buckets: Vec<String>,
+}
+
+#[derive(Debug, serde::Deserialize)]
+struct IndexStatus {
+    unique: bool,
+    columns: Vec<String>,
+}
+
+/// Whether a Supabase project is set up the way this version of weapon expects, from
+/// `verify_remote_schema`
+#[derive(Debug, serde::Serialize, serde::Deserialize, tsify::Tsify)]
+#[tsify(into_wasm_abi)]
+pub struct SchemaCheck {
+    /// The last migration applied, or `None` if the project can't say (it was set up by hand, or
+    /// not at all)
+    pub version: Option<u32>,
+    pub expected_version: u32,
+    /// What's missing or out of date, one sentence each. Empty if everything is in place.
+    pub problems: Vec<String>,
+}
+
+impl SchemaCheck {
+    pub fn is_ok(&self) -> bool {
+        self.problems.is_empty()
+    }
+
+    fn new(status: Option<SchemaStatus>, supabase_config: &SupabaseConfig) -> Self {
+        let mut problems = Vec::new();
+        let Some(status) = status else {
+            problems.push(format!(
+                "{} doesn't exist, so the project was set up by hand or not at all. Apply the \
+                 migrations in weapon's supabase/migrations.",
+                supabase_config.schema_status_rpc
+            ));
+            return SchemaCheck {
+                version: None,
+                expected_version: SCHEMA_VERSION,
+                problems,
+            };
+        };
+
+        if status.version < SCHEMA_VERSION {
+            problems.push(format!(
+                "The schema is at version {}, apply the migrations after it to get to \
+                 {SCHEMA_VERSION}",
+                status.version
+            ));
+        } else if status.version > SCHEMA_VERSION {
+            problems.push(format!(
+                "The schema is at version {}, which is newer than this version of weapon \
+                 ({SCHEMA_VERSION})",
+                status.version
+            ));
+        }
+
+        let table = &supabase_config.events_table;
+        if !status.table_exists {
+            problems.push(format!("The {table} table doesn't exist"));
+        } else {
+            if !status.rls_enabled {
+                problems.push(format!(
+                    "Row level security is off for {table}, so users can read each other's events"
+                ));
+            }
+            for (columns, unique) in EXPECTED_INDEXES {
+                let found = status
+                    .indexes
+                    .iter()
+                    .any(|index| index.columns == columns && (index.unique || !unique));
+                if !found {
+                    let kind = if unique { "unique index" } else { "index" };
+                    problems.push(format!(
+                        "{table} is missing a {kind} on ({})",
+                        columns.join(", ")
+                    ));
+                }
+            }
+            for command in EXPECTED_POLICY_COMMANDS {
+                if !status.policy_commands.iter().any(|found| found == command) {
+                    problems.push(format!("{table} has no {command} policy"));
+                }
+            }
+            for trigger in EXPECTED_TRIGGERS {
+                if !status.triggers.iter().any(|found| found == trigger) {
+                    problems.push(format!(
+                        "{table} has no {trigger} trigger, so it accepts malformed events"
+                    ));
+                }
+            }
+        }
+
+        for function in [
+            &supabase_config.sync_rpc,
+            &supabase_config.clock_rpc,
+            &supabase_config.sync_delta_rpc,
+            &supabase_config.clock_delta_rpc,
+        ] {
+            if !status.functions.contains(function) {
+                problems.push(format!("The {function} function doesn't exist"));
+            }
+        }
+        for bucket in [
+            &supabase_config.archive_bucket,
+            &supabase_config.snapshot_bucket,
+        ] {
+            if !status.buckets.contains(bucket) {
+                problems.push(format!("The {bucket} storage bucket doesn't exist"));
+            }
+        }
+
+        SchemaCheck {
+            version: Some(status.version),
+            expected_version: SCHEMA_VERSION,
+            problems,
+        }
+    }
+}
+
+/// Checks that the project has the tables, indexes, RLS policies, functions and buckets this
+/// version of weapon syncs with, so a new deployment can tell what it's missing before the first
+/// sync fails. Needs the `weapon_schema_status` function from `MIGRATIONS`.
+pub async fn verify_remote_schema(
+    access_token: &str,
+    supabase_config: &SupabaseConfig,
+) -> Result<SchemaCheck, JsValue> {
+    use serde_json::json;
+
+    let table = match &supabase_config.schema {
+        Some(schema) => format!("{schema}.{}", supabase_config.events_table),
+        None => supabase_config.events_table.clone(),
+    };
+    let body = json!({
+        "p_table": table,
+        "p_functions": [
+            &supabase_config.sync_rpc,
+            &supabase_config.clock_rpc,
+            &supabase_config.sync_delta_rpc,
+            &supabase_config.clock_delta_rpc,
+        ],
+        "p_buckets": [&supabase_config.archive_bucket, &supabase_config.snapshot_bucket],
+    });
+
+    let client = fetch_happen::Client;
+    let url = supabase_config.rest_url(&format!("rpc/{}", supabase_config.schema_status_rpc));
+    let mut request = client.post(&url);
+    for (name, value) in supabase_config.headers(access_token) {
+        request = request.header(name, value);
+    }
+    let response = request
+        .json(&body)
+        .map_err(|e| JsValue::from_str(&format!("{e:?}")))?
+        .send()
+        .await
+        .map_err(|e| JsValue::from_str(&format!("{e:?}")))?;
+
+    // PostgREST answers 404 for functions it doesn't know
+    if response.status() == 404 {
+        return Ok(SchemaCheck::new(None, supabase_config));
+    }
+    if !response.ok() {
+        return Err(JsValue::from_str(&format!(
+            "{} RPC failed with status: {}",
+            supabase_config.schema_status_rpc,
+            response.status()
+        )));
+    }
+
+    let text = response
+        .text()
+        .await
+        .map_err(|e| JsValue::from_str(&format!("{e:?}")))?;
+    let status: SchemaStatus = serde_json::from_str(&text).map_err(|e| {
+        JsValue::from_str(&format!(
+            "Failed to parse {} response: {e}. Body: {text}",
+            supabase_config.schema_status_rpc
+        ))
+    })?;
+    Ok(SchemaCheck::new(Some(status), supabase_config))
+}
+
 #[derive(Debug)]
 #[cfg_attr(target_arch = "wasm32", wasm_bindgen::prelude::wasm_bindgen)]
 pub struct SupabaseSyncResult {
     pub uploaded_to_supabase: usize,
     pub downloaded_from_supabase: usize,
 }
+
+/// What `sync_with_supabase` would transfer, from `preview_sync_with_supabase`
+#[derive(Debug)]
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen::prelude::wasm_bindgen)]
+pub struct SupabaseSyncPreview {
+    pub events_to_upload: usize,
+    pub bytes_to_upload: usize,
+    pub events_to_download: usize,
+    /// Estimated from the size of the events we already have, since the server only tells us how
+    /// many there are
+    pub estimated_bytes_to_download: usize,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::json_stream::JsonEvent;
+
+    #[test]
+    fn test_sync_preview_counts_both_directions() {
+        let mut store: EventStore<String, String> = EventStore::default();
+        for n in 0..3 {
+            store
+                .add_raw_event(
+                    "reviews".to_string(),
+                    "phone".to_string(),
+                    JsonEvent::new(&serde_json::json!({ "n": n })),
+                    None,
+                )
+                .unwrap();
+        }
+        let clock = |entries: &[(&str, &str, usize)]| {
+            let mut clock: Clock<String, String> = BTreeMap::new();
+            for (stream, device, count) in entries {
+                clock
+                    .entry(stream.to_string())
+                    .or_default()
+                    .insert(device.to_string(), *count);
+            }
+            clock
+        };
+
+        let in_sync = store.sync_preview(&clock(&[("reviews", "phone", 3)]), "user");
+        assert_eq!(in_sync.events_to_upload, 0);
+        assert_eq!(in_sync.events_to_download, 0);
+        assert_eq!(in_sync.estimated_bytes_to_download, 0);
+
+        let preview = store.sync_preview(
+            &clock(&[
+                ("reviews", "phone", 1),
+                ("reviews", "laptop", 4),
+                ("settings", "laptop", 2),
+            ]),
+            "user",
+        );
+        assert_eq!(preview.events_to_upload, 2);
+        assert!(preview.bytes_to_upload > 0);
+        assert_eq!(preview.events_to_download, 6);
+
+        // Downloads are sized like the stream's local events, or the default for a stream we
+        // have nothing of
+        let local_events = store.events_missing_from(&BTreeMap::new(), "user");
+        let average_bytes = local_events
+            .iter()
+            .map(|event| serde_json::to_vec(&event.event).unwrap().len())
+            .sum::<usize>()
+            / local_events.len();
+        assert_eq!(
+            preview.estimated_bytes_to_download,
+            4 * average_bytes + 2 * DEFAULT_EVENT_BYTES
+        );
+    }
+
+    #[test]
+    fn test_parse_rejection_reads_the_trigger_detail() {
+        // What PostgREST returns when the validation trigger raises
+        let detail = serde_json::json!({
+            "stream_id": "reviews",
+            "device_id": "phone",
+            "within_device_events_index": 7,
+            "reason": { "kind": "out_of_order", "expected_index": 5 },
+        });
+        let body = serde_json::json!({
+            "code": "23514",
+            "message": REJECTION_MESSAGE,
+            "details": detail.to_string(),
+            "hint": null,
+        });
+        assert_eq!(
+            parse_rejection(&body.to_string()),
+            Some(RejectedEvent {
+                stream: "reviews".to_string(),
+                device: "phone".to_string(),
+                within_device_events_index: 7,
+                reason: RejectionReason::OutOfOrder { expected_index: 5 },
+            })
+        );
+
+        let too_large = serde_json::json!({
+            "message": REJECTION_MESSAGE,
+            "details": serde_json::json!({
+                "stream_id": "reviews",
+                "device_id": "phone",
+                "within_device_events_index": 0,
+                "reason": { "kind": "too_large", "bytes": 70000, "limit": 65536 },
+            })
+            .to_string(),
+        });
+        assert_eq!(
+            parse_rejection(&too_large.to_string()).map(|rejected| rejected.reason),
+            Some(RejectionReason::TooLarge {
+                bytes: 70000,
+                limit: 65536
+            })
+        );
+
+        // Other failures aren't rejections
+        let unique_violation = serde_json::json!({
+            "code": "23505",
+            "message": "duplicate key value violates unique constraint",
+            "details": detail.to_string(),
+        });
+        assert_eq!(parse_rejection(&unique_violation.to_string()), None);
+        assert_eq!(parse_rejection("Unknown error"), None);
+    }
+
+    #[test]
+    fn test_rejected_events_are_replaced_on_each_upload() {
+        let mut store: EventStore<String, String> = EventStore::default();
+        let rejected = RejectedEvent {
+            stream: "reviews".to_string(),
+            device: "phone".to_string(),
+            within_device_events_index: 0,
+            reason: RejectionReason::MalformedEnvelope {
+                problem: "event is missing".to_string(),
+            },
+        };
+        store.record_rejected_events(SyncTarget::Supabase, vec![rejected.clone()]);
+        let rejected_events = |store: &EventStore<String, String>, target| {
+            store
+                .sync_state(target)
+                .map(|state| state.rejected_events.clone())
+                .unwrap_or_default()
+        };
+        assert_eq!(
+            rejected_events(&store, SyncTarget::Supabase),
+            vec![rejected]
+        );
+        assert!(rejected_events(&store, SyncTarget::Opfs).is_empty());
+
+        store.record_rejected_events(SyncTarget::Supabase, Vec::new());
+        assert!(rejected_events(&store, SyncTarget::Supabase).is_empty());
+    }
+}
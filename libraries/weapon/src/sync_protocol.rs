@@ -0,0 +1,344 @@
+//! What goes over the wire when syncing with the server, without the I/O. The wasm app sends it
+//! with `supabase` and native shells (see the `weapon-ffi` crate) with their own HTTP clients, so
+//! every platform sends and reads exactly the same events.
+
+use std::collections::{BTreeMap, HashMap};
+
+use crate::data_model::{
+    Clock, ClockBaseline, EventStore, ListenerKey, RejectedEvent, RejectionReason, Timestamped,
+    is_branch_stream,
+};
+
+/// An event as the events table stores it, see `EventStore::events_missing_from`
+#[derive(serde::Serialize, serde::Deserialize, Debug)]
+#[cfg_attr(target_arch = "wasm32", derive(tsify::Tsify))]
+#[cfg_attr(target_arch = "wasm32", tsify(into_wasm_abi, from_wasm_abi))]
+pub struct SyncableEvent {
+    pub user_id: String,
+    pub device_id: String,
+    pub event: serde_json::Value,
+    pub created_at: String,
+    pub within_device_events_index: usize,
+    pub stream_id: String,
+}
+
+/// The events the sync RPC returned, by stream and then device
+pub type SyncResponse = HashMap<String, HashMap<String, Vec<Timestamped<serde_json::Value>>>>;
+
+/// The body of the sync RPC, asking for the events `vector_clock` doesn't have. Branches are
+/// local, so they're left out (see `is_branch_stream`).
+pub fn sync_request(vector_clock: &Clock<String, String>) -> serde_json::Value {
+    serde_json::json!({
+        "sync_request": vector_clock
+            .iter()
+            .filter(|(stream_id, _)| !is_branch_stream(stream_id))
+            .map(|(stream_id, device_events)| {
+                (stream_id, serde_json::json!({ "last_synced_ids": device_events }))
+            })
+            .collect::<HashMap<_, _>>()
+    })
+}
+
+/// How a clock differs from a `ClockBaseline`, by stream and then device. `None` means the
+/// stream or device isn't in the clock at all.
+pub type ClockDelta = BTreeMap<String, Option<BTreeMap<String, Option<usize>>>>;
+
+/// The changes that turn `baseline` into `clock`, see `apply_clock_delta`. Branches are left out,
+/// like in `sync_request`.
+pub fn clock_delta(clock: &Clock<String, String>, baseline: &Clock<String, String>) -> ClockDelta {
+    let clock = clock
+        .iter()
+        .filter(|(stream_id, _)| !is_branch_stream(stream_id))
+        .collect::<BTreeMap<_, _>>();
+    let mut delta = ClockDelta::new();
+    for (stream_id, devices) in &clock {
+        let Some(baseline_devices) = baseline.get(*stream_id) else {
+            let devices = devices
+                .iter()
+                .map(|(device_id, count)| (device_id.clone(), Some(*count)))
+                .collect();
+            delta.insert((*stream_id).clone(), Some(devices));
+            continue;
+        };
+        let changed = devices
+            .iter()
+            .filter(|(device_id, count)| baseline_devices.get(*device_id) != Some(count))
+            .map(|(device_id, count)| (device_id.clone(), Some(*count)))
+            .chain(
+                baseline_devices
+                    .keys()
+                    .filter(|device_id| !devices.contains_key(*device_id))
+                    .map(|device_id| (device_id.clone(), None)),
+            )
+            .collect::<BTreeMap<_, _>>();
+        if !changed.is_empty() {
+            delta.insert((*stream_id).clone(), Some(changed));
+        }
+    }
+    for stream_id in baseline.keys() {
+        if !clock.contains_key(stream_id) {
+            delta.insert(stream_id.clone(), None);
+        }
+    }
+    delta
+}
+
+/// The clock `delta` describes, the inverse of `clock_delta`
+pub fn apply_clock_delta(
+    baseline: &Clock<String, String>,
+    delta: &ClockDelta,
+) -> Clock<String, String> {
+    let mut clock = baseline.clone();
+    for (stream_id, devices) in delta {
+        let Some(devices) = devices else {
+            clock.remove(stream_id);
+            continue;
+        };
+        let stream = clock.entry(stream_id.clone()).or_default();
+        for (device_id, count) in devices {
+            match count {
+                Some(count) => stream.insert(device_id.clone(), *count),
+                None => stream.remove(device_id),
+            };
+        }
+    }
+    clock
+}
+
+/// The body of the delta sync RPC: like `sync_request`, but only with the entries of
+/// `vector_clock` that differ from `baseline`. The server fills in the rest from its copy.
+pub fn delta_sync_request(
+    vector_clock: &Clock<String, String>,
+    baseline: &ClockBaseline<String, String>,
+) -> serde_json::Value {
+    serde_json::json!({
+        "p_baseline": baseline.id,
+        "sync_request": clock_delta(vector_clock, &baseline.clock)
+            .into_iter()
+            .map(|(stream_id, device_events)| {
+                let request = device_events
+                    .map(|device_events| serde_json::json!({ "last_synced_ids": device_events }));
+                (stream_id, request)
+            })
+            .collect::<HashMap<_, _>>()
+    })
+}
+
+/// Reads the body the delta sync RPC responded with. `None` if the server doesn't have the
+/// baseline, in which case the whole clock has to be sent with `sync_request`.
+pub fn parse_delta_sync_response(body: &str) -> Result<Option<SyncResponse>, serde_json::Error> {
+    #[derive(serde::Deserialize)]
+    struct BaselineUnknown {
+        baseline_unknown: bool,
+    }
+
+    if serde_json::from_str::<BaselineUnknown>(body).is_ok_and(|unknown| unknown.baseline_unknown) {
+        return Ok(None);
+    }
+    parse_sync_response(body).map(Some)
+}
+
+/// The body of the delta clock RPC, which returns the server's clock relative to `baseline` and
+/// keeps a copy of it as the next baseline
+pub fn clock_delta_request(
+    user_id: &str,
+    baseline: Option<&ClockBaseline<String, String>>,
+) -> serde_json::Value {
+    serde_json::json!({
+        "p_user_id": user_id,
+        "p_baseline": baseline.map(|baseline| &baseline.id),
+    })
+}
+
+/// What the delta clock RPC returns: the new baseline's id, and either the changes from the
+/// baseline we sent or, if the server didn't have it, the whole clock
+#[derive(Debug, serde::Deserialize)]
+struct ClockDeltaResponse {
+    baseline: String,
+    #[serde(default)]
+    delta: Option<ClockDelta>,
+    #[serde(default)]
+    clock: Option<Clock<String, String>>,
+}
+
+/// Reads the body the delta clock RPC responded with into the server's clock, which is also the
+/// new baseline
+pub fn parse_clock_delta_response(
+    body: &str,
+    baseline: Option<&ClockBaseline<String, String>>,
+) -> Result<ClockBaseline<String, String>, serde_json::Error> {
+    use serde::de::Error;
+
+    let response: ClockDeltaResponse = serde_json::from_str(body)?;
+    let clock = match (response.clock, response.delta, baseline) {
+        (Some(clock), _, _) => clock,
+        (None, Some(delta), Some(baseline)) => apply_clock_delta(&baseline.clock, &delta),
+        _ => {
+            return Err(serde_json::Error::custom(
+                "expected a clock, or a delta from the baseline we sent",
+            ));
+        }
+    };
+    Ok(ClockBaseline {
+        id: response.baseline,
+        clock,
+    })
+}
+
+/// Reads the body the sync RPC responded with
+pub fn parse_sync_response(body: &str) -> Result<SyncResponse, serde_json::Error> {
+    #[allow(clippy::type_complexity)]
+    let response: HashMap<
+        String,
+        HashMap<String, Vec<SyncEventResponse<Timestamped<serde_json::Value>>>>,
+    > = serde_json::from_str(body)?;
+    Ok(response
+        .into_iter()
+        .map(|(stream, device_events)| {
+            let device_events = device_events
+                .into_iter()
+                .map(|(device, events)| {
+                    (
+                        device,
+                        events.into_iter().map(|event| event.event).collect(),
+                    )
+                })
+                .collect();
+            (stream, device_events)
+        })
+        .collect())
+}
+
+fn deserialize_event<'de, E, D>(deserializer: D) -> Result<E, D::Error>
+where
+    D: serde::de::Deserializer<'de>,
+    E: serde::de::DeserializeOwned,
+{
+    use serde::Deserialize;
+    use serde::de::Error;
+
+    // First try to deserialize directly
+    let value = serde_json::Value::deserialize(deserializer)?;
+
+    // If it's already an object, try to deserialize it directly
+    if value.is_object() {
+        return serde_json::from_value(value).map_err(D::Error::custom);
+    }
+
+    // If it's a string, parse it as JSON
+    if let Some(s) = value.as_str() {
+        return serde_json::from_str(s).map_err(D::Error::custom);
+    }
+
+    // Otherwise, fail with an appropriate error
+    Err(D::Error::custom(
+        "Expected either a JSON object or a JSON string",
+    ))
+}
+
+#[derive(Debug, serde::Deserialize)]
+#[serde(bound(deserialize = "Event: serde::de::DeserializeOwned"))]
+struct SyncEventResponse<Event> {
+    #[expect(unused)]
+    id: u64,
+    #[serde(deserialize_with = "deserialize_event")]
+    event: Event,
+    #[expect(unused)]
+    // we will use this later, once we stop duplicating the within_device_events_index in Event itself
+    within_device_events_index: u32,
+}
+
+/// The message the validation trigger (see `supabase::MIGRATIONS`) rejects events with
+const REJECTION_MESSAGE: &str = "weapon: event rejected";
+
+/// A PostgREST error response
+#[derive(Debug, serde::Deserialize)]
+struct PostgrestError {
+    message: String,
+    #[serde(default)]
+    details: Option<String>,
+}
+
+/// The detail of a rejection, as the validation trigger raises it
+#[derive(Debug, serde::Deserialize)]
+struct RejectionDetail {
+    stream_id: String,
+    device_id: String,
+    within_device_events_index: usize,
+    reason: RejectionReason,
+}
+
+/// The event the validation trigger rejected, if that's why an upload failed
+pub fn parse_rejection(error_body: &str) -> Option<RejectedEvent<String, String>> {
+    let error: PostgrestError = serde_json::from_str(error_body).ok()?;
+    if error.message != REJECTION_MESSAGE {
+        return None;
+    }
+    let detail: RejectionDetail = serde_json::from_str(error.details.as_deref()?).ok()?;
+    Some(RejectedEvent {
+        stream: detail.stream_id,
+        device: detail.device_id,
+        within_device_events_index: detail.within_device_events_index,
+        reason: detail.reason,
+    })
+}
+
+impl EventStore<String, String> {
+    /// The local events that aren't on the server according to `remote_clock`, ready to upload
+    pub fn events_missing_from(
+        &self,
+        remote_clock: &Clock<String, String>,
+        user_id: &str,
+    ) -> Vec<SyncableEvent> {
+        self.iter()
+            .filter(|(stream_id, _)| !is_branch_stream(stream_id))
+            .flat_map(|(stream_id, stream_events)| {
+                // Get all devices with events in this stream
+                let device_event_counts = stream_events.num_events_per_device();
+
+                // For each device, upload any events not yet on the server
+                device_event_counts
+                    .into_iter()
+                    .flat_map(|(local_device_id, _local_count)| {
+                        let device_events_on_db: usize = remote_clock
+                            .get(stream_id)
+                            .and_then(|device_map| {
+                                device_map.get(&local_device_id.to_string()).copied()
+                            })
+                            .unwrap_or(0);
+
+                        let events_to_upload =
+                            stream_events.jsons(local_device_id, device_events_on_db);
+
+                        events_to_upload
+                            .into_iter()
+                            .map(|event| SyncableEvent {
+                                user_id: user_id.to_string(),
+                                device_id: local_device_id.to_string(),
+                                created_at: event.timestamp.to_string(),
+                                within_device_events_index: event.within_device_events_index,
+                                event: serde_json::to_value(&event).unwrap(),
+                                stream_id: stream_id.clone(),
+                            })
+                            .collect::<Vec<_>>()
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>()
+    }
+
+    /// Adds the events from a sync response. Returns how many were new.
+    pub fn add_sync_response(
+        &mut self,
+        response: SyncResponse,
+        modifier: Option<ListenerKey>,
+    ) -> usize {
+        let mut added = 0;
+        for (stream, device_events) in response {
+            for (device, events) in device_events {
+                added += self.add_device_events_jsons(stream.clone(), device, events, modifier);
+            }
+        }
+        added
+    }
+}
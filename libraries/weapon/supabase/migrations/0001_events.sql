@@ -0,0 +1,222 @@
+-- Weapon schema version 1: the events table, the sync functions, the storage buckets for
+-- archives and snapshots, and `weapon_schema_status` for `verify_remote_schema`.
+-- Safe to run on a project that was set up by hand from sync.md.
+
+create table if not exists public.events (
+  id bigserial primary key,
+  user_id uuid references auth.users,
+  stream_id text not null,
+  device_id text not null,
+  within_device_events_index integer not null,
+  event jsonb not null,
+  created_at timestamptz default now()
+);
+
+create index if not exists idx_events_sync on public.events(device_id, id);
+create index if not exists idx_events_stream_sync on public.events(stream_id, device_id, id);
+
+do $$
+begin
+  alter table public.events
+    add constraint events_unique_stream_device_index
+    unique (user_id, stream_id, device_id, within_device_events_index);
+exception
+  when duplicate_object or duplicate_table then null;
+end $$;
+
+alter table public.events enable row level security;
+
+drop policy if exists "Users can see own events" on public.events;
+create policy "Users can see own events" on public.events
+  for select using (auth.uid() = user_id);
+
+drop policy if exists "Users can insert own events" on public.events;
+create policy "Users can insert own events" on public.events
+  for insert with check (auth.uid() = user_id);
+
+drop policy if exists "Users can update own events" on public.events;
+create policy "Users can update own events" on public.events
+  for update using (auth.uid() = user_id);
+
+do $$
+begin
+  alter publication supabase_realtime add table public.events;
+exception
+  when duplicate_object then null;
+end $$;
+
+create or replace function public.sync_events(sync_request jsonb)
+returns jsonb as $$
+declare
+  result jsonb = '{}'::jsonb;
+  stream_record record;
+  device_record record;
+  stream_result jsonb;
+  requested_devices jsonb;
+begin
+  for stream_record in select * from jsonb_each(sync_request)
+  loop
+    stream_result := '{}'::jsonb;
+    requested_devices := stream_record.value->'last_synced_ids';
+
+    -- Events after the last one we have, for devices we've heard from
+    for device_record in select * from jsonb_each_text(requested_devices)
+    loop
+      stream_result := stream_result || jsonb_build_object(
+        device_record.key,
+        (
+          select coalesce(jsonb_agg(
+            row_to_json(e) order by e.within_device_events_index
+          ), '[]'::jsonb)
+          from events e
+          where e.device_id = device_record.key
+          and e.stream_id = stream_record.key
+          and e.within_device_events_index > device_record.value::integer
+          and e.user_id = auth.uid()
+        )
+      );
+    end loop;
+
+    -- Every event, for devices we haven't
+    for device_record in
+      select distinct device_id
+      from events
+      where stream_id = stream_record.key
+      and user_id = auth.uid()
+      and device_id not in (select jsonb_object_keys(requested_devices))
+    loop
+      stream_result := stream_result || jsonb_build_object(
+        device_record.device_id,
+        (
+          select coalesce(jsonb_agg(
+            row_to_json(e) order by e.within_device_events_index
+          ), '[]'::jsonb)
+          from events e
+          where e.device_id = device_record.device_id
+          and e.stream_id = stream_record.key
+          and e.user_id = auth.uid()
+        )
+      );
+    end loop;
+
+    result := result || jsonb_build_object(stream_record.key, stream_result);
+  end loop;
+
+  return result;
+end;
+$$ language plpgsql
+set search_path = public, auth, extensions, pg_catalog;
+
+grant execute on function public.sync_events(jsonb) to authenticated;
+
+create or replace function public.get_clock(p_user_id uuid)
+returns jsonb
+language sql
+stable
+set search_path = public
+as $$
+  with counts as (
+    select
+      stream_id,
+      device_id,
+      count(*)::int as event_count
+    from public.events
+    where user_id = p_user_id
+    group by stream_id, device_id
+  ),
+  device_map as (
+    select
+      stream_id,
+      jsonb_object_agg(device_id::text, to_jsonb(event_count)) as devices
+    from counts
+    group by stream_id
+  )
+  select coalesce(
+    jsonb_object_agg(stream_id::text, devices),
+    '{}'::jsonb
+  )
+  from device_map;
+$$;
+
+grant execute on function public.get_clock(uuid) to authenticated, service_role;
+
+-- Archives and snapshots are stored under `<user id>/<stream id>/`
+insert into storage.buckets (id, name)
+values ('event-archives', 'event-archives'), ('state-snapshots', 'state-snapshots')
+on conflict (id) do nothing;
+
+drop policy if exists "Users can read own sync files" on storage.objects;
+create policy "Users can read own sync files" on storage.objects
+  for select using (
+    bucket_id in ('event-archives', 'state-snapshots')
+    and (storage.foldername(name))[1] = auth.uid()::text
+  );
+
+drop policy if exists "Users can upload own sync files" on storage.objects;
+create policy "Users can upload own sync files" on storage.objects
+  for insert with check (
+    bucket_id in ('event-archives', 'state-snapshots')
+    and (storage.foldername(name))[1] = auth.uid()::text
+  );
+
+drop policy if exists "Users can replace own sync files" on storage.objects;
+create policy "Users can replace own sync files" on storage.objects
+  for update using (
+    bucket_id in ('event-archives', 'state-snapshots')
+    and (storage.foldername(name))[1] = auth.uid()::text
+  );
+
+-- What `verify_remote_schema` checks. Every migration replaces it, so `version` is the last one
+-- applied. It reads the catalogs, so it only reports names and flags, never data.
+create or replace function public.weapon_schema_status(
+  p_table text,
+  p_functions text[],
+  p_buckets text[]
+)
+returns jsonb
+language sql
+stable
+security definer
+set search_path = public, pg_catalog
+as $$
+  select jsonb_build_object(
+    'version', 1,
+    'table_exists', to_regclass(p_table) is not null,
+    'rls_enabled', coalesce(
+      (select relrowsecurity from pg_class where oid = to_regclass(p_table)),
+      false
+    ),
+    'indexes', coalesce((
+      select jsonb_agg(jsonb_build_object(
+        'name', i.relname,
+        'unique', ix.indisunique,
+        'columns', (
+          select jsonb_agg(a.attname order by k.ord)
+          from unnest(ix.indkey) with ordinality as k(attnum, ord)
+          join pg_attribute a on a.attrelid = ix.indrelid and a.attnum = k.attnum
+        )
+      ))
+      from pg_index ix
+      join pg_class i on i.oid = ix.indexrelid
+      where ix.indrelid = to_regclass(p_table)
+    ), '[]'::jsonb),
+    'policy_commands', coalesce((
+      select jsonb_agg(distinct cmd)
+      from pg_policies
+      where schemaname || '.' || tablename = to_regclass(p_table)::text
+        or tablename = to_regclass(p_table)::text
+    ), '[]'::jsonb),
+    'functions', coalesce((
+      select jsonb_agg(distinct proname)
+      from pg_proc
+      where proname = any(p_functions)
+    ), '[]'::jsonb),
+    'buckets', coalesce((
+      select jsonb_agg(id)
+      from storage.buckets
+      where id = any(p_buckets)
+    ), '[]'::jsonb)
+  );
+$$;
+
+grant execute on function public.weapon_schema_status(text, text[], text[]) to authenticated;
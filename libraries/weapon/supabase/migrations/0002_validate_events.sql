@@ -0,0 +1,138 @@
+-- Weapon schema version 2: events are validated before they're stored. The events table takes any
+-- JSON, so without this one buggy client could upload events that every other device downloads
+-- and chokes on. Rejected events fail the insert with the `check_violation` code, the message
+-- `weapon: event rejected`, and a JSON detail saying which event was rejected and why, which
+-- `upload_missing_events` reports in `SyncState::rejected_events`.
+
+create or replace function public.weapon_validate_event()
+returns trigger
+language plpgsql
+set search_path = public, pg_catalog
+as $$
+declare
+  -- Bigger than any real event, small enough that a runaway one can't fill the table
+  max_event_bytes constant integer := 65536;
+  event_bytes integer := octet_length(new.event::text);
+  expected_index integer;
+  problem text;
+  reason jsonb;
+begin
+  if jsonb_typeof(new.event) is distinct from 'object' then
+    problem := 'the event isn''t a JSON object';
+  elsif jsonb_typeof(new.event->'timestamp') is distinct from 'string' then
+    problem := 'timestamp is missing or isn''t a string';
+  elsif jsonb_typeof(new.event->'within_device_events_index') is distinct from 'number' then
+    problem := 'within_device_events_index is missing or isn''t a number';
+  elsif (new.event->>'within_device_events_index')::numeric <> new.within_device_events_index then
+    problem := 'within_device_events_index doesn''t match the row''s';
+  elsif not new.event ? 'event' then
+    problem := 'event is missing';
+  elsif new.stream_id = '' or new.device_id = '' then
+    problem := 'stream_id and device_id can''t be empty';
+  end if;
+  if problem is null then
+    begin
+      perform (new.event->>'timestamp')::timestamptz;
+    exception when others then
+      problem := 'timestamp isn''t a date';
+    end;
+  end if;
+
+  if problem is not null then
+    reason := jsonb_build_object('kind', 'malformed_envelope', 'problem', problem);
+  elsif event_bytes > max_event_bytes then
+    reason := jsonb_build_object('kind', 'too_large', 'bytes', event_bytes, 'limit', max_event_bytes);
+  else
+    -- Each device's events are numbered from 0 with no gaps, which is what the clocks count on.
+    -- Indices that are already taken are left to the unique constraint.
+    select coalesce(max(within_device_events_index) + 1, 0) into expected_index
+    from public.events
+    where user_id = new.user_id
+      and stream_id = new.stream_id
+      and device_id = new.device_id;
+    if new.within_device_events_index > expected_index
+      or new.within_device_events_index < 0 then
+      reason := jsonb_build_object('kind', 'out_of_order', 'expected_index', expected_index);
+    end if;
+  end if;
+
+  if reason is not null then
+    raise exception using
+      errcode = 'check_violation',
+      message = 'weapon: event rejected',
+      detail = jsonb_build_object(
+        'stream_id', new.stream_id,
+        'device_id', new.device_id,
+        'within_device_events_index', new.within_device_events_index,
+        'reason', reason
+      )::text;
+  end if;
+
+  return new;
+end;
+$$;
+
+drop trigger if exists weapon_validate_event on public.events;
+create trigger weapon_validate_event
+  before insert or update on public.events
+  for each row execute function public.weapon_validate_event();
+
+-- Same as version 1, plus the triggers on the table
+create or replace function public.weapon_schema_status(
+  p_table text,
+  p_functions text[],
+  p_buckets text[]
+)
+returns jsonb
+language sql
+stable
+security definer
+set search_path = public, pg_catalog
+as $$
+  select jsonb_build_object(
+    'version', 2,
+    'table_exists', to_regclass(p_table) is not null,
+    'rls_enabled', coalesce(
+      (select relrowsecurity from pg_class where oid = to_regclass(p_table)),
+      false
+    ),
+    'indexes', coalesce((
+      select jsonb_agg(jsonb_build_object(
+        'name', i.relname,
+        'unique', ix.indisunique,
+        'columns', (
+          select jsonb_agg(a.attname order by k.ord)
+          from unnest(ix.indkey) with ordinality as k(attnum, ord)
+          join pg_attribute a on a.attrelid = ix.indrelid and a.attnum = k.attnum
+        )
+      ))
+      from pg_index ix
+      join pg_class i on i.oid = ix.indexrelid
+      where ix.indrelid = to_regclass(p_table)
+    ), '[]'::jsonb),
+    'policy_commands', coalesce((
+      select jsonb_agg(distinct cmd)
+      from pg_policies
+      where schemaname || '.' || tablename = to_regclass(p_table)::text
+        or tablename = to_regclass(p_table)::text
+    ), '[]'::jsonb),
+    'triggers', coalesce((
+      select jsonb_agg(tgname)
+      from pg_trigger
+      where tgrelid = to_regclass(p_table)
+        and not tgisinternal
+    ), '[]'::jsonb),
+    'functions', coalesce((
+      select jsonb_agg(distinct proname)
+      from pg_proc
+      where proname = any(p_functions)
+    ), '[]'::jsonb),
+    'buckets', coalesce((
+      select jsonb_agg(id)
+      from storage.buckets
+      where id = any(p_buckets)
+    ), '[]'::jsonb)
+  );
+$$;
+
+grant execute on function public.weapon_schema_status(text, text[], text[]) to authenticated;
@@ -0,0 +1,195 @@
+-- Weapon schema version 3: clocks can be sent as changes from a baseline. Every sync sends the
+-- device's clock and reads the server's, which grow with every stream and device (every reinstall
+-- is a new device). The server now keeps a copy of the clock it last returned, and clients send
+-- and receive only what changed since then (see `weapon::sync_protocol::clock_delta`). A client
+-- whose baseline the server doesn't have (it's new, or the baseline expired) gets the whole clock.
+
+create table if not exists public.sync_baselines (
+  user_id uuid not null references auth.users,
+  baseline_id uuid not null default gen_random_uuid(),
+  clock jsonb not null,
+  created_at timestamptz not null default now(),
+  -- The baseline the client had when this one was made
+  replaces uuid,
+  primary key (user_id, baseline_id)
+);
+
+alter table public.sync_baselines add column if not exists replaces uuid;
+
+alter table public.sync_baselines enable row level security;
+
+drop policy if exists "Users can manage own sync baselines" on public.sync_baselines;
+create policy "Users can manage own sync baselines" on public.sync_baselines
+  for all using (auth.uid() = user_id) with check (auth.uid() = user_id);
+
+-- `sync_events`, with the streams and devices missing from `sync_request` filled in from the
+-- baseline. A `null` stream or device is one the client doesn't have at all. Returns
+-- `{"baseline_unknown": true}` if there's no such baseline, for the client to send its whole
+-- clock to `sync_events` instead.
+create or replace function public.sync_events_delta(p_baseline uuid, sync_request jsonb)
+returns jsonb as $$
+declare
+  baseline_clock jsonb;
+  full_request jsonb = '{}'::jsonb;
+  stream_record record;
+begin
+  select clock into baseline_clock
+  from sync_baselines
+  where user_id = auth.uid() and baseline_id = p_baseline;
+  if baseline_clock is null then
+    return jsonb_build_object('baseline_unknown', true);
+  end if;
+
+  for stream_record in
+    select coalesce(r.key, b.key) as key, b.value as baseline, r.value as changed
+    from jsonb_each(baseline_clock) b
+    full join jsonb_each(sync_request) r on r.key = b.key
+  loop
+    continue when jsonb_typeof(stream_record.changed) = 'null';
+    full_request := full_request || jsonb_build_object(
+      stream_record.key,
+      jsonb_build_object('last_synced_ids', jsonb_strip_nulls(
+        coalesce(stream_record.baseline, '{}'::jsonb)
+          || coalesce(stream_record.changed->'last_synced_ids', '{}'::jsonb)
+      ))
+    );
+  end loop;
+
+  return sync_events(full_request);
+end;
+$$ language plpgsql
+set search_path = public, auth, extensions, pg_catalog;
+
+grant execute on function public.sync_events_delta(uuid, jsonb) to authenticated;
+
+-- `get_clock`, kept as a new baseline. Returns `{"baseline": <its id>, "delta": <changes>}`,
+-- where the changes are from `p_baseline`, or `{"baseline": <its id>, "clock": <whole clock>}` if
+-- there's no such baseline. A client only ever asks for the latest baseline it was given, so
+-- asking for one means the baseline it replaced isn't needed anymore. Baselines a client never
+-- came back for (it was reinstalled, or lost the response) are dropped after a month, or once
+-- the user has more than 32.
+create or replace function public.get_clock_delta(p_user_id uuid, p_baseline uuid)
+returns jsonb
+language plpgsql
+set search_path = public
+as $$
+declare
+  current_clock jsonb := get_clock(p_user_id);
+  baseline_clock jsonb;
+  new_baseline uuid;
+  delta jsonb;
+begin
+  select clock into baseline_clock
+  from sync_baselines
+  where user_id = p_user_id and baseline_id = p_baseline;
+
+  delete from sync_baselines
+  where user_id = p_user_id
+    and (
+      created_at < now() - interval '30 days'
+      or baseline_id = (
+        select replaces from sync_baselines
+        where user_id = p_user_id and baseline_id = p_baseline
+      )
+    );
+
+  insert into sync_baselines (user_id, clock, replaces)
+  values (p_user_id, current_clock, p_baseline)
+  returning baseline_id into new_baseline;
+
+  delete from sync_baselines
+  where user_id = p_user_id
+    and baseline_id not in (
+      select baseline_id from sync_baselines
+      where user_id = p_user_id
+      order by created_at desc, baseline_id = new_baseline desc
+      limit 32
+    );
+
+  if baseline_clock is null then
+    return jsonb_build_object('baseline', new_baseline, 'clock', current_clock);
+  end if;
+
+  select coalesce(jsonb_object_agg(stream_id, devices), '{}'::jsonb) into delta
+  from (
+    select
+      coalesce(c.key, b.key) as stream_id,
+      case when c.key is null then 'null'::jsonb else (
+        select coalesce(jsonb_object_agg(
+          coalesce(cd.key, bd.key),
+          coalesce(cd.value, 'null'::jsonb)
+        ), '{}'::jsonb)
+        from jsonb_each(c.value) cd
+        full join jsonb_each(coalesce(b.value, '{}'::jsonb)) bd on bd.key = cd.key
+        where cd.value is distinct from bd.value
+      ) end as devices
+    from jsonb_each(current_clock) c
+    full join jsonb_each(baseline_clock) b on b.key = c.key
+    where c.value is distinct from b.value
+  ) changed;
+
+  return jsonb_build_object('baseline', new_baseline, 'delta', delta);
+end;
+$$;
+
+grant execute on function public.get_clock_delta(uuid, uuid) to authenticated, service_role;
+
+-- Same as version 2
+create or replace function public.weapon_schema_status(
+  p_table text,
+  p_functions text[],
+  p_buckets text[]
+)
+returns jsonb
+language sql
+stable
+security definer
+set search_path = public, pg_catalog
+as $$
+  select jsonb_build_object(
+    'version', 3,
+    'table_exists', to_regclass(p_table) is not null,
+    'rls_enabled', coalesce(
+      (select relrowsecurity from pg_class where oid = to_regclass(p_table)),
+      false
+    ),
+    'indexes', coalesce((
+      select jsonb_agg(jsonb_build_object(
+        'name', i.relname,
+        'unique', ix.indisunique,
+        'columns', (
+          select jsonb_agg(a.attname order by k.ord)
+          from unnest(ix.indkey) with ordinality as k(attnum, ord)
+          join pg_attribute a on a.attrelid = ix.indrelid and a.attnum = k.attnum
+        )
+      ))
+      from pg_index ix
+      join pg_class i on i.oid = ix.indexrelid
+      where ix.indrelid = to_regclass(p_table)
+    ), '[]'::jsonb),
+    'policy_commands', coalesce((
+      select jsonb_agg(distinct cmd)
+      from pg_policies
+      where schemaname || '.' || tablename = to_regclass(p_table)::text
+        or tablename = to_regclass(p_table)::text
+    ), '[]'::jsonb),
+    'triggers', coalesce((
+      select jsonb_agg(tgname)
+      from pg_trigger
+      where tgrelid = to_regclass(p_table)
+        and not tgisinternal
+    ), '[]'::jsonb),
+    'functions', coalesce((
+      select jsonb_agg(distinct proname)
+      from pg_proc
+      where proname = any(p_functions)
+    ), '[]'::jsonb),
+    'buckets', coalesce((
+      select jsonb_agg(id)
+      from storage.buckets
+      where id = any(p_buckets)
+    ), '[]'::jsonb)
+  );
+$$;
+
+grant execute on function public.weapon_schema_status(text, text[], text[]) to authenticated;
@@ -0,0 +1,55 @@
+-- Checks that `get_clock_delta` keeps a bounded number of baselines. Run with `supabase test db`.
+begin;
+create extension if not exists pgtap with schema extensions;
+select plan(3);
+
+insert into auth.users (id) values ('00000000-0000-0000-0000-00000000c10c');
+create temp table issued_baselines (n serial, baseline_id uuid);
+
+-- One device syncing 50 times, asking for the clock twice per sync like weapon does
+do $$
+declare
+  baseline uuid;
+begin
+  for i in 1..100 loop
+    baseline := (
+      get_clock_delta('00000000-0000-0000-0000-00000000c10c', baseline)->>'baseline'
+    )::uuid;
+  end loop;
+end;
+$$;
+
+select is(
+  (select count(*)::int from sync_baselines
+   where user_id = '00000000-0000-0000-0000-00000000c10c'),
+  2,
+  'a device only keeps the baseline it has and the one it was just given'
+);
+
+-- Clients that never come back for their baseline, e.g. after reinstalling
+do $$
+begin
+  for i in 1..100 loop
+    insert into issued_baselines (baseline_id)
+    select (get_clock_delta('00000000-0000-0000-0000-00000000c10c', null)->>'baseline')::uuid;
+  end loop;
+end;
+$$;
+
+select is(
+  (select count(*)::int from sync_baselines
+   where user_id = '00000000-0000-0000-0000-00000000c10c'),
+  32,
+  'a user keeps at most 32 baselines'
+);
+
+select ok(
+  get_clock_delta(
+    '00000000-0000-0000-0000-00000000c10c',
+    (select baseline_id from issued_baselines order by n desc limit 1)
+  ) ? 'delta',
+  'the latest baseline is kept'
+);
+
+select * from finish();
+rollback;
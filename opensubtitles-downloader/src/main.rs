@@ -1,6 +1,6 @@
 use anyhow::{anyhow, Context, Result};
 use clap::Parser;
-use language_utils::MovieMetadataBasic;
+use language_utils::{MaturityRating, MovieMetadataBasic};
 use rustc_hash::FxHashMap;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
@@ -141,6 +141,7 @@ struct SubtitleLineJson {
 /// TMDB API Movie Response
 #[derive(Debug, Deserialize)]
 struct TmdbMovie {
+    id: u64,
     title: String,
     release_date: Option<String>,
     poster_path: Option<String>,
@@ -152,6 +153,23 @@ struct TmdbFindResponse {
     movie_results: Vec<TmdbMovie>,
 }
 
+/// TMDB Release Dates API Response, with each country's releases
+#[derive(Debug, Deserialize)]
+struct TmdbReleaseDatesResponse {
+    results: Vec<TmdbCountryReleases>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TmdbCountryReleases {
+    iso_3166_1: String,
+    release_dates: Vec<TmdbRelease>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TmdbRelease {
+    certification: String,
+}
+
 struct TmdbClient {
     api_key: String,
     client: reqwest::Client,
@@ -185,6 +203,40 @@ impl TmdbClient {
 
         Ok(find_response.movie_results.into_iter().next().unwrap())
     }
+
+    /// The movie's US rating, from the first of its US releases that was rated
+    async fn get_maturity(&self, tmdb_id: u64) -> Result<Option<MaturityRating>> {
+        let url = format!(
+            "https://api.themoviedb.org/3/movie/{}/release_dates?api_key={}",
+            tmdb_id, self.api_key
+        );
+
+        let response = self.client.get(&url).send().await?;
+        let response_text = response.text().await?;
+        let release_dates: TmdbReleaseDatesResponse = serde_json::from_str(&response_text)?;
+
+        tokio::time::sleep(tokio::time::Duration::from_millis(2500)).await;
+
+        Ok(release_dates
+            .results
+            .iter()
+            .filter(|country| country.iso_3166_1 == "US")
+            .flat_map(|country| &country.release_dates)
+            .find_map(|release| MaturityRating::from_us_certification(&release.certification)))
+    }
+
+    /// Like `get_maturity`, but failing to fetch the rating is only a warning, since the movie
+    /// is still usable without it
+    async fn get_maturity_or_warn(&self, tmdb_id: u64) -> Option<MaturityRating> {
+        println!("  Fetching rating...");
+        match self.get_maturity(tmdb_id).await {
+            Ok(maturity) => maturity,
+            Err(e) => {
+                println!("  ⚠ Could not fetch rating: {e}");
+                None
+            }
+        }
+    }
 }
 
 struct OpenSubtitlesClient {
@@ -535,9 +587,10 @@ async fn download_movie_subtitles(
 
         // Fetch metadata from TMDB
         println!("  Fetching metadata from TMDB...");
-        let (title, year, _poster_bytes) =
+        let (title, year, _poster_bytes, maturity) =
             match tmdb_client.get_movie(imdb_id_str, tmdb_language).await {
                 Ok(tmdb_data) => {
+                    let maturity = tmdb_client.get_maturity_or_warn(tmdb_data.id).await;
                     let title = tmdb_data.title;
                     let year = tmdb_data
                         .release_date
@@ -568,11 +621,11 @@ async fn download_movie_subtitles(
                         None
                     };
 
-                    (title, year, poster_bytes)
+                    (title, year, poster_bytes, maturity)
                 }
                 Err(e) => {
                     println!("  ⚠ Could not fetch TMDB metadata: {e:?}");
-                    ("Unknown".to_string(), None, None)
+                    ("Unknown".to_string(), None, None, None)
                 }
             };
 
@@ -580,6 +633,7 @@ async fn download_movie_subtitles(
             id: imdb_id_str.to_string(),
             title,
             year,
+            maturity,
         };
 
         return Ok(Some((subtitle_lines, movie)));
@@ -596,9 +650,10 @@ async fn fetch_tmdb_metadata(
     opensub_client: &OpenSubtitlesClient,
     posters_dir: &std::path::Path,
 ) -> Result<MovieMetadataBasic> {
-    let (tmdb_title, tmdb_year, _poster_bytes) =
+    let (tmdb_title, tmdb_year, _poster_bytes, maturity) =
         match tmdb_client.get_movie(imdb_id_str, tmdb_language).await {
             Ok(tmdb_data) => {
+                let maturity = tmdb_client.get_maturity_or_warn(tmdb_data.id).await;
                 let tmdb_title = tmdb_data.title;
                 let tmdb_year = tmdb_data
                     .release_date
@@ -629,7 +684,7 @@ async fn fetch_tmdb_metadata(
                     None
                 };
 
-                (tmdb_title, tmdb_year, poster_bytes)
+                (tmdb_title, tmdb_year, poster_bytes, maturity)
             }
             Err(e) => {
                 println!("  ⚠ Could not fetch TMDB metadata: {e}");
@@ -641,6 +696,7 @@ async fn fetch_tmdb_metadata(
         id: imdb_id_str.to_string(),
         title: tmdb_title,
         year: tmdb_year,
+        maturity,
     })
 }
 
@@ -693,7 +749,14 @@ async fn process_movie(
         meta
     } else if let Some(existing) = existing_metadata.get(imdb_id_str) {
         println!("  ✓ Using existing metadata");
-        existing.clone()
+        let mut existing = existing.clone();
+        // Metadata from before ratings were fetched
+        if existing.maturity.is_none() {
+            if let Ok(tmdb_data) = tmdb_client.get_movie(imdb_id_str, tmdb_language).await {
+                existing.maturity = tmdb_client.get_maturity_or_warn(tmdb_data.id).await;
+            }
+        }
+        existing
     } else {
         println!("  Fetching metadata from TMDB...");
         fetch_tmdb_metadata(
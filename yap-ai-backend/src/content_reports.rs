@@ -0,0 +1,142 @@
+//! Corrections users report for pack content, see `language_utils::content_report`. Reports are
+//! reviewed by hand, by setting `state` (and optionally `reviewer_note`) on their row.
+//!
+//! Reports are stored in the `content_reports` table:
+//!
+//! ```sql
+//! create table content_reports (
+//!     id text primary key,
+//!     received_at timestamptz not null default now(),
+//!     user_id uuid references auth.users,
+//!     target_language text not null,
+//!     native_language text not null,
+//!     pack_version text not null,
+//!     report jsonb not null,
+//!     -- 'open', 'accepted' or 'rejected'
+//!     state text not null default 'open',
+//!     reviewer_note text
+//! );
+//! create index content_reports_user on content_reports (user_id);
+//! ```
+
+use axum::{extract::Json, http::StatusCode};
+use axum_extra::{
+    TypedHeader,
+    headers::{Authorization, authorization::Bearer},
+};
+use language_utils::Language;
+use language_utils::content_report::{
+    ContentReport, ContentReportState, ContentReportStatus, MAX_CONTENT_REPORT_TEXT_LENGTH,
+    SubmitContentReportRequest,
+};
+use postgrest::Postgrest;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    supabase::{execute, fetch_rows, supabase_client},
+    verify_jwt,
+};
+
+/// Longer ids aren't ones the app made
+const MAX_ID_LENGTH: usize = 64;
+
+#[derive(Debug, Serialize)]
+struct ContentReportRow {
+    id: String,
+    user_id: Option<uuid::Uuid>,
+    target_language: Language,
+    native_language: Language,
+    pack_version: String,
+    report: ContentReport,
+}
+
+#[derive(Debug, Deserialize)]
+struct StatusRow {
+    id: String,
+    state: ContentReportState,
+    reviewer_note: Option<String>,
+}
+
+impl From<StatusRow> for ContentReportStatus {
+    fn from(row: StatusRow) -> Self {
+        ContentReportStatus {
+            id: row.id,
+            state: row.state,
+            reviewer_note: row.reviewer_note,
+        }
+    }
+}
+
+async fn fetch_statuses(
+    client: &Postgrest,
+    column: &str,
+    value: String,
+) -> Result<Vec<ContentReportStatus>, StatusCode> {
+    let rows: Vec<StatusRow> = fetch_rows(
+        client
+            .from("content_reports")
+            .select("id,state,reviewer_note")
+            .eq(column, value)
+            .order("received_at"),
+        "content reports",
+    )
+    .await?;
+    Ok(rows.into_iter().map(ContentReportStatus::from).collect())
+}
+
+pub(crate) async fn submit_content_report(
+    TypedHeader(auth): TypedHeader<Authorization<Bearer>>,
+    Json(request): Json<SubmitContentReportRequest>,
+) -> Result<Json<ContentReportStatus>, StatusCode> {
+    // Logged-out users send a dummy token, and their corrections are still wanted
+    let user_id = verify_jwt(auth.token()).await.ok().map(|claims| claims.sub);
+
+    let report = request.report;
+    let too_long = |text: &str| text.len() > MAX_CONTENT_REPORT_TEXT_LENGTH;
+    if report.id.is_empty()
+        || report.id.len() > MAX_ID_LENGTH
+        || too_long(&report.comment)
+        || report.suggestion.as_deref().is_some_and(too_long)
+    {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    // A report that's uploaded again after a dropped response keeps the state it was given
+    let client = supabase_client()?;
+    if let Some(existing) = fetch_statuses(&client, "id", report.id.clone())
+        .await?
+        .into_iter()
+        .next()
+    {
+        return Ok(Json(existing));
+    }
+
+    let id = report.id.clone();
+    let row = ContentReportRow {
+        id: id.clone(),
+        user_id,
+        target_language: report.course.target_language,
+        native_language: report.course.native_language,
+        pack_version: report.pack_version.clone(),
+        report,
+    };
+    let body = serde_json::to_string(&row).map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    execute(
+        client.from("content_reports").insert(body),
+        "inserting content report",
+    )
+    .await?;
+    Ok(Json(ContentReportStatus {
+        id,
+        state: ContentReportState::Open,
+        reviewer_note: None,
+    }))
+}
+
+pub(crate) async fn get_content_reports(
+    TypedHeader(auth): TypedHeader<Authorization<Bearer>>,
+) -> Result<Json<Vec<ContentReportStatus>>, StatusCode> {
+    let claims = verify_jwt(auth.token()).await?;
+    let statuses = fetch_statuses(&supabase_client()?, "user_id", claims.sub.to_string()).await?;
+    Ok(Json(statuses))
+}
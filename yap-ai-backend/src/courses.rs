@@ -0,0 +1,55 @@
+use axum::extract::Json;
+use language_utils::{
+    AvailableCourse, Course, Language,
+    pack_manifest::{PackChannel, PackQuery},
+};
+
+use crate::PACK_STORE;
+
+/// Keep in sync with the languages `autograde_translation` and `autograde_transcription` have
+/// prompts for
+fn autograde_supported(language: Language) -> bool {
+    matches!(
+        language,
+        Language::French
+            | Language::Spanish
+            | Language::English
+            | Language::Korean
+            | Language::German
+    )
+}
+
+async fn pack_version(course: Course, channel: PackChannel) -> Option<String> {
+    PACK_STORE
+        .pack_version(
+            course,
+            &PackQuery {
+                channel,
+                version: None,
+            },
+        )
+        .await
+        .inspect_err(|e| eprintln!("Error getting {channel:?} pack version for {course:?}: {e:?}"))
+        .ok()
+        .flatten()
+}
+
+/// The courses this deploy can serve, so clients can pick up new courses without a release
+pub(crate) async fn courses() -> Json<Vec<AvailableCourse>> {
+    let tts_available = ["ELEVENLABS_API_KEY", "GOOGLE_CLOUD_API_KEY"]
+        .iter()
+        .any(|key| std::env::var(key).is_ok_and(|value| !value.is_empty()));
+
+    let mut courses = Vec::new();
+    for course in PACK_STORE.courses() {
+        courses.push(AvailableCourse {
+            course,
+            pack_version: pack_version(course, PackChannel::Stable).await,
+            beta_pack_version: pack_version(course, PackChannel::Beta).await,
+            tts_available,
+            autograde_available: autograde_supported(course.target_language),
+        });
+    }
+
+    Json(courses)
+}
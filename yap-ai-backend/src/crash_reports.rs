@@ -0,0 +1,75 @@
+//! Crash reports from users who opted in to sharing them, see `language_utils::crash_report`.
+//!
+//! Reports are stored in the `crash_reports` table:
+//!
+//! ```sql
+//! create table crash_reports (
+//!     id text primary key,
+//!     received_at timestamptz not null default now(),
+//!     user_id uuid references auth.users,
+//!     app_version text not null,
+//!     report jsonb not null
+//! );
+//! ```
+
+use axum::{extract::Json, http::StatusCode};
+use axum_extra::{
+    TypedHeader,
+    headers::{Authorization, authorization::Bearer},
+};
+use language_utils::crash_report::{
+    CrashReport, MAX_CRASH_REPORT_LOGS, SubmitCrashReportRequest, SubmitCrashReportResponse,
+};
+use serde::Serialize;
+
+use crate::{
+    supabase::{execute, supabase_client},
+    verify_jwt,
+};
+
+/// Longer ids aren't ones the app made
+const MAX_ID_LENGTH: usize = 64;
+
+#[derive(Debug, Serialize)]
+struct CrashReportRow {
+    id: String,
+    user_id: Option<uuid::Uuid>,
+    app_version: String,
+    report: CrashReport,
+}
+
+pub(crate) async fn submit_crash_report(
+    TypedHeader(auth): TypedHeader<Authorization<Bearer>>,
+    Json(request): Json<SubmitCrashReportRequest>,
+) -> Result<Json<SubmitCrashReportResponse>, StatusCode> {
+    // Logged-out users send a dummy token, and their reports are still wanted
+    let user_id = verify_jwt(auth.token()).await.ok().map(|claims| claims.sub);
+
+    let report = request.report;
+    if report.id.is_empty()
+        || report.id.len() > MAX_ID_LENGTH
+        || report.logs.len() > MAX_CRASH_REPORT_LOGS
+    {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    let id = report.id.clone();
+    let row = CrashReportRow {
+        id: id.clone(),
+        user_id,
+        app_version: report.app_version.clone(),
+        report,
+    };
+    let body = serde_json::to_string(&row).map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    // Upserting, so a report that's uploaded again after a dropped response isn't an error
+    execute(
+        supabase_client()?
+            .from("crash_reports")
+            .upsert(body)
+            .on_conflict("id"),
+        "inserting crash report",
+    )
+    .await?;
+    Ok(Json(SubmitCrashReportResponse { id }))
+}
@@ -0,0 +1,454 @@
+//! Exports of everything stored about a user, see `language_utils::data_export`. The archive is a
+//! zip with a JSON file per table (`tables/<table>.<owner column>.json`), the user's files from
+//! the sync buckets under `storage/`, and a `manifest.json` listing what's in it. It's built in
+//! the background and uploaded to the private `data-exports` bucket, which is only handed out
+//! through signed links.
+//!
+//! Exports are tracked in the `data_exports` table:
+//!
+//! ```sql
+//! create table data_exports (
+//!     id uuid primary key,
+//!     user_id uuid not null references auth.users,
+//!     state text not null,
+//!     requested_at timestamptz not null,
+//!     completed_at timestamptz,
+//!     path text,
+//!     error text
+//! );
+//! create index data_exports_user on data_exports (user_id, requested_at);
+//! insert into storage.buckets (id, name) values ('data-exports', 'data-exports');
+//! ```
+
+use std::io::{Cursor, Write};
+
+use axum::{extract::Json, http::StatusCode};
+use axum_extra::{
+    TypedHeader,
+    headers::{Authorization, authorization::Bearer},
+};
+use chrono::Utc;
+use language_utils::data_export::{DataExportState, DataExportStatus};
+use postgrest::Postgrest;
+use serde::{Deserialize, Serialize};
+use zip::write::SimpleFileOptions;
+
+use crate::{
+    supabase::{fetch_rows, supabase_client},
+    verify_jwt,
+};
+
+/// The tables with rows about a user, the column that says whose a row is, and a unique column
+/// to page through the rows in order (if the table has one we know of). A user's rows in
+/// `follows` are the ones on either side. Tables a deployment doesn't have are listed in the
+/// manifest as skipped.
+const USER_TABLES: &[(&str, &str, Option<&str>)] = &[
+    ("events", "user_id", Some("id")),
+    ("profiles", "id", Some("id")),
+    ("user_language_stats", "user_id", Some("language")),
+    ("follows", "follower_id", Some("following_id")),
+    ("follows", "following_id", Some("follower_id")),
+    ("push_subscriptions", "user_id", None),
+    ("ai_usage", "user_id", Some("id")),
+    ("crash_reports", "user_id", Some("id")),
+    ("content_reports", "user_id", Some("id")),
+    ("public_stats", "user_id", None),
+    ("share_tokens", "user_id", Some("token")),
+    ("shared_lists", "owner_id", Some("code")),
+    ("user_webhooks", "user_id", None),
+    ("data_exports", "user_id", Some("id")),
+];
+/// The sync buckets, where a user's files are under `<user id>/`
+const USER_BUCKETS: &[&str] = &["event-archives", "state-snapshots"];
+const EXPORT_BUCKET: &str = "data-exports";
+/// PostgREST caps how many rows a request returns, so bigger tables are fetched in pages
+const PAGE_SIZE: usize = 1000;
+/// A pending export older than this is assumed to have died with the server, so a new request
+/// starts another one
+const PENDING_TIMEOUT: chrono::Duration = chrono::Duration::hours(1);
+const DOWNLOAD_LINK_SECONDS: u64 = 60 * 60;
+
+/// Supabase Storage, with the service role
+pub(crate) struct Storage {
+    url: String,
+    service_role_key: String,
+    client: reqwest::Client,
+}
+
+#[derive(Deserialize)]
+struct StorageEntry {
+    name: String,
+    /// `None` for folders
+    id: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct SignedUrl {
+    #[serde(rename = "signedURL")]
+    signed_url: String,
+}
+
+impl Storage {
+    pub(crate) fn from_env() -> Result<Self, String> {
+        let supabase_url = std::env::var("SUPABASE_URL").map_err(|e| e.to_string())?;
+        Ok(Self {
+            url: format!("{}/storage/v1", supabase_url.trim_end_matches('/')),
+            service_role_key: std::env::var("SUPABASE_SERVICE_ROLE_KEY")
+                .map_err(|e| e.to_string())?,
+            client: reqwest::Client::new(),
+        })
+    }
+
+    fn request(&self, method: reqwest::Method, path: &str) -> reqwest::RequestBuilder {
+        self.client
+            .request(method, format!("{}/{path}", self.url))
+            .header("apikey", &self.service_role_key)
+            .bearer_auth(&self.service_role_key)
+    }
+
+    async fn checked(
+        response: Result<reqwest::Response, reqwest::Error>,
+        what: &str,
+    ) -> Result<reqwest::Response, String> {
+        let response = response.map_err(|e| format!("Error trying to {what}: {e}"))?;
+        if response.status().is_success() {
+            Ok(response)
+        } else {
+            Err(format!(
+                "Failed to {what}: {} {:?}",
+                response.status(),
+                response.text().await
+            ))
+        }
+    }
+
+    /// The paths of every file under `prefix` (which ends with a `/`), including in subfolders
+    async fn list(&self, bucket: &str, prefix: &str) -> Result<Vec<String>, String> {
+        let mut files = Vec::new();
+        let mut folders = vec![prefix.to_string()];
+        while let Some(folder) = folders.pop() {
+            let mut offset = 0;
+            loop {
+                let response = self
+                    .request(reqwest::Method::POST, &format!("object/list/{bucket}"))
+                    .json(&serde_json::json!({
+                        "prefix": folder,
+                        "limit": PAGE_SIZE,
+                        "offset": offset,
+                    }))
+                    .send()
+                    .await;
+                let entries: Vec<StorageEntry> =
+                    Self::checked(response, &format!("list {bucket}/{folder}"))
+                        .await?
+                        .json()
+                        .await
+                        .map_err(|e| e.to_string())?;
+                let page_len = entries.len();
+                for entry in entries {
+                    let path = format!("{folder}{}", entry.name);
+                    match entry.id {
+                        Some(_) => files.push(path),
+                        None => folders.push(format!("{path}/")),
+                    }
+                }
+                if page_len < PAGE_SIZE {
+                    break;
+                }
+                offset += PAGE_SIZE;
+            }
+        }
+        files.sort();
+        Ok(files)
+    }
+
+    pub(crate) async fn download(&self, bucket: &str, path: &str) -> Result<Vec<u8>, String> {
+        let response = self
+            .request(reqwest::Method::GET, &format!("object/{bucket}/{path}"))
+            .send()
+            .await;
+        let bytes = Self::checked(response, &format!("download {bucket}/{path}"))
+            .await?
+            .bytes()
+            .await
+            .map_err(|e| e.to_string())?;
+        Ok(bytes.to_vec())
+    }
+
+    async fn upload(&self, bucket: &str, path: &str, bytes: Vec<u8>) -> Result<(), String> {
+        let response = self
+            .request(reqwest::Method::POST, &format!("object/{bucket}/{path}"))
+            .header("Content-Type", "application/zip")
+            .header("x-upsert", "true")
+            .body(bytes)
+            .send()
+            .await;
+        Self::checked(response, &format!("upload {bucket}/{path}")).await?;
+        Ok(())
+    }
+
+    async fn signed_url(&self, bucket: &str, path: &str) -> Result<String, String> {
+        let response = self
+            .request(
+                reqwest::Method::POST,
+                &format!("object/sign/{bucket}/{path}"),
+            )
+            .json(&serde_json::json!({ "expiresIn": DOWNLOAD_LINK_SECONDS }))
+            .send()
+            .await;
+        let signed: SignedUrl = Self::checked(response, &format!("sign {bucket}/{path}"))
+            .await?
+            .json()
+            .await
+            .map_err(|e| e.to_string())?;
+        Ok(format!("{}{}", self.url, signed.signed_url))
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct DataExportRow {
+    id: uuid::Uuid,
+    user_id: uuid::Uuid,
+    state: DataExportState,
+    requested_at: String,
+    completed_at: Option<String>,
+    path: Option<String>,
+    error: Option<String>,
+}
+
+impl DataExportRow {
+    async fn status(&self) -> DataExportStatus {
+        let download_url = match (&self.state, &self.path) {
+            (DataExportState::Ready, Some(path)) => match Storage::from_env() {
+                Ok(storage) => storage
+                    .signed_url(EXPORT_BUCKET, path)
+                    .await
+                    .inspect_err(|e| eprintln!("{e}"))
+                    .ok(),
+                Err(e) => {
+                    eprintln!("Error signing data export link: {e}");
+                    None
+                }
+            },
+            _ => None,
+        };
+        DataExportStatus {
+            id: self.id.to_string(),
+            state: self.state,
+            requested_at: self.requested_at.clone(),
+            completed_at: self.completed_at.clone(),
+            download_url,
+            error: self.error.clone(),
+        }
+    }
+
+    fn is_stuck(&self) -> bool {
+        chrono::DateTime::parse_from_rfc3339(&self.requested_at).is_ok_and(|requested_at| {
+            Utc::now() - requested_at.with_timezone(&Utc) > PENDING_TIMEOUT
+        })
+    }
+}
+
+async fn latest_export(
+    client: &Postgrest,
+    user_id: uuid::Uuid,
+) -> Result<Option<DataExportRow>, StatusCode> {
+    let rows: Vec<DataExportRow> = fetch_rows(
+        client
+            .from("data_exports")
+            .select("*")
+            .eq("user_id", user_id.to_string())
+            .order("requested_at.desc")
+            .limit(1),
+        "data export",
+    )
+    .await?;
+    Ok(rows.into_iter().next())
+}
+
+async fn save_export(client: &Postgrest, row: &DataExportRow) -> Result<(), String> {
+    let body = serde_json::to_string(row).map_err(|e| e.to_string())?;
+    let response = client
+        .from("data_exports")
+        .upsert(body)
+        .on_conflict("id")
+        .execute()
+        .await
+        .map_err(|e| format!("Error saving data export: {e:?}"))?;
+    if response.status().is_success() {
+        Ok(())
+    } else {
+        Err(format!(
+            "Failed to save data export: {:?}",
+            response.text().await
+        ))
+    }
+}
+
+pub(crate) async fn request_data_export(
+    TypedHeader(auth): TypedHeader<Authorization<Bearer>>,
+) -> Result<Json<DataExportStatus>, StatusCode> {
+    let claims = verify_jwt(auth.token()).await?;
+    let client = supabase_client()?;
+
+    if let Some(latest) = latest_export(&client, claims.sub).await?
+        && latest.state == DataExportState::Pending
+        && !latest.is_stuck()
+    {
+        return Ok(Json(latest.status().await));
+    }
+
+    let row = DataExportRow {
+        id: uuid::Uuid::new_v4(),
+        user_id: claims.sub,
+        state: DataExportState::Pending,
+        requested_at: Utc::now().to_rfc3339(),
+        completed_at: None,
+        path: None,
+        error: None,
+    };
+    save_export(&client, &row).await.map_err(|e| {
+        eprintln!("{e}");
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    let status = row.status().await;
+
+    tokio::spawn(async move {
+        let mut row = row;
+        match build_archive(&client, row.user_id, row.id).await {
+            Ok(path) => {
+                row.state = DataExportState::Ready;
+                row.path = Some(path);
+            }
+            Err(e) => {
+                eprintln!("Failed to export data for {}: {e}", row.user_id);
+                row.state = DataExportState::Failed;
+                row.error = Some(e);
+            }
+        }
+        row.completed_at = Some(Utc::now().to_rfc3339());
+        if let Err(e) = save_export(&client, &row).await {
+            eprintln!("{e}");
+        }
+    });
+
+    Ok(Json(status))
+}
+
+pub(crate) async fn get_data_export(
+    TypedHeader(auth): TypedHeader<Authorization<Bearer>>,
+) -> Result<Json<Option<DataExportStatus>>, StatusCode> {
+    let claims = verify_jwt(auth.token()).await?;
+    let latest = latest_export(&supabase_client()?, claims.sub).await?;
+    Ok(Json(match latest {
+        Some(latest) => Some(latest.status().await),
+        None => None,
+    }))
+}
+
+#[derive(Serialize)]
+struct ExportManifest {
+    user_id: uuid::Uuid,
+    exported_at: String,
+    /// The archive's files, with how many rows each table file has
+    tables: Vec<(String, usize)>,
+    /// Tables this deployment doesn't have
+    skipped_tables: Vec<String>,
+    files: Vec<String>,
+}
+
+/// Every row in `table` whose `owner_column` is `user_id`. `None` if the table doesn't exist.
+async fn fetch_table_rows(
+    client: &Postgrest,
+    (table, owner_column, order_column): (&str, &str, Option<&str>),
+    user_id: uuid::Uuid,
+) -> Result<Option<Vec<serde_json::Value>>, String> {
+    let mut rows = Vec::new();
+    loop {
+        let mut query = client
+            .from(table)
+            .select("*")
+            .eq(owner_column, user_id.to_string());
+        if let Some(order_column) = order_column {
+            query = query.order(order_column);
+        }
+        let response = query
+            .range(rows.len(), rows.len() + PAGE_SIZE - 1)
+            .execute()
+            .await
+            .map_err(|e| format!("Error exporting {table}: {e:?}"))?;
+        if response.status() == 404 {
+            return Ok(None);
+        }
+        if !response.status().is_success() {
+            return Err(format!(
+                "Failed to export {table}: {:?}",
+                response.text().await
+            ));
+        }
+        let page: Vec<serde_json::Value> = response
+            .json()
+            .await
+            .map_err(|e| format!("Error reading {table}: {e:?}"))?;
+        let page_len = page.len();
+        rows.extend(page);
+        if page_len < PAGE_SIZE {
+            return Ok(Some(rows));
+        }
+    }
+}
+
+/// Builds the user's archive, uploads it and returns its path in `EXPORT_BUCKET`
+async fn build_archive(
+    client: &Postgrest,
+    user_id: uuid::Uuid,
+    export_id: uuid::Uuid,
+) -> Result<String, String> {
+    let storage = Storage::from_env()?;
+    let mut archive = zip::ZipWriter::new(Cursor::new(Vec::new()));
+    let options = SimpleFileOptions::default();
+    let mut manifest = ExportManifest {
+        user_id,
+        exported_at: Utc::now().to_rfc3339(),
+        tables: Vec::new(),
+        skipped_tables: Vec::new(),
+        files: Vec::new(),
+    };
+
+    for &(table, owner_column, order_column) in USER_TABLES {
+        let Some(rows) =
+            fetch_table_rows(client, (table, owner_column, order_column), user_id).await?
+        else {
+            manifest.skipped_tables.push(table.to_string());
+            continue;
+        };
+        let name = format!("tables/{table}.{owner_column}.json");
+        archive
+            .start_file(name.as_str(), options)
+            .map_err(|e| e.to_string())?;
+        serde_json::to_writer_pretty(&mut archive, &rows).map_err(|e| e.to_string())?;
+        manifest.tables.push((name, rows.len()));
+    }
+
+    for bucket in USER_BUCKETS {
+        for path in storage.list(bucket, &format!("{user_id}/")).await? {
+            let bytes = storage.download(bucket, &path).await?;
+            let name = format!("storage/{bucket}/{path}");
+            archive
+                .start_file(name.as_str(), options)
+                .map_err(|e| e.to_string())?;
+            archive.write_all(&bytes).map_err(|e| e.to_string())?;
+            manifest.files.push(name);
+        }
+    }
+
+    archive
+        .start_file("manifest.json", options)
+        .map_err(|e| e.to_string())?;
+    serde_json::to_writer_pretty(&mut archive, &manifest).map_err(|e| e.to_string())?;
+    let bytes = archive.finish().map_err(|e| e.to_string())?.into_inner();
+
+    let path = format!("{user_id}/{export_id}.zip");
+    storage.upload(EXPORT_BUCKET, &path, bytes).await?;
+    Ok(path)
+}
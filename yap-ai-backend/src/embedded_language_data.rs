@@ -0,0 +1,59 @@
+use language_data_server::EmbeddedPack;
+use language_utils::{Course, Language};
+use std::{collections::BTreeMap, sync::LazyLock};
+
+// Include the language data rkyv file at compile time
+pub(crate) static LANGUAGE_DATA: LazyLock<BTreeMap<Course, EmbeddedPack>> = LazyLock::new(|| {
+    let mut data = BTreeMap::new();
+    data.insert(
+        Course {
+            native_language: Language::English,
+            target_language: Language::French,
+        },
+        EmbeddedPack {
+            data: include_bytes!("../../out/fra_for_eng/language_data.rkyv"),
+            hash: include_str!("../../out/fra_for_eng/language_data.hash"),
+        },
+    );
+    data.insert(
+        Course {
+            native_language: Language::French,
+            target_language: Language::English,
+        },
+        EmbeddedPack {
+            data: include_bytes!("../../out/eng_for_fra/language_data.rkyv"),
+            hash: include_str!("../../out/eng_for_fra/language_data.hash"),
+        },
+    );
+    data.insert(
+        Course {
+            native_language: Language::English,
+            target_language: Language::Spanish,
+        },
+        EmbeddedPack {
+            data: include_bytes!("../../out/spa_for_eng/language_data.rkyv"),
+            hash: include_str!("../../out/spa_for_eng/language_data.hash"),
+        },
+    );
+    data.insert(
+        Course {
+            native_language: Language::English,
+            target_language: Language::Korean,
+        },
+        EmbeddedPack {
+            data: include_bytes!("../../out/kor_for_eng/language_data.rkyv"),
+            hash: include_str!("../../out/kor_for_eng/language_data.hash"),
+        },
+    );
+    data.insert(
+        Course {
+            native_language: Language::English,
+            target_language: Language::German,
+        },
+        EmbeddedPack {
+            data: include_bytes!("../../out/deu_for_eng/language_data.rkyv"),
+            hash: include_str!("../../out/deu_for_eng/language_data.hash"),
+        },
+    );
+    data
+});
@@ -0,0 +1,101 @@
+//! Every error the backend responds with has an `ErrorEnvelope` body, so clients can tell whether
+//! and when to retry. Handlers that know more than the status (like an upstream's rate limit)
+//! return an `ApiError`, and `envelope_bare_errors` wraps the rest.
+
+use axum::{
+    extract::Json,
+    http::{HeaderValue, StatusCode, header},
+    response::{IntoResponse, Response},
+};
+use language_utils::backend_routes::ErrorEnvelope;
+
+/// Bodies of bare errors longer than this aren't used as the message
+const MAX_MESSAGE_BYTES: usize = 4096;
+
+#[derive(Debug)]
+pub(crate) struct ApiError {
+    status: StatusCode,
+    envelope: ErrorEnvelope,
+}
+
+impl ApiError {
+    fn new(status: StatusCode, message: impl Into<String>) -> Self {
+        let mut envelope = ErrorEnvelope::from_status(status.as_u16());
+        envelope.message = message.into();
+        Self { status, envelope }
+    }
+
+    /// An upstream service couldn't be reached, or its response couldn't be used
+    pub(crate) fn upstream_failed(service: &str) -> Self {
+        Self::new(StatusCode::BAD_GATEWAY, format!("{service} failed"))
+    }
+
+    /// An upstream service responded with an error. Its rate limits are passed on to the client,
+    /// along with its `Retry-After`.
+    pub(crate) fn upstream_status(service: &str, response: &reqwest::Response) -> Self {
+        let status = response.status().as_u16();
+        if status != StatusCode::TOO_MANY_REQUESTS.as_u16() {
+            return Self::new(
+                StatusCode::BAD_GATEWAY,
+                format!("{service} responded with {status}"),
+            );
+        }
+        let mut error = Self::new(
+            StatusCode::TOO_MANY_REQUESTS,
+            format!("{service} is rate limited"),
+        );
+        error.envelope.retry_after = response
+            .headers()
+            .get(reqwest::header::RETRY_AFTER)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.trim().parse().ok());
+        error
+    }
+}
+
+impl From<StatusCode> for ApiError {
+    fn from(status: StatusCode) -> Self {
+        Self::new(
+            status,
+            status.canonical_reason().unwrap_or("Request failed"),
+        )
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let retry_after = self.envelope.retry_after;
+        let mut response = (self.status, Json(self.envelope)).into_response();
+        if let Some(retry_after) = retry_after {
+            response
+                .headers_mut()
+                .insert(header::RETRY_AFTER, HeaderValue::from(retry_after));
+        }
+        response
+    }
+}
+
+/// Gives error responses that aren't JSON (a handler's bare `StatusCode`, a rejected request
+/// body, an unknown route) an envelope. A plain-text body is kept as the message.
+pub(crate) async fn envelope_bare_errors(response: Response) -> Response {
+    let status = response.status();
+    let is_json = response
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .is_some_and(|content_type| content_type.as_bytes().starts_with(b"application/json"));
+    if !(status.is_client_error() || status.is_server_error()) || is_json {
+        return response;
+    }
+
+    let (_, body) = response.into_parts();
+    let body = axum::body::to_bytes(body, MAX_MESSAGE_BYTES)
+        .await
+        .unwrap_or_default();
+    let mut error = ApiError::from(status);
+    if let Ok(message) = std::str::from_utf8(&body)
+        && !message.trim().is_empty()
+    {
+        error.envelope.message = message.trim().to_string();
+    }
+    error.into_response()
+}
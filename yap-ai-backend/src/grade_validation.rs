@@ -0,0 +1,116 @@
+//! Checks that the LLM's grades are consistent with what was asked to be graded. The JSON schema
+//! can't express any of this, so the autograding handlers run these on each response and ask
+//! again once with the problem appended to the prompt.
+
+use language_utils::Lexeme;
+use language_utils::autograde::{AutoGradeTranslationResponse, Remembered};
+
+/// There must be exactly one grade per word that was asked to be transcribed, in order
+pub(crate) fn check_transcription_grades(words: usize, grades: usize) -> Result<(), String> {
+    if grades == words {
+        return Ok(());
+    }
+    Err(format!(
+        "A previous response contained {grades} grades, but there are exactly {words} words that need grading. The grades array must contain exactly {words} grades, one for each word, in order."
+    ))
+}
+
+/// No expression may be both remembered and forgotten, and the primary expression can't be in
+/// the array contradicting its status
+pub(crate) fn check_translation_grade(
+    response: &AutoGradeTranslationResponse,
+    primary_expression: &Lexeme<String>,
+) -> Result<(), String> {
+    let describe = |lexeme: &Lexeme<String>| serde_json::to_string(lexeme).unwrap_or_default();
+
+    if let Some(lexeme) = response
+        .expressions_remembered
+        .iter()
+        .find(|lexeme| response.expressions_forgot.contains(lexeme))
+    {
+        return Err(format!(
+            "A previous response put {} in both expressions_remembered and expressions_forgot. Each expression can be in at most one of them.",
+            describe(lexeme)
+        ));
+    }
+
+    let contradicting = match response.primary_expression_status {
+        Remembered::Remembered => &response.expressions_forgot,
+        Remembered::Forgot => &response.expressions_remembered,
+    };
+    if contradicting.contains(primary_expression) {
+        return Err(format!(
+            "A previous response set primary_expression_status to {:?}, but put the primary expression {} in the other array. They must agree.",
+            response.primary_expression_status,
+            describe(primary_expression)
+        ));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use language_utils::{Heteronym, PartOfSpeech};
+
+    fn word(word: &str) -> Lexeme<String> {
+        Lexeme::Heteronym(Heteronym {
+            word: word.to_string(),
+            lemma: word.to_string(),
+            pos: PartOfSpeech::Noun,
+        })
+    }
+
+    fn response(
+        primary_expression_status: Remembered,
+        expressions_remembered: &[&str],
+        expressions_forgot: &[&str],
+    ) -> AutoGradeTranslationResponse {
+        AutoGradeTranslationResponse {
+            encouragement: None,
+            explanation: None,
+            primary_expression_status,
+            expressions_remembered: expressions_remembered.iter().map(|w| word(w)).collect(),
+            expressions_forgot: expressions_forgot.iter().map(|w| word(w)).collect(),
+            validation: Default::default(),
+        }
+    }
+
+    #[test]
+    fn test_transcription_needs_one_grade_per_word() {
+        assert!(check_transcription_grades(3, 3).is_ok());
+        assert!(check_transcription_grades(0, 0).is_ok());
+        assert!(check_transcription_grades(3, 2).is_err());
+        assert!(check_transcription_grades(3, 4).is_err());
+    }
+
+    #[test]
+    fn test_translation_grades_must_agree() {
+        let cases = [
+            (
+                response(Remembered::Remembered, &["chat", "noir"], &[]),
+                true,
+            ),
+            (response(Remembered::Forgot, &["noir"], &["chat"]), true),
+            // The frontend adds the primary expression to its array if it's missing
+            (response(Remembered::Forgot, &["noir"], &[]), true),
+            (
+                response(Remembered::Remembered, &["noir"], &["chat"]),
+                false,
+            ),
+            (response(Remembered::Forgot, &["chat"], &[]), false),
+            (
+                response(Remembered::Remembered, &["chat", "noir"], &["noir"]),
+                false,
+            ),
+        ];
+        for (response, consistent) in cases {
+            assert_eq!(
+                check_translation_grade(&response, &word("chat")).is_ok(),
+                consistent,
+                "{response:?}"
+            );
+        }
+    }
+}
@@ -0,0 +1,139 @@
+use std::time::Duration;
+
+use axum::{extract::Json, http::StatusCode};
+use language_utils::Course;
+use serde::Serialize;
+
+use crate::{LLM_API_URL, PACK_STORE, supabase::supabase_client};
+
+const CHECK_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// The process is up. Doesn't check any dependencies, so it's safe to use for restarts.
+pub(crate) async fn healthz() -> &'static str {
+    "ok"
+}
+
+#[derive(Debug, Serialize)]
+pub(crate) struct Check {
+    ok: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+impl Check {
+    fn pass() -> Self {
+        Self {
+            ok: true,
+            error: None,
+        }
+    }
+
+    fn fail(error: impl Into<String>) -> Self {
+        Self {
+            ok: false,
+            error: Some(error.into()),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub(crate) struct Readiness {
+    ready: bool,
+    postgres: Check,
+    llm: Check,
+    elevenlabs_key: Check,
+    google_tts_key: Check,
+}
+
+async fn check_postgres() -> Check {
+    let Ok(client) = supabase_client() else {
+        return Check::fail("SUPABASE_URL or SUPABASE_SERVICE_ROLE_KEY is not set");
+    };
+    let query = client.from("profiles").select("id").limit(1).execute();
+
+    match tokio::time::timeout(CHECK_TIMEOUT, query).await {
+        Ok(Ok(response)) if response.status().is_success() => Check::pass(),
+        Ok(Ok(response)) => Check::fail(format!("Supabase returned {}", response.status())),
+        Ok(Err(e)) => Check::fail(format!("{e:?}")),
+        Err(_) => Check::fail("Timed out"),
+    }
+}
+
+/// We only check that the LLM proxy can be reached, since a real completion costs money.
+/// Any HTTP response counts, even an error status.
+async fn check_llm() -> Check {
+    let client = reqwest::Client::builder()
+        .timeout(CHECK_TIMEOUT)
+        .build()
+        .unwrap_or_default();
+    match client.get(LLM_API_URL).send().await {
+        Ok(_) => Check::pass(),
+        Err(e) => Check::fail(format!("{e:?}")),
+    }
+}
+
+fn check_env(key: &str) -> Check {
+    match std::env::var(key) {
+        Ok(value) if !value.is_empty() => Check::pass(),
+        _ => Check::fail(format!("{key} is not set")),
+    }
+}
+
+/// Whether this instance can serve traffic. Responds with 503 if any check fails.
+pub(crate) async fn readyz() -> (StatusCode, Json<Readiness>) {
+    let (postgres, llm) = tokio::join!(check_postgres(), check_llm());
+    let elevenlabs_key = check_env("ELEVENLABS_API_KEY");
+    let google_tts_key = check_env("GOOGLE_CLOUD_API_KEY");
+
+    let ready = postgres.ok && llm.ok && elevenlabs_key.ok && google_tts_key.ok;
+    let status = if ready {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+
+    (
+        status,
+        Json(Readiness {
+            ready,
+            postgres,
+            llm,
+            elevenlabs_key,
+            google_tts_key,
+        }),
+    )
+}
+
+#[derive(Debug, Serialize)]
+pub(crate) struct PackInfo {
+    course: Course,
+    /// Whether the pack is already in memory (always true for embedded packs)
+    loaded: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub(crate) struct Version {
+    version: &'static str,
+    /// Set from the `GIT_SHA` environment variable at build time
+    git_sha: Option<&'static str>,
+    packs: Vec<PackInfo>,
+    courses: Vec<Course>,
+}
+
+pub(crate) async fn version() -> Json<Version> {
+    let packs = PACK_STORE
+        .courses()
+        .into_iter()
+        .map(|course| PackInfo {
+            course,
+            loaded: PACK_STORE.is_loaded(course),
+        })
+        .collect();
+
+    Json(Version {
+        version: env!("CARGO_PKG_VERSION"),
+        git_sha: option_env!("GIT_SHA"),
+        packs,
+        courses: language_utils::COURSES.to_vec(),
+    })
+}
@@ -1,8 +1,26 @@
+mod content_reports;
+mod courses;
+mod crash_reports;
+mod data_export;
+#[cfg(feature = "embedded-language-data")]
+mod embedded_language_data;
+mod errors;
+mod grade_validation;
+mod health;
+mod public_stats;
+mod scheduler_telemetry;
+mod sentence_generation;
+mod share_tokens;
+mod shared_lists;
+mod summaries;
+mod supabase;
+mod usage;
+mod webhooks;
+
 use axum::{
     Router,
     extract::Json,
-    http::{StatusCode, header},
-    response::Response,
+    http::StatusCode,
     routing::{get, post},
 };
 use axum_extra::{
@@ -10,9 +28,12 @@ use axum_extra::{
     headers::{Authorization, authorization::Bearer},
 };
 use base64::Engine;
+use errors::ApiError;
 use jsonwebtoken::{Algorithm, DecodingKey, Validation, decode};
+use language_data_server::{PackSource, PackStore};
 use language_utils::{
-    Course, Language, TtsRequest, autograde,
+    Language, TtsRequest, autograde,
+    backend_routes::{self as routes, Route as _},
     profile::{
         FollowRequest, FollowResponse, FollowStatus, GetProfileQuery, Profile,
         UpdateLanguageStatsRequest, UpdateLanguageStatsResponse, UpdateProfileRequest,
@@ -23,87 +44,62 @@ use language_utils::{
 use postgrest::Postgrest;
 use resend_rs::{Resend, types::CreateEmailBaseOptions};
 use serde::{Deserialize, Serialize};
-use std::{collections::BTreeMap, sync::LazyLock};
+use std::sync::{Arc, LazyLock};
 use tower_http::compression::CompressionLayer;
 use tower_http::cors::{Any, CorsLayer};
 use tysm::chat_completions::ChatClient;
 
+const LLM_API_URL: &str = "https://g7edusstdonmn3vxdh3qdypkrq0wzttx.lambda-url.us-east-1.on.aws/v1/";
+
 static CLIENT: LazyLock<ChatClient> = LazyLock::new(|| {
-    let my_api =
-        "https://g7edusstdonmn3vxdh3qdypkrq0wzttx.lambda-url.us-east-1.on.aws/v1/".to_string();
-    ChatClient::from_env("gpt-5.1")
+    ChatClient::from_env(usage::MODEL)
         .unwrap()
-        .with_url(my_api)
+        .with_url(LLM_API_URL.to_string())
         .with_reasoning_effort("medium")
         .with_max_concurrent_requests(3)
 });
 
 static LOW_REASONING_CLIENT: LazyLock<ChatClient> = LazyLock::new(|| {
-    let my_api =
-        "https://g7edusstdonmn3vxdh3qdypkrq0wzttx.lambda-url.us-east-1.on.aws/v1/".to_string();
-    ChatClient::from_env("gpt-5.1")
+    ChatClient::from_env(usage::MODEL)
         .unwrap()
-        .with_url(my_api)
+        .with_url(LLM_API_URL.to_string())
         .with_reasoning_effort("low")
         .with_max_concurrent_requests(3)
 });
 
 static UNAUTHENTICATED_CLIENT: LazyLock<ChatClient> = LazyLock::new(|| {
-    let my_api =
-        "https://g7edusstdonmn3vxdh3qdypkrq0wzttx.lambda-url.us-east-1.on.aws/v1/".to_string();
-    ChatClient::from_env("gpt-5.1")
+    ChatClient::from_env(usage::MODEL)
         .unwrap()
-        .with_url(my_api)
+        .with_url(LLM_API_URL.to_string())
         .with_reasoning_effort("low")
         .with_max_concurrent_requests(1)
 });
 
 const PERSONALITY: &str = r#"You are a helpful assistant that helps users learn languages. You are friendly and encouraging, and you always try to help the user learn from their mistakes. When correcting the user's mistakes, first congratulate them on the parts they did well on, and then explain the mistakes they made and how they can improve. But the main thing to do is to explain the mistakes in a helpful (but concise) way, and encourage the user. You speak conversationally, as if you were speaking to the user directly. You don't use bullet points or headings, but you do break concepts into individual lines as necessary."#;
 
-fn language_data_for_course(course: &Course) -> Option<&'static [u8]> {
-    LANGUAGE_DATA.get(course).copied()
-}
+static PACK_STORE: LazyLock<Arc<PackStore>> = LazyLock::new(|| Arc::new(language_data_store()));
 
-// Include the language data rkyv file at compile time
-static LANGUAGE_DATA: LazyLock<BTreeMap<Course, &'static [u8]>> = LazyLock::new(|| {
-    let mut data = BTreeMap::new();
-    data.insert(
-        Course {
-            native_language: Language::English,
-            target_language: Language::French,
-        },
-        include_bytes!("../../out/fra_for_eng/language_data.rkyv") as &'static [u8],
-    );
-    data.insert(
-        Course {
-            native_language: Language::French,
-            target_language: Language::English,
-        },
-        include_bytes!("../../out/eng_for_fra/language_data.rkyv") as &'static [u8],
-    );
-    data.insert(
-        Course {
-            native_language: Language::English,
-            target_language: Language::Spanish,
-        },
-        include_bytes!("../../out/spa_for_eng/language_data.rkyv") as &'static [u8],
-    );
-    data.insert(
-        Course {
-            native_language: Language::English,
-            target_language: Language::Korean,
-        },
-        include_bytes!("../../out/kor_for_eng/language_data.rkyv") as &'static [u8],
-    );
-    data.insert(
-        Course {
-            native_language: Language::English,
-            target_language: Language::German,
-        },
-        include_bytes!("../../out/deu_for_eng/language_data.rkyv") as &'static [u8],
-    );
-    data
-});
+/// Language packs are served from object storage if `LANGUAGE_DATA_URL` is set, and from the
+/// copies embedded in the binary otherwise
+fn language_data_store() -> PackStore {
+    if let Ok(base_url) = std::env::var("LANGUAGE_DATA_URL") {
+        return PackStore::new(PackSource::ObjectStorage {
+            base_url,
+            courses: language_utils::COURSES.to_vec(),
+        });
+    }
+
+    #[cfg(feature = "embedded-language-data")]
+    {
+        PackStore::new(PackSource::Embedded(
+            embedded_language_data::LANGUAGE_DATA.clone(),
+        ))
+    }
+    #[cfg(not(feature = "embedded-language-data"))]
+    {
+        panic!("LANGUAGE_DATA_URL must be set when built without embedded-language-data")
+    }
+}
 
 #[derive(Serialize)]
 struct ElevenLabsRequest {
@@ -175,10 +171,10 @@ async fn verify_jwt(token: &str) -> Result<Claims, StatusCode> {
 async fn text_to_speech(
     TypedHeader(auth): TypedHeader<Authorization<Bearer>>,
     Json(request): Json<TtsRequest>,
-) -> Result<String, StatusCode> {
-    // Verify JWT token
-    // actually, disable authentication for now until people start abusing it:
-    let _claims = verify_jwt(auth.token()).await;
+) -> Result<String, ApiError> {
+    // Anonymous users speak sentences on their device instead, so nobody can use this to get
+    // free speech synthesis
+    verify_jwt(auth.token()).await?;
 
     let client = reqwest::Client::new();
 
@@ -194,7 +190,8 @@ async fn text_to_speech(
     let elevenlabs_api_key =
         std::env::var("ELEVENLABS_API_KEY").map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
 
-    // Select voice based on language
+    // Select voice based on language. Bump `TtsProvider::voice_version` when changing one, so
+    // clients replace the audio they cached.
     let voice_id = match request.language {
         Language::French => "ohItIVrXTBI80RrUECOD", // Existing French voice
         Language::Spanish => "zl1Ut8dvwcVSuQSB9XkG", // Ninoska - Spanish voice
@@ -218,16 +215,16 @@ async fn text_to_speech(
         .json(&elevenlabs_request)
         .send()
         .await
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+        .map_err(|_| ApiError::upstream_failed("ElevenLabs"))?;
 
     if !response.status().is_success() {
-        return Err(StatusCode::BAD_GATEWAY);
+        return Err(ApiError::upstream_status("ElevenLabs", &response));
     }
 
     let audio_bytes = response
         .bytes()
         .await
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+        .map_err(|_| ApiError::upstream_failed("ElevenLabs"))?;
 
     let base64_audio = base64::engine::general_purpose::STANDARD.encode(&audio_bytes);
 
@@ -237,17 +234,18 @@ async fn text_to_speech(
 async fn google_text_to_speech(
     TypedHeader(auth): TypedHeader<Authorization<Bearer>>,
     Json(request): Json<TtsRequest>,
-) -> Result<String, StatusCode> {
-    // Verify JWT token
-    // actually, disable authentication for now until people start abusing it:
-    let _claims = verify_jwt(auth.token()).await;
+) -> Result<String, ApiError> {
+    // Anonymous users speak sentences on their device instead, so nobody can use this to get
+    // free speech synthesis
+    verify_jwt(auth.token()).await?;
 
     let client = reqwest::Client::new();
 
     let google_api_key =
         std::env::var("GOOGLE_CLOUD_API_KEY").map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
 
-    // Select voice and language code based on language
+    // Select voice and language code based on language. Bump `TtsProvider::voice_version` when
+    // changing one.
     let (language_code, voice_name) = match request.language {
         Language::French => ("fr-FR", "fr-FR-Chirp3-HD-Achernar"),
         Language::Spanish => ("es-ES", "es-ES-Chirp3-HD-Achernar"),
@@ -282,29 +280,54 @@ async fn google_text_to_speech(
         .json(&google_request)
         .send()
         .await
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+        .map_err(|_| ApiError::upstream_failed("Google TTS"))?;
 
     if !response.status().is_success() {
-        return Err(StatusCode::BAD_GATEWAY);
+        return Err(ApiError::upstream_status("Google TTS", &response));
     }
 
     let response_json: GoogleTtsResponse = response
         .json()
         .await
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+        .map_err(|_| ApiError::upstream_failed("Google TTS"))?;
 
     // Google TTS already returns base64-encoded audio
     Ok(response_json.audio_content)
 }
 
+/// The user's past mistakes, to add to a grading prompt (or nothing if there aren't any)
+fn mistake_history_prompt(mistake_history: Option<autograde::MistakeDigest>) -> String {
+    let Some(mistake_history) = mistake_history.map(autograde::MistakeDigest::capped) else {
+        return String::new();
+    };
+    if mistake_history.is_empty() {
+        return String::new();
+    }
+    let confusions = mistake_history
+        .confusions
+        .iter()
+        .map(|confusion| {
+            format!(
+                "- wrote {:?} instead of {:?} ({} times)",
+                confusion.wrote, confusion.expected, confusion.count
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+    format!(
+        "\n\nThe user has made these mistakes before. If they make one of them again, point out in the explanation that it's a recurring mistake and how to tell the words apart:\n{confusions}"
+    )
+}
+
 async fn autograde_translation(
     TypedHeader(auth): TypedHeader<Authorization<Bearer>>,
     Json(request): Json<autograde::AutoGradeTranslationRequest>,
-) -> Result<Json<autograde::AutoGradeTranslationResponse>, StatusCode> {
+) -> Result<Json<autograde::AutoGradeTranslationResponse>, ApiError> {
     // Verify JWT token
     // actually, disable authentication for now until people start abusing it:
-    let _claims = verify_jwt(auth.token()).await;
-    let logged_in = verify_jwt(auth.token()).await.is_ok();
+    let claims = verify_jwt(auth.token()).await;
+    let logged_in = claims.is_ok();
+    let user_id = claims.ok().map(|claims| claims.sub);
 
     let autograde::AutoGradeTranslationRequest {
         challenge_sentence,
@@ -312,6 +335,9 @@ async fn autograde_translation(
         primary_expression,
         lexemes,
         course,
+        explanation_language,
+        mistake_history,
+        other_native_languages,
     } = request;
 
     let target_language = course.target_language;
@@ -402,13 +428,21 @@ Output: {{
         | Language::Japanese
         | Language::Russian
         | Language::Portuguese
-        | Language::Italian => return Err(StatusCode::NOT_IMPLEMENTED),
+        | Language::Italian => return Err(StatusCode::NOT_IMPLEMENTED.into()),
     };
 
-    let native_language_name = native_language.to_string();
+    let explanation_language_name = explanation_language
+        .unwrap_or(native_language)
+        .to_string();
+    // Bilingual users may answer in any language they speak
+    let answer_language_names = std::iter::once(native_language)
+        .chain(other_native_languages)
+        .map(|language| language.to_string())
+        .collect::<Vec<_>>()
+        .join(" or ");
 
     let system_prompt = format!(
-        r#"{PERSONALITY}The user is learning {target_language_name}. They were challenged to translate a {target_language_name} sentence to {native_language_name}. Your goal is to identify which {target_language_name} words or phrases they remembered, and which ones they forgot. If they translated the sentence correctly, that means they remembered everything! But if they translated the sentence incorrectly, we need to figure out what words and phrases they seemed to have remembered correctly, and which ones they seem to have remembered incorrectly. This will be used as part of a spaced-repetition system, which will help users study the words they need to. The system can only incorporate this for the words that it knows are in the sentence, which will be provided to you. Words are provided with additional context about their part of speech and lemmatised form, to allow you to distinguish between different usages of the same word. The 'primary word' is also provided, which is the word that the sentence most needed to test. You should always provide encouragement highlighting what the user got right and acknowledging their progress. If there are any errors, also provide a brief explanation focusing on where they made mistakes and how they can improve.
+        r#"{PERSONALITY}The user is learning {target_language_name}. They were challenged to translate a {target_language_name} sentence to {answer_language_names}. Your goal is to identify which {target_language_name} words or phrases they remembered, and which ones they forgot. If they translated the sentence correctly, that means they remembered everything! But if they translated the sentence incorrectly, we need to figure out what words and phrases they seemed to have remembered correctly, and which ones they seem to have remembered incorrectly. This will be used as part of a spaced-repetition system, which will help users study the words they need to. The system can only incorporate this for the words that it knows are in the sentence, which will be provided to you. Words are provided with additional context about their part of speech and lemmatised form, to allow you to distinguish between different usages of the same word. The 'primary word' is also provided, which is the word that the sentence most needed to test. You should always provide encouragement highlighting what the user got right and acknowledging their progress. If there are any errors, also provide a brief explanation focusing on where they made mistakes and how they can improve.
 
 Many sentences will be "partial sentences," such as "Ne pas." meaning "Do not." These partial sentences are still useful as test sentences for the user, so you should still grade them.
 
@@ -419,7 +453,7 @@ Respond with JSON.
 {example}
 If there are lexemes (particularly multiword terms) that are not in the challenge sentence, do not include them in the expressions_remembered or expressions_forgot arrays. (Since the user did not have a chance to try to translate them.) However, if the user forgot a word that is in the challenge sentence, include it in the expressions_forgot array. The conjugations used in the multiword terms might be different than how they appear in the challenge sentence.
 
-The encouragement should always be provided, be a short positive message (1-2 sentences), focus on what they got right, and be written as if speaking directly to the user. The explanation should only be provided if there are errors, focus on their mistakes and how to improve, and be written as if speaking directly to the user. Markdown formatting is allowed for both (just no bullet points or numbered lists). Try to keep both short and concise. The user is still learning {target_language_name}, so respond in {native_language_name}!
+The encouragement should always be provided, be a short positive message (1-2 sentences), focus on what they got right, and be written as if speaking directly to the user. The explanation should only be provided if there are errors, focus on their mistakes and how to improve, and be written as if speaking directly to the user. Markdown formatting is allowed for both (just no bullet points or numbered lists). Try to keep both short and concise. The user is still learning {target_language_name}, so respond in {explanation_language_name}!
 "#,
     );
 
@@ -432,37 +466,81 @@ The encouragement should always be provided, be a short positive message (1-2 se
         &CLIENT
     };
 
-    let autograde_response: autograde::AutoGradeTranslationResponse = client.chat_with_system_prompt(
-        system_prompt,
-        &{
-            format!(
-            "{target_language_name} challenge sentence: {challenge_sentence}\nUser response: {user_sentence}\nPrimary expression: {primary_expression}\nExpressions: {expressions}",
-            challenge_sentence = challenge_sentence,
-            user_sentence = user_sentence,
-            primary_expression = serde_json::to_value(&primary_expression).unwrap(),
-            expressions = serde_json::to_value(&lexemes).unwrap()
-        )}
-    )
-    .await
-    .inspect_err(|e| eprintln!("Error: {e:?}"))
-    .map_err(|_e| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let prompt = format!(
+        "{target_language_name} challenge sentence: {challenge_sentence}\nUser response: {user_sentence}\nPrimary expression: {primary_expression}\nExpressions: {expressions}{mistake_history}",
+        challenge_sentence = challenge_sentence,
+        user_sentence = user_sentence,
+        primary_expression = serde_json::to_value(&primary_expression).unwrap(),
+        expressions = serde_json::to_value(&lexemes).unwrap(),
+        mistake_history = mistake_history_prompt(mistake_history),
+    );
+
+    let call = usage::LlmCall::start("autograde-translation", user_id, &system_prompt, &prompt);
+    let autograde_response: Result<autograde::AutoGradeTranslationResponse, _> = client
+        .chat_with_system_prompt(system_prompt.clone(), &prompt)
+        .await;
+    call.finish(autograde_response.as_ref().ok());
+    let mut autograde_response = autograde_response
+        .inspect_err(|e| eprintln!("Error: {e:?}"))
+        .map_err(|_e| ApiError::upstream_failed("The language model"))?;
     eprintln!("Response: {autograde_response:?}");
 
+    // Same as for transcriptions: ask again once if the grade contradicts itself
+    let mut validation = transcription_challenge::GradeValidation::Valid;
+    if let Err(problem) =
+        grade_validation::check_translation_grade(&autograde_response, &primary_expression)
+    {
+        eprintln!("{problem} Asking again.");
+        let repair_prompt = format!("{prompt}\n\n{problem}");
+        let call = usage::LlmCall::start(
+            "autograde-translation",
+            user_id,
+            &system_prompt,
+            &repair_prompt,
+        );
+        let repaired: Result<autograde::AutoGradeTranslationResponse, _> = client
+            .chat_with_system_prompt(system_prompt, &repair_prompt)
+            .await;
+        call.finish(repaired.as_ref().ok());
+        validation = match repaired {
+            Ok(repaired) => {
+                match grade_validation::check_translation_grade(&repaired, &primary_expression) {
+                    Ok(()) => {
+                        autograde_response = repaired;
+                        transcription_challenge::GradeValidation::Repaired
+                    }
+                    Err(problem) => {
+                        eprintln!("{problem} Giving up.");
+                        transcription_challenge::GradeValidation::Invalid
+                    }
+                }
+            }
+            Err(e) => {
+                eprintln!("Error: {e:?}");
+                transcription_challenge::GradeValidation::Invalid
+            }
+        };
+    }
+    autograde_response.validation = validation;
+
     Ok(Json(autograde_response))
 }
 
 async fn autograde_transcription(
     TypedHeader(auth): TypedHeader<Authorization<Bearer>>,
     Json(request): Json<autograde::AutoGradeTranscriptionRequest>,
-) -> Result<Json<transcription_challenge::Grade>, StatusCode> {
+) -> Result<Json<transcription_challenge::Grade>, ApiError> {
     // Verify JWT token
     // actually, disable authentication for now until people start abusing it:
-    let _claims = verify_jwt(auth.token()).await;
+    let user_id = verify_jwt(auth.token()).await.ok().map(|claims| claims.sub);
 
     let target_language = request.course.target_language;
     let native_language = request.course.native_language;
     let target_language_name = target_language.to_string();
-    let native_language_name = native_language.to_string();
+    let explanation_language_name = request
+        .explanation_language
+        .unwrap_or(native_language)
+        .to_string();
 
     let system_prompt = format!(
         r#"{PERSONALITY}The user is learning {target_language_name} through transcription exercises. They listened to {target_language_name} audio and were asked to transcribe certain parts of the sentence while other parts were provided to them. Your job is to grade their transcription by comparing what they heard with what they wrote.
@@ -488,7 +566,7 @@ Respond with JSON in this format:
 
 The grades array should have one grade for each word the user was asked to transcribe, in the order they appear.
 
-The encouragement should always be provided, be in {native_language_name}, be a short positive message (1-2 sentences), and focus on what they got right. The explanation should only be provided if there are errors, be in {native_language_name}, focus on their mistakes and how to improve, and help the user learn from their errors. Markdown formatting is allowed, and encouraged for emphasis (just no bullet points or numbered lists). If the user appeared to confuse some words, you can include those words in the compare array, and a TTS example for each word will be generated for the user to hear. {}
+The encouragement should always be provided, be in {explanation_language_name}, be a short positive message (1-2 sentences), and focus on what they got right. The explanation should only be provided if there are errors, be in {explanation_language_name}, focus on their mistakes and how to improve, and help the user learn from their errors. Markdown formatting is allowed, and encouraged for emphasis (just no bullet points or numbered lists). If the user appeared to confuse some words, you can include those words in the compare array, and a TTS example for each word will be generated for the user to hear. {}
 
 P.S. Don't bother giving the user IPA-style phonetic transcriptions as they may not understand them. But you can still try to explain the phonetic differences in terms that the user might understand."#,
         match target_language {
@@ -507,7 +585,7 @@ P.S. Don't bother giving the user IPA-style phonetic transcriptions as they may
             | Language::Japanese
             | Language::Russian
             | Language::Portuguese
-            | Language::Italian => return Err(StatusCode::NOT_IMPLEMENTED),
+            | Language::Italian => return Err(StatusCode::NOT_IMPLEMENTED.into()),
         }
     );
 
@@ -577,11 +655,12 @@ User saw: {}
 User wrote: {}
 
 Words that need grading:
-{}"#,
+{}{}"#,
         full_sentence,
         sentence_shown,
         user_sentence,
-        words_to_grade_list.join("\n")
+        words_to_grade_list.join("\n"),
+        mistake_history_prompt(request.mistake_history.clone()),
     );
 
     #[derive(
@@ -635,7 +714,7 @@ Words that need grading:
     }
 
     // Get response from LLM
-    #[derive(Deserialize, schemars::JsonSchema)]
+    #[derive(Serialize, Deserialize, schemars::JsonSchema)]
     struct LlmResponse {
         encouragement: Option<String>,
         explanation: Option<String>,
@@ -643,11 +722,54 @@ Words that need grading:
         compare: Vec<String>,
     }
 
-    let llm_response: LlmResponse = CLIENT
-        .chat_with_system_prompt(system_prompt, &prompt)
-        .await
+    let call = usage::LlmCall::start("autograde-transcription", user_id, &system_prompt, &prompt);
+    let llm_response: Result<LlmResponse, _> = CLIENT
+        .chat_with_system_prompt(system_prompt.clone(), &prompt)
+        .await;
+    call.finish(llm_response.as_ref().ok());
+    let mut llm_response = llm_response
         .inspect_err(|e| eprintln!("Error: {e:?}"))
-        .map_err(|_e| StatusCode::INTERNAL_SERVER_ERROR)?;
+        .map_err(|_e| ApiError::upstream_failed("The language model"))?;
+
+    // The schema can't express that the grades array must have exactly one entry per word,
+    // so check that ourselves and ask again once if it doesn't
+    let mut validation = transcription_challenge::GradeValidation::Valid;
+    if let Err(problem) = grade_validation::check_transcription_grades(
+        all_words_to_grade.len(),
+        llm_response.grades.len(),
+    ) {
+        eprintln!("{problem} Asking again.");
+        let repair_prompt = format!("{prompt}\n\n{problem}");
+        let call = usage::LlmCall::start(
+            "autograde-transcription",
+            user_id,
+            &system_prompt,
+            &repair_prompt,
+        );
+        let repaired: Result<LlmResponse, _> = CLIENT
+            .chat_with_system_prompt(system_prompt, &repair_prompt)
+            .await;
+        call.finish(repaired.as_ref().ok());
+        validation = match repaired {
+            Ok(repaired) => match grade_validation::check_transcription_grades(
+                all_words_to_grade.len(),
+                repaired.grades.len(),
+            ) {
+                Ok(()) => {
+                    llm_response = repaired;
+                    transcription_challenge::GradeValidation::Repaired
+                }
+                Err(problem) => {
+                    eprintln!("{problem} Giving up.");
+                    transcription_challenge::GradeValidation::Invalid
+                }
+            },
+            Err(e) => {
+                eprintln!("Error: {e:?}");
+                transcription_challenge::GradeValidation::Invalid
+            }
+        };
+    }
 
     // Convert LLM response to Grade structure
     let mut results = Vec::new();
@@ -691,6 +813,7 @@ Words that need grading:
         compare: llm_response.compare,
         results,
         autograding_error: None,
+        validation,
     };
 
     Ok(Json(grade))
@@ -1034,6 +1157,8 @@ async fn update_language_stats(
     // Verify JWT token to get the user's ID
     let claims = verify_jwt(auth.token()).await?;
     let user_id = claims.sub;
+    // Webhooks also look at the stats that aren't stored
+    let webhook_stats = request.clone();
 
     // Get Supabase credentials from environment
     let supabase_url =
@@ -1101,6 +1226,8 @@ async fn update_language_stats(
         })?;
 
     if response.status().is_success() {
+        webhooks::dispatch(user_id, webhook_stats);
+        summaries::invalidate(user_id);
         Ok(Json(UpdateLanguageStatsResponse { success: true }))
     } else {
         eprintln!(
@@ -1111,22 +1238,6 @@ async fn update_language_stats(
     }
 }
 
-async fn serve_language_data(Json(course): Json<Course>) -> Response {
-    if let Some(language_data) = language_data_for_course(&course) {
-        Response::builder()
-            .status(StatusCode::OK)
-            .header(header::CONTENT_TYPE, "application/octet-stream")
-            .header(header::CONTENT_LENGTH, language_data.len())
-            .body(axum::body::Body::from(language_data))
-            .unwrap()
-    } else {
-        Response::builder()
-            .status(StatusCode::NOT_FOUND)
-            .body(axum::body::Body::from("Not found"))
-            .unwrap()
-    }
-}
-
 async fn follow_user(
     TypedHeader(auth): TypedHeader<Authorization<Bearer>>,
     Json(request): Json<FollowRequest>,
@@ -1378,17 +1489,99 @@ async fn main() {
 
     let app = Router::new()
         .route("/", get(|| async { "Hello from fly.io!" }))
-        .route("/tts", post(text_to_speech))
-        .route("/tts/google", post(google_text_to_speech))
-        .route("/autograde-translation", post(autograde_translation))
-        .route("/autograde-transcription", post(autograde_transcription))
-        .route("/language-data", post(serve_language_data))
-        .route("/profile", get(get_profile).patch(update_profile))
-        .route("/language-stats", post(update_language_stats))
-        .route("/user-language-stats", get(get_language_stats))
-        .route("/follow", post(follow_user))
-        .route("/unfollow", post(unfollow_user))
-        .route("/follow-status", get(get_follow_status))
+        .route(routes::TTS_PATH, post(text_to_speech))
+        .route(routes::GOOGLE_TTS_PATH, post(google_text_to_speech))
+        .route(
+            routes::AutogradeTranslation::PATH,
+            post(autograde_translation),
+        )
+        .route(
+            routes::AutogradeTranscription::PATH,
+            post(autograde_transcription),
+        )
+        .route(
+            routes::GenerateSentence::PATH,
+            post(sentence_generation::generate_sentence),
+        )
+        .merge(language_data_server::router(PACK_STORE.clone()))
+        .route("/healthz", get(health::healthz))
+        .route("/readyz", get(health::readyz))
+        .route("/version", get(health::version))
+        .route(routes::Courses::PATH, get(courses::courses))
+        // `UpdateProfile` has the same path
+        .route(
+            routes::GetProfile::PATH,
+            get(get_profile).patch(update_profile),
+        )
+        .route(
+            routes::UpdateLanguageStats::PATH,
+            post(update_language_stats),
+        )
+        .route(routes::GetLanguageStats::PATH, get(get_language_stats))
+        .route(routes::Follow::PATH, post(follow_user))
+        .route(routes::Unfollow::PATH, post(unfollow_user))
+        .route(routes::GetFollowStatus::PATH, get(get_follow_status))
+        .route(routes::SummariesDirty::PATH, post(summaries::summaries_dirty))
+        .route("/usage/summary", get(usage::usage_summary))
+        .route("/usage/me", get(usage::my_usage))
+        // `ShareList` has the same path
+        .route(
+            routes::GetSharedList::PATH,
+            get(shared_lists::get_shared_list).post(shared_lists::share_list),
+        )
+        .route(
+            routes::SubmitCrashReport::PATH,
+            post(crash_reports::submit_crash_report),
+        )
+        .route(
+            routes::SubmitSchedulerTelemetry::PATH,
+            post(scheduler_telemetry::submit_scheduler_telemetry),
+        )
+        // `SubmitContentReport` has the same path
+        .route(
+            routes::GetContentReports::PATH,
+            get(content_reports::get_content_reports)
+                .post(content_reports::submit_content_report),
+        )
+        // `SetPublicStats` has the same path
+        .route(
+            routes::GetPublicStatsSettings::PATH,
+            get(public_stats::get_public_stats_settings).post(public_stats::set_public_stats),
+        )
+        .route(routes::GetPublicStats::PATH, get(public_stats::get_public_stats))
+        // `CreateShareToken` has the same path
+        .route(
+            routes::GetShareTokens::PATH,
+            get(share_tokens::get_share_tokens).post(share_tokens::create_share_token),
+        )
+        .route(
+            routes::RevokeShareToken::PATH,
+            post(share_tokens::revoke_share_token),
+        )
+        .route(
+            routes::GetSharedStats::PATH,
+            get(share_tokens::get_shared_stats),
+        )
+        // `UpdateSharedDeck` has the same path
+        .route(
+            routes::GetSharedDeck::PATH,
+            get(share_tokens::get_shared_deck).post(share_tokens::update_shared_deck),
+        )
+        .route(
+            routes::GetSharedEvents::PATH,
+            get(share_tokens::get_shared_events),
+        )
+        // `SetWebhook` has the same path
+        .route(
+            routes::GetWebhook::PATH,
+            get(webhooks::get_webhook).post(webhooks::set_webhook),
+        )
+        // `RequestDataExport` has the same path
+        .route(
+            routes::GetDataExport::PATH,
+            get(data_export::get_data_export).post(data_export::request_data_export),
+        )
+        .layer(axum::middleware::map_response(errors::envelope_bare_errors))
         .layer(CompressionLayer::new())
         .layer(cors);
 
@@ -0,0 +1,182 @@
+//! Public progress pages, see `language_utils::public_stats`. The page's stats are a copy of the
+//! user's `user_language_stats` rows, made whenever the app uploads new ones, so serving a page
+//! is a single lookup by share id and never touches the user's own rows.
+//!
+//! Pages are stored in the `public_stats` table, with a row only for users who opted in:
+//!
+//! ```sql
+//! create table public_stats (
+//!     user_id uuid primary key references auth.users,
+//!     share_id text not null unique,
+//!     stats jsonb
+//! );
+//! ```
+
+use axum::{
+    extract::{Json, Query},
+    http::{StatusCode, header},
+    response::IntoResponse,
+};
+use axum_extra::{
+    TypedHeader,
+    headers::{Authorization, authorization::Bearer},
+};
+use chrono::Utc;
+use language_utils::profile::UserLanguageStats;
+use language_utils::public_stats::{
+    GetPublicStatsQuery, PublicLanguageStats, PublicStats, PublicStatsSettings,
+    SetPublicStatsRequest,
+};
+use postgrest::Postgrest;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    supabase::{execute, fetch_rows, supabase_client},
+    verify_jwt,
+};
+
+/// Pages only change when the user syncs, so they can be cached for a while
+const CACHE_CONTROL: &str = "public, max-age=300";
+
+#[derive(Debug, Serialize, Deserialize)]
+struct PublicStatsRow {
+    user_id: uuid::Uuid,
+    share_id: String,
+    /// `None` until the stats are first copied
+    stats: Option<PublicStats>,
+}
+
+async fn fetch_row(
+    client: &Postgrest,
+    column: &str,
+    value: String,
+) -> Result<Option<PublicStatsRow>, StatusCode> {
+    let rows: Vec<PublicStatsRow> = fetch_rows(
+        client.from("public_stats").select("*").eq(column, value),
+        "public stats",
+    )
+    .await?;
+    Ok(rows.into_iter().next())
+}
+
+async fn upsert_row(client: &Postgrest, row: &PublicStatsRow) -> Result<(), StatusCode> {
+    let body = serde_json::to_string(row).map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    execute(
+        client
+            .from("public_stats")
+            .upsert(body)
+            .on_conflict("user_id"),
+        "saving public stats",
+    )
+    .await
+}
+
+/// The user's stats, without their id
+async fn public_stats_of(
+    client: &Postgrest,
+    user_id: uuid::Uuid,
+) -> Result<PublicStats, StatusCode> {
+    let stats: Vec<UserLanguageStats> = fetch_rows(
+        client
+            .from("user_language_stats")
+            .select("*")
+            .eq("user_id", user_id.to_string()),
+        "language stats",
+    )
+    .await?;
+
+    let mut languages = stats
+        .into_iter()
+        .map(|stats| PublicLanguageStats {
+            language: stats.language,
+            total_reviews: stats.total_count,
+            daily_streak: stats.daily_streak,
+            xp: stats.xp,
+            percent_known: stats.percent_known,
+            started: stats.started,
+        })
+        .collect::<Vec<_>>();
+    languages.sort_by(|a, b| b.xp.total_cmp(&a.xp));
+    Ok(PublicStats {
+        languages,
+        updated_at: Utc::now().to_rfc3339(),
+    })
+}
+
+/// Copies the user's latest stats to their public page, if they have one. Called after the app
+/// uploads stats, without holding up the response.
+pub(crate) fn refresh(user_id: uuid::Uuid) {
+    tokio::spawn(async move {
+        let Ok(client) = supabase_client() else {
+            return;
+        };
+        let Ok(Some(mut row)) = fetch_row(&client, "user_id", user_id.to_string()).await else {
+            return;
+        };
+        let Ok(stats) = public_stats_of(&client, user_id).await else {
+            return;
+        };
+        row.stats = Some(stats);
+        let _ = upsert_row(&client, &row).await;
+    });
+}
+
+pub(crate) async fn get_public_stats_settings(
+    TypedHeader(auth): TypedHeader<Authorization<Bearer>>,
+) -> Result<Json<PublicStatsSettings>, StatusCode> {
+    let claims = verify_jwt(auth.token()).await?;
+    let row = fetch_row(&supabase_client()?, "user_id", claims.sub.to_string()).await?;
+    Ok(Json(PublicStatsSettings {
+        enabled: row.is_some(),
+        share_id: row.map(|row| row.share_id),
+    }))
+}
+
+pub(crate) async fn set_public_stats(
+    TypedHeader(auth): TypedHeader<Authorization<Bearer>>,
+    Json(request): Json<SetPublicStatsRequest>,
+) -> Result<Json<PublicStatsSettings>, StatusCode> {
+    let claims = verify_jwt(auth.token()).await?;
+    let client = supabase_client()?;
+
+    if !request.enabled {
+        execute(
+            client
+                .from("public_stats")
+                .delete()
+                .eq("user_id", claims.sub.to_string()),
+            "deleting public stats",
+        )
+        .await?;
+        return Ok(Json(PublicStatsSettings {
+            enabled: false,
+            share_id: None,
+        }));
+    }
+
+    // Enabling a page that's already public keeps its link
+    let share_id = match fetch_row(&client, "user_id", claims.sub.to_string()).await? {
+        Some(existing) => existing.share_id,
+        None => uuid::Uuid::new_v4().simple().to_string(),
+    };
+    let row = PublicStatsRow {
+        user_id: claims.sub,
+        share_id: share_id.clone(),
+        stats: Some(public_stats_of(&client, claims.sub).await?),
+    };
+    upsert_row(&client, &row).await?;
+    Ok(Json(PublicStatsSettings {
+        enabled: true,
+        share_id: Some(share_id),
+    }))
+}
+
+pub(crate) async fn get_public_stats(
+    Query(query): Query<GetPublicStatsQuery>,
+) -> Result<impl IntoResponse, StatusCode> {
+    let row = fetch_row(&supabase_client()?, "share_id", query.share_id)
+        .await?
+        .ok_or(StatusCode::NOT_FOUND)?;
+    let stats = row.stats.ok_or(StatusCode::NOT_FOUND)?;
+    Ok(([(header::CACHE_CONTROL, CACHE_CONTROL)], Json(stats)))
+}
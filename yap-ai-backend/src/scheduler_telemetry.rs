@@ -0,0 +1,38 @@
+//! Noised histograms of how reviews went after each length of interval, from users who opted in
+//! to sharing them, see `language_utils::scheduler_telemetry`. They're sent without a token and
+//! stored without a user, in the `scheduler_telemetry` table:
+//!
+//! ```sql
+//! create table scheduler_telemetry (
+//!     id bigint generated always as identity primary key,
+//!     received_at timestamptz not null default now(),
+//!     app_version text not null,
+//!     epsilon double precision not null,
+//!     window_days integer not null,
+//!     buckets jsonb not null
+//! );
+//! ```
+
+use axum::{extract::Json, http::StatusCode};
+use language_utils::scheduler_telemetry::{MAX_TELEMETRY_BUCKETS, SchedulerTelemetry};
+
+use crate::supabase::{execute, supabase_client};
+
+pub(crate) async fn submit_scheduler_telemetry(
+    Json(telemetry): Json<SchedulerTelemetry>,
+) -> Result<Json<()>, StatusCode> {
+    if telemetry.buckets.is_empty()
+        || telemetry.buckets.len() > MAX_TELEMETRY_BUCKETS
+        || !(telemetry.epsilon.is_finite() && telemetry.epsilon > 0.0)
+    {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    let body = serde_json::to_string(&telemetry).map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    execute(
+        supabase_client()?.from("scheduler_telemetry").insert(body),
+        "inserting scheduler telemetry",
+    )
+    .await?;
+    Ok(Json(()))
+}
@@ -0,0 +1,413 @@
+use axum::{extract::Json, http::StatusCode};
+use axum_extra::{
+    TypedHeader,
+    headers::{Authorization, authorization::Bearer},
+};
+use language_utils::{
+    Course, Heteronym, Language, Lexeme, Literal, PartOfSpeech,
+    sentence_generation::{
+        GenerateSentenceRequest, GenerateSentenceResponse, GeneratedSentence, MAX_KNOWN_LEXEMES,
+    },
+};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    sync::{LazyLock, Mutex},
+};
+
+use crate::{CLIENT, PERSONALITY, errors::ApiError, usage::LlmCall, verify_jwt};
+
+/// How many candidate sentences we ask the LLM for in one go
+const CANDIDATES_PER_REQUEST: usize = 4;
+
+/// How many lexemes' sentences `GENERATED_SENTENCES` holds before evicting the least recently
+/// used
+const MAX_CACHED_LEXEMES: usize = 10_000;
+/// How many sentences `GENERATED_SENTENCES` keeps for one lexeme. Later ones are dropped.
+const MAX_SENTENCES_PER_LEXEME: usize = 16;
+
+/// Sentences that passed validation, keyed by the lexeme they were generated for.
+/// A cached sentence is only reused if the requesting user knows all of its lexemes.
+static GENERATED_SENTENCES: LazyLock<Mutex<SentenceCache>> =
+    LazyLock::new(|| Mutex::new(SentenceCache::default()));
+
+#[derive(Default)]
+struct SentenceCache {
+    lexemes: BTreeMap<(Course, Lexeme<String>), CachedSentences>,
+    /// Incremented on every use, to tell which lexeme was used least recently
+    clock: u64,
+}
+
+struct CachedSentences {
+    last_used: u64,
+    sentences: Vec<GeneratedSentence>,
+}
+
+impl SentenceCache {
+    fn get(&mut self, key: &(Course, Lexeme<String>)) -> Option<&[GeneratedSentence]> {
+        self.clock += 1;
+        let cached = self.lexemes.get_mut(key)?;
+        cached.last_used = self.clock;
+        Some(&cached.sentences)
+    }
+
+    fn insert(&mut self, key: (Course, Lexeme<String>), sentences: Vec<GeneratedSentence>) {
+        self.clock += 1;
+        let cached = self.lexemes.entry(key).or_insert_with(|| CachedSentences {
+            last_used: 0,
+            sentences: Vec::new(),
+        });
+        cached.last_used = self.clock;
+        cached.sentences.extend(sentences);
+        cached.sentences.truncate(MAX_SENTENCES_PER_LEXEME);
+
+        if self.lexemes.len() > MAX_CACHED_LEXEMES
+            && let Some(least_recently_used) = self
+                .lexemes
+                .iter()
+                .min_by_key(|(_, cached)| cached.last_used)
+                .map(|(key, _)| key.clone())
+        {
+            self.lexemes.remove(&least_recently_used);
+        }
+    }
+}
+
+static LEXIDE: LazyLock<Option<lexide::Lexide>> = LazyLock::new(|| {
+    lexide::Lexide::from_server("https://anchpop--lexide-gemma-3-27b-vllm-serve.modal.run")
+        .inspect_err(|e| eprintln!("Failed to initialize lexide: {e:?}"))
+        .ok()
+});
+
+fn to_lexide_language(language: Language) -> Option<lexide::Language> {
+    match language {
+        Language::French => Some(lexide::Language::French),
+        Language::English => Some(lexide::Language::English),
+        Language::Spanish => Some(lexide::Language::Spanish),
+        Language::Korean => Some(lexide::Language::Korean),
+        Language::German => Some(lexide::Language::German),
+        Language::Chinese
+        | Language::Japanese
+        | Language::Russian
+        | Language::Portuguese
+        | Language::Italian => None,
+    }
+}
+
+fn token_to_literal(token: &lexide::Token) -> Literal<String> {
+    // Both enums have identical variants with the same serde renames,
+    // so we can convert by serializing and deserializing
+    let pos: PartOfSpeech = serde_json::to_value(token.pos)
+        .and_then(serde_json::from_value)
+        .unwrap_or(PartOfSpeech::X);
+
+    let text = token
+        .text
+        .text
+        .trim_matches(|c| matches!(c, '.' | ',' | '!' | '?' | ':' | ';' | '-'));
+
+    let heteronym = (!matches!(
+        pos,
+        PartOfSpeech::Punct | PartOfSpeech::Space | PartOfSpeech::X | PartOfSpeech::Propn
+    ) && !text.is_empty())
+    .then(|| Heteronym {
+        word: text.to_lowercase(),
+        lemma: token.lemma.lemma.to_lowercase(),
+        pos,
+    });
+
+    Literal {
+        text: token.text.text.clone(),
+        whitespace: token.whitespace.clone(),
+        heteronym,
+        morph: None,
+    }
+}
+
+/// Checks a tokenized candidate against the user's vocabulary.
+///
+/// Words are matched on their surface form and part of speech rather than on the lemma, since
+/// the lemmas produced at pack-generation time go through language-specific normalization that
+/// we don't replicate here. That makes us a bit stricter than necessary, which is fine.
+fn validate_candidate(
+    target_language: String,
+    native_translation: String,
+    literals: Vec<Literal<String>>,
+    target_lexeme: &Lexeme<String>,
+    known_lexemes: &BTreeSet<Lexeme<String>>,
+) -> Option<GeneratedSentence> {
+    let allowed_words: BTreeSet<(&str, PartOfSpeech)> = known_lexemes
+        .iter()
+        .chain(std::iter::once(target_lexeme))
+        .filter_map(Lexeme::heteronym)
+        .map(|heteronym| (heteronym.word.as_str(), heteronym.pos))
+        .collect();
+
+    let mut lexemes = Vec::new();
+    for heteronym in literals.iter().filter_map(|l| l.heteronym.as_ref()) {
+        if !allowed_words.contains(&(heteronym.word.as_str(), heteronym.pos)) {
+            return None;
+        }
+        let lexeme = Lexeme::Heteronym(heteronym.clone());
+        if !lexemes.contains(&lexeme) {
+            lexemes.push(lexeme);
+        }
+    }
+
+    let contains_target = match target_lexeme {
+        Lexeme::Heteronym(target) => literals
+            .iter()
+            .filter_map(|l| l.heteronym.as_ref())
+            .any(|h| h.word == target.word && h.pos == target.pos),
+        // We don't run the multiword matcher here, so settle for the term appearing verbatim
+        Lexeme::Multiword(term) => target_language
+            .to_lowercase()
+            .contains(&term.to_lowercase()),
+    };
+    if !contains_target {
+        return None;
+    }
+    if let Lexeme::Multiword(_) = target_lexeme {
+        lexemes.push(target_lexeme.clone());
+    }
+
+    Some(GeneratedSentence {
+        target_language,
+        native_translations: vec![native_translation],
+        literals,
+        lexemes,
+    })
+}
+
+fn cached_sentence(
+    course: Course,
+    target_lexeme: &Lexeme<String>,
+    known_lexemes: &BTreeSet<Lexeme<String>>,
+) -> Option<GeneratedSentence> {
+    let mut cache = GENERATED_SENTENCES.lock().ok()?;
+    cache
+        .get(&(course, target_lexeme.clone()))?
+        .iter()
+        .find(|sentence| {
+            sentence
+                .lexemes
+                .iter()
+                .all(|lexeme| lexeme == target_lexeme || known_lexemes.contains(lexeme))
+        })
+        .cloned()
+}
+
+pub(crate) async fn generate_sentence(
+    TypedHeader(auth): TypedHeader<Authorization<Bearer>>,
+    Json(request): Json<GenerateSentenceRequest>,
+) -> Result<Json<GenerateSentenceResponse>, ApiError> {
+    // Generation is comparatively expensive, so unlike grading we require a logged-in user
+    let claims = verify_jwt(auth.token()).await?;
+
+    let GenerateSentenceRequest {
+        course,
+        target_lexeme,
+        known_lexemes,
+    } = request;
+    // They all go in the prompt
+    if known_lexemes.len() > MAX_KNOWN_LEXEMES {
+        return Err(StatusCode::PAYLOAD_TOO_LARGE.into());
+    }
+    let known_lexemes: BTreeSet<Lexeme<String>> = known_lexemes.into_iter().collect();
+
+    if let Some(sentence) = cached_sentence(course, &target_lexeme, &known_lexemes) {
+        return Ok(Json(GenerateSentenceResponse {
+            sentence: Some(sentence),
+        }));
+    }
+
+    let lexide_language =
+        to_lexide_language(course.target_language).ok_or(StatusCode::NOT_IMPLEMENTED)?;
+    let lexide = LEXIDE.as_ref().ok_or(StatusCode::SERVICE_UNAVAILABLE)?;
+
+    let target_language_name = course.target_language.to_string();
+    let native_language_name = course.native_language.to_string();
+
+    let system_prompt = format!(
+        r#"{PERSONALITY}The user is learning {target_language_name}. We need short, natural example sentences that help them practice a specific word or expression. The catch is that the user only knows a limited vocabulary, so every other word in the sentence must come from the list of known words provided. Do not use any word that isn't on the list, including different conjugations or inflections of listed words, and avoid proper nouns. Short sentences (even partial sentences) are fine. Provide {CANDIDATES_PER_REQUEST} different candidates, each with a natural {native_language_name} translation.
+
+Respond with JSON."#
+    );
+
+    let prompt = format!(
+        "Target expression: {target}\nKnown words: {known}",
+        target = serde_json::to_value(&target_lexeme).unwrap(),
+        known = serde_json::to_value(&known_lexemes).unwrap(),
+    );
+
+    #[derive(serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+    struct Candidate {
+        target_language: String,
+        native_language: String,
+    }
+
+    #[derive(serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+    struct LlmResponse {
+        candidates: Vec<Candidate>,
+    }
+
+    let call = LlmCall::start("generate-sentence", Some(claims.sub), &system_prompt, &prompt);
+    let llm_response: Result<LlmResponse, _> =
+        CLIENT.chat_with_system_prompt(system_prompt, &prompt).await;
+    call.finish(llm_response.as_ref().ok());
+    let llm_response = llm_response
+        .inspect_err(|e| eprintln!("Error: {e:?}"))
+        .map_err(|_e| ApiError::upstream_failed("The language model"))?;
+
+    let mut valid_sentences = Vec::new();
+    for candidate in llm_response.candidates {
+        let sentence = language_utils::text_cleanup::cleanup_sentence(
+            candidate.target_language,
+            course.target_language,
+        );
+        let tokenization = match lexide.analyze(&sentence, lexide_language).await {
+            Ok(tokenization) => tokenization,
+            Err(e) => {
+                eprintln!("Failed to analyze generated sentence '{sentence}': {e:?}");
+                continue;
+            }
+        };
+        let literals = tokenization.tokens.iter().map(token_to_literal).collect();
+        if let Some(valid) = validate_candidate(
+            sentence,
+            candidate.native_language,
+            literals,
+            &target_lexeme,
+            &known_lexemes,
+        ) {
+            valid_sentences.push(valid);
+        }
+    }
+
+    let sentence = valid_sentences.first().cloned();
+
+    if !valid_sentences.is_empty()
+        && let Ok(mut cache) = GENERATED_SENTENCES.lock()
+    {
+        cache.insert((course, target_lexeme), valid_sentences);
+    }
+
+    Ok(Json(GenerateSentenceResponse { sentence }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const COURSE: Course = Course {
+        native_language: Language::English,
+        target_language: Language::French,
+    };
+
+    fn word(word: &str, pos: PartOfSpeech) -> Heteronym<String> {
+        Heteronym {
+            word: word.to_string(),
+            lemma: word.to_string(),
+            pos,
+        }
+    }
+
+    fn key(n: usize) -> (Course, Lexeme<String>) {
+        let lexeme = Lexeme::Heteronym(word(&format!("mot{n}"), PartOfSpeech::Noun));
+        (COURSE, lexeme)
+    }
+
+    fn sentence(text: &str) -> GeneratedSentence {
+        GeneratedSentence {
+            target_language: text.to_string(),
+            native_translations: Vec::new(),
+            literals: Vec::new(),
+            lexemes: Vec::new(),
+        }
+    }
+
+    /// One literal per word, separated by spaces
+    fn literals(words: &[Heteronym<String>]) -> Vec<Literal<String>> {
+        words
+            .iter()
+            .map(|heteronym| Literal {
+                text: heteronym.word.clone(),
+                whitespace: " ".to_string(),
+                heteronym: Some(heteronym.clone()),
+                morph: None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_cache_evicts_the_least_recently_used_lexeme() {
+        let mut cache = SentenceCache::default();
+        for n in 0..MAX_CACHED_LEXEMES {
+            cache.insert(key(n), vec![sentence("phrase")]);
+        }
+        // Using the oldest lexeme makes the second oldest the least recently used
+        assert!(cache.get(&key(0)).is_some());
+
+        cache.insert(key(MAX_CACHED_LEXEMES), vec![sentence("phrase")]);
+        assert_eq!(cache.lexemes.len(), MAX_CACHED_LEXEMES);
+        assert!(cache.get(&key(1)).is_none());
+        assert!(cache.get(&key(0)).is_some());
+        assert!(cache.get(&key(MAX_CACHED_LEXEMES)).is_some());
+    }
+
+    #[test]
+    fn test_cache_keeps_the_first_sentences_for_each_lexeme() {
+        let mut cache = SentenceCache::default();
+        let sentences = |range: std::ops::Range<usize>| {
+            range
+                .map(|n| sentence(&format!("phrase {n}")))
+                .collect::<Vec<_>>()
+        };
+        cache.insert(key(0), sentences(0..10));
+        cache.insert(key(0), sentences(10..20));
+
+        let cached = cache.get(&key(0)).unwrap();
+        assert_eq!(cached.len(), MAX_SENTENCES_PER_LEXEME);
+        assert_eq!(cached[0].target_language, "phrase 0");
+        assert_eq!(
+            cached[MAX_SENTENCES_PER_LEXEME - 1].target_language,
+            format!("phrase {}", MAX_SENTENCES_PER_LEXEME - 1)
+        );
+    }
+
+    #[test]
+    fn test_candidates_must_contain_the_target_lexeme() {
+        let (le, chat, dort) = (
+            word("le", PartOfSpeech::Det),
+            word("chat", PartOfSpeech::Noun),
+            word("dort", PartOfSpeech::Verb),
+        );
+        let known = [&le, &chat, &dort]
+            .into_iter()
+            .map(|heteronym| Lexeme::Heteronym(heteronym.clone()))
+            .collect::<BTreeSet<_>>();
+        let validate = |target: Lexeme<String>, words: &[Heteronym<String>]| {
+            validate_candidate(
+                "le chat dort".to_string(),
+                "the cat sleeps".to_string(),
+                literals(words),
+                &target,
+                &known,
+            )
+        };
+
+        let chien = Lexeme::Heteronym(word("chien", PartOfSpeech::Noun));
+        assert!(validate(chien, &[le.clone(), chat.clone(), dort.clone()]).is_none());
+        // The right word, but used as a different part of speech
+        let chat_verb = Lexeme::Heteronym(word("chat", PartOfSpeech::Verb));
+        assert!(validate(chat_verb, &[le.clone(), chat.clone(), dort.clone()]).is_none());
+        let multiword = Lexeme::Multiword("chat noir".to_string());
+        assert!(validate(multiword, &[le.clone(), chat.clone(), dort.clone()]).is_none());
+
+        let sentence = validate(
+            Lexeme::Heteronym(chat.clone()),
+            &[le.clone(), chat.clone(), dort.clone()],
+        )
+        .unwrap();
+        assert_eq!(sentence.lexemes.len(), 3);
+    }
+}
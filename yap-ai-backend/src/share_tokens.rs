@@ -0,0 +1,344 @@
+//! Share tokens, so users can let someone else (a tutor, a friend keeping them accountable) follow
+//! their progress without logging in as them. Each token has a `ShareScope`, and the `/shared`
+//! routes check it before reading anything: stats-only tokens never see the deck, and only
+//! full-history tokens see the review events themselves. The token is sent as the bearer token,
+//! so it stays out of URLs.
+//!
+//! Deck tokens don't see the deck itself (its snapshots are encrypted), but the `SharedDeck` the
+//! app uploads with `update_shared_deck`.
+//!
+//! Tokens are stored in the `share_tokens` table, and shared decks in `shared_decks`:
+//!
+//! ```sql
+//! create table share_tokens (
+//!     token text primary key,
+//!     user_id uuid not null references auth.users,
+//!     scope text not null,
+//!     label text,
+//!     created_at timestamptz not null default now()
+//! );
+//! create index share_tokens_user on share_tokens (user_id, created_at);
+//!
+//! create table shared_decks (
+//!     user_id uuid primary key references auth.users,
+//!     deck jsonb not null,
+//!     updated_at timestamptz not null default now()
+//! );
+//! ```
+
+use axum::{
+    extract::{Json, Query},
+    http::StatusCode,
+};
+use axum_extra::{
+    TypedHeader,
+    headers::{Authorization, authorization::Bearer},
+};
+use chrono::Utc;
+use language_utils::profile::{
+    CreateShareTokenRequest, RevokeShareTokenRequest, RevokeShareTokenResponse, ShareScope,
+    ShareToken, SharedDeck, SharedEvent, SharedEvents, SharedEventsQuery, UpdateSharedDeckResponse,
+    UserLanguageStats,
+};
+use postgrest::Postgrest;
+use serde::{Deserialize, Serialize};
+
+use crate::supabase::{execute, fetch_rows, supabase_client};
+use crate::verify_jwt;
+
+/// Only the account's own reviews stream is shared, not sub-profiles' or the settings
+const SHARED_STREAM: &str = "reviews";
+/// PostgREST caps how many rows a request returns anyway
+const EVENTS_PAGE_SIZE: usize = 1000;
+const MAX_LABEL_LENGTH: usize = 100;
+
+#[derive(Debug, Serialize, Deserialize)]
+struct ShareTokenRow {
+    token: String,
+    user_id: uuid::Uuid,
+    scope: ShareScope,
+    label: Option<String>,
+    created_at: String,
+}
+
+impl From<ShareTokenRow> for ShareToken {
+    fn from(row: ShareTokenRow) -> Self {
+        ShareToken {
+            token: row.token,
+            scope: row.scope,
+            label: row.label,
+            created_at: row.created_at,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct SharedDeckRow {
+    user_id: uuid::Uuid,
+    deck: SharedDeck,
+    updated_at: String,
+}
+
+/// The `/shared` routes
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum SharedRoute {
+    Stats,
+    Deck,
+    Events,
+}
+
+impl SharedRoute {
+    fn needs(self) -> ShareScope {
+        match self {
+            SharedRoute::Stats => ShareScope::Stats,
+            SharedRoute::Deck => ShareScope::Deck,
+            SharedRoute::Events => ShareScope::FullHistory,
+        }
+    }
+}
+
+/// Whose data `route` may read with the token `row` is for. A token that doesn't exist (or was
+/// revoked) is unauthorized, and one with too narrow a scope is forbidden.
+fn check_scope(row: Option<ShareTokenRow>, route: SharedRoute) -> Result<uuid::Uuid, StatusCode> {
+    let row = row.ok_or(StatusCode::UNAUTHORIZED)?;
+    if row.scope.allows(route.needs()) {
+        Ok(row.user_id)
+    } else {
+        Err(StatusCode::FORBIDDEN)
+    }
+}
+
+/// The user `token` was made by, if it allows `route`
+async fn authorize(
+    client: &Postgrest,
+    token: &str,
+    route: SharedRoute,
+) -> Result<uuid::Uuid, StatusCode> {
+    let row = fetch_rows::<ShareTokenRow>(
+        client.from("share_tokens").select("*").eq("token", token),
+        "share token",
+    )
+    .await?
+    .into_iter()
+    .next();
+    check_scope(row, route)
+}
+
+pub(crate) async fn get_share_tokens(
+    TypedHeader(auth): TypedHeader<Authorization<Bearer>>,
+) -> Result<Json<Vec<ShareToken>>, StatusCode> {
+    let claims = verify_jwt(auth.token()).await?;
+    let rows = fetch_rows::<ShareTokenRow>(
+        supabase_client()?
+            .from("share_tokens")
+            .select("*")
+            .eq("user_id", claims.sub.to_string())
+            .order("created_at.desc"),
+        "share tokens",
+    )
+    .await?;
+    Ok(Json(rows.into_iter().map(ShareToken::from).collect()))
+}
+
+pub(crate) async fn create_share_token(
+    TypedHeader(auth): TypedHeader<Authorization<Bearer>>,
+    Json(request): Json<CreateShareTokenRequest>,
+) -> Result<Json<ShareToken>, StatusCode> {
+    let claims = verify_jwt(auth.token()).await?;
+
+    if request
+        .label
+        .as_ref()
+        .is_some_and(|label| label.chars().count() > MAX_LABEL_LENGTH)
+    {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    let row = ShareTokenRow {
+        // Two uuids, so tokens can't be guessed any more easily than a login
+        token: format!(
+            "{}{}",
+            uuid::Uuid::new_v4().simple(),
+            uuid::Uuid::new_v4().simple()
+        ),
+        user_id: claims.sub,
+        scope: request.scope,
+        label: request.label,
+        created_at: Utc::now().to_rfc3339(),
+    };
+    let body = serde_json::to_string(&row).map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    execute(
+        supabase_client()?.from("share_tokens").insert(body),
+        "inserting share token",
+    )
+    .await?;
+    Ok(Json(row.into()))
+}
+
+pub(crate) async fn revoke_share_token(
+    TypedHeader(auth): TypedHeader<Authorization<Bearer>>,
+    Json(request): Json<RevokeShareTokenRequest>,
+) -> Result<Json<RevokeShareTokenResponse>, StatusCode> {
+    let claims = verify_jwt(auth.token()).await?;
+
+    // Matching on the user too, so users can only revoke their own tokens
+    execute(
+        supabase_client()?
+            .from("share_tokens")
+            .delete()
+            .eq("token", request.token)
+            .eq("user_id", claims.sub.to_string()),
+        "deleting share token",
+    )
+    .await?;
+    Ok(Json(RevokeShareTokenResponse { success: true }))
+}
+
+pub(crate) async fn update_shared_deck(
+    TypedHeader(auth): TypedHeader<Authorization<Bearer>>,
+    Json(deck): Json<SharedDeck>,
+) -> Result<Json<UpdateSharedDeckResponse>, StatusCode> {
+    let claims = verify_jwt(auth.token()).await?;
+    let row = SharedDeckRow {
+        user_id: claims.sub,
+        deck,
+        updated_at: Utc::now().to_rfc3339(),
+    };
+    let body = serde_json::to_string(&row).map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    execute(
+        supabase_client()?.from("shared_decks").upsert(body),
+        "upserting shared deck",
+    )
+    .await?;
+    Ok(Json(UpdateSharedDeckResponse { success: true }))
+}
+
+pub(crate) async fn get_shared_stats(
+    TypedHeader(auth): TypedHeader<Authorization<Bearer>>,
+) -> Result<Json<Vec<UserLanguageStats>>, StatusCode> {
+    let client = supabase_client()?;
+    let user_id = authorize(&client, auth.token(), SharedRoute::Stats).await?;
+    let stats = fetch_rows(
+        client
+            .from("user_language_stats")
+            .select("*")
+            .eq("user_id", user_id.to_string()),
+        "language stats",
+    )
+    .await?;
+    Ok(Json(stats))
+}
+
+pub(crate) async fn get_shared_deck(
+    TypedHeader(auth): TypedHeader<Authorization<Bearer>>,
+) -> Result<Json<SharedDeck>, StatusCode> {
+    let client = supabase_client()?;
+    let user_id = authorize(&client, auth.token(), SharedRoute::Deck).await?;
+
+    fetch_rows::<SharedDeckRow>(
+        client
+            .from("shared_decks")
+            .select("*")
+            .eq("user_id", user_id.to_string()),
+        "shared deck",
+    )
+    .await?
+    .into_iter()
+    .next()
+    .map(|row| Json(row.deck))
+    .ok_or(StatusCode::NOT_FOUND)
+}
+
+pub(crate) async fn get_shared_events(
+    TypedHeader(auth): TypedHeader<Authorization<Bearer>>,
+    Query(query): Query<SharedEventsQuery>,
+) -> Result<Json<SharedEvents>, StatusCode> {
+    let client = supabase_client()?;
+    let user_id = authorize(&client, auth.token(), SharedRoute::Events).await?;
+
+    let events: Vec<SharedEvent> = fetch_rows(
+        client
+            .from("events")
+            .select("id,device_id,within_device_events_index,event,created_at")
+            .eq("user_id", user_id.to_string())
+            .eq("stream_id", SHARED_STREAM)
+            .gt("id", query.after_id.unwrap_or(0).to_string())
+            .order("id.asc")
+            .limit(EVENTS_PAGE_SIZE),
+        "events",
+    )
+    .await?;
+    let next_after_id = if events.len() < EVENTS_PAGE_SIZE {
+        None
+    } else {
+        events.last().map(|event| event.id)
+    };
+    Ok(Json(SharedEvents {
+        events,
+        next_after_id,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn token(scope: ShareScope) -> ShareTokenRow {
+        ShareTokenRow {
+            token: "token".to_string(),
+            user_id: uuid::Uuid::nil(),
+            scope,
+            label: None,
+            created_at: Utc::now().to_rfc3339(),
+        }
+    }
+
+    #[test]
+    fn test_each_scope_allows_the_narrower_ones() {
+        use ShareScope::*;
+        let cases = [
+            (Stats, Stats, true),
+            (Stats, Deck, false),
+            (Stats, FullHistory, false),
+            (Deck, Stats, true),
+            (Deck, Deck, true),
+            (Deck, FullHistory, false),
+            (FullHistory, Stats, true),
+            (FullHistory, Deck, true),
+            (FullHistory, FullHistory, true),
+        ];
+        for (scope, needed, allowed) in cases {
+            assert_eq!(scope.allows(needed), allowed, "{scope:?} for {needed:?}");
+        }
+    }
+
+    #[test]
+    fn test_under_scoped_tokens_are_forbidden() {
+        use SharedRoute::*;
+        let cases = [
+            (ShareScope::Stats, [true, false, false]),
+            (ShareScope::Deck, [true, true, false]),
+            (ShareScope::FullHistory, [true, true, true]),
+        ];
+        for (scope, allowed) in cases {
+            for (route, allowed) in [Stats, Deck, Events].into_iter().zip(allowed) {
+                let expected = if allowed {
+                    Ok(uuid::Uuid::nil())
+                } else {
+                    Err(StatusCode::FORBIDDEN)
+                };
+                assert_eq!(
+                    check_scope(Some(token(scope)), route),
+                    expected,
+                    "{scope:?} on {route:?}"
+                );
+            }
+        }
+
+        for route in [Stats, Deck, Events] {
+            assert_eq!(check_scope(None, route), Err(StatusCode::UNAUTHORIZED));
+        }
+    }
+}
@@ -0,0 +1,98 @@
+//! Hosting for vocabulary lists users share with each other, so they can be passed around as a
+//! short code instead of the whole list.
+//!
+//! Lists are stored in the `shared_lists` table:
+//!
+//! ```sql
+//! create table shared_lists (
+//!     code text primary key,
+//!     created_at timestamptz not null default now(),
+//!     owner_id uuid not null references auth.users,
+//!     list jsonb not null
+//! );
+//! ```
+
+use axum::{
+    extract::{Json, Query},
+    http::StatusCode,
+};
+use axum_extra::{
+    TypedHeader,
+    headers::{Authorization, authorization::Bearer},
+};
+use language_utils::shared_list::{
+    GetSharedListQuery, MAX_SHARED_LIST_LEXEMES, ShareListRequest, ShareListResponse, SharedList,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    supabase::{execute, supabase_client},
+    verify_jwt,
+};
+
+/// Long enough that codes can't be guessed by enumerating them
+const CODE_LENGTH: usize = 12;
+
+#[derive(Debug, Serialize, Deserialize)]
+struct SharedListRow {
+    code: String,
+    owner_id: uuid::Uuid,
+    list: SharedList,
+}
+
+pub(crate) async fn share_list(
+    TypedHeader(auth): TypedHeader<Authorization<Bearer>>,
+    Json(request): Json<ShareListRequest>,
+) -> Result<Json<ShareListResponse>, StatusCode> {
+    let claims = verify_jwt(auth.token()).await?;
+
+    if request.list.lexemes.is_empty() || request.list.lexemes.len() > MAX_SHARED_LIST_LEXEMES {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    let mut code = uuid::Uuid::new_v4().simple().to_string();
+    code.truncate(CODE_LENGTH);
+    let row = SharedListRow {
+        code: code.clone(),
+        owner_id: claims.sub,
+        list: request.list,
+    };
+    let body = serde_json::to_string(&row).map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    execute(
+        supabase_client()?.from("shared_lists").insert(body),
+        "inserting shared list",
+    )
+    .await?;
+    Ok(Json(ShareListResponse { code }))
+}
+
+pub(crate) async fn get_shared_list(
+    Query(params): Query<GetSharedListQuery>,
+) -> Result<Json<SharedList>, StatusCode> {
+    let response = supabase_client()?
+        .from("shared_lists")
+        .select("*")
+        .eq("code", params.code)
+        .single()
+        .execute()
+        .await
+        .map_err(|e| {
+            eprintln!("Error fetching shared list: {e:?}");
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    if response.status().is_success() {
+        let row: SharedListRow = response
+            .json()
+            .await
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+        Ok(Json(row.list))
+    } else if response.status() == 406 {
+        // 406 is what Supabase returns when no rows match
+        Err(StatusCode::NOT_FOUND)
+    } else {
+        eprintln!("Failed to fetch shared list: {:?}", response.text().await);
+        Err(StatusCode::INTERNAL_SERVER_ERROR)
+    }
+}
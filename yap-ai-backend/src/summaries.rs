@@ -0,0 +1,83 @@
+//! Per-user aggregates the backend keeps copies of, like the public progress page (see
+//! `public_stats`). They go stale whenever the user's progress changes, which the app tells us
+//! about by uploading stats (`update_language_stats`) or, after a sync that moved events, by posting
+//! `SummariesDirty`. Either way `invalidate` refreshes them all.
+//!
+//! A big sync can come with several of these in a row, so refreshes are coalesced: a user's
+//! summaries are refreshed `REFRESH_DELAY` after the first notification, and notifications that
+//! arrive while a refresh is waiting are covered by it.
+
+use std::collections::HashSet;
+use std::sync::{LazyLock, Mutex};
+use std::time::Duration;
+
+use axum::{extract::Json, http::StatusCode};
+use axum_extra::{
+    TypedHeader,
+    headers::{Authorization, authorization::Bearer},
+};
+
+use crate::{public_stats, verify_jwt};
+
+const REFRESH_DELAY: Duration = Duration::from_secs(3);
+
+/// Users with a refresh waiting to run
+#[derive(Default)]
+struct Pending(Mutex<HashSet<uuid::Uuid>>);
+
+impl Pending {
+    /// Whether a refresh should be scheduled for the user, i.e. there isn't one waiting already
+    fn schedule(&self, user_id: uuid::Uuid) -> bool {
+        self.0.lock().unwrap().insert(user_id)
+    }
+
+    /// Called when the user's refresh stops waiting and starts running
+    fn start(&self, user_id: uuid::Uuid) {
+        self.0.lock().unwrap().remove(&user_id);
+    }
+}
+
+static PENDING: LazyLock<Pending> = LazyLock::new(Default::default);
+
+/// Refreshes the user's summaries shortly, without holding up the response
+pub(crate) fn invalidate(user_id: uuid::Uuid) {
+    if !PENDING.schedule(user_id) {
+        return;
+    }
+    tokio::spawn(async move {
+        tokio::time::sleep(REFRESH_DELAY).await;
+        // Removed before refreshing, so a notification that arrives during the refresh gets one
+        // of its own
+        PENDING.start(user_id);
+        public_stats::refresh(user_id);
+    });
+}
+
+pub(crate) async fn summaries_dirty(
+    TypedHeader(auth): TypedHeader<Authorization<Bearer>>,
+) -> Result<Json<()>, StatusCode> {
+    let claims = verify_jwt(auth.token()).await?;
+    invalidate(claims.sub);
+    Ok(Json(()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_notifications_are_coalesced_until_the_refresh_starts() {
+        let pending = Pending::default();
+        let alice = uuid::Uuid::from_u128(1);
+        let bob = uuid::Uuid::from_u128(2);
+
+        assert!(pending.schedule(alice));
+        assert!(!pending.schedule(alice));
+        // Other users aren't held up
+        assert!(pending.schedule(bob));
+
+        pending.start(alice);
+        assert!(pending.schedule(alice));
+        assert!(!pending.schedule(bob));
+    }
+}
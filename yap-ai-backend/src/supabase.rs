@@ -0,0 +1,53 @@
+//! The Supabase client the route modules share, and wrappers around running a query on it that
+//! log what went wrong before turning it into a status code.
+
+use axum::http::StatusCode;
+use postgrest::{Builder, Postgrest};
+use serde::de::DeserializeOwned;
+
+/// A client with the service role key, so row level security doesn't apply to it
+pub(crate) fn supabase_client() -> Result<Postgrest, StatusCode> {
+    let supabase_url =
+        std::env::var("SUPABASE_URL").map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let service_role_key = std::env::var("SUPABASE_SERVICE_ROLE_KEY")
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    Ok(Postgrest::new(format!("{supabase_url}/rest/v1"))
+        .insert_header("apikey", service_role_key.clone())
+        .insert_header("Authorization", format!("Bearer {service_role_key}")))
+}
+
+/// The rows `query` selects. `what` names them in the log.
+pub(crate) async fn fetch_rows<T: DeserializeOwned>(
+    query: Builder,
+    what: &str,
+) -> Result<Vec<T>, StatusCode> {
+    let response = query.execute().await.map_err(|e| {
+        eprintln!("Error fetching {what}: {e:?}");
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    if response.status().is_success() {
+        response.json().await.map_err(|e| {
+            eprintln!("Error parsing {what}: {e:?}");
+            StatusCode::INTERNAL_SERVER_ERROR
+        })
+    } else {
+        eprintln!("Failed to fetch {what}: {:?}", response.text().await);
+        Err(StatusCode::INTERNAL_SERVER_ERROR)
+    }
+}
+
+/// Runs an insert, upsert or delete. `doing` says what it does in the log, like "saving webhook".
+pub(crate) async fn execute(query: Builder, doing: &str) -> Result<(), StatusCode> {
+    let response = query.execute().await.map_err(|e| {
+        eprintln!("Error {doing}: {e:?}");
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    if response.status().is_success() {
+        Ok(())
+    } else {
+        eprintln!("Failed {doing}: {:?}", response.text().await);
+        Err(StatusCode::INTERNAL_SERVER_ERROR)
+    }
+}
@@ -0,0 +1,383 @@
+//! Token, cost and latency accounting for the LLM-backed endpoints.
+//!
+//! Every call is recorded in the `ai_usage` table:
+//!
+//! ```sql
+//! create table ai_usage (
+//!     id bigint generated always as identity primary key,
+//!     created_at timestamptz not null default now(),
+//!     endpoint text not null,
+//!     model text not null,
+//!     user_id uuid references auth.users,
+//!     prompt_tokens bigint not null,
+//!     completion_tokens bigint not null,
+//!     cost_usd double precision not null,
+//!     latency_ms bigint not null,
+//!     success boolean not null
+//! );
+//! create index ai_usage_created_at on ai_usage (created_at);
+//! ```
+
+use std::{
+    collections::BTreeMap,
+    sync::atomic::{AtomicU64, Ordering},
+    time::Instant,
+};
+
+use axum::{extract::Json, http::StatusCode};
+use axum_extra::{
+    TypedHeader,
+    headers::{Authorization, authorization::Bearer},
+};
+use chrono::{Datelike, TimeZone, Utc};
+use postgrest::Postgrest;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    supabase::{execute, fetch_rows, supabase_client},
+    verify_jwt,
+};
+
+/// The model behind all of our chat clients. Keep the prices below in sync with it.
+pub(crate) const MODEL: &str = "gpt-5.1";
+const INPUT_USD_PER_MILLION_TOKENS: f64 = 1.25;
+const OUTPUT_USD_PER_MILLION_TOKENS: f64 = 10.0;
+
+/// The chat client doesn't report token counts per call, so we estimate them from the length of
+/// the text (roughly 4 bytes per token). Reasoning tokens are invisible to us, so treat the
+/// completion count (and therefore the cost) as a lower bound.
+fn estimate_tokens(bytes: usize) -> u64 {
+    bytes.div_ceil(4) as u64
+}
+
+fn cost_usd(prompt_tokens: u64, completion_tokens: u64) -> f64 {
+    (prompt_tokens as f64 * INPUT_USD_PER_MILLION_TOKENS
+        + completion_tokens as f64 * OUTPUT_USD_PER_MILLION_TOKENS)
+        / 1_000_000.0
+}
+
+/// Calls `LlmCall::finish` couldn't record since the server started, so the summary can say how
+/// much it's missing
+static UNRECORDED_CALLS: AtomicU64 = AtomicU64::new(0);
+
+#[derive(Debug, Serialize, Deserialize)]
+struct UsageRow {
+    endpoint: String,
+    model: String,
+    user_id: Option<uuid::Uuid>,
+    prompt_tokens: u64,
+    completion_tokens: u64,
+    cost_usd: f64,
+    latency_ms: u64,
+    success: bool,
+}
+
+/// An in-flight LLM call. Create it right before calling the model and `finish` it right after.
+pub(crate) struct LlmCall {
+    endpoint: &'static str,
+    user_id: Option<uuid::Uuid>,
+    prompt_tokens: u64,
+    started: Instant,
+}
+
+impl LlmCall {
+    pub(crate) fn start(
+        endpoint: &'static str,
+        user_id: Option<uuid::Uuid>,
+        system_prompt: &str,
+        prompt: &str,
+    ) -> Self {
+        Self {
+            endpoint,
+            user_id,
+            prompt_tokens: estimate_tokens(system_prompt.len() + prompt.len()),
+            started: Instant::now(),
+        }
+    }
+
+    /// Records the call in the background, so it doesn't add to the request's latency.
+    /// `response` is what the model returned, or `None` if the call failed.
+    pub(crate) fn finish<T: Serialize>(self, response: Option<&T>) {
+        let latency_ms = self.started.elapsed().as_millis() as u64;
+        let completion_tokens = response
+            .and_then(|response| serde_json::to_string(response).ok())
+            .map(|json| estimate_tokens(json.len()))
+            .unwrap_or(0);
+        let row = UsageRow {
+            endpoint: self.endpoint.to_string(),
+            model: MODEL.to_string(),
+            user_id: self.user_id,
+            prompt_tokens: self.prompt_tokens,
+            completion_tokens,
+            cost_usd: cost_usd(self.prompt_tokens, completion_tokens),
+            latency_ms,
+            success: response.is_some(),
+        };
+
+        tokio::spawn(async move {
+            let recorded = match (supabase_client(), serde_json::to_string(&row)) {
+                // Logged by `execute`, and the call it's recording already went through
+                (Ok(client), Ok(body)) => {
+                    execute(client.from("ai_usage").insert(body), "recording AI usage")
+                        .await
+                        .is_ok()
+                }
+                _ => false,
+            };
+            if !recorded {
+                UNRECORDED_CALLS.fetch_add(1, Ordering::Relaxed);
+            }
+        });
+    }
+}
+
+/// Token counts and costs are estimated from the length of the text (see `estimate_tokens`), and
+/// are named as such in the response
+#[derive(Debug, Clone, Default, Serialize)]
+pub(crate) struct EndpointUsage {
+    endpoint: String,
+    requests: u64,
+    failures: u64,
+    estimated_prompt_tokens: u64,
+    estimated_completion_tokens: u64,
+    estimated_cost_usd: f64,
+    average_latency_ms: u64,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub(crate) struct UserUsage {
+    user_id: Option<uuid::Uuid>,
+    requests: u64,
+    /// See `EndpointUsage`
+    estimated_cost_usd: f64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub(crate) struct UsageSummary {
+    since: String,
+    endpoints: Vec<EndpointUsage>,
+    /// The most expensive users this month, most expensive first
+    top_users: Vec<UserUsage>,
+    /// Calls this server instance failed to record since it started, which are missing from the
+    /// numbers above
+    unrecorded_calls: u64,
+}
+
+const TOP_USERS: usize = 20;
+
+fn start_of_month() -> chrono::DateTime<Utc> {
+    let now = Utc::now();
+    Utc.with_ymd_and_hms(now.year(), now.month(), 1, 0, 0, 0)
+        .single()
+        .unwrap_or(now)
+}
+
+/// PostgREST caps how many rows a request returns, so a month's rows are fetched in pages
+const PAGE_SIZE: usize = 1000;
+
+async fn fetch_rows_since(
+    client: &Postgrest,
+    since: &chrono::DateTime<Utc>,
+    user_id: Option<uuid::Uuid>,
+) -> Result<Vec<UsageRow>, StatusCode> {
+    let mut rows = Vec::new();
+    loop {
+        let mut query = client
+            .from("ai_usage")
+            .select(
+                "endpoint,model,user_id,prompt_tokens,completion_tokens,cost_usd,latency_ms,success",
+            )
+            .gte("created_at", since.to_rfc3339());
+        if let Some(user_id) = user_id {
+            query = query.eq("user_id", user_id.to_string());
+        }
+
+        let page: Vec<UsageRow> = fetch_rows(
+            query
+                .order("id.asc")
+                .range(rows.len(), rows.len() + PAGE_SIZE - 1),
+            "AI usage",
+        )
+        .await?;
+        let page_len = page.len();
+        rows.extend(page);
+        if page_len < PAGE_SIZE {
+            return Ok(rows);
+        }
+    }
+}
+
+/// User ids allowed to see everyone's usage, from the comma-separated `USAGE_ADMIN_USER_IDS`
+fn is_admin(user_id: uuid::Uuid) -> bool {
+    std::env::var("USAGE_ADMIN_USER_IDS")
+        .map(|ids| {
+            ids.split(',')
+                .any(|id| id.trim().parse::<uuid::Uuid>().ok() == Some(user_id))
+        })
+        .unwrap_or(false)
+}
+
+/// Totals `rows` by endpoint, with the average latency of each, and the `TOP_USERS` most expensive
+/// users
+fn summarize(rows: Vec<UsageRow>) -> (Vec<EndpointUsage>, Vec<UserUsage>) {
+    let mut endpoints: BTreeMap<String, EndpointUsage> = BTreeMap::new();
+    let mut users: BTreeMap<Option<uuid::Uuid>, UserUsage> = BTreeMap::new();
+    let mut total_latency_ms: BTreeMap<String, u64> = BTreeMap::new();
+    for row in rows {
+        let endpoint = endpoints
+            .entry(row.endpoint.clone())
+            .or_insert_with(|| EndpointUsage {
+                endpoint: row.endpoint.clone(),
+                ..Default::default()
+            });
+        endpoint.requests += 1;
+        endpoint.failures += u64::from(!row.success);
+        endpoint.estimated_prompt_tokens += row.prompt_tokens;
+        endpoint.estimated_completion_tokens += row.completion_tokens;
+        endpoint.estimated_cost_usd += row.cost_usd;
+        *total_latency_ms.entry(row.endpoint).or_default() += row.latency_ms;
+
+        let user = users.entry(row.user_id).or_insert_with(|| UserUsage {
+            user_id: row.user_id,
+            ..Default::default()
+        });
+        user.requests += 1;
+        user.estimated_cost_usd += row.cost_usd;
+    }
+
+    let endpoints = endpoints
+        .into_values()
+        .map(|mut endpoint| {
+            endpoint.average_latency_ms =
+                total_latency_ms[&endpoint.endpoint] / endpoint.requests.max(1);
+            endpoint
+        })
+        .collect();
+
+    let mut top_users: Vec<UserUsage> = users.into_values().collect();
+    top_users.sort_by(|a, b| b.estimated_cost_usd.total_cmp(&a.estimated_cost_usd));
+    top_users.truncate(TOP_USERS);
+
+    (endpoints, top_users)
+}
+
+/// This month's usage broken down by endpoint, plus the most expensive users
+pub(crate) async fn usage_summary(
+    TypedHeader(auth): TypedHeader<Authorization<Bearer>>,
+) -> Result<Json<UsageSummary>, StatusCode> {
+    let claims = verify_jwt(auth.token()).await?;
+    if !is_admin(claims.sub) {
+        return Err(StatusCode::FORBIDDEN);
+    }
+
+    let client = supabase_client()?;
+    let since = start_of_month();
+    let rows = fetch_rows_since(&client, &since, None).await?;
+
+    let (endpoints, top_users) = summarize(rows);
+
+    Ok(Json(UsageSummary {
+        since: since.to_rfc3339(),
+        endpoints,
+        top_users,
+        unrecorded_calls: UNRECORDED_CALLS.load(Ordering::Relaxed),
+    }))
+}
+
+/// The calling user's usage this month
+pub(crate) async fn my_usage(
+    TypedHeader(auth): TypedHeader<Authorization<Bearer>>,
+) -> Result<Json<UserUsage>, StatusCode> {
+    let claims = verify_jwt(auth.token()).await?;
+
+    let client = supabase_client()?;
+    let rows = fetch_rows_since(&client, &start_of_month(), Some(claims.sub)).await?;
+
+    Ok(Json(UserUsage {
+        user_id: Some(claims.sub),
+        requests: rows.len() as u64,
+        estimated_cost_usd: rows.iter().map(|row| row.cost_usd).sum(),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn row(endpoint: &str, user: u128, cost_usd: f64, latency_ms: u64, success: bool) -> UsageRow {
+        UsageRow {
+            endpoint: endpoint.to_string(),
+            model: MODEL.to_string(),
+            user_id: Some(uuid::Uuid::from_u128(user)),
+            prompt_tokens: 100,
+            completion_tokens: 10,
+            cost_usd,
+            latency_ms,
+            success,
+        }
+    }
+
+    #[test]
+    fn test_tokens_are_estimated_at_four_bytes_each_rounding_up() {
+        assert_eq!(estimate_tokens(0), 0);
+        assert_eq!(estimate_tokens(1), 1);
+        assert_eq!(estimate_tokens(4), 1);
+        assert_eq!(estimate_tokens(5), 2);
+    }
+
+    #[test]
+    fn test_cost_is_priced_per_million_tokens() {
+        assert_eq!(cost_usd(0, 0), 0.0);
+        assert_eq!(cost_usd(1_000_000, 0), INPUT_USD_PER_MILLION_TOKENS);
+        assert_eq!(cost_usd(0, 1_000_000), OUTPUT_USD_PER_MILLION_TOKENS);
+        assert_eq!(
+            cost_usd(2_000_000, 500_000),
+            2.0 * INPUT_USD_PER_MILLION_TOKENS + 0.5 * OUTPUT_USD_PER_MILLION_TOKENS
+        );
+    }
+
+    #[test]
+    fn test_summary_totals_each_endpoint_and_ranks_users_by_cost() {
+        let (endpoints, top_users) = summarize(vec![
+            row("autograde-translation", 1, 0.5, 100, true),
+            row("autograde-translation", 2, 0.25, 300, false),
+            row("generate-sentence", 2, 1.0, 1000, true),
+        ]);
+
+        assert_eq!(endpoints.len(), 2);
+        let translation = &endpoints[0];
+        assert_eq!(translation.endpoint, "autograde-translation");
+        assert_eq!(translation.requests, 2);
+        assert_eq!(translation.failures, 1);
+        assert_eq!(translation.estimated_prompt_tokens, 200);
+        assert_eq!(translation.estimated_completion_tokens, 20);
+        assert_eq!(translation.estimated_cost_usd, 0.75);
+        assert_eq!(translation.average_latency_ms, 200);
+        assert_eq!(endpoints[1].average_latency_ms, 1000);
+
+        let users = top_users
+            .iter()
+            .map(|user| (user.user_id, user.requests, user.estimated_cost_usd))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            users,
+            vec![
+                (Some(uuid::Uuid::from_u128(2)), 2, 1.25),
+                (Some(uuid::Uuid::from_u128(1)), 1, 0.5),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_summary_keeps_only_the_top_users() {
+        let rows = (0..TOP_USERS as u128 + 5)
+            .map(|user| row("generate-sentence", user, user as f64, 10, true))
+            .collect();
+        let (_, top_users) = summarize(rows);
+        assert_eq!(top_users.len(), TOP_USERS);
+        assert_eq!(
+            top_users[0].user_id,
+            Some(uuid::Uuid::from_u128(TOP_USERS as u128 + 4))
+        );
+    }
+}
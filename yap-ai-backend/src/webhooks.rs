@@ -0,0 +1,319 @@
+//! Webhooks for users' own automations, see `language_utils::webhook`. Milestones are checked
+//! against the stats the app uploads during sync (`update_language_stats`), so nothing is
+//! delivered for a user who isn't syncing.
+//!
+//! Webhooks are stored in the `user_webhooks` table:
+//!
+//! ```sql
+//! create table user_webhooks (
+//!     user_id uuid primary key references auth.users,
+//!     url text not null,
+//!     events jsonb not null,
+//!     due_threshold bigint not null default 100,
+//!     secret text not null,
+//!     -- When each event was last delivered for each language, see `cooldown_key`
+//!     last_sent jsonb not null default '{}'
+//! );
+//! ```
+
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+use axum::{extract::Json, http::StatusCode};
+use axum_extra::{
+    TypedHeader,
+    headers::{Authorization, authorization::Bearer},
+};
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use language_utils::Language;
+use language_utils::profile::UpdateLanguageStatsRequest;
+use language_utils::webhook::{
+    DEFAULT_DUE_THRESHOLD, SIGNATURE_HEADER, SetWebhookRequest, Webhook, WebhookEvent,
+    WebhookPayload,
+};
+use postgrest::Postgrest;
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+
+use crate::{
+    supabase::{execute, fetch_rows, supabase_client},
+    verify_jwt,
+};
+
+/// How long before the streak expires `StreakAtRisk` is sent
+const STREAK_AT_RISK_HOURS: i64 = 6;
+/// Attempts per delivery, including the first
+const MAX_ATTEMPTS: u32 = 4;
+/// Doubled after every failed attempt
+const FIRST_RETRY_DELAY: Duration = Duration::from_secs(2);
+const DELIVERY_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Serialize, Deserialize)]
+struct WebhookRow {
+    user_id: uuid::Uuid,
+    url: String,
+    events: Vec<WebhookEvent>,
+    due_threshold: i64,
+    secret: String,
+    #[serde(default)]
+    last_sent: BTreeMap<String, DateTime<Utc>>,
+}
+
+impl WebhookRow {
+    fn webhook(&self) -> Webhook {
+        Webhook {
+            url: self.url.clone(),
+            events: self.events.clone(),
+            due_threshold: self.due_threshold,
+            secret: self.secret.clone(),
+        }
+    }
+}
+
+fn new_secret() -> String {
+    format!(
+        "{}{}",
+        uuid::Uuid::new_v4().simple(),
+        uuid::Uuid::new_v4().simple()
+    )
+}
+
+async fn fetch_row(
+    client: &Postgrest,
+    user_id: uuid::Uuid,
+) -> Result<Option<WebhookRow>, StatusCode> {
+    let rows: Vec<WebhookRow> = fetch_rows(
+        client
+            .from("user_webhooks")
+            .select("*")
+            .eq("user_id", user_id.to_string()),
+        "webhook",
+    )
+    .await?;
+    Ok(rows.into_iter().next())
+}
+
+async fn upsert_row(client: &Postgrest, row: &WebhookRow) -> Result<(), StatusCode> {
+    let body = serde_json::to_string(row).map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    execute(
+        client
+            .from("user_webhooks")
+            .upsert(body)
+            .on_conflict("user_id"),
+        "saving webhook",
+    )
+    .await
+}
+
+pub(crate) async fn get_webhook(
+    TypedHeader(auth): TypedHeader<Authorization<Bearer>>,
+) -> Result<Json<Option<Webhook>>, StatusCode> {
+    let claims = verify_jwt(auth.token()).await?;
+    let row = fetch_row(&supabase_client()?, claims.sub).await?;
+    Ok(Json(row.as_ref().map(WebhookRow::webhook)))
+}
+
+pub(crate) async fn set_webhook(
+    TypedHeader(auth): TypedHeader<Authorization<Bearer>>,
+    Json(request): Json<SetWebhookRequest>,
+) -> Result<Json<Option<Webhook>>, StatusCode> {
+    let claims = verify_jwt(auth.token()).await?;
+    let client = supabase_client()?;
+
+    let Some(url) = request.url else {
+        execute(
+            client
+                .from("user_webhooks")
+                .delete()
+                .eq("user_id", claims.sub.to_string()),
+            "deleting webhook",
+        )
+        .await?;
+        return Ok(Json(None));
+    };
+
+    // Plain http would send the payloads (and let anyone change them) in the clear
+    if !url.starts_with("https://") || reqwest::Url::parse(&url).is_err() {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    let due_threshold = request.due_threshold.unwrap_or(DEFAULT_DUE_THRESHOLD);
+    if due_threshold < 1 {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    let existing = fetch_row(&client, claims.sub).await?;
+    let row = WebhookRow {
+        user_id: claims.sub,
+        url,
+        events: request.events,
+        due_threshold,
+        secret: match &existing {
+            Some(existing) if !request.rotate_secret => existing.secret.clone(),
+            _ => new_secret(),
+        },
+        last_sent: existing
+            .map(|existing| existing.last_sent)
+            .unwrap_or_default(),
+    };
+    upsert_row(&client, &row).await?;
+    Ok(Json(Some(row.webhook())))
+}
+
+/// How long after an event is delivered for a language it can't be delivered for it again
+fn cooldown(event: WebhookEvent) -> chrono::Duration {
+    match event {
+        // Longer than the warning window, so one streak only warns once
+        WebhookEvent::StreakAtRisk => chrono::Duration::hours(STREAK_AT_RISK_HOURS * 2),
+        WebhookEvent::CardsDue => chrono::Duration::days(1),
+        WebhookEvent::WeeklySummary => chrono::Duration::days(7),
+    }
+}
+
+fn cooldown_key(event: WebhookEvent, language: Language) -> String {
+    let event = match event {
+        WebhookEvent::StreakAtRisk => "streak_at_risk",
+        WebhookEvent::CardsDue => "cards_due",
+        WebhookEvent::WeeklySummary => "weekly_summary",
+    };
+    format!("{event}:{}", language.iso_639_3())
+}
+
+/// The events in `row` that `stats` reached and that are off cooldown
+fn triggered_events(
+    row: &WebhookRow,
+    stats: &UpdateLanguageStatsRequest,
+    now: DateTime<Utc>,
+) -> Vec<WebhookEvent> {
+    row.events
+        .iter()
+        .copied()
+        .filter(|event| match event {
+            WebhookEvent::StreakAtRisk => {
+                stats.daily_streak > 0
+                    && stats
+                        .daily_streak_expiry
+                        .as_deref()
+                        .and_then(|expiry| DateTime::parse_from_rfc3339(expiry).ok())
+                        .is_some_and(|expiry| {
+                            let left = expiry.with_timezone(&Utc) - now;
+                            left > chrono::Duration::zero()
+                                && left <= chrono::Duration::hours(STREAK_AT_RISK_HOURS)
+                        })
+            }
+            WebhookEvent::CardsDue => stats
+                .due_count
+                .is_some_and(|due_count| due_count >= row.due_threshold),
+            WebhookEvent::WeeklySummary => true,
+        })
+        .filter(|event| {
+            row.last_sent
+                .get(&cooldown_key(*event, stats.language))
+                .is_none_or(|last_sent| now - *last_sent >= cooldown(*event))
+        })
+        .collect()
+}
+
+/// The value of `SIGNATURE_HEADER` for `body` sent at `timestamp`
+fn signature(secret: &str, timestamp: i64, body: &str) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any length");
+    mac.update(format!("{timestamp}.{body}").as_bytes());
+    let hex = mac
+        .finalize()
+        .into_bytes()
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect::<String>();
+    format!("t={timestamp},v1={hex}")
+}
+
+/// Posts `payload` to `url`, retrying with backoff on network errors, 429s and 5xxs. Each attempt
+/// is signed again, so a receiver can reject old timestamps.
+async fn deliver(url: &str, secret: &str, payload: &WebhookPayload) -> Result<(), String> {
+    let body = serde_json::to_string(payload).map_err(|e| e.to_string())?;
+    let client = reqwest::Client::builder()
+        .timeout(DELIVERY_TIMEOUT)
+        .build()
+        .map_err(|e| e.to_string())?;
+
+    let mut delay = FIRST_RETRY_DELAY;
+    let mut last_error = String::new();
+    for attempt in 1..=MAX_ATTEMPTS {
+        let response = client
+            .post(url)
+            .header("Content-Type", "application/json")
+            .header(
+                SIGNATURE_HEADER,
+                signature(secret, Utc::now().timestamp(), &body),
+            )
+            .body(body.clone())
+            .send()
+            .await;
+        match response {
+            Ok(response) if response.status().is_success() => return Ok(()),
+            Ok(response)
+                if response.status().is_client_error()
+                    && response.status() != reqwest::StatusCode::TOO_MANY_REQUESTS =>
+            {
+                return Err(format!(
+                    "{url} rejected the delivery: {}",
+                    response.status()
+                ));
+            }
+            Ok(response) => last_error = format!("{url} responded with {}", response.status()),
+            Err(e) => last_error = format!("Error posting to {url}: {e}"),
+        }
+        if attempt < MAX_ATTEMPTS {
+            tokio::time::sleep(delay).await;
+            delay *= 2;
+        }
+    }
+    Err(last_error)
+}
+
+/// Delivers the events `stats` triggers for `user_id`'s webhook, if they have one. Runs in the
+/// background, so a slow or failing endpoint never holds up sync.
+pub(crate) fn dispatch(user_id: uuid::Uuid, stats: UpdateLanguageStatsRequest) {
+    tokio::spawn(async move {
+        let Ok(client) = supabase_client() else {
+            return;
+        };
+        let Ok(Some(mut row)) = fetch_row(&client, user_id).await else {
+            return;
+        };
+        let now = Utc::now();
+        let events = triggered_events(&row, &stats, now);
+        if events.is_empty() {
+            return;
+        }
+
+        // Recorded before delivering, so syncs from two devices at once don't both deliver
+        for event in &events {
+            row.last_sent
+                .insert(cooldown_key(*event, stats.language), now);
+        }
+        if upsert_row(&client, &row).await.is_err() {
+            return;
+        }
+
+        for event in events {
+            let payload = WebhookPayload {
+                event,
+                id: uuid::Uuid::new_v4().to_string(),
+                user_id: user_id.to_string(),
+                language: stats.language,
+                sent_at: now.to_rfc3339(),
+                daily_streak: stats.daily_streak,
+                daily_streak_expiry: stats.daily_streak_expiry.clone(),
+                due_count: stats.due_count,
+                total_count: stats.total_count,
+                xp: stats.xp,
+                percent_known: stats.percent_known,
+            };
+            if let Err(e) = deliver(&row.url, &row.secret, &payload).await {
+                eprintln!("Failed to deliver {event:?} webhook: {e}");
+            }
+        }
+    });
+}
@@ -0,0 +1,238 @@
+//! The interactive review loop. Only challenges that work in a terminal are supported; the rest
+//! are banned when building the review info.
+
+use std::io::{self, BufRead as _, Write as _};
+
+use anyhow::Result;
+use language_utils::text_cleanup::normalize_for_grading;
+use yap_core::{
+    CardContent, Challenge, DisambiguateHeteronym, Rating, TranslateComprehensibleSentence,
+};
+
+use crate::{BANNED_CHALLENGE_TYPES, Client, now_ms};
+
+/// With `ahead_hours`, cards due within that many hours are reviewed once nothing is due
+pub(crate) async fn study(
+    client: &Client,
+    limit: Option<usize>,
+    ahead_hours: Option<f64>,
+) -> Result<()> {
+    let mut completed = 0;
+    while limit.is_none_or(|limit| completed < limit) {
+        let deck = client.deck();
+        let banned = BANNED_CHALLENGE_TYPES.to_vec();
+        let review_info = match ahead_hours {
+            Some(window_hours) => deck.get_review_info_ahead(banned, now_ms(), window_hours),
+            None => deck.get_review_info(banned, now_ms()),
+        };
+        let Some(challenge) = review_info.get_next_challenge(&deck) else {
+            println!("Nothing due. Add more words with `yap-cli add`.");
+            break;
+        };
+        println!();
+        if review_info.due_count() == 0 {
+            println!("[reviewing ahead, {} left]", review_info.ahead_count());
+        } else {
+            println!("[{} due]", review_info.due_count());
+        }
+
+        let event = match challenge {
+            Challenge::FlashCardReview {
+                indicator, content, ..
+            } => {
+                match &content {
+                    CardContent::Heteronym { heteronym, .. } => println!("{}", heteronym.word),
+                    CardContent::Multiword(phrase, _) => println!("{phrase}"),
+                    CardContent::Listening { .. } | CardContent::LetterPronunciation { .. } => {
+                        println!("Pronunciation cards need audio, which this client can't play.");
+                        break;
+                    }
+                }
+                let Some(_) = prompt("(press enter to reveal) ")? else {
+                    break;
+                };
+                match &content {
+                    CardContent::Heteronym { definitions, .. } => {
+                        for definition in definitions {
+                            match &definition.note {
+                                Some(note) => println!("  {} ({note})", definition.native),
+                                None => println!("  {}", definition.native),
+                            }
+                            println!(
+                                "    {} / {}",
+                                definition.example_sentence_target_language,
+                                definition.example_sentence_native_language
+                            );
+                        }
+                    }
+                    CardContent::Multiword(_, content) => {
+                        println!("  {}", content.meaning);
+                        println!(
+                            "    {} / {}",
+                            content.example_sentence_target_language,
+                            content.example_sentence_native_language
+                        );
+                    }
+                    CardContent::Listening { .. } | CardContent::LetterPronunciation { .. } => {
+                        unreachable!("pronunciation cards are handled above")
+                    }
+                }
+                let rating = loop {
+                    match prompt("1) forgot  2) remembered: ")?.as_deref() {
+                        Some("1") => break Rating::Again,
+                        Some("2") => break Rating::Remembered,
+                        Some(_) => continue,
+                        None => return Ok(()),
+                    }
+                };
+                deck.review_card(indicator, rating)
+            }
+            Challenge::TranslateComprehensibleSentence(challenge) => {
+                let Some(event) = translate_sentence(client, &deck, challenge)? else {
+                    break;
+                };
+                event
+            }
+            Challenge::TranscribeComprehensibleSentence(_) => {
+                println!("Transcription challenges need audio, which this client can't play.");
+                break;
+            }
+            Challenge::DisambiguateHeteronym(challenge) => {
+                let Some(event) = disambiguate_heteronym(&deck, challenge)? else {
+                    break;
+                };
+                event
+            }
+        };
+
+        if let Some(event) = event {
+            client.add_event(event).await?;
+        }
+        completed += 1;
+    }
+    println!("Done: {completed} challenges.");
+    Ok(())
+}
+
+/// Asks for a translation and grades it. Returns `None` if the user quit.
+fn translate_sentence(
+    client: &Client,
+    deck: &yap_core::Deck,
+    challenge: TranslateComprehensibleSentence<String>,
+) -> Result<Option<Option<yap_core::DeckEvent>>> {
+    println!("Translate: {}", challenge.target_language);
+    let Some(submission) = prompt("> ")? else {
+        return Ok(None);
+    };
+
+    let native_language = client.course.native_language;
+    let spelling_variants = client.language_pack.spelling_variants(native_language);
+    let normalized_submission =
+        normalize_for_grading(&submission, native_language, spelling_variants);
+    let perfect = challenge.native_translations.iter().any(|translation| {
+        normalize_for_grading(translation, native_language, spelling_variants)
+            == normalized_submission
+    });
+
+    let correct = if perfect {
+        println!("Correct!");
+        true
+    } else {
+        for translation in &challenge.native_translations {
+            println!("  {translation}");
+        }
+        match prompt("Count it as correct? (y/N) ")? {
+            Some(answer) => answer.eq_ignore_ascii_case("y"),
+            None => return Ok(None),
+        }
+    };
+
+    let event = if correct {
+        deck.translate_sentence_perfect(
+            vec![],
+            challenge.target_language,
+            Some(challenge.favorite_practice),
+        )
+    } else {
+        let words_remembered = challenge
+            .unique_target_language_lexemes
+            .iter()
+            .filter(|lexeme| **lexeme != challenge.primary_expression)
+            .cloned()
+            .collect();
+        deck.translate_sentence_wrong(
+            challenge.target_language,
+            submission,
+            words_remembered,
+            vec![challenge.primary_expression],
+            vec![],
+            Some(challenge.favorite_practice),
+        )
+    };
+    Ok(Some(event))
+}
+
+/// Asks which of the heteronyms spelled like the marked word it is. Returns `None` if the user quit.
+fn disambiguate_heteronym(
+    deck: &yap_core::Deck,
+    challenge: DisambiguateHeteronym<String>,
+) -> Result<Option<Option<yap_core::DeckEvent>>> {
+    let sentence = challenge
+        .target_language_literals
+        .iter()
+        .enumerate()
+        .map(|(i, literal)| {
+            if i == challenge.literal_index {
+                format!("[{}]{}", literal.text, literal.whitespace)
+            } else {
+                format!("{}{}", literal.text, literal.whitespace)
+            }
+        })
+        .collect::<String>();
+    println!("Which word is this? {sentence}");
+    for (i, (heteronym, definitions)) in challenge.options.iter().enumerate() {
+        let meanings = definitions
+            .iter()
+            .map(|definition| definition.native.as_str())
+            .collect::<Vec<_>>()
+            .join("; ");
+        println!(
+            "  {}) {} ({:?}): {meanings}",
+            i + 1,
+            heteronym.lemma,
+            heteronym.pos
+        );
+    }
+    let picked = loop {
+        let Some(answer) = prompt("> ")? else {
+            return Ok(None);
+        };
+        if let Some((picked, _)) = answer
+            .parse::<usize>()
+            .ok()
+            .and_then(|n| challenge.options.get(n.checked_sub(1)?))
+        {
+            break picked.clone();
+        }
+    };
+    if picked == challenge.answer {
+        println!("Correct!");
+    } else {
+        println!(
+            "It's {} ({:?}).",
+            challenge.answer.lemma, challenge.answer.pos
+        );
+    }
+    Ok(Some(deck.disambiguate_heteronym(challenge.answer, picked)))
+}
+
+/// Reads a trimmed line from stdin, or `None` at end of input
+fn prompt(message: &str) -> Result<Option<String>> {
+    print!("{message}");
+    io::stdout().flush()?;
+    let mut line = String::new();
+    if io::stdin().lock().read_line(&mut line)? == 0 {
+        return Ok(None);
+    }
+    Ok(Some(line.trim().to_string()))
+}
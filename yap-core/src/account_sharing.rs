@@ -0,0 +1,360 @@
+//! Spotting accounts that two people take turns on, which mixes their reviews into one memory
+//! model and schedules cards for neither of them. Sessions are tracked across every course on the
+//! account (each deck sees the events of the others), and the hint only shows when the account
+//! is used at two separate times of day that look like different people: a different course at
+//! each, or very different accuracy. Sub-profiles (see `sub_profiles`) are the fix to suggest.
+
+use std::collections::VecDeque;
+
+use chrono::{DateTime, Timelike as _, Utc};
+use language_utils::Language;
+use serde::{Deserialize, Serialize};
+#[cfg(target_arch = "wasm32")]
+use wasm_bindgen::prelude::*;
+
+use crate::fatigue::SESSION_GAP_MINUTES;
+use crate::{Deck, datetime_from_ms};
+
+/// How many of the latest sessions are kept
+const RECENT_SESSIONS: usize = 60;
+/// Only sessions from this many days before the hint is asked for are looked at
+const WINDOW_DAYS: i64 = 30;
+/// Fewer sessions than this can't tell a habit from a coincidence
+const MIN_SESSIONS: usize = 12;
+/// Times of day separated by at least this many hours without a session start are apart
+const MIN_HOURS_APART: u32 = 3;
+/// Each of the two times of day needs at least this share of the sessions
+const MIN_STUDY_TIME_SHARE: f64 = 0.25;
+/// The share of a time of day's sessions that have to be in its course to call it that time's
+/// course
+const MIN_COURSE_SHARE: f64 = 0.8;
+/// The share of consecutive sessions that have to be in different courses for the courses to be
+/// taking turns
+const MIN_COURSE_SWITCHES: f64 = 0.5;
+/// Each time of day needs this many reviews to compare their accuracy
+const MIN_REVIEWS_TO_COMPARE: u32 = 30;
+/// How far apart the accuracy at the two times of day has to be
+const MIN_ACCURACY_GAP: f64 = 0.25;
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq)]
+struct AccountSession {
+    start: DateTime<Utc>,
+    /// When the last event in the session happened
+    end: DateTime<Utc>,
+    /// The course the session started in
+    course: Language,
+    /// Whether the user went on to another course in the same session, which one person does
+    /// and two people taking turns don't
+    switched_course: bool,
+    reviews: u32,
+    correct: u32,
+}
+
+/// The latest study sessions on the account in any course, filled in as events are processed
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq)]
+pub(crate) struct AccountSessions {
+    sessions: VecDeque<AccountSession>,
+}
+
+impl AccountSessions {
+    /// An event in `course` at `timestamp`. `correct` is whether it was remembered, for events
+    /// that review something.
+    pub(crate) fn record(
+        &mut self,
+        timestamp: DateTime<Utc>,
+        course: Language,
+        correct: Option<bool>,
+    ) {
+        let reviews = u32::from(correct.is_some());
+        let correct = u32::from(correct == Some(true));
+        match self.sessions.back_mut().filter(|session| {
+            timestamp - session.end < chrono::Duration::minutes(SESSION_GAP_MINUTES)
+        }) {
+            Some(session) => {
+                session.end = session.end.max(timestamp);
+                session.switched_course |= session.course != course;
+                session.reviews += reviews;
+                session.correct += correct;
+            }
+            None => {
+                self.sessions.push_back(AccountSession {
+                    start: timestamp,
+                    end: timestamp,
+                    course,
+                    switched_course: false,
+                    reviews,
+                    correct,
+                });
+                if self.sessions.len() > RECENT_SESSIONS {
+                    self.sessions.pop_front();
+                }
+            }
+        }
+    }
+}
+
+/// Why the account looks shared
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[cfg_attr(target_arch = "wasm32", derive(tsify::Tsify))]
+#[cfg_attr(target_arch = "wasm32", tsify(into_wasm_abi, from_wasm_abi))]
+pub enum AccountSharingReason {
+    /// Each time of day has its own course, and sessions take turns between them
+    DifferentCourses,
+    /// The user remembers far more at one time of day than the other
+    DifferentAccuracy,
+}
+
+/// One of the times of day the account is used at
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(target_arch = "wasm32", derive(tsify::Tsify))]
+#[cfg_attr(target_arch = "wasm32", tsify(into_wasm_abi, from_wasm_abi))]
+pub struct StudyTime {
+    /// The hours sessions start in, in the user's time zone. `last_hour` is before `first_hour`
+    /// when it spans midnight.
+    pub first_hour: u32,
+    pub last_hour: u32,
+    pub sessions: u32,
+    /// The course most sessions at this time were in, if at least `MIN_COURSE_SHARE` were
+    pub course: Option<Language>,
+    /// The share of reviews at this time that were remembered, or `None` if there weren't any
+    pub accuracy: Option<f64>,
+}
+
+/// See `Deck::get_account_sharing_hint`
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(target_arch = "wasm32", derive(tsify::Tsify))]
+#[cfg_attr(target_arch = "wasm32", tsify(into_wasm_abi))]
+pub struct AccountSharingHint {
+    /// The two times of day, earliest first
+    pub study_times: Vec<StudyTime>,
+    pub reasons: Vec<AccountSharingReason>,
+}
+
+/// The hours (UTC) that session starts cluster in, with at least `MIN_HOURS_APART` empty hours
+/// between clusters. Clusters can wrap around midnight.
+fn hour_clusters(sessions_by_hour: &[u32; 24]) -> Vec<Vec<u32>> {
+    let busy = (0..24)
+        .filter(|&hour| sessions_by_hour[hour as usize] > 0)
+        .collect::<Vec<u32>>();
+    // Start right after the widest gap, so no cluster is split at midnight
+    let Some(start) = (0..busy.len()).max_by_key(|&i| {
+        let previous = busy[(i + busy.len() - 1) % busy.len()];
+        (busy[i] + 24 - previous - 1) % 24
+    }) else {
+        return Vec::new();
+    };
+    let mut clusters: Vec<Vec<u32>> = Vec::new();
+    for i in 0..busy.len() {
+        let hour = busy[(start + i) % busy.len()];
+        match clusters.last_mut() {
+            Some(cluster) if (hour + 24 - cluster.last().unwrap() - 1) % 24 < MIN_HOURS_APART => {
+                cluster.push(hour);
+            }
+            _ => clusters.push(vec![hour]),
+        }
+    }
+    clusters
+}
+
+impl AccountSessions {
+    fn hint(&self, now: DateTime<Utc>, utc_offset_minutes: i32) -> Option<AccountSharingHint> {
+        let since = now - chrono::Duration::days(WINDOW_DAYS);
+        let recent = self
+            .sessions
+            .iter()
+            .filter(|session| session.start >= since)
+            .collect::<Vec<_>>();
+        if recent.len() < MIN_SESSIONS {
+            return None;
+        }
+
+        let mut sessions_by_hour = [0; 24];
+        for session in &recent {
+            sessions_by_hour[session.start.hour() as usize] += 1;
+        }
+        let cluster_sessions = |cluster: &[u32]| {
+            recent
+                .iter()
+                .filter(|session| cluster.contains(&session.start.hour()))
+                .copied()
+                .collect::<Vec<_>>()
+        };
+        let mut clusters = hour_clusters(&sessions_by_hour)
+            .into_iter()
+            .map(|cluster| {
+                let sessions = cluster_sessions(&cluster);
+                (cluster, sessions)
+            })
+            .collect::<Vec<_>>();
+        clusters.sort_by_key(|(_, sessions)| std::cmp::Reverse(sessions.len()));
+        clusters.truncate(2);
+        if clusters.len() < 2
+            || clusters.iter().any(|(_, sessions)| {
+                (sessions.len() as f64) < MIN_STUDY_TIME_SHARE * recent.len() as f64
+            })
+        {
+            return None;
+        }
+
+        let local_hour = |utc_hour: u32| {
+            (utc_hour as i32 * 60 + utc_offset_minutes).rem_euclid(24 * 60) as u32 / 60
+        };
+        let mut study_times = clusters
+            .iter()
+            .map(|(hours, sessions)| {
+                let mut courses = sessions
+                    .iter()
+                    .map(|session| session.course)
+                    .collect::<Vec<_>>();
+                courses.sort();
+                courses.dedup();
+                let course = courses.into_iter().find(|course| {
+                    let in_course = sessions
+                        .iter()
+                        .filter(|session| session.course == *course)
+                        .count();
+                    in_course as f64 >= MIN_COURSE_SHARE * sessions.len() as f64
+                });
+                let reviews = sessions.iter().map(|session| session.reviews).sum::<u32>();
+                let correct = sessions.iter().map(|session| session.correct).sum::<u32>();
+                (
+                    reviews,
+                    StudyTime {
+                        first_hour: local_hour(hours[0]),
+                        last_hour: local_hour(*hours.last().unwrap()),
+                        sessions: sessions.len() as u32,
+                        course,
+                        accuracy: (reviews > 0).then(|| f64::from(correct) / f64::from(reviews)),
+                    },
+                )
+            })
+            .collect::<Vec<_>>();
+
+        let mut reasons = Vec::new();
+        let [(first_reviews, first), (second_reviews, second)] = &study_times[..] else {
+            return None;
+        };
+        let switches = recent
+            .windows(2)
+            .filter(|pair| pair[0].course != pair[1].course)
+            .count();
+        if first.course.is_some()
+            && second.course.is_some()
+            && first.course != second.course
+            && !recent.iter().any(|session| session.switched_course)
+            && switches as f64 >= MIN_COURSE_SWITCHES * (recent.len() - 1) as f64
+        {
+            reasons.push(AccountSharingReason::DifferentCourses);
+        }
+        if let (Some(first_accuracy), Some(second_accuracy)) = (first.accuracy, second.accuracy)
+            && *first_reviews >= MIN_REVIEWS_TO_COMPARE
+            && *second_reviews >= MIN_REVIEWS_TO_COMPARE
+            && (first_accuracy - second_accuracy).abs() >= MIN_ACCURACY_GAP
+        {
+            reasons.push(AccountSharingReason::DifferentAccuracy);
+        }
+        if reasons.is_empty() {
+            return None;
+        }
+
+        study_times.sort_by_key(|(_, study_time)| study_time.first_hour);
+        Some(AccountSharingHint {
+            study_times: study_times
+                .into_iter()
+                .map(|(_, study_time)| study_time)
+                .collect(),
+            reasons,
+        })
+    }
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+impl Deck {
+    /// Whether the account looks like two people are taking turns on it, judging by the sessions
+    /// in the `WINDOW_DAYS` before `timestamp_ms` in any course. If so, the app can gently suggest
+    /// giving the other person a sub-profile, so each gets their own schedule. `utc_offset_minutes`
+    /// is the user's time zone, which the hours in the hint are given in.
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn get_account_sharing_hint(
+        &self,
+        timestamp_ms: f64,
+        utc_offset_minutes: i32,
+    ) -> Option<AccountSharingHint> {
+        self.stats
+            .account_sessions
+            .hint(datetime_from_ms(timestamp_ms), utc_offset_minutes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A session of `reviews` at `hour` (UTC) on `day`, of which `correct` were remembered
+    fn session(
+        sessions: &mut AccountSessions,
+        start: DateTime<Utc>,
+        (day, hour): (i64, i64),
+        course: Language,
+        reviews: u32,
+        correct: u32,
+    ) {
+        let session_start = start + chrono::Duration::days(day) + chrono::Duration::hours(hour);
+        for i in 0..reviews {
+            sessions.record(
+                session_start + chrono::Duration::minutes(i64::from(i)),
+                course,
+                Some(i < correct),
+            );
+        }
+    }
+
+    #[test]
+    fn test_turns_at_different_times_look_shared() {
+        // Midnight UTC
+        let start = DateTime::<Utc>::from_timestamp(1_759_968_000, 0).unwrap();
+        let now = start + chrono::Duration::days(10);
+
+        // One person studying morning and evening isn't sharing
+        let mut one_person = AccountSessions::default();
+        for day in 0..10 {
+            session(&mut one_person, start, (day, 7), Language::French, 10, 8);
+            session(&mut one_person, start, (day, 20), Language::French, 10, 8);
+        }
+        assert_eq!(one_person.hint(now, 0), None);
+
+        // Someone learning French in the morning, and someone else Spanish in the evening
+        let mut two_people = AccountSessions::default();
+        for day in 0..10 {
+            session(&mut two_people, start, (day, 7), Language::French, 10, 9);
+            session(&mut two_people, start, (day, 20), Language::Spanish, 10, 3);
+        }
+        let hint = two_people.hint(now, 60).unwrap();
+        assert_eq!(
+            hint.reasons,
+            vec![
+                AccountSharingReason::DifferentCourses,
+                AccountSharingReason::DifferentAccuracy
+            ]
+        );
+        assert_eq!(hint.study_times[0].first_hour, 8);
+        assert_eq!(hint.study_times[0].course, Some(Language::French));
+        assert_eq!(hint.study_times[1].first_hour, 21);
+        assert_eq!(hint.study_times[1].course, Some(Language::Spanish));
+
+        // Everything is long enough ago not to matter
+        assert_eq!(two_people.hint(now + chrono::Duration::days(60), 0), None);
+    }
+
+    #[test]
+    fn test_clusters_wrap_around_midnight() {
+        let mut sessions_by_hour = [0; 24];
+        for hour in [22, 23, 0, 1, 12, 13] {
+            sessions_by_hour[hour] = 1;
+        }
+        assert_eq!(
+            hour_clusters(&sessions_by_hour),
+            vec![vec![12, 13], vec![22, 23, 0, 1]]
+        );
+    }
+}
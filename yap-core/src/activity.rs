@@ -0,0 +1,464 @@
+//! Study sessions, for the post-session recap, the weekly digest, and exporting to time trackers
+//! like Toggl or Exist. Like the sessions in `fatigue`, a session ends at a gap between events,
+//! or earlier if the app records that the user finished it (`Deck::complete_session`).
+
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, FixedOffset, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+#[cfg(target_arch = "wasm32")]
+use wasm_bindgen::prelude::*;
+
+use crate::fatigue::SESSION_GAP_MINUTES;
+use crate::{Deck, DeckEvent, LanguageEvent, LanguageEventContent, datetime_from_ms};
+
+/// Time trackers drop (or can't show) events without a duration, so even a session of a single
+/// review lasts at least this long
+const MIN_SESSION_SECONDS: i64 = 60;
+
+/// How many of each kind of challenge were graded
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(target_arch = "wasm32", derive(tsify::Tsify))]
+#[cfg_attr(target_arch = "wasm32", tsify(into_wasm_abi, from_wasm_abi))]
+pub struct ChallengeCounts {
+    pub flashcards: u32,
+    pub translations: u32,
+    pub transcriptions: u32,
+}
+
+impl ChallengeCounts {
+    pub fn total(&self) -> u32 {
+        self.flashcards + self.translations + self.transcriptions
+    }
+
+    fn add(&mut self, kind: ChallengeKind) {
+        match kind {
+            ChallengeKind::Flashcard => self.flashcards += 1,
+            ChallengeKind::Translation => self.translations += 1,
+            ChallengeKind::Transcription => self.transcriptions += 1,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum ChallengeKind {
+    Flashcard,
+    Translation,
+    Transcription,
+}
+
+impl ChallengeKind {
+    /// The kind of challenge `event` grades, or `None` if it doesn't grade one
+    pub(crate) fn of(event: &LanguageEventContent) -> Option<Self> {
+        match event {
+            LanguageEventContent::ReviewCard { .. } | LanguageEventContent::WarmupReview { .. } => {
+                Some(Self::Flashcard)
+            }
+            LanguageEventContent::TranslationChallenge { .. }
+            | LanguageEventContent::SentenceRepetition { .. } => Some(Self::Translation),
+            LanguageEventContent::TranscriptionChallenge { .. } => Some(Self::Transcription),
+            // A sentence heard hands-free is like a transcription, without typing it out
+            LanguageEventContent::HandsFreeReview { sentence, .. } => Some(match sentence {
+                Some(_) => Self::Transcription,
+                None => Self::Flashcard,
+            }),
+            LanguageEventContent::AddCards { .. }
+            | LanguageEventContent::SetScheduler { .. }
+            | LanguageEventContent::AudioFeedback { .. }
+            | LanguageEventContent::FavoriteSentence { .. }
+            | LanguageEventContent::PrioritizeCard { .. }
+            | LanguageEventContent::SetSentenceFilters { .. }
+            | LanguageEventContent::SetFsrsParameters { .. }
+            | LanguageEventContent::SetShareMistakeHistory { .. }
+            | LanguageEventContent::SetComponentCredit { .. }
+            | LanguageEventContent::SetCardMode { .. }
+            | LanguageEventContent::SetReviewOrder { .. }
+            | LanguageEventContent::SetDailyGoal { .. }
+            | LanguageEventContent::SetNewCardAccuracyThreshold { .. }
+            | LanguageEventContent::RequestCalibration { .. }
+            | LanguageEventContent::ImportAnkiMemoryStates { .. }
+            | LanguageEventContent::LookedUp { .. }
+            | LanguageEventContent::MovieQuizCompleted { .. }
+            | LanguageEventContent::ReportContent { .. }
+            | LanguageEventContent::Graduate {}
+            | LanguageEventContent::SetSchedulingProfile { .. }
+            | LanguageEventContent::ResetDeck {}
+            | LanguageEventContent::SingAlong { .. }
+            | LanguageEventContent::SessionCompleted { .. } => None,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq)]
+pub(crate) struct StudySession {
+    pub(crate) start: DateTime<Utc>,
+    /// When the last event in the session happened
+    pub(crate) end: DateTime<Utc>,
+    /// Flashcards and challenges graded in the session
+    pub(crate) reviews: u32,
+    /// How many of `reviews` were remembered
+    pub(crate) correct: u32,
+    pub(crate) xp: f64,
+    /// `reviews` by kind. Snapshots from before this was tracked don't have it.
+    #[serde(default)]
+    pub(crate) challenges: ChallengeCounts,
+    /// Whether the user finished the session, so the next event starts a new one even without a
+    /// gap
+    #[serde(default)]
+    pub(crate) completed: bool,
+}
+
+impl StudySession {
+    /// Whether an event at `timestamp` is part of this session
+    fn continues_at(&self, timestamp: DateTime<Utc>) -> bool {
+        !self.completed && timestamp - self.end < chrono::Duration::minutes(SESSION_GAP_MINUTES)
+    }
+
+    fn exported_end(&self) -> DateTime<Utc> {
+        self.end
+            .max(self.start + chrono::Duration::seconds(MIN_SESSION_SECONDS))
+    }
+}
+
+/// Every session the user has studied in, filled in as events are processed
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct ActivityHistory {
+    sessions: Vec<StudySession>,
+}
+
+impl ActivityHistory {
+    /// `challenge` is the kind of challenge the event graded and whether it was remembered, for
+    /// events that review something
+    pub(crate) fn record(
+        &mut self,
+        timestamp: DateTime<Utc>,
+        challenge: Option<(ChallengeKind, bool)>,
+        xp: f64,
+    ) {
+        let reviews = u32::from(challenge.is_some());
+        let correct = u32::from(challenge.is_some_and(|(_, correct)| correct));
+        let mut challenges = ChallengeCounts::default();
+        if let Some((kind, _)) = challenge {
+            challenges.add(kind);
+        }
+        match self.ongoing_session_mut(timestamp) {
+            Some(session) => {
+                session.end = session.end.max(timestamp);
+                session.reviews += reviews;
+                session.correct += correct;
+                session.xp += xp;
+                if let Some((kind, _)) = challenge {
+                    session.challenges.add(kind);
+                }
+            }
+            None => self.sessions.push(StudySession {
+                start: timestamp,
+                end: timestamp,
+                reviews,
+                correct,
+                xp,
+                challenges,
+                completed: false,
+            }),
+        }
+    }
+
+    /// Ends the session going on at `timestamp`, if there is one
+    pub(crate) fn complete_session(&mut self, timestamp: DateTime<Utc>) {
+        if let Some(session) = self.ongoing_session_mut(timestamp) {
+            session.end = session.end.max(timestamp);
+            session.completed = true;
+        }
+    }
+
+    /// The last session, if an event at `timestamp` would be part of it
+    fn ongoing_session(&self, timestamp: DateTime<Utc>) -> Option<&StudySession> {
+        self.sessions
+            .last()
+            .filter(|session| session.continues_at(timestamp))
+    }
+
+    fn ongoing_session_mut(&mut self, timestamp: DateTime<Utc>) -> Option<&mut StudySession> {
+        self.sessions
+            .last_mut()
+            .filter(|session| session.continues_at(timestamp))
+    }
+
+    /// When the session an event at `timestamp` is part of started, which is `timestamp` itself
+    /// if the event starts a new one
+    pub(crate) fn session_start_at(&self, timestamp: DateTime<Utc>) -> DateTime<Utc> {
+        self.ongoing_session(timestamp)
+            .map_or(timestamp, |session| session.start)
+    }
+
+    /// When the last event of the session going on at `timestamp` happened, if there is one
+    pub(crate) fn last_event_in_session(&self, timestamp: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        self.ongoing_session(timestamp).map(|session| session.end)
+    }
+
+    /// When the last event was, if there's been one
+    pub(crate) fn last_studied(&self) -> Option<DateTime<Utc>> {
+        self.sessions.last().map(|session| session.end)
+    }
+
+    /// The sessions that started in `[start, end)`
+    pub(crate) fn sessions_between(
+        &self,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> impl Iterator<Item = &StudySession> {
+        self.sessions
+            .iter()
+            .filter(move |session| session.start >= start && session.start < end)
+    }
+}
+
+/// A study session, for the recap shown after it
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(target_arch = "wasm32", derive(tsify::Tsify))]
+#[cfg_attr(target_arch = "wasm32", tsify(into_wasm_abi))]
+pub struct SessionSummary {
+    pub start_ms: f64,
+    /// When the last event in the session happened
+    pub end_ms: f64,
+    pub duration_seconds: u64,
+    pub challenges: ChallengeCounts,
+    /// How many of the challenges were remembered
+    pub correct: u32,
+    /// The share of challenges that were remembered, or `None` if there weren't any
+    pub accuracy: Option<f64>,
+    pub xp: f64,
+    /// Whether the app recorded that the user finished the session. Otherwise it ended at a gap,
+    /// or is still going.
+    pub completed: bool,
+}
+
+impl From<&StudySession> for SessionSummary {
+    fn from(session: &StudySession) -> Self {
+        SessionSummary {
+            start_ms: session.start.timestamp_millis() as f64,
+            end_ms: session.end.timestamp_millis() as f64,
+            duration_seconds: (session.end - session.start).num_seconds().max(0) as u64,
+            challenges: session.challenges,
+            correct: session.correct,
+            accuracy: (session.reviews > 0)
+                .then(|| f64::from(session.correct) / f64::from(session.reviews)),
+            xp: session.xp,
+            completed: session.completed,
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct ExportedSession {
+    start: String,
+    end: String,
+    minutes: f64,
+    reviews: u32,
+    xp: f64,
+}
+
+#[derive(Serialize)]
+struct ExportedDay {
+    date: NaiveDate,
+    minutes: f64,
+    reviews: u32,
+    xp: f64,
+    sessions: Vec<ExportedSession>,
+}
+
+#[derive(Serialize)]
+struct ActivityExport {
+    target_language: language_utils::Language,
+    native_language: language_utils::Language,
+    days: Vec<ExportedDay>,
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+impl Deck {
+    /// Records that the user finished the session going on at `timestamp_ms`, e.g. when they leave
+    /// the review screen. Later events start a new session even if they come soon after. `None` if
+    /// no session is going on.
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn complete_session(&self, timestamp_ms: f64) -> Option<DeckEvent> {
+        let timestamp = datetime_from_ms(timestamp_ms);
+        let session = self.stats.activity.ongoing_session(timestamp)?;
+        Some(DeckEvent::Language(LanguageEvent {
+            target_language: self.context.target_language,
+            native_language: self.context.native_language,
+            content: LanguageEventContent::SessionCompleted {
+                duration_seconds: (timestamp.max(session.end) - session.start)
+                    .num_seconds()
+                    .max(0) as u64,
+                challenges: session.challenges,
+                correct: session.correct,
+            },
+        }))
+    }
+
+    /// The last `count` sessions, most recent first. The last one may still be going on.
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn get_recent_sessions(&self, count: usize) -> Vec<SessionSummary> {
+        self.stats
+            .activity
+            .sessions
+            .iter()
+            .rev()
+            .take(count)
+            .map(SessionSummary::from)
+            .collect()
+    }
+
+    /// A daily summary of the sessions that started between `start_ms` and `end_ms`, as JSON.
+    /// `utc_offset_minutes` is the user's time zone, which days and times are given in.
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn export_activity_json(
+        &self,
+        start_ms: f64,
+        end_ms: f64,
+        utc_offset_minutes: i32,
+    ) -> String {
+        let offset = FixedOffset::east_opt(utc_offset_minutes * 60)
+            .unwrap_or_else(|| FixedOffset::east_opt(0).unwrap());
+        let mut days: BTreeMap<NaiveDate, ExportedDay> = BTreeMap::new();
+        for session in &self.stats.activity.sessions {
+            let session_start_ms = session.start.timestamp_millis() as f64;
+            if session_start_ms < start_ms || session_start_ms >= end_ms {
+                continue;
+            }
+            let start = session.start.with_timezone(&offset);
+            let end = session.exported_end().with_timezone(&offset);
+            let minutes = (end - start).num_seconds() as f64 / 60.0;
+            let date = start.date_naive();
+            let day = days.entry(date).or_insert_with(|| ExportedDay {
+                date,
+                minutes: 0.0,
+                reviews: 0,
+                xp: 0.0,
+                sessions: Vec::new(),
+            });
+            day.minutes += minutes;
+            day.reviews += session.reviews;
+            day.xp += session.xp;
+            day.sessions.push(ExportedSession {
+                start: start.to_rfc3339(),
+                end: end.to_rfc3339(),
+                minutes,
+                reviews: session.reviews,
+                xp: session.xp,
+            });
+        }
+
+        let export = ActivityExport {
+            target_language: self.context.target_language,
+            native_language: self.context.native_language,
+            days: days.into_values().collect(),
+        };
+        serde_json::to_string_pretty(&export).unwrap_or_default()
+    }
+
+    /// Every session as an event in an iCalendar file, which most time trackers can subscribe to
+    /// or import
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn export_activity_ical(&self) -> String {
+        let format = |time: DateTime<Utc>| time.format("%Y%m%dT%H%M%SZ").to_string();
+        let target_language = self.context.target_language;
+        let mut lines = vec![
+            "BEGIN:VCALENDAR".to_string(),
+            "VERSION:2.0".to_string(),
+            "PRODID:-//Yap//Study sessions//EN".to_string(),
+        ];
+        for session in &self.stats.activity.sessions {
+            lines.extend([
+                "BEGIN:VEVENT".to_string(),
+                format!(
+                    "UID:{}-{target_language:?}@yap.town",
+                    session.start.timestamp_millis()
+                ),
+                format!("DTSTAMP:{}", format(session.start)),
+                format!("DTSTART:{}", format(session.start)),
+                format!("DTEND:{}", format(session.exported_end())),
+                format!(
+                    "SUMMARY:Studied {target_language}: {} reviews\\, {} XP",
+                    session.reviews,
+                    session.xp.round()
+                ),
+                "END:VEVENT".to_string(),
+            ]);
+        }
+        lines.push("END:VCALENDAR".to_string());
+        // iCalendar lines end in CRLF, including the last one
+        lines.iter().map(|line| format!("{line}\r\n")).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sessions_are_split_at_gaps() {
+        let start = DateTime::<Utc>::from_timestamp(1_700_000_000, 0).unwrap();
+        let mut history = ActivityHistory::default();
+        for minute in [0, 5, 10] {
+            history.record(
+                start + chrono::Duration::minutes(minute),
+                Some((ChallengeKind::Flashcard, minute > 0)),
+                2.0,
+            );
+        }
+        // Adding cards isn't a review, but it's still studying
+        history.record(start + chrono::Duration::minutes(12), None, 0.0);
+        history.record(
+            start + chrono::Duration::hours(3),
+            Some((ChallengeKind::Translation, true)),
+            1.0,
+        );
+
+        assert_eq!(history.sessions.len(), 2);
+        let first = history.sessions[0];
+        assert_eq!(first.end - first.start, chrono::Duration::minutes(12));
+        assert_eq!(first.reviews, 3);
+        assert_eq!(first.correct, 2);
+        assert_eq!(first.xp, 6.0);
+        let second = history.sessions[1];
+        assert_eq!(
+            second.exported_end() - second.start,
+            chrono::Duration::seconds(MIN_SESSION_SECONDS)
+        );
+    }
+
+    #[test]
+    fn test_completing_a_session_ends_it_early() {
+        let start = DateTime::<Utc>::from_timestamp(1_700_000_000, 0).unwrap();
+        let mut history = ActivityHistory::default();
+        history.record(start, Some((ChallengeKind::Flashcard, true)), 1.0);
+        history.record(
+            start + chrono::Duration::minutes(4),
+            Some((ChallengeKind::Transcription, false)),
+            1.0,
+        );
+        history.complete_session(start + chrono::Duration::minutes(5));
+        // Well within the gap, but the user said they were done
+        history.record(
+            start + chrono::Duration::minutes(6),
+            Some((ChallengeKind::Flashcard, true)),
+            1.0,
+        );
+
+        assert_eq!(history.sessions.len(), 2);
+        let first = SessionSummary::from(&history.sessions[0]);
+        assert!(first.completed);
+        assert_eq!(first.duration_seconds, 5 * 60);
+        assert_eq!(
+            first.challenges,
+            ChallengeCounts {
+                flashcards: 1,
+                translations: 0,
+                transcriptions: 1,
+            }
+        );
+        assert_eq!(first.accuracy, Some(0.5));
+        assert!(!history.sessions[1].completed);
+    }
+}
@@ -0,0 +1,344 @@
+//! Keeping a parallel Anki deck roughly in line with this one. Anki's FSRS uses the same memory
+//! model as ours (stability in days, difficulty from 1 to 10), so a card's memory state carries
+//! over as it is. Both directions use a tab-separated notes file with the header lines Anki's text
+//! importer reads, one word per line:
+//!
+//! ```text
+//! #separator:tab
+//! #html:false
+//! #columns:Word	Lemma	Part of speech	Stability	Difficulty	Due	Last review
+//! comer	comer	VERB	42.17	4.80	2026-11-20	2026-10-09
+//! ```
+//!
+//! Multiword terms leave the lemma and part of speech empty, and so can a word from Anki, which is
+//! then matched to its most common heteronym. Dates are UTC. States imported from Anki only fill
+//! in ghost cards (see `CardData::Ghost`): a card added here has its own review history, which
+//! is always trusted over Anki's.
+
+use chrono::{DateTime, NaiveDate, Utc};
+use language_utils::{Heteronym, Lexeme, PartOfSpeech};
+use lasso::Spur;
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+#[cfg(target_arch = "wasm32")]
+use wasm_bindgen::prelude::*;
+
+use crate::data_mismatches::DataMismatchKind;
+use crate::{
+    CardData, CardIndicator, CardStatus, Deck, DeckEvent, DeckState, LanguageEvent,
+    LanguageEventContent,
+};
+
+const HEADER: &str = "#separator:tab\n#html:false\n#columns:Word\tLemma\tPart of speech\tStability\tDifficulty\tDue\tLast review\n";
+
+/// One card's FSRS memory state, as recorded by `LanguageEventContent::ImportAnkiMemoryStates`
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[cfg_attr(target_arch = "wasm32", derive(tsify::Tsify))]
+#[cfg_attr(target_arch = "wasm32", tsify(into_wasm_abi, from_wasm_abi))]
+pub struct AnkiMemoryState {
+    pub lexeme: Lexeme<String>,
+    /// In days
+    pub stability: f64,
+    pub difficulty: f64,
+    pub due_ms: i64,
+    pub last_review_ms: i64,
+}
+
+// States are compared by their exact values, so they can be part of deck events (which are
+// ordered)
+impl Ord for AnkiMemoryState {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.lexeme
+            .cmp(&other.lexeme)
+            .then_with(|| self.stability.total_cmp(&other.stability))
+            .then_with(|| self.difficulty.total_cmp(&other.difficulty))
+            .then_with(|| self.due_ms.cmp(&other.due_ms))
+            .then_with(|| self.last_review_ms.cmp(&other.last_review_ms))
+    }
+}
+
+impl PartialOrd for AnkiMemoryState {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for AnkiMemoryState {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other).is_eq()
+    }
+}
+
+impl Eq for AnkiMemoryState {}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(target_arch = "wasm32", derive(tsify::Tsify))]
+#[cfg_attr(target_arch = "wasm32", tsify(into_wasm_abi))]
+pub struct AnkiImport {
+    /// `None` if there's nothing to import
+    pub event: Option<DeckEvent>,
+    /// How many cards get Anki's memory state
+    pub imported: u32,
+    /// Words the user has already added here, which keep their own state
+    pub already_added: u32,
+    /// Lines that couldn't be read, or whose word isn't in the language pack
+    pub skipped_lines: Vec<String>,
+}
+
+fn format_date(date: DateTime<Utc>) -> String {
+    date.format("%Y-%m-%d").to_string()
+}
+
+fn parse_date(date: &str) -> Option<DateTime<Utc>> {
+    let date = NaiveDate::parse_from_str(date.trim(), "%Y-%m-%d").ok()?;
+    Some(date.and_hms_opt(0, 0, 0)?.and_utc())
+}
+
+fn pos_name(pos: PartOfSpeech) -> String {
+    serde_json::to_value(pos)
+        .ok()
+        .and_then(|value| value.as_str().map(str::to_string))
+        .unwrap_or_default()
+}
+
+fn parse_pos(pos: &str) -> Option<PartOfSpeech> {
+    serde_json::from_value(serde_json::Value::String(pos.trim().to_uppercase())).ok()
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+impl Deck {
+    /// The memory state of every word card that's been reviewed, as a notes file for Anki (see
+    /// the module docs)
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn export_anki_memory_states(&self) -> String {
+        let rodeo = &self.context.language_pack.rodeo;
+        let mut rows = self
+            .cards
+            .iter()
+            .filter_map(|(card, status)| {
+                let CardIndicator::TargetLanguage { lexeme } = card else {
+                    return None;
+                };
+                let CardStatus::Tracked(
+                    CardData::Added { fsrs_card } | CardData::Ghost { fsrs_card },
+                ) = status
+                else {
+                    return None;
+                };
+                if fsrs_card.state == rs_fsrs::State::New {
+                    return None;
+                }
+                let (word, lemma, pos) = match lexeme.resolve(rodeo) {
+                    Lexeme::Heteronym(Heteronym { word, lemma, pos }) => {
+                        (word, lemma, pos_name(pos))
+                    }
+                    Lexeme::Multiword(term) => (term, String::new(), String::new()),
+                };
+                Some(format!(
+                    "{word}\t{lemma}\t{pos}\t{:.2}\t{:.2}\t{}\t{}\n",
+                    fsrs_card.stability,
+                    fsrs_card.difficulty,
+                    format_date(fsrs_card.due),
+                    format_date(fsrs_card.last_review),
+                ))
+            })
+            .collect::<Vec<_>>();
+        rows.sort();
+
+        let mut file = HEADER.to_string();
+        file.extend(rows);
+        file
+    }
+
+    /// Reads memory states from an Anki notes file in the format `export_anki_memory_states`
+    /// writes. Importing them gives the words the user hasn't added here ghost cards with Anki's
+    /// state, unless they already have a ghost card that was reviewed more recently.
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn import_anki_memory_states(&self, file: String) -> AnkiImport {
+        let mut states = Vec::new();
+        let mut already_added = 0;
+        let mut skipped_lines = Vec::new();
+        for line in file.lines() {
+            if line.trim().is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let Some((lexeme, state)) = self.parse_anki_line(line) else {
+                skipped_lines.push(line.to_string());
+                continue;
+            };
+            let card = CardIndicator::TargetLanguage { lexeme };
+            if matches!(
+                self.cards.get(&card),
+                Some(CardStatus::Tracked(CardData::Added { .. }))
+            ) {
+                already_added += 1;
+            } else {
+                states.push(state);
+            }
+        }
+
+        AnkiImport {
+            imported: states.len() as u32,
+            event: (!states.is_empty()).then(|| {
+                DeckEvent::Language(LanguageEvent {
+                    target_language: self.context.target_language,
+                    native_language: self.context.native_language,
+                    content: LanguageEventContent::ImportAnkiMemoryStates { states },
+                })
+            }),
+            already_added,
+            skipped_lines,
+        }
+    }
+}
+
+impl Deck {
+    fn parse_anki_line(&self, line: &str) -> Option<(Lexeme<Spur>, AnkiMemoryState)> {
+        let [word, lemma, pos, stability, difficulty, due, last_review] =
+            <[&str; 7]>::try_from(line.split('\t').collect::<Vec<_>>()).ok()?;
+        let lexeme = self.anki_lexeme(word.trim(), lemma.trim(), pos)?;
+        let stability = stability.trim().parse::<f64>().ok()?;
+        let difficulty = difficulty.trim().parse::<f64>().ok()?;
+        if !(stability > 0.0 && (1.0..=10.0).contains(&difficulty)) {
+            return None;
+        }
+        let state = AnkiMemoryState {
+            lexeme: lexeme.resolve(&self.context.language_pack.rodeo),
+            stability,
+            difficulty,
+            due_ms: parse_date(due)?.timestamp_millis(),
+            last_review_ms: parse_date(last_review)?.timestamp_millis(),
+        };
+        Some((lexeme, state))
+    }
+
+    /// The lexeme a line from Anki is about. Without a lemma and part of speech, a word is taken
+    /// to be its most common heteronym.
+    fn anki_lexeme(&self, word: &str, lemma: &str, pos: &str) -> Option<Lexeme<Spur>> {
+        let pack = &self.context.language_pack;
+        let lexeme = if !lemma.is_empty() {
+            self.context.intern_lexeme(&Lexeme::Heteronym(Heteronym {
+                word: word.to_string(),
+                lemma: lemma.to_string(),
+                pos: parse_pos(pos)?,
+            }))?
+        } else if word.contains(char::is_whitespace) {
+            self.context
+                .intern_lexeme(&Lexeme::Multiword(word.to_string()))?
+        } else {
+            pack.words_to_heteronyms
+                .get(&pack.rodeo.get(word)?)?
+                .iter()
+                .map(|heteronym| Lexeme::Heteronym(*heteronym))
+                .max_by_key(|lexeme| pack.word_frequencies.get(lexeme).map(|f| f.count))?
+        };
+        self.context
+            .is_card_valid(&CardIndicator::TargetLanguage { lexeme })
+            .then_some(lexeme)
+    }
+}
+
+impl DeckState {
+    pub(crate) fn import_anki_memory_states(&mut self, states: &[AnkiMemoryState]) {
+        for state in states {
+            let Some(lexeme) = self.data_mismatches.check(
+                self.context.intern_lexeme(&state.lexeme),
+                DataMismatchKind::Lexeme,
+                || format!("{:?}", state.lexeme),
+            ) else {
+                continue;
+            };
+            let card = CardIndicator::TargetLanguage { lexeme };
+            let (Some(due), Some(last_review)) = (
+                DateTime::<Utc>::from_timestamp_millis(state.due_ms),
+                DateTime::<Utc>::from_timestamp_millis(state.last_review_ms),
+            ) else {
+                continue;
+            };
+            let keep_existing = match self.cards.get(&card) {
+                Some(CardData::Added { .. }) => true,
+                Some(CardData::Ghost { fsrs_card }) => fsrs_card.last_review >= last_review,
+                None => false,
+            };
+            if keep_existing || !self.context.is_card_valid(&card) {
+                continue;
+            }
+
+            let mut fsrs_card = rs_fsrs::Card::new(last_review);
+            fsrs_card.state = rs_fsrs::State::Review;
+            fsrs_card.stability = state.stability;
+            fsrs_card.difficulty = state.difficulty;
+            fsrs_card.due = due;
+            fsrs_card.last_review = last_review;
+            fsrs_card.scheduled_days = (due - last_review).num_days().max(0) as _;
+            fsrs_card.reps = 1;
+            self.cards.insert(card, CardData::Ghost { fsrs_card });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use weapon::AppState;
+    use weapon::data_model::Timestamped;
+
+    #[test]
+    fn test_memory_states_round_trip_through_anki() {
+        let now = DateTime::<Utc>::from_timestamp(1_760_000_000, 0).unwrap();
+        let mut deck = Deck::default();
+        let mut heteronyms = deck
+            .context
+            .language_pack
+            .word_frequencies
+            .keys()
+            .filter(|lexeme| matches!(lexeme, Lexeme::Heteronym(_)))
+            .copied();
+        let reviewed = CardIndicator::TargetLanguage {
+            lexeme: heteronyms.next().unwrap(),
+        };
+        let fsrs_card = rs_fsrs::Card {
+            state: rs_fsrs::State::Review,
+            stability: 30.0,
+            difficulty: 4.5,
+            last_review: now - chrono::Duration::days(10),
+            due: now + chrono::Duration::days(20),
+            ..rs_fsrs::Card::new(now - chrono::Duration::days(40))
+        };
+        deck.cards
+            .insert(reviewed, CardStatus::Tracked(CardData::Added { fsrs_card }));
+
+        let exported = deck.export_anki_memory_states();
+        assert!(exported.starts_with(HEADER));
+        assert_eq!(exported.lines().count(), 4);
+
+        // Another device, where the word was only ever studied in Anki
+        let file = format!("{exported}not a word\t\t\t1.0\t5.0\t2026-01-01\t2025-12-01\n");
+        let fresh = Deck::default();
+        let import = fresh.import_anki_memory_states(file.clone());
+        assert_eq!(import.imported, 1);
+        assert_eq!(import.already_added, 0);
+        assert_eq!(import.skipped_lines.len(), 1);
+        let fresh = fresh.apply_event(&Timestamped {
+            timestamp: now,
+            within_device_events_index: 0,
+            event: import.event.unwrap(),
+        });
+        let Some(CardStatus::Tracked(CardData::Ghost { fsrs_card })) = fresh.cards.get(&reviewed)
+        else {
+            panic!("the imported word should be a ghost card");
+        };
+        assert_eq!(fsrs_card.stability, 30.0);
+        assert_eq!(fsrs_card.difficulty, 4.5);
+        assert_eq!(
+            format_date(fsrs_card.due),
+            format_date(now + chrono::Duration::days(20))
+        );
+        assert_eq!(fresh.export_anki_memory_states(), exported);
+
+        // The deck's own reviews win
+        let import = deck.import_anki_memory_states(file);
+        assert_eq!(import.event, None);
+        assert_eq!(import.already_added, 1);
+    }
+}
@@ -0,0 +1,96 @@
+//! Prefetching the audio for upcoming challenges, so they can be done offline. Where the audio is
+//! kept (OPFS, in the browser) and how it's downloaded is up to the client's `AudioStore`.
+
+use std::collections::BTreeSet;
+use std::future::Future;
+
+use futures::StreamExt as _;
+
+use crate::{AudioRequest, Deck, datetime_from_ms};
+
+/// Somewhere challenge audio can be downloaded to and kept
+pub trait AudioStore: Clone {
+    type Error: std::fmt::Debug;
+
+    /// The name the audio for `request` is stored under
+    fn cache_filename(&self, request: &AudioRequest) -> String;
+
+    /// Downloads the audio for `request`, unless it's already stored
+    fn fetch_and_cache(
+        &self,
+        request: &AudioRequest,
+    ) -> impl Future<Output = Result<(), Self::Error>>;
+
+    /// Removes all stored audio except the files in `keep_filenames`
+    fn cleanup_except(
+        &mut self,
+        keep_filenames: BTreeSet<String>,
+    ) -> impl Future<Output = Result<(), Self::Error>>;
+
+    /// Called between simulated days, so prefetching doesn't hog the client
+    fn pause(&self) -> impl Future<Output = ()>;
+
+    /// Whether prefetching should stop early
+    fn cancelled(&self) -> bool;
+}
+
+impl Deck {
+    /// Downloads the audio for the challenges the user is likely to see in the couple of days
+    /// after `timestamp_ms`, and removes everything else from `audio_store`
+    pub async fn cache_challenge_audio<A: AudioStore>(
+        &self,
+        mut audio_store: A,
+        timestamp_ms: f64,
+    ) {
+        const SIMULATION_DAYS: u32 = 2;
+        let mut requested_filenames = BTreeSet::new();
+        let mut simulation_iterator = self.simulate_usage(datetime_from_ms(timestamp_ms));
+        for _ in 0..SIMULATION_DAYS {
+            audio_store.pause().await;
+
+            // Check if aborted before progressing
+            if audio_store.cancelled() {
+                return;
+            }
+
+            let challenges;
+            (simulation_iterator, challenges) = simulation_iterator.next();
+
+            // get the audio files
+            requested_filenames.extend(
+                futures::stream::iter(challenges)
+                    .map(|challenge| {
+                        let request = challenge.audio_request();
+                        let audio_store = audio_store.clone();
+                        async move {
+                            let request = request?;
+                            // Check if aborted before processing
+                            if audio_store.cancelled() {
+                                return None;
+                            }
+
+                            let cache_filename = audio_store.cache_filename(&request);
+
+                            // Just try to fetch and cache, ignoring errors for individual requests
+                            let _ = audio_store.fetch_and_cache(&request).await;
+                            Some(cache_filename)
+                        }
+                    })
+                    .buffered(3)
+                    .filter_map(|x| async { x })
+                    .collect::<BTreeSet<_>>()
+                    .await,
+            );
+        }
+
+        // Check if aborted before cleanup
+        if audio_store.cancelled() {
+            return;
+        }
+
+        // Clean up any files that weren't in the requested set
+        if let Err(e) = audio_store.cleanup_except(requested_filenames).await {
+            log::error!("Failed to clean up audio cache: {e:?}");
+        }
+    }
+}
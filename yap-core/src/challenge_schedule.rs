@@ -0,0 +1,137 @@
+//! Challenge types banned at certain times of the week, e.g. "weekday mornings: no speaking" for
+//! someone who reviews on their commute. The schedules are one of the user's synced settings, and
+//! are combined with the challenge types they've banned outright by `banned_challenge_types_at`.
+
+use chrono::{Datelike as _, Duration, FixedOffset, Timelike as _};
+use serde::{Deserialize, Serialize};
+
+use crate::{ChallengeRequirements, datetime_from_ms};
+
+const MINUTES_PER_DAY: u32 = 24 * 60;
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[cfg_attr(target_arch = "wasm32", derive(tsify::Tsify))]
+#[cfg_attr(target_arch = "wasm32", tsify(into_wasm_abi, from_wasm_abi))]
+pub struct ChallengeTypeSchedule {
+    /// Shown to the user, e.g. "Commute"
+    pub name: String,
+    /// The days it starts on, as days since Monday (so 0 to 4 for weekdays). Empty for every day.
+    #[serde(default)]
+    pub days: Vec<u32>,
+    /// Minutes after local midnight. An `end_minute` before `start_minute` runs past midnight
+    /// into the next day.
+    pub start_minute: u32,
+    pub end_minute: u32,
+    pub banned: Vec<ChallengeRequirements>,
+}
+
+impl ChallengeTypeSchedule {
+    /// Whether `timestamp_ms` is within the schedule, for a user `utc_offset_minutes` from UTC
+    pub fn is_active(&self, timestamp_ms: f64, utc_offset_minutes: i32) -> bool {
+        let offset = FixedOffset::east_opt(utc_offset_minutes * 60)
+            .unwrap_or_else(|| FixedOffset::east_opt(0).unwrap());
+        let local = datetime_from_ms(timestamp_ms).with_timezone(&offset);
+        let minute = local.hour() * 60 + local.minute();
+        let starts_on = |date: chrono::DateTime<FixedOffset>| {
+            self.days.is_empty() || self.days.contains(&date.weekday().num_days_from_monday())
+        };
+
+        let start = self.start_minute % MINUTES_PER_DAY;
+        let end = self.end_minute % MINUTES_PER_DAY;
+        if start <= end {
+            start <= minute && minute < end && starts_on(local)
+        } else if minute >= start {
+            starts_on(local)
+        } else {
+            // In the part after midnight, so it started yesterday
+            minute < end && starts_on(local - Duration::days(1))
+        }
+    }
+}
+
+/// The challenge types banned at `timestamp_ms`: `always_banned`, plus those of every schedule
+/// that's active then
+pub fn banned_challenge_types_at(
+    always_banned: &[ChallengeRequirements],
+    schedules: &[ChallengeTypeSchedule],
+    timestamp_ms: f64,
+    utc_offset_minutes: i32,
+) -> Vec<ChallengeRequirements> {
+    let mut banned = always_banned.to_vec();
+    for schedule in schedules {
+        if schedule.is_active(timestamp_ms, utc_offset_minutes) {
+            banned.extend(schedule.banned.iter().copied());
+        }
+    }
+    banned.sort();
+    banned.dedup();
+    banned
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn timestamp_ms(day: u32, hour: u32, minute: u32) -> f64 {
+        // Monday 2024-01-01, in UTC
+        chrono::NaiveDate::from_ymd_opt(2024, 1, day)
+            .unwrap()
+            .and_hms_opt(hour, minute, 0)
+            .unwrap()
+            .and_utc()
+            .timestamp_millis() as f64
+    }
+
+    #[test]
+    fn test_schedules_follow_the_local_week() {
+        let commute = ChallengeTypeSchedule {
+            name: "Commute".to_string(),
+            days: vec![0, 1, 2, 3, 4],
+            start_minute: 7 * 60,
+            end_minute: 9 * 60,
+            banned: vec![ChallengeRequirements::Speaking],
+        };
+        let night = ChallengeTypeSchedule {
+            name: "Night".to_string(),
+            days: vec![4],
+            start_minute: 22 * 60,
+            end_minute: 6 * 60,
+            banned: vec![
+                ChallengeRequirements::Listening,
+                ChallengeRequirements::Speaking,
+            ],
+        };
+        let schedules = [commute.clone(), night.clone()];
+
+        assert!(commute.is_active(timestamp_ms(1, 8, 0), 0));
+        assert!(!commute.is_active(timestamp_ms(1, 9, 0), 0));
+        // Saturday
+        assert!(!commute.is_active(timestamp_ms(6, 8, 0), 0));
+        // 8:00 in UTC+2 is 6:00 UTC
+        assert!(commute.is_active(timestamp_ms(1, 6, 0), 120));
+        // Sunday 23:00 UTC is Monday 8:00 in UTC+9
+        assert!(commute.is_active(timestamp_ms(7, 23, 0), 9 * 60));
+
+        // Friday night runs into Saturday morning, but Thursday night isn't included
+        assert!(night.is_active(timestamp_ms(5, 23, 0), 0));
+        assert!(night.is_active(timestamp_ms(6, 2, 0), 0));
+        assert!(!night.is_active(timestamp_ms(5, 2, 0), 0));
+
+        assert_eq!(
+            banned_challenge_types_at(
+                &[ChallengeRequirements::Speaking],
+                &schedules,
+                timestamp_ms(6, 2, 0),
+                0,
+            ),
+            vec![
+                ChallengeRequirements::Listening,
+                ChallengeRequirements::Speaking,
+            ]
+        );
+        assert_eq!(
+            banned_challenge_types_at(&[], &schedules, timestamp_ms(3, 12, 0), 0),
+            vec![]
+        );
+    }
+}
@@ -0,0 +1,569 @@
+use std::collections::BTreeSet;
+
+use language_utils::{
+    Heteronym, Language, Lexeme, Literal, PatternPosition, SentenceSource, TtsProvider, TtsRequest,
+    language_pack::LanguagePack, transcription_challenge, transcription_challenge::DictationLevel,
+    ui_strings,
+};
+use lasso::Spur;
+
+use crate::{
+    AudioRequest, CardContent, CardData, CardIndicator, CardStatus, Challenge, Deck, Rating,
+    ReviewInfo, TranscribeComprehensibleSentence, WordAudio,
+};
+
+/// Why a challenge couldn't be built for a card. These all mean the deck and the language pack
+/// disagree about something, so the card is skipped rather than taking the whole app down.
+#[derive(Debug, Clone, thiserror::Error, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(target_arch = "wasm32", derive(tsify::Tsify))]
+#[cfg_attr(target_arch = "wasm32", tsify(into_wasm_abi, from_wasm_abi))]
+#[serde(tag = "type")]
+pub enum ChallengeError {
+    #[error("Card is not in the deck")]
+    CardNotInDeck,
+
+    #[error("Heteronym {heteronym:?} was in the deck, but was not found in dictionary")]
+    MissingDictionaryEntry { heteronym: Heteronym<String> },
+
+    #[error("Multiword term {multiword:?} was in the deck, but was not found in phrasebook")]
+    MissingPhrasebookEntry { multiword: String },
+
+    #[error("Word {word:?} has no pronunciation")]
+    MissingPronunciation { word: String },
+
+    #[error("Pronunciation {pronunciation:?} was in the deck, but no words have it")]
+    MissingPronunciationWords { pronunciation: String },
+
+    #[error(
+        "Pattern {pattern} with position {position:?} was in the deck, but was not found in pronunciation guides"
+    )]
+    MissingPronunciationGuide {
+        pattern: String,
+        position: PatternPosition,
+    },
+
+    #[error("Sentence {sentence:?} has no translations")]
+    MissingTranslations { sentence: String },
+
+    #[error("Multiword lexemes are not supported in ListeningLexeme cards yet: {multiword:?}")]
+    UnsupportedListeningMultiword { multiword: String },
+}
+
+/// A card that was skipped because its challenge couldn't be built
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(target_arch = "wasm32", derive(tsify::Tsify))]
+#[cfg_attr(target_arch = "wasm32", tsify(into_wasm_abi, from_wasm_abi))]
+pub struct ChallengeErrorReport {
+    pub card: CardIndicator<String>,
+    pub error: ChallengeError,
+    /// The error's message, for logging
+    pub message: String,
+}
+
+/// ListeningLexeme cards less stable than this (in days) only have to transcribe the word itself
+const SINGLE_WORD_MAX_STABILITY: f64 = 3.0;
+/// ListeningLexeme cards less stable than this (in days) transcribe a short phrase around the word
+const PHRASE_CHUNK_MAX_STABILITY: f64 = 14.0;
+/// How many words on each side of the tested word are part of a phrase chunk
+const PHRASE_CHUNK_RADIUS: usize = 1;
+
+pub(crate) fn dictation_level_for_card(card_status: Option<&CardStatus>) -> DictationLevel {
+    let Some(CardStatus::Tracked(CardData::Added { fsrs_card } | CardData::Ghost { fsrs_card })) =
+        card_status
+    else {
+        return DictationLevel::SingleWord;
+    };
+    if fsrs_card.state == rs_fsrs::State::New || fsrs_card.stability < SINGLE_WORD_MAX_STABILITY {
+        DictationLevel::SingleWord
+    } else if fsrs_card.stability < PHRASE_CHUNK_MAX_STABILITY {
+        DictationLevel::PhraseChunk
+    } else {
+        DictationLevel::FullSentence
+    }
+}
+
+/// Easier dictation levels earn less credit on the ListeningLexeme card
+pub(crate) fn scale_listening_rating(level: DictationLevel, rating: Rating) -> Rating {
+    match (level, rating) {
+        (_, Rating::Again) => Rating::Again,
+        (DictationLevel::SingleWord, _) => Rating::Hard,
+        (DictationLevel::PhraseChunk, Rating::Remembered | Rating::Easy) => Rating::Good,
+        (_, rating) => rating,
+    }
+}
+
+/// How many sound-alike words the word bank offers for each word to transcribe
+const WORD_BANK_DISTRACTORS_PER_WORD: usize = 3;
+
+/// The words the user is asked to transcribe, plus words that are pronounced the same way (e.g.
+/// "vert" for "verre"), so picking from the bank still tests listening. Distractors copy the
+/// capitalization of the word they stand in for, so they can't be told apart by case.
+pub(crate) fn word_bank(
+    parts: &[transcription_challenge::Part],
+    language_pack: &LanguagePack,
+) -> Vec<String> {
+    let mut word_bank = BTreeSet::new();
+    for part in parts {
+        let transcription_challenge::Part::AskedToTranscribe { parts } = part else {
+            continue;
+        };
+        for literal in parts {
+            word_bank.insert(literal.text.clone());
+
+            let Some(heteronym) = &literal.heteronym else {
+                continue;
+            };
+            let Some(word) = language_pack.rodeo.get(&heteronym.word) else {
+                continue;
+            };
+            let homophones = language_pack
+                .word_to_pronunciation
+                .get(&word)
+                .and_then(|pronunciation| language_pack.pronunciation_to_words.get(pronunciation))
+                .into_iter()
+                .flatten()
+                .filter(|&&homophone| homophone != word)
+                .take(WORD_BANK_DISTRACTORS_PER_WORD);
+            let capitalized = literal.text.chars().next().is_some_and(char::is_uppercase);
+            for homophone in homophones {
+                let homophone = language_pack.rodeo.resolve(homophone);
+                word_bank.insert(if capitalized {
+                    capitalize_first(homophone)
+                } else {
+                    homophone.to_string()
+                });
+            }
+        }
+    }
+    word_bank.into_iter().collect()
+}
+
+fn capitalize_first(word: &str) -> String {
+    let mut chars = word.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+/// The (id, title) of each movie with the sentence in it, for showing where it came from
+pub(crate) fn movie_titles(
+    source: Option<&SentenceSource>,
+    language_pack: &LanguagePack,
+) -> Vec<(String, String)> {
+    source
+        .into_iter()
+        .flat_map(|source| &source.movie_ids)
+        .filter_map(|movie_id| {
+            language_pack
+                .movies
+                .get(movie_id)
+                .map(|metadata| (movie_id.clone(), metadata.title.clone()))
+        })
+        .collect()
+}
+
+/// Per-word audio for a sentence whose audio text is its literals joined together
+pub(crate) fn word_audio(
+    literals: &[Literal<Spur>],
+    language: Language,
+    provider: TtsProvider,
+    rodeo: &lasso::RodeoReader,
+) -> Vec<WordAudio> {
+    let mut offset = 0;
+    let mut word_audio = Vec::new();
+    for literal in literals {
+        let text = rodeo.resolve(&literal.text);
+        let start = offset;
+        let end = start + text.encode_utf16().count();
+        offset = end + rodeo.resolve(&literal.whitespace).encode_utf16().count();
+
+        if literal.heteronym.is_some() {
+            word_audio.push(WordAudio {
+                start,
+                end,
+                audio: AudioRequest {
+                    request: TtsRequest {
+                        text: text.to_string(),
+                        language,
+                    },
+                    provider,
+                },
+            });
+        }
+    }
+    word_audio
+}
+
+/// Splits a sentence into transcription parts for the given level. Consecutive words the user has
+/// to transcribe are grouped together, and everything else (including punctuation) is provided.
+pub(crate) fn dictation_parts(
+    literals: &[Literal<Spur>],
+    target: Heteronym<Spur>,
+    level: DictationLevel,
+    rodeo: &lasso::RodeoReader,
+) -> Vec<transcription_challenge::Part> {
+    let word_indices: Vec<usize> = literals
+        .iter()
+        .enumerate()
+        .filter(|(_, literal)| literal.heteronym.is_some())
+        .map(|(i, _)| i)
+        .collect();
+    let target_position = word_indices
+        .iter()
+        .position(|&i| literals[i].heteronym == Some(target));
+
+    let asked: BTreeSet<usize> = match (level, target_position) {
+        (DictationLevel::SingleWord, Some(position)) => BTreeSet::from([word_indices[position]]),
+        (DictationLevel::PhraseChunk, Some(position)) => {
+            let start = position.saturating_sub(PHRASE_CHUNK_RADIUS);
+            let end = (position + PHRASE_CHUNK_RADIUS).min(word_indices.len() - 1);
+            word_indices[start..=end].iter().copied().collect()
+        }
+        // Also covers the target somehow not being in the sentence
+        _ => word_indices.iter().copied().collect(),
+    };
+
+    let mut parts = Vec::new();
+    let mut current_words: Vec<Literal<String>> = Vec::new();
+    for (i, literal) in literals.iter().enumerate() {
+        let resolved = literal.resolve(rodeo);
+        if asked.contains(&i) {
+            current_words.push(resolved);
+        } else {
+            if !current_words.is_empty() {
+                parts.push(transcription_challenge::Part::AskedToTranscribe {
+                    parts: std::mem::take(&mut current_words),
+                });
+            }
+            parts.push(transcription_challenge::Part::Provided { part: resolved });
+        }
+    }
+    if !current_words.is_empty() {
+        parts.push(transcription_challenge::Part::AskedToTranscribe {
+            parts: current_words,
+        });
+    }
+    parts
+}
+
+impl Deck {
+    pub(crate) fn get_homophonous_listening_challenge(
+        &self,
+        review_info: &ReviewInfo,
+        card_indicator: CardIndicator<Spur>,
+        is_new: bool,
+        warmup: bool,
+        pronunciation: Spur,
+    ) -> Result<Challenge<Spur>, ChallengeError> {
+        let Some(words) = self
+            .context
+            .language_pack
+            .pronunciation_to_words
+            .get(&pronunciation)
+            .filter(|words| !words.is_empty())
+        else {
+            return Err(ChallengeError::MissingPronunciationWords {
+                pronunciation: self
+                    .context
+                    .language_pack
+                    .rodeo
+                    .resolve(&pronunciation)
+                    .to_string(),
+            });
+        };
+
+        let flashcard = {
+            let listening_prefix = self
+                .context
+                .language_pack
+                .ui_string(self.context.target_language, ui_strings::LISTENING_PREFIX)
+                .unwrap_or_default()
+                .to_string();
+            let possible_words: Vec<(bool, Spur)> = {
+                let possible_words = words.iter().copied().collect::<BTreeSet<_>>();
+
+                // figure out which of those words the user knows
+                possible_words
+                    .iter()
+                    .map(|word| {
+                        // Check if any lexeme for this word is known
+                        let word_known = self
+                            .context
+                            .language_pack
+                            .pronunciation_to_lexemes(&pronunciation)
+                            .filter(|(w, _)| w == word)
+                            .any(|(_, lexeme)| {
+                                self.cards
+                                    .get(&CardIndicator::TargetLanguage { lexeme })
+                                    .is_some_and(|status| match status {
+                                        CardStatus::Tracked(CardData::Added { fsrs_card })
+                                        | CardStatus::Tracked(CardData::Ghost { fsrs_card }) => {
+                                            fsrs_card.state != rs_fsrs::State::New
+                                        }
+                                        _ => false,
+                                    })
+                            });
+                        (word_known, *word)
+                    })
+                    .collect()
+            };
+            let audio = AudioRequest {
+                request: TtsRequest {
+                    text: format!(
+                        "{}... \"{}\".",
+                        listening_prefix,
+                        self.context.language_pack.rodeo.resolve(
+                            &possible_words
+                                .iter()
+                                .find(|(known, _)| *known)
+                                .or(possible_words.first())
+                                .map(|(_, word)| *word)
+                                .unwrap_or(pronunciation)
+                        )
+                    ),
+                    language: self.context.target_language,
+                },
+                provider: self.preferred_tts_provider(TtsProvider::Google),
+            };
+            Challenge::<Spur>::FlashCardReview {
+                indicator: card_indicator,
+                audio: Some(audio),
+                content: CardContent::Listening {
+                    pronunciation,
+                    possible_words,
+                },
+                is_new,
+                listening_prefix: Some(listening_prefix),
+                warmup,
+            }
+        };
+        if is_new || warmup {
+            Ok(flashcard)
+        } else {
+            let mut heteronyms = words
+                .iter()
+                .filter_map(|word| self.context.language_pack.words_to_heteronyms.get(word))
+                .flatten()
+                .copied()
+                .filter(|heteronym| self.lexeme_known(&Lexeme::Heteronym(*heteronym)))
+                .collect::<Vec<_>>();
+            heteronyms
+                .sort_by_key(|heteronym| self.stats.words_listened_to.get(heteronym).unwrap_or(&0));
+
+            if let Some((target_heteronym, sentence)) = heteronyms
+                .iter()
+                .filter_map(|heteronym| {
+                    let comprehensible_lexemes =
+                        review_info.get_comprehensible_written_lexemes(self);
+                    let sentence = self.get_comprehensible_sentence_containing(
+                        Some(&Lexeme::Heteronym(*heteronym)),
+                        comprehensible_lexemes,
+                        &self.stats.sentences_reviewed,
+                        &self.context.language_pack,
+                    )?;
+                    Some((*heteronym, sentence))
+                })
+                .next()
+            {
+                let parts: Vec<transcription_challenge::Part> = sentence
+                    .target_language_literals
+                    .iter()
+                    .map(|literal| {
+                        if let Some(ref heteronym) = literal.heteronym
+                            && heteronym == &target_heteronym
+                        {
+                            transcription_challenge::Part::AskedToTranscribe {
+                                parts: vec![literal.resolve(&self.context.language_pack.rodeo)],
+                            }
+                        } else {
+                            transcription_challenge::Part::Provided {
+                                part: literal.resolve(&self.context.language_pack.rodeo),
+                            }
+                        }
+                    })
+                    .collect();
+
+                let source = self
+                    .context
+                    .language_pack
+                    .sentence_sources
+                    .get(&sentence.target_language)
+                    .cloned();
+                let movie_titles = movie_titles(source.as_ref(), &self.context.language_pack);
+
+                let native_language = sentence.native_languages.first().ok_or_else(|| {
+                    ChallengeError::MissingTranslations {
+                        sentence: self
+                            .context
+                            .language_pack
+                            .rodeo
+                            .resolve(&sentence.target_language)
+                            .to_string(),
+                    }
+                })?;
+
+                let word_bank = word_bank(&parts, &self.context.language_pack);
+                Ok(Challenge::TranscribeComprehensibleSentence(
+                    TranscribeComprehensibleSentence {
+                        target_language: sentence.target_language,
+                        native_language: native_language.to_string(),
+                        parts,
+                        audio: AudioRequest {
+                            request: TtsRequest {
+                                text: self
+                                    .context
+                                    .language_pack
+                                    .rodeo
+                                    .resolve(&sentence.target_language)
+                                    .to_string(),
+                                language: self.context.target_language,
+                            },
+                            provider: self.preferred_tts_provider(TtsProvider::Google),
+                        },
+                        source,
+                        movie_titles,
+                        level: DictationLevel::SingleWord,
+                        word_audio: word_audio(
+                            &sentence.target_language_literals,
+                            self.context.target_language,
+                            self.preferred_tts_provider(TtsProvider::Google),
+                            &self.context.language_pack.rodeo,
+                        ),
+                        word_bank,
+                        explanation: review_info.explanation(&sentence, self),
+                    },
+                ))
+            } else {
+                Ok(flashcard)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use language_utils::PartOfSpeech;
+
+    fn card(state: rs_fsrs::State, stability: f64) -> CardData {
+        let mut fsrs_card = rs_fsrs::Card::new(chrono::Utc::now());
+        fsrs_card.state = state;
+        fsrs_card.stability = stability;
+        CardData::Added { fsrs_card }
+    }
+
+    #[test]
+    fn test_dictation_level_grows_with_stability() {
+        use DictationLevel::*;
+        use rs_fsrs::State::{New, Review};
+
+        let CardData::Added { fsrs_card } = card(Review, 20.0) else {
+            unreachable!()
+        };
+        let ghost = CardData::Ghost { fsrs_card };
+        let cases = [
+            (None, SingleWord),
+            (Some(CardStatus::Unadded(crate::Unadded {})), SingleWord),
+            (Some(CardStatus::Tracked(card(New, 20.0))), SingleWord),
+            (Some(CardStatus::Tracked(card(Review, 2.9))), SingleWord),
+            (Some(CardStatus::Tracked(card(Review, 3.0))), PhraseChunk),
+            (Some(CardStatus::Tracked(card(Review, 13.9))), PhraseChunk),
+            (Some(CardStatus::Tracked(card(Review, 14.0))), FullSentence),
+            (Some(CardStatus::Tracked(ghost)), FullSentence),
+        ];
+        for (card_status, level) in cases {
+            assert_eq!(
+                dictation_level_for_card(card_status.as_ref()),
+                level,
+                "{card_status:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_easier_dictation_levels_earn_less_credit() {
+        use DictationLevel::*;
+        use Rating::*;
+
+        let cases = [
+            (SingleWord, [Again, Hard, Hard, Hard, Hard]),
+            (PhraseChunk, [Again, Good, Hard, Good, Good]),
+            (FullSentence, [Again, Remembered, Hard, Good, Easy]),
+        ];
+        for (level, scaled) in cases {
+            for (rating, scaled) in [Again, Remembered, Hard, Good, Easy]
+                .into_iter()
+                .zip(scaled)
+            {
+                assert_eq!(
+                    scale_listening_rating(level, rating),
+                    scaled,
+                    "{rating:?} at {level:?}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_dictation_parts_ask_for_the_words_around_the_target() {
+        use DictationLevel::*;
+
+        let mut rodeo = lasso::Rodeo::new();
+        let word = |text: &str, pos| Literal {
+            text: text.to_string(),
+            whitespace: " ".to_string(),
+            heteronym: Some(Heteronym {
+                word: text.to_lowercase(),
+                lemma: text.to_lowercase(),
+                pos,
+            }),
+            morph: None,
+        };
+        let literals: Vec<Literal<Spur>> = [
+            word("Le", PartOfSpeech::Det),
+            word("chat", PartOfSpeech::Noun),
+            word("noir", PartOfSpeech::Adj),
+            word("dort", PartOfSpeech::Verb),
+            Literal {
+                text: ".".to_string(),
+                whitespace: String::new(),
+                heteronym: None,
+                morph: None,
+            },
+        ]
+        .iter()
+        .map(|literal| literal.get_or_intern(&mut rodeo))
+        .collect();
+        let heteronym = |index: usize| literals[index].heteronym.unwrap();
+        let missing = Heteronym {
+            word: rodeo.get_or_intern("chien"),
+            lemma: rodeo.get_or_intern("chien"),
+            pos: PartOfSpeech::Noun,
+        };
+        let rodeo = rodeo.into_reader();
+
+        // Words to transcribe are in brackets
+        let cases = [
+            (heteronym(2), SingleWord, "Le chat [noir] dort ."),
+            (heteronym(2), PhraseChunk, "Le [chat noir dort] ."),
+            (heteronym(0), PhraseChunk, "[Le chat] noir dort ."),
+            (heteronym(3), PhraseChunk, "Le chat [noir dort] ."),
+            (heteronym(2), FullSentence, "[Le chat noir dort] ."),
+            (missing, SingleWord, "[Le chat noir dort] ."),
+        ];
+        for (target, level, expected) in cases {
+            let parts: Vec<String> = dictation_parts(&literals, target, level, &rodeo)
+                .into_iter()
+                .map(|part| match part {
+                    transcription_challenge::Part::AskedToTranscribe { parts } => {
+                        let words: Vec<_> = parts.into_iter().map(|part| part.text).collect();
+                        format!("[{}]", words.join(" "))
+                    }
+                    transcription_challenge::Part::Provided { part } => part.text,
+                })
+                .collect();
+            assert_eq!(parts.join(" "), expected, "{level:?}");
+        }
+    }
+}
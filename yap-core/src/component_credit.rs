@@ -0,0 +1,316 @@
+//! Credit for the words of a multiword term. Remembering "se rendre compte" says something about
+//! "rendre" and "compte" too, just less than reviewing them would, so their cards move part of the
+//! way to where a review would take them. Forgetting the term pulls them back a little.
+//!
+//! Like `XpFormula`, each translation event records the rule it was scored under, so changing the
+//! weights means adding a new `ComponentCredit` variant and older events replay the same way.
+//! Users can turn the credit off with `LanguageEventContent::SetComponentCredit`.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use chrono::{DateTime, Utc};
+use language_utils::{Lexeme, text_cleanup};
+use lasso::Spur;
+use serde::{Deserialize, Serialize};
+#[cfg(target_arch = "wasm32")]
+use wasm_bindgen::prelude::*;
+
+use crate::{
+    CardData, CardIndicator, Deck, DeckEvent, DeckState, LanguageEvent, LanguageEventContent,
+    Rating, SentenceReviewIndicator, SentenceReviewResult, schedule_review,
+};
+
+#[derive(Copy, Clone, Debug, Serialize, Deserialize, PartialEq, Eq, Ord, PartialOrd)]
+#[cfg_attr(target_arch = "wasm32", derive(tsify::Tsify))]
+#[cfg_attr(target_arch = "wasm32", tsify(into_wasm_abi, from_wasm_abi))]
+pub enum ComponentCredit {
+    /// Reviewing a term doesn't count for its words. Events that don't record a rule use this, as
+    /// they're from before there was any other.
+    None,
+    /// A remembered term moves its words' cards `PARTIAL_V1_SUCCESS_WEIGHT` of the way to where a
+    /// successful review would, and a forgotten one `PARTIAL_V1_FAILURE_WEIGHT` of the way to
+    /// where a lapse would
+    PartialV1,
+}
+
+/// The rule new translation events are scored with
+pub(crate) const CURRENT_COMPONENT_CREDIT: ComponentCredit = ComponentCredit::PartialV1;
+
+const PARTIAL_V1_SUCCESS_WEIGHT: f64 = 0.3;
+const PARTIAL_V1_FAILURE_WEIGHT: f64 = 0.1;
+
+impl ComponentCredit {
+    fn weight(self, remembered: bool) -> f64 {
+        match self {
+            ComponentCredit::None => 0.0,
+            ComponentCredit::PartialV1 if remembered => PARTIAL_V1_SUCCESS_WEIGHT,
+            ComponentCredit::PartialV1 => PARTIAL_V1_FAILURE_WEIGHT,
+        }
+    }
+}
+
+/// `before`, moved `weight` of the way to `after`. A partial review isn't a review, so the review
+/// count, lapses and last review are left alone, and credit never brings the due date closer (nor
+/// a penalty push it further out).
+fn partial_review(
+    before: &rs_fsrs::Card,
+    after: &rs_fsrs::Card,
+    weight: f64,
+    remembered: bool,
+) -> rs_fsrs::Card {
+    let due_shift = (after.due - before.due).num_milliseconds() as f64 * weight;
+    let due = before.due + chrono::Duration::milliseconds(due_shift as i64);
+    rs_fsrs::Card {
+        stability: before.stability + (after.stability - before.stability) * weight,
+        difficulty: before.difficulty + (after.difficulty - before.difficulty) * weight,
+        due: if remembered {
+            due.max(before.due)
+        } else {
+            due.min(before.due)
+        },
+        ..before.clone()
+    }
+}
+
+impl DeckState {
+    /// Gives the words of the multiword terms in a translation challenge credit under `rule`.
+    /// Words the challenge reviewed themselves already got a full review, so they're skipped.
+    pub(crate) fn credit_components(
+        &mut self,
+        rule: ComponentCredit,
+        review: &SentenceReviewIndicator,
+        timestamp: DateTime<Utc>,
+    ) {
+        let reviewed = self.translation_outcomes(review);
+        for (term, remembered) in &reviewed {
+            let Lexeme::Multiword(term) = term else {
+                continue;
+            };
+            let weight = rule.weight(*remembered);
+            if weight == 0.0 {
+                continue;
+            }
+            for component in self.tracked_components(term) {
+                if reviewed.contains_key(&component) {
+                    continue;
+                }
+                let card = CardIndicator::TargetLanguage { lexeme: component };
+                let Some(CardData::Added { fsrs_card } | CardData::Ghost { fsrs_card }) =
+                    self.cards.get_mut(&card)
+                else {
+                    continue;
+                };
+                let rating = if *remembered {
+                    Rating::Remembered
+                } else {
+                    Rating::Again
+                };
+                let after = schedule_review(
+                    &self.fsrs,
+                    self.scheduler,
+                    self.scheduling_profile,
+                    fsrs_card.clone(),
+                    rating,
+                    timestamp,
+                );
+                *fsrs_card = partial_review(fsrs_card, &after, weight, *remembered);
+            }
+        }
+    }
+
+    /// Each lexeme a translation challenge rated, and whether it was remembered. Like
+    /// `process_event`, re-practicing a favorite only counts what was forgotten.
+    fn translation_outcomes(
+        &self,
+        review: &SentenceReviewIndicator,
+    ) -> BTreeMap<Lexeme<Spur>, bool> {
+        let SentenceReviewIndicator::TargetToNative {
+            challenge_sentence,
+            result,
+            favorite_practice,
+        } = review;
+        let pack = &self.context.language_pack;
+        let intern_all = |lexemes: &BTreeSet<Lexeme<String>>| {
+            lexemes
+                .iter()
+                .filter_map(|lexeme| self.context.intern_lexeme(lexeme))
+                .collect::<Vec<_>>()
+        };
+
+        let (remembered, forgotten) = match result {
+            SentenceReviewResult::Perfect {
+                lexemes_needed_hint,
+                generated_sentence_lexemes,
+            } => {
+                let cleaned_sentence = text_cleanup::cleanup_sentence(
+                    challenge_sentence.clone(),
+                    self.context.target_language,
+                );
+                let lexemes = match pack
+                    .rodeo
+                    .get(&cleaned_sentence)
+                    .and_then(|sentence| pack.sentences_to_lexemes.get(&sentence))
+                {
+                    Some(lexemes) => lexemes.clone(),
+                    None => intern_all(generated_sentence_lexemes),
+                };
+                (lexemes, intern_all(lexemes_needed_hint))
+            }
+            SentenceReviewResult::Wrong {
+                lexemes_remembered,
+                lexemes_forgotten,
+                lexemes_needed_hint,
+                ..
+            } => {
+                let mut forgotten = intern_all(lexemes_forgotten);
+                forgotten.extend(intern_all(lexemes_needed_hint));
+                (intern_all(lexemes_remembered), forgotten)
+            }
+        };
+
+        let mut outcomes = BTreeMap::new();
+        if !favorite_practice {
+            outcomes.extend(remembered.into_iter().map(|lexeme| (lexeme, true)));
+        }
+        outcomes.extend(forgotten.into_iter().map(|lexeme| (lexeme, false)));
+        outcomes
+    }
+
+    /// The cards for the words of `term` that the user has reviewed before. A word with several
+    /// heteronyms is taken to be the most common one the user has a card for.
+    fn tracked_components(&self, term: &Spur) -> BTreeSet<Lexeme<Spur>> {
+        let pack = &self.context.language_pack;
+        pack.rodeo
+            .resolve(term)
+            .split_whitespace()
+            .filter_map(|word| {
+                pack.words_to_heteronyms
+                    .get(&pack.rodeo.get(word)?)?
+                    .iter()
+                    .map(|heteronym| Lexeme::Heteronym(*heteronym))
+                    .filter(|lexeme| {
+                        matches!(
+                            self.cards.get(&CardIndicator::TargetLanguage { lexeme: *lexeme }),
+                            Some(CardData::Added { fsrs_card } | CardData::Ghost { fsrs_card })
+                                if fsrs_card.state != rs_fsrs::State::New
+                        )
+                    })
+                    .max_by_key(|lexeme| pack.word_frequencies.get(lexeme).map(|f| f.count))
+            })
+            .collect()
+    }
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+impl Deck {
+    /// Turns credit for the words of multiword terms (see the module docs) on or off
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn set_component_credit(&self, enabled: bool) -> Option<DeckEvent> {
+        (enabled != self.component_credit).then_some(DeckEvent::Language(LanguageEvent {
+            target_language: self.context.target_language,
+            native_language: self.context.native_language,
+            content: LanguageEventContent::SetComponentCredit { enabled },
+        }))
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn get_component_credit(&self) -> bool {
+        self.component_credit
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::CardStatus;
+    use weapon::AppState;
+    use weapon::data_model::Timestamped;
+
+    #[test]
+    fn test_remembering_a_term_credits_its_words() {
+        let now = DateTime::<Utc>::from_timestamp(1_760_000_000, 0).unwrap();
+        let mut deck = Deck::default();
+        let pack = deck.context.language_pack.clone();
+        // A term whose words all have a heteronym, like "bien sûr"
+        let (term, words) = pack
+            .word_frequencies
+            .keys()
+            .find_map(|lexeme| {
+                let Lexeme::Multiword(term) = lexeme else {
+                    return None;
+                };
+                let words = pack
+                    .rodeo
+                    .resolve(term)
+                    .split_whitespace()
+                    .map(|word| {
+                        let heteronyms = pack.words_to_heteronyms.get(&pack.rodeo.get(word)?)?;
+                        Some(CardIndicator::TargetLanguage {
+                            lexeme: Lexeme::Heteronym(*heteronyms.first()?),
+                        })
+                    })
+                    .collect::<Option<Vec<_>>>()?;
+                Some((pack.rodeo.resolve(term).to_string(), words))
+            })
+            .unwrap();
+        let fsrs_card = rs_fsrs::Card {
+            state: rs_fsrs::State::Review,
+            stability: 10.0,
+            difficulty: 5.0,
+            last_review: now - chrono::Duration::days(5),
+            due: now + chrono::Duration::days(5),
+            ..rs_fsrs::Card::new(now - chrono::Duration::days(20))
+        };
+        for word in &words {
+            deck.cards.insert(
+                *word,
+                CardStatus::Tracked(CardData::Added {
+                    fsrs_card: fsrs_card.clone(),
+                }),
+            );
+        }
+        let stability = |deck: &Deck, word| match deck.cards.get(word) {
+            Some(CardStatus::Tracked(CardData::Added { fsrs_card })) => fsrs_card.stability,
+            _ => panic!("the word should still be added"),
+        };
+        let apply = |deck: Deck, event| {
+            deck.apply_event(&Timestamped {
+                timestamp: now,
+                within_device_events_index: 0,
+                event,
+            })
+        };
+        let translate = |deck: &Deck| {
+            deck.translate_sentence_wrong(
+                format!("{term}, oui."),
+                "...".to_string(),
+                vec![Lexeme::Multiword(term.clone())],
+                vec![],
+                vec![],
+                None,
+            )
+            .unwrap()
+        };
+
+        let full_review = schedule_review(
+            &deck.fsrs,
+            deck.scheduler,
+            deck.scheduling_profile,
+            fsrs_card.clone(),
+            Rating::Remembered,
+            now,
+        );
+        let credited = apply(deck.clone(), translate(&deck));
+        for word in &words {
+            let credited_stability = stability(&credited, word);
+            assert!(credited_stability > 10.0 && credited_stability < full_review.stability);
+        }
+
+        let disabled = apply(deck.clone(), deck.set_component_credit(false).unwrap());
+        assert_eq!(disabled.set_component_credit(false), None);
+        let disabled = apply(disabled.clone(), translate(&disabled));
+        for word in &words {
+            assert_eq!(stability(&disabled, word), 10.0);
+        }
+    }
+}
@@ -0,0 +1,144 @@
+//! Words the user wrote in place of the one they heard in transcription challenges, like
+//! "pendant" for "depuis". The most frequent ones go to the autograder in a
+//! `language_utils::autograde::MistakeDigest` (see `Deck::get_mistake_history`), so it can point
+//! out a habit rather than grading each slip on its own.
+
+use std::collections::BTreeMap;
+
+use language_utils::autograde::{Confusion, MistakeDigest};
+use language_utils::transcription_challenge::WordGrade;
+
+/// Only this many different confusions are kept. Once it's full, the least frequent one makes
+/// room for a new one, so new habits still show up.
+const MAX_DISTINCT: usize = 200;
+
+/// Confusions that only happened once are as likely to be slips, so they're left out of digests
+const MIN_DIGEST_COUNT: u32 = 2;
+
+#[derive(Clone, Debug, Default)]
+pub(crate) struct Confusions {
+    /// How many times the user wrote the second word when they heard the first
+    counts: BTreeMap<(String, String), u32>,
+}
+
+impl Confusions {
+    /// Records the word graded as `grade` if the user wrote a different word than `heard`
+    pub(crate) fn record(&mut self, heard: &str, grade: &WordGrade) {
+        let (WordGrade::Incorrect { wrote: Some(wrote) }
+        | WordGrade::PhoneticallyIdenticalButContextuallyIncorrect { wrote: Some(wrote) }
+        | WordGrade::PhoneticallySimilarButContextuallyIncorrect { wrote: Some(wrote) }) = grade
+        else {
+            return;
+        };
+        let heard = heard.trim().to_lowercase();
+        let wrote = wrote.trim().to_lowercase();
+        if heard.is_empty() || wrote.is_empty() || heard == wrote {
+            return;
+        }
+
+        let key = (heard, wrote);
+        if let Some(count) = self.counts.get_mut(&key) {
+            *count += 1;
+            return;
+        }
+        if self.counts.len() >= MAX_DISTINCT
+            && let Some(rarest) = self
+                .counts
+                .iter()
+                .min_by_key(|(_, count)| **count)
+                .map(|(key, _)| key.clone())
+        {
+            self.counts.remove(&rarest);
+        }
+        self.counts.insert(key, 1);
+    }
+
+    /// The most frequent confusions, capped to what the autograder accepts
+    pub(crate) fn digest(&self) -> MistakeDigest {
+        let mut confusions: Vec<Confusion> = self
+            .counts
+            .iter()
+            .filter(|(_, count)| **count >= MIN_DIGEST_COUNT)
+            .map(|((heard, wrote), count)| Confusion {
+                expected: heard.clone(),
+                wrote: wrote.clone(),
+                count: *count,
+            })
+            .collect();
+        confusions.sort_by_key(|confusion| std::cmp::Reverse(confusion.count));
+        MistakeDigest { confusions }.capped()
+    }
+
+    /// Every tracked confusion, for saving in a snapshot
+    pub(crate) fn entries(&self) -> Vec<(String, String, u32)> {
+        self.counts
+            .iter()
+            .map(|((heard, wrote), count)| (heard.clone(), wrote.clone(), *count))
+            .collect()
+    }
+
+    pub(crate) fn from_entries(entries: impl IntoIterator<Item = (String, String, u32)>) -> Self {
+        Self {
+            counts: entries
+                .into_iter()
+                .map(|(heard, wrote, count)| ((heard, wrote), count))
+                .collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn incorrect(wrote: &str) -> WordGrade {
+        WordGrade::Incorrect {
+            wrote: Some(wrote.to_string()),
+        }
+    }
+
+    #[test]
+    fn test_repeated_confusions_are_digested() {
+        let mut confusions = Confusions::default();
+        for _ in 0..3 {
+            confusions.record("depuis", &incorrect("Pendant"));
+        }
+        confusions.record("de", &incorrect("des"));
+        confusions.record("de", &incorrect("de"));
+        confusions.record(
+            "bien",
+            &WordGrade::CorrectWithTypo {
+                wrote: Some("bein".to_string()),
+            },
+        );
+        confusions.record("très", &WordGrade::Missed {});
+
+        assert_eq!(
+            confusions.digest(),
+            MistakeDigest {
+                confusions: vec![Confusion {
+                    expected: "depuis".to_string(),
+                    wrote: "pendant".to_string(),
+                    count: 3,
+                }],
+            }
+        );
+        assert_eq!(confusions.entries().len(), 2);
+    }
+
+    #[test]
+    fn test_rare_confusions_make_room_for_new_ones() {
+        let mut confusions = Confusions::default();
+        for i in 0..MAX_DISTINCT {
+            confusions.record(&format!("word{i}"), &incorrect("other"));
+            confusions.record(&format!("word{i}"), &incorrect("other"));
+        }
+        confusions.record("rare", &incorrect("wrong"));
+        confusions.record("new", &incorrect("wrong"));
+
+        let entries = confusions.entries();
+        assert_eq!(entries.len(), MAX_DISTINCT);
+        assert!(entries.iter().any(|(heard, ..)| heard == "new"));
+        assert!(!entries.iter().any(|(heard, ..)| heard == "rare"));
+    }
+}
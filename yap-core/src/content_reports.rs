@@ -0,0 +1,179 @@
+//! Corrections the user reported for the language pack's content, see
+//! `language_utils::content_report`. Reporting records a `LanguageEventContent::ReportContent`, so
+//! the reports follow the user to their other devices, and the app uploads the report on its
+//! own. `Deck::get_content_reports` lists them with what the backend says about each.
+
+use std::hash::{Hash, Hasher};
+
+use chrono::{DateTime, Utc};
+use language_utils::content_report::{
+    ContentReport, ContentReportKind, ContentReportState, ContentReportStatus,
+};
+use language_utils::{Course, Lexeme};
+use serde::{Deserialize, Serialize};
+#[cfg(target_arch = "wasm32")]
+use wasm_bindgen::prelude::*;
+
+use crate::{Deck, DeckEvent, LanguageEvent, LanguageEventContent, datetime_from_ms};
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub(crate) struct ReportedContent {
+    pub(crate) report: ContentReport,
+    pub(crate) reported_at: DateTime<Utc>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(target_arch = "wasm32", derive(tsify::Tsify))]
+#[cfg_attr(target_arch = "wasm32", tsify(into_wasm_abi))]
+pub struct SubmittedContentReport {
+    pub report: ContentReport,
+    pub reported_at_ms: f64,
+    /// `None` if the backend doesn't have the report, e.g. because it hasn't been uploaded yet
+    pub status: Option<ContentReportStatus>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(target_arch = "wasm32", derive(tsify::Tsify))]
+#[cfg_attr(target_arch = "wasm32", tsify(into_wasm_abi))]
+pub struct ContentReportHistory {
+    /// Most recent first
+    pub reports: Vec<SubmittedContentReport>,
+    /// How many of the user's corrections were taken, for thanking them
+    pub accepted: u32,
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+impl Deck {
+    /// Records a correction to content the user saw in the pack with hash `pack_version` (see
+    /// `LoadedPackInfo::version`). Upload the event's report with
+    /// `Weapon::submit_content_report`.
+    #[allow(clippy::too_many_arguments)]
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn report_content(
+        &self,
+        kind: ContentReportKind,
+        pack_version: String,
+        sentence: Option<String>,
+        lexeme: Option<Lexeme<String>>,
+        suggestion: Option<String>,
+        comment: String,
+        timestamp_ms: f64,
+    ) -> DeckEvent {
+        let mut hasher = rustc_hash::FxHasher::default();
+        (kind, &sentence, &lexeme, &suggestion, &comment).hash(&mut hasher);
+        let report = ContentReport {
+            id: format!("{}-{:08x}", timestamp_ms as i64, hasher.finish() as u32),
+            kind,
+            course: Course {
+                native_language: self.context.native_language,
+                target_language: self.context.target_language,
+            },
+            pack_version,
+            sentence,
+            lexeme,
+            suggestion,
+            comment,
+        };
+        DeckEvent::Language(LanguageEvent {
+            target_language: self.context.target_language,
+            native_language: self.context.native_language,
+            content: LanguageEventContent::ReportContent { report },
+        })
+    }
+
+    /// The user's reports, with `statuses` from `Weapon::get_content_report_statuses`. Reports
+    /// without a status still need uploading.
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn get_content_reports(&self, statuses: Vec<ContentReportStatus>) -> ContentReportHistory {
+        let reports = self
+            .stats
+            .content_reports
+            .iter()
+            .rev()
+            .map(|reported| SubmittedContentReport {
+                report: reported.report.clone(),
+                reported_at_ms: reported.reported_at.timestamp_millis() as f64,
+                status: statuses
+                    .iter()
+                    .find(|status| status.id == reported.report.id)
+                    .cloned(),
+            })
+            .collect::<Vec<_>>();
+        let accepted = reports
+            .iter()
+            .filter(|report| {
+                report
+                    .status
+                    .as_ref()
+                    .is_some_and(|status| status.state == ContentReportState::Accepted)
+            })
+            .count() as u32;
+        ContentReportHistory { reports, accepted }
+    }
+}
+
+/// Reports synced from several devices may arrive more than once
+pub(crate) fn record_content_report(
+    content_reports: &mut Vec<ReportedContent>,
+    report: &ContentReport,
+    timestamp: DateTime<Utc>,
+) {
+    if content_reports
+        .iter()
+        .all(|reported| reported.report.id != report.id)
+    {
+        content_reports.push(ReportedContent {
+            report: report.clone(),
+            reported_at: timestamp,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use weapon::AppState;
+    use weapon::data_model::Timestamped;
+
+    #[test]
+    fn test_reports_are_listed_with_their_status() {
+        let deck = Deck::default();
+        let timestamp_ms = 1_760_000_000_000.0;
+        let event = deck.report_content(
+            ContentReportKind::WrongTranslation,
+            "pack-hash".to_string(),
+            Some("Bonjour.".to_string()),
+            None,
+            Some("Hello.".to_string()),
+            "It's a greeting".to_string(),
+            timestamp_ms,
+        );
+        let DeckEvent::Language(LanguageEvent {
+            content: LanguageEventContent::ReportContent { report },
+            ..
+        }) = &event
+        else {
+            panic!("expected a report");
+        };
+        let id = report.id.clone();
+        let timestamped = Timestamped {
+            timestamp: datetime_from_ms(timestamp_ms),
+            within_device_events_index: 0,
+            event,
+        };
+        // The same report synced twice is only listed once
+        let deck = deck.apply_event(&timestamped).apply_event(&timestamped);
+
+        let history = deck.get_content_reports(Vec::new());
+        assert_eq!(history.reports.len(), 1);
+        assert_eq!(history.reports[0].status, None);
+        assert_eq!(history.accepted, 0);
+
+        let history = deck.get_content_reports(vec![ContentReportStatus {
+            id,
+            state: ContentReportState::Accepted,
+            reviewer_note: None,
+        }]);
+        assert_eq!(history.accepted, 1);
+    }
+}
@@ -0,0 +1,161 @@
+//! Events store strings, which are looked up in the language pack's rodeo when they're processed.
+//! A string that isn't there (an event from an older or newer pack, or a pack bug) used to just
+//! be skipped. Misses are now counted, so they show up in `Deck::get_data_mismatch_report` and
+//! in the course spot check.
+//!
+//! Lookups never intern: the pack's rodeo is a read-only `RodeoReader` built from the pack alone.
+//! So when a pack update removes sentences, events that still mention them don't add strings to
+//! it on each load. The only copies kept are the misses here, capped at `MAX_DISTINCT`.
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+/// Only this many different strings are kept, so a badly mismatched pack doesn't grow the deck
+/// without bound. Misses past that are still counted.
+const MAX_DISTINCT: usize = 100;
+
+/// What an unresolved string was supposed to be
+#[derive(Copy, Clone, Debug, Serialize, Deserialize, PartialEq, Eq, Ord, PartialOrd, Hash)]
+#[cfg_attr(target_arch = "wasm32", derive(tsify::Tsify))]
+#[cfg_attr(target_arch = "wasm32", tsify(into_wasm_abi, from_wasm_abi))]
+pub enum DataMismatchKind {
+    Card,
+    Lexeme,
+    Sentence,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[cfg_attr(target_arch = "wasm32", derive(tsify::Tsify))]
+#[cfg_attr(target_arch = "wasm32", tsify(into_wasm_abi, from_wasm_abi))]
+pub struct DataMismatch {
+    pub kind: DataMismatchKind,
+    /// The string (or the debug representation of the card or lexeme) that wasn't in the pack
+    pub value: String,
+    /// How many times events referred to it
+    pub count: u32,
+}
+
+/// Strings in the deck's events that aren't in its language pack, see
+/// `Deck::get_data_mismatch_report`
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[cfg_attr(target_arch = "wasm32", derive(tsify::Tsify))]
+#[cfg_attr(target_arch = "wasm32", tsify(into_wasm_abi, from_wasm_abi))]
+pub struct DataMismatchReport {
+    /// Most missed first
+    pub mismatches: Vec<DataMismatch>,
+    /// Misses of strings that weren't kept, because `mismatches` was full
+    pub untracked_misses: u32,
+    /// Tracked cards whose content is no longer in the pack
+    pub orphaned_cards: u32,
+}
+
+impl DataMismatchReport {
+    pub fn is_empty(&self) -> bool {
+        self.mismatches.is_empty() && self.untracked_misses == 0 && self.orphaned_cards == 0
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub(crate) struct DataMismatches {
+    misses: BTreeMap<(DataMismatchKind, String), u32>,
+    untracked_misses: u32,
+}
+
+impl DataMismatches {
+    /// Passes `found` (the result of a rodeo lookup) through, recording a miss if it's `None`.
+    /// `missing` describes what was looked up, and is only called on a miss.
+    pub(crate) fn check<T>(
+        &mut self,
+        found: Option<T>,
+        kind: DataMismatchKind,
+        missing: impl FnOnce() -> String,
+    ) -> Option<T> {
+        if found.is_none() {
+            let key = (kind, missing());
+            if let Some(count) = self.misses.get_mut(&key) {
+                *count += 1;
+            } else if self.misses.len() < MAX_DISTINCT {
+                self.misses.insert(key, 1);
+            } else {
+                self.untracked_misses += 1;
+            }
+        }
+        found
+    }
+
+    pub(crate) fn report(&self, orphaned_cards: usize) -> DataMismatchReport {
+        let mut mismatches: Vec<DataMismatch> = self
+            .misses
+            .iter()
+            .map(|((kind, value), count)| DataMismatch {
+                kind: *kind,
+                value: value.clone(),
+                count: *count,
+            })
+            .collect();
+        mismatches.sort_by_key(|mismatch| std::cmp::Reverse(mismatch.count));
+        DataMismatchReport {
+            mismatches,
+            untracked_misses: self.untracked_misses,
+            orphaned_cards: orphaned_cards as u32,
+        }
+    }
+
+    /// Every tracked miss and the untracked count, for saving in a snapshot
+    pub(crate) fn entries(&self) -> (Vec<(DataMismatchKind, String, u32)>, u32) {
+        let misses = self
+            .misses
+            .iter()
+            .map(|((kind, value), count)| (*kind, value.clone(), *count))
+            .collect();
+        (misses, self.untracked_misses)
+    }
+
+    pub(crate) fn from_entries(
+        misses: impl IntoIterator<Item = (DataMismatchKind, String, u32)>,
+        untracked_misses: u32,
+    ) -> Self {
+        Self {
+            misses: misses
+                .into_iter()
+                .map(|(kind, value, count)| ((kind, value), count))
+                .collect(),
+            untracked_misses,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_misses_are_counted_and_capped() {
+        let mut mismatches = DataMismatches::default();
+        assert_eq!(
+            mismatches.check(Some(1), DataMismatchKind::Card, || unreachable!()),
+            Some(1)
+        );
+        for _ in 0..3 {
+            mismatches.check(None::<u32>, DataMismatchKind::Sentence, || {
+                "Bonjour.".into()
+            });
+        }
+        for i in 0..MAX_DISTINCT {
+            mismatches.check(None::<u32>, DataMismatchKind::Lexeme, || i.to_string());
+        }
+
+        let report = mismatches.report(0);
+        assert_eq!(report.mismatches.len(), MAX_DISTINCT);
+        assert_eq!(
+            report.mismatches[0],
+            DataMismatch {
+                kind: DataMismatchKind::Sentence,
+                value: "Bonjour.".to_string(),
+                count: 3,
+            }
+        );
+        assert_eq!(report.untracked_misses, 1);
+    }
+}
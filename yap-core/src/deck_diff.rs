@@ -0,0 +1,260 @@
+//! What changed between two versions of a deck, e.g. rebuilt before and after a sync, for support
+//! tickets like "my due counts changed after sync". The decks are compared by their cards'
+//! strings, so they can come from different versions of the language pack.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use serde::{Deserialize, Serialize};
+#[cfg(target_arch = "wasm32")]
+use wasm_bindgen::prelude::*;
+
+use crate::{CardData, CardIndicator, CardStatus, Deck};
+
+/// How many cards each list in a `DeckDifference` names. Its counts include the rest.
+const MAX_LISTED_CARDS: usize = 50;
+
+const MS_PER_DAY: f64 = 24.0 * 60.0 * 60.0 * 1000.0;
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(target_arch = "wasm32", derive(tsify::Tsify))]
+#[cfg_attr(target_arch = "wasm32", tsify(into_wasm_abi, from_wasm_abi))]
+pub struct DueDateShift {
+    pub card: CardIndicator<String>,
+    pub before_ms: f64,
+    pub after_ms: f64,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(target_arch = "wasm32", derive(tsify::Tsify))]
+#[cfg_attr(target_arch = "wasm32", tsify(into_wasm_abi, from_wasm_abi))]
+pub struct StatChange {
+    pub stat: String,
+    pub before: f64,
+    pub after: f64,
+}
+
+/// See `Deck::explain_difference`
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(target_arch = "wasm32", derive(tsify::Tsify))]
+#[cfg_attr(target_arch = "wasm32", tsify(into_wasm_abi, from_wasm_abi))]
+pub struct DeckDifference {
+    /// Cards that are only added in the second deck
+    pub cards_added: Vec<CardIndicator<String>>,
+    pub cards_added_count: usize,
+    /// Cards that are only added in the first deck
+    pub cards_removed: Vec<CardIndicator<String>>,
+    pub cards_removed_count: usize,
+    /// Cards added in both decks that come due at different times, the biggest shifts first
+    pub due_date_shifts: Vec<DueDateShift>,
+    pub due_date_shift_count: usize,
+    pub due_before: usize,
+    pub due_after: usize,
+    /// Only the stats that changed
+    pub stats: Vec<StatChange>,
+    /// The names of the settings that changed
+    pub settings_changed: Vec<String>,
+    /// All of the above in a few lines, for pasting into a support ticket
+    pub summary: String,
+}
+
+impl DeckDifference {
+    pub fn is_empty(&self) -> bool {
+        self.cards_added_count == 0
+            && self.cards_removed_count == 0
+            && self.due_date_shift_count == 0
+            && self.stats.is_empty()
+            && self.settings_changed.is_empty()
+    }
+
+    fn summarize(&self) -> String {
+        if self.is_empty() {
+            return "The decks are the same".to_string();
+        }
+        let mut lines = Vec::new();
+        if self.cards_added_count > 0 || self.cards_removed_count > 0 {
+            lines.push(format!(
+                "Cards: {} added, {} removed",
+                self.cards_added_count, self.cards_removed_count
+            ));
+        }
+        if self.due_before != self.due_after {
+            lines.push(format!("Due now: {} → {}", self.due_before, self.due_after));
+        }
+        if let Some(biggest) = self.due_date_shifts.first() {
+            lines.push(format!(
+                "Due dates: {} moved, the most by {:+.1} days",
+                self.due_date_shift_count,
+                (biggest.after_ms - biggest.before_ms) / MS_PER_DAY
+            ));
+        }
+        for change in &self.stats {
+            lines.push(format!(
+                "{}: {} → {}",
+                change.stat, change.before, change.after
+            ));
+        }
+        if !self.settings_changed.is_empty() {
+            lines.push(format!(
+                "Settings changed: {}",
+                self.settings_changed.join(", ")
+            ));
+        }
+        lines.join("\n")
+    }
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+impl Deck {
+    /// What changed from this deck to `after`, as of `timestamp_ms` (for the due counts and streaks)
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn explain_difference(&self, after: &Deck, timestamp_ms: f64) -> DeckDifference {
+        let before_cards = self.added_card_due_dates();
+        let after_cards = after.added_card_due_dates();
+
+        let cards_added = after_cards
+            .keys()
+            .filter(|card| !before_cards.contains_key(*card))
+            .cloned()
+            .collect::<Vec<_>>();
+        let cards_removed = before_cards
+            .keys()
+            .filter(|card| !after_cards.contains_key(*card))
+            .cloned()
+            .collect::<Vec<_>>();
+        let mut due_date_shifts = before_cards
+            .iter()
+            .filter_map(|(card, &before_ms)| {
+                let &after_ms = after_cards.get(card)?;
+                (after_ms != before_ms).then(|| DueDateShift {
+                    card: card.clone(),
+                    before_ms,
+                    after_ms,
+                })
+            })
+            .collect::<Vec<_>>();
+        due_date_shifts.sort_by(|a, b| {
+            let shift = |shift: &DueDateShift| (shift.after_ms - shift.before_ms).abs();
+            shift(b).total_cmp(&shift(a))
+        });
+        let due_count =
+            |cards: &BTreeMap<_, f64>| cards.values().filter(|&&due| due <= timestamp_ms).count();
+
+        let stats = [
+            (
+                "Total reviews",
+                self.stats.total_reviews as f64,
+                after.stats.total_reviews as f64,
+            ),
+            ("XP", self.stats.xp, after.stats.xp),
+            (
+                "Daily streak",
+                self.get_daily_streak(timestamp_ms) as f64,
+                after.get_daily_streak(timestamp_ms) as f64,
+            ),
+            (
+                "Sentences reviewed",
+                self.stats.sentences_reviewed.len() as f64,
+                after.stats.sentences_reviewed.len() as f64,
+            ),
+            (
+                "Leeches",
+                self.leeches.len() as f64,
+                after.leeches.len() as f64,
+            ),
+            (
+                "Orphaned cards",
+                self.orphaned.len() as f64,
+                after.orphaned.len() as f64,
+            ),
+        ]
+        .into_iter()
+        .filter(|(_, before, after)| before != after)
+        .map(|(stat, before, after)| StatChange {
+            stat: stat.to_string(),
+            before,
+            after,
+        })
+        .collect();
+
+        let recognition_only = |deck: &Deck| {
+            let rodeo = &deck.context.language_pack.rodeo;
+            deck.recognition_only
+                .iter()
+                .map(|card| card.resolve(rodeo))
+                .collect::<BTreeSet<_>>()
+        };
+        let settings_changed = [
+            (
+                "course",
+                self.context.target_language != after.context.target_language
+                    || self.context.native_language != after.context.native_language,
+            ),
+            ("scheduler", self.scheduler != after.scheduler),
+            ("review order", self.review_order != after.review_order),
+            ("daily goal", self.daily_goal != after.daily_goal),
+            (
+                "new card accuracy threshold",
+                self.new_card_accuracy_threshold != after.new_card_accuracy_threshold,
+            ),
+            (
+                "sentence filters",
+                self.sentence_filters != after.sentence_filters,
+            ),
+            (
+                "FSRS parameters",
+                self.personal_fsrs_parameters != after.personal_fsrs_parameters,
+            ),
+            (
+                "mistake history sharing",
+                self.share_mistake_history != after.share_mistake_history,
+            ),
+            (
+                "component credit",
+                self.component_credit != after.component_credit,
+            ),
+            (
+                "card modes",
+                recognition_only(self) != recognition_only(after),
+            ),
+            (
+                "scheduling profile",
+                self.scheduling_profile != after.scheduling_profile,
+            ),
+        ]
+        .into_iter()
+        .filter(|(_, changed)| *changed)
+        .map(|(setting, _)| setting.to_string())
+        .collect();
+
+        let mut difference = DeckDifference {
+            cards_added_count: cards_added.len(),
+            cards_added: cards_added.into_iter().take(MAX_LISTED_CARDS).collect(),
+            cards_removed_count: cards_removed.len(),
+            cards_removed: cards_removed.into_iter().take(MAX_LISTED_CARDS).collect(),
+            due_date_shift_count: due_date_shifts.len(),
+            due_date_shifts: due_date_shifts.into_iter().take(MAX_LISTED_CARDS).collect(),
+            due_before: due_count(&before_cards),
+            due_after: due_count(&after_cards),
+            stats,
+            settings_changed,
+            summary: String::new(),
+        };
+        difference.summary = difference.summarize();
+        difference
+    }
+}
+
+impl Deck {
+    /// The due date of every schedulable card that's been added, by its strings
+    fn added_card_due_dates(&self) -> BTreeMap<CardIndicator<String>, f64> {
+        let rodeo = &self.context.language_pack.rodeo;
+        self.schedulable_cards()
+            .filter_map(|(card, status)| match status {
+                CardStatus::Tracked(card_data @ CardData::Added { .. }) => {
+                    Some((card.resolve(rodeo), card_data.due_timestamp_ms()))
+                }
+                CardStatus::Tracked(CardData::Ghost { .. }) | CardStatus::Unadded(_) => None,
+            })
+            .collect()
+    }
+}
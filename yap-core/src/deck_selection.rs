@@ -0,0 +1,201 @@
+use language_utils::Language;
+use weapon::data_model::Event;
+
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(target_arch = "wasm32", derive(tsify::Tsify))]
+#[cfg_attr(target_arch = "wasm32", tsify(into_wasm_abi, from_wasm_abi))]
+#[serde(rename_all = "camelCase")]
+pub struct DeckSelection {
+    pub target_language: Option<Language>,
+    pub native_language: Option<Language>,
+    /// Write grading explanations in English rather than the native language
+    #[serde(default)]
+    pub force_english_explanations: bool,
+    /// Other languages the user speaks, most preferred first, see `native_languages`
+    #[serde(default)]
+    pub other_native_languages: Vec<Language>,
+}
+
+impl DeckSelection {
+    /// Every language the user speaks, most preferred first, starting with the course's native
+    /// language. Definitions and translations are shown in each of them that the course's target
+    /// language has a pack for, and translations can be answered in any of them.
+    pub fn native_languages(&self) -> Vec<Language> {
+        let mut languages: Vec<Language> = Vec::new();
+        for language in self
+            .native_language
+            .iter()
+            .chain(&self.other_native_languages)
+        {
+            if Some(*language) != self.target_language && !languages.contains(language) {
+                languages.push(*language);
+            }
+        }
+        languages
+    }
+
+    /// The `explanation_language` to send with autograding requests. `None` leaves it up to the
+    /// server, which writes explanations in the course's native language.
+    pub fn explanation_language(&self) -> Option<Language> {
+        self.force_english_explanations.then_some(Language::English)
+    }
+}
+
+impl weapon::PartialAppState for DeckSelection {
+    type Event = DeckSelectionEvent;
+    type Partial = Self; // For now, partial state is the same as final state
+
+    fn process_event(
+        mut partial: Self::Partial,
+        event: &weapon::data_model::Timestamped<Self::Event>,
+    ) -> Self::Partial {
+        match event.event {
+            DeckSelectionEvent::SelectTargetLanguage(language) => {
+                partial.target_language = Some(language);
+                partial
+            }
+            DeckSelectionEvent::SelectBothLanguages { native, target } => {
+                partial.native_language = Some(native);
+                partial.target_language = Some(target);
+                partial
+            }
+            DeckSelectionEvent::ForceEnglishExplanations(force) => {
+                partial.force_english_explanations = force;
+                partial
+            }
+            DeckSelectionEvent::SelectNativeLanguages(ref languages) => {
+                if let Some((native, others)) = languages.split_first() {
+                    partial.native_language = Some(*native);
+                    partial.other_native_languages = others.to_vec();
+                }
+                partial
+            }
+        }
+    }
+
+    fn finalize(partial: Self::Partial) -> Self {
+        // For now, finalization is a no-op since Partial = Self
+        partial
+    }
+}
+
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize, PartialEq, Eq, Ord, PartialOrd)]
+#[cfg_attr(target_arch = "wasm32", derive(tsify::Tsify))]
+#[cfg_attr(target_arch = "wasm32", tsify(into_wasm_abi, from_wasm_abi))]
+pub enum DeckSelectionEvent {
+    #[serde(alias = "SelectLanguage")]
+    SelectTargetLanguage(Language),
+    SelectBothLanguages {
+        native: Language,
+        target: Language,
+    },
+    ForceEnglishExplanations(bool),
+    /// The languages the user speaks, most preferred first. The first is the course's native
+    /// language.
+    SelectNativeLanguages(Vec<Language>),
+}
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize, Ord, PartialOrd, Eq, PartialEq)]
+#[cfg_attr(target_arch = "wasm32", derive(tsify::Tsify))]
+#[cfg_attr(target_arch = "wasm32", tsify(into_wasm_abi, from_wasm_abi))]
+#[serde(tag = "version")]
+pub enum VersionedDeckSelectionEvent {
+    V1(DeckSelectionEvent),
+}
+impl Event for DeckSelectionEvent {
+    fn to_json(&self) -> Result<serde_json::Value, serde_json::Error> {
+        let versioned = VersionedDeckSelectionEvent::from(self.clone());
+        serde_json::to_value(versioned)
+    }
+
+    fn from_json(json: &serde_json::Value) -> Result<Self, serde_json::Error> {
+        serde_json::from_value::<VersionedDeckSelectionEvent>(json.clone())
+            .map(|versioned| versioned.into())
+    }
+}
+impl From<DeckSelectionEvent> for VersionedDeckSelectionEvent {
+    fn from(event: DeckSelectionEvent) -> Self {
+        VersionedDeckSelectionEvent::V1(event)
+    }
+}
+impl From<VersionedDeckSelectionEvent> for DeckSelectionEvent {
+    fn from(event: VersionedDeckSelectionEvent) -> Self {
+        match event {
+            VersionedDeckSelectionEvent::V1(event) => event,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use weapon::AppState;
+    use weapon::data_model::Timestamped;
+
+    fn select(selection: DeckSelection, event: DeckSelectionEvent) -> DeckSelection {
+        // Goes through JSON like a synced event does
+        let event = DeckSelectionEvent::from_json(&event.to_json().unwrap()).unwrap();
+        selection.apply_event(&Timestamped {
+            timestamp: chrono::Utc::now(),
+            within_device_events_index: 0,
+            event,
+        })
+    }
+
+    #[test]
+    fn test_native_languages_start_with_the_course_native_language() {
+        let selection = DeckSelection {
+            target_language: Some(Language::French),
+            native_language: Some(Language::English),
+            force_english_explanations: false,
+            other_native_languages: Vec::new(),
+        };
+        assert_eq!(selection.native_languages(), vec![Language::English]);
+
+        let selection = select(
+            selection,
+            DeckSelectionEvent::SelectNativeLanguages(vec![
+                Language::Spanish,
+                Language::English,
+                Language::Spanish,
+                Language::French,
+            ]),
+        );
+        assert_eq!(selection.native_language, Some(Language::Spanish));
+        assert_eq!(selection.target_language, Some(Language::French));
+        // Without duplicates or the target language
+        assert_eq!(
+            selection.native_languages(),
+            vec![Language::Spanish, Language::English]
+        );
+
+        // An empty list leaves the languages alone
+        let selection = select(selection, DeckSelectionEvent::SelectNativeLanguages(vec![]));
+        assert_eq!(
+            selection.native_languages(),
+            vec![Language::Spanish, Language::English]
+        );
+    }
+
+    #[test]
+    fn test_forcing_english_explanations_sets_the_explanation_language() {
+        let selection = DeckSelection {
+            target_language: Some(Language::French),
+            native_language: Some(Language::Spanish),
+            force_english_explanations: false,
+            other_native_languages: Vec::new(),
+        };
+        assert_eq!(selection.explanation_language(), None);
+
+        let selection = select(
+            selection,
+            DeckSelectionEvent::ForceEnglishExplanations(true),
+        );
+        assert_eq!(selection.explanation_language(), Some(Language::English));
+
+        let selection = select(
+            selection,
+            DeckSelectionEvent::ForceEnglishExplanations(false),
+        );
+        assert_eq!(selection.explanation_language(), None);
+    }
+}
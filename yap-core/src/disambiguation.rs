@@ -0,0 +1,142 @@
+//! Challenges for words that are spelled like another word, e.g. "est" (is) and "est" (east). The
+//! user is shown a sentence the language pack's tokenizer tagged one of them in and picks which one
+//! it is there, which reviews the card of the one that's actually in the sentence.
+
+use std::hash::Hash;
+
+use language_utils::{Heteronym, Lexeme, Literal, TargetToNativeWord, TtsProvider, TtsRequest};
+use lasso::Spur;
+#[cfg(target_arch = "wasm32")]
+use wasm_bindgen::prelude::*;
+
+use crate::{AudioRequest, CardIndicator, Challenge, Deck, DeckEvent, Rating, ReviewInfo};
+
+/// Every this-many-th challenge for a card whose word is spelled like another asks which one it is
+pub(crate) const DISAMBIGUATION_INTERVAL: u64 = 3;
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
+#[cfg_attr(target_arch = "wasm32", derive(tsify::Tsify))]
+#[cfg_attr(target_arch = "wasm32", tsify(into_wasm_abi, from_wasm_abi))]
+pub struct DisambiguateHeteronym<S>
+where
+    S: rkyv::Archive,
+    <S as rkyv::Archive>::Archived: PartialEq + PartialOrd + Eq + Ord + Hash,
+    <Heteronym<S> as rkyv::Archive>::Archived: PartialEq + PartialOrd + Eq + Ord + Hash,
+    <Option<Heteronym<S>> as rkyv::Archive>::Archived: PartialEq + PartialOrd + Eq + Ord + Hash,
+{
+    pub audio: AudioRequest,
+    pub target_language: S,
+    pub target_language_literals: Vec<Literal<S>>,
+    /// Which of `target_language_literals` is the word being asked about
+    pub literal_index: usize,
+    /// Every heteronym spelled like the word, with its definitions. One of them is `answer`.
+    pub options: Vec<(Heteronym<S>, Vec<TargetToNativeWord>)>,
+    /// Pass this back to `disambiguate_heteronym` along with the option the user picked
+    pub answer: Heteronym<S>,
+    pub native_translations: Vec<String>,
+}
+
+impl DisambiguateHeteronym<Spur> {
+    pub(crate) fn resolve(&self, rodeo: &lasso::RodeoReader) -> DisambiguateHeteronym<String> {
+        DisambiguateHeteronym {
+            audio: self.audio.clone(),
+            target_language: rodeo.resolve(&self.target_language).to_string(),
+            target_language_literals: self
+                .target_language_literals
+                .iter()
+                .map(|literal| literal.resolve(rodeo))
+                .collect(),
+            literal_index: self.literal_index,
+            options: self
+                .options
+                .iter()
+                .map(|(heteronym, definitions)| (heteronym.resolve(rodeo), definitions.clone()))
+                .collect(),
+            answer: self.answer.resolve(rodeo),
+            native_translations: self.native_translations.clone(),
+        }
+    }
+}
+
+impl ReviewInfo {
+    /// A challenge asking which heteronym `heteronym.word` is in a comprehensible sentence, if
+    /// the dictionary has another heteronym spelled the same way
+    pub(crate) fn disambiguation_challenge(
+        &self,
+        deck: &Deck,
+        heteronym: Heteronym<Spur>,
+    ) -> Option<Challenge<Spur>> {
+        let language_pack = &deck.context.language_pack;
+        let options = language_pack
+            .words_to_heteronyms
+            .get(&heteronym.word)?
+            .iter()
+            .filter_map(|option| {
+                let entry = language_pack.dictionary.get(option)?;
+                Some((*option, entry.definitions.clone()))
+            })
+            .collect::<Vec<_>>();
+        if options.len() < 2 || !options.iter().any(|(option, _)| *option == heteronym) {
+            return None;
+        }
+
+        let sentence = deck.get_comprehensible_sentence_containing(
+            Some(&Lexeme::Heteronym(heteronym)),
+            self.get_comprehensible_written_lexemes(deck),
+            &deck.stats.sentences_reviewed,
+            language_pack,
+        )?;
+        // Only ask about the word where the tokenizer tagged it, not where it was inferred
+        let literal_index = sentence
+            .target_language_literals
+            .iter()
+            .position(|literal| literal.heteronym == Some(heteronym))?;
+
+        Some(Challenge::DisambiguateHeteronym(DisambiguateHeteronym {
+            audio: AudioRequest {
+                request: TtsRequest {
+                    text: language_pack
+                        .rodeo
+                        .resolve(&sentence.target_language)
+                        .to_string(),
+                    language: deck.context.target_language,
+                },
+                provider: deck.preferred_tts_provider(TtsProvider::Google),
+            },
+            target_language: sentence.target_language,
+            target_language_literals: sentence.target_language_literals,
+            literal_index,
+            options,
+            answer: heteronym,
+            native_translations: sentence
+                .native_languages
+                .iter()
+                .map(|translation| translation.to_string())
+                .collect(),
+        }))
+    }
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+impl Deck {
+    /// Grades a `DisambiguateHeteronym` challenge. The card of `answer` is remembered if the user
+    /// `picked` it, and forgotten otherwise.
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn disambiguate_heteronym(
+        &self,
+        answer: Heteronym<String>,
+        picked: Heteronym<String>,
+    ) -> Option<DeckEvent> {
+        let rating = if picked == answer {
+            Rating::Remembered
+        } else {
+            Rating::Again
+        };
+        self.review_card(
+            CardIndicator::TargetLanguage {
+                lexeme: Lexeme::Heteronym(answer),
+            },
+            rating,
+        )
+    }
+}
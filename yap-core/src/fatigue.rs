@@ -0,0 +1,288 @@
+//! Accuracy by time of day and by how far into a session a challenge was, for spotting when the
+//! user gets tired. Sessions aren't recorded anywhere, so a gap of `SESSION_GAP_MINUTES` between
+//! challenges is taken to start a new one.
+
+use chrono::{DateTime, Timelike as _, Utc};
+use language_utils::transcription_challenge::{PartGraded, WordGrade};
+use serde::{Deserialize, Serialize};
+
+use crate::{LanguageEventContent, Rating, SentenceReviewIndicator, SentenceReviewResult};
+
+/// Challenges further apart than this are in different sessions
+pub(crate) const SESSION_GAP_MINUTES: i64 = 30;
+/// Session positions are grouped into buckets of this many challenges
+const POSITION_BUCKET_SIZE: u32 = 5;
+/// Buckets with fewer challenges than this are too noisy to compare
+const MIN_BUCKET_CHALLENGES: u32 = 20;
+/// How far accuracy has to fall below the start of a session to count as fatigue
+const FATIGUE_ACCURACY_DROP: f64 = 0.1;
+
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[cfg_attr(target_arch = "wasm32", derive(tsify::Tsify))]
+#[cfg_attr(target_arch = "wasm32", tsify(into_wasm_abi, from_wasm_abi))]
+pub struct AccuracyCounts {
+    pub correct: u32,
+    pub total: u32,
+}
+
+impl AccuracyCounts {
+    fn record(&mut self, correct: bool) {
+        self.total += 1;
+        if correct {
+            self.correct += 1;
+        }
+    }
+
+    fn accuracy(&self) -> Option<f64> {
+        (self.total > 0).then(|| self.correct as f64 / self.total as f64)
+    }
+}
+
+impl std::ops::Add for AccuracyCounts {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        Self {
+            correct: self.correct + other.correct,
+            total: self.total + other.total,
+        }
+    }
+}
+
+/// How accurate the user is, tracked as challenge events are processed
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct ChallengeAccuracy {
+    /// Indexed by UTC hour
+    by_hour: [AccuracyCounts; 24],
+    /// Indexed by position within the session, in buckets of `POSITION_BUCKET_SIZE`
+    by_session_position: Vec<AccuracyCounts>,
+    last_challenge: Option<DateTime<Utc>>,
+    /// How many challenges came before the current one in its session
+    session_position: u32,
+}
+
+impl ChallengeAccuracy {
+    pub(crate) fn record(&mut self, timestamp: DateTime<Utc>, correct: bool) {
+        let same_session = self
+            .last_challenge
+            .is_some_and(|last| timestamp - last < chrono::Duration::minutes(SESSION_GAP_MINUTES));
+        self.session_position = if same_session {
+            self.session_position + 1
+        } else {
+            0
+        };
+        self.last_challenge = Some(timestamp);
+
+        self.by_hour[timestamp.hour() as usize].record(correct);
+        let bucket = (self.session_position / POSITION_BUCKET_SIZE) as usize;
+        if self.by_session_position.len() <= bucket {
+            self.by_session_position
+                .resize(bucket + 1, AccuracyCounts::default());
+        }
+        self.by_session_position[bucket].record(correct);
+    }
+}
+
+/// Whether a challenge event was answered correctly, or `None` if it isn't a challenge
+pub(crate) fn challenge_outcome(event: &LanguageEventContent) -> Option<bool> {
+    match event {
+        LanguageEventContent::ReviewCard { rating, .. } => Some(*rating != Rating::Again),
+        LanguageEventContent::HandsFreeReview { understood, .. } => Some(*understood),
+        LanguageEventContent::SentenceRepetition { remembered, .. }
+        | LanguageEventContent::WarmupReview { remembered, .. } => Some(*remembered),
+        LanguageEventContent::TranslationChallenge {
+            review: SentenceReviewIndicator::TargetToNative { result, .. },
+            ..
+        } => Some(matches!(result, SentenceReviewResult::Perfect { .. })),
+        LanguageEventContent::TranscriptionChallenge { challenge, .. } => {
+            Some(challenge.iter().all(|part| match part {
+                PartGraded::AskedToTranscribe { parts, .. } => parts.iter().all(|part| {
+                    matches!(
+                        part.grade,
+                        WordGrade::Perfect { .. } | WordGrade::CorrectWithTypo { .. }
+                    )
+                }),
+                PartGraded::Provided { .. } => true,
+            }))
+        }
+        LanguageEventContent::AddCards { .. }
+        | LanguageEventContent::SetScheduler { .. }
+        | LanguageEventContent::AudioFeedback { .. }
+        | LanguageEventContent::FavoriteSentence { .. }
+        | LanguageEventContent::PrioritizeCard { .. }
+        | LanguageEventContent::SetSentenceFilters { .. }
+        | LanguageEventContent::SetFsrsParameters { .. }
+        | LanguageEventContent::SetShareMistakeHistory { .. }
+        | LanguageEventContent::SetComponentCredit { .. }
+        | LanguageEventContent::ResetDeck {}
+        | LanguageEventContent::SetReviewOrder { .. }
+        | LanguageEventContent::SetDailyGoal { .. }
+        | LanguageEventContent::SetNewCardAccuracyThreshold { .. }
+        | LanguageEventContent::RequestCalibration { .. }
+        | LanguageEventContent::ImportAnkiMemoryStates { .. }
+        | LanguageEventContent::LookedUp { .. }
+        | LanguageEventContent::MovieQuizCompleted { .. }
+        | LanguageEventContent::ReportContent { .. }
+        | LanguageEventContent::Graduate {}
+        | LanguageEventContent::SetSchedulingProfile { .. }
+        | LanguageEventContent::SingAlong { .. }
+        | LanguageEventContent::SessionCompleted { .. }
+        | LanguageEventContent::SetCardMode { .. } => None,
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(target_arch = "wasm32", derive(tsify::Tsify))]
+#[cfg_attr(target_arch = "wasm32", tsify(into_wasm_abi, from_wasm_abi))]
+pub struct HourAccuracy {
+    /// In the user's time zone
+    pub hour: u32,
+    pub counts: AccuracyCounts,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(target_arch = "wasm32", derive(tsify::Tsify))]
+#[cfg_attr(target_arch = "wasm32", tsify(into_wasm_abi, from_wasm_abi))]
+pub struct SessionPositionAccuracy {
+    /// How many challenges into a session this bucket starts
+    pub first_position: u32,
+    pub counts: AccuracyCounts,
+}
+
+/// See `Deck::get_fatigue_report`
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(target_arch = "wasm32", derive(tsify::Tsify))]
+#[cfg_attr(target_arch = "wasm32", tsify(into_wasm_abi, from_wasm_abi))]
+pub struct FatigueReport {
+    pub by_hour: Vec<HourAccuracy>,
+    pub by_session_position: Vec<SessionPositionAccuracy>,
+    /// How many challenges into a session accuracy starts to drop, if it does. A good point to
+    /// suggest a break.
+    pub fatigue_after: Option<u32>,
+    /// The hour (in the user's time zone) with the best accuracy, among those with enough
+    /// challenges to tell
+    pub best_hour: Option<u32>,
+}
+
+impl ChallengeAccuracy {
+    /// `utc_offset_minutes` is the user's time zone, so hours can be reported in local time
+    pub(crate) fn report(&self, utc_offset_minutes: i32) -> FatigueReport {
+        let local_hour = |utc_hour: usize| {
+            (utc_hour as i32 * 60 + utc_offset_minutes).rem_euclid(24 * 60) as u32 / 60
+        };
+        let mut by_hour: Vec<HourAccuracy> = self
+            .by_hour
+            .iter()
+            .enumerate()
+            .map(|(utc_hour, counts)| HourAccuracy {
+                hour: local_hour(utc_hour),
+                counts: *counts,
+            })
+            .collect();
+        by_hour.sort_by_key(|hour| hour.hour);
+
+        let best_hour = by_hour
+            .iter()
+            .filter(|hour| hour.counts.total >= MIN_BUCKET_CHALLENGES)
+            .filter_map(|hour| Some((hour.hour, hour.counts.accuracy()?)))
+            .max_by(|(_, a), (_, b)| a.total_cmp(b))
+            .map(|(hour, _)| hour);
+
+        FatigueReport {
+            by_hour,
+            by_session_position: self
+                .by_session_position
+                .iter()
+                .enumerate()
+                .map(|(bucket, counts)| SessionPositionAccuracy {
+                    first_position: bucket as u32 * POSITION_BUCKET_SIZE,
+                    counts: *counts,
+                })
+                .collect(),
+            fatigue_after: self.fatigue_after(),
+            best_hour,
+        }
+    }
+
+    /// The first session position from which accuracy stays well below the start of sessions
+    fn fatigue_after(&self) -> Option<u32> {
+        let (baseline, rest) = self.by_session_position.split_first()?;
+        let baseline = (baseline.total >= MIN_BUCKET_CHALLENGES)
+            .then(|| baseline.accuracy())
+            .flatten()?;
+
+        (0..rest.len())
+            .find(|&start| {
+                let remaining = &rest[start..];
+                let pooled = remaining
+                    .iter()
+                    .fold(AccuracyCounts::default(), |sum, counts| sum + *counts);
+                let each_bucket_low = remaining
+                    .iter()
+                    .filter(|counts| counts.total >= MIN_BUCKET_CHALLENGES)
+                    .all(|counts| {
+                        counts.accuracy().is_some_and(|accuracy| {
+                            accuracy < baseline - FATIGUE_ACCURACY_DROP / 2.0
+                        })
+                    });
+                pooled.total >= MIN_BUCKET_CHALLENGES
+                    && each_bucket_low
+                    && pooled
+                        .accuracy()
+                        .is_some_and(|accuracy| accuracy < baseline - FATIGUE_ACCURACY_DROP)
+            })
+            .map(|start| (start as u32 + 1) * POSITION_BUCKET_SIZE)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn session(accuracy: &mut ChallengeAccuracy, start: DateTime<Utc>, results: &[bool]) {
+        for (i, correct) in results.iter().enumerate() {
+            accuracy.record(start + chrono::Duration::seconds(30 * i as i64), *correct);
+        }
+    }
+
+    #[test]
+    fn test_fatigue_detected_after_accuracy_drops() {
+        let mut accuracy = ChallengeAccuracy::default();
+        let start = DateTime::<Utc>::from_timestamp(1_700_000_000, 0).unwrap();
+        for day in 0..10 {
+            // Right for the first 25 challenges, then wrong every other time
+            let results = (0..40).map(|i| i < 25 || i % 2 == 0).collect::<Vec<_>>();
+            session(&mut accuracy, start + chrono::Duration::days(day), &results);
+        }
+
+        let report = accuracy.report(0);
+        assert_eq!(report.fatigue_after, Some(25));
+        assert_eq!(report.by_session_position.len(), 8);
+        assert_eq!(report.by_session_position[0].counts.total, 50);
+    }
+
+    #[test]
+    fn test_no_fatigue_when_accuracy_holds() {
+        let mut accuracy = ChallengeAccuracy::default();
+        let start = DateTime::<Utc>::from_timestamp(1_700_000_000, 0).unwrap();
+        for day in 0..10 {
+            let results = (0..40).map(|i| i % 5 != 0).collect::<Vec<_>>();
+            session(&mut accuracy, start + chrono::Duration::days(day), &results);
+        }
+
+        assert_eq!(accuracy.report(0).fatigue_after, None);
+    }
+
+    #[test]
+    fn test_hours_are_reported_in_local_time() {
+        let mut accuracy = ChallengeAccuracy::default();
+        // 22:13 UTC
+        let start = DateTime::<Utc>::from_timestamp(1_700_000_000, 0).unwrap();
+        session(&mut accuracy, start, &[true; 20]);
+
+        let report = accuracy.report(-5 * 60);
+        assert_eq!(report.best_hour, Some(17));
+        let hour = report.by_hour.iter().find(|hour| hour.hour == 17).unwrap();
+        assert_eq!(hour.counts.total, 20);
+    }
+}
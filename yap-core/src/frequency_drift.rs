@@ -0,0 +1,56 @@
+//! The deck's cards whose words changed frequency rank a lot in the latest build of the language
+//! pack (see `language_utils::frequency_drift`). A card's value, and so when the words around it
+//! get added, follows its word's frequency, so this explains why the deck behaves differently
+//! after a pack update.
+
+use serde::{Deserialize, Serialize};
+#[cfg(target_arch = "wasm32")]
+use wasm_bindgen::prelude::*;
+
+use crate::{CardData, CardIndicator, CardStatus, Deck};
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(target_arch = "wasm32", derive(tsify::Tsify))]
+#[cfg_attr(target_arch = "wasm32", tsify(into_wasm_abi))]
+pub struct CardFrequencyDrift {
+    pub card: CardIndicator<String>,
+    /// The word's frequency rank in the previous build of the pack, 1 being the most frequent
+    pub previous_rank: u32,
+    pub rank: u32,
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+impl Deck {
+    /// The cards in the deck whose word's rank changed by at least `min_factor` either way in the
+    /// latest pack build, the biggest changes first. Packs only report changes of at least
+    /// `language_utils::frequency_drift::REPORTED_FACTOR`, so a lower `min_factor` doesn't find
+    /// any more. Cards for pronunciations and letter patterns don't have a word, so they're never
+    /// flagged.
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn get_frequency_drift(&self, min_factor: f64) -> Vec<CardFrequencyDrift> {
+        let language_pack = &self.context.language_pack;
+        let mut drifted = self
+            .cards
+            .iter()
+            .filter(|(_, status)| matches!(status, CardStatus::Tracked(CardData::Added { .. })))
+            .filter_map(|(card, _)| {
+                let lexeme = card.target_language().or(card.listening_lexeme())?;
+                let drift = language_pack.frequency_drift.get(lexeme)?;
+                (drift.factor() >= min_factor).then_some((card, *drift))
+            })
+            .collect::<Vec<_>>();
+        drifted.sort_by(|(card_a, a), (card_b, b)| {
+            b.factor()
+                .total_cmp(&a.factor())
+                .then_with(|| card_a.cmp(card_b))
+        });
+        drifted
+            .into_iter()
+            .map(|(card, drift)| CardFrequencyDrift {
+                card: card.resolve(&language_pack.rodeo),
+                previous_rank: drift.previous_rank,
+                rank: drift.rank,
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,150 @@
+use std::collections::BTreeSet;
+
+use language_utils::sentence_generation::{
+    GenerateSentenceRequest, GeneratedSentence, MAX_KNOWN_LEXEMES,
+};
+use language_utils::{Course, Lexeme, TargetToNativeWord, TtsProvider, TtsRequest};
+#[cfg(target_arch = "wasm32")]
+use wasm_bindgen::prelude::*;
+
+use crate::{
+    AudioRequest, CardIndicator, Challenge, Deck, ReviewInfo, TranslateComprehensibleSentence,
+};
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+impl ReviewInfo {
+    /// If the next due card is a word that should be tested in a sentence, but the language pack
+    /// has no sentence made up entirely of words the user knows, returns a request that can be
+    /// passed to `generate_sentence`.
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn get_sentence_generation_request(&self, deck: &Deck) -> Option<GenerateSentenceRequest> {
+        let card_indicator = *self.due_cards.iter().chain(&self.ahead_cards).next()?;
+        let CardIndicator::TargetLanguage { lexeme } = card_indicator else {
+            return None;
+        };
+        if deck.cards.get(&card_indicator)?.is_new() {
+            // New cards are shown as flashcards anyway
+            return None;
+        }
+
+        let language_pack = &deck.context.language_pack;
+        let comprehensible_lexemes = self.get_comprehensible_written_lexemes(deck);
+        if deck
+            .get_comprehensible_sentence_containing(
+                Some(&lexeme),
+                comprehensible_lexemes.clone(),
+                &deck.stats.sentences_reviewed,
+                language_pack,
+            )
+            .is_some()
+        {
+            return None;
+        }
+
+        // The most common ones, if the user knows more than the request can hold
+        let known_lexemes = language_pack
+            .word_frequencies
+            .keys()
+            .filter(|known| **known != lexeme && comprehensible_lexemes.contains(known))
+            .take(MAX_KNOWN_LEXEMES)
+            .map(|known| known.resolve(&language_pack.rodeo))
+            .collect::<Vec<_>>();
+        if known_lexemes.is_empty() {
+            return None;
+        }
+
+        Some(GenerateSentenceRequest {
+            course: Course {
+                native_language: deck.context.native_language,
+                target_language: deck.context.target_language,
+            },
+            target_lexeme: lexeme.resolve(&language_pack.rodeo),
+            known_lexemes,
+        })
+    }
+
+    /// Turns a sentence returned by `generate_sentence` into a translation challenge.
+    /// Returns None if the sentence uses a lexeme the user doesn't (or no longer) know.
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn get_challenge_from_generated_sentence(
+        &self,
+        deck: &Deck,
+        primary_expression: Lexeme<String>,
+        sentence: GeneratedSentence,
+    ) -> Option<Challenge<String>> {
+        let language_pack = &deck.context.language_pack;
+        let rodeo = &language_pack.rodeo;
+
+        let comprehensible_lexemes = self.get_comprehensible_written_lexemes(deck);
+        let primary_interned = primary_expression.get_interned(rodeo)?;
+
+        let mut unique_lexemes = Vec::new();
+        let mut seen = BTreeSet::new();
+        for lexeme in &sentence.lexemes {
+            let interned = lexeme.get_interned(rodeo)?;
+            if interned != primary_interned && !comprehensible_lexemes.contains(&interned) {
+                return None;
+            }
+            if seen.insert(interned) {
+                unique_lexemes.push((lexeme.clone(), interned));
+            }
+        }
+
+        let unique_target_language_lexeme_definitions = unique_lexemes
+            .iter()
+            .map(|(lexeme, interned)| {
+                let definitions = match interned {
+                    Lexeme::Heteronym(heteronym) => language_pack
+                        .dictionary
+                        .get(heteronym)
+                        .map(|entry| entry.definitions.clone())
+                        .unwrap_or_default(),
+                    Lexeme::Multiword(term) => language_pack
+                        .phrasebook
+                        .get(term)
+                        .map(|entry| {
+                            vec![TargetToNativeWord {
+                                native: entry.meaning.clone(),
+                                note: Some(entry.additional_notes.clone()),
+                                example_sentence_target_language: entry
+                                    .target_language_example
+                                    .clone(),
+                                example_sentence_native_language: entry
+                                    .native_language_example
+                                    .clone(),
+                            }]
+                        })
+                        .unwrap_or_default(),
+                };
+                (lexeme.clone(), definitions)
+            })
+            .collect();
+
+        Some(Challenge::TranslateComprehensibleSentence(
+            TranslateComprehensibleSentence {
+                audio: AudioRequest {
+                    request: TtsRequest {
+                        text: sentence.target_language.clone(),
+                        language: deck.context.target_language,
+                    },
+                    provider: deck.preferred_tts_provider(TtsProvider::ElevenLabs),
+                },
+                target_language: sentence.target_language,
+                target_language_literals: sentence.literals,
+                primary_expression,
+                unique_target_language_lexemes: unique_lexemes
+                    .into_iter()
+                    .map(|(lexeme, _)| lexeme)
+                    .collect(),
+                unique_target_language_lexeme_definitions,
+                native_translations: sentence.native_translations,
+                source: None,
+                movie_titles: Vec::new(),
+                favorite_practice: false,
+                sentence_repetition: false,
+                explanation: None,
+                hint_policy: deck.stats.hint_usage.policy(),
+            },
+        ))
+    }
+}
@@ -0,0 +1,239 @@
+//! One way to grade every kind of challenge. The UI passes back the `Challenge` it showed along
+//! with a `ChallengeOutcome` saying how the user did, and gets exactly one event to add. Everything
+//! the event needs that the challenge already knows (the sentence, the card, whether it was a
+//! favorite, the dictation level) is taken from the challenge, so it can't be passed back wrong.
+//! Sentences that came back for repetition are graded as a whole, see `Deck::repeat_sentence`,
+//! and warm-up flashcards without touching the card's schedule, see `Deck::warmup_review`.
+
+use language_utils::transcription_challenge::{InputMode, PartGraded};
+use language_utils::{Heteronym, Lexeme};
+#[cfg(target_arch = "wasm32")]
+use wasm_bindgen::prelude::*;
+
+use crate::{Challenge, Deck, DeckEvent, Rating};
+
+/// How the user did on a challenge, see `Deck::grade_challenge`
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize, PartialEq)]
+#[cfg_attr(target_arch = "wasm32", derive(tsify::Tsify))]
+#[cfg_attr(target_arch = "wasm32", tsify(into_wasm_abi, from_wasm_abi))]
+#[serde(tag = "type")]
+pub enum ChallengeOutcome {
+    /// For `FlashCardReview`
+    FlashCard { rating: Rating },
+    /// For `TranslateComprehensibleSentence`, translated correctly
+    TranslationPerfect { words_tapped: Vec<Lexeme<String>> },
+    /// For `TranslateComprehensibleSentence`, translated wrong
+    TranslationWrong {
+        submission: String,
+        words_remembered: Vec<Lexeme<String>>,
+        words_forgotten: Vec<Lexeme<String>>,
+        words_tapped: Vec<Lexeme<String>>,
+    },
+    /// For `TranscribeComprehensibleSentence`
+    Transcription {
+        graded: Vec<PartGraded>,
+        input_mode: Option<InputMode>,
+    },
+    /// For `DisambiguateHeteronym`
+    Disambiguation { picked: Heteronym<String> },
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+impl Deck {
+    /// The event for `challenge` having gone as `outcome` says. `None` if `outcome` is for another
+    /// kind of challenge, or the challenge's card isn't in the deck.
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn grade_challenge(
+        &self,
+        challenge: Challenge<String>,
+        outcome: ChallengeOutcome,
+    ) -> Option<DeckEvent> {
+        match (challenge, outcome) {
+            (
+                Challenge::FlashCardReview {
+                    indicator,
+                    warmup: true,
+                    ..
+                },
+                ChallengeOutcome::FlashCard { rating },
+            ) => self.warmup_review(indicator, rating != Rating::Again),
+            (
+                Challenge::FlashCardReview { indicator, .. },
+                ChallengeOutcome::FlashCard { rating },
+            ) => self.review_card(indicator, rating),
+            (
+                Challenge::TranslateComprehensibleSentence(challenge),
+                outcome @ (ChallengeOutcome::TranslationPerfect { .. }
+                | ChallengeOutcome::TranslationWrong { .. }),
+            ) if challenge.sentence_repetition => self.repeat_sentence(
+                challenge.target_language,
+                matches!(outcome, ChallengeOutcome::TranslationPerfect { .. }),
+            ),
+            (
+                Challenge::TranslateComprehensibleSentence(challenge),
+                ChallengeOutcome::TranslationPerfect { words_tapped },
+            ) => match challenge.source {
+                Some(_) => self.translate_sentence_perfect(
+                    words_tapped,
+                    challenge.target_language,
+                    Some(challenge.favorite_practice),
+                ),
+                // Generated sentences aren't in the language pack, so their lexemes go in the event
+                None => self.translate_generated_sentence_perfect(
+                    words_tapped,
+                    challenge.target_language,
+                    challenge.unique_target_language_lexemes,
+                ),
+            },
+            (
+                Challenge::TranslateComprehensibleSentence(challenge),
+                ChallengeOutcome::TranslationWrong {
+                    submission,
+                    words_remembered,
+                    words_forgotten,
+                    words_tapped,
+                },
+            ) => self.translate_sentence_wrong(
+                challenge.target_language,
+                submission,
+                words_remembered,
+                words_forgotten,
+                words_tapped,
+                Some(challenge.favorite_practice),
+            ),
+            (
+                Challenge::TranscribeComprehensibleSentence(challenge),
+                ChallengeOutcome::Transcription { graded, input_mode },
+            ) => self.transcribe_sentence(graded, Some(challenge.level), input_mode),
+            (
+                Challenge::DisambiguateHeteronym(challenge),
+                ChallengeOutcome::Disambiguation { picked },
+            ) => self.disambiguate_heteronym(challenge.answer, picked),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{AudioRequest, CardContent, CardIndicator, HintPolicy};
+    use crate::{TranscribeComprehensibleSentence, TranslateComprehensibleSentence};
+    use language_utils::transcription_challenge::DictationLevel;
+    use language_utils::{Language, SentenceSource, TtsProvider, TtsRequest};
+
+    fn audio() -> AudioRequest {
+        AudioRequest {
+            request: TtsRequest {
+                text: "Bonjour".to_string(),
+                language: Language::French,
+            },
+            provider: TtsProvider::Google,
+        }
+    }
+
+    fn translation(source: Option<SentenceSource>) -> Challenge<String> {
+        let bonjour = Lexeme::Multiword("bonjour".to_string());
+        Challenge::TranslateComprehensibleSentence(TranslateComprehensibleSentence {
+            audio: audio(),
+            target_language: "Bonjour".to_string(),
+            target_language_literals: Vec::new(),
+            primary_expression: bonjour.clone(),
+            unique_target_language_lexemes: vec![bonjour],
+            unique_target_language_lexeme_definitions: Vec::new(),
+            native_translations: vec!["Hello".to_string()],
+            source,
+            movie_titles: Vec::new(),
+            favorite_practice: true,
+            sentence_repetition: false,
+            explanation: None,
+            hint_policy: HintPolicy::default(),
+        })
+    }
+
+    #[test]
+    fn test_outcomes_grade_like_the_event_constructors() {
+        let deck = Deck::default();
+        let tapped = vec![Lexeme::Multiword("bonjour".to_string())];
+
+        assert_eq!(
+            deck.grade_challenge(
+                translation(Some(SentenceSource::none())),
+                ChallengeOutcome::TranslationPerfect {
+                    words_tapped: tapped.clone(),
+                },
+            ),
+            deck.translate_sentence_perfect(tapped.clone(), "Bonjour".to_string(), Some(true))
+        );
+        assert_eq!(
+            deck.grade_challenge(
+                translation(None),
+                ChallengeOutcome::TranslationPerfect {
+                    words_tapped: tapped.clone(),
+                },
+            ),
+            deck.translate_generated_sentence_perfect(
+                tapped.clone(),
+                "Bonjour".to_string(),
+                tapped.clone(),
+            )
+        );
+        assert_eq!(
+            deck.grade_challenge(
+                Challenge::TranscribeComprehensibleSentence(TranscribeComprehensibleSentence {
+                    target_language: "Bonjour".to_string(),
+                    audio: audio(),
+                    native_language: "Hello".to_string(),
+                    parts: Vec::new(),
+                    source: None,
+                    movie_titles: Vec::new(),
+                    level: DictationLevel::PhraseChunk,
+                    word_audio: Vec::new(),
+                    word_bank: Vec::new(),
+                    explanation: None,
+                }),
+                ChallengeOutcome::Transcription {
+                    graded: Vec::new(),
+                    input_mode: None,
+                },
+            ),
+            deck.transcribe_sentence(Vec::new(), Some(DictationLevel::PhraseChunk), None)
+        );
+    }
+
+    #[test]
+    fn test_outcomes_for_another_kind_of_challenge_are_rejected() {
+        let deck = Deck::default();
+        let flashcard = Challenge::FlashCardReview {
+            indicator: CardIndicator::TargetLanguage {
+                lexeme: Lexeme::Multiword("bonjour".to_string()),
+            },
+            content: CardContent::Listening {
+                pronunciation: "bɔ̃ʒuʁ".to_string(),
+                possible_words: Vec::new(),
+            },
+            audio: None,
+            is_new: false,
+            listening_prefix: None,
+            warmup: false,
+        };
+        assert_eq!(
+            deck.grade_challenge(
+                flashcard,
+                ChallengeOutcome::TranslationPerfect {
+                    words_tapped: Vec::new(),
+                },
+            ),
+            None
+        );
+        assert_eq!(
+            deck.grade_challenge(
+                translation(None),
+                ChallengeOutcome::FlashCard {
+                    rating: Rating::Good,
+                },
+            ),
+            None
+        );
+    }
+}
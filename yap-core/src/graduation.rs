@@ -0,0 +1,181 @@
+//! Finishing a course. A course's language pack says what it takes (see
+//! `language_utils::course_completion`), and a user who meets the criteria can graduate with
+//! `Deck::graduate`. That switches the deck to `SchedulingProfile::Maintenance`, so the user keeps
+//! what they've learned with a few reviews a day.
+
+use chrono::{DateTime, Utc};
+use language_utils::course_completion::CompletionCriteria;
+use serde::{Deserialize, Serialize};
+#[cfg(target_arch = "wasm32")]
+use wasm_bindgen::prelude::*;
+
+use crate::scheduler::SchedulingProfile;
+use crate::{
+    CardData, CardIndicator, CardStatus, Deck, DeckEvent, LanguageEvent, LanguageEventContent,
+};
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq)]
+pub(crate) struct Graduation {
+    /// When the user first graduated. Switching back to `SchedulingProfile::Standard` doesn't
+    /// undo it.
+    pub(crate) graduated_at: DateTime<Utc>,
+}
+
+/// How close the user is to finishing the course, see `Deck::get_completion_status`
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(target_arch = "wasm32", derive(tsify::Tsify))]
+#[cfg_attr(target_arch = "wasm32", tsify(into_wasm_abi))]
+pub struct CompletionStatus {
+    /// Words (including multiword terms) in FSRS's review state
+    pub words_in_review: u32,
+    pub words_required: u32,
+    /// Pronunciation pattern cards in the review state
+    pub patterns_learned: u32,
+    /// 0 if the course doesn't ask for pronunciation patterns, or doesn't have any
+    pub patterns_required: u32,
+    /// Whether the user meets the course's criteria, and can graduate
+    pub complete: bool,
+    pub graduated_at_ms: Option<f64>,
+}
+
+fn in_review(card_status: &CardStatus) -> bool {
+    matches!(
+        card_status,
+        CardStatus::Tracked(CardData::Added { fsrs_card } | CardData::Ghost { fsrs_card })
+            if fsrs_card.state == rs_fsrs::State::Review
+    )
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+impl Deck {
+    /// How close the user is to meeting the course's completion criteria, and whether they've
+    /// graduated
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn get_completion_status(&self) -> CompletionStatus {
+        let criteria = self
+            .context
+            .language_pack
+            .completion_criteria
+            .clone()
+            .unwrap_or_default();
+        self.completion_status(&criteria)
+    }
+
+    fn completion_status(&self, criteria: &CompletionCriteria) -> CompletionStatus {
+        let language_pack = &self.context.language_pack;
+        let words_in_review = self
+            .cards
+            .iter()
+            .filter(|(card, card_status)| {
+                matches!(card, CardIndicator::TargetLanguage { .. })
+                    && !self.orphaned.contains(card)
+                    && in_review(card_status)
+            })
+            .count() as u32;
+
+        let pattern_cards = language_pack
+            .pronunciation_data
+            .pattern_frequencies
+            .iter()
+            .filter_map(|((pattern, position), _)| {
+                Some(CardIndicator::LetterPronunciation {
+                    pattern: language_pack.rodeo.get(pattern)?,
+                    position: *position,
+                })
+            })
+            .collect::<Vec<_>>();
+        let patterns_learned = pattern_cards
+            .iter()
+            .filter(|card| self.cards.get(card).is_some_and(in_review))
+            .count() as u32;
+        let patterns_required = if criteria.pronunciation_patterns {
+            pattern_cards.len() as u32
+        } else {
+            0
+        };
+
+        CompletionStatus {
+            words_in_review,
+            words_required: criteria.words_in_review,
+            patterns_learned,
+            patterns_required,
+            complete: words_in_review >= criteria.words_in_review
+                && patterns_learned >= patterns_required,
+            graduated_at_ms: self
+                .stats
+                .graduation
+                .map(|graduation| graduation.graduated_at.timestamp_millis() as f64),
+        }
+    }
+
+    /// Graduates from the course, switching the deck to `SchedulingProfile::Maintenance`. `None`
+    /// if the user doesn't meet the course's completion criteria yet, or the deck already uses
+    /// that profile. The user can keep learning new words afterwards with
+    /// `set_scheduling_profile`.
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn graduate(&self) -> Option<DeckEvent> {
+        (self.get_completion_status().complete
+            && self.scheduling_profile != SchedulingProfile::Maintenance)
+            .then_some(DeckEvent::Language(LanguageEvent {
+                target_language: self.context.target_language,
+                native_language: self.context.native_language,
+                content: LanguageEventContent::Graduate {},
+            }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::datetime_from_ms;
+    use weapon::AppState;
+    use weapon::data_model::Timestamped;
+
+    #[test]
+    fn test_graduating_switches_to_the_maintenance_profile() {
+        let deck = Deck::default();
+        assert!(!deck.get_completion_status().complete);
+        assert_eq!(deck.graduate(), None);
+
+        let criteria = CompletionCriteria {
+            words_in_review: 0,
+            pronunciation_patterns: false,
+        };
+        assert!(deck.completion_status(&criteria).complete);
+
+        // The event is applied as synced, since the default criteria aren't met
+        let graduated_at_ms = 1_760_000_000_000.0;
+        let deck = deck.apply_event(&Timestamped {
+            timestamp: datetime_from_ms(graduated_at_ms),
+            within_device_events_index: 0,
+            event: DeckEvent::Language(LanguageEvent {
+                target_language: deck.context.target_language,
+                native_language: deck.context.native_language,
+                content: LanguageEventContent::Graduate {},
+            }),
+        });
+        assert_eq!(
+            deck.get_completion_status().graduated_at_ms,
+            Some(graduated_at_ms)
+        );
+        assert_eq!(
+            deck.get_scheduling_profile(),
+            SchedulingProfile::Maintenance
+        );
+
+        // Going back to learning new words doesn't undo the graduation
+        let event = deck
+            .set_scheduling_profile(SchedulingProfile::Standard)
+            .unwrap();
+        let deck = deck.apply_event(&Timestamped {
+            timestamp: datetime_from_ms(graduated_at_ms + 1000.0),
+            within_device_events_index: 0,
+            event,
+        });
+        assert_eq!(
+            deck.get_completion_status().graduated_at_ms,
+            Some(graduated_at_ms)
+        );
+        assert_eq!(deck.get_scheduling_profile(), SchedulingProfile::Standard);
+    }
+}
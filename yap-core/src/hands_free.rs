@@ -0,0 +1,195 @@
+//! Hands-free review, for when the user can listen but not look at the screen or type (e.g. while
+//! commuting). A session only has listening cards, heard on their own or in short sentences, and
+//! each is graded by the user saying or tapping whether they understood it, which is recorded as
+//! a `LanguageEventContent::HandsFreeReview`.
+//!
+//! Understanding something is weaker evidence than transcribing it, so an understood card is
+//! scheduled as `Rating::Hard`, like the easiest dictation level (see
+//! `challenges::scale_listening_rating`).
+
+use chrono::{DateTime, Utc};
+use language_utils::text_cleanup;
+use serde::{Deserialize, Serialize};
+#[cfg(target_arch = "wasm32")]
+use wasm_bindgen::prelude::*;
+
+use crate::{
+    CardIndicator, CardStatus, Challenge, ChallengeRequirements, DataMismatchKind, Deck, DeckEvent,
+    DeckState, LanguageEvent, LanguageEventContent, Rating, ReviewInfo, SentenceLength,
+};
+
+/// How long to wait after a word before revealing it
+const WORD_PAUSE_MS: u32 = 3_000;
+
+/// How long to wait after a sentence before revealing it, per word in the sentence
+const SENTENCE_PAUSE_MS_PER_WORD: u32 = 700;
+
+/// A challenge in a hands-free session, see `ReviewInfo::get_next_hands_free_challenge`
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[cfg_attr(target_arch = "wasm32", derive(tsify::Tsify))]
+#[cfg_attr(target_arch = "wasm32", tsify(into_wasm_abi))]
+pub struct HandsFreeChallenge {
+    /// The card to grade with `Deck::hands_free_review`
+    pub card: CardIndicator<String>,
+    /// The sentence the card is heard in, if it isn't heard on its own
+    pub sentence: Option<String>,
+    /// What to play, and the answer to reveal after `pause_ms`
+    pub challenge: Challenge<String>,
+    /// How long to wait between the audio and the answer, longer than in a normal session since
+    /// the user has to answer out loud or with a tap
+    pub pause_ms: u32,
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+impl Deck {
+    /// A hands-free session over the listening cards due at `timestamp_ms`. Get its challenges
+    /// with `ReviewInfo::get_next_hands_free_challenge`.
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn get_hands_free_review_info(&self, timestamp_ms: f64) -> ReviewInfo {
+        let mut review_info = self.get_review_info(
+            vec![ChallengeRequirements::Text, ChallengeRequirements::Speaking],
+            timestamp_ms,
+        );
+        review_info.set_sentence_length(SentenceLength::Short);
+        review_info
+    }
+
+    /// Grades a hands-free challenge. Returns `None` if the card isn't in the deck.
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn hands_free_review(
+        &self,
+        reviewed: CardIndicator<String>,
+        sentence: Option<String>,
+        understood: bool,
+    ) -> Option<DeckEvent> {
+        let indicator = reviewed.get_interned(&self.context.language_pack.rodeo)?;
+        matches!(self.cards.get(&indicator), Some(CardStatus::Tracked(_))).then_some(
+            DeckEvent::Language(LanguageEvent {
+                target_language: self.context.target_language,
+                native_language: self.context.native_language,
+                content: LanguageEventContent::HandsFreeReview {
+                    reviewed,
+                    sentence,
+                    understood,
+                },
+            }),
+        )
+    }
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+impl ReviewInfo {
+    /// Like `get_next_challenge`, but only for listening cards, and with how long to pause before
+    /// the answer. Works best on a `Deck::get_hands_free_review_info`, which keeps sentences short.
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn get_next_hands_free_challenge(&self, deck: &Deck) -> Option<HandsFreeChallenge> {
+        let listening_cards = self
+            .due_cards
+            .iter()
+            .chain(&self.ahead_cards)
+            .filter(|card| card.card_type().challenge_type() == ChallengeRequirements::Listening);
+        let (card, challenge) = self.first_buildable_challenge(deck, listening_cards, false)?;
+
+        let sentence = match &challenge {
+            Challenge::TranscribeComprehensibleSentence(challenge) => {
+                Some(challenge.target_language.clone())
+            }
+            _ => None,
+        };
+        let pause_ms = sentence.as_ref().map_or(WORD_PAUSE_MS, |sentence| {
+            let words = sentence.split_whitespace().count() as u32;
+            (words * SENTENCE_PAUSE_MS_PER_WORD).max(WORD_PAUSE_MS)
+        });
+        Some(HandsFreeChallenge {
+            card: card.resolve(&deck.context.language_pack.rodeo),
+            sentence,
+            challenge,
+            pause_ms,
+        })
+    }
+}
+
+impl DeckState {
+    pub(crate) fn log_hands_free_review(
+        &mut self,
+        reviewed: &CardIndicator<String>,
+        sentence: Option<&str>,
+        understood: bool,
+        timestamp: DateTime<Utc>,
+    ) {
+        if let Some(reviewed) = self.data_mismatches.check(
+            self.context.intern_card(reviewed),
+            DataMismatchKind::Card,
+            || format!("{reviewed:?}"),
+        ) {
+            let rating = if understood {
+                Rating::Hard
+            } else {
+                Rating::Again
+            };
+            self.log_review(reviewed, rating, timestamp);
+        }
+
+        // Counted like any other sentence, so the next session picks different ones
+        if let Some(sentence) = sentence {
+            let cleaned_sentence =
+                text_cleanup::cleanup_sentence(sentence.to_string(), self.context.target_language);
+            if let Some(sentence) = self.data_mismatches.check(
+                self.context.language_pack.rodeo.get(&cleaned_sentence),
+                DataMismatchKind::Sentence,
+                || cleaned_sentence.clone(),
+            ) {
+                *self.stats.sentences_reviewed.entry(sentence).or_insert(0) += 1;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::CardData;
+    use language_utils::Lexeme;
+    use weapon::AppState;
+    use weapon::data_model::Timestamped;
+
+    #[test]
+    fn test_hands_free_sessions_only_have_listening_cards() {
+        let now = DateTime::<Utc>::from_timestamp(1_760_000_000, 0).unwrap();
+        let mut deck = Deck::default();
+        let pack = deck.context.language_pack.clone();
+        let lexeme = *pack
+            .word_frequencies
+            .keys()
+            .find(|lexeme| matches!(lexeme, Lexeme::Heteronym(_)))
+            .unwrap();
+        let listening = CardIndicator::ListeningLexeme { lexeme };
+        let text = CardIndicator::TargetLanguage { lexeme };
+        for card in [listening, text] {
+            let mut fsrs_card = rs_fsrs::Card::new(now - chrono::Duration::days(1));
+            fsrs_card.due = now - chrono::Duration::days(1);
+            deck.cards
+                .insert(card, CardStatus::Tracked(CardData::Added { fsrs_card }));
+        }
+
+        let review_info = deck.get_hands_free_review_info(now.timestamp_millis() as f64);
+        let challenge = review_info.get_next_hands_free_challenge(&deck).unwrap();
+        assert_eq!(challenge.card, listening.resolve(&pack.rodeo));
+        assert!(challenge.pause_ms >= WORD_PAUSE_MS);
+
+        let event = deck
+            .hands_free_review(challenge.card, challenge.sentence, true)
+            .unwrap();
+        let deck = deck.apply_event(&Timestamped {
+            timestamp: now,
+            within_device_events_index: 0,
+            event,
+        });
+        let Some(CardStatus::Tracked(CardData::Added { fsrs_card })) = deck.cards.get(&listening)
+        else {
+            panic!("the card should still be added");
+        };
+        assert!(fsrs_card.due > now);
+        assert_eq!(deck.stats.total_reviews, 1);
+    }
+}
@@ -0,0 +1,197 @@
+//! When to offer hints in translation challenges. Tapping a word for its meaning marks the word as
+//! forgotten, so a user who taps out of habit keeps their words from ever spacing out, while a user
+//! who's lost doesn't get anything out of struggling on alone. The deck keeps track of how the
+//! latest translations went, and each translation challenge comes with a `HintPolicy` saying how
+//! long the UI should wait before showing the hint button.
+
+use std::collections::VecDeque;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{LanguageEventContent, SentenceReviewIndicator, SentenceReviewResult};
+
+/// How many of the latest translations the policy is based on
+const RECENT_TRANSLATIONS: usize = 20;
+/// With fewer translations than this to go on, the default policy is used
+const MIN_TRANSLATIONS: usize = 5;
+
+const DEFAULT_DELAY_SECONDS: u32 = 3;
+/// For users who lean on hints, long enough to give recalling the word a real try
+const LEANS_ON_HINTS_DELAY_SECONDS: u32 = 10;
+
+/// The share of translations with a hint above which the user leans on hints
+const LEANS_ON_HINTS_RATE: f64 = 0.6;
+/// The share of translations gotten wrong above which the user is struggling. It takes priority
+/// over leaning on hints, since then the hints aren't a habit but needed.
+const STRUGGLING_RATE: f64 = 0.4;
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq)]
+struct TranslationOutcome {
+    used_hints: bool,
+    wrong: bool,
+}
+
+/// The latest translations, tracked as events are processed
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq)]
+pub(crate) struct HintUsage {
+    /// Oldest first, at most `RECENT_TRANSLATIONS`
+    recent: VecDeque<TranslationOutcome>,
+}
+
+impl HintUsage {
+    pub(crate) fn record(&mut self, event: &LanguageEventContent) {
+        let LanguageEventContent::TranslationChallenge {
+            review: SentenceReviewIndicator::TargetToNative { result, .. },
+            ..
+        } = event
+        else {
+            return;
+        };
+        let outcome = match result {
+            SentenceReviewResult::Perfect {
+                lexemes_needed_hint,
+                ..
+            } => TranslationOutcome {
+                used_hints: !lexemes_needed_hint.is_empty(),
+                wrong: false,
+            },
+            SentenceReviewResult::Wrong {
+                lexemes_needed_hint,
+                ..
+            } => TranslationOutcome {
+                used_hints: !lexemes_needed_hint.is_empty(),
+                wrong: true,
+            },
+        };
+        self.recent.push_back(outcome);
+        if self.recent.len() > RECENT_TRANSLATIONS {
+            self.recent.pop_front();
+        }
+    }
+
+    pub(crate) fn policy(&self) -> HintPolicy {
+        if self.recent.len() < MIN_TRANSLATIONS {
+            return HintPolicy::default();
+        }
+        let share = |f: fn(&TranslationOutcome) -> bool| {
+            self.recent.iter().filter(|outcome| f(outcome)).count() as f64
+                / self.recent.len() as f64
+        };
+
+        if share(|outcome| outcome.wrong) > STRUGGLING_RATE {
+            HintPolicy {
+                delay_seconds: 0,
+                reason: HintPolicyReason::Struggling,
+            }
+        } else if share(|outcome| outcome.used_hints) > LEANS_ON_HINTS_RATE {
+            HintPolicy {
+                delay_seconds: LEANS_ON_HINTS_DELAY_SECONDS,
+                reason: HintPolicyReason::LeansOnHints,
+            }
+        } else {
+            HintPolicy::default()
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[cfg_attr(target_arch = "wasm32", derive(tsify::Tsify))]
+#[cfg_attr(target_arch = "wasm32", tsify(into_wasm_abi, from_wasm_abi))]
+pub enum HintPolicyReason {
+    /// Too few recent translations to tell, or nothing stands out about them
+    #[default]
+    Default,
+    /// Most recent translations used a hint, but few were wrong
+    LeansOnHints,
+    /// Many recent translations were wrong
+    Struggling,
+}
+
+/// When the UI should show the hint button in a translation challenge
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[cfg_attr(target_arch = "wasm32", derive(tsify::Tsify))]
+#[cfg_attr(target_arch = "wasm32", tsify(into_wasm_abi, from_wasm_abi))]
+pub struct HintPolicy {
+    /// How long after the challenge appears. 0 shows it right away.
+    pub delay_seconds: u32,
+    pub reason: HintPolicyReason,
+}
+
+impl Default for HintPolicy {
+    fn default() -> Self {
+        Self {
+            delay_seconds: DEFAULT_DELAY_SECONDS,
+            reason: HintPolicyReason::Default,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Deck, DeckEvent, LanguageEvent, datetime_from_ms};
+    use language_utils::Lexeme;
+    use weapon::AppState;
+    use weapon::data_model::Timestamped;
+
+    fn translate(deck: Deck, index: usize, words_tapped: Vec<Lexeme<String>>, wrong: bool) -> Deck {
+        let content = if wrong {
+            LanguageEventContent::translation_wrong(
+                "Bonjour".to_string(),
+                "Goodbye".to_string(),
+                Vec::new(),
+                Vec::new(),
+                words_tapped,
+                false,
+            )
+        } else {
+            let Some(DeckEvent::Language(event)) =
+                deck.translate_sentence_perfect(words_tapped, "Bonjour".to_string(), None)
+            else {
+                unreachable!()
+            };
+            event.content
+        };
+        let event = DeckEvent::Language(LanguageEvent {
+            target_language: deck.context.target_language,
+            native_language: deck.context.native_language,
+            content,
+        });
+        deck.apply_event(&Timestamped {
+            timestamp: datetime_from_ms(1_760_000_000_000.0 + index as f64 * 60_000.0),
+            within_device_events_index: index,
+            event,
+        })
+    }
+
+    #[test]
+    fn test_hints_are_delayed_for_habitual_users_and_offered_early_when_struggling() {
+        let tapped = || vec![Lexeme::Multiword("bonjour".to_string())];
+
+        let mut deck = Deck::default();
+        for index in 0..MIN_TRANSLATIONS - 1 {
+            deck = translate(deck, index, tapped(), false);
+        }
+        assert_eq!(deck.stats.hint_usage.policy(), HintPolicy::default());
+
+        for index in MIN_TRANSLATIONS - 1..RECENT_TRANSLATIONS {
+            deck = translate(deck, index, tapped(), false);
+        }
+        assert_eq!(
+            deck.stats.hint_usage.policy().reason,
+            HintPolicyReason::LeansOnHints
+        );
+
+        // Only the latest translations count, so the habit is forgotten as the user gets worse
+        for index in RECENT_TRANSLATIONS..RECENT_TRANSLATIONS + 10 {
+            deck = translate(deck, index, Vec::new(), true);
+        }
+        assert_eq!(
+            deck.stats.hint_usage.policy(),
+            HintPolicy {
+                delay_seconds: 0,
+                reason: HintPolicyReason::Struggling,
+            }
+        );
+    }
+}
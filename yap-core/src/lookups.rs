@@ -0,0 +1,202 @@
+//! The vocabulary notebook. Opening a word's details during a sentence challenge records a
+//! `LanguageEventContent::LookedUp`, and `Deck::get_lookup_notebook` groups the recent lookups by
+//! word. A word the user keeps checking is one they'd probably like to learn, so each entry comes
+//! with the event that adds its card.
+
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, Duration, Utc};
+use language_utils::Lexeme;
+use lasso::Spur;
+use serde::{Deserialize, Serialize};
+#[cfg(target_arch = "wasm32")]
+use wasm_bindgen::prelude::*;
+
+use crate::{
+    CardData, CardIndicator, CardStatus, Deck, DeckEvent, LanguageEvent, LanguageEventContent,
+    datetime_from_ms,
+};
+
+/// Only the most recent lookups of each word are kept
+const MAX_LOOKUPS_PER_WORD: usize = 50;
+
+/// How far back the notebook counts lookups
+const NOTEBOOK_WINDOW_DAYS: i64 = 30;
+
+#[derive(Clone, Debug, Default)]
+pub(crate) struct LookupHistory {
+    pub(crate) words: BTreeMap<Lexeme<Spur>, WordLookups>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub(crate) struct WordLookups {
+    /// Oldest first, at most `MAX_LOOKUPS_PER_WORD`
+    pub(crate) times: Vec<DateTime<Utc>>,
+    /// The sentence the word was last looked up in
+    pub(crate) last_sentence: String,
+}
+
+impl LookupHistory {
+    pub(crate) fn record(
+        &mut self,
+        lexeme: Lexeme<Spur>,
+        sentence: &str,
+        timestamp: DateTime<Utc>,
+    ) {
+        let lookups = self.words.entry(lexeme).or_insert_with(|| WordLookups {
+            times: Vec::new(),
+            last_sentence: String::new(),
+        });
+        lookups.times.push(timestamp);
+        if lookups.times.len() > MAX_LOOKUPS_PER_WORD {
+            lookups.times.remove(0);
+        }
+        lookups.last_sentence = sentence.to_string();
+    }
+}
+
+/// A word in the vocabulary notebook, see `Deck::get_lookup_notebook`
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(target_arch = "wasm32", derive(tsify::Tsify))]
+#[cfg_attr(target_arch = "wasm32", tsify(into_wasm_abi))]
+pub struct LookedUpWord {
+    pub lexeme: Lexeme<String>,
+    /// How many times it was looked up in the past `NOTEBOOK_WINDOW_DAYS` days
+    pub recent_lookups: u32,
+    pub last_looked_up_ms: f64,
+    /// The sentence it was last looked up in
+    pub last_sentence: String,
+    /// Adds the word's card, or `None` if it's already in the deck
+    pub add_card: Option<DeckEvent>,
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+impl Deck {
+    /// Records that the user opened `lexeme`'s details while doing a challenge on `sentence`.
+    /// Returns `None` if the language pack doesn't have the word.
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn look_up(&self, lexeme: Lexeme<String>, sentence: String) -> Option<DeckEvent> {
+        self.context.intern_lexeme(&lexeme)?;
+        Some(DeckEvent::Language(LanguageEvent {
+            target_language: self.context.target_language,
+            native_language: self.context.native_language,
+            content: LanguageEventContent::LookedUp { lexeme, sentence },
+        }))
+    }
+
+    /// The words looked up in the past `NOTEBOOK_WINDOW_DAYS` days before `timestamp_ms`, most
+    /// looked up first, and most recently looked up among equals
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn get_lookup_notebook(&self, timestamp_ms: f64) -> Vec<LookedUpWord> {
+        let now = datetime_from_ms(timestamp_ms);
+        let since = now - Duration::days(NOTEBOOK_WINDOW_DAYS);
+        let rodeo = &self.context.language_pack.rodeo;
+
+        let mut notebook = self
+            .stats
+            .lookups
+            .words
+            .iter()
+            .filter_map(|(lexeme, lookups)| {
+                let recent_lookups = lookups
+                    .times
+                    .iter()
+                    .filter(|time| **time > since && **time <= now)
+                    .count() as u32;
+                let last_looked_up = lookups.times.iter().filter(|time| **time <= now).max()?;
+                (recent_lookups > 0).then(|| LookedUpWord {
+                    lexeme: lexeme.resolve(rodeo),
+                    recent_lookups,
+                    last_looked_up_ms: last_looked_up.timestamp_millis() as f64,
+                    last_sentence: lookups.last_sentence.clone(),
+                    add_card: self.add_looked_up_card(*lexeme),
+                })
+            })
+            .collect::<Vec<_>>();
+        notebook.sort_by(|a, b| {
+            b.recent_lookups
+                .cmp(&a.recent_lookups)
+                .then(b.last_looked_up_ms.total_cmp(&a.last_looked_up_ms))
+        });
+        notebook
+    }
+}
+
+impl Deck {
+    fn add_looked_up_card(&self, lexeme: Lexeme<Spur>) -> Option<DeckEvent> {
+        let card = CardIndicator::TargetLanguage { lexeme };
+        let added = matches!(
+            self.cards.get(&card),
+            Some(CardStatus::Tracked(CardData::Added { .. }))
+        );
+        (!added && self.context.is_card_valid(&card)).then(|| {
+            DeckEvent::Language(LanguageEvent {
+                target_language: self.context.target_language,
+                native_language: self.context.native_language,
+                content: LanguageEventContent::AddCards {
+                    cards: vec![card.resolve(&self.context.language_pack.rodeo)],
+                },
+            })
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use weapon::AppState;
+    use weapon::data_model::Timestamped;
+
+    #[test]
+    fn test_words_looked_up_often_come_first() {
+        let now = DateTime::<Utc>::from_timestamp(1_760_000_000, 0).unwrap();
+        let mut deck = Deck::default();
+        let mut words = deck
+            .context
+            .language_pack
+            .word_frequencies
+            .keys()
+            .map(|lexeme| lexeme.resolve(&deck.context.language_pack.rodeo));
+        let (often, once, long_ago) = (
+            words.next().unwrap(),
+            words.next().unwrap(),
+            words.next().unwrap(),
+        );
+
+        let lookups = [
+            (&long_ago, now - Duration::days(NOTEBOOK_WINDOW_DAYS + 1)),
+            (&often, now - Duration::days(3)),
+            (&once, now - Duration::days(2)),
+            (&often, now - Duration::days(1)),
+        ];
+        for (index, (lexeme, timestamp)) in lookups.into_iter().enumerate() {
+            let event = deck
+                .look_up(lexeme.clone(), "Un exemple.".to_string())
+                .unwrap();
+            deck = deck.apply_event(&Timestamped {
+                timestamp,
+                within_device_events_index: index,
+                event,
+            });
+        }
+
+        let notebook = deck.get_lookup_notebook(now.timestamp_millis() as f64);
+        assert_eq!(
+            notebook
+                .iter()
+                .map(|word| (word.lexeme.clone(), word.recent_lookups))
+                .collect::<Vec<_>>(),
+            vec![(often.clone(), 2), (once, 1)]
+        );
+
+        let add_card = notebook[0].add_card.clone().unwrap();
+        let deck = deck.apply_event(&Timestamped {
+            timestamp: now,
+            within_device_events_index: lookups.len(),
+            event: add_card,
+        });
+        let notebook = deck.get_lookup_notebook(now.timestamp_millis() as f64);
+        assert_eq!(notebook[0].lexeme, often);
+        assert_eq!(notebook[0].add_card, None);
+    }
+}
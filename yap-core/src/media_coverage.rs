@@ -0,0 +1,336 @@
+//! How much of a list of media the user would understand: the movies in a watchlist, or a word
+//! list from somewhere else (e.g. a book's frequency list). Besides the overall coverage, it ranks
+//! the unknown words by how much of the list they make up, and picks the fewest words to learn so
+//! every item on the list reaches a target coverage.
+
+use std::collections::BinaryHeap;
+use std::hash::Hash;
+
+use language_utils::Lexeme;
+use lasso::Spur;
+use rustc_hash::{FxHashMap, FxHashSet};
+use serde::{Deserialize, Serialize};
+#[cfg(target_arch = "wasm32")]
+use wasm_bindgen::prelude::*;
+
+use crate::{CardIndicator, Deck};
+
+/// How many of the unknown words `MediaCoverage` lists
+const UNKNOWN_WORDS_LISTED: usize = 100;
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(target_arch = "wasm32", derive(tsify::Tsify))]
+#[cfg_attr(target_arch = "wasm32", tsify(into_wasm_abi, from_wasm_abi))]
+pub struct WordListEntry {
+    pub lexeme: Lexeme<String>,
+    /// How many times the word appears in the media
+    #[serde(default = "one")]
+    pub count: u32,
+}
+
+fn one() -> u32 {
+    1
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(target_arch = "wasm32", derive(tsify::Tsify))]
+#[cfg_attr(target_arch = "wasm32", tsify(into_wasm_abi))]
+pub struct MediaCoverage {
+    /// The percent of all the words in the list the user would understand
+    pub percent_known: f64,
+    pub total_words: u64,
+    /// The words the user doesn't know that appear the most across the whole list, most common
+    /// first
+    pub unknown_words: Vec<UnknownWord>,
+    /// The fewest words to learn for every item on the list to reach the target coverage, in the
+    /// order to learn them. Empty if they already have.
+    pub words_to_target: Vec<Lexeme<String>>,
+    /// Whether learning `words_to_target` is enough. It isn't if too much of an item is words
+    /// there aren't cards for.
+    pub reaches_target: bool,
+    /// Movies the language pack doesn't have, which were left out
+    pub skipped_movies: Vec<String>,
+    /// Words from a word list the language pack doesn't know, which were left out
+    pub skipped_words: Vec<Lexeme<String>>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(target_arch = "wasm32", derive(tsify::Tsify))]
+#[cfg_attr(target_arch = "wasm32", tsify(into_wasm_abi))]
+pub struct UnknownWord {
+    pub lexeme: Lexeme<String>,
+    pub count: u64,
+    /// How many percentage points `percent_known` would go up by if the user learned it
+    pub percent_of_words: f64,
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+impl Deck {
+    /// Coverage of the movies in `movie_ids` taken together. `target_percent` is the coverage each
+    /// movie should reach, e.g. 95.0.
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn get_movie_list_coverage(
+        &self,
+        movie_ids: Vec<String>,
+        target_percent: f64,
+    ) -> MediaCoverage {
+        let language_pack = &self.context.language_pack;
+        let mut items = Vec::new();
+        let mut skipped = Vec::new();
+        for movie_id in movie_ids {
+            match language_pack.movie_word_frequencies.get(&movie_id) {
+                Some(frequencies) => items.push(
+                    frequencies
+                        .iter()
+                        .map(|(lexeme, frequency)| (*lexeme, frequency.count as u64))
+                        .collect(),
+                ),
+                None => skipped.push(movie_id),
+            }
+        }
+        MediaCoverage {
+            skipped_movies: skipped,
+            ..self.media_coverage(items, target_percent)
+        }
+    }
+
+    /// Coverage of a word list from outside the language pack, e.g. a book's. Words the pack
+    /// doesn't know are left out.
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn get_word_list_coverage(
+        &self,
+        words: Vec<WordListEntry>,
+        target_percent: f64,
+    ) -> MediaCoverage {
+        let rodeo = &self.context.language_pack.rodeo;
+        let mut counts: FxHashMap<Lexeme<Spur>, u64> = FxHashMap::default();
+        let mut skipped = Vec::new();
+        for entry in words {
+            match entry.lexeme.get_interned(rodeo) {
+                Some(lexeme) => *counts.entry(lexeme).or_default() += u64::from(entry.count),
+                None => skipped.push(entry.lexeme),
+            }
+        }
+        MediaCoverage {
+            skipped_words: skipped,
+            ..self.media_coverage(vec![counts], target_percent)
+        }
+    }
+}
+
+impl Deck {
+    fn media_coverage(
+        &self,
+        items: Vec<FxHashMap<Lexeme<Spur>, u64>>,
+        target_percent: f64,
+    ) -> MediaCoverage {
+        let known = self.comprehensible_lexemes();
+
+        let mut unknown_counts: FxHashMap<Lexeme<Spur>, u64> = FxHashMap::default();
+        let mut total_words = 0;
+        let mut known_words = 0;
+        for (lexeme, count) in items.iter().flatten() {
+            total_words += count;
+            if known.contains(lexeme) {
+                known_words += count;
+            } else {
+                *unknown_counts.entry(*lexeme).or_default() += count;
+            }
+        }
+
+        let rodeo = &self.context.language_pack.rodeo;
+        let percent = |count: u64| {
+            if total_words == 0 {
+                0.0
+            } else {
+                count as f64 / total_words as f64 * 100.0
+            }
+        };
+        let mut unknown_words = unknown_counts.into_iter().collect::<Vec<_>>();
+        unknown_words.sort_by(|(a, a_count), (b, b_count)| b_count.cmp(a_count).then(a.cmp(b)));
+        let unknown_words = unknown_words
+            .into_iter()
+            .take(UNKNOWN_WORDS_LISTED)
+            .map(|(lexeme, count)| UnknownWord {
+                lexeme: lexeme.resolve(rodeo),
+                count,
+                percent_of_words: percent(count),
+            })
+            .collect();
+
+        let (words_to_target, reaches_target) = words_to_reach(
+            &items,
+            &known,
+            |lexeme| {
+                self.context
+                    .is_card_valid(&CardIndicator::TargetLanguage { lexeme: *lexeme })
+            },
+            (target_percent / 100.0).clamp(0.0, 1.0),
+        );
+
+        MediaCoverage {
+            percent_known: percent(known_words),
+            total_words,
+            unknown_words,
+            words_to_target: words_to_target
+                .into_iter()
+                .map(|lexeme| lexeme.resolve(rodeo))
+                .collect(),
+            reaches_target,
+            skipped_movies: Vec::new(),
+            skipped_words: Vec::new(),
+        }
+    }
+}
+
+/// A word that could be learned next, and how much of the remaining gap it closes
+struct Candidate<L> {
+    gain: f64,
+    lexeme: L,
+}
+
+impl<L: Ord> Ord for Candidate<L> {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.gain
+            .total_cmp(&other.gain)
+            // Ties go to the smaller lexeme, so the result doesn't depend on hash order
+            .then_with(|| other.lexeme.cmp(&self.lexeme))
+    }
+}
+
+impl<L: Ord> PartialOrd for Candidate<L> {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<L: Ord> PartialEq for Candidate<L> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other).is_eq()
+    }
+}
+
+impl<L: Ord> Eq for Candidate<L> {}
+
+/// Greedy set cover: picks the `learnable` word that closes the most of the items' remaining gaps
+/// to `target_fraction` coverage, until every item reaches it. Each item's gap counts as a fraction
+/// of the item, so long items don't crowd out short ones. Returns the words in the order they
+/// were picked, and whether every item reached the target.
+///
+/// A word's gain only shrinks as other words are picked, so gains are recomputed lazily: a word
+/// whose stale gain is at the top of the heap is only taken once its fresh gain still is.
+pub(crate) fn words_to_reach<L: Copy + Eq + Hash + Ord>(
+    items: &[FxHashMap<L, u64>],
+    known: &FxHashSet<L>,
+    learnable: impl Fn(&L) -> bool,
+    target_fraction: f64,
+) -> (Vec<L>, bool) {
+    let totals = items
+        .iter()
+        .map(|item| item.values().sum::<u64>())
+        .collect::<Vec<_>>();
+    let mut gaps = items
+        .iter()
+        .zip(&totals)
+        .map(|(item, total)| {
+            let known_count: u64 = item
+                .iter()
+                .filter(|(lexeme, _)| known.contains(lexeme))
+                .map(|(_, count)| count)
+                .sum();
+            ((target_fraction * *total as f64).ceil() as u64).saturating_sub(known_count)
+        })
+        .collect::<Vec<_>>();
+
+    let mut appearances: FxHashMap<L, Vec<(usize, u64)>> = FxHashMap::default();
+    for (index, item) in items.iter().enumerate() {
+        for (lexeme, count) in item {
+            if !known.contains(lexeme) && learnable(lexeme) {
+                appearances
+                    .entry(*lexeme)
+                    .or_default()
+                    .push((index, *count));
+            }
+        }
+    }
+    let gain = |lexeme: &L, gaps: &[u64]| {
+        appearances[lexeme]
+            .iter()
+            .map(|&(index, count)| count.min(gaps[index]) as f64 / totals[index] as f64)
+            .sum::<f64>()
+    };
+
+    let mut candidates = appearances
+        .keys()
+        .map(|lexeme| Candidate {
+            gain: gain(lexeme, &gaps),
+            lexeme: *lexeme,
+        })
+        .collect::<BinaryHeap<_>>();
+    let mut picked = Vec::new();
+    while gaps.iter().any(|gap| *gap > 0) {
+        let Some(candidate) = candidates.pop() else {
+            break;
+        };
+        let fresh = Candidate {
+            gain: gain(&candidate.lexeme, &gaps),
+            lexeme: candidate.lexeme,
+        };
+        if fresh.gain <= 0.0 {
+            continue;
+        }
+        if candidates.peek().is_some_and(|next| *next > fresh) {
+            candidates.push(fresh);
+            continue;
+        }
+        for &(index, count) in &appearances[&fresh.lexeme] {
+            gaps[index] = gaps[index].saturating_sub(count);
+        }
+        picked.push(fresh.lexeme);
+    }
+    let reached = gaps.iter().all(|gap| *gap == 0);
+    (picked, reached)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn item(counts: &[(u32, u64)]) -> FxHashMap<u32, u64> {
+        counts.iter().copied().collect()
+    }
+
+    #[test]
+    fn test_picks_words_shared_between_items() {
+        // Word 1 is common in both items, so it's worth more than word 2 even though word 2 is
+        // more common in the first one
+        let items = [
+            item(&[(0, 50), (1, 20), (2, 25), (3, 5)]),
+            item(&[(0, 50), (1, 20), (4, 30)]),
+        ];
+        let known = FxHashSet::from_iter([0]);
+
+        let (picked, reached) = words_to_reach(&items, &known, |_| true, 0.7);
+        assert!(reached);
+        assert_eq!(picked, vec![1]);
+
+        let (picked, reached) = words_to_reach(&items, &known, |_| true, 0.95);
+        assert!(reached);
+        // 2 and 4 close the same gap, so the tie goes to 2
+        assert_eq!(picked, vec![1, 2, 4]);
+
+        // Nothing to do if the target is already reached
+        let (picked, reached) = words_to_reach(&items, &known, |_| true, 0.5);
+        assert!(reached);
+        assert!(picked.is_empty());
+    }
+
+    #[test]
+    fn test_unlearnable_words_can_leave_the_target_out_of_reach() {
+        let items = [item(&[(0, 50), (1, 50)])];
+        let (picked, reached) =
+            words_to_reach(&items, &FxHashSet::default(), |word| *word == 0, 0.9);
+        assert!(!reached);
+        assert_eq!(picked, vec![0]);
+    }
+}
@@ -0,0 +1,264 @@
+//! Comprehension quizzes for movies. Once the user understands another `MOVIE_MILESTONE_PERCENT`
+//! of a movie's words, `get_movie_stats` offers a quiz on its sentences (see `Deck::get_movie_quiz`),
+//! to check that knowing the words means understanding the lines. A quiz is graded as a whole
+//! and recorded with a single `LanguageEventContent::MovieQuizCompleted`, which doesn't touch any
+//! card's schedule. Without the `movies` feature there are no quizzes to take, but the ones in
+//! the user's events are still recorded, so the deck comes out the same.
+
+use std::collections::BTreeMap;
+
+#[cfg(feature = "movies")]
+use language_utils::{TtsProvider, TtsRequest};
+use serde::{Deserialize, Serialize};
+#[cfg(all(target_arch = "wasm32", feature = "movies"))]
+use wasm_bindgen::prelude::*;
+
+#[cfg(feature = "movies")]
+use crate::weekly_digest::MOVIE_MILESTONE_PERCENT;
+#[cfg(feature = "movies")]
+use crate::{
+    AudioRequest, ComprehensibleSentence, Deck, DeckEvent, LanguageEvent, LanguageEventContent,
+};
+
+/// How many questions a quiz has, if the movie has enough sentences
+#[cfg(feature = "movies")]
+const QUIZ_QUESTIONS: usize = 10;
+
+/// The last quiz the user took on a movie
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub(crate) struct MovieQuizResult {
+    pub(crate) milestone: u32,
+    pub(crate) questions: u32,
+    pub(crate) correct: u32,
+}
+
+/// The highest milestone `percent_known` has reached, e.g. 45 for 47.5%
+#[cfg(feature = "movies")]
+pub(crate) fn reached_milestone(percent_known: f64) -> u32 {
+    ((percent_known / MOVIE_MILESTONE_PERCENT).floor() * MOVIE_MILESTONE_PERCENT) as u32
+}
+
+#[cfg(feature = "movies")]
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[cfg_attr(target_arch = "wasm32", derive(tsify::Tsify))]
+#[cfg_attr(target_arch = "wasm32", tsify(into_wasm_abi, from_wasm_abi))]
+pub enum MovieQuizQuestionKind {
+    /// Read `sentence` and translate it
+    Translation,
+    /// Listen to `audio` and write down `sentence`
+    Transcription,
+}
+
+#[cfg(feature = "movies")]
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(target_arch = "wasm32", derive(tsify::Tsify))]
+#[cfg_attr(target_arch = "wasm32", tsify(into_wasm_abi, from_wasm_abi))]
+pub struct MovieQuizQuestion {
+    pub kind: MovieQuizQuestionKind,
+    /// A line from the movie
+    pub sentence: String,
+    /// The expected translation, for grading translations and revealing after transcriptions
+    pub translation: String,
+    pub audio: AudioRequest,
+}
+
+/// A quiz on a movie's sentences, see `Deck::get_movie_quiz`. The frontend grades the questions,
+/// and records the score with `get_results_event`.
+#[cfg(feature = "movies")]
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+#[derive(Clone, Debug)]
+pub struct MovieQuiz {
+    movie_id: String,
+    milestone: u32,
+    questions: Vec<MovieQuizQuestion>,
+}
+
+#[cfg(feature = "movies")]
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+impl MovieQuiz {
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen(getter))]
+    pub fn movie_id(&self) -> String {
+        self.movie_id.clone()
+    }
+
+    /// The milestone the quiz is for, in percent of the movie's words
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen(getter))]
+    pub fn milestone(&self) -> u32 {
+        self.milestone
+    }
+
+    /// Alternating between translations and transcriptions
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn get_questions(&self) -> Vec<MovieQuizQuestion> {
+        self.questions.clone()
+    }
+
+    /// Records that the user answered `correct` of the questions correctly
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn get_results_event(&self, deck: &Deck, correct: u32) -> DeckEvent {
+        DeckEvent::Language(LanguageEvent {
+            target_language: deck.context.target_language,
+            native_language: deck.context.native_language,
+            content: LanguageEventContent::MovieQuizCompleted {
+                movie_id: self.movie_id.clone(),
+                milestone: self.milestone,
+                questions: self.questions.len() as u32,
+                correct: correct.min(self.questions.len() as u32),
+            },
+        })
+    }
+}
+
+#[cfg(feature = "movies")]
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+impl Deck {
+    /// A quiz on `movie_id`'s sentences for the milestone the user has reached in it. The
+    /// sentences the user knows the most words of are asked first, and sentences left out by
+    /// the user's `SentenceFilters` are never asked. `None` if the movie has no usable sentences.
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn get_movie_quiz(&self, movie_id: String) -> Option<MovieQuiz> {
+        let language_pack = &self.context.language_pack;
+        let percent_known = self
+            .get_movie_stats()
+            .into_iter()
+            .find(|stats| stats.id == movie_id)?
+            .percent_known;
+        let comprehensible_lexemes = self.comprehensible_lexemes();
+
+        let mut candidates = language_pack
+            .sentence_sources
+            .iter()
+            .filter(|(_, source)| source.movie_ids.contains(&movie_id))
+            .filter(|(sentence, _)| {
+                self.sentence_filters
+                    .rejected_by(**sentence, language_pack)
+                    .is_none()
+            })
+            .filter_map(|(sentence, _)| ComprehensibleSentence::new(*sentence, language_pack))
+            .filter(|sentence| !sentence.unique_target_language_lexemes.is_empty())
+            .map(|sentence| {
+                let known = sentence
+                    .unique_target_language_lexemes
+                    .iter()
+                    .filter(|lexeme| comprehensible_lexemes.contains(lexeme))
+                    .count();
+                let fraction_known =
+                    known as f64 / sentence.unique_target_language_lexemes.len() as f64;
+                (fraction_known, sentence)
+            })
+            .collect::<Vec<_>>();
+        // Ties go to the first sentence alphabetically, so the quiz doesn't depend on hash order
+        candidates.sort_by(|(a_known, a), (b_known, b)| {
+            b_known.total_cmp(a_known).then_with(|| {
+                language_pack
+                    .rodeo
+                    .resolve(&a.target_language)
+                    .cmp(language_pack.rodeo.resolve(&b.target_language))
+            })
+        });
+
+        let questions = candidates
+            .into_iter()
+            .take(QUIZ_QUESTIONS)
+            .enumerate()
+            .filter_map(|(index, (_, sentence))| {
+                let text = language_pack
+                    .rodeo
+                    .resolve(&sentence.target_language)
+                    .to_string();
+                Some(MovieQuizQuestion {
+                    kind: if index % 2 == 0 {
+                        MovieQuizQuestionKind::Translation
+                    } else {
+                        MovieQuizQuestionKind::Transcription
+                    },
+                    translation: sentence.native_languages.first()?.to_string(),
+                    audio: AudioRequest {
+                        request: TtsRequest {
+                            text: text.clone(),
+                            language: self.context.target_language,
+                        },
+                        provider: self.preferred_tts_provider(TtsProvider::Google),
+                    },
+                    sentence: text,
+                })
+            })
+            .collect::<Vec<_>>();
+
+        (!questions.is_empty()).then(|| MovieQuiz {
+            movie_id,
+            milestone: reached_milestone(percent_known),
+            questions,
+        })
+    }
+}
+
+#[cfg(feature = "movies")]
+impl Deck {
+    /// The milestone a quiz is waiting to be taken for in a movie the user knows `percent_known`
+    /// of, if they haven't taken one for it (or a later one) yet
+    pub(crate) fn movie_quiz_milestone(&self, movie_id: &str, percent_known: f64) -> Option<u32> {
+        let milestone = reached_milestone(percent_known);
+        let last_quiz = self.stats.movie_quizzes.get(movie_id);
+        (milestone > 0 && last_quiz.is_none_or(|quiz| quiz.milestone < milestone))
+            .then_some(milestone)
+    }
+}
+
+/// Keeps the quiz for the highest milestone, so retaking an older one doesn't bring back the
+/// offer for a newer one
+pub(crate) fn record_movie_quiz(
+    movie_quizzes: &mut BTreeMap<String, MovieQuizResult>,
+    movie_id: &str,
+    result: MovieQuizResult,
+) {
+    let last_quiz = movie_quizzes.entry(movie_id.to_string()).or_insert(result);
+    if result.milestone >= last_quiz.milestone {
+        *last_quiz = result;
+    }
+}
+
+#[cfg(all(test, feature = "movies"))]
+mod tests {
+    use super::*;
+    use weapon::AppState;
+    use weapon::data_model::Timestamped;
+
+    #[test]
+    fn test_quizzes_alternate_and_record_their_results() {
+        let deck = Deck::default();
+        let pack = deck.context.language_pack.clone();
+        let movie_id = pack
+            .sentence_sources
+            .values()
+            .flat_map(|source| &source.movie_ids)
+            .find(|movie_id| pack.movies.contains_key(*movie_id))
+            .unwrap()
+            .clone();
+
+        let quiz = deck.get_movie_quiz(movie_id.clone()).unwrap();
+        assert!(quiz.questions.len() <= QUIZ_QUESTIONS);
+        assert_eq!(
+            quiz.questions.first().map(|question| question.kind),
+            Some(MovieQuizQuestionKind::Translation)
+        );
+        if let Some(second) = quiz.questions.get(1) {
+            assert_eq!(second.kind, MovieQuizQuestionKind::Transcription);
+        }
+
+        let deck = deck.apply_event(&Timestamped {
+            timestamp: chrono::Utc::now(),
+            within_device_events_index: 0,
+            event: quiz.get_results_event(&deck, 100),
+        });
+        assert_eq!(
+            deck.stats.movie_quizzes.get(&movie_id),
+            Some(&MovieQuizResult {
+                milestone: quiz.milestone,
+                questions: quiz.questions.len() as u32,
+                correct: quiz.questions.len() as u32,
+            })
+        );
+        assert_eq!(deck.movie_quiz_milestone(&movie_id, 0.0), None);
+    }
+}
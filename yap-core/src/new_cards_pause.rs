@@ -0,0 +1,131 @@
+//! Pausing new cards while the user is struggling. When they remember less than their threshold
+//! of the reviews from their last week of studying, smart add offers no new cards, so they can
+//! catch up on the ones they've already added. Reviewing more brings the accuracy back up.
+
+use chrono::Duration;
+use serde::{Deserialize, Serialize};
+#[cfg(target_arch = "wasm32")]
+use wasm_bindgen::prelude::*;
+
+use crate::{Deck, DeckEvent, LanguageEvent, LanguageEventContent};
+
+/// The threshold for decks that haven't set one
+pub(crate) const DEFAULT_THRESHOLD_PERCENT: u32 = 70;
+
+/// How far back from the last review the accuracy is measured. It's counted from the last review
+/// rather than from now, so taking a break doesn't lift the pause without the user recovering.
+const WINDOW_DAYS: i64 = 7;
+
+/// Fewer reviews than this in the window say too little about the user's accuracy to pause
+const MIN_REVIEWS: u32 = 20;
+
+/// Why smart add isn't offering new cards, so the app can explain it
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq, Ord, PartialOrd)]
+#[cfg_attr(target_arch = "wasm32", derive(tsify::Tsify))]
+#[cfg_attr(target_arch = "wasm32", tsify(into_wasm_abi, from_wasm_abi))]
+pub struct NewCardsPaused {
+    /// The percent of reviews the user remembered over their last week of studying, rounded down
+    pub accuracy_percent: u32,
+    /// New cards are offered again once `accuracy_percent` reaches this
+    pub threshold_percent: u32,
+    /// How many reviews `accuracy_percent` is from
+    pub reviews: u32,
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+impl Deck {
+    /// Pauses new cards while the user remembers less than `threshold_percent` of their reviews,
+    /// or never pauses them if it's `None`
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn set_new_card_accuracy_threshold(
+        &self,
+        threshold_percent: Option<u32>,
+    ) -> Option<DeckEvent> {
+        let threshold_percent = threshold_percent.map(|threshold| threshold.min(100));
+        (threshold_percent != self.new_card_accuracy_threshold).then_some(DeckEvent::Language(
+            LanguageEvent {
+                target_language: self.context.target_language,
+                native_language: self.context.native_language,
+                content: LanguageEventContent::SetNewCardAccuracyThreshold { threshold_percent },
+            },
+        ))
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn get_new_card_accuracy_threshold(&self) -> Option<u32> {
+        self.new_card_accuracy_threshold
+    }
+
+    /// Why smart add isn't offering new cards, or `None` if the user's accuracy is fine
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn get_new_cards_paused(&self) -> Option<NewCardsPaused> {
+        let threshold_percent = self.new_card_accuracy_threshold?;
+        let activity = &self.stats.activity;
+        let last_studied = activity.last_studied()?;
+        let (reviews, correct) = activity
+            .sessions_between(
+                last_studied - Duration::days(WINDOW_DAYS),
+                last_studied + Duration::seconds(1),
+            )
+            .fold((0, 0), |(reviews, correct), session| {
+                (reviews + session.reviews, correct + session.correct)
+            });
+        if reviews < MIN_REVIEWS {
+            return None;
+        }
+        let accuracy_percent = correct * 100 / reviews;
+        (accuracy_percent < threshold_percent).then_some(NewCardsPaused {
+            accuracy_percent,
+            threshold_percent,
+            reviews,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::activity::ChallengeKind;
+
+    #[test]
+    fn test_smart_add_pauses_while_accuracy_is_low() {
+        let mut deck = Deck::default();
+        let start = chrono::DateTime::<chrono::Utc>::from_timestamp(1_700_000_000, 0).unwrap();
+        // Half remembered, ten days ago and yesterday
+        for (day, minute) in (0..30).map(|i| (if i < 15 { -10 } else { -1 }, i)) {
+            deck.stats.activity.record(
+                start + Duration::days(day) + Duration::minutes(minute),
+                Some((ChallengeKind::Flashcard, minute % 2 == 0)),
+                1.0,
+            );
+        }
+        // Only yesterday's 15 reviews are in the window, which isn't enough to go on
+        assert_eq!(deck.get_new_cards_paused(), None);
+
+        for minute in 0..10 {
+            deck.stats.activity.record(
+                start + Duration::minutes(minute),
+                Some((ChallengeKind::Flashcard, false)),
+                1.0,
+            );
+        }
+        let paused = NewCardsPaused {
+            accuracy_percent: 28,
+            threshold_percent: DEFAULT_THRESHOLD_PERCENT,
+            reviews: 25,
+        };
+        assert_eq!(deck.get_new_cards_paused(), Some(paused));
+        let options = deck.add_card_options(Vec::new(), start.timestamp_millis() as f64);
+        assert_eq!(options.smart_add, 0);
+        assert_eq!(options.paused, Some(paused));
+
+        deck.new_card_accuracy_threshold = Some(25);
+        assert_eq!(deck.get_new_cards_paused(), None);
+        deck.new_card_accuracy_threshold = None;
+        assert_eq!(
+            deck.add_card_options(Vec::new(), start.timestamp_millis() as f64)
+                .paused,
+            None
+        );
+    }
+}
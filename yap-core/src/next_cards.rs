@@ -1,7 +1,7 @@
 use rustc_hash::FxHashMap;
 use std::collections::BTreeSet;
 
-use chrono::Utc;
+use chrono::{DateTime, Utc};
 use language_utils::Lexeme;
 use lasso::Spur;
 use ordered_float::NotNan;
@@ -16,9 +16,15 @@ pub(crate) struct NextCardsIterator<'a> {
     pub(crate) allowed_cards: AllowedCards,
     pub(crate) context: &'a Context,
     pub(crate) regressions: &'a Regressions,
+    /// Cards the user pinned, most recently pinned last. They come before anything picked by value.
+    prioritized: Vec<CardIndicator<Spur>>,
     // Cached counts to avoid repeated iteration
     added_count: usize,
     card_type_counts: FxHashMap<CardType, u32>,
+    /// Each type's share of new cards, see `Deck::smart_add_mix`
+    target_ratios: FxHashMap<CardType, f64>,
+    /// When the cards are being added
+    now: DateTime<Utc>,
 }
 
 pub(crate) enum AllowedCards {
@@ -30,7 +36,7 @@ pub(crate) enum AllowedCards {
 }
 
 impl<'a> NextCardsIterator<'a> {
-    pub fn new(deck: &'a Deck, allowed_cards: AllowedCards) -> Self {
+    pub fn new(deck: &'a Deck, allowed_cards: AllowedCards, now: DateTime<Utc>) -> Self {
         let cards = deck.cards.clone();
 
         // Initialize counts by iterating once
@@ -48,13 +54,22 @@ impl<'a> NextCardsIterator<'a> {
             }
         }
 
+        let target_ratios = deck
+            .smart_add_mix(now)
+            .into_iter()
+            .map(|weight| (weight.card_type, f64::from(weight.percent) / 100.0))
+            .collect();
+
         Self {
             cards,
             allowed_cards,
             context: &deck.context,
             regressions: &deck.regressions,
+            prioritized: deck.prioritized.clone(),
             added_count,
             card_type_counts,
+            target_ratios,
+            now,
         }
     }
 
@@ -78,7 +93,7 @@ impl<'a> NextCardsIterator<'a> {
                     self.context
                         .get_card_value_with_status(card, status, self.regressions)?;
 
-                let fsrs_card = rs_fsrs::Card::new(Utc::now());
+                let fsrs_card = rs_fsrs::Card::new(self.now);
 
                 Some((lexeme, fsrs_card, value))
             })
@@ -103,7 +118,7 @@ impl<'a> NextCardsIterator<'a> {
                     self.context
                         .get_card_value_with_status(card, status, self.regressions)?;
 
-                let fsrs_card = rs_fsrs::Card::new(Utc::now());
+                let fsrs_card = rs_fsrs::Card::new(self.now);
 
                 Some((pattern, position, fsrs_card, value))
             })
@@ -162,7 +177,7 @@ impl<'a> NextCardsIterator<'a> {
                     return None;
                 }
 
-                let fsrs_card = rs_fsrs::Card::new(Utc::now());
+                let fsrs_card = rs_fsrs::Card::new(self.now);
 
                 Some((pronunciation, fsrs_card, value))
             })
@@ -179,7 +194,35 @@ impl<'a> NextCardsIterator<'a> {
 }
 
 impl NextCardsIterator<'_> {
+    fn is_allowed(&self, card_type: CardType) -> bool {
+        match &self.allowed_cards {
+            AllowedCards::All => true,
+            AllowedCards::BannedRequirements(banned_requirements) => {
+                !banned_requirements.contains(&card_type.challenge_type())
+            }
+            AllowedCards::Type(allowed_card_type) => card_type == *allowed_card_type,
+        }
+    }
+
+    fn next_prioritized_card(&self) -> Option<(CardIndicator<Spur>, rs_fsrs::Card)> {
+        self.prioritized
+            .iter()
+            .rev()
+            .find(|card| {
+                self.is_allowed(card.card_type())
+                    && self
+                        .cards
+                        .get(card)
+                        .is_some_and(|status| status.unadded().is_some())
+            })
+            .map(|card| (*card, rs_fsrs::Card::new(self.now)))
+    }
+
     fn next_card(&self) -> Option<(CardIndicator<Spur>, rs_fsrs::Card)> {
+        if let Some(card) = self.next_prioritized_card() {
+            return Some(card);
+        }
+
         if self.added_count < 20 {
             let card = self.next_text_card()?;
             return Some(card);
@@ -191,20 +234,10 @@ impl NextCardsIterator<'_> {
             let mut card_type_ratios = self
                 .card_type_counts
                 .iter()
-                .filter(|(card_type, _)| match &self.allowed_cards {
-                    AllowedCards::All => true,
-                    AllowedCards::BannedRequirements(banned_requirements) => {
-                        !banned_requirements.contains(&card_type.challenge_type())
-                    }
-                    AllowedCards::Type(allowed_card_type) => **card_type == *allowed_card_type,
-                })
+                .filter(|(card_type, _)| self.is_allowed(**card_type))
                 .map(|(card_type, count)| {
                     (*card_type, {
-                        let target_ratio = match card_type {
-                            CardType::TargetLanguage => 0.6,
-                            CardType::Listening => 0.3,
-                            CardType::LetterPronunciation => 0.1,
-                        };
+                        let target_ratio = self.target_ratios[card_type].max(0.01);
                         (*count as f64 / total_cards as f64) / target_ratio
                     })
                 })
@@ -0,0 +1,232 @@
+//! Push notifications worked out from when the deck's cards come due. Sending them is up to the
+//! client.
+
+use crate::{CardSummary, Deck, datetime_from_ms};
+
+#[derive(serde::Deserialize, serde::Serialize, Debug, Clone)]
+#[serde(rename_all = "snake_case")]
+pub enum NotificationType {
+    DueNow,
+    MorningReminder,
+    AfternoonReminder,
+    EveningReminder,
+    EncourageNewCards,
+    EncourageNewCards3d,
+    WeeklyCheckpoint,
+    WeeklyForecast,
+    BiweeklyForecast,
+    MonthlyMilestone,
+}
+
+#[derive(serde::Deserialize, serde::Serialize, Debug, Clone)]
+pub struct ScheduledNotification {
+    pub scheduled_at: f64, // timestamp in milliseconds
+    pub notification: Notification,
+}
+
+#[derive(serde::Deserialize, serde::Serialize, Debug, Clone)]
+pub struct Notification {
+    pub title: String,
+    pub body: String,
+    pub notification_type: NotificationType,
+}
+
+impl NotificationType {
+    pub fn show(&self, due_cards: &[&CardSummary]) -> Option<Notification> {
+        match self {
+            NotificationType::DueNow => (!due_cards.is_empty()).then(|| Notification {
+                title: "Time to study! 📚".to_string(),
+                body: "Your next card is ready for review".to_string(),
+                notification_type: self.clone(),
+            }),
+            NotificationType::MorningReminder => (!due_cards.is_empty()).then(|| Notification {
+                title: "Good morning! ☀️".to_string(),
+                body: format!(
+                    "{} card{} to review today",
+                    due_cards.len(),
+                    if due_cards.len() == 1 { "" } else { "s" }
+                ),
+                notification_type: self.clone(),
+            }),
+            NotificationType::AfternoonReminder => (due_cards.len() > 2).then(|| Notification {
+                title: "Afternoon study break? ☕".to_string(),
+                body: "Perfect time for a quick review session!".to_string(),
+                notification_type: self.clone(),
+            }),
+            NotificationType::EveningReminder => (!due_cards.is_empty()).then(|| Notification {
+                title: "Evening review time 🌙".to_string(),
+                body: format!(
+                    "Wrap up the day with {} card{}",
+                    due_cards.len(),
+                    if due_cards.len() == 1 { "" } else { "s" }
+                ),
+                notification_type: self.clone(),
+            }),
+            NotificationType::EncourageNewCards => due_cards.is_empty().then(|| Notification {
+                title: "Keep the momentum going! 🚀".to_string(),
+                body: "You're all caught up! Time to learn some new words?".to_string(),
+                notification_type: self.clone(),
+            }),
+            NotificationType::EncourageNewCards3d => due_cards.is_empty().then(|| Notification {
+                title: "Ready for a challenge? 💪".to_string(),
+                body: "Add 5 new words to keep your learning streak alive!".to_string(),
+                notification_type: self.clone(),
+            }),
+            NotificationType::WeeklyCheckpoint => (!due_cards.is_empty()).then(|| Notification {
+                title: "Stay on track! 📊".to_string(),
+                body: format!(
+                    "{} cards coming up this week - you've got this!",
+                    due_cards.len()
+                ),
+                notification_type: self.clone(),
+            }),
+            NotificationType::WeeklyForecast => (!due_cards.is_empty()).then(|| Notification {
+                title: "Weekly review forecast 📅".to_string(),
+                body: format!("{} cards scheduled for the next week", due_cards.len()),
+                notification_type: self.clone(),
+            }),
+            NotificationType::BiweeklyForecast => (!due_cards.is_empty()).then(|| Notification {
+                title: "Two-week outlook 🔮".to_string(),
+                body: format!("{} cards coming up in the next month", due_cards.len()),
+                notification_type: self.clone(),
+            }),
+            NotificationType::MonthlyMilestone => (!due_cards.is_empty()).then(|| Notification {
+                title: "Ready to start again? 🏆".to_string(),
+                body: "This will be your last Yap notification until you start Yapping again."
+                    .to_string(),
+                notification_type: self.clone(),
+            }),
+        }
+    }
+}
+
+impl Notification {
+    pub fn at(self, scheduled_at: f64) -> ScheduledNotification {
+        ScheduledNotification {
+            scheduled_at,
+            notification: self,
+        }
+    }
+}
+
+impl Deck {
+    /// The notifications to schedule from `timestamp_ms` on
+    pub fn compute_scheduled_notifications(
+        &self,
+        timezone_offset_minutes: i32,
+        timestamp_ms: f64,
+    ) -> Vec<ScheduledNotification> {
+        let mut notifications = Vec::new();
+        let now = datetime_from_ms(timestamp_ms);
+        let now_millis = now.timestamp_millis() as f64;
+
+        // Convert UTC to user's local time using the offset
+        // Note: getTimezoneOffset returns positive when local is behind UTC,
+        // so we need to subtract it to get local time
+        let local_now = now - chrono::Duration::minutes(timezone_offset_minutes as i64);
+
+        // Get all cards sorted by due date
+        let cards = self.get_all_cards_summary();
+
+        // Find cards that are due
+        let due_cards: Vec<&CardSummary> = cards
+            .iter()
+            .filter(|card| card.due_timestamp_ms <= now_millis)
+            .collect();
+
+        // Helper function to get a specific hour today or in the future
+        let get_next_occurrence = |hour: u32, days_ahead: i64| {
+            // Get the target date in the user's local time
+            let local_target = (local_now + chrono::Duration::days(days_ahead))
+                .date_naive()
+                .and_hms_opt(hour, 0, 0)
+                .unwrap();
+
+            // Convert from local time to UTC by adding the timezone offset
+            // (opposite of the conversion to local time)
+            local_target.and_utc() + chrono::Duration::minutes(timezone_offset_minutes as i64)
+        };
+
+        // 1. Notification for when next card becomes due
+        if due_cards.is_empty() {
+            // Find the next card that will become due
+            let next_due_card = cards
+                .iter()
+                .filter(|card| card.due_timestamp_ms > now_millis)
+                .min_by_key(|card| card.due_timestamp_ms as i64);
+
+            if let Some(next_card) = next_due_card {
+                if let Some(notification) = NotificationType::DueNow.show(&[next_card]) {
+                    notifications.push(notification.at(next_card.due_timestamp_ms));
+                }
+            }
+        }
+
+        // First, determine all potential notification times
+        let mut notification_times: std::collections::BTreeMap<i64, NotificationType> =
+            std::collections::BTreeMap::new();
+
+        let cards_due_by = |time_millis: i64| -> Vec<&CardSummary> {
+            cards
+                .iter()
+                .filter(|card| card.due_timestamp_ms <= time_millis as f64)
+                .collect()
+        };
+
+        // Today's notification times
+        notification_times.insert(
+            get_next_occurrence(9, 0).timestamp_millis(),
+            NotificationType::MorningReminder,
+        );
+        notification_times.insert(
+            get_next_occurrence(15, 0).timestamp_millis(),
+            NotificationType::AfternoonReminder,
+        );
+        notification_times.insert(
+            get_next_occurrence(20, 0).timestamp_millis(),
+            NotificationType::EveningReminder,
+        );
+
+        // Future notification times
+        notification_times.insert(
+            get_next_occurrence(19, 1).timestamp_millis(),
+            NotificationType::EncourageNewCards,
+        );
+        notification_times.insert(
+            get_next_occurrence(18, 3).timestamp_millis(),
+            NotificationType::EncourageNewCards3d,
+        );
+        notification_times.insert(
+            get_next_occurrence(19, 3).timestamp_millis(),
+            NotificationType::WeeklyCheckpoint,
+        );
+        notification_times.insert(
+            get_next_occurrence(19, 7).timestamp_millis(),
+            NotificationType::WeeklyForecast,
+        );
+        notification_times.insert(
+            get_next_occurrence(19, 14).timestamp_millis(),
+            NotificationType::BiweeklyForecast,
+        );
+        notification_times.insert(
+            get_next_occurrence(19, 30).timestamp_millis(),
+            NotificationType::MonthlyMilestone,
+        );
+
+        for (&time, notification_type) in notification_times.iter() {
+            let cards = cards_due_by(time);
+            let time_f64 = time as f64;
+
+            // Find the notification type for this time
+            if let Some(notification) = notification_type.show(&cards) {
+                notifications.push(notification.at(time_f64));
+            }
+        }
+
+        // Remove duplicate notifications at the same time
+        notifications.sort_by(|a, b| a.scheduled_at.partial_cmp(&b.scheduled_at).unwrap());
+        notifications.dedup_by(|a, b| (a.scheduled_at - b.scheduled_at).abs() < 60000.0); // Within 1 minute
+
+        notifications
+    }
+}
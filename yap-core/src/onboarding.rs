@@ -0,0 +1,190 @@
+//! The answers a new user gives while setting up their first course, and what they mean for the
+//! deck. The answers are recorded as ordinary events (the deck selection, a daily goal and a
+//! calibration request), so they sync like everything else; the recommendation is recomputed
+//! from them rather than stored.
+
+use language_utils::Language;
+use serde::{Deserialize, Serialize};
+#[cfg(target_arch = "wasm32")]
+use wasm_bindgen::prelude::*;
+
+use crate::deck_selection::DeckSelectionEvent;
+use crate::{CardType, Deck, DeckEvent, LanguageEvent, LanguageEventContent};
+
+/// How much of the target language the user thinks they already know
+#[derive(Copy, Clone, Debug, Serialize, Deserialize, PartialEq, Eq, Ord, PartialOrd)]
+#[cfg_attr(target_arch = "wasm32", derive(tsify::Tsify))]
+#[cfg_attr(target_arch = "wasm32", tsify(into_wasm_abi, from_wasm_abi))]
+pub enum SelfAssessedLevel {
+    /// Never studied it
+    Beginner,
+    /// Knows some common words and phrases
+    Elementary,
+    /// Can follow simple conversations
+    Intermediate,
+    /// Can follow most conversations, and wants to fill in gaps
+    Advanced,
+}
+
+/// How much the user wants to study each day, set with `Deck::set_daily_goal`
+#[derive(Copy, Clone, Debug, Serialize, Deserialize, PartialEq, Eq, Ord, PartialOrd)]
+#[cfg_attr(target_arch = "wasm32", derive(tsify::Tsify))]
+#[cfg_attr(target_arch = "wasm32", tsify(into_wasm_abi, from_wasm_abi))]
+pub struct DailyGoal {
+    pub minutes: u32,
+    pub new_cards_per_day: u32,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(target_arch = "wasm32", derive(tsify::Tsify))]
+#[cfg_attr(target_arch = "wasm32", tsify(into_wasm_abi, from_wasm_abi))]
+pub struct OnboardingAnswers {
+    pub native_language: Language,
+    pub target_language: Language,
+    pub level: SelfAssessedLevel,
+    pub daily_minutes: u32,
+}
+
+/// What a new deck should start with, see `OnboardingAnswers::recommended_configuration`
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(target_arch = "wasm32", derive(tsify::Tsify))]
+#[cfg_attr(target_arch = "wasm32", tsify(into_wasm_abi, from_wasm_abi))]
+pub struct RecommendedConfiguration {
+    pub new_cards_per_day: u32,
+    /// Roughly how many reviews a day the time budget covers once the deck has settled
+    pub daily_reviews: u32,
+    /// What share of the new cards should be of each type. The shares add up to 1.
+    pub card_type_mix: Vec<(CardType, f64)>,
+    /// Whether the user should be placed before any cards are added, since they already know
+    /// some of the language
+    pub calibrate: bool,
+}
+
+/// A rough average over flashcards and sentence challenges
+const SECONDS_PER_REVIEW: u32 = 12;
+
+/// Once a deck has settled, each card added a day brings about this many reviews a day
+const REVIEWS_PER_NEW_CARD: u32 = 10;
+
+const MIN_NEW_CARDS_PER_DAY: u32 = 3;
+const MAX_NEW_CARDS_PER_DAY: u32 = 40;
+
+impl OnboardingAnswers {
+    pub fn recommended_configuration(&self) -> RecommendedConfiguration {
+        let daily_reviews = self.daily_minutes * 60 / SECONDS_PER_REVIEW;
+        let new_cards_per_day = (daily_reviews / REVIEWS_PER_NEW_CARD)
+            .clamp(MIN_NEW_CARDS_PER_DAY, MAX_NEW_CARDS_PER_DAY);
+        // Beginners need the sounds of the language first, and people who can already read it
+        // get the most out of listening
+        let (target_language, listening, letter_pronunciation) = match self.level {
+            SelfAssessedLevel::Beginner => (0.6, 0.2, 0.2),
+            SelfAssessedLevel::Elementary => (0.6, 0.3, 0.1),
+            SelfAssessedLevel::Intermediate => (0.55, 0.4, 0.05),
+            SelfAssessedLevel::Advanced => (0.5, 0.5, 0.0),
+        };
+        RecommendedConfiguration {
+            new_cards_per_day,
+            daily_reviews,
+            card_type_mix: vec![
+                (CardType::TargetLanguage, target_language),
+                (CardType::Listening, listening),
+                (CardType::LetterPronunciation, letter_pronunciation),
+            ],
+            calibrate: self.level != SelfAssessedLevel::Beginner,
+        }
+    }
+
+    /// Selects the course the user picked
+    pub fn selection_event(&self) -> DeckSelectionEvent {
+        DeckSelectionEvent::SelectBothLanguages {
+            native: self.native_language,
+            target: self.target_language,
+        }
+    }
+
+    /// The course's daily goal, and a calibration request if the user isn't a beginner
+    pub fn deck_events(&self) -> Vec<DeckEvent> {
+        let configuration = self.recommended_configuration();
+        let event = |content| {
+            DeckEvent::Language(LanguageEvent {
+                target_language: self.target_language,
+                native_language: self.native_language,
+                content,
+            })
+        };
+        let mut events = vec![event(LanguageEventContent::SetDailyGoal {
+            goal: DailyGoal {
+                minutes: self.daily_minutes,
+                new_cards_per_day: configuration.new_cards_per_day,
+            },
+        })];
+        if configuration.calibrate {
+            events.push(event(LanguageEventContent::RequestCalibration {
+                level: self.level,
+            }));
+        }
+        events
+    }
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+impl Deck {
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn set_daily_goal(&self, goal: DailyGoal) -> Option<DeckEvent> {
+        (Some(goal) != self.daily_goal).then_some(DeckEvent::Language(LanguageEvent {
+            target_language: self.context.target_language,
+            native_language: self.context.native_language,
+            content: LanguageEventContent::SetDailyGoal { goal },
+        }))
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn get_daily_goal(&self) -> Option<DailyGoal> {
+        self.daily_goal
+    }
+
+    /// The level the user said they're at, if they asked to be placed and no cards have been
+    /// added since. The calibration should run before the first cards are added, so words the
+    /// user already knows aren't taught.
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn get_pending_calibration(&self) -> Option<SelfAssessedLevel> {
+        self.pending_calibration
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_recommendation_follows_the_answers() {
+        let answers = |level, daily_minutes| OnboardingAnswers {
+            native_language: Language::English,
+            target_language: Language::French,
+            level,
+            daily_minutes,
+        };
+
+        let beginner = answers(SelfAssessedLevel::Beginner, 10).recommended_configuration();
+        assert_eq!(beginner.daily_reviews, 50);
+        assert_eq!(beginner.new_cards_per_day, 5);
+        assert!(!beginner.calibrate);
+        let total: f64 = beginner.card_type_mix.iter().map(|(_, share)| share).sum();
+        assert!((total - 1.0).abs() < 1e-9);
+
+        let advanced = answers(SelfAssessedLevel::Advanced, 600);
+        assert_eq!(
+            advanced.recommended_configuration().new_cards_per_day,
+            MAX_NEW_CARDS_PER_DAY
+        );
+        assert!(advanced.recommended_configuration().calibrate);
+        assert_eq!(advanced.deck_events().len(), 2);
+
+        let short_on_time = answers(SelfAssessedLevel::Beginner, 1);
+        assert_eq!(
+            short_on_time.recommended_configuration().new_cards_per_day,
+            MIN_NEW_CARDS_PER_DAY
+        );
+        assert_eq!(short_on_time.deck_events().len(), 1);
+    }
+}
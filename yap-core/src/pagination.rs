@@ -0,0 +1,216 @@
+//! Paged versions of the deck's long lists, so the frontend can virtualize a list without copying
+//! all of it across the wasm boundary. A page comes with a cursor for the next one. The cursor
+//! holds the sort key of the page's last item rather than an offset, so cards being added or
+//! removed between calls doesn't repeat or skip items on the next page.
+
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+#[cfg(target_arch = "wasm32")]
+use wasm_bindgen::prelude::*;
+
+use crate::{CardIndicator, CardSummary, Deck, DictionaryEntryResolved, DictionaryGroup};
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(target_arch = "wasm32", derive(tsify::Tsify))]
+#[cfg_attr(target_arch = "wasm32", tsify(into_wasm_abi))]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    /// How many items there are across all pages
+    pub total: u32,
+    /// Opaque. Pass it back to the same method for the next page. `None` on the last page.
+    pub next_cursor: Option<String>,
+}
+
+/// A `Page` of `CardSummary`s, which are JS classes rather than plain objects
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+pub struct CardSummaryPage(Page<CardSummary>);
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+impl CardSummaryPage {
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen(getter))]
+    pub fn items(&self) -> Vec<CardSummary> {
+        self.0.items.clone()
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen(getter))]
+    pub fn total(&self) -> u32 {
+        self.0.total
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen(getter))]
+    pub fn next_cursor(&self) -> Option<String> {
+        self.0.next_cursor.clone()
+    }
+}
+
+/// The page of `items` after `cursor` (from the start if it's `None` or can't be read). `items`
+/// must be in increasing order of their keys, and no two may share a key.
+pub(crate) fn paginate<K, T>(
+    items: impl IntoIterator<Item = (K, T)>,
+    cursor: Option<String>,
+    page_size: u32,
+) -> Page<T>
+where
+    K: Ord + Serialize + DeserializeOwned,
+{
+    let items = items.into_iter().collect::<Vec<_>>();
+    let after = cursor.and_then(|cursor| serde_json::from_str::<K>(&cursor).ok());
+    let start = after.map_or(0, |after| items.partition_point(|(key, _)| *key <= after));
+    let end = items.len().min(start + page_size.max(1) as usize);
+    let next_cursor = (end < items.len())
+        .then(|| serde_json::to_string(&items[end - 1].0).expect("page keys serialize"));
+    Page {
+        total: items.len() as u32,
+        next_cursor,
+        items: items
+            .into_iter()
+            .skip(start)
+            .take(end - start)
+            .map(|(_, item)| item)
+            .collect(),
+    }
+}
+
+impl Deck {
+    /// Every schedulable card's summary keyed by when it's due, soonest first
+    pub(crate) fn card_summaries_by_due(&self) -> Vec<((i64, CardIndicator<String>), CardSummary)> {
+        let mut summaries = self
+            .schedulable_cards()
+            .filter_map(|(card_indicator, card_status)| {
+                self.card_to_summary(card_indicator, card_status)
+            })
+            .map(|summary| {
+                (
+                    (
+                        summary.due_timestamp_ms as i64,
+                        summary.card_indicator.clone(),
+                    ),
+                    summary,
+                )
+            })
+            .collect::<Vec<_>>();
+        summaries.sort_by(|(a, _), (b, _)| a.cmp(b));
+        summaries
+    }
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+impl Deck {
+    /// A page of `get_all_cards_summary`, ordered by when cards are due. Reviewing a card while
+    /// the list is open pushes back its due date, so it can show up again on a later page.
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn get_all_cards_summary_page(
+        &self,
+        cursor: Option<String>,
+        page_size: u32,
+    ) -> CardSummaryPage {
+        CardSummaryPage(paginate(self.card_summaries_by_due(), cursor, page_size))
+    }
+
+    /// A page of `get_leeches`, ordered by card
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn get_leeches_page(&self, cursor: Option<String>, page_size: u32) -> CardSummaryPage {
+        let mut leeches = self
+            .get_leeches()
+            .into_iter()
+            .map(|summary| (summary.card_indicator.clone(), summary))
+            .collect::<Vec<_>>();
+        leeches.sort_by(|(a, _), (b, _)| a.cmp(b));
+        CardSummaryPage(paginate(leeches, cursor, page_size))
+    }
+
+    /// A page of `get_orphaned_cards`, ordered by card
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn get_orphaned_cards_page(
+        &self,
+        cursor: Option<String>,
+        page_size: u32,
+    ) -> Page<CardIndicator<String>> {
+        let mut orphaned = self.get_orphaned_cards();
+        orphaned.sort();
+        paginate(
+            orphaned.into_iter().map(|card| (card.clone(), card)),
+            cursor,
+            page_size,
+        )
+    }
+
+    /// A page of `get_dictionary_entries`, most common first
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn get_dictionary_entries_page(
+        &self,
+        cursor: Option<String>,
+        page_size: u32,
+    ) -> Page<DictionaryEntryResolved> {
+        // Every entry has a frequency rank, and they're in its order
+        let entries = self
+            .get_dictionary_entries()
+            .into_iter()
+            .map(|entry| (entry.frequency_rank, entry));
+        paginate(entries, cursor, page_size)
+    }
+
+    /// A page of `get_grouped_dictionary_entries`, most common first
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn get_grouped_dictionary_entries_page(
+        &self,
+        cursor: Option<String>,
+        page_size: u32,
+    ) -> Page<DictionaryGroup> {
+        // Groups come from the language pack, so their position is a stable key
+        let groups = self
+            .get_grouped_dictionary_entries()
+            .into_iter()
+            .enumerate()
+            .map(|(index, group)| (index as u32, group));
+        paginate(groups, cursor, page_size)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn letters(text: &str) -> Vec<(char, char)> {
+        text.chars().map(|letter| (letter, letter)).collect()
+    }
+
+    #[test]
+    fn test_pages_cover_every_item_once() {
+        let mut cursor = None;
+        let mut seen = String::new();
+        loop {
+            let page = paginate(letters("abcdefg"), cursor, 3);
+            assert_eq!(page.total, 7);
+            seen.extend(page.items);
+            cursor = page.next_cursor;
+            if cursor.is_none() {
+                break;
+            }
+        }
+        assert_eq!(seen, "abcdefg");
+
+        assert_eq!(paginate(letters(""), None, 3).items, Vec::<char>::new());
+        assert_eq!(paginate(letters("abc"), None, 3).next_cursor, None);
+    }
+
+    #[test]
+    fn test_cursors_survive_changes_to_the_list() {
+        let first = paginate(letters("abcdef"), None, 3);
+        assert_eq!(first.items, vec!['a', 'b', 'c']);
+
+        // Removing an item from the first page doesn't skip "d", and removing the page's last
+        // item doesn't either
+        let second = paginate(letters("acdef"), first.next_cursor.clone(), 3);
+        assert_eq!(second.items, vec!['d', 'e', 'f']);
+        let second = paginate(letters("abdef"), first.next_cursor.clone(), 3);
+        assert_eq!(second.items, vec!['d', 'e', 'f']);
+
+        // Adding one before the cursor doesn't repeat "c"
+        let second = paginate(letters("0abcdef"), first.next_cursor, 3);
+        assert_eq!(second.items, vec!['d', 'e', 'f']);
+
+        // A cursor that can't be read starts over
+        let again = paginate(letters("abcdef"), Some("not a cursor".to_string()), 3);
+        assert_eq!(again.items, first.items);
+    }
+}
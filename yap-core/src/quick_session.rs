@@ -0,0 +1,263 @@
+//! Quick sessions ("I have 5 minutes"): the due cards worth reviewing most in the time the user
+//! has. How long each kind of challenge takes comes from the user's own history, as the time
+//! since the previous event in the same session, so someone who types slowly gets fewer
+//! transcriptions rather than a session that runs over.
+
+use chrono::{DateTime, Utc};
+use lasso::Spur;
+use serde::{Deserialize, Serialize};
+#[cfg(target_arch = "wasm32")]
+use wasm_bindgen::prelude::*;
+
+use crate::activity::ChallengeKind;
+use crate::{
+    CardData, CardIndicator, CardMode, CardStatus, Deck, ReviewInfo, datetime_from_ms, scheduler,
+};
+
+/// Longer gaps between challenges are the user taking a break, not the challenge taking that long
+const MAX_CHALLENGE_SECONDS: f64 = 180.0;
+
+/// How long challenges take before the user has done any of that kind
+const DEFAULT_FLASHCARD_SECONDS: f64 = 8.0;
+const DEFAULT_TRANSLATION_SECONDS: f64 = 30.0;
+const DEFAULT_TRANSCRIPTION_SECONDS: f64 = 45.0;
+
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize, PartialEq)]
+pub(crate) struct AverageSeconds {
+    total: f64,
+    count: u32,
+}
+
+impl AverageSeconds {
+    fn get(&self) -> Option<f64> {
+        (self.count > 0).then(|| self.total / f64::from(self.count))
+    }
+}
+
+/// How long the user takes on each kind of challenge, tracked as events are processed
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq)]
+pub(crate) struct ChallengeDurations {
+    flashcards: AverageSeconds,
+    translations: AverageSeconds,
+    transcriptions: AverageSeconds,
+}
+
+impl ChallengeDurations {
+    /// A challenge of `kind` graded at `timestamp`. The first challenge of a session isn't timed,
+    /// since there's no telling when the user started it.
+    pub(crate) fn record(
+        &mut self,
+        kind: ChallengeKind,
+        previous_event: Option<DateTime<Utc>>,
+        timestamp: DateTime<Utc>,
+    ) {
+        let Some(previous_event) = previous_event else {
+            return;
+        };
+        let seconds = (timestamp - previous_event).num_milliseconds() as f64 / 1000.0;
+        let average = self.average_mut(kind);
+        average.total += seconds.clamp(0.0, MAX_CHALLENGE_SECONDS);
+        average.count += 1;
+    }
+
+    fn average_mut(&mut self, kind: ChallengeKind) -> &mut AverageSeconds {
+        match kind {
+            ChallengeKind::Flashcard => &mut self.flashcards,
+            ChallengeKind::Translation => &mut self.translations,
+            ChallengeKind::Transcription => &mut self.transcriptions,
+        }
+    }
+
+    fn seconds(&self, kind: ChallengeKind) -> f64 {
+        match kind {
+            ChallengeKind::Flashcard => self.flashcards.get().unwrap_or(DEFAULT_FLASHCARD_SECONDS),
+            ChallengeKind::Translation => self
+                .translations
+                .get()
+                .unwrap_or(DEFAULT_TRANSLATION_SECONDS),
+            ChallengeKind::Transcription => self
+                .transcriptions
+                .get()
+                .unwrap_or(DEFAULT_TRANSCRIPTION_SECONDS),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(target_arch = "wasm32", derive(tsify::Tsify))]
+#[cfg_attr(target_arch = "wasm32", tsify(into_wasm_abi))]
+pub struct QuickSessionPlan {
+    /// In the order they'll be reviewed
+    pub cards: Vec<CardIndicator<String>>,
+    /// How long reviewing `cards` should take, at the user's usual pace
+    pub expected_seconds: f64,
+    /// Due cards that didn't fit, which wait for the next session
+    pub left_out: usize,
+}
+
+impl Deck {
+    /// The kind of challenge `card` will most likely get. New and recognition-only cards are
+    /// always flashcards, and the others are flashcards when no sentence fits them.
+    fn expected_challenge_kind(&self, card: &CardIndicator<Spur>) -> ChallengeKind {
+        let is_new = self.cards.get(card).is_some_and(CardStatus::is_new);
+        match card {
+            _ if is_new || self.card_mode(card) != CardMode::Full => ChallengeKind::Flashcard,
+            CardIndicator::TargetLanguage { .. } => ChallengeKind::Translation,
+            CardIndicator::ListeningLexeme { .. } => ChallengeKind::Transcription,
+            _ => ChallengeKind::Flashcard,
+        }
+    }
+
+    /// Picks from `due_cards` (in review order) what fits in `minutes`, see `plan_quick_session`
+    fn quick_session(
+        &self,
+        due_cards: &[CardIndicator<Spur>],
+        minutes: f64,
+        now: DateTime<Utc>,
+    ) -> (Vec<CardIndicator<Spur>>, f64) {
+        let mut candidates = due_cards
+            .iter()
+            .enumerate()
+            .filter_map(|(index, card)| {
+                let Some(CardStatus::Tracked(CardData::Added { fsrs_card })) = self.cards.get(card)
+                else {
+                    return None;
+                };
+                let is_new = fsrs_card.state == rs_fsrs::State::New;
+                // How likely the user is to have forgotten it. New cards can't be forgotten, so
+                // they only fill the time that's left.
+                let value = if is_new {
+                    0.0
+                } else {
+                    1.0 - scheduler::retrievability(fsrs_card, now)
+                };
+                let seconds = self
+                    .stats
+                    .challenge_durations
+                    .seconds(self.expected_challenge_kind(card));
+                Some((index, *card, is_new, value / seconds, seconds))
+            })
+            .collect::<Vec<_>>();
+        candidates.sort_by(|a, b| {
+            a.2.cmp(&b.2)
+                .then(b.3.total_cmp(&a.3))
+                .then(a.4.total_cmp(&b.4))
+                .then(a.0.cmp(&b.0))
+        });
+
+        let budget = minutes * 60.0;
+        let mut expected_seconds = 0.0;
+        let mut chosen = Vec::new();
+        for (index, card, _, _, seconds) in candidates {
+            if expected_seconds + seconds <= budget {
+                expected_seconds += seconds;
+                chosen.push((index, card));
+            }
+        }
+        chosen.sort_by_key(|(index, _)| *index);
+        (
+            chosen.into_iter().map(|(_, card)| card).collect(),
+            expected_seconds,
+        )
+    }
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+impl Deck {
+    /// The due cards at `timestamp_ms` that are worth reviewing most in `minutes`, going by how
+    /// long the user usually takes on each kind of challenge. Cards the user is likeliest to have
+    /// forgotten for the time they take come first, so quick flashcards beat long transcriptions,
+    /// and new cards only fill the time that's left.
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn plan_quick_session(&self, minutes: f64, timestamp_ms: f64) -> QuickSessionPlan {
+        let due_cards = self.get_review_info(Vec::new(), timestamp_ms).due_cards;
+        let (cards, expected_seconds) =
+            self.quick_session(&due_cards, minutes, datetime_from_ms(timestamp_ms));
+        QuickSessionPlan {
+            left_out: due_cards.len() - cards.len(),
+            cards: cards
+                .into_iter()
+                .map(|card| card.resolve(&self.context.language_pack.rodeo))
+                .collect(),
+            expected_seconds,
+        }
+    }
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+impl ReviewInfo {
+    /// Limits the session to the due cards that fit in `minutes`, see `Deck::plan_quick_session`.
+    /// The others are left for the next session.
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn start_quick_session(
+        &mut self,
+        deck: &Deck,
+        minutes: f64,
+        timestamp_ms: f64,
+    ) -> QuickSessionPlan {
+        let (cards, expected_seconds) =
+            deck.quick_session(&self.due_cards, minutes, datetime_from_ms(timestamp_ms));
+        let left_out = self.due_cards.len() - cards.len();
+        self.due_cards = cards;
+        QuickSessionPlan {
+            cards: self
+                .due_cards
+                .iter()
+                .map(|card| card.resolve(&deck.context.language_pack.rodeo))
+                .collect(),
+            expected_seconds,
+            left_out,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{DeckEvent, LanguageEvent, LanguageEventContent, Rating};
+    use chrono::Duration;
+    use weapon::AppState;
+    use weapon::data_model::Timestamped;
+
+    #[test]
+    fn test_plans_what_fits_at_the_users_pace() {
+        let start = DateTime::<Utc>::from_timestamp(1_760_000_000, 0).unwrap();
+        let mut deck = Deck::default();
+        let event = deck
+            .add_next_unknown_cards(None, 5, Vec::new(), start.timestamp_millis() as f64)
+            .unwrap();
+        let DeckEvent::Language(LanguageEvent {
+            content: LanguageEventContent::AddCards { cards },
+            ..
+        }) = &event
+        else {
+            panic!("expected an AddCards event");
+        };
+        let cards = cards.clone();
+        let apply = |deck: &Deck, event: DeckEvent, timestamp: DateTime<Utc>| {
+            deck.apply_event(&Timestamped {
+                timestamp,
+                within_device_events_index: 0,
+                event,
+            })
+        };
+        deck = apply(&deck, event, start);
+        // Three flashcards, ten seconds each, that won't be due again for a while
+        for (i, card) in cards.iter().take(3).enumerate() {
+            let event = deck.review_card(card.clone(), Rating::Easy).unwrap();
+            deck = apply(&deck, event, start + Duration::seconds(10 * (i as i64 + 1)));
+        }
+        let now = (start + Duration::minutes(2)).timestamp_millis() as f64;
+
+        // The two new cards left are flashcards too
+        let plan = deck.plan_quick_session(0.25, now);
+        assert_eq!(plan.cards.len(), 1);
+        assert_eq!(plan.expected_seconds, 10.0);
+        assert_eq!(plan.left_out, 1);
+
+        let plan = deck.plan_quick_session(5.0, now);
+        assert_eq!(plan.cards, cards[3..].to_vec());
+        assert_eq!(plan.expected_seconds, 20.0);
+        assert_eq!(plan.left_out, 0);
+    }
+}
@@ -0,0 +1,149 @@
+//! Resolving a challenge looks up every string in it in the rodeo and copies it out, which adds
+//! up when the same card comes back a few times in a session (learning steps). Translation
+//! challenges only depend on their card and sentence, so the last few resolved ones are kept.
+
+use std::cell::RefCell;
+use std::collections::VecDeque;
+
+use lasso::Spur;
+
+use crate::{CardIndicator, Challenge, TranslateComprehensibleSentence};
+
+/// How many resolved challenges a deck keeps
+const CAPACITY: usize = 32;
+
+/// The card, the sentence, whether it's a favorite being practiced, and whether it's a sentence
+/// being repeated
+type Key = (CardIndicator<Spur>, Spur, bool, bool);
+
+#[derive(Clone, Debug, Default)]
+pub(crate) struct ResolvedChallenges {
+    /// Least recently used first
+    entries: RefCell<VecDeque<(Key, TranslateComprehensibleSentence<String>)>>,
+}
+
+impl ResolvedChallenges {
+    pub(crate) fn resolve(
+        &self,
+        challenge: &Challenge<Spur>,
+        rodeo: &lasso::RodeoReader,
+    ) -> Challenge<String> {
+        // Flashcards and transcriptions depend on the state of the deck (and transcriptions
+        // barely intern anything), so they're resolved every time
+        let Challenge::TranslateComprehensibleSentence(translation) = challenge else {
+            return challenge.resolve(rodeo);
+        };
+        let key = (
+            CardIndicator::TargetLanguage {
+                lexeme: translation.primary_expression,
+            },
+            translation.target_language,
+            translation.favorite_practice,
+            translation.sentence_repetition,
+        );
+
+        let mut entries = self.entries.borrow_mut();
+        if let Some(position) = entries.iter().position(|(existing, _)| *existing == key) {
+            let entry = entries.remove(position).expect("position is in bounds");
+            let resolved = TranslateComprehensibleSentence {
+                // The explanation is about this pick, which may have had other alternatives
+                explanation: translation.explanation.clone(),
+                // The hint policy follows the user's latest translations
+                hint_policy: translation.hint_policy,
+                ..entry.1.clone()
+            };
+            entries.push_back(entry);
+            return Challenge::TranslateComprehensibleSentence(resolved);
+        }
+
+        let resolved = translation.resolve(rodeo);
+        if entries.len() >= CAPACITY {
+            entries.pop_front();
+        }
+        entries.push_back((key, resolved.clone()));
+        Challenge::TranslateComprehensibleSentence(resolved)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{AudioRequest, HintPolicy, HintPolicyReason};
+    use language_utils::{Language, Lexeme, TtsProvider, TtsRequest};
+
+    fn translation(sentence: Spur, word: Spur) -> TranslateComprehensibleSentence<Spur> {
+        TranslateComprehensibleSentence {
+            audio: AudioRequest {
+                request: TtsRequest {
+                    text: String::new(),
+                    language: Language::French,
+                },
+                provider: TtsProvider::Google,
+            },
+            target_language: sentence,
+            target_language_literals: Vec::new(),
+            primary_expression: Lexeme::Multiword(word),
+            unique_target_language_lexemes: vec![Lexeme::Multiword(word)],
+            unique_target_language_lexeme_definitions: Vec::new(),
+            native_translations: vec!["The cat".to_string()],
+            source: None,
+            movie_titles: Vec::new(),
+            favorite_practice: false,
+            sentence_repetition: false,
+            explanation: None,
+            hint_policy: HintPolicy::default(),
+        }
+    }
+
+    #[test]
+    fn test_resolved_translations_are_reused_until_evicted() {
+        let mut rodeo = lasso::Rodeo::new();
+        let word = rodeo.get_or_intern("chat");
+        let sentences = (0..=CAPACITY)
+            .map(|index| rodeo.get_or_intern(format!("Le chat {index}")))
+            .collect::<Vec<_>>();
+        let rodeo = rodeo.into_reader();
+        let resolved = ResolvedChallenges::default();
+        let resolve = |translation: TranslateComprehensibleSentence<Spur>| {
+            let challenge = Challenge::TranslateComprehensibleSentence(translation);
+            let Challenge::TranslateComprehensibleSentence(translation) =
+                resolved.resolve(&challenge, &rodeo)
+            else {
+                panic!("expected a translation");
+            };
+            translation
+        };
+
+        let first = resolve(translation(sentences[0], word));
+        assert_eq!(first.target_language, "Le chat 0");
+        assert_eq!(
+            first.primary_expression,
+            Lexeme::Multiword("chat".to_string())
+        );
+
+        // The cached copy still takes the latest hint policy
+        let struggling = HintPolicy {
+            delay_seconds: 0,
+            reason: HintPolicyReason::Struggling,
+        };
+        let again = resolve(TranslateComprehensibleSentence {
+            hint_policy: struggling,
+            ..translation(sentences[0], word)
+        });
+        assert_eq!(again.target_language, "Le chat 0");
+        assert_eq!(again.hint_policy, struggling);
+        assert_eq!(resolved.entries.borrow().len(), 1);
+
+        // Once it's full, the least recently used challenge makes room
+        for sentence in &sentences[1..] {
+            resolve(translation(*sentence, word));
+        }
+        let entries = resolved.entries.borrow();
+        assert_eq!(entries.len(), CAPACITY);
+        assert!(
+            entries
+                .iter()
+                .all(|((_, sentence, ..), _)| *sentence != sentences[0])
+        );
+    }
+}
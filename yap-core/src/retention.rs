@@ -0,0 +1,258 @@
+//! How often the user actually remembers cards when they come due, next to the retention they were
+//! scheduled for, to check the scheduler is doing what it's configured to. Every review of a card
+//! that was due is kept for `HISTORY_DAYS`, along with the retention the card was scheduled for at
+//! the time, since it changes with the FSRS parameters, the scheduling profile and the card's mode.
+
+use std::collections::VecDeque;
+
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+#[cfg(target_arch = "wasm32")]
+use wasm_bindgen::prelude::*;
+
+use crate::{CARD_TYPES, CardType, Deck, datetime_from_ms};
+
+/// Due reviews older than this are dropped, so it's also the longest window that can be reported on
+const HISTORY_DAYS: u32 = 90;
+
+/// How long the interval a card was reviewed after was
+#[derive(Copy, Clone, Debug, Serialize, Deserialize, PartialEq, Eq, Ord, PartialOrd)]
+#[cfg_attr(target_arch = "wasm32", derive(tsify::Tsify))]
+#[cfg_attr(target_arch = "wasm32", tsify(into_wasm_abi))]
+pub enum IntervalBucket {
+    UpToADay,
+    UpToAWeek,
+    UpToAMonth,
+    UpToThreeMonths,
+    Longer,
+}
+
+const INTERVAL_BUCKETS: [IntervalBucket; 5] = [
+    IntervalBucket::UpToADay,
+    IntervalBucket::UpToAWeek,
+    IntervalBucket::UpToAMonth,
+    IntervalBucket::UpToThreeMonths,
+    IntervalBucket::Longer,
+];
+
+impl IntervalBucket {
+    fn of(interval_days: f64) -> Self {
+        match interval_days {
+            days if days <= 1.0 => IntervalBucket::UpToADay,
+            days if days <= 7.0 => IntervalBucket::UpToAWeek,
+            days if days <= 30.0 => IntervalBucket::UpToAMonth,
+            days if days <= 90.0 => IntervalBucket::UpToThreeMonths,
+            _ => IntervalBucket::Longer,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+struct DueReview {
+    timestamp: DateTime<Utc>,
+    card_type: CardType,
+    interval: IntervalBucket,
+    remembered: bool,
+    target_retention: f64,
+}
+
+/// The latest due reviews, tracked as events are processed
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq)]
+pub(crate) struct RetentionHistory {
+    /// Oldest first
+    reviews: VecDeque<DueReview>,
+}
+
+impl RetentionHistory {
+    /// A review of a card that was due, after an interval of `interval_days`
+    pub(crate) fn record(
+        &mut self,
+        timestamp: DateTime<Utc>,
+        card_type: CardType,
+        interval_days: f64,
+        remembered: bool,
+        target_retention: f64,
+    ) {
+        self.reviews.push_back(DueReview {
+            timestamp,
+            card_type,
+            interval: IntervalBucket::of(interval_days),
+            remembered,
+            target_retention,
+        });
+        let cutoff = timestamp - Duration::days(HISTORY_DAYS.into());
+        while self
+            .reviews
+            .front()
+            .is_some_and(|review| review.timestamp < cutoff)
+        {
+            self.reviews.pop_front();
+        }
+    }
+
+    fn stats(&self, window_days: u32, now: DateTime<Utc>) -> RetentionStats {
+        let window_days = window_days.min(HISTORY_DAYS);
+        let start = now - Duration::days(window_days.into());
+        let reviews = self
+            .reviews
+            .iter()
+            .filter(|review| start <= review.timestamp && review.timestamp <= now)
+            .collect::<Vec<_>>();
+        let retention = |matches: &dyn Fn(&DueReview) -> bool| {
+            RetentionCounts::of(reviews.iter().copied().filter(|review| matches(review)))
+        };
+
+        RetentionStats {
+            window_days,
+            overall: retention(&|_| true),
+            by_card_type: CARD_TYPES
+                .into_iter()
+                .map(|card_type| CardTypeRetention {
+                    card_type,
+                    retention: retention(&|review| review.card_type == card_type),
+                })
+                .collect(),
+            by_interval: INTERVAL_BUCKETS
+                .into_iter()
+                .map(|interval| IntervalRetention {
+                    interval,
+                    retention: retention(&|review| review.interval == interval),
+                })
+                .collect(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(target_arch = "wasm32", derive(tsify::Tsify))]
+#[cfg_attr(target_arch = "wasm32", tsify(into_wasm_abi))]
+pub struct RetentionCounts {
+    /// Reviews of cards that were due
+    pub reviews: u32,
+    /// How many of `reviews` weren't rated `Rating::Again`
+    pub remembered: u32,
+    /// `remembered / reviews`, or `None` without any reviews
+    pub observed_retention: Option<f64>,
+    /// The average retention the reviewed cards were scheduled for, or `None` without any reviews
+    pub target_retention: Option<f64>,
+}
+
+impl RetentionCounts {
+    fn of<'a>(reviews: impl Iterator<Item = &'a DueReview>) -> Self {
+        let (mut count, mut remembered, mut target_sum) = (0, 0, 0.0);
+        for review in reviews {
+            count += 1;
+            remembered += u32::from(review.remembered);
+            target_sum += review.target_retention;
+        }
+        RetentionCounts {
+            reviews: count,
+            remembered,
+            observed_retention: (count > 0).then(|| remembered as f64 / count as f64),
+            target_retention: (count > 0).then(|| target_sum / count as f64),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(target_arch = "wasm32", derive(tsify::Tsify))]
+#[cfg_attr(target_arch = "wasm32", tsify(into_wasm_abi))]
+pub struct CardTypeRetention {
+    pub card_type: CardType,
+    pub retention: RetentionCounts,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(target_arch = "wasm32", derive(tsify::Tsify))]
+#[cfg_attr(target_arch = "wasm32", tsify(into_wasm_abi))]
+pub struct IntervalRetention {
+    pub interval: IntervalBucket,
+    pub retention: RetentionCounts,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(target_arch = "wasm32", derive(tsify::Tsify))]
+#[cfg_attr(target_arch = "wasm32", tsify(into_wasm_abi))]
+pub struct RetentionStats {
+    /// The window the stats cover, which is shorter than asked for if it was over `HISTORY_DAYS`
+    pub window_days: u32,
+    pub overall: RetentionCounts,
+    /// Every card type, in the order of `CARD_TYPES`
+    pub by_card_type: Vec<CardTypeRetention>,
+    /// Every interval bucket, shortest first
+    pub by_interval: Vec<IntervalRetention>,
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+impl Deck {
+    /// How often cards were remembered when they came due in the `window_days` (usually 30)
+    /// before `timestamp_ms`, next to the retention they were scheduled for. Reviews of cards that
+    /// weren't due yet don't count, since they'd make retention look better than it is. With SM-2
+    /// or fixed intervals the target is still FSRS's, which those schedulers don't aim for.
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn get_retention_stats(&self, window_days: u32, timestamp_ms: f64) -> RetentionStats {
+        self.stats
+            .retention
+            .stats(window_days, datetime_from_ms(timestamp_ms))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stats_split_the_window_by_card_type_and_interval() {
+        let now = DateTime::<Utc>::from_timestamp(1_760_000_000, 0).unwrap();
+        let mut history = RetentionHistory::default();
+        // Too old for a 30 day window
+        history.record(
+            now - Duration::days(40),
+            CardType::Listening,
+            3.0,
+            false,
+            0.9,
+        );
+        history.record(
+            now - Duration::days(2),
+            CardType::TargetLanguage,
+            0.5,
+            true,
+            0.9,
+        );
+        history.record(
+            now - Duration::days(1),
+            CardType::TargetLanguage,
+            20.0,
+            false,
+            0.9,
+        );
+        history.record(now, CardType::Listening, 20.0, true, 0.8);
+
+        let stats = history.stats(30, now);
+        assert_eq!(stats.overall.reviews, 3);
+        assert_eq!(stats.overall.remembered, 2);
+        assert!((stats.overall.target_retention.unwrap() - 2.6 / 3.0).abs() < 1e-9);
+
+        let by_card_type = |card_type| {
+            stats
+                .by_card_type
+                .iter()
+                .find(|retention| retention.card_type == card_type)
+                .map(|retention| retention.retention.observed_retention)
+        };
+        assert_eq!(by_card_type(CardType::TargetLanguage), Some(Some(0.5)));
+        assert_eq!(by_card_type(CardType::Listening), Some(Some(1.0)));
+        assert_eq!(by_card_type(CardType::LetterPronunciation), Some(None));
+
+        let month = &stats.by_interval[2];
+        assert_eq!(month.interval, IntervalBucket::UpToAMonth);
+        assert_eq!(
+            (month.retention.reviews, month.retention.remembered),
+            (2, 1)
+        );
+
+        assert_eq!(history.stats(365, now).window_days, HISTORY_DAYS);
+        assert_eq!(history.stats(365, now).overall.reviews, 4);
+    }
+}
@@ -0,0 +1,444 @@
+use chrono::{DateTime, Duration, Utc};
+use language_utils::fsrs_parameters::FsrsParameters;
+use rs_fsrs::{Card, FSRS, Rating, State};
+use serde::{Deserialize, Serialize};
+
+/// Decides when a card is due next after it's reviewed.
+///
+/// All schedulers work on `rs_fsrs::Card`s so the rest of the deck doesn't care which one is in
+/// use. `stability` is always roughly the current interval in days, which is what the rest of the
+/// deck reads it as.
+pub(crate) trait Scheduler {
+    fn next(&self, card: Card, rating: Rating, now: DateTime<Utc>) -> Card;
+}
+
+/// Which scheduler a deck uses. Chosen with a deck event, so every device replaying the same
+/// events schedules the same way.
+#[derive(Copy, Clone, Debug, Default, Serialize, Deserialize, PartialEq, Eq, Ord, PartialOrd)]
+#[cfg_attr(target_arch = "wasm32", derive(tsify::Tsify))]
+#[cfg_attr(target_arch = "wasm32", tsify(into_wasm_abi, from_wasm_abi))]
+pub enum SchedulerKind {
+    #[default]
+    Fsrs,
+    Sm2,
+    FixedIntervals,
+}
+
+impl SchedulerKind {
+    /// Whether this scheduler keeps SM-2's ease factor in a card's `difficulty`, rather than FSRS's
+    /// difficulty. Fixed intervals don't use `difficulty`, so it's left as FSRS had it.
+    fn keeps_ease_in_difficulty(self) -> bool {
+        self == SchedulerKind::Sm2
+    }
+
+    /// `card` with its `difficulty` rewritten in the scale `to` reads it in, for when the deck
+    /// switches from this scheduler to `to`. New cards have no difficulty yet.
+    pub(crate) fn convert_card(self, to: SchedulerKind, mut card: Card) -> Card {
+        if card.state == State::New {
+            return card;
+        }
+        match (
+            self.keeps_ease_in_difficulty(),
+            to.keeps_ease_in_difficulty(),
+        ) {
+            (false, true) => card.difficulty = ease_from_fsrs_difficulty(card.difficulty),
+            (true, false) => card.difficulty = fsrs_difficulty_from_ease(card.difficulty),
+            _ => {}
+        }
+        card
+    }
+}
+
+/// The order due cards are reviewed in. Chosen with a deck event, like `SchedulerKind`.
+#[derive(Copy, Clone, Debug, Default, Serialize, Deserialize, PartialEq, Eq, Ord, PartialOrd)]
+#[cfg_attr(target_arch = "wasm32", derive(tsify::Tsify))]
+#[cfg_attr(target_arch = "wasm32", tsify(into_wasm_abi, from_wasm_abi))]
+pub enum ReviewOrder {
+    /// The card that's been due the longest first
+    #[default]
+    DueDate,
+    /// The card the user is least likely to remember first, so an easy card that's long overdue
+    /// waits for a hard one that's only just due
+    Retrievability,
+    /// Like `Retrievability`, but weighted by how often the word comes up, since forgetting a
+    /// common word costs more than forgetting a rare one
+    ForgettingCost,
+}
+
+/// The retention FSRS schedules for when neither the user nor the course's pack say otherwise
+const DEFAULT_REQUEST_RETENTION: f64 = 0.7;
+
+/// How a single card is practiced, chosen with a deck event. Some words the user only wants to
+/// understand when they see or hear them, not to come up with themselves.
+#[derive(Copy, Clone, Debug, Default, Serialize, Deserialize, PartialEq, Eq, Ord, PartialOrd)]
+#[cfg_attr(target_arch = "wasm32", derive(tsify::Tsify))]
+#[cfg_attr(target_arch = "wasm32", tsify(into_wasm_abi, from_wasm_abi))]
+pub enum CardMode {
+    #[default]
+    Full,
+    /// Only flashcards and multiple-choice listening, never translating or transcribing a
+    /// sentence, and scheduled for a lower retention (see `recognition_fsrs_with`)
+    Recognition,
+}
+
+/// How much lower the retention recognition-only cards are scheduled for than the deck's other
+/// cards
+const RECOGNITION_RETENTION_DROP: f64 = 0.1;
+
+/// Recognition-only cards aren't scheduled for less retention than this, unless the deck's is
+/// already lower
+const MIN_RECOGNITION_RETENTION: f64 = 0.5;
+
+/// How a deck's reviews are scheduled overall, chosen per course with a deck event
+#[derive(Copy, Clone, Debug, Default, Serialize, Deserialize, PartialEq, Eq, Ord, PartialOrd)]
+#[cfg_attr(target_arch = "wasm32", derive(tsify::Tsify))]
+#[cfg_attr(target_arch = "wasm32", tsify(into_wasm_abi, from_wasm_abi))]
+pub enum SchedulingProfile {
+    #[default]
+    Standard,
+    /// For a language the user has finished (see `Deck::graduate`) and only wants to keep: a
+    /// lower retention, intervals that grow faster, and no new cards from smart add
+    Maintenance,
+}
+
+/// How much lower the retention is with `SchedulingProfile::Maintenance`
+const MAINTENANCE_RETENTION_DROP: f64 = 0.1;
+
+/// `SchedulingProfile::Maintenance` doesn't schedule for less retention than this, unless the
+/// deck's is already lower
+const MIN_MAINTENANCE_RETENTION: f64 = 0.6;
+
+/// Where the FSRS parameters of a deck came from
+#[derive(Copy, Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[cfg_attr(target_arch = "wasm32", derive(tsify::Tsify))]
+#[cfg_attr(target_arch = "wasm32", tsify(into_wasm_abi, from_wasm_abi))]
+pub enum FsrsParametersSource {
+    /// The app's defaults
+    Default,
+    /// The course's recommended parameters, from its language pack
+    Preset,
+    /// The user's own, set with `Deck::set_fsrs_parameters`
+    Personalized,
+}
+
+/// FSRS set up with `parameters`, or the defaults if there aren't any
+pub(crate) fn fsrs_with(parameters: Option<&FsrsParameters>) -> FSRS {
+    let mut fsrs_parameters = rs_fsrs::Parameters {
+        request_retention: DEFAULT_REQUEST_RETENTION,
+        ..Default::default()
+    };
+    if let Some(parameters) = parameters {
+        fsrs_parameters.request_retention = parameters.request_retention;
+        if !parameters.weights.is_empty() {
+            match parameters.weights.as_slice().try_into() {
+                Ok(weights) => fsrs_parameters.w = weights,
+                Err(_) => log::warn!(
+                    "Ignoring {} FSRS weights, which is the wrong number for this FSRS version",
+                    parameters.weights.len()
+                ),
+            }
+        }
+    }
+    FSRS::new(fsrs_parameters)
+}
+
+/// Like `fsrs_with`, but for the lower retention of `CardMode::Recognition` cards
+pub(crate) fn recognition_fsrs_with(parameters: Option<&FsrsParameters>) -> FSRS {
+    fsrs_with(Some(&FsrsParameters {
+        request_retention: recognition_retention(requested_retention(parameters)),
+        weights: parameters
+            .map(|parameters| parameters.weights.clone())
+            .unwrap_or_default(),
+    }))
+}
+
+/// The FSRS a deck schedules its `CardMode::Full` cards with: `fsrs_with` its parameters, at a
+/// lower retention with `SchedulingProfile::Maintenance`
+pub(crate) fn deck_fsrs(parameters: Option<&FsrsParameters>, profile: SchedulingProfile) -> FSRS {
+    if profile == SchedulingProfile::Standard {
+        return fsrs_with(parameters);
+    }
+    fsrs_with(Some(&FsrsParameters {
+        request_retention: maintenance_retention(requested_retention(parameters)),
+        weights: parameters
+            .map(|parameters| parameters.weights.clone())
+            .unwrap_or_default(),
+    }))
+}
+
+fn requested_retention(parameters: Option<&FsrsParameters>) -> f64 {
+    parameters.map_or(DEFAULT_REQUEST_RETENTION, |parameters| {
+        parameters.request_retention
+    })
+}
+
+fn recognition_retention(request_retention: f64) -> f64 {
+    (request_retention - RECOGNITION_RETENTION_DROP)
+        .max(MIN_RECOGNITION_RETENTION.min(request_retention))
+}
+
+fn maintenance_retention(request_retention: f64) -> f64 {
+    (request_retention - MAINTENANCE_RETENTION_DROP)
+        .max(MIN_MAINTENANCE_RETENTION.min(request_retention))
+}
+
+/// The retention a card in `mode` is scheduled for with `parameters` and `profile`, as
+/// `recognition_fsrs_with` and `deck_fsrs` pick it
+pub(crate) fn target_retention(
+    parameters: Option<&FsrsParameters>,
+    profile: SchedulingProfile,
+    mode: CardMode,
+) -> f64 {
+    let request_retention = requested_retention(parameters);
+    match (mode, profile) {
+        (CardMode::Recognition, _) => recognition_retention(request_retention),
+        (CardMode::Full, SchedulingProfile::Standard) => request_retention,
+        (CardMode::Full, SchedulingProfile::Maintenance) => {
+            maintenance_retention(request_retention)
+        }
+    }
+}
+
+impl SchedulingProfile {
+    /// The rating `rating` is scheduled as. A maintenance review is of a word the user already
+    /// knows well, so remembering it grows the interval like an easy review would.
+    pub(crate) fn scheduled_rating(self, state: State, rating: Rating) -> Rating {
+        match (self, state, rating) {
+            (SchedulingProfile::Maintenance, State::Review, Rating::Good) => Rating::Easy,
+            (_, _, rating) => rating,
+        }
+    }
+}
+
+impl Scheduler for FSRS {
+    fn next(&self, card: Card, rating: Rating, now: DateTime<Utc>) -> Card {
+        FSRS::next(self, card, now, rating).card
+    }
+}
+
+const FORGETTING_CURVE_DECAY: f64 = -0.5;
+const FORGETTING_CURVE_FACTOR: f64 = 19.0 / 81.0;
+
+/// How likely the user is to remember `card` at `now`, by FSRS's forgetting curve. The other
+/// schedulers keep `stability` in days too, so this is a fair estimate for them as well. Cards
+/// that were just failed have no stability, and count as forgotten.
+pub(crate) fn retrievability(card: &Card, now: DateTime<Utc>) -> f64 {
+    if card.stability <= 0.0 {
+        return 0.0;
+    }
+    let elapsed_days = (now - card.last_review).num_seconds().max(0) as f64 / 86400.0;
+    (1.0 + FORGETTING_CURVE_FACTOR * elapsed_days / card.stability).powf(FORGETTING_CURVE_DECAY)
+}
+
+/// How soon a failed card comes back with SM-2 or fixed intervals, so it can be relearned in the
+/// same session
+const RELEARNING_DELAY_MINUTES: i64 = 10;
+
+fn schedule_in_days(mut card: Card, interval_days: f64, now: DateTime<Utc>) -> Card {
+    card.stability = interval_days;
+    card.due = now + Duration::seconds((interval_days * 86400.0).round() as i64);
+    card
+}
+
+fn fail(mut card: Card, now: DateTime<Utc>) -> Card {
+    card.lapses += 1;
+    card.state = match card.state {
+        State::New | State::Learning => State::Learning,
+        State::Review | State::Relearning => State::Relearning,
+    };
+    card.stability = 0.0;
+    card.due = now + Duration::minutes(RELEARNING_DELAY_MINUTES);
+    card
+}
+
+/// Classic SuperMemo 2. The ease factor lives in the card's `difficulty`, see
+/// `SchedulerKind::convert_card`.
+pub(crate) struct Sm2;
+
+const SM2_INITIAL_EASE: f64 = 2.5;
+const SM2_MINIMUM_EASE: f64 = 1.3;
+
+/// FSRS keeps difficulty between these
+const FSRS_MINIMUM_DIFFICULTY: f64 = 1.0;
+const FSRS_MAXIMUM_DIFFICULTY: f64 = 10.0;
+
+/// The easiest FSRS difficulty maps to SM-2's initial ease and the hardest to its minimum, since
+/// a card only gets an ease above the initial one from Easy reviews
+fn ease_from_fsrs_difficulty(difficulty: f64) -> f64 {
+    let hardness = (difficulty.clamp(FSRS_MINIMUM_DIFFICULTY, FSRS_MAXIMUM_DIFFICULTY)
+        - FSRS_MINIMUM_DIFFICULTY)
+        / (FSRS_MAXIMUM_DIFFICULTY - FSRS_MINIMUM_DIFFICULTY);
+    SM2_INITIAL_EASE - hardness * (SM2_INITIAL_EASE - SM2_MINIMUM_EASE)
+}
+
+/// The inverse of `ease_from_fsrs_difficulty`
+fn fsrs_difficulty_from_ease(ease: f64) -> f64 {
+    let hardness = (SM2_INITIAL_EASE - ease.clamp(SM2_MINIMUM_EASE, SM2_INITIAL_EASE))
+        / (SM2_INITIAL_EASE - SM2_MINIMUM_EASE);
+    FSRS_MINIMUM_DIFFICULTY + hardness * (FSRS_MAXIMUM_DIFFICULTY - FSRS_MINIMUM_DIFFICULTY)
+}
+
+impl Scheduler for Sm2 {
+    fn next(&self, mut card: Card, rating: Rating, now: DateTime<Utc>) -> Card {
+        let quality: f64 = match rating {
+            Rating::Again => 1.0,
+            Rating::Hard => 3.0,
+            Rating::Good => 4.0,
+            Rating::Easy => 5.0,
+        };
+        let ease = if card.state == State::New {
+            SM2_INITIAL_EASE
+        } else {
+            card.difficulty
+        };
+        card.difficulty = (ease + (0.1 - (5.0 - quality) * (0.08 + (5.0 - quality) * 0.02)))
+            .max(SM2_MINIMUM_EASE);
+        card.reps += 1;
+        card.last_review = now;
+
+        if matches!(rating, Rating::Again) {
+            return fail(card, now);
+        }
+
+        let interval_days = match card.state {
+            // Failing a card restarts its repetitions
+            State::New | State::Learning | State::Relearning => 1.0,
+            State::Review if card.stability < 6.0 => 6.0,
+            State::Review => card.stability * ease,
+        };
+        card.state = State::Review;
+        schedule_in_days(card, interval_days, now)
+    }
+}
+
+/// A fixed ladder of intervals. Good moves a card up a step, Easy two steps, Hard keeps it on
+/// the same step, and Again starts it over.
+pub(crate) struct FixedIntervals;
+
+const FIXED_INTERVAL_DAYS: [f64; 8] = [1.0, 3.0, 7.0, 14.0, 30.0, 60.0, 120.0, 240.0];
+
+impl Scheduler for FixedIntervals {
+    fn next(&self, mut card: Card, rating: Rating, now: DateTime<Utc>) -> Card {
+        card.reps += 1;
+        card.last_review = now;
+
+        let current_step = match card.state {
+            State::New | State::Learning | State::Relearning => None,
+            State::Review => FIXED_INTERVAL_DAYS
+                .iter()
+                .rposition(|&days| days <= card.stability),
+        };
+        let next_step = match (rating, current_step) {
+            (Rating::Again, _) => return fail(card, now),
+            (_, None) => 0,
+            (Rating::Hard, Some(step)) => step,
+            (Rating::Good, Some(step)) => step + 1,
+            (Rating::Easy, Some(step)) => step + 2,
+        };
+        card.state = State::Review;
+        schedule_in_days(
+            card,
+            FIXED_INTERVAL_DAYS[next_step.min(FIXED_INTERVAL_DAYS.len() - 1)],
+            now,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn review(scheduler: &impl Scheduler, ratings: &[Rating]) -> Card {
+        let mut now = Utc::now();
+        let mut card = Card::new(now);
+        for rating in ratings {
+            card = scheduler.next(card, *rating, now);
+            now = card.due;
+        }
+        card
+    }
+
+    #[test]
+    fn test_sm2_intervals() {
+        let card = review(&Sm2, &[Rating::Good]);
+        assert_eq!(card.stability, 1.0);
+        let card = review(&Sm2, &[Rating::Good, Rating::Good]);
+        assert_eq!(card.stability, 6.0);
+        let card = review(&Sm2, &[Rating::Good, Rating::Good, Rating::Good]);
+        assert!(card.stability > 6.0);
+
+        let card = review(&Sm2, &[Rating::Good, Rating::Good, Rating::Again]);
+        assert_eq!(card.state, State::Relearning);
+        assert_eq!(card.lapses, 1);
+        assert_eq!(card.reps, 3);
+    }
+
+    #[test]
+    fn test_switching_schedulers_converts_difficulty() {
+        let fsrs = fsrs_with(None);
+        let card = review(&fsrs, &[Rating::Good, Rating::Good, Rating::Hard]);
+        let ease = SchedulerKind::Fsrs
+            .convert_card(SchedulerKind::Sm2, card.clone())
+            .difficulty;
+        assert!((SM2_MINIMUM_EASE..=SM2_INITIAL_EASE).contains(&ease));
+        // Round trips, including through fixed intervals, which don't touch difficulty
+        let back = SchedulerKind::Sm2.convert_card(
+            SchedulerKind::FixedIntervals,
+            SchedulerKind::Fsrs.convert_card(SchedulerKind::Sm2, card.clone()),
+        );
+        assert!((back.difficulty - card.difficulty).abs() < 1e-9);
+        assert_eq!(
+            SchedulerKind::Fsrs
+                .convert_card(SchedulerKind::FixedIntervals, card.clone())
+                .difficulty,
+            card.difficulty
+        );
+
+        assert_eq!(
+            ease_from_fsrs_difficulty(FSRS_MINIMUM_DIFFICULTY),
+            SM2_INITIAL_EASE
+        );
+        assert_eq!(
+            ease_from_fsrs_difficulty(FSRS_MAXIMUM_DIFFICULTY),
+            SM2_MINIMUM_EASE
+        );
+        // An ease above the initial one (from Easy reviews) is as easy as FSRS goes
+        assert_eq!(fsrs_difficulty_from_ease(2.8), FSRS_MINIMUM_DIFFICULTY);
+
+        let new = Card::new(Utc::now());
+        assert_eq!(
+            SchedulerKind::Fsrs
+                .convert_card(SchedulerKind::Sm2, new.clone())
+                .difficulty,
+            new.difficulty
+        );
+    }
+
+    #[test]
+    fn test_retrievability_decays() {
+        let card = review(&Sm2, &[Rating::Good, Rating::Good]);
+        let just_reviewed = retrievability(&card, card.last_review);
+        let due = retrievability(&card, card.due);
+        let overdue = retrievability(&card, card.due + Duration::days(30));
+        assert_eq!(just_reviewed, 1.0);
+        assert!(just_reviewed > due && due > overdue && overdue > 0.0);
+
+        let failed = review(&Sm2, &[Rating::Good, Rating::Again]);
+        assert_eq!(retrievability(&failed, failed.last_review), 0.0);
+    }
+
+    #[test]
+    fn test_fixed_intervals() {
+        let card = review(&FixedIntervals, &[Rating::Good, Rating::Good, Rating::Good]);
+        assert_eq!(card.stability, 7.0);
+        let card = review(&FixedIntervals, &[Rating::Good, Rating::Hard]);
+        assert_eq!(card.stability, 1.0);
+        let card = review(&FixedIntervals, &[Rating::Good, Rating::Easy]);
+        assert_eq!(card.stability, 7.0);
+        let card = review(
+            &FixedIntervals,
+            &[Rating::Good, Rating::Again, Rating::Good],
+        );
+        assert_eq!(card.stability, 1.0);
+    }
+}
@@ -0,0 +1,148 @@
+//! Why a sentence was picked for a challenge. `Deck::get_comprehensible_sentence_containing` tallies
+//! what happened to every candidate as it goes, which costs a few counters. The tally is only
+//! turned into a `SentenceChoiceExplanation` (resolving the alternatives' text) and attached to
+//! the challenge when `ReviewInfo::set_explain_sentence_choices` is on, so selection can be tuned
+//! against real cases.
+
+use lasso::Spur;
+use serde::{Deserialize, Serialize};
+
+use crate::sentence_filters::SentenceFilter;
+
+/// How many of the sentences that weren't picked are listed
+const ALTERNATIVES_LISTED: usize = 5;
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[cfg_attr(target_arch = "wasm32", derive(tsify::Tsify))]
+#[cfg_attr(target_arch = "wasm32", tsify(into_wasm_abi, from_wasm_abi))]
+pub struct SentenceChoiceExplanation {
+    /// The sentences containing the card's word, or every sentence if any word would do
+    pub candidates: usize,
+    /// Candidates the language pack has no lexemes for
+    pub missing_lexemes: usize,
+    /// Candidates with a word the user can't read (or hear) yet
+    pub unknown_words: usize,
+    /// Candidates with more literals than `SentenceFilters::max_literals`
+    pub too_long: usize,
+    /// Candidates flagged as profane, with `SentenceFilters::exclude_profanity` on
+    pub profane: usize,
+    /// Candidates that only come from mature movies, with `SentenceFilters::exclude_mature_movies`
+    /// on
+    pub mature_movie: usize,
+    /// Candidates that only come from sources in `SentenceFilters::excluded_sources`
+    pub excluded_source: usize,
+    /// The candidates that passed every check, including the one picked
+    pub comprehensible: usize,
+    /// How many times the picked sentence had been reviewed. Sentences reviewed less are
+    /// preferred, as are ones closer to the user's `SentenceLength`.
+    pub times_reviewed: u32,
+    /// The next sentences in line, best first
+    pub alternatives: Vec<SentenceAlternative>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[cfg_attr(target_arch = "wasm32", derive(tsify::Tsify))]
+#[cfg_attr(target_arch = "wasm32", tsify(into_wasm_abi, from_wasm_abi))]
+pub struct SentenceAlternative {
+    pub sentence: String,
+    pub times_reviewed: u32,
+}
+
+/// What `get_comprehensible_sentence_containing` did with its candidates, before resolving
+#[derive(Clone, Debug, Default)]
+pub(crate) struct SentenceChoice {
+    explanation: SentenceChoiceExplanation,
+    alternatives: Vec<(Spur, u32)>,
+}
+
+impl SentenceChoice {
+    pub(crate) fn new(candidates: usize) -> Self {
+        Self {
+            explanation: SentenceChoiceExplanation {
+                candidates,
+                ..Default::default()
+            },
+            alternatives: Vec::new(),
+        }
+    }
+
+    pub(crate) fn missing_lexemes(&mut self) {
+        self.explanation.missing_lexemes += 1;
+    }
+
+    pub(crate) fn unknown_words(&mut self) {
+        self.explanation.unknown_words += 1;
+    }
+
+    pub(crate) fn filtered(&mut self, filter: SentenceFilter) {
+        match filter {
+            SentenceFilter::MaxLiterals => self.explanation.too_long += 1,
+            SentenceFilter::Profanity => self.explanation.profane += 1,
+            SentenceFilter::MatureMovie => self.explanation.mature_movie += 1,
+            SentenceFilter::ExcludedSource => self.explanation.excluded_source += 1,
+        }
+    }
+
+    /// Records the outcome, given the comprehensible sentences sorted by `sentence_score`, so the
+    /// first is the one picked
+    pub(crate) fn picked(&mut self, sorted: &[(Spur, u32)]) {
+        self.explanation.comprehensible = sorted.len();
+        self.explanation.times_reviewed = sorted.first().map_or(0, |(_, reviewed)| *reviewed);
+        self.alternatives = sorted
+            .iter()
+            .skip(1)
+            .take(ALTERNATIVES_LISTED)
+            .copied()
+            .collect();
+    }
+
+    pub(crate) fn explain(&self, rodeo: &lasso::RodeoReader) -> SentenceChoiceExplanation {
+        SentenceChoiceExplanation {
+            alternatives: self
+                .alternatives
+                .iter()
+                .map(|(sentence, times_reviewed)| SentenceAlternative {
+                    sentence: rodeo.resolve(sentence).to_string(),
+                    times_reviewed: *times_reviewed,
+                })
+                .collect(),
+            ..self.explanation.clone()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_explanation_lists_the_next_sentences_in_line() {
+        let mut rodeo = lasso::Rodeo::new();
+        let sorted = (0..8)
+            .map(|i| (rodeo.get_or_intern(format!("sentence {i}")), i))
+            .collect::<Vec<_>>();
+
+        let mut choice = SentenceChoice::new(12);
+        choice.unknown_words();
+        choice.unknown_words();
+        choice.missing_lexemes();
+        choice.filtered(SentenceFilter::Profanity);
+        choice.picked(&sorted);
+        let explanation = choice.explain(&rodeo.into_reader());
+
+        assert_eq!(explanation.candidates, 12);
+        assert_eq!(explanation.unknown_words, 2);
+        assert_eq!(explanation.missing_lexemes, 1);
+        assert_eq!(explanation.profane, 1);
+        assert_eq!(explanation.comprehensible, 8);
+        assert_eq!(explanation.times_reviewed, 0);
+        assert_eq!(
+            explanation.alternatives.first(),
+            Some(&SentenceAlternative {
+                sentence: "sentence 1".to_string(),
+                times_reviewed: 1,
+            })
+        );
+        assert_eq!(explanation.alternatives.len(), ALTERNATIVES_LISTED);
+    }
+}
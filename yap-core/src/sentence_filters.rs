@@ -0,0 +1,192 @@
+use language_utils::MaturityRating;
+use language_utils::language_pack::LanguagePack;
+use lasso::Spur;
+use serde::{Deserialize, Serialize};
+
+/// Where a sentence can come from, for excluding sources with `SentenceFilters`
+#[derive(Copy, Clone, Debug, Serialize, Deserialize, PartialEq, Eq, Ord, PartialOrd, Hash)]
+#[cfg_attr(target_arch = "wasm32", derive(tsify::Tsify))]
+#[cfg_attr(target_arch = "wasm32", tsify(into_wasm_abi, from_wasm_abi))]
+pub enum SentenceSourceKind {
+    Anki,
+    Tatoeba,
+    Song,
+    Movie,
+}
+
+/// Which sentences the user doesn't want to see in challenges. Set with a deck event, so every
+/// device picks from the same sentences. Manually added sentences are never filtered out.
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[cfg_attr(target_arch = "wasm32", derive(tsify::Tsify))]
+#[cfg_attr(target_arch = "wasm32", tsify(into_wasm_abi, from_wasm_abi))]
+pub struct SentenceFilters {
+    /// Sentences with more literals than this (words and punctuation) are skipped
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_literals: Option<u32>,
+    /// Skip sentences flagged as containing profanity when the pack was built
+    #[serde(default)]
+    pub exclude_profanity: bool,
+    /// Skip sentences that only come from movies rated R or above (see
+    /// `MaturityRating::is_mature`). Movie stats leave those movies out too.
+    #[serde(default)]
+    pub exclude_mature_movies: bool,
+    /// Skip sentences that only come from these sources
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub excluded_sources: Vec<SentenceSourceKind>,
+}
+
+/// Which of the `SentenceFilters` left a sentence out
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub(crate) enum SentenceFilter {
+    MaxLiterals,
+    Profanity,
+    MatureMovie,
+    ExcludedSource,
+}
+
+impl SentenceFilters {
+    pub fn is_empty(&self) -> bool {
+        self == &Self::default()
+    }
+
+    /// The filter that leaves `sentence` out of challenges, if any
+    pub(crate) fn rejected_by(
+        &self,
+        sentence: Spur,
+        language_pack: &LanguagePack,
+    ) -> Option<SentenceFilter> {
+        if self.is_empty() {
+            return None;
+        }
+
+        let source = language_pack.sentence_sources.get(&sentence);
+        if source.is_some_and(|source| source.is_manual()) {
+            return None;
+        }
+
+        if let Some(max_literals) = self.max_literals
+            && language_pack
+                .sentences_to_literals
+                .get(&sentence)
+                .is_some_and(|literals| literals.len() > max_literals as usize)
+        {
+            return Some(SentenceFilter::MaxLiterals);
+        }
+
+        if self.exclude_profanity && language_pack.profane_sentences.contains(&sentence) {
+            return Some(SentenceFilter::Profanity);
+        }
+
+        if self.exclude_mature_movies
+            && let Some(source) = source
+            && !source.from_anki
+            && !source.from_tatoeba
+            && !source.from_song
+            && !source.movie_ids.is_empty()
+            && source
+                .movie_ids
+                .iter()
+                .all(|movie_id| is_mature_movie(movie_id, language_pack))
+        {
+            return Some(SentenceFilter::MatureMovie);
+        }
+
+        // Sentences we don't know the source of are kept, as are ones that also come from a
+        // source the user didn't exclude
+        if !self.excluded_sources.is_empty()
+            && let Some(source) = source
+        {
+            let sources = [
+                (SentenceSourceKind::Anki, source.from_anki),
+                (SentenceSourceKind::Tatoeba, source.from_tatoeba),
+                (SentenceSourceKind::Song, source.from_song),
+                (SentenceSourceKind::Movie, !source.movie_ids.is_empty()),
+            ];
+            let mut from_any = false;
+            let mut from_allowed = false;
+            for (kind, from) in sources {
+                if from {
+                    from_any = true;
+                    from_allowed |= !self.excluded_sources.contains(&kind);
+                }
+            }
+            if from_any && !from_allowed {
+                return Some(SentenceFilter::ExcludedSource);
+            }
+        }
+
+        None
+    }
+
+    /// Whether `movie_id` should be left out of movie stats
+    pub(crate) fn excludes_movie(&self, movie_id: &str, language_pack: &LanguagePack) -> bool {
+        self.exclude_mature_movies && is_mature_movie(movie_id, language_pack)
+    }
+}
+
+/// Movies without a rating aren't taken to be mature
+fn is_mature_movie(movie_id: &str, language_pack: &LanguagePack) -> bool {
+    language_pack
+        .movies
+        .get(movie_id)
+        .and_then(|movie| movie.maturity)
+        .is_some_and(MaturityRating::is_mature)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use language_utils::SentenceSource;
+
+    #[test]
+    fn test_mature_movie_filter_only_drops_sentences_no_other_source_has() {
+        const UNRATED_MOVIE_ID: &str = "tt0000002";
+
+        let mut language_pack = fixture_pack::language_pack();
+        let mut movie = language_pack.movies[fixture_pack::MOVIE_ID].clone();
+        movie.maturity = Some(MaturityRating::R);
+        language_pack
+            .movies
+            .insert(fixture_pack::MOVIE_ID.to_string(), movie.clone());
+        movie.id = UNRATED_MOVIE_ID.to_string();
+        movie.maturity = None;
+        language_pack
+            .movies
+            .insert(UNRATED_MOVIE_ID.to_string(), movie);
+
+        let mut sentences = language_pack.sentence_sources.keys().copied();
+        let mut source_of = |movie_ids: &[&str], from_tatoeba: bool| {
+            let sentence = sentences.next().unwrap();
+            let source = SentenceSource {
+                from_tatoeba,
+                movie_ids: movie_ids.iter().map(|id| id.to_string()).collect(),
+                ..SentenceSource::none()
+            };
+            (sentence, source)
+        };
+        let only_mature = source_of(&[fixture_pack::MOVIE_ID], false);
+        let also_tatoeba = source_of(&[fixture_pack::MOVIE_ID], true);
+        let also_unrated = source_of(&[fixture_pack::MOVIE_ID, UNRATED_MOVIE_ID], false);
+        let only_unrated = source_of(&[UNRATED_MOVIE_ID], false);
+        let sentences =
+            [only_mature, also_tatoeba, also_unrated, only_unrated].map(|(sentence, source)| {
+                language_pack.sentence_sources.insert(sentence, source);
+                sentence
+            });
+
+        let filters = SentenceFilters {
+            exclude_mature_movies: true,
+            ..SentenceFilters::default()
+        };
+        assert_eq!(
+            sentences.map(|sentence| filters.rejected_by(sentence, &language_pack)),
+            [Some(SentenceFilter::MatureMovie), None, None, None]
+        );
+        assert!(filters.excludes_movie(fixture_pack::MOVIE_ID, &language_pack));
+        assert!(!filters.excludes_movie(UNRATED_MOVIE_ID, &language_pack));
+
+        let off = SentenceFilters::default();
+        assert_eq!(off.rejected_by(sentences[0], &language_pack), None);
+        assert!(!off.excludes_movie(fixture_pack::MOVIE_ID, &language_pack));
+    }
+}
@@ -0,0 +1,70 @@
+//! How long the user likes their challenge sentences. Among the comprehensible sentences for a
+//! card, `Deck::get_comprehensible_sentence_containing` picks the one with the best
+//! `sentence_score`, which weighs how often a sentence has been reviewed against how far it is from
+//! the preferred length.
+
+use serde::{Deserialize, Serialize};
+
+/// How much being twice (or half) the preferred length counts against a sentence, in reviews
+const LENGTH_WEIGHT: f64 = 1.5;
+
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[cfg_attr(target_arch = "wasm32", derive(tsify::Tsify))]
+#[cfg_attr(target_arch = "wasm32", tsify(into_wasm_abi, from_wasm_abi))]
+pub enum SentenceLength {
+    Short,
+    Medium,
+    Long,
+    /// Short sentences while the user only knows a few words, and longer ones as they learn more
+    #[default]
+    Auto,
+}
+
+impl SentenceLength {
+    /// The preferred number of literals (words and punctuation), for a user who can read
+    /// `comprehensible_words` words
+    pub(crate) fn target_literals(self, comprehensible_words: usize) -> f64 {
+        match self {
+            SentenceLength::Short => 6.0,
+            SentenceLength::Medium => 10.0,
+            SentenceLength::Long => 16.0,
+            SentenceLength::Auto => match comprehensible_words {
+                ..300 => SentenceLength::Short.target_literals(0),
+                300..1500 => SentenceLength::Medium.target_literals(0),
+                1500.. => SentenceLength::Long.target_literals(0),
+            },
+        }
+    }
+}
+
+/// Lower is better. A sentence's distance from the target length is measured as a ratio, so one
+/// word too many matters more in a short sentence than in a long one.
+pub(crate) fn sentence_score(times_reviewed: u32, literals: usize, target_literals: f64) -> f64 {
+    let length_penalty = ((literals.max(1) as f64) / target_literals).log2().abs();
+    times_reviewed as f64 + LENGTH_WEIGHT * length_penalty
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_length_preference_can_outweigh_a_review() {
+        let short = SentenceLength::Short.target_literals(5000);
+        // A fresh sentence of the right length beats a fresh long one...
+        assert!(sentence_score(0, 6, short) < sentence_score(0, 18, short));
+        // ...and one that's been seen once still beats a fresh one three times too long
+        assert!(sentence_score(1, 6, short) < sentence_score(0, 18, short));
+        // but not one that's only a little longer
+        assert!(sentence_score(1, 6, short) > sentence_score(0, 7, short));
+
+        assert_eq!(
+            SentenceLength::Auto.target_literals(100),
+            SentenceLength::Short.target_literals(0)
+        );
+        assert_eq!(
+            SentenceLength::Auto.target_literals(3000),
+            SentenceLength::Long.target_literals(0)
+        );
+    }
+}
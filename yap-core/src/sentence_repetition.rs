@@ -0,0 +1,174 @@
+//! Spaced repetition for whole sentences. Word cards come back on their own schedule, but a sentence
+//! the user got wrong (or liked enough to favorite) is worth seeing again as a whole, since the
+//! trouble is often how its words fit together rather than any one word. Only those sentences are
+//! tracked, each with a much simpler schedule than FSRS: every time the user gets it right the
+//! interval grows, and getting it wrong starts it over. A failed sentence stops being tracked once
+//! its interval is long enough that the user has clearly got it, while favorites stay.
+
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+
+/// The interval after a sentence is failed or favorited, and after it's failed again
+const INITIAL_STABILITY_DAYS: f64 = 1.0;
+/// How much the interval grows each time the sentence is remembered
+const STABILITY_GROWTH: f64 = 2.5;
+/// Failed sentences that aren't favorites stop being tracked once their interval gets this long
+const RETIRE_AFTER_DAYS: f64 = 30.0;
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub(crate) struct SentenceSchedule {
+    /// The interval until the sentence is next due
+    stability_days: f64,
+    due: DateTime<Utc>,
+    reviews: u32,
+    /// How many times the user got the sentence wrong, including the failure it was tracked for
+    lapses: u32,
+}
+
+impl SentenceSchedule {
+    fn new(timestamp: DateTime<Utc>, lapses: u32) -> Self {
+        SentenceSchedule {
+            stability_days: INITIAL_STABILITY_DAYS,
+            due: timestamp + days(INITIAL_STABILITY_DAYS),
+            reviews: 0,
+            lapses,
+        }
+    }
+}
+
+fn days(days: f64) -> Duration {
+    Duration::seconds((days * 24.0 * 60.0 * 60.0) as i64)
+}
+
+/// The sentences being repeated, tracked as events are processed
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq)]
+pub(crate) struct SentenceSchedules {
+    schedules: BTreeMap<String, SentenceSchedule>,
+}
+
+impl SentenceSchedules {
+    /// The user got `sentence` wrong in a translation challenge
+    pub(crate) fn failed(&mut self, sentence: &str, timestamp: DateTime<Utc>) {
+        self.schedules
+            .entry(sentence.to_string())
+            .and_modify(|schedule| {
+                schedule.lapses += 1;
+                schedule.stability_days = INITIAL_STABILITY_DAYS;
+                schedule.due = timestamp + days(INITIAL_STABILITY_DAYS);
+            })
+            .or_insert_with(|| SentenceSchedule::new(timestamp, 1));
+    }
+
+    /// The user favorited `sentence`, or unfavorited it. Unfavoriting only stops tracking sentences
+    /// that were never failed.
+    pub(crate) fn favorited(&mut self, sentence: &str, favorite: bool, timestamp: DateTime<Utc>) {
+        if favorite {
+            self.schedules
+                .entry(sentence.to_string())
+                .or_insert_with(|| SentenceSchedule::new(timestamp, 0));
+        } else if self
+            .schedules
+            .get(sentence)
+            .is_some_and(|schedule| schedule.lapses == 0)
+        {
+            self.schedules.remove(sentence);
+        }
+    }
+
+    /// The user repeated `sentence`. Reviews of sentences that aren't tracked are ignored.
+    pub(crate) fn review(
+        &mut self,
+        sentence: &str,
+        remembered: bool,
+        favorite: bool,
+        timestamp: DateTime<Utc>,
+    ) {
+        let Some(schedule) = self.schedules.get_mut(sentence) else {
+            return;
+        };
+        schedule.reviews += 1;
+        if remembered {
+            schedule.stability_days *= STABILITY_GROWTH;
+        } else {
+            schedule.lapses += 1;
+            schedule.stability_days = INITIAL_STABILITY_DAYS;
+        }
+        schedule.due = timestamp + days(schedule.stability_days);
+
+        if !favorite && schedule.stability_days > RETIRE_AFTER_DAYS {
+            self.schedules.remove(sentence);
+        }
+    }
+
+    pub(crate) fn is_tracked(&self, sentence: &str) -> bool {
+        self.schedules.contains_key(sentence)
+    }
+
+    /// The sentences due at `now`, most overdue first
+    pub(crate) fn due(&self, now: DateTime<Utc>) -> Vec<&str> {
+        let mut due = self
+            .schedules
+            .iter()
+            .filter(|(_, schedule)| schedule.due <= now)
+            .collect::<Vec<_>>();
+        due.sort_by_key(|(_, schedule)| schedule.due);
+        due.into_iter()
+            .map(|(sentence, _)| sentence.as_str())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_failed_sentences_space_out_until_retired() {
+        let start = Utc::now();
+        let mut schedules = SentenceSchedules::default();
+        schedules.failed("Je suis là.", start);
+        assert!(schedules.due(start).is_empty());
+
+        let mut now = start + Duration::days(1);
+        assert_eq!(schedules.due(now), vec!["Je suis là."]);
+
+        // Forgetting it again starts the interval over
+        schedules.review("Je suis là.", false, false, now);
+        now += Duration::days(1);
+        assert_eq!(schedules.due(now), vec!["Je suis là."]);
+
+        let mut intervals = Vec::new();
+        while schedules.is_tracked("Je suis là.") {
+            schedules.review("Je suis là.", true, false, now);
+            let Some(schedule) = schedules.schedules.get("Je suis là.") else {
+                break;
+            };
+            intervals.push(schedule.stability_days);
+            now = schedule.due;
+        }
+        assert!(intervals.windows(2).all(|pair| pair[0] < pair[1]));
+        assert!(intervals.last().unwrap() * STABILITY_GROWTH > RETIRE_AFTER_DAYS);
+    }
+
+    #[test]
+    fn test_favorites_are_tracked_until_unfavorited_unless_failed() {
+        let now = Utc::now();
+        let mut schedules = SentenceSchedules::default();
+        schedules.favorited("Bonjour.", true, now);
+        schedules.favorited("Merci.", true, now);
+        schedules.failed("Merci.", now);
+
+        // Favorites never retire
+        for _ in 0..10 {
+            schedules.review("Bonjour.", true, true, now);
+        }
+        assert!(schedules.is_tracked("Bonjour."));
+
+        schedules.favorited("Bonjour.", false, now);
+        schedules.favorited("Merci.", false, now);
+        assert!(!schedules.is_tracked("Bonjour."));
+        assert!(schedules.is_tracked("Merci."));
+    }
+}
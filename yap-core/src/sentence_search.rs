@@ -0,0 +1,257 @@
+//! Searching the language pack's sentences for a word or phrase, concordance style, so the user
+//! can see a word in context. A query that's a word or multiword term in the pack finds every
+//! sentence with any of its heteronyms; anything else (part of a word, several words that aren't
+//! a term) is searched for as text.
+
+use std::collections::BTreeSet;
+
+use language_utils::{Lexeme, Literal};
+use lasso::Spur;
+use serde::{Deserialize, Serialize};
+#[cfg(target_arch = "wasm32")]
+use wasm_bindgen::prelude::*;
+
+use crate::Deck;
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(target_arch = "wasm32", derive(tsify::Tsify))]
+#[cfg_attr(target_arch = "wasm32", tsify(into_wasm_abi))]
+pub struct SentenceSearchResults {
+    /// How many sentences matched, across all pages
+    pub total: u32,
+    pub sentences: Vec<SearchedSentence>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(target_arch = "wasm32", derive(tsify::Tsify))]
+#[cfg_attr(target_arch = "wasm32", tsify(into_wasm_abi))]
+pub struct SearchedSentence {
+    pub sentence: String,
+    pub translation: Option<String>,
+    /// Empty if the language pack couldn't analyze the sentence
+    pub literals: Vec<Literal<String>>,
+    /// Whether the user knows each of `literals`. Literals that aren't words, like punctuation,
+    /// are known.
+    pub known: Vec<bool>,
+    /// Whether each of `literals` is (part of) what was searched for
+    pub matched: Vec<bool>,
+}
+
+/// What a query turned out to be
+enum SearchMatch {
+    Lexemes(BTreeSet<Lexeme<Spur>>),
+    /// Lowercase text
+    Text(String),
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+impl Deck {
+    /// The `page`th (counting from 0) `page_size` sentences containing `query`. Sentences with
+    /// the fewest words the user doesn't know come first, and sentences left out by the user's
+    /// `SentenceFilters` aren't searched.
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn search_sentences(
+        &self,
+        query: String,
+        page: u32,
+        page_size: u32,
+    ) -> SentenceSearchResults {
+        let language_pack = &self.context.language_pack;
+        let query = query.trim().to_lowercase();
+        if query.is_empty() {
+            return SentenceSearchResults {
+                total: 0,
+                sentences: Vec::new(),
+            };
+        }
+
+        let lexemes = language_pack
+            .rodeo
+            .get(&query)
+            .map(|spur| {
+                language_pack
+                    .words_to_heteronyms
+                    .get(&spur)
+                    .into_iter()
+                    .flatten()
+                    .map(|heteronym| Lexeme::Heteronym(*heteronym))
+                    .chain(std::iter::once(Lexeme::Multiword(spur)))
+                    .filter(|lexeme| {
+                        language_pack
+                            .sentences_containing_lexeme_index
+                            .contains_key(lexeme)
+                    })
+                    .collect::<BTreeSet<_>>()
+            })
+            .unwrap_or_default();
+        let (search_match, candidates) = if lexemes.is_empty() {
+            let candidates = language_pack
+                .translations
+                .keys()
+                .filter(|sentence| {
+                    language_pack
+                        .rodeo
+                        .resolve(sentence)
+                        .to_lowercase()
+                        .contains(&query)
+                })
+                .copied()
+                .collect::<BTreeSet<_>>();
+            (SearchMatch::Text(query), candidates)
+        } else {
+            let candidates = lexemes
+                .iter()
+                .flat_map(|lexeme| &language_pack.sentences_containing_lexeme_index[lexeme])
+                .copied()
+                .collect::<BTreeSet<_>>();
+            (SearchMatch::Lexemes(lexemes), candidates)
+        };
+
+        let comprehensible_lexemes = self.comprehensible_lexemes();
+        let mut sentences = candidates
+            .into_iter()
+            .filter(|sentence| {
+                self.sentence_filters
+                    .rejected_by(*sentence, language_pack)
+                    .is_none()
+            })
+            .map(|sentence| {
+                let unknown = language_pack
+                    .sentences_to_lexemes
+                    .get(&sentence)
+                    .into_iter()
+                    .flatten()
+                    .filter(|lexeme| !comprehensible_lexemes.contains(lexeme))
+                    .count();
+                (unknown, language_pack.rodeo.resolve(&sentence), sentence)
+            })
+            .collect::<Vec<_>>();
+        sentences.sort();
+
+        let start = page as usize * page_size as usize;
+        SentenceSearchResults {
+            total: sentences.len() as u32,
+            sentences: sentences
+                .iter()
+                .skip(start)
+                .take(page_size as usize)
+                .map(|(_, text, sentence)| {
+                    let literals = language_pack
+                        .sentences_to_literals
+                        .get(sentence)
+                        .cloned()
+                        .unwrap_or_default();
+                    SearchedSentence {
+                        sentence: text.to_string(),
+                        translation: language_pack
+                            .translations
+                            .get(sentence)
+                            .and_then(|translations| translations.first())
+                            .map(|translation| translation.to_string()),
+                        known: literals
+                            .iter()
+                            .map(|literal| {
+                                literal.heteronym.is_none_or(|heteronym| {
+                                    comprehensible_lexemes.contains(&Lexeme::Heteronym(heteronym))
+                                })
+                            })
+                            .collect(),
+                        matched: self.matched_literals(&literals, &search_match),
+                        literals: literals
+                            .iter()
+                            .map(|literal| literal.resolve(&language_pack.rodeo))
+                            .collect(),
+                    }
+                })
+                .collect(),
+        }
+    }
+}
+
+impl Deck {
+    fn matched_literals(
+        &self,
+        literals: &[Literal<Spur>],
+        search_match: &SearchMatch,
+    ) -> Vec<bool> {
+        let rodeo = &self.context.language_pack.rodeo;
+        match search_match {
+            SearchMatch::Lexemes(lexemes) => {
+                let multiword_words = lexemes
+                    .iter()
+                    .filter_map(|lexeme| lexeme.multiword())
+                    .flat_map(|multiword| rodeo.resolve(multiword).split_whitespace())
+                    .collect::<BTreeSet<_>>();
+                literals
+                    .iter()
+                    .map(|literal| {
+                        literal.heteronym.is_some_and(|heteronym| {
+                            lexemes.contains(&Lexeme::Heteronym(heteronym))
+                        }) || multiword_words
+                            .contains(rodeo.resolve(&literal.text).to_lowercase().as_str())
+                    })
+                    .collect()
+            }
+            // Literals overlapping the first occurrence of the text, found in the literals
+            // themselves so the offsets line up
+            SearchMatch::Text(text) => {
+                let mut offsets = Vec::with_capacity(literals.len());
+                let mut haystack = String::new();
+                for literal in literals {
+                    let start = haystack.len();
+                    haystack.push_str(&rodeo.resolve(&literal.text).to_lowercase());
+                    offsets.push(start..haystack.len());
+                    haystack.push_str(&rodeo.resolve(&literal.whitespace).to_lowercase());
+                }
+                let found = haystack.find(text.as_str());
+                offsets
+                    .into_iter()
+                    .map(|range| {
+                        found.is_some_and(|found| {
+                            range.start < found + text.len() && found < range.end
+                        })
+                    })
+                    .collect()
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_searches_words_and_text() {
+        let deck = Deck::default();
+        let pack = deck.context.language_pack.clone();
+        let heteronym = pack
+            .word_frequencies
+            .keys()
+            .find_map(|lexeme| match lexeme {
+                Lexeme::Heteronym(heteronym) => Some(*heteronym),
+                Lexeme::Multiword(_) => None,
+            })
+            .unwrap();
+        let word = pack.rodeo.resolve(&heteronym.word).to_string();
+
+        let results = deck.search_sentences(word.to_uppercase(), 0, 5);
+        assert!(results.total > 0);
+        assert!(results.sentences.len() <= 5);
+        for sentence in &results.sentences {
+            assert_eq!(sentence.literals.len(), sentence.matched.len());
+            assert!(sentence.matched.iter().any(|matched| *matched));
+        }
+
+        let second_page = deck.search_sentences(word, 1, 5);
+        assert_eq!(second_page.total, results.total);
+        assert!(
+            second_page
+                .sentences
+                .iter()
+                .all(|sentence| !results.sentences.contains(sentence))
+        );
+
+        assert_eq!(deck.search_sentences("  ".to_string(), 0, 5).total, 0);
+    }
+}
@@ -0,0 +1,147 @@
+//! "Not now": cards the user doesn't want to deal with at the moment. Skipping a due card sends it
+//! to the back of the session's queue, and skipping it again leaves it for tomorrow. Skips are
+//! only kept by the frontend for as long as it wants (usually a session), never as deck events, so
+//! they don't change when a card is scheduled or how it's graded later.
+
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, Duration, Utc};
+#[cfg(target_arch = "wasm32")]
+use wasm_bindgen::prelude::*;
+
+use crate::{CardIndicator, Deck, ReviewInfo, datetime_from_ms};
+
+/// Skipping a card this many times leaves it for tomorrow rather than the back of the queue
+const SKIPS_BEFORE_DEFERRING: u32 = 2;
+
+/// How long a card skipped `SKIPS_BEFORE_DEFERRING` times stays out of sessions
+const DEFER_HOURS: i64 = 24;
+
+#[derive(Clone, Copy, Debug, Default)]
+struct Skip {
+    count: u32,
+    deferred_until: Option<DateTime<Utc>>,
+}
+
+/// The cards skipped with "not now", applied to each session's queue with
+/// `ReviewInfo::apply_session_skips`
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+#[derive(Clone, Debug, Default)]
+pub struct SessionSkips {
+    skips: BTreeMap<CardIndicator<String>, Skip>,
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+impl SessionSkips {
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen(constructor))]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Skips `card` at `timestamp_ms`: to the back of the queue the first time, and out of
+    /// sessions for a day the second
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn skip(&mut self, card: CardIndicator<String>, timestamp_ms: f64) {
+        let skip = self.skips.entry(card).or_default();
+        skip.count += 1;
+        if skip.count >= SKIPS_BEFORE_DEFERRING {
+            skip.deferred_until =
+                Some(datetime_from_ms(timestamp_ms) + Duration::hours(DEFER_HOURS));
+        }
+    }
+
+    /// How many times `card` was skipped
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn skip_count(&self, card: CardIndicator<String>) -> u32 {
+        self.skips.get(&card).map_or(0, |skip| skip.count)
+    }
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+impl ReviewInfo {
+    /// Moves the cards in `skips` to the back of the queue, keeping their order, and drops the
+    /// ones left for tomorrow. Cards whose day is up come back like they were never skipped.
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn apply_session_skips(&mut self, deck: &Deck, skips: &SessionSkips, timestamp_ms: f64) {
+        let now = datetime_from_ms(timestamp_ms);
+        let rodeo = &deck.context.language_pack.rodeo;
+        let skips = skips
+            .skips
+            .iter()
+            .filter(|(_, skip)| skip.deferred_until.is_none_or(|until| now < until))
+            .filter_map(|(card, skip)| Some((card.get_interned(rodeo)?, *skip)))
+            .collect::<BTreeMap<_, _>>();
+
+        for cards in [&mut self.due_cards, &mut self.ahead_cards] {
+            cards.retain(|card| {
+                skips
+                    .get(card)
+                    .is_none_or(|skip| skip.deferred_until.is_none())
+            });
+            let (mut skipped, not_skipped): (Vec<_>, Vec<_>) =
+                cards.drain(..).partition(|card| skips.contains_key(card));
+            *cards = not_skipped;
+            cards.append(&mut skipped);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{DeckEvent, LanguageEvent, LanguageEventContent};
+    use weapon::AppState;
+    use weapon::data_model::Timestamped;
+
+    #[test]
+    fn test_skipped_cards_go_to_the_back_then_wait_a_day() {
+        let start = DateTime::<Utc>::from_timestamp(1_760_000_000, 0).unwrap();
+        let deck = Deck::default();
+        let event = deck
+            .add_next_unknown_cards(None, 3, Vec::new(), start.timestamp_millis() as f64)
+            .unwrap();
+        let DeckEvent::Language(LanguageEvent {
+            content: LanguageEventContent::AddCards { cards },
+            ..
+        }) = &event
+        else {
+            panic!("expected an AddCards event");
+        };
+        let cards = cards.clone();
+        let deck = deck.apply_event(&Timestamped {
+            timestamp: start,
+            within_device_events_index: 0,
+            event,
+        });
+        let at = |minutes: i64| (start + Duration::minutes(minutes)).timestamp_millis() as f64;
+        let queue = |skips: &SessionSkips, timestamp_ms: f64| {
+            let mut review_info = deck.get_review_info(Vec::new(), timestamp_ms);
+            review_info.apply_session_skips(&deck, skips, timestamp_ms);
+            let rodeo = &deck.context.language_pack.rodeo;
+            review_info
+                .due_cards
+                .iter()
+                .map(|card| card.resolve(rodeo))
+                .collect::<Vec<_>>()
+        };
+
+        let mut skips = SessionSkips::new();
+        let first = queue(&skips, at(1));
+        assert_eq!(first.len(), cards.len());
+
+        skips.skip(first[0].clone(), at(1));
+        let after_one_skip = queue(&skips, at(2));
+        assert_eq!(after_one_skip.last(), Some(&first[0]));
+        assert_eq!(after_one_skip[..2], first[1..]);
+
+        skips.skip(first[0].clone(), at(2));
+        assert_eq!(skips.skip_count(first[0].clone()), 2);
+        assert_eq!(queue(&skips, at(3)), first[1..]);
+
+        let tomorrow = at(DEFER_HOURS * 60 + 3);
+        assert_eq!(
+            queue(&skips, tomorrow),
+            queue(&SessionSkips::new(), tomorrow)
+        );
+    }
+}
@@ -0,0 +1,275 @@
+//! What the user struggled with in a study session, for a "what to focus on" card at the end of
+//! it. Every review in a session is tallied by card as events are processed, and
+//! `Deck::get_session_struggles` turns the tallies into the words missed the most, the grammatical
+//! forms behind the misses, and the cards that were still shaky when the session ended.
+
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, Utc};
+use language_utils::morph_tag::MorphTag;
+use language_utils::{Heteronym, Lexeme};
+use lasso::Spur;
+use serde::{Deserialize, Serialize};
+#[cfg(target_arch = "wasm32")]
+use wasm_bindgen::prelude::*;
+
+use crate::{CardIndicator, Deck, Rating, datetime_from_ms};
+
+/// Only the tallies of this many of the latest sessions are kept
+const MAX_SESSIONS: usize = 10;
+
+/// How many words, forms and cards the report lists of each
+const REPORT_LENGTH: usize = 5;
+
+/// How many of a word's sentences are looked through for its grammatical form
+const MORPH_SAMPLE_SENTENCES: usize = 20;
+
+#[derive(Clone, Debug, Default)]
+pub(crate) struct SessionReviewLog {
+    /// Oldest first, at most `MAX_SESSIONS`
+    pub(crate) sessions: Vec<SessionReviews>,
+}
+
+#[derive(Clone, Debug)]
+pub(crate) struct SessionReviews {
+    pub(crate) start: DateTime<Utc>,
+    pub(crate) cards: BTreeMap<CardIndicator<Spur>, ReviewCounts>,
+}
+
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub(crate) struct ReviewCounts {
+    pub(crate) reviews: u32,
+    /// How many of `reviews` were rated `Rating::Again`
+    pub(crate) misses: u32,
+}
+
+impl ReviewCounts {
+    fn add(&mut self, other: ReviewCounts) {
+        self.reviews += other.reviews;
+        self.misses += other.misses;
+    }
+}
+
+impl SessionReviewLog {
+    /// Tallies a review of `card` in the session that started at `session_start`
+    pub(crate) fn record(
+        &mut self,
+        session_start: DateTime<Utc>,
+        card: CardIndicator<Spur>,
+        rating: Rating,
+    ) {
+        if self
+            .sessions
+            .last()
+            .is_none_or(|session| session.start != session_start)
+        {
+            self.sessions.push(SessionReviews {
+                start: session_start,
+                cards: BTreeMap::new(),
+            });
+            if self.sessions.len() > MAX_SESSIONS {
+                self.sessions.remove(0);
+            }
+        }
+        if let Some(session) = self.sessions.last_mut() {
+            session.cards.entry(card).or_default().add(ReviewCounts {
+                reviews: 1,
+                misses: u32::from(rating == Rating::Again),
+            });
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(target_arch = "wasm32", derive(tsify::Tsify))]
+#[cfg_attr(target_arch = "wasm32", tsify(into_wasm_abi))]
+pub struct SessionStruggles {
+    /// Most missed first, counting both reading and listening reviews
+    pub missed_words: Vec<MissedWord>,
+    /// The grammatical forms of the missed words, most missed first
+    pub grammar: Vec<GrammarStruggle>,
+    /// Cards that were missed at least as often as they were remembered, most missed first
+    pub follow_up_cards: Vec<CardIndicator<String>>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(target_arch = "wasm32", derive(tsify::Tsify))]
+#[cfg_attr(target_arch = "wasm32", tsify(into_wasm_abi))]
+pub struct MissedWord {
+    pub lexeme: Lexeme<String>,
+    pub misses: u32,
+    pub reviews: u32,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(target_arch = "wasm32", derive(tsify::Tsify))]
+#[cfg_attr(target_arch = "wasm32", tsify(into_wasm_abi))]
+pub struct GrammarStruggle {
+    /// Like "3rd person plural, imparfait", see `MorphTag::describe`
+    pub description: String,
+    /// Misses of words in this form
+    pub misses: u32,
+    /// Reviews of words in this form
+    pub reviews: u32,
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+impl Deck {
+    /// What the user struggled with in the session that started at `session_start_ms` (the
+    /// `start_ms` of a `SessionSummary`). `None` if it isn't one of the last `MAX_SESSIONS`
+    /// sessions, or nothing was reviewed in it.
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn get_session_struggles(&self, session_start_ms: f64) -> Option<SessionStruggles> {
+        let session_start = datetime_from_ms(session_start_ms);
+        let session = self
+            .stats
+            .session_reviews
+            .sessions
+            .iter()
+            .find(|session| session.start == session_start)?;
+        let rodeo = &self.context.language_pack.rodeo;
+
+        let mut words: BTreeMap<Lexeme<Spur>, ReviewCounts> = BTreeMap::new();
+        for (card, counts) in &session.cards {
+            if let CardIndicator::TargetLanguage { lexeme }
+            | CardIndicator::ListeningLexeme { lexeme } = card
+            {
+                words.entry(*lexeme).or_default().add(*counts);
+            }
+        }
+
+        let mut grammar: BTreeMap<MorphTag, ReviewCounts> = BTreeMap::new();
+        for (lexeme, counts) in &words {
+            if let Lexeme::Heteronym(heteronym) = lexeme
+                && let Some(morph) = self.usual_morph(*heteronym)
+            {
+                grammar.entry(morph).or_default().add(*counts);
+            }
+        }
+        let mut grammar = grammar
+            .into_iter()
+            .filter(|(_, counts)| counts.misses > 0)
+            .filter_map(|(morph, counts)| {
+                Some(GrammarStruggle {
+                    description: morph.describe(self.context.target_language)?,
+                    misses: counts.misses,
+                    reviews: counts.reviews,
+                })
+            })
+            .collect::<Vec<_>>();
+        grammar.sort_by_key(|struggle| std::cmp::Reverse(struggle.misses));
+        grammar.truncate(REPORT_LENGTH);
+
+        let mut missed_words = words
+            .into_iter()
+            .filter(|(_, counts)| counts.misses > 0)
+            .collect::<Vec<_>>();
+        missed_words.sort_by_key(|(_, counts)| std::cmp::Reverse(counts.misses));
+
+        let mut follow_up_cards = session
+            .cards
+            .iter()
+            .filter(|(_, counts)| counts.misses > 0 && counts.misses * 2 >= counts.reviews)
+            .collect::<Vec<_>>();
+        follow_up_cards.sort_by_key(|(_, counts)| std::cmp::Reverse(counts.misses));
+
+        Some(SessionStruggles {
+            missed_words: missed_words
+                .into_iter()
+                .take(REPORT_LENGTH)
+                .map(|(lexeme, counts)| MissedWord {
+                    lexeme: lexeme.resolve(rodeo),
+                    misses: counts.misses,
+                    reviews: counts.reviews,
+                })
+                .collect(),
+            grammar,
+            follow_up_cards: follow_up_cards
+                .into_iter()
+                .take(REPORT_LENGTH)
+                .map(|(card, _)| card.resolve(rodeo))
+                .collect(),
+        })
+    }
+}
+
+impl Deck {
+    /// The grammatical form `heteronym` has in the first of its sentences that the tokenizer
+    /// gave one for. A heteronym is a single spelling of a word, so it's nearly always the same.
+    fn usual_morph(&self, heteronym: Heteronym<Spur>) -> Option<MorphTag> {
+        let language_pack = &self.context.language_pack;
+        language_pack
+            .sentences_containing_lexeme_index
+            .get(&Lexeme::Heteronym(heteronym))?
+            .iter()
+            .take(MORPH_SAMPLE_SENTENCES)
+            .filter_map(|sentence| language_pack.sentences_to_literals.get(sentence))
+            .flatten()
+            .filter(|literal| literal.heteronym == Some(heteronym))
+            .find_map(|literal| literal.morph)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{DeckEvent, LanguageEvent, LanguageEventContent};
+    use weapon::AppState;
+    use weapon::data_model::Timestamped;
+
+    #[test]
+    fn test_struggles_tally_the_sessions_misses() {
+        let start = DateTime::<Utc>::from_timestamp(1_760_000_000, 0).unwrap();
+        let mut deck = Deck::default();
+        let pack = deck.context.language_pack.clone();
+        let mut lexemes = pack.word_frequencies.keys().copied();
+        let missed = CardIndicator::TargetLanguage {
+            lexeme: lexemes.next().unwrap(),
+        };
+        let remembered = CardIndicator::TargetLanguage {
+            lexeme: lexemes.next().unwrap(),
+        };
+
+        let reviews = [
+            (missed, Rating::Again),
+            (remembered, Rating::Again),
+            (remembered, Rating::Remembered),
+            (remembered, Rating::Remembered),
+            (missed, Rating::Again),
+        ];
+        for (minute, (card, rating)) in reviews.into_iter().enumerate() {
+            deck = deck.apply_event(&Timestamped {
+                timestamp: start + chrono::Duration::minutes(minute as i64),
+                within_device_events_index: 0,
+                event: DeckEvent::Language(LanguageEvent {
+                    target_language: deck.context.target_language,
+                    native_language: deck.context.native_language,
+                    content: LanguageEventContent::ReviewCard {
+                        reviewed: card.resolve(&pack.rodeo),
+                        rating,
+                    },
+                }),
+            });
+        }
+
+        let struggles = deck
+            .get_session_struggles(start.timestamp_millis() as f64)
+            .unwrap();
+        assert_eq!(
+            struggles.missed_words.first(),
+            Some(&MissedWord {
+                lexeme: missed.target_language().unwrap().resolve(&pack.rodeo),
+                misses: 2,
+                reviews: 2,
+            })
+        );
+        assert_eq!(struggles.missed_words.len(), 2);
+        assert_eq!(struggles.follow_up_cards, vec![missed.resolve(&pack.rodeo)]);
+
+        let later = start + chrono::Duration::days(1);
+        assert_eq!(
+            deck.get_session_struggles(later.timestamp_millis() as f64),
+            None
+        );
+    }
+}
@@ -0,0 +1,403 @@
+use crate::Rating;
+use crate::scheduler;
+use crate::{
+    CardData, Challenge, Deck, DisambiguateHeteronym, TranscribeComprehensibleSentence,
+    TranslateComprehensibleSentence,
+};
+use chrono::{DateTime, Duration, Utc};
+use language_utils::transcription_challenge;
+use weapon::AppState;
+use weapon::data_model::Timestamped;
+
+/// Iterator that simulates daily usage of a deck, yielding all challenges for each day
+pub struct DailySimulationIterator {
+    deck: Deck,
+    current_time: DateTime<Utc>,
+    event_index: usize,
+}
+
+impl DailySimulationIterator {
+    pub fn new(deck: Deck, current_time: DateTime<Utc>) -> Self {
+        Self {
+            deck,
+            current_time,
+            event_index: 0,
+        }
+    }
+
+    /// The deck with all the simulated events so far applied
+    pub fn deck(&self) -> &Deck {
+        &self.deck
+    }
+}
+
+impl DailySimulationIterator {
+    /// Simulates a `StudyDay::default()`
+    pub fn next(self) -> (Self, Vec<Challenge<String>>) {
+        self.next_with(StudyDay::default())
+    }
+
+    /// Simulates a day where the learner answers up to `day.max_challenges` challenges and then
+    /// adds `day.new_cards` cards
+    pub fn next_with(mut self, day: StudyDay) -> (Self, Vec<Challenge<String>>) {
+        let mut day_challenges = Vec::new();
+
+        loop {
+            if day_challenges.len() >= day.max_challenges {
+                break;
+            }
+
+            let review_info = self
+                .deck
+                .get_review_info(vec![], self.current_time.timestamp_millis() as f64);
+            if let Some(challenge) = review_info.get_next_challenge(&self.deck) {
+                day_challenges.push(challenge.clone());
+
+                // Answer the challenge, marking new flashcards as forgotten once
+                let event = match challenge {
+                    Challenge::FlashCardReview {
+                        indicator, is_new, ..
+                    } => {
+                        let rating = if is_new {
+                            Rating::Again
+                        } else {
+                            Rating::Remembered
+                        };
+                        self.deck.review_card(indicator, rating)
+                    }
+                    Challenge::TranslateComprehensibleSentence(
+                        TranslateComprehensibleSentence {
+                            target_language,
+                            favorite_practice,
+                            ..
+                        },
+                    ) => self.deck.translate_sentence_perfect(
+                        vec![],
+                        target_language,
+                        Some(favorite_practice),
+                    ),
+                    Challenge::TranscribeComprehensibleSentence(
+                        TranscribeComprehensibleSentence { parts, level, .. },
+                    ) => {
+                        let graded = parts
+                            .into_iter()
+                            .map(|part| match part {
+                                transcription_challenge::Part::AskedToTranscribe { parts } => {
+                                    let submission = parts
+                                        .iter()
+                                        .map(|p| p.text.clone())
+                                        .collect::<Vec<_>>()
+                                        .join(" ");
+                                    transcription_challenge::PartGraded::AskedToTranscribe {
+                                        submission,
+                                        parts: parts
+                                            .into_iter()
+                                            .map(|p| transcription_challenge::PartGradedPart {
+                                                grade:
+                                                    transcription_challenge::WordGrade::Perfect {
+                                                        wrote: Some(p.text.clone()),
+                                                    },
+                                                heard: p,
+                                            })
+                                            .collect(),
+                                    }
+                                }
+                                transcription_challenge::Part::Provided { part } => {
+                                    transcription_challenge::PartGraded::Provided { part }
+                                }
+                            })
+                            .collect();
+                        self.deck.transcribe_sentence(
+                            graded,
+                            Some(level),
+                            Some(transcription_challenge::InputMode::Typed),
+                        )
+                    }
+                    Challenge::DisambiguateHeteronym(DisambiguateHeteronym { answer, .. }) => {
+                        self.deck.disambiguate_heteronym(answer.clone(), answer)
+                    }
+                };
+
+                if let Some(event) = event {
+                    let ts = Timestamped {
+                        timestamp: self.current_time,
+                        within_device_events_index: self.event_index,
+                        event,
+                    };
+                    self.deck = self.deck.apply_event(&ts);
+                    self.event_index += 1;
+                }
+            } else {
+                break;
+            }
+        }
+
+        // Add the new cards at the end of the day
+        if let Some(event) = self.deck.add_next_unknown_cards(
+            None,
+            day.new_cards,
+            vec![],
+            self.current_time.timestamp_millis() as f64,
+        ) {
+            let ts = Timestamped {
+                timestamp: self.current_time,
+                within_device_events_index: self.event_index,
+                event,
+            };
+            self.deck = self.deck.apply_event(&ts);
+            self.event_index += 1;
+        }
+
+        // Advance to next day
+        self.current_time += Duration::days(1);
+
+        (self, day_challenges)
+    }
+}
+
+/// How much a simulated learner does in a day
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct StudyDay {
+    pub max_challenges: usize,
+    /// Added after the day's challenges
+    pub new_cards: usize,
+}
+
+impl StudyDay {
+    /// A day off
+    pub const REST: Self = Self {
+        max_challenges: 0,
+        new_cards: 0,
+    };
+}
+
+impl Default for StudyDay {
+    /// At most 20 challenges, to keep the simulation fast, and 10 new cards
+    fn default() -> Self {
+        Self {
+            max_challenges: 20,
+            new_cards: 10,
+        }
+    }
+}
+
+/// Simulated learners with different study habits, for comparing how scheduler and challenge
+/// selection changes hold up for each of them. See `Deck::simulate_persona`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Persona {
+    /// Studies for about 10 minutes every day
+    Casual,
+    /// Only studies on weekends, but for a long time
+    Binge,
+    /// Studies on about half the days, with no pattern to which ones
+    Inconsistent,
+}
+
+impl Persona {
+    pub const ALL: [Persona; 3] = [Persona::Casual, Persona::Binge, Persona::Inconsistent];
+
+    /// What the persona does on day `day` of the simulation, counting from 0
+    pub fn study_day(self, day: u32) -> StudyDay {
+        match self {
+            // About 40 seconds a challenge
+            Persona::Casual => StudyDay {
+                max_challenges: 15,
+                new_cards: 5,
+            },
+            Persona::Binge if day % 7 >= 5 => StudyDay {
+                max_challenges: 90,
+                new_cards: 20,
+            },
+            Persona::Binge => StudyDay::REST,
+            Persona::Inconsistent if coin_flip(day) => StudyDay {
+                max_challenges: 30,
+                new_cards: 10,
+            },
+            Persona::Inconsistent => StudyDay::REST,
+        }
+    }
+}
+
+/// A fixed hash of `day` rather than a random number, so simulations are comparable between runs
+fn coin_flip(day: u32) -> bool {
+    u64::from(day).wrapping_mul(0x9E37_79B9_7F4A_7C15) >> 63 == 0
+}
+
+/// How a persona did over a simulation, see `Deck::simulate_persona`
+#[derive(Clone, Debug, PartialEq)]
+pub struct PersonaReport {
+    pub persona: Persona,
+    pub days: u32,
+    /// Words the learner is at least 90% likely to remember on the day after the simulation
+    pub words_known: usize,
+    /// Challenges answered each day
+    pub challenges_per_day: Vec<usize>,
+}
+
+impl PersonaReport {
+    pub fn mean_workload(&self) -> f64 {
+        if self.challenges_per_day.is_empty() {
+            return 0.0;
+        }
+        self.challenges_per_day.iter().sum::<usize>() as f64 / self.challenges_per_day.len() as f64
+    }
+
+    /// Variance of the challenges answered per day. Higher means the workload is lumpier.
+    pub fn workload_variance(&self) -> f64 {
+        if self.challenges_per_day.is_empty() {
+            return 0.0;
+        }
+        let mean = self.mean_workload();
+        self.challenges_per_day
+            .iter()
+            .map(|&challenges| (challenges as f64 - mean).powi(2))
+            .sum::<f64>()
+            / self.challenges_per_day.len() as f64
+    }
+}
+
+/// Recall probability a word needs to count towards `PersonaReport::words_known`
+const KNOWN_RETRIEVABILITY: f64 = 0.9;
+
+impl Deck {
+    /// Simulates `persona` using the deck for `days` days from `start_time`, answering the way
+    /// `DailySimulationIterator::next` does
+    pub fn simulate_persona(
+        &self,
+        persona: Persona,
+        start_time: DateTime<Utc>,
+        days: u32,
+    ) -> PersonaReport {
+        let mut simulator = self.simulate_usage(start_time);
+        let mut challenges_per_day = Vec::new();
+        for day in 0..days {
+            let challenges;
+            (simulator, challenges) = simulator.next_with(persona.study_day(day));
+            challenges_per_day.push(challenges.len());
+        }
+        let end_time = start_time + Duration::days(i64::from(days));
+        PersonaReport {
+            persona,
+            days,
+            words_known: simulator.deck().words_known_at(end_time),
+            challenges_per_day,
+        }
+    }
+
+    fn words_known_at(&self, now: DateTime<Utc>) -> usize {
+        self.schedulable_cards()
+            .filter(|(card, _)| card.target_language().is_some())
+            .filter_map(|(_, status)| match status.reviewed()? {
+                CardData::Added { fsrs_card } | CardData::Ghost { fsrs_card } => Some(fsrs_card),
+            })
+            .filter(|fsrs_card| {
+                fsrs_card.state != rs_fsrs::State::New
+                    && scheduler::retrievability(fsrs_card, now) >= KNOWN_RETRIEVABILITY
+            })
+            .count()
+    }
+}
+
+impl Deck {
+    /// Create an iterator that simulates daily usage starting from a specific time.
+    /// The iterator yields all challenges for each day as a Vec, answering them perfectly,
+    /// and adds 10 new cards at the end of each day.
+    /// Use .take(n) to limit to n days.
+    ///
+    /// The start_time parameter ensures deterministic simulation -
+    /// callers must be explicit about their time choice.
+    pub fn simulate_usage(&self, start_time: DateTime<Utc>) -> DailySimulationIterator {
+        DailySimulationIterator::new(self.clone(), start_time)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_simulator_is_deterministic() {
+        // Create a fixed start time
+        let fixed_time = Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
+
+        // Run simulation 3 times and collect results
+        let mut results = Vec::new();
+
+        for _ in 0..3 {
+            let deck = Deck::default();
+            let mut simulator = deck.simulate_usage(fixed_time);
+
+            // Collect challenges for first 5 days
+            let mut challenges_per_day = Vec::new();
+            for _ in 0..5 {
+                let (next_sim, challenges) = simulator.next();
+                simulator = next_sim;
+
+                // Convert challenges to a comparable format (just count by type for simplicity)
+                let mut flash_count = 0;
+                let mut translate_count = 0;
+                let mut transcribe_count = 0;
+
+                for challenge in challenges {
+                    match challenge {
+                        Challenge::FlashCardReview { .. } | Challenge::DisambiguateHeteronym(_) => {
+                            flash_count += 1
+                        }
+                        Challenge::TranslateComprehensibleSentence(_) => translate_count += 1,
+                        Challenge::TranscribeComprehensibleSentence(_) => transcribe_count += 1,
+                    }
+                }
+
+                challenges_per_day.push((flash_count, translate_count, transcribe_count));
+            }
+
+            results.push(challenges_per_day);
+        }
+
+        // Verify all three runs produced identical results
+        assert_eq!(
+            results[0], results[1],
+            "First and second simulation runs differ"
+        );
+        assert_eq!(
+            results[1], results[2],
+            "Second and third simulation runs differ"
+        );
+    }
+
+    /// Run with `--nocapture` to compare the personas before and after a scheduler or challenge
+    /// selection change
+    #[test]
+    fn test_personas() {
+        const DAYS: u32 = 90;
+        let start_time = Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
+        let deck = Deck::default();
+
+        let reports = Persona::ALL.map(|persona| deck.simulate_persona(persona, start_time, DAYS));
+        for report in &reports {
+            println!(
+                "{:?}: {} words known at day {DAYS}, {:.1} challenges a day (variance {:.1})",
+                report.persona,
+                report.words_known,
+                report.mean_workload(),
+                report.workload_variance()
+            );
+        }
+
+        let [casual, binge, inconsistent] = &reports;
+        for report in &reports {
+            assert!(
+                report.words_known > 0,
+                "{:?} learned nothing",
+                report.persona
+            );
+        }
+        assert!(binge.workload_variance() > casual.workload_variance());
+        assert!(inconsistent.workload_variance() > casual.workload_variance());
+        assert_eq!(
+            deck.simulate_persona(Persona::Inconsistent, start_time, DAYS),
+            *inconsistent
+        );
+    }
+}
@@ -0,0 +1,243 @@
+//! Singing along to songs from the language pack, karaoke style. Each line comes with when it's
+//! sung, when each of its words is sung and which words the user knows. Singing along isn't
+//! graded, so finishing a song only counts as a light listening review: the listening cards of its
+//! words that are due are remembered, and nothing else is scheduled.
+
+use std::collections::BTreeSet;
+
+use language_utils::{Lexeme, Literal, TtsProvider, TtsRequest};
+use lasso::Spur;
+use serde::{Deserialize, Serialize};
+#[cfg(target_arch = "wasm32")]
+use wasm_bindgen::prelude::*;
+
+use crate::{
+    AudioRequest, CardData, CardIndicator, DataMismatchKind, Deck, DeckEvent, DeckState,
+    LanguageEvent, LanguageEventContent, Rating,
+};
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(target_arch = "wasm32", derive(tsify::Tsify))]
+#[cfg_attr(target_arch = "wasm32", tsify(into_wasm_abi))]
+pub struct SongSummary {
+    pub id: String,
+    pub title: String,
+    pub artist: Option<String>,
+    pub lines: u32,
+    /// How many of the lines have only words the user knows
+    pub comprehensible_lines: u32,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(target_arch = "wasm32", derive(tsify::Tsify))]
+#[cfg_attr(target_arch = "wasm32", tsify(into_wasm_abi))]
+pub struct SingAlongSong {
+    pub id: String,
+    pub title: String,
+    pub artist: Option<String>,
+    pub lines: Vec<SingAlongLine>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(target_arch = "wasm32", derive(tsify::Tsify))]
+#[cfg_attr(target_arch = "wasm32", tsify(into_wasm_abi))]
+pub struct SingAlongLine {
+    /// Pass this back to `Deck::sing_along` once the line has been sung
+    pub sentence: String,
+    pub start_ms: u32,
+    pub end_ms: u32,
+    /// Empty if the language pack couldn't analyze the line
+    pub literals: Vec<Literal<String>>,
+    /// When each of `literals` is sung
+    pub literal_start_ms: Vec<u32>,
+    /// Whether the user knows each of `literals`. Literals that aren't words, like punctuation,
+    /// are known.
+    pub known: Vec<bool>,
+    /// Whether the user knows every word in the line
+    pub comprehensible: bool,
+    pub audio: AudioRequest,
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+impl Deck {
+    /// The songs in the language pack that have timed lyrics, sorted by title
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn get_songs(&self) -> Vec<SongSummary> {
+        let comprehensible_lexemes = self.comprehensible_lexemes();
+        let mut songs = self
+            .context
+            .language_pack
+            .songs
+            .values()
+            .map(|song| SongSummary {
+                id: song.id.clone(),
+                title: song.title.clone(),
+                artist: song.artist.clone(),
+                lines: song.lines.len() as u32,
+                comprehensible_lines: song
+                    .lines
+                    .iter()
+                    .filter(|line| {
+                        self.is_line_comprehensible(line.sentence, &comprehensible_lexemes)
+                    })
+                    .count() as u32,
+            })
+            .collect::<Vec<_>>();
+        songs.sort_by(|a, b| a.title.cmp(&b.title).then_with(|| a.id.cmp(&b.id)));
+        songs
+    }
+
+    /// A song's lines for singing along, or `None` if the language pack doesn't have the song
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn get_sing_along_song(&self, song_id: String) -> Option<SingAlongSong> {
+        let language_pack = &self.context.language_pack;
+        let song = language_pack.songs.get(&song_id)?;
+        let comprehensible_lexemes = self.comprehensible_lexemes();
+        let provider = self.preferred_tts_provider(TtsProvider::Google);
+
+        let lines = song
+            .lines
+            .iter()
+            .map(|line| {
+                let sentence = language_pack.rodeo.resolve(&line.sentence).to_string();
+                let literals = language_pack
+                    .sentences_to_literals
+                    .get(&line.sentence)
+                    .cloned()
+                    .unwrap_or_default();
+                let known = literals
+                    .iter()
+                    .map(|literal| {
+                        literal.heteronym.is_none_or(|heteronym| {
+                            comprehensible_lexemes.contains(&Lexeme::Heteronym(heteronym))
+                        })
+                    })
+                    .collect();
+                SingAlongLine {
+                    start_ms: line.start_ms,
+                    end_ms: line.end_ms,
+                    literals: literals
+                        .iter()
+                        .map(|literal| literal.resolve(&language_pack.rodeo))
+                        .collect(),
+                    literal_start_ms: line.literal_start_ms.clone(),
+                    known,
+                    comprehensible: self
+                        .is_line_comprehensible(line.sentence, &comprehensible_lexemes),
+                    audio: AudioRequest {
+                        request: TtsRequest {
+                            text: sentence.clone(),
+                            language: self.context.target_language,
+                        },
+                        provider,
+                    },
+                    sentence,
+                }
+            })
+            .collect();
+
+        Some(SingAlongSong {
+            id: song.id.clone(),
+            title: song.title.clone(),
+            artist: song.artist.clone(),
+            lines,
+        })
+    }
+
+    /// Records that the user sang `lines` (the `sentence` of each `SingAlongLine`) of a song
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn sing_along(&self, song_id: String, lines: Vec<String>) -> Option<DeckEvent> {
+        (!lines.is_empty()).then_some(DeckEvent::Language(LanguageEvent {
+            target_language: self.context.target_language,
+            native_language: self.context.native_language,
+            content: LanguageEventContent::SingAlong {
+                song: song_id,
+                lines,
+            },
+        }))
+    }
+}
+
+impl Deck {
+    fn comprehensible_lexemes(&self) -> BTreeSet<Lexeme<Spur>> {
+        self.cards
+            .iter()
+            .filter_map(|(indicator, status)| match indicator {
+                CardIndicator::TargetLanguage { lexeme }
+                    if self
+                        .context
+                        .is_comprehensible(indicator, status, &self.regressions) =>
+                {
+                    Some(*lexeme)
+                }
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Lines the pack couldn't analyze aren't comprehensible, since there's no telling what's in
+    /// them
+    fn is_line_comprehensible(
+        &self,
+        sentence: Spur,
+        comprehensible_lexemes: &BTreeSet<Lexeme<Spur>>,
+    ) -> bool {
+        self.context
+            .language_pack
+            .sentences_to_lexemes
+            .get(&sentence)
+            .is_some_and(|lexemes| {
+                lexemes
+                    .iter()
+                    .all(|lexeme| comprehensible_lexemes.contains(lexeme))
+            })
+    }
+}
+
+impl DeckState {
+    /// Remembers the due listening cards of the words in the `lines` the user sang
+    pub(crate) fn log_sing_along(
+        &mut self,
+        lines: &[String],
+        timestamp: chrono::DateTime<chrono::Utc>,
+    ) {
+        let mut heard = BTreeSet::new();
+        for line in lines {
+            let cleaned = language_utils::text_cleanup::cleanup_sentence(
+                line.clone(),
+                self.context.target_language,
+            );
+            let Some(sentence) = self.data_mismatches.check(
+                self.context.language_pack.rodeo.get(&cleaned),
+                DataMismatchKind::Sentence,
+                || line.clone(),
+            ) else {
+                continue;
+            };
+            if let Some(lexemes) = self
+                .context
+                .language_pack
+                .sentences_to_lexemes
+                .get(&sentence)
+            {
+                heard.extend(
+                    lexemes
+                        .iter()
+                        .filter(|lexeme| matches!(lexeme, Lexeme::Heteronym(_)))
+                        .copied(),
+                );
+            }
+        }
+
+        for lexeme in heard {
+            let card = CardIndicator::ListeningLexeme { lexeme };
+            let due = matches!(
+                self.cards.get(&card),
+                Some(CardData::Added { fsrs_card }) if fsrs_card.due <= timestamp
+            );
+            if due {
+                self.log_review(card, Rating::Remembered, timestamp);
+            }
+        }
+    }
+}
@@ -0,0 +1,278 @@
+//! How smart add splits new cards between card types. Each type starts from a base share, which is
+//! then weighted by how the user is doing at that skill: a type whose cards the user remembers
+//! worse than the rest gets more new cards, so the weak skill gets more practice, while a type
+//! with a big backlog of due cards gets fewer, so the user catches up before it grows.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::{CARD_TYPES, CardData, CardStatus, CardType, Deck, scheduler};
+
+/// Types with fewer reviewed cards than this keep their base share
+const MIN_REVIEWED_CARDS: u32 = 10;
+/// A type lags once its cards' average retrievability is this far below the deck's
+const LAG_MARGIN: f64 = 0.05;
+/// How much a lagging type's share grows per point of retrievability it lags by
+const LAG_WEIGHT: f64 = 5.0;
+/// A lagging type gets at most this many times its base share
+const MAX_LAG_FACTOR: f64 = 2.0;
+/// A type is backlogged once it has this many due cards, and they're most of its cards
+const MIN_BACKLOG: u32 = 20;
+const BACKLOG_FACTOR: f64 = 0.5;
+
+/// The share of new cards a type gets before weighting, in percent
+fn base_percent(card_type: CardType) -> u32 {
+    match card_type {
+        CardType::TargetLanguage => 60,
+        CardType::Listening => 30,
+        CardType::LetterPronunciation => 10,
+    }
+}
+
+/// Why a type's share differs from its base share
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq, Ord, PartialOrd)]
+#[cfg_attr(target_arch = "wasm32", derive(tsify::Tsify))]
+#[cfg_attr(target_arch = "wasm32", tsify(into_wasm_abi, from_wasm_abi))]
+pub enum SmartAddReason {
+    /// Too few of the type's cards have been reviewed to tell how the user is doing
+    TooFewReviews,
+    /// The user is doing about as well at this type as at the others
+    Balanced,
+    /// The user remembers this type's cards worse than the others', so it gets more new cards
+    Lagging,
+    /// Most of this type's cards are due, so it gets fewer new cards until the user catches up.
+    /// This takes priority over lagging, since overdue cards lag anyway.
+    Backlogged,
+}
+
+/// One card type's share of smart add, see `AddCardOptions::smart_add_mix`
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq, Ord, PartialOrd)]
+#[cfg_attr(target_arch = "wasm32", derive(tsify::Tsify))]
+#[cfg_attr(target_arch = "wasm32", tsify(into_wasm_abi, from_wasm_abi))]
+pub struct SmartAddWeight {
+    pub card_type: CardType,
+    pub base_percent: u32,
+    /// The share of new cards the type gets after weighting, rounded
+    pub percent: u32,
+    /// The average retrievability of the type's reviewed cards in percent, `None` if none have
+    /// been reviewed
+    pub retrievability_percent: Option<u32>,
+    pub due: u32,
+    pub reason: SmartAddReason,
+}
+
+#[derive(Default)]
+struct TypeSummary {
+    reviewed: u32,
+    retrievability_sum: f64,
+    due: u32,
+}
+
+impl TypeSummary {
+    fn retrievability(&self) -> Option<f64> {
+        (self.reviewed > 0).then(|| self.retrievability_sum / f64::from(self.reviewed))
+    }
+}
+
+impl Deck {
+    /// Each card type's share of smart add at `now`, in `CARD_TYPES` order
+    pub(crate) fn smart_add_mix(&self, now: DateTime<Utc>) -> Vec<SmartAddWeight> {
+        let mut summaries = CARD_TYPES.map(|_| TypeSummary::default());
+        for (card, status) in &self.cards {
+            let CardStatus::Tracked(CardData::Added { fsrs_card }) = status else {
+                continue;
+            };
+            if fsrs_card.state == rs_fsrs::State::New {
+                continue;
+            }
+            let Some(index) = CARD_TYPES.iter().position(|t| *t == card.card_type()) else {
+                continue;
+            };
+            let summary = &mut summaries[index];
+            summary.reviewed += 1;
+            summary.retrievability_sum += scheduler::retrievability(fsrs_card, now);
+            if fsrs_card.due <= now {
+                summary.due += 1;
+            }
+        }
+
+        let total_reviewed: u32 = summaries.iter().map(|summary| summary.reviewed).sum();
+        let deck_retrievability = (total_reviewed > 0).then(|| {
+            summaries
+                .iter()
+                .map(|summary| summary.retrievability_sum)
+                .sum::<f64>()
+                / f64::from(total_reviewed)
+        });
+
+        let weighted = CARD_TYPES
+            .iter()
+            .zip(&summaries)
+            .map(|(card_type, summary)| {
+                let (factor, reason) = match (summary.retrievability(), deck_retrievability) {
+                    _ if summary.reviewed < MIN_REVIEWED_CARDS => {
+                        (1.0, SmartAddReason::TooFewReviews)
+                    }
+                    _ if summary.due >= MIN_BACKLOG && summary.due * 2 > summary.reviewed => {
+                        (BACKLOG_FACTOR, SmartAddReason::Backlogged)
+                    }
+                    (Some(retrievability), Some(deck_retrievability))
+                        if deck_retrievability - retrievability > LAG_MARGIN =>
+                    {
+                        let lag = deck_retrievability - retrievability;
+                        (
+                            (1.0 + lag * LAG_WEIGHT).min(MAX_LAG_FACTOR),
+                            SmartAddReason::Lagging,
+                        )
+                    }
+                    _ => (1.0, SmartAddReason::Balanced),
+                };
+                (
+                    *card_type,
+                    summary,
+                    f64::from(base_percent(*card_type)) * factor,
+                    reason,
+                )
+            })
+            .collect::<Vec<_>>();
+
+        let total_weight: f64 = weighted.iter().map(|(_, _, weight, _)| weight).sum();
+        weighted
+            .into_iter()
+            .map(|(card_type, summary, weight, reason)| SmartAddWeight {
+                card_type,
+                base_percent: base_percent(card_type),
+                percent: (weight / total_weight * 100.0).round() as u32,
+                retrievability_percent: summary
+                    .retrievability()
+                    .map(|retrievability| (retrievability * 100.0).round() as u32),
+                due: summary.due,
+                reason,
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn set_reviewed(
+        deck: &mut Deck,
+        card_type: CardType,
+        last_review: DateTime<Utc>,
+        due: DateTime<Utc>,
+    ) {
+        let cards = deck
+            .cards
+            .keys()
+            .filter(|card| card.card_type() == card_type)
+            .take(MIN_BACKLOG as usize)
+            .copied()
+            .collect::<Vec<_>>();
+        assert_eq!(cards.len(), MIN_BACKLOG as usize);
+        let fsrs_card = rs_fsrs::Card {
+            state: rs_fsrs::State::Review,
+            stability: 5.0,
+            difficulty: 5.0,
+            last_review,
+            due,
+            ..rs_fsrs::Card::new(last_review)
+        };
+        for card in cards {
+            deck.cards.insert(
+                card,
+                CardStatus::Tracked(CardData::Added {
+                    fsrs_card: fsrs_card.clone(),
+                }),
+            );
+        }
+    }
+
+    fn share(mix: &[SmartAddWeight], card_type: CardType) -> (u32, SmartAddReason) {
+        let weight = mix
+            .iter()
+            .find(|weight| weight.card_type == card_type)
+            .unwrap();
+        (weight.percent, weight.reason)
+    }
+
+    #[test]
+    fn test_lagging_skills_get_more_new_cards_and_backlogged_ones_fewer() {
+        let now = Utc::now();
+        let mut deck = Deck::default();
+        assert!(deck.smart_add_mix(now).iter().all(|weight| {
+            weight.percent == weight.base_percent && weight.reason == SmartAddReason::TooFewReviews
+        }));
+
+        // Text cards reviewed yesterday, listening cards reviewed two weeks ago
+        set_reviewed(
+            &mut deck,
+            CardType::TargetLanguage,
+            now - chrono::Duration::days(1),
+            now + chrono::Duration::days(4),
+        );
+        set_reviewed(
+            &mut deck,
+            CardType::Listening,
+            now - chrono::Duration::days(14),
+            now + chrono::Duration::days(1),
+        );
+        let mix = deck.smart_add_mix(now);
+        let (listening, reason) = share(&mix, CardType::Listening);
+        assert_eq!(reason, SmartAddReason::Lagging);
+        assert!(listening > base_percent(CardType::Listening));
+        assert_eq!(
+            share(&mix, CardType::TargetLanguage).1,
+            SmartAddReason::Balanced
+        );
+
+        // Once they're all due, the user should catch up on them first
+        set_reviewed(
+            &mut deck,
+            CardType::Listening,
+            now - chrono::Duration::days(14),
+            now - chrono::Duration::days(1),
+        );
+        let (listening, reason) = share(&deck.smart_add_mix(now), CardType::Listening);
+        assert_eq!(reason, SmartAddReason::Backlogged);
+        assert!(listening < base_percent(CardType::Listening));
+    }
+
+    #[test]
+    fn test_add_card_options_mix_is_as_of_the_given_time() {
+        let now = DateTime::<Utc>::from_timestamp(1_760_000_000, 0).unwrap();
+        let mut deck = Deck::default();
+        set_reviewed(
+            &mut deck,
+            CardType::TargetLanguage,
+            now - chrono::Duration::days(1),
+            now + chrono::Duration::days(4),
+        );
+        set_reviewed(
+            &mut deck,
+            CardType::Listening,
+            now - chrono::Duration::days(14),
+            now + chrono::Duration::days(1),
+        );
+
+        let mix_at = |time: DateTime<Utc>| {
+            deck.add_card_options(Vec::new(), time.timestamp_millis() as f64)
+                .smart_add_mix
+        };
+        assert_eq!(mix_at(now), deck.smart_add_mix(now));
+        assert_eq!(
+            share(&mix_at(now), CardType::Listening).1,
+            SmartAddReason::Lagging
+        );
+        // Two days later the listening cards are all due
+        assert_eq!(
+            share(
+                &mix_at(now + chrono::Duration::days(2)),
+                CardType::Listening
+            )
+            .1,
+            SmartAddReason::Backlogged
+        );
+    }
+}
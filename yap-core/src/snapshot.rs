@@ -0,0 +1,419 @@
+//! Saving a `DeckState` in a `weapon::data_model::StateSnapshot`, so a new device can start from a
+//! snapshot and only replay the events after it. The deck refers to the language pack's strings by
+//! `Spur`, which are only meaningful for the pack they came from, so the snapshot stores the
+//! strings themselves, and lexemes the pack has renamed since are followed to their new strings.
+//! If the pack no longer has one of them, the snapshot can't be restored and the events are
+//! replayed instead.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use chrono::{DateTime, Utc};
+use language_utils::fsrs_parameters::FsrsParameters;
+use language_utils::{Heteronym, HomophoneSentencePair, Language, Lexeme, TtsProvider};
+use serde::{Deserialize, Serialize};
+
+use crate::account_sharing::AccountSessions;
+use crate::activity::ActivityHistory;
+use crate::confusions::Confusions;
+use crate::content_reports::ReportedContent;
+use crate::data_mismatches::{DataMismatchKind, DataMismatches};
+use crate::fatigue::ChallengeAccuracy;
+use crate::graduation::Graduation;
+use crate::hint_policy::HintUsage;
+use crate::lookups::{LookupHistory, WordLookups};
+use crate::movie_quiz::MovieQuizResult;
+use crate::quick_session::ChallengeDurations;
+use crate::retention::RetentionHistory;
+use crate::scheduler::{self, ReviewOrder, SchedulerKind, SchedulingProfile};
+use crate::sentence_repetition::SentenceSchedules;
+use crate::session_struggles::{ReviewCounts, SessionReviewLog, SessionReviews};
+use crate::vocabulary_rank::VocabularyRankHistory;
+use crate::warmup::{RecentMiss, RecentMisses};
+use crate::{
+    AudioFeedbackCounts, CardData, CardIndicator, DailyGoal, DailyStreak, Deck, DeckState,
+    ResetPoint, SelfAssessedLevel, SentenceFilters, Stats,
+};
+
+#[derive(Serialize, Deserialize)]
+struct DeckSnapshot {
+    target_language: Language,
+    native_language: Language,
+    cards: Vec<(CardIndicator<String>, SnapshotCard)>,
+    scheduler: SchedulerKind,
+    stats: StatsSnapshot,
+    leeches: Vec<(CardIndicator<String>, u64)>,
+    prioritized: Vec<CardIndicator<String>>,
+    sentence_filters: SentenceFilters,
+    personal_fsrs_parameters: Option<FsrsParameters>,
+    data_mismatches: Vec<(DataMismatchKind, String, u32)>,
+    untracked_data_mismatches: u32,
+    confusions: Vec<(String, String, u32)>,
+    share_mistake_history: bool,
+    component_credit: bool,
+    recognition_only: Vec<CardIndicator<String>>,
+    review_order: ReviewOrder,
+    daily_goal: Option<DailyGoal>,
+    pending_calibration: Option<SelfAssessedLevel>,
+    new_card_accuracy_threshold: Option<u32>,
+    scheduling_profile: SchedulingProfile,
+}
+
+#[derive(Serialize, Deserialize)]
+struct SnapshotCard {
+    ghost: bool,
+    due: DateTime<Utc>,
+    stability: f64,
+    difficulty: f64,
+    elapsed_days: i64,
+    scheduled_days: i64,
+    reps: i64,
+    lapses: i64,
+    state: SnapshotCardState,
+    last_review: DateTime<Utc>,
+    created_at: DateTime<Utc>,
+    accumulated_positive_surprise: f64,
+    accumulated_negative_surprise: f64,
+}
+
+/// `rs_fsrs::State` isn't serializable
+#[derive(Serialize, Deserialize)]
+enum SnapshotCardState {
+    New,
+    Learning,
+    Review,
+    Relearning,
+}
+
+#[derive(Serialize, Deserialize)]
+struct StatsSnapshot {
+    sentences_reviewed: Vec<(String, u32)>,
+    words_listened_to: Vec<(Heteronym<String>, u32)>,
+    words_misheard: Vec<(Heteronym<String>, u32)>,
+    sentence_pairs_reviewed: Vec<(HomophoneSentencePair<String>, u32)>,
+    total_reviews: u64,
+    xp: f64,
+    daily_streak: Option<DailyStreak>,
+    past_week_challenges: BTreeMap<i64, u32>,
+    start_time: Option<DateTime<Utc>>,
+    audio_feedback: Vec<((TtsProvider, Language), AudioFeedbackCounts)>,
+    favorite_sentences: BTreeSet<String>,
+    challenge_accuracy: ChallengeAccuracy,
+    vocabulary_rank: VocabularyRankHistory,
+    last_reset: Option<ResetPoint>,
+    activity: ActivityHistory,
+    lookups: Vec<(Lexeme<String>, WordLookups)>,
+    movie_quizzes: BTreeMap<String, MovieQuizResult>,
+    session_reviews: Vec<(DateTime<Utc>, Vec<(CardIndicator<String>, ReviewCounts)>)>,
+    content_reports: Vec<ReportedContent>,
+    graduation: Option<Graduation>,
+    hint_usage: HintUsage,
+    sentence_repetition: SentenceSchedules,
+    retention: RetentionHistory,
+    recent_misses: Vec<(CardIndicator<String>, RecentMiss)>,
+    challenge_durations: ChallengeDurations,
+    account_sessions: AccountSessions,
+}
+
+impl SnapshotCard {
+    fn new(card_data: &CardData) -> Self {
+        let (ghost, card) = match card_data {
+            CardData::Added { fsrs_card } => (false, fsrs_card),
+            CardData::Ghost { fsrs_card } => (true, fsrs_card),
+        };
+        Self {
+            ghost,
+            due: card.due,
+            stability: card.stability,
+            difficulty: card.difficulty,
+            elapsed_days: card.elapsed_days as i64,
+            scheduled_days: card.scheduled_days as i64,
+            reps: card.reps as i64,
+            lapses: card.lapses as i64,
+            state: match card.state {
+                rs_fsrs::State::New => SnapshotCardState::New,
+                rs_fsrs::State::Learning => SnapshotCardState::Learning,
+                rs_fsrs::State::Review => SnapshotCardState::Review,
+                rs_fsrs::State::Relearning => SnapshotCardState::Relearning,
+            },
+            last_review: card.last_review,
+            created_at: card.created_at,
+            accumulated_positive_surprise: card.accumulated_positive_surprise,
+            accumulated_negative_surprise: card.accumulated_negative_surprise,
+        }
+    }
+
+    fn card_data(&self) -> CardData {
+        let mut fsrs_card = rs_fsrs::Card::new(self.created_at);
+        fsrs_card.due = self.due;
+        fsrs_card.stability = self.stability;
+        fsrs_card.difficulty = self.difficulty;
+        fsrs_card.elapsed_days = self.elapsed_days as _;
+        fsrs_card.scheduled_days = self.scheduled_days as _;
+        fsrs_card.reps = self.reps as _;
+        fsrs_card.lapses = self.lapses as _;
+        fsrs_card.state = match self.state {
+            SnapshotCardState::New => rs_fsrs::State::New,
+            SnapshotCardState::Learning => rs_fsrs::State::Learning,
+            SnapshotCardState::Review => rs_fsrs::State::Review,
+            SnapshotCardState::Relearning => rs_fsrs::State::Relearning,
+        };
+        fsrs_card.last_review = self.last_review;
+        fsrs_card.accumulated_positive_surprise = self.accumulated_positive_surprise;
+        fsrs_card.accumulated_negative_surprise = self.accumulated_negative_surprise;
+        if self.ghost {
+            CardData::Ghost { fsrs_card }
+        } else {
+            CardData::Added { fsrs_card }
+        }
+    }
+}
+
+impl weapon::data_model::SnapshotState for Deck {
+    fn save_partial(deck: &DeckState) -> Option<serde_json::Value> {
+        let rodeo = &deck.context.language_pack.rodeo;
+        let stats = &deck.stats;
+        let (data_mismatches, untracked_data_mismatches) = deck.data_mismatches.entries();
+        let snapshot = DeckSnapshot {
+            target_language: deck.context.target_language,
+            native_language: deck.context.native_language,
+            cards: deck
+                .cards
+                .iter()
+                .map(|(card, card_data)| (card.resolve(rodeo), SnapshotCard::new(card_data)))
+                .collect(),
+            scheduler: deck.scheduler,
+            stats: StatsSnapshot {
+                sentences_reviewed: stats
+                    .sentences_reviewed
+                    .iter()
+                    .map(|(sentence, count)| (rodeo.resolve(sentence).to_string(), *count))
+                    .collect(),
+                words_listened_to: stats
+                    .words_listened_to
+                    .iter()
+                    .map(|(word, count)| (word.resolve(rodeo), *count))
+                    .collect(),
+                words_misheard: stats
+                    .words_misheard
+                    .iter()
+                    .map(|(word, count)| (word.resolve(rodeo), *count))
+                    .collect(),
+                sentence_pairs_reviewed: stats
+                    .sentence_pairs_reviewed
+                    .iter()
+                    .map(|(pair, count)| (pair.resolve(rodeo), *count))
+                    .collect(),
+                total_reviews: stats.total_reviews,
+                xp: stats.xp,
+                daily_streak: stats.daily_streak.clone(),
+                past_week_challenges: stats.past_week_challenges.clone(),
+                start_time: stats.start_time,
+                audio_feedback: stats
+                    .audio_feedback
+                    .iter()
+                    .map(|(key, counts)| (key.clone(), *counts))
+                    .collect(),
+                favorite_sentences: stats.favorite_sentences.clone(),
+                challenge_accuracy: stats.challenge_accuracy.clone(),
+                vocabulary_rank: stats.vocabulary_rank.clone(),
+                activity: stats.activity.clone(),
+                last_reset: stats.last_reset.clone(),
+                lookups: stats
+                    .lookups
+                    .words
+                    .iter()
+                    .map(|(lexeme, lookups)| (lexeme.resolve(rodeo), lookups.clone()))
+                    .collect(),
+                movie_quizzes: stats.movie_quizzes.clone(),
+                session_reviews: stats
+                    .session_reviews
+                    .sessions
+                    .iter()
+                    .map(|session| {
+                        let cards = session
+                            .cards
+                            .iter()
+                            .map(|(card, counts)| (card.resolve(rodeo), *counts))
+                            .collect();
+                        (session.start, cards)
+                    })
+                    .collect(),
+                content_reports: stats.content_reports.clone(),
+                graduation: stats.graduation,
+                hint_usage: stats.hint_usage.clone(),
+                sentence_repetition: stats.sentence_repetition.clone(),
+                retention: stats.retention.clone(),
+                recent_misses: stats
+                    .recent_misses
+                    .misses
+                    .iter()
+                    .map(|(card, miss)| (card.resolve(rodeo), *miss))
+                    .collect(),
+                challenge_durations: stats.challenge_durations.clone(),
+                account_sessions: stats.account_sessions.clone(),
+            },
+            leeches: deck
+                .leeches
+                .iter()
+                .map(|(card, reviews)| (card.resolve(rodeo), *reviews))
+                .collect(),
+            prioritized: deck
+                .prioritized
+                .iter()
+                .map(|card| card.resolve(rodeo))
+                .collect(),
+            sentence_filters: deck.sentence_filters.clone(),
+            personal_fsrs_parameters: deck.personal_fsrs_parameters.clone(),
+            data_mismatches,
+            untracked_data_mismatches,
+            confusions: deck.confusions.entries(),
+            share_mistake_history: deck.share_mistake_history,
+            component_credit: deck.component_credit,
+            recognition_only: deck
+                .recognition_only
+                .iter()
+                .map(|card| card.resolve(rodeo))
+                .collect(),
+            review_order: deck.review_order,
+            daily_goal: deck.daily_goal,
+            pending_calibration: deck.pending_calibration,
+            new_card_accuracy_threshold: deck.new_card_accuracy_threshold,
+            scheduling_profile: deck.scheduling_profile,
+        };
+        serde_json::to_value(snapshot)
+            .inspect_err(|e| log::error!("Failed to serialize deck snapshot: {e:?}"))
+            .ok()
+    }
+
+    fn restore_partial(
+        snapshot: &serde_json::Value,
+        initial_state: &DeckState,
+    ) -> Option<DeckState> {
+        let snapshot: DeckSnapshot = serde_json::from_value(snapshot.clone())
+            .inspect_err(|e| log::warn!("Failed to parse deck snapshot: {e:?}"))
+            .ok()?;
+        let context = initial_state.context.clone();
+        if snapshot.target_language != context.target_language
+            || snapshot.native_language != context.native_language
+        {
+            return None;
+        }
+        let rodeo = &context.language_pack.rodeo;
+        let stats = snapshot.stats;
+        Some(DeckState {
+            cards: snapshot
+                .cards
+                .iter()
+                .map(|(card, card_data)| Some((context.intern_card(card)?, card_data.card_data())))
+                .collect::<Option<_>>()?,
+            fsrs: scheduler::deck_fsrs(
+                snapshot
+                    .personal_fsrs_parameters
+                    .as_ref()
+                    .or(context.language_pack.fsrs_preset.as_ref()),
+                snapshot.scheduling_profile,
+            ),
+            scheduler: snapshot.scheduler,
+            stats: Stats {
+                sentences_reviewed: stats
+                    .sentences_reviewed
+                    .iter()
+                    .map(|(sentence, count)| Some((rodeo.get(sentence)?, *count)))
+                    .collect::<Option<_>>()?,
+                words_listened_to: stats
+                    .words_listened_to
+                    .iter()
+                    .map(|(word, count)| Some((context.intern_heteronym(word)?, *count)))
+                    .collect::<Option<_>>()?,
+                words_misheard: stats
+                    .words_misheard
+                    .iter()
+                    .map(|(word, count)| Some((context.intern_heteronym(word)?, *count)))
+                    .collect::<Option<_>>()?,
+                sentence_pairs_reviewed: stats
+                    .sentence_pairs_reviewed
+                    .iter()
+                    .map(|(pair, count)| Some((pair.get_interned(rodeo)?, *count)))
+                    .collect::<Option<_>>()?,
+                total_reviews: stats.total_reviews,
+                xp: stats.xp,
+                daily_streak: stats.daily_streak,
+                past_week_challenges: stats.past_week_challenges,
+                start_time: stats.start_time,
+                audio_feedback: stats.audio_feedback.into_iter().collect(),
+                favorite_sentences: stats.favorite_sentences,
+                challenge_accuracy: stats.challenge_accuracy,
+                vocabulary_rank: stats.vocabulary_rank,
+                activity: stats.activity,
+                last_reset: stats.last_reset,
+                lookups: LookupHistory {
+                    words: stats
+                        .lookups
+                        .into_iter()
+                        .map(|(lexeme, lookups)| Some((context.intern_lexeme(&lexeme)?, lookups)))
+                        .collect::<Option<_>>()?,
+                },
+                movie_quizzes: stats.movie_quizzes,
+                session_reviews: SessionReviewLog {
+                    sessions: stats
+                        .session_reviews
+                        .into_iter()
+                        .map(|(start, cards)| {
+                            let cards = cards
+                                .iter()
+                                .map(|(card, counts)| Some((context.intern_card(card)?, *counts)))
+                                .collect::<Option<_>>()?;
+                            Some(SessionReviews { start, cards })
+                        })
+                        .collect::<Option<_>>()?,
+                },
+                content_reports: stats.content_reports,
+                graduation: stats.graduation,
+                hint_usage: stats.hint_usage,
+                sentence_repetition: stats.sentence_repetition,
+                retention: stats.retention,
+                recent_misses: RecentMisses {
+                    misses: stats
+                        .recent_misses
+                        .iter()
+                        .map(|(card, miss)| Some((context.intern_card(card)?, *miss)))
+                        .collect::<Option<_>>()?,
+                },
+                challenge_durations: stats.challenge_durations,
+                account_sessions: stats.account_sessions,
+            },
+            leeches: snapshot
+                .leeches
+                .iter()
+                .map(|(card, reviews)| Some((context.intern_card(card)?, *reviews)))
+                .collect::<Option<_>>()?,
+            prioritized: snapshot
+                .prioritized
+                .iter()
+                .map(|card| context.intern_card(card))
+                .collect::<Option<_>>()?,
+            sentence_filters: snapshot.sentence_filters,
+            personal_fsrs_parameters: snapshot.personal_fsrs_parameters,
+            // Only depends on the language pack
+            resolved_challenges: initial_state.resolved_challenges.clone(),
+            data_mismatches: DataMismatches::from_entries(
+                snapshot.data_mismatches,
+                snapshot.untracked_data_mismatches,
+            ),
+            confusions: Confusions::from_entries(snapshot.confusions),
+            share_mistake_history: snapshot.share_mistake_history,
+            component_credit: snapshot.component_credit,
+            recognition_only: snapshot
+                .recognition_only
+                .iter()
+                .map(|card| context.intern_card(card))
+                .collect::<Option<_>>()?,
+            review_order: snapshot.review_order,
+            daily_goal: snapshot.daily_goal,
+            pending_calibration: snapshot.pending_calibration,
+            new_card_accuracy_threshold: snapshot.new_card_accuracy_threshold,
+            scheduling_profile: snapshot.scheduling_profile,
+            context,
+        })
+    }
+}
@@ -0,0 +1,126 @@
+//! Names of event streams. A stream is created the first time it's mentioned, so a misspelled
+//! id doesn't fail, it quietly reads and writes a new empty stream. `StreamId` names Yap's own
+//! streams with constants, and ids that come in as strings (from JS, or saved by older versions)
+//! go through `StreamId::parse`, which rejects ones no registered schema covers.
+
+use std::borrow::Cow;
+use std::fmt;
+
+use weapon::data_model::EventStore;
+
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct StreamId(Cow<'static, str>);
+
+impl StreamId {
+    /// The list of sub-profiles
+    pub const SUB_PROFILES: StreamId = StreamId(Cow::Borrowed("sub_profiles"));
+    /// The account's own reviews. Sub-profiles have their own, see `for_sub_profile`.
+    pub const REVIEWS: StreamId = StreamId(Cow::Borrowed("reviews"));
+    /// The account's own deck selection. Sub-profiles have their own, see `for_sub_profile`.
+    pub const DECK_SELECTION: StreamId = StreamId(Cow::Borrowed("deck_selection"));
+    pub const SETTINGS: StreamId = StreamId(Cow::Borrowed(weapon::settings::SETTINGS_STREAM));
+
+    /// Every stream Yap registers a schema for
+    pub const WELL_KNOWN: [StreamId; 4] = [
+        Self::SUB_PROFILES,
+        Self::REVIEWS,
+        Self::DECK_SELECTION,
+        Self::SETTINGS,
+    ];
+
+    /// Checks a stream id given as a string against the schemas registered with `store`. Besides
+    /// the exact prefix a schema was registered with, a schema covers the ids namespaced under it
+    /// with a `.`, like a sub-profile's `reviews.<id>`.
+    pub fn parse<Device>(
+        id: impl Into<String>,
+        store: &EventStore<String, Device>,
+    ) -> Result<StreamId, UnknownStreamId>
+    where
+        Device: Eq + std::hash::Hash + Clone + Ord + 'static,
+    {
+        let id = id.into();
+        let covered = store
+            .schema_prefix(&id)
+            .is_some_and(|prefix| id.len() == prefix.len() || id[prefix.len()..].starts_with('.'));
+        if covered {
+            Ok(StreamId(Cow::Owned(id)))
+        } else {
+            Err(UnknownStreamId { id })
+        }
+    }
+
+    /// This stream for a sub-profile, or the stream itself for the account's own profile (`None`)
+    pub fn for_sub_profile(&self, sub_profile: Option<&str>) -> StreamId {
+        match sub_profile {
+            Some(id) => StreamId(Cow::Owned(format!("{self}.{id}"))),
+            None => self.clone(),
+        }
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+#[error("No schema is registered for stream {id:?}, is it misspelled?")]
+pub struct UnknownStreamId {
+    pub id: String,
+}
+
+impl fmt::Display for StreamId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl AsRef<str> for StreamId {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl PartialEq<str> for StreamId {
+    fn eq(&self, other: &str) -> bool {
+        self.0 == other
+    }
+}
+
+impl PartialEq<String> for StreamId {
+    fn eq(&self, other: &String) -> bool {
+        self.0 == other.as_str()
+    }
+}
+
+/// The event store is keyed by plain strings, which is also how stream ids are saved and synced
+impl From<StreamId> for String {
+    fn from(id: StreamId) -> Self {
+        id.0.into_owned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use weapon::data_model::EventType;
+
+    use super::*;
+
+    #[test]
+    fn test_parse_rejects_unregistered_streams() {
+        let mut store: EventStore<String, String> = EventStore::default();
+        for stream in StreamId::WELL_KNOWN {
+            store.register_schema::<EventType<crate::DeckEvent>>(stream);
+        }
+
+        assert_eq!(
+            StreamId::parse("reviews", &store).unwrap(),
+            StreamId::REVIEWS
+        );
+        assert_eq!(
+            StreamId::parse("reviews.a", &store).unwrap(),
+            StreamId::REVIEWS.for_sub_profile(Some("a"))
+        );
+        assert!(StreamId::parse("reveiws", &store).is_err());
+        assert!(StreamId::parse("reviewsa", &store).is_err());
+    }
+}
@@ -0,0 +1,179 @@
+//! Sub-profiles let several people learn on one account (e.g. a family sharing a tablet). Each one
+//! gets its own deck streams, named by `reviews_stream` and `deck_selection_stream`, so their
+//! reviews and stats stay apart while everything still syncs under the same Supabase user. The
+//! account's own profile is `None` and keeps the original stream names.
+
+use std::collections::BTreeMap;
+
+use weapon::data_model::Event;
+
+use crate::StreamId;
+
+/// The reviews stream of a sub-profile, or of the account's own profile for `None`
+pub fn reviews_stream(sub_profile: Option<&str>) -> StreamId {
+    StreamId::REVIEWS.for_sub_profile(sub_profile)
+}
+
+/// The deck selection stream of a sub-profile, or of the account's own profile for `None`
+pub fn deck_selection_stream(sub_profile: Option<&str>) -> StreamId {
+    StreamId::DECK_SELECTION.for_sub_profile(sub_profile)
+}
+
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize, PartialEq, Eq)]
+#[cfg_attr(target_arch = "wasm32", derive(tsify::Tsify))]
+#[cfg_attr(target_arch = "wasm32", tsify(into_wasm_abi, from_wasm_abi))]
+pub struct SubProfile {
+    pub id: String,
+    pub name: String,
+}
+
+/// The sub-profiles on the account, in the order they were created
+#[derive(Clone, Debug, Default)]
+pub struct SubProfiles {
+    profiles: BTreeMap<String, (usize, String)>,
+}
+
+impl SubProfiles {
+    pub fn contains(&self, id: &str) -> bool {
+        self.profiles.contains_key(id)
+    }
+
+    pub fn list(&self) -> Vec<SubProfile> {
+        let mut profiles: Vec<_> = self.profiles.iter().collect();
+        profiles.sort_by_key(|(_, (created, _))| *created);
+        profiles
+            .into_iter()
+            .map(|(id, (_, name))| SubProfile {
+                id: id.clone(),
+                name: name.clone(),
+            })
+            .collect()
+    }
+}
+
+impl weapon::PartialAppState for SubProfiles {
+    type Event = SubProfileEvent;
+    type Partial = (Self, usize);
+
+    fn process_event(
+        (mut state, created): Self::Partial,
+        event: &weapon::data_model::Timestamped<Self::Event>,
+    ) -> Self::Partial {
+        match &event.event {
+            SubProfileEvent::Create { id, name } => {
+                state
+                    .profiles
+                    .entry(id.clone())
+                    .or_insert_with(|| (created, name.clone()));
+                (state, created + 1)
+            }
+            SubProfileEvent::Rename { id, name } => {
+                if let Some((_, existing)) = state.profiles.get_mut(id) {
+                    *existing = name.clone();
+                }
+                (state, created)
+            }
+            // The profile's streams are left alone, so its history isn't lost if it was removed by
+            // mistake
+            SubProfileEvent::Remove { id } => {
+                state.profiles.remove(id);
+                (state, created)
+            }
+        }
+    }
+
+    fn finalize((state, _): Self::Partial) -> Self {
+        state
+    }
+}
+
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize, PartialEq, Eq, Ord, PartialOrd)]
+#[cfg_attr(target_arch = "wasm32", derive(tsify::Tsify))]
+#[cfg_attr(target_arch = "wasm32", tsify(into_wasm_abi, from_wasm_abi))]
+pub enum SubProfileEvent {
+    Create { id: String, name: String },
+    Rename { id: String, name: String },
+    Remove { id: String },
+}
+
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize, Ord, PartialOrd, Eq, PartialEq)]
+#[serde(tag = "version")]
+pub enum VersionedSubProfileEvent {
+    V1(SubProfileEvent),
+}
+
+impl Event for SubProfileEvent {
+    fn to_json(&self) -> Result<serde_json::Value, serde_json::Error> {
+        serde_json::to_value(VersionedSubProfileEvent::V1(self.clone()))
+    }
+
+    fn from_json(json: &serde_json::Value) -> Result<Self, serde_json::Error> {
+        serde_json::from_value::<VersionedSubProfileEvent>(json.clone()).map(|versioned| {
+            match versioned {
+                VersionedSubProfileEvent::V1(event) => event,
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use weapon::PartialAppState as _;
+    use weapon::data_model::Timestamped;
+
+    #[test]
+    fn test_sub_profiles_keep_creation_order() {
+        let events = [
+            SubProfileEvent::Create {
+                id: "b".to_string(),
+                name: "Sam".to_string(),
+            },
+            SubProfileEvent::Create {
+                id: "a".to_string(),
+                name: "Alex".to_string(),
+            },
+            SubProfileEvent::Rename {
+                id: "b".to_string(),
+                name: "Samantha".to_string(),
+            },
+            SubProfileEvent::Create {
+                id: "c".to_string(),
+                name: "Jo".to_string(),
+            },
+            SubProfileEvent::Remove {
+                id: "c".to_string(),
+            },
+        ];
+        let state = events.into_iter().enumerate().fold(
+            (SubProfiles::default(), 0),
+            |state, (index, event)| {
+                SubProfiles::process_event(
+                    state,
+                    &Timestamped {
+                        timestamp: chrono::Utc::now(),
+                        within_device_events_index: index,
+                        event,
+                    },
+                )
+            },
+        );
+        let profiles = SubProfiles::finalize(state);
+
+        assert_eq!(
+            profiles.list(),
+            vec![
+                SubProfile {
+                    id: "b".to_string(),
+                    name: "Samantha".to_string(),
+                },
+                SubProfile {
+                    id: "a".to_string(),
+                    name: "Alex".to_string(),
+                },
+            ]
+        );
+        assert_eq!(reviews_stream(Some("a")).as_str(), "reviews.a");
+        assert_eq!(deck_selection_stream(None), StreamId::DECK_SELECTION);
+    }
+}
@@ -0,0 +1,126 @@
+//! Knowledge carried over from the user's decks in other courses. Someone who knows French
+//! already knows many Spanish words, so a new Spanish deck shouldn't start by teaching "nación".
+//! The language pack pairs lemmas with their cognates in the other courses' packs (see
+//! `LanguagePack::cognates`), and each lemma the user knows in another course becomes a data
+//! point for this deck's knowledge regression, as long as this deck hasn't seen the word itself.
+
+use std::sync::Arc;
+
+use language_utils::{Heteronym, Language, Lexeme};
+use pav_regression::Point;
+use rustc_hash::FxHashMap;
+use serde::{Deserialize, Serialize};
+#[cfg(target_arch = "wasm32")]
+use wasm_bindgen::prelude::*;
+
+use crate::{CardData, CardIndicator, CardStatus, Context, Deck, DeckState};
+
+/// A cognate is a different word, so knowing one only counts for part of knowing the other
+const TRANSFER_DISCOUNT: f64 = 0.5;
+/// Cognates weigh less in the regression than the deck's own reviews
+const TRANSFER_WEIGHT: f64 = 0.5;
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(target_arch = "wasm32", derive(tsify::Tsify))]
+#[cfg_attr(target_arch = "wasm32", tsify(into_wasm_abi, from_wasm_abi))]
+pub struct TransferableKnowledge {
+    /// The target language of the deck it comes from
+    pub language: Language,
+    pub lemmas: Vec<KnownLemma>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(target_arch = "wasm32", derive(tsify::Tsify))]
+#[cfg_attr(target_arch = "wasm32", tsify(into_wasm_abi, from_wasm_abi))]
+pub struct KnownLemma {
+    pub heteronym: Heteronym<String>,
+    /// The card's pre-existing knowledge in that deck, see `CardData::pre_existing_knowledge`
+    pub knowledge: f64,
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+impl Deck {
+    /// The lemmas this deck has reviewed without ever failing them, for seeding a deck in another
+    /// course with `DeckState::with_transferred_knowledge`
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn get_transferable_knowledge(&self) -> TransferableKnowledge {
+        let rodeo = &self.context.language_pack.rodeo;
+        let mut lemmas = self
+            .cards
+            .iter()
+            .filter_map(|(card, status)| {
+                let CardIndicator::TargetLanguage {
+                    lexeme: Lexeme::Heteronym(heteronym),
+                } = card
+                else {
+                    return None;
+                };
+                let CardStatus::Tracked(card_data) = status else {
+                    return None;
+                };
+                let (CardData::Added { fsrs_card } | CardData::Ghost { fsrs_card }) = card_data;
+                let knowledge = card_data.pre_existing_knowledge();
+                (heteronym.word == heteronym.lemma
+                    && fsrs_card.state != rs_fsrs::State::New
+                    && knowledge > 0.0)
+                    .then(|| KnownLemma {
+                        heteronym: heteronym.resolve(rodeo),
+                        knowledge,
+                    })
+            })
+            .collect::<Vec<_>>();
+        lemmas.sort_by(|a, b| a.heteronym.cmp(&b.heteronym));
+        TransferableKnowledge {
+            language: self.context.target_language,
+            lemmas,
+        }
+    }
+}
+
+impl DeckState {
+    /// Seeds the deck's knowledge regression with what the user knows in their other courses.
+    /// Knowledge from this deck's own course, or from a course the pack has no cognates for, is
+    /// ignored.
+    pub fn with_transferred_knowledge(mut self, other_courses: Vec<TransferableKnowledge>) -> Self {
+        let language_pack = &self.context.language_pack;
+        let mut transferred: FxHashMap<Lexeme<lasso::Spur>, f64> = FxHashMap::default();
+        for course in other_courses
+            .iter()
+            .filter(|course| course.language != self.context.target_language)
+        {
+            for lemma in &course.lemmas {
+                let Some(cognate) = language_pack.cognate(course.language, &lemma.heteronym) else {
+                    continue;
+                };
+                let knowledge = transferred.entry(Lexeme::Heteronym(cognate)).or_default();
+                *knowledge = knowledge.max(lemma.knowledge * TRANSFER_DISCOUNT);
+            }
+        }
+        self.context.transferred_knowledge = Arc::new(transferred);
+        self
+    }
+}
+
+impl Context {
+    /// Regression points for the words known in other courses that `cards` doesn't track yet
+    pub(crate) fn transferred_points(
+        &self,
+        cards: &FxHashMap<CardIndicator<lasso::Spur>, CardData>,
+    ) -> Vec<Point<f64>> {
+        self.transferred_knowledge
+            .iter()
+            .filter_map(|(lexeme, knowledge)| {
+                let card = CardIndicator::TargetLanguage { lexeme: *lexeme };
+                if cards.contains_key(&card) {
+                    return None;
+                }
+                let frequency = self.get_card_frequency(&card)?;
+                Some(Point::new_with_weight(
+                    frequency.sqrt_frequency(),
+                    *knowledge,
+                    TRANSFER_WEIGHT,
+                ))
+            })
+            .collect()
+    }
+}
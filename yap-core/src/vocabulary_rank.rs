@@ -0,0 +1,129 @@
+//! The effective vocabulary rank: how far down the frequency list the user's vocabulary reaches,
+//! as the largest N such that they know at least `KNOWN_FRACTION` of the N most frequent words. A
+//! value is recorded for each day they study, so the frontend can show the rank climbing.
+
+use language_utils::{Frequency, Lexeme, indexmap::IndexMap};
+use lasso::Spur;
+use rustc_hash::FxHashSet;
+use serde::{Deserialize, Serialize};
+
+/// How much of the top N words the user has to know for N to count
+const KNOWN_FRACTION: f64 = 0.95;
+
+/// `word_frequencies` is the language pack's, which is already ordered most frequent first
+pub(crate) fn effective_rank(
+    word_frequencies: &IndexMap<Lexeme<Spur>, Frequency>,
+    known: impl IntoIterator<Item = Lexeme<Spur>>,
+) -> u32 {
+    let known: FxHashSet<Lexeme<Spur>> = known.into_iter().collect();
+    let mut known_so_far = 0;
+    let mut rank = 0;
+    for (index, lexeme) in word_frequencies.keys().enumerate() {
+        let n = index + 1;
+        // Past this point even knowing every remaining word couldn't get back to the threshold
+        if (known.len() as f64) < KNOWN_FRACTION * n as f64 {
+            break;
+        }
+        if known.contains(lexeme) {
+            known_so_far += 1;
+        }
+        if known_so_far as f64 >= KNOWN_FRACTION * n as f64 {
+            rank = n as u32;
+        }
+    }
+    rank
+}
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[cfg_attr(target_arch = "wasm32", derive(tsify::Tsify))]
+#[cfg_attr(target_arch = "wasm32", tsify(into_wasm_abi, from_wasm_abi))]
+pub struct VocabularyRankPoint {
+    /// Days since the Unix epoch (UTC)
+    pub day: i64,
+    /// The effective vocabulary rank at the end of that day
+    pub rank: u32,
+}
+
+/// The rank at the end of each day the user studied, filled in as events are processed
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct VocabularyRankHistory {
+    points: Vec<VocabularyRankPoint>,
+    /// The day of the most recent event, whose rank isn't final yet
+    current_day: Option<i64>,
+}
+
+impl VocabularyRankHistory {
+    /// Called before an event on `day` is processed. Returns the previous day if this event starts
+    /// a new one, in which case its final rank should be passed to `record`.
+    pub(crate) fn start_day(&mut self, day: i64) -> Option<i64> {
+        let finished = self.current_day.filter(|current| *current != day);
+        self.current_day = Some(day);
+        finished
+    }
+
+    pub(crate) fn record(&mut self, day: i64, rank: u32) {
+        self.points.push(VocabularyRankPoint { day, rank });
+    }
+
+    /// Every finished day, plus the current one at `current_rank`
+    pub(crate) fn points(&self, current_rank: u32) -> Vec<VocabularyRankPoint> {
+        let mut points = self.points.clone();
+        if let Some(day) = self.current_day {
+            points.push(VocabularyRankPoint {
+                day,
+                rank: current_rank,
+            });
+        }
+        points
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_effective_rank_allows_a_few_gaps() {
+        let mut rodeo = lasso::Rodeo::new();
+        let lexemes: Vec<Lexeme<Spur>> = (0..100)
+            .map(|i| Lexeme::Multiword(rodeo.get_or_intern(format!("word {i}"))))
+            .collect();
+        let mut word_frequencies = IndexMap::new();
+        for (i, lexeme) in lexemes.iter().enumerate() {
+            word_frequencies.insert(
+                *lexeme,
+                Frequency {
+                    count: 1000 - i as u32,
+                },
+            );
+        }
+
+        // Knows the top 40 except the 10th, then nothing
+        let known = lexemes
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| *i < 40 && *i != 9)
+            .map(|(_, lexeme)| *lexeme);
+        // 39 of the top 41 is still over 95%, 39 of 42 isn't
+        assert_eq!(effective_rank(&word_frequencies, known), 41);
+
+        assert_eq!(effective_rank(&word_frequencies, []), 0);
+    }
+
+    #[test]
+    fn test_history_records_finished_days() {
+        let mut history = VocabularyRankHistory::default();
+        assert_eq!(history.start_day(10), None);
+        assert_eq!(history.start_day(10), None);
+        assert_eq!(history.start_day(12), Some(10));
+        history.record(10, 5);
+
+        assert_eq!(
+            history.points(8),
+            vec![
+                VocabularyRankPoint { day: 10, rank: 5 },
+                VocabularyRankPoint { day: 12, rank: 8 },
+            ]
+        );
+    }
+}
@@ -0,0 +1,265 @@
+//! A short warm-up at the start of a session, over the cards the user forgot in the last
+//! `WARMUP_WINDOW_HOURS`, while the mistake is still fresh. Cards that are due anyway are just
+//! reviewed first. The rest come up as flashcards graded with `Deck::warmup_review`, which doesn't
+//! touch their schedule, so going over a card early doesn't make FSRS think it's better known
+//! than it is.
+
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, Duration, Utc};
+use lasso::Spur;
+use serde::{Deserialize, Serialize};
+#[cfg(target_arch = "wasm32")]
+use wasm_bindgen::prelude::*;
+
+use crate::{
+    CardData, CardIndicator, CardStatus, ChallengeRequirements, Deck, DeckEvent, LanguageEvent,
+    LanguageEventContent, Rating, ReviewInfo, datetime_from_ms,
+};
+
+/// Cards forgotten longer ago than this aren't warmed up
+const WARMUP_WINDOW_HOURS: i64 = 48;
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq)]
+pub(crate) struct RecentMiss {
+    at: DateTime<Utc>,
+    /// The start of the session the card was forgotten in
+    session_start: DateTime<Utc>,
+}
+
+/// The cards forgotten in the last `WARMUP_WINDOW_HOURS`, tracked as events are processed
+#[derive(Clone, Debug, Default)]
+pub(crate) struct RecentMisses {
+    pub(crate) misses: BTreeMap<CardIndicator<Spur>, RecentMiss>,
+}
+
+impl RecentMisses {
+    /// A scheduled review of `card`. Remembering it clears the miss, unless it was forgotten in
+    /// the same session, since getting it right a few minutes later doesn't mean it stuck.
+    pub(crate) fn record(
+        &mut self,
+        card: CardIndicator<Spur>,
+        rating: Rating,
+        timestamp: DateTime<Utc>,
+        session_start: DateTime<Utc>,
+    ) {
+        if rating == Rating::Again {
+            self.missed(card, timestamp, session_start);
+        } else if self
+            .misses
+            .get(&card)
+            .is_some_and(|miss| miss.session_start != session_start)
+        {
+            self.misses.remove(&card);
+        }
+    }
+
+    /// A warm-up review of `card`, see `Deck::warmup_review`
+    pub(crate) fn warmed_up(
+        &mut self,
+        card: CardIndicator<Spur>,
+        remembered: bool,
+        timestamp: DateTime<Utc>,
+        session_start: DateTime<Utc>,
+    ) {
+        if remembered {
+            self.misses.remove(&card);
+        } else {
+            self.missed(card, timestamp, session_start);
+        }
+    }
+
+    fn missed(
+        &mut self,
+        card: CardIndicator<Spur>,
+        timestamp: DateTime<Utc>,
+        session_start: DateTime<Utc>,
+    ) {
+        self.misses.insert(
+            card,
+            RecentMiss {
+                at: timestamp,
+                session_start,
+            },
+        );
+        let cutoff = timestamp - Duration::hours(WARMUP_WINDOW_HOURS);
+        self.misses.retain(|_, miss| miss.at >= cutoff);
+    }
+
+    pub(crate) fn contains(&self, card: &CardIndicator<Spur>) -> bool {
+        self.misses.contains_key(card)
+    }
+}
+
+impl Deck {
+    /// See `get_warmup_cards`
+    fn warmup_cards(&self, limit: usize, now: DateTime<Utc>) -> Vec<CardIndicator<Spur>> {
+        let cutoff = now - Duration::hours(WARMUP_WINDOW_HOURS);
+        let session_start = self.stats.activity.session_start_at(now);
+        let mut misses = self
+            .stats
+            .recent_misses
+            .misses
+            .iter()
+            .filter(|(card, miss)| {
+                miss.at >= cutoff
+                    && miss.at <= now
+                    && miss.session_start != session_start
+                    && matches!(
+                        self.cards.get(*card),
+                        Some(CardStatus::Tracked(CardData::Added { .. }))
+                    )
+            })
+            .collect::<Vec<_>>();
+        misses.sort_by_key(|(card, miss)| (std::cmp::Reverse(miss.at), **card));
+        misses
+            .into_iter()
+            .take(limit)
+            .map(|(card, _)| *card)
+            .collect()
+    }
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+impl Deck {
+    /// Up to `limit` cards the user forgot in the last `WARMUP_WINDOW_HOURS` before
+    /// `timestamp_ms`, most recently forgotten first, whether or not they're due. Cards forgotten
+    /// in the ongoing session are left for the next one, and remembering a card in a later
+    /// session (in its warm-up or a regular review) takes it off the list.
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn get_warmup_cards(&self, limit: usize, timestamp_ms: f64) -> Vec<CardIndicator<String>> {
+        let rodeo = &self.context.language_pack.rodeo;
+        self.warmup_cards(limit, datetime_from_ms(timestamp_ms))
+            .into_iter()
+            .map(|card| card.resolve(rodeo))
+            .collect()
+    }
+
+    /// Grades a warm-up flashcard for a card that wasn't due (see `ReviewInfo::start_with_warmup`).
+    /// The card's schedule doesn't change. Returns `None` if the card wasn't forgotten lately.
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn warmup_review(
+        &self,
+        reviewed: CardIndicator<String>,
+        remembered: bool,
+    ) -> Option<DeckEvent> {
+        let card = reviewed.get_interned(&self.context.language_pack.rodeo)?;
+        self.stats
+            .recent_misses
+            .contains(&card)
+            .then_some(DeckEvent::Language(LanguageEvent {
+                target_language: self.context.target_language,
+                native_language: self.context.native_language,
+                content: LanguageEventContent::WarmupReview {
+                    reviewed,
+                    remembered,
+                },
+            }))
+    }
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+impl ReviewInfo {
+    /// Starts the session with up to `limit` of the cards from `Deck::get_warmup_cards`. Ones
+    /// that are due move to the front of the queue and are reviewed as usual. The rest come first
+    /// as flashcards with `warmup` set, leaving out types in `banned_challenge_types`.
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn start_with_warmup(
+        &mut self,
+        deck: &Deck,
+        banned_challenge_types: Vec<ChallengeRequirements>,
+        limit: usize,
+        timestamp_ms: f64,
+    ) {
+        let (mut due, not_due): (Vec<_>, Vec<_>) = deck
+            .warmup_cards(limit, datetime_from_ms(timestamp_ms))
+            .into_iter()
+            .filter(|card| !self.due_but_banned_cards.contains(card))
+            .partition(|card| self.due_cards.contains(card));
+        self.due_cards.retain(|card| !due.contains(card));
+        due.append(&mut self.due_cards);
+        self.due_cards = due;
+        self.warmup_cards = not_due
+            .into_iter()
+            .filter(|card| !banned_challenge_types.contains(&card.card_type().challenge_type()))
+            .collect();
+    }
+
+    /// Whether a challenge for `card` is a warm-up of a card that isn't due, so the frontend can
+    /// flag it
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn is_warmup(&self, deck: &Deck, card: CardIndicator<String>) -> bool {
+        card.get_interned(&deck.context.language_pack.rodeo)
+            .is_some_and(|card| self.warmup_cards.contains(&card))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use weapon::AppState;
+    use weapon::data_model::Timestamped;
+
+    #[test]
+    fn test_forgotten_cards_are_warmed_up_in_the_next_session() {
+        let start = DateTime::<Utc>::from_timestamp(1_760_000_000, 0).unwrap();
+        let mut deck = Deck::default();
+        let event = deck
+            .add_next_unknown_cards(None, 3, Vec::new(), start.timestamp_millis() as f64)
+            .unwrap();
+        let DeckEvent::Language(LanguageEvent {
+            content: LanguageEventContent::AddCards { cards },
+            ..
+        }) = &event
+        else {
+            panic!("expected an AddCards event");
+        };
+        let (forgotten, other) = (cards[0].clone(), cards[1].clone());
+        let apply = |deck: &Deck, event: DeckEvent, timestamp: DateTime<Utc>| {
+            deck.apply_event(&Timestamped {
+                timestamp,
+                within_device_events_index: 0,
+                event,
+            })
+        };
+        deck = apply(&deck, event, start);
+        let event = deck.review_card(forgotten.clone(), Rating::Again).unwrap();
+        deck = apply(&deck, event, start + Duration::minutes(1));
+        let at = |time: DateTime<Utc>| time.timestamp_millis() as f64;
+
+        // Not in the session it was forgotten in, nor once the window has passed
+        assert!(
+            deck.get_warmup_cards(5, at(start + Duration::minutes(2)))
+                .is_empty()
+        );
+        let tomorrow = start + Duration::days(1);
+        assert_eq!(
+            deck.get_warmup_cards(5, at(tomorrow)),
+            vec![forgotten.clone()]
+        );
+        assert!(
+            deck.get_warmup_cards(5, at(start + Duration::hours(WARMUP_WINDOW_HOURS + 1)))
+                .is_empty()
+        );
+
+        // It's due by now, so it's reviewed first as usual
+        let mut review_info = deck.get_review_info(Vec::new(), at(tomorrow));
+        review_info.start_with_warmup(&deck, Vec::new(), 5, at(tomorrow));
+        let rodeo = &deck.context.language_pack.rodeo;
+        assert_eq!(review_info.due_cards[0].resolve(rodeo), forgotten);
+        assert!(!review_info.is_warmup(&deck, forgotten.clone()));
+
+        let card = review_info.due_cards[0];
+        let due = |deck: &Deck| match deck.cards.get(&card) {
+            Some(CardStatus::Tracked(card_data)) => card_data.due_timestamp_ms(),
+            _ => panic!("the card should be in the deck"),
+        };
+
+        assert_eq!(deck.warmup_review(other, true), None);
+        let due_before = due(&deck);
+        let event = deck.warmup_review(forgotten, true).unwrap();
+        deck = apply(&deck, event, tomorrow);
+        assert!(deck.get_warmup_cards(5, at(tomorrow)).is_empty());
+        assert_eq!(due(&deck), due_before);
+    }
+}
@@ -0,0 +1,260 @@
+//! A summary of a week of studying, computed here so every platform shows the same numbers
+
+use language_utils::Lexeme;
+use lasso::Spur;
+use rustc_hash::FxHashSet;
+use serde::{Deserialize, Serialize};
+#[cfg(target_arch = "wasm32")]
+use wasm_bindgen::prelude::*;
+
+use crate::{CardData, CardIndicator, CardStatus, Deck, datetime_from_ms};
+
+const DAYS_PER_WEEK: usize = 7;
+const MS_PER_DAY: f64 = 24.0 * 60.0 * 60.0 * 1000.0;
+
+/// How many struggled words the digest lists
+const STRUGGLED_WORDS: usize = 5;
+
+/// Movie comprehension milestones are every this-many percent, like in `get_movie_stats`
+pub(crate) const MOVIE_MILESTONE_PERCENT: f64 = 5.0;
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(target_arch = "wasm32", derive(tsify::Tsify))]
+#[cfg_attr(target_arch = "wasm32", tsify(into_wasm_abi))]
+pub struct WeeklyDigest {
+    pub reviews: u32,
+    /// The share of reviews that were remembered, or `None` if there weren't any
+    pub accuracy: Option<f64>,
+    /// Words whose cards were added during the week
+    pub new_words: u32,
+    /// Reviews on each day of the week, starting with the day of `week_start_ms`
+    pub reviews_per_day: Vec<u32>,
+    /// Which day of the week had the most reviews, counting from 0, or `None` if there weren't any
+    pub best_day: Option<u32>,
+    /// The daily streak as of the end of the week
+    pub streak: u32,
+    /// Whether the user studied on every day of the week
+    pub studied_every_day: bool,
+    /// The words reviewed during the week that have been forgotten the most, most forgotten first
+    pub struggled_words: Vec<StruggledWord>,
+    pub movie_milestones: Vec<MovieMilestone>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(target_arch = "wasm32", derive(tsify::Tsify))]
+#[cfg_attr(target_arch = "wasm32", tsify(into_wasm_abi))]
+pub struct StruggledWord {
+    pub lexeme: Lexeme<String>,
+    /// How many times the word has been forgotten after being learned
+    pub lapses: u32,
+}
+
+/// A movie whose comprehension crossed a milestone during the week
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(target_arch = "wasm32", derive(tsify::Tsify))]
+#[cfg_attr(target_arch = "wasm32", tsify(into_wasm_abi))]
+pub struct MovieMilestone {
+    pub id: String,
+    /// The highest milestone crossed, e.g. 45.0 for 45% of the movie's words
+    pub milestone: f64,
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+impl Deck {
+    /// The digest for the seven days starting at `week_start_ms`, which should be midnight in the
+    /// user's time zone
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn get_weekly_digest(&self, week_start_ms: f64) -> WeeklyDigest {
+        let week_end_ms = week_start_ms + DAYS_PER_WEEK as f64 * MS_PER_DAY;
+        let week_start = datetime_from_ms(week_start_ms);
+        let week_end = datetime_from_ms(week_end_ms);
+
+        let mut reviews_per_day = vec![0; DAYS_PER_WEEK];
+        let mut correct = 0;
+        for session in self.stats.activity.sessions_between(week_start, week_end) {
+            let day =
+                ((session.start.timestamp_millis() as f64 - week_start_ms) / MS_PER_DAY) as usize;
+            reviews_per_day[day.min(DAYS_PER_WEEK - 1)] += session.reviews;
+            correct += session.correct;
+        }
+        let reviews: u32 = reviews_per_day.iter().sum();
+        let best_day = (reviews > 0).then(|| {
+            (0..DAYS_PER_WEEK as u32)
+                .max_by_key(|day| (reviews_per_day[*day as usize], std::cmp::Reverse(*day)))
+                .unwrap_or_default()
+        });
+
+        let added_cards = || {
+            self.cards
+                .iter()
+                .filter_map(|(indicator, status)| match status {
+                    CardStatus::Tracked(CardData::Added { fsrs_card }) => {
+                        Some((indicator, fsrs_card))
+                    }
+                    _ => None,
+                })
+        };
+        let new_words = added_cards()
+            .filter(|(indicator, fsrs_card)| {
+                matches!(indicator, CardIndicator::TargetLanguage { .. })
+                    && fsrs_card.created_at >= week_start
+                    && fsrs_card.created_at < week_end
+            })
+            .count() as u32;
+
+        let rodeo = &self.context.language_pack.rodeo;
+        let mut struggled_words = added_cards()
+            .filter_map(|(indicator, fsrs_card)| match indicator {
+                CardIndicator::TargetLanguage { lexeme }
+                    if fsrs_card.lapses > 0
+                        && fsrs_card.last_review >= week_start
+                        && fsrs_card.last_review < week_end =>
+                {
+                    Some((*lexeme, fsrs_card.lapses as u32))
+                }
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        struggled_words.sort_by_key(|(_, lapses)| std::cmp::Reverse(*lapses));
+        let struggled_words = struggled_words
+            .into_iter()
+            .take(STRUGGLED_WORDS)
+            .map(|(lexeme, lapses)| StruggledWord {
+                lexeme: lexeme.resolve(rodeo),
+                lapses,
+            })
+            .collect();
+
+        WeeklyDigest {
+            reviews,
+            accuracy: (reviews > 0).then(|| correct as f64 / reviews as f64),
+            new_words,
+            best_day,
+            streak: self.get_daily_streak(week_end_ms),
+            studied_every_day: reviews_per_day.iter().all(|reviews| *reviews > 0),
+            reviews_per_day,
+            struggled_words,
+            movie_milestones: self.movie_milestones_crossed(week_start),
+        }
+    }
+}
+
+impl Deck {
+    /// Movies whose comprehension crossed a milestone since `since`. The comprehension back then
+    /// is estimated from the words that are comprehensible now and were already added by then.
+    fn movie_milestones_crossed(
+        &self,
+        since: chrono::DateTime<chrono::Utc>,
+    ) -> Vec<MovieMilestone> {
+        let mut comprehensible_now: FxHashSet<Lexeme<Spur>> = FxHashSet::default();
+        let mut comprehensible_before: FxHashSet<Lexeme<Spur>> = FxHashSet::default();
+        for (indicator, status) in &self.cards {
+            let CardIndicator::TargetLanguage { lexeme } = indicator else {
+                continue;
+            };
+            if !self
+                .context
+                .is_comprehensible(indicator, status, &self.regressions)
+            {
+                continue;
+            }
+            comprehensible_now.insert(*lexeme);
+            if let CardStatus::Tracked(CardData::Added { fsrs_card }) = status
+                && fsrs_card.created_at < since
+            {
+                comprehensible_before.insert(*lexeme);
+            }
+        }
+
+        let language_pack = &self.context.language_pack;
+        let mut milestones = language_pack
+            .movies
+            .keys()
+            .filter(|movie_id| {
+                !self
+                    .sentence_filters
+                    .excludes_movie(movie_id, language_pack)
+            })
+            .filter_map(|movie_id| {
+                let movie_frequencies = language_pack.movie_word_frequencies.get(movie_id)?;
+                let mut total = 0u64;
+                let mut before = 0u64;
+                let mut now = 0u64;
+                for (lexeme, frequency) in movie_frequencies.iter() {
+                    let count = frequency.count as u64;
+                    total += count;
+                    if comprehensible_now.contains(lexeme) {
+                        now += count;
+                    }
+                    if comprehensible_before.contains(lexeme) {
+                        before += count;
+                    }
+                }
+                if total == 0 {
+                    return None;
+                }
+                let milestone = |count: u64| {
+                    (count as f64 / total as f64 * 100.0 / MOVIE_MILESTONE_PERCENT).floor()
+                        * MOVIE_MILESTONE_PERCENT
+                };
+                (milestone(now) > milestone(before)).then(|| MovieMilestone {
+                    id: movie_id.clone(),
+                    milestone: milestone(now),
+                })
+            })
+            .collect::<Vec<_>>();
+        milestones.sort_by(|a, b| a.id.cmp(&b.id));
+        milestones
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::activity::ChallengeKind;
+
+    #[test]
+    fn test_digest_summarizes_the_week() {
+        let week_start = chrono::DateTime::<chrono::Utc>::from_timestamp(1_700_006_400, 0).unwrap();
+        let week_start_ms = week_start.timestamp_millis() as f64;
+        let mut deck = Deck::default();
+        let mut lexemes = deck.context.language_pack.word_frequencies.keys().copied();
+        for (lapses, created_days_ago, reviewed_days_ago) in [(3, -1, -2), (1, 10, -1), (5, 10, 1)]
+        {
+            let fsrs_card = rs_fsrs::Card {
+                state: rs_fsrs::State::Review,
+                lapses,
+                last_review: week_start - chrono::Duration::days(reviewed_days_ago),
+                ..rs_fsrs::Card::new(week_start - chrono::Duration::days(created_days_ago))
+            };
+            deck.cards.insert(
+                CardIndicator::TargetLanguage {
+                    lexeme: lexemes.next().unwrap(),
+                },
+                CardStatus::Tracked(CardData::Added { fsrs_card }),
+            );
+        }
+        for (day, outcome) in [(0, true), (2, true), (2, false), (2, true), (9, true)] {
+            deck.stats.activity.record(
+                week_start + chrono::Duration::days(day),
+                Some((ChallengeKind::Flashcard, outcome)),
+                1.0,
+            );
+        }
+
+        let digest = deck.get_weekly_digest(week_start_ms);
+        assert_eq!(digest.reviews, 4);
+        assert_eq!(digest.accuracy, Some(0.75));
+        assert_eq!(digest.reviews_per_day, vec![1, 0, 3, 0, 0, 0, 0]);
+        assert_eq!(digest.best_day, Some(2));
+        assert!(!digest.studied_every_day);
+        assert_eq!(digest.new_words, 1);
+        // The card last reviewed before the week isn't listed
+        let lapses = digest
+            .struggled_words
+            .iter()
+            .map(|word| word.lapses)
+            .collect::<Vec<_>>();
+        assert_eq!(lapses, vec![3, 1]);
+    }
+}
@@ -0,0 +1,392 @@
+//! XP for sentence challenges. Harder challenges (longer sentences, rarer words, no hints) are
+//! worth more than easy ones.
+//!
+//! XP is recomputed from the event log every time the deck is loaded, so each challenge event
+//! records the formula it was earned under. Changing the formula means adding a new `XpFormula`
+//! variant: older events keep theirs, so nobody's historical XP moves. The parts of the score that
+//! come from the language pack (which words were in the sentence, and how rare they are) are
+//! recorded in the event too, as `XpInputs`, so rebuilding the pack doesn't move it either.
+
+use std::collections::BTreeSet;
+
+use language_utils::{Language, Lexeme, language_pack::LanguagePack};
+use language_utils::{text_cleanup, transcription_challenge};
+use lasso::Spur;
+use serde::{Deserialize, Serialize};
+
+use crate::{Deck, LanguageEventContent, SentenceReviewIndicator, SentenceReviewResult};
+
+#[derive(Copy, Clone, Debug, Serialize, Deserialize, PartialEq, Eq, Ord, PartialOrd)]
+#[cfg_attr(target_arch = "wasm32", derive(tsify::Tsify))]
+#[cfg_attr(target_arch = "wasm32", tsify(into_wasm_abi, from_wasm_abi))]
+pub enum XpFormula {
+    /// A flat amount for every word reviewed. Events that don't record a formula use this, as
+    /// they're from before there was any other.
+    Flat,
+    /// Scored by `difficulty_xp`
+    Difficulty,
+}
+
+/// The formula new challenge events are scored with
+pub(crate) const CURRENT_XP_FORMULA: XpFormula = XpFormula::Difficulty;
+
+const BASE_XP: f64 = 2.0;
+/// Words a sentence can have before it starts earning the length bonus
+const FREE_WORDS: usize = 3;
+const LENGTH_BONUS_PER_WORD: f64 = 0.5;
+const MAX_RARITY_BONUS_PER_WORD: f64 = 1.0;
+const NO_HINT_BONUS: f64 = 2.0;
+
+/// How the XP for one challenge was worked out
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(target_arch = "wasm32", derive(tsify::Tsify))]
+#[cfg_attr(target_arch = "wasm32", tsify(into_wasm_abi, from_wasm_abi))]
+pub struct XpBreakdown {
+    /// For doing the challenge at all
+    pub base: f64,
+    /// For each word remembered beyond the first few
+    pub length_bonus: f64,
+    /// For remembering uncommon words
+    pub rarity_bonus: f64,
+    /// For getting everything right without tapping for hints
+    pub no_hint_bonus: f64,
+    pub total: f64,
+}
+
+/// What an `XpFormula::Difficulty` score is worked out from, recorded in the challenge event when
+/// the deck creates it. Events without them (from before they were recorded, or graded from the
+/// offline queue) are scored from the current language pack instead.
+#[derive(Copy, Clone, Debug, Serialize, Deserialize, PartialEq, Eq, Ord, PartialOrd)]
+#[cfg_attr(target_arch = "wasm32", derive(tsify::Tsify))]
+#[cfg_attr(target_arch = "wasm32", tsify(into_wasm_abi, from_wasm_abi))]
+pub struct XpInputs {
+    /// How many words the user remembered without a hint
+    pub remembered: u32,
+    /// The sum of `rarity` over those words, in thousandths
+    pub rarity_thousandths: u32,
+    pub without_mistakes_or_hints: bool,
+}
+
+/// Between 0 for words more common than one in a thousand and 1 for words rarer than one in a
+/// hundred thousand
+fn rarity(lexeme: &Lexeme<Spur>, language_pack: &LanguagePack) -> f64 {
+    let Some(frequency) = language_pack.word_frequencies.get(lexeme) else {
+        return 0.0;
+    };
+    let one_in = language_pack.total_word_count as f64 / frequency.count.max(1) as f64;
+    ((one_in.log10() - 3.0) / 2.0).clamp(0.0, 1.0)
+}
+
+fn difficulty_inputs(
+    remembered: &BTreeSet<Lexeme<Spur>>,
+    without_mistakes_or_hints: bool,
+    language_pack: &LanguagePack,
+) -> XpInputs {
+    let rarity = remembered
+        .iter()
+        .map(|lexeme| rarity(lexeme, language_pack))
+        .sum::<f64>();
+    XpInputs {
+        remembered: remembered.len() as u32,
+        rarity_thousandths: (rarity * 1000.0).round() as u32,
+        without_mistakes_or_hints,
+    }
+}
+
+fn difficulty_xp(inputs: XpInputs) -> XpBreakdown {
+    let base = BASE_XP;
+    let length_bonus =
+        (inputs.remembered as usize).saturating_sub(FREE_WORDS) as f64 * LENGTH_BONUS_PER_WORD;
+    let rarity_bonus = f64::from(inputs.rarity_thousandths) / 1000.0 * MAX_RARITY_BONUS_PER_WORD;
+    let no_hint_bonus = if inputs.without_mistakes_or_hints {
+        NO_HINT_BONUS
+    } else {
+        0.0
+    };
+    XpBreakdown {
+        base,
+        length_bonus,
+        rarity_bonus,
+        no_hint_bonus,
+        total: base + length_bonus + rarity_bonus + no_hint_bonus,
+    }
+}
+
+/// The XP for a challenge event, or `None` if it isn't a challenge or is scored with
+/// `XpFormula::Flat` (which `Deck::log_review` takes care of)
+pub(crate) fn challenge_xp(
+    event: &LanguageEventContent,
+    language_pack: &LanguagePack,
+    target_language: Language,
+) -> Option<XpBreakdown> {
+    let (LanguageEventContent::TranslationChallenge {
+        xp_formula: Some(XpFormula::Difficulty),
+        xp_inputs: recorded,
+        ..
+    }
+    | LanguageEventContent::TranscriptionChallenge {
+        xp_formula: Some(XpFormula::Difficulty),
+        xp_inputs: recorded,
+        ..
+    }) = event
+    else {
+        return None;
+    };
+    recorded
+        .or_else(|| xp_inputs(event, language_pack, target_language))
+        .map(difficulty_xp)
+}
+
+/// What a challenge event scored with `XpFormula::Difficulty` would be scored from with the
+/// current language pack
+fn xp_inputs(
+    event: &LanguageEventContent,
+    language_pack: &LanguagePack,
+    target_language: Language,
+) -> Option<XpInputs> {
+    let intern_all = |lexemes: &BTreeSet<Lexeme<String>>| {
+        lexemes
+            .iter()
+            .filter_map(|lexeme| lexeme.get_interned(&language_pack.rodeo))
+            .collect::<BTreeSet<_>>()
+    };
+
+    match event {
+        LanguageEventContent::TranslationChallenge {
+            review:
+                SentenceReviewIndicator::TargetToNative {
+                    challenge_sentence,
+                    result,
+                    ..
+                },
+            xp_formula: Some(XpFormula::Difficulty),
+            ..
+        } => match result {
+            SentenceReviewResult::Perfect {
+                lexemes_needed_hint,
+                generated_sentence_lexemes,
+            } => {
+                let cleaned_sentence =
+                    text_cleanup::cleanup_sentence(challenge_sentence.clone(), target_language);
+                let lexemes: BTreeSet<Lexeme<Spur>> = match language_pack
+                    .rodeo
+                    .get(&cleaned_sentence)
+                    .and_then(|sentence| language_pack.sentences_to_lexemes.get(&sentence))
+                {
+                    Some(lexemes) => lexemes.iter().copied().collect(),
+                    None => intern_all(generated_sentence_lexemes),
+                };
+                let needed_hint = intern_all(lexemes_needed_hint);
+                let remembered = lexemes.difference(&needed_hint).copied().collect();
+                Some(difficulty_inputs(
+                    &remembered,
+                    lexemes_needed_hint.is_empty(),
+                    language_pack,
+                ))
+            }
+            SentenceReviewResult::Wrong {
+                lexemes_remembered,
+                lexemes_needed_hint,
+                ..
+            } => {
+                let remembered = intern_all(lexemes_remembered)
+                    .difference(&intern_all(lexemes_needed_hint))
+                    .copied()
+                    .collect();
+                Some(difficulty_inputs(&remembered, false, language_pack))
+            }
+        },
+        LanguageEventContent::TranscriptionChallenge {
+            challenge,
+            xp_formula: Some(XpFormula::Difficulty),
+            ..
+        } => {
+            let mut heard = BTreeSet::new();
+            let mut misheard = BTreeSet::new();
+            for part in challenge {
+                let transcription_challenge::PartGraded::AskedToTranscribe { parts, .. } = part
+                else {
+                    continue;
+                };
+                for part in parts {
+                    let Some(heteronym) = part
+                        .heard
+                        .heteronym
+                        .as_ref()
+                        .and_then(|heteronym| heteronym.get_interned(&language_pack.rodeo))
+                    else {
+                        continue;
+                    };
+                    // Same split as the ratings in `process_event`
+                    match part.grade {
+                        transcription_challenge::WordGrade::Perfect { .. }
+                        | transcription_challenge::WordGrade::CorrectWithTypo { .. }
+                        | transcription_challenge::WordGrade::PhoneticallyIdenticalButContextuallyIncorrect { .. } => {
+                            heard.insert(heteronym);
+                        }
+                        transcription_challenge::WordGrade::PhoneticallySimilarButContextuallyIncorrect { .. }
+                        | transcription_challenge::WordGrade::Incorrect { .. }
+                        | transcription_challenge::WordGrade::Missed {} => {
+                            misheard.insert(heteronym);
+                        }
+                    }
+                }
+            }
+            let remembered = heard
+                .difference(&misheard)
+                .map(|heteronym| Lexeme::Heteronym(*heteronym))
+                .collect();
+            Some(difficulty_inputs(
+                &remembered,
+                misheard.is_empty(),
+                language_pack,
+            ))
+        }
+        _ => None,
+    }
+}
+
+impl Deck {
+    /// `content` with its `XpInputs` worked out from the current language pack, if it's a
+    /// challenge scored with `XpFormula::Difficulty`
+    pub(crate) fn with_xp_inputs(&self, mut content: LanguageEventContent) -> LanguageEventContent {
+        let inputs = xp_inputs(
+            &content,
+            &self.context.language_pack,
+            self.context.target_language,
+        );
+        if let LanguageEventContent::TranslationChallenge { xp_inputs, .. }
+        | LanguageEventContent::TranscriptionChallenge { xp_inputs, .. } = &mut content
+        {
+            *xp_inputs = inputs;
+        }
+        content
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{DeckEvent, LanguageEvent};
+    use chrono::{DateTime, Utc};
+    use weapon::AppState;
+    use weapon::data_model::Timestamped;
+
+    /// A challenge on a sentence that isn't in the language pack, scored from `xp_inputs`
+    fn translation(perfect: bool, xp_inputs: Option<XpInputs>) -> LanguageEventContent {
+        LanguageEventContent::TranslationChallenge {
+            review: SentenceReviewIndicator::TargetToNative {
+                challenge_sentence: "Ceci n'est pas dans le pack".to_string(),
+                result: if perfect {
+                    SentenceReviewResult::Perfect {
+                        lexemes_needed_hint: BTreeSet::new(),
+                        generated_sentence_lexemes: BTreeSet::new(),
+                    }
+                } else {
+                    SentenceReviewResult::Wrong {
+                        submission: "This isn't in the pack".to_string(),
+                        lexemes_remembered: BTreeSet::new(),
+                        lexemes_forgotten: BTreeSet::new(),
+                        lexemes_needed_hint: BTreeSet::new(),
+                    }
+                },
+                favorite_practice: false,
+            },
+            xp_formula: Some(XpFormula::Difficulty),
+            xp_inputs,
+            component_credit: None,
+        }
+    }
+
+    #[test]
+    fn test_difficulty_xp() {
+        let breakdown = difficulty_xp(XpInputs {
+            remembered: 5,
+            rarity_thousandths: 1_500,
+            without_mistakes_or_hints: true,
+        });
+        assert_eq!(
+            breakdown,
+            XpBreakdown {
+                base: 2.0,
+                length_bonus: 1.0,
+                rarity_bonus: 1.5,
+                no_hint_bonus: 2.0,
+                total: 6.5,
+            }
+        );
+    }
+
+    #[test]
+    fn test_recorded_xp_inputs_are_scored_without_the_language_pack() {
+        let start = DateTime::<Utc>::from_timestamp(1_760_000_000, 0).unwrap();
+        let mut deck = Deck::default();
+        let events = [
+            translation(
+                false,
+                Some(XpInputs {
+                    remembered: 5,
+                    rarity_thousandths: 1_500,
+                    without_mistakes_or_hints: false,
+                }),
+            ),
+            translation(
+                true,
+                Some(XpInputs {
+                    remembered: 2,
+                    rarity_thousandths: 250,
+                    without_mistakes_or_hints: true,
+                }),
+            ),
+        ];
+        for (index, content) in events.into_iter().enumerate() {
+            deck = deck.apply_event(&Timestamped {
+                timestamp: start + chrono::Duration::minutes(index as i64),
+                within_device_events_index: index,
+                event: DeckEvent::Language(LanguageEvent {
+                    target_language: deck.context.target_language,
+                    native_language: deck.context.native_language,
+                    content,
+                }),
+            });
+        }
+        // 2 + 1 + 1.5 for the first, 2 + 0.25 + 2 for the second
+        assert_eq!(deck.get_xp(), 8.75);
+
+        // Without recorded inputs, the sentence's words come from the pack, which doesn't have it
+        let unrecorded = deck.with_xp_inputs(translation(true, None));
+        let LanguageEventContent::TranslationChallenge { xp_inputs, .. } = &unrecorded else {
+            unreachable!();
+        };
+        assert_eq!(
+            *xp_inputs,
+            Some(XpInputs {
+                remembered: 0,
+                rarity_thousandths: 0,
+                without_mistakes_or_hints: true,
+            })
+        );
+
+        let flat = LanguageEventContent::TranslationChallenge {
+            review: SentenceReviewIndicator::TargetToNative {
+                challenge_sentence: "Bonjour".to_string(),
+                result: SentenceReviewResult::Perfect {
+                    lexemes_needed_hint: BTreeSet::new(),
+                    generated_sentence_lexemes: BTreeSet::new(),
+                },
+                favorite_practice: false,
+            },
+            xp_formula: None,
+            xp_inputs: None,
+            component_credit: None,
+        };
+        assert_eq!(
+            challenge_xp(
+                &flat,
+                &deck.context.language_pack,
+                deck.context.target_language
+            ),
+            None
+        );
+    }
+}
@@ -0,0 +1,39 @@
+//! Using the app without an account, i.e. with a `Weapon` made without a user id. Nothing about
+//! an anonymous user leaves the device:
+//!
+//! - translations are graded with `language_utils::local_grading` and transcriptions with
+//!   `heuristic_transcription_grade`, rather than by the LLM
+//! - sentences are spoken with the browser's own voices (`speak_on_device`), since the server
+//!   only synthesizes audio for signed-in users. Audio cached before signing out still plays.
+//! - `Weapon::sync` keeps the streams in OPFS and never talks to Supabase, and the pending grades
+//!   queue waits for an account
+//!
+//! Signing in later moves the anonymous streams into the account (see `Weapon::new`), and the
+//! next sync uploads them.
+
+use language_utils::Language;
+use wasm_bindgen::prelude::*;
+
+use crate::Weapon;
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+impl Weapon {
+    /// Whether the user is using the app without an account, see the `anonymous` module
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn is_anonymous(&self) -> bool {
+        self.user_id.is_none()
+    }
+}
+
+/// Speaks `text` with the browser's speech synthesis, for when there's no account to fetch audio
+/// from the server with (see `get_audio`)
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+pub fn speak_on_device(text: String, language: Language) -> Result<(), JsValue> {
+    let synthesis = web_sys::window()
+        .ok_or_else(|| JsValue::from_str("No window"))?
+        .speech_synthesis()?;
+    let utterance = web_sys::SpeechSynthesisUtterance::new_with_text(&text)?;
+    utterance.set_lang(language.iso_639_1());
+    synthesis.speak(&utterance);
+    Ok(())
+}
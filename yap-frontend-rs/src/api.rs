@@ -0,0 +1,1048 @@
+//! Typed façades over `Weapon` and `Deck`, grouping their wasm surface by what it's for:
+//!
+//! - `SyncApi` (`Weapon::sync_api`): loading, saving and syncing event streams
+//! - `DeckApi` (`Weapon::deck_api`): language packs, deck state, adding events, and review
+//!   sessions that follow the user's challenge type settings
+//! - `StatsApi` (`new StatsApi(deck, timestamp_ms)`): progress and statistics for a deck
+//! - `ChallengeApi` (`new ChallengeApi(deck, ...)`): a review session, from picking challenges
+//!   to grading them
+//!
+//! Every fallible method returns an `ApiError`. The older methods on `Weapon` and `Deck` are
+//! still there while the TS side moves over; these just delegate to them.
+
+use std::rc::Rc;
+
+#[cfg(feature = "movies")]
+use language_utils::MovieMetadata;
+use language_utils::content_report::ContentReportStatus;
+use language_utils::pack_manifest::PackChannel;
+use language_utils::sentence_generation::GenerateSentenceRequest;
+use language_utils::{Course, Heteronym, Language, Lexeme, transcription_challenge};
+use opfs::persistent;
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::prelude::*;
+use weapon::data_model::{
+    DeviceProgress, DuplicateEvent, ListenerKey, SyncHealth, SyncState, SyncTarget,
+};
+use weapon::supabase::SupabaseSyncPreview;
+
+#[cfg(target_arch = "wasm32")]
+use crate::BackgroundSyncReport;
+use crate::{
+    AccountSharingHint, AddCardOptions, AudioFeedback, AudioRequest, CardFrequencyDrift,
+    CardIndicator, CardSummary, CardSummaryPage, Challenge, ChallengeErrorReport, ChallengeOutcome,
+    ChallengeRequirements, ChallengeResult, ContentReportHistory, Deck, DeckEvent,
+    EarliestUnsyncedEvent, FatigueReport, FetchedLanguagePack, FrequencyKnowledgePoint,
+    HandsFreeChallenge, LookedUpWord, OnboardingAnswers, PronunciationCoverage,
+    PronunciationWeakness, ProviderAudioFeedback, QuickSessionPlan, Rating,
+    RecommendedConfiguration, RetentionStats, ReviewInfo, ReviewPreview, SentenceLength,
+    SentenceSearchResults, SessionSkips, SessionStruggles, SinceReset, UpcomingReviewStats,
+    VocabularyRankPoint, Weapon, WeeklyDigest, XpBreakdown,
+    deck_selection::{DeckSelection, DeckSelectionEvent},
+    language_pack::{LanguageDataError, LoadedPackInfo},
+};
+#[cfg(feature = "movies")]
+use crate::{MovieQuiz, MovieStats};
+#[cfg(target_arch = "wasm32")]
+#[cfg(feature = "ai-grading")]
+use crate::{PendingGradeRequest, PendingGradesReport};
+
+/// The error type of every façade method
+#[derive(Debug, Clone, thiserror::Error, tsify::Tsify, Serialize, Deserialize)]
+#[tsify(into_wasm_abi, from_wasm_abi)]
+#[serde(tag = "type")]
+pub enum ApiError {
+    #[error("Storage error: {message}")]
+    Storage { message: String },
+
+    #[error("Sync error: {message}")]
+    Sync { message: String },
+
+    #[error("Language pack error: {message}")]
+    LanguagePack { message: String },
+
+    /// The event couldn't be created or added, e.g. because it reviews a card that isn't in
+    /// the deck
+    #[error("Invalid event: {message}")]
+    InvalidEvent { message: String },
+}
+
+impl ApiError {
+    fn sync(error: JsValue) -> Self {
+        ApiError::Sync {
+            message: error.as_string().unwrap_or_else(|| format!("{error:?}")),
+        }
+    }
+
+    fn invalid_event(error: impl std::fmt::Display) -> Self {
+        ApiError::InvalidEvent {
+            message: error.to_string(),
+        }
+    }
+}
+
+impl From<persistent::Error> for ApiError {
+    fn from(error: persistent::Error) -> Self {
+        ApiError::Storage {
+            message: format!("{error:?}"),
+        }
+    }
+}
+
+impl From<LanguageDataError> for ApiError {
+    fn from(error: LanguageDataError) -> Self {
+        ApiError::LanguagePack {
+            message: error.to_string(),
+        }
+    }
+}
+
+/// `Option`s from the event builders are `None` when the event wouldn't do anything
+fn event_or(event: Option<DeckEvent>, reason: &str) -> Result<DeckEvent, ApiError> {
+    event.ok_or_else(|| ApiError::invalid_event(reason))
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+impl Weapon {
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn sync_api(&self) -> SyncApi {
+        SyncApi {
+            weapon: self.clone(),
+        }
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn deck_api(&self) -> DeckApi {
+        DeckApi {
+            weapon: self.clone(),
+        }
+    }
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+pub struct SyncApi {
+    weapon: Weapon,
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+impl SyncApi {
+    /// Loads `stream_id` from OPFS, saves it back, and syncs it with Supabase if
+    /// `attempt_supabase` is set and there's an access token
+    #[cfg(target_arch = "wasm32")]
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub async fn sync(
+        &self,
+        stream_id: String,
+        access_token: Option<String>,
+        attempt_supabase: bool,
+        modifier: Option<ListenerKey>,
+    ) -> Result<(), ApiError> {
+        self.weapon
+            .sync(stream_id, access_token, attempt_supabase, modifier)
+            .await
+            .map_err(ApiError::sync)
+    }
+
+    /// Syncs every stream with Supabase. Does nothing when logged out.
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub async fn sync_with_supabase(
+        &self,
+        access_token: String,
+        modifier: Option<ListenerKey>,
+    ) -> Result<(), ApiError> {
+        self.weapon
+            .sync_with_supabase(access_token, modifier)
+            .await
+            .map_err(ApiError::sync)
+    }
+
+    /// What `sync_with_supabase` would transfer, without transferring anything. `None` when
+    /// logged out.
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub async fn preview_sync(
+        &self,
+        access_token: String,
+    ) -> Result<Option<SupabaseSyncPreview>, ApiError> {
+        self.weapon
+            .preview_sync(access_token)
+            .await
+            .map_err(ApiError::sync)
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub async fn load_from_local_storage(&self, stream_id: String) -> Result<(), ApiError> {
+        Ok(self.weapon.load_from_local_storage(stream_id).await?)
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub async fn take_background_sync_report(
+        &self,
+    ) -> Result<Option<BackgroundSyncReport>, ApiError> {
+        Ok(self.weapon.take_background_sync_report().await?)
+    }
+
+    /// See `Weapon::queue_pending_grade`
+    #[cfg(target_arch = "wasm32")]
+    #[cfg(feature = "ai-grading")]
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub async fn queue_pending_grade(
+        &self,
+        request: PendingGradeRequest,
+        timestamp_ms: f64,
+    ) -> Result<PendingGradesReport, ApiError> {
+        self.weapon
+            .queue_pending_grade(request, timestamp_ms)
+            .await
+            .map_err(ApiError::sync)
+    }
+
+    /// See `Weapon::retry_pending_grades`
+    #[cfg(target_arch = "wasm32")]
+    #[cfg(feature = "ai-grading")]
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub async fn retry_pending_grades(
+        &self,
+        access_token: Option<String>,
+        ignore_backoff: Option<bool>,
+    ) -> Result<PendingGradesReport, ApiError> {
+        self.weapon
+            .retry_pending_grades(access_token, ignore_backoff)
+            .await
+            .map_err(ApiError::sync)
+    }
+
+    /// Adds an event pushed from another device, as JSON
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn add_remote_event(
+        &self,
+        device_id: String,
+        stream_id: String,
+        event: String,
+    ) -> Result<(), ApiError> {
+        self.weapon
+            .add_remote_event(device_id, stream_id, event)
+            .map_err(ApiError::sync)
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn state(&self, target: SyncTarget) -> SyncState<String, String> {
+        self.weapon.get_sync_state(target)
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn earliest_unsynced_event(&self, target: SyncTarget) -> Option<EarliestUnsyncedEvent> {
+        self.weapon.get_timestamp_of_earliest_unsynced_event(target)
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn health(&self, target: SyncTarget) -> SyncHealth {
+        self.weapon.get_sync_health(target)
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn num_events_on_remote_as_of_last_sync(&self, target: SyncTarget) -> usize {
+        self.weapon.num_events_on_remote_as_of_last_sync(target)
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn device_progress(&self) -> Vec<DeviceProgress<String>> {
+        self.weapon.get_device_progress()
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn merge_device(&self, from_device_id: String) -> Result<usize, ApiError> {
+        self.weapon
+            .merge_device(from_device_id)
+            .map_err(ApiError::sync)
+    }
+
+    /// See `Weapon::archive_old_events`
+    #[cfg(target_arch = "wasm32")]
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub async fn archive_old_events(
+        &self,
+        stream_id: String,
+        older_than_days: u32,
+        access_token: Option<String>,
+    ) -> Result<usize, ApiError> {
+        self.weapon
+            .archive_old_events(stream_id, older_than_days, access_token)
+            .await
+            .map_err(ApiError::sync)
+    }
+
+    /// See `Weapon::load_full_history`
+    #[cfg(target_arch = "wasm32")]
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub async fn load_full_history(&self, stream_id: String) -> Result<usize, ApiError> {
+        self.weapon
+            .load_full_history(stream_id)
+            .await
+            .map_err(ApiError::sync)
+    }
+
+    /// See `Weapon::find_duplicate_events`
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn find_duplicate_events(
+        &self,
+        stream_id: String,
+    ) -> Result<Vec<DuplicateEvent<String>>, ApiError> {
+        self.weapon
+            .find_duplicate_events(stream_id)
+            .map_err(ApiError::sync)
+    }
+
+    /// See `Weapon::repair_duplicate_events`
+    #[cfg(target_arch = "wasm32")]
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub async fn repair_duplicate_events(
+        &self,
+        stream_id: String,
+    ) -> Result<Vec<DuplicateEvent<String>>, ApiError> {
+        self.weapon
+            .repair_duplicate_events(stream_id)
+            .await
+            .map_err(ApiError::sync)
+    }
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+pub struct DeckApi {
+    weapon: Weapon,
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+impl DeckApi {
+    /// Downloads (or loads from OPFS) the language pack for `course`, see
+    /// `Weapon::download_language_pack`
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub async fn language_pack(
+        &self,
+        course: Course,
+        on_progress: Option<js_sys::Function>,
+        signal: Option<web_sys::AbortSignal>,
+    ) -> Result<FetchedLanguagePack, ApiError> {
+        Ok(self
+            .weapon
+            .download_language_pack(course, on_progress, signal)
+            .await?)
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub async fn sideload_language_pack(
+        &self,
+        course: Course,
+        bytes: Vec<u8>,
+    ) -> Result<FetchedLanguagePack, ApiError> {
+        Ok(self
+            .weapon
+            .load_language_pack_from_bytes(course, bytes)
+            .await?)
+    }
+
+    /// See `Weapon::set_pack_channel`
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub async fn set_pack_channel(&self, channel: PackChannel) -> Result<(), ApiError> {
+        Ok(self.weapon.set_pack_channel(channel).await?)
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn pack_channel(&self) -> PackChannel {
+        self.weapon.get_pack_channel()
+    }
+
+    /// See `Weapon::pin_language_pack`
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub async fn pin_language_pack(
+        &self,
+        course: Course,
+        version: Option<String>,
+    ) -> Result<(), ApiError> {
+        Ok(self.weapon.pin_language_pack(course, version).await?)
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn loaded_pack_info(&self, course: Course) -> Option<LoadedPackInfo> {
+        self.weapon.get_loaded_pack_info(course)
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn selection(&self) -> Option<DeckSelection> {
+        self.weapon.get_deck_selection_state()
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn explanation_language(&self) -> Option<Language> {
+        self.weapon.get_explanation_language()
+    }
+
+    /// The deck of the current sub-profile for `course` as of the events loaded so far
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn deck(&self, language_pack: FetchedLanguagePack, course: Course) -> Deck {
+        let store = self.weapon.store.borrow();
+        let sub_profile = self.weapon.sub_profile.borrow();
+        if let Some(snapshot) = self
+            .weapon
+            .snapshots
+            .borrow()
+            .get(&self.weapon.reviews_stream_id())
+            && let Some(deck) = crate::deck_state_from_snapshot(
+                &store,
+                sub_profile.as_deref(),
+                &language_pack,
+                course,
+                snapshot,
+            )
+        {
+            return deck;
+        }
+        crate::deck_state(&store, sub_profile.as_deref(), language_pack, course)
+    }
+
+    /// See `Weapon::save_deck_snapshot`
+    #[cfg(target_arch = "wasm32")]
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub async fn save_snapshot(
+        &self,
+        language_pack: &FetchedLanguagePack,
+        course: Course,
+        access_token: Option<String>,
+    ) -> Result<usize, ApiError> {
+        self.weapon
+            .save_deck_snapshot(language_pack, course, access_token)
+            .await
+            .map_err(ApiError::sync)
+    }
+
+    /// See `Weapon::preview_namespace_deck`
+    #[cfg(target_arch = "wasm32")]
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub async fn preview_namespace_deck(
+        &self,
+        user_id: Option<String>,
+        language_pack: FetchedLanguagePack,
+        course: Course,
+    ) -> Result<Option<Deck>, ApiError> {
+        self.weapon
+            .preview_namespace_deck(user_id, language_pack, course)
+            .await
+            .map_err(|error| ApiError::Storage {
+                message: error.as_string().unwrap_or_else(|| format!("{error:?}")),
+            })
+    }
+
+    /// Adds an event made by `ChallengeApi` (or one of the `Deck` event builders)
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn add_event(&self, event: DeckEvent) -> Result<(), ApiError> {
+        self.weapon
+            .add_deck_event(event)
+            .map_err(|error| ApiError::invalid_event(format!("{error:?}")))
+    }
+
+    /// See `Weapon::submit_challenge_results`
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn submit_challenge_results(&self, batch: Vec<ChallengeResult>) -> Result<usize, ApiError> {
+        self.weapon
+            .submit_challenge_results(batch)
+            .map_err(|error| ApiError::invalid_event(format!("{error:?}")))
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn add_selection_event(&self, event: DeckSelectionEvent) -> Result<(), ApiError> {
+        self.weapon
+            .add_deck_selection_event(event)
+            .map_err(|error| ApiError::invalid_event(format!("{error:?}")))
+    }
+
+    /// See `Weapon::complete_onboarding`
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn complete_onboarding(
+        &self,
+        answers: OnboardingAnswers,
+    ) -> Result<RecommendedConfiguration, ApiError> {
+        self.weapon
+            .complete_onboarding(answers)
+            .map_err(|error| ApiError::invalid_event(format!("{error:?}")))
+    }
+
+    /// A review session over `deck` at `timestamp_ms`, following the user's settings: it leaves
+    /// out the challenge types banned at that time (see
+    /// `Weapon::get_active_banned_challenge_types`) and prefers sentences of their chosen length.
+    /// With `window_hours`, it reviews ahead like `ChallengeApi::ahead`.
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn review_session(
+        &self,
+        deck: &Deck,
+        timestamp_ms: f64,
+        window_hours: Option<f64>,
+    ) -> ChallengeApi {
+        let banned_challenge_types = self.weapon.get_active_banned_challenge_types(timestamp_ms);
+        let mut session = match window_hours {
+            Some(window_hours) => {
+                ChallengeApi::ahead(deck, banned_challenge_types, timestamp_ms, window_hours)
+            }
+            None => ChallengeApi::new(deck, banned_challenge_types, timestamp_ms),
+        };
+        session.set_sentence_length(self.weapon.get_sentence_length());
+        session
+    }
+
+    /// `Deck::add_card_options` with the challenge types banned at `timestamp_ms`
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn add_card_options(&self, deck: &Deck, timestamp_ms: f64) -> AddCardOptions {
+        deck.add_card_options(
+            self.weapon.get_active_banned_challenge_types(timestamp_ms),
+            timestamp_ms,
+        )
+    }
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+pub struct StatsApi {
+    deck: Rc<Deck>,
+    timestamp_ms: f64,
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+impl StatsApi {
+    /// Statistics for `deck` as of `timestamp_ms`. Like the deck, they don't update as events are
+    /// added or as time passes, so everything read from one `StatsApi` agrees.
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen(constructor))]
+    pub fn new(deck: &Deck, timestamp_ms: f64) -> Self {
+        Self {
+            deck: Rc::new(deck.clone()),
+            timestamp_ms,
+        }
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn xp(&self) -> f64 {
+        self.deck.get_xp()
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn xp_breakdown(&self, event: DeckEvent) -> Option<XpBreakdown> {
+        self.deck.get_xp_breakdown(event)
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn total_reviews(&self) -> u64 {
+        self.deck.get_total_reviews()
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn since_reset(&self) -> Option<SinceReset> {
+        self.deck.get_since_reset()
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn daily_streak(&self) -> u32 {
+        self.deck.get_daily_streak(self.timestamp_ms)
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn percent_of_words_known(&self) -> f64 {
+        self.deck.get_percent_of_words_known()
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn effective_vocabulary_rank(&self) -> u32 {
+        self.deck.get_effective_vocabulary_rank()
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn vocabulary_rank_history(&self) -> Vec<VocabularyRankPoint> {
+        self.deck.get_vocabulary_rank_history()
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn num_cards(&self) -> usize {
+        self.deck.num_cards()
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn cards_added_in_past_hours(&self, hours: f64) -> u32 {
+        self.deck
+            .get_cards_added_in_past_hours(hours, self.timestamp_ms)
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn past_week_challenge_average(&self) -> f64 {
+        self.deck.get_past_week_challenge_average()
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn upcoming_week_review_stats(&self) -> UpcomingReviewStats {
+        self.deck.get_upcoming_week_review_stats(self.timestamp_ms)
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn frequency_knowledge_chart_data(&self) -> Vec<FrequencyKnowledgePoint> {
+        self.deck.get_frequency_knowledge_chart_data()
+    }
+
+    #[cfg(feature = "movies")]
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn movie_stats(&self) -> Vec<MovieStats> {
+        self.deck.get_movie_stats()
+    }
+
+    /// See `Deck::get_movie_quiz`
+    #[cfg(feature = "movies")]
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn movie_quiz(&self, movie_id: String) -> Option<MovieQuiz> {
+        self.deck.get_movie_quiz(movie_id)
+    }
+
+    #[cfg(feature = "movies")]
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn movie_quiz_results(&self, quiz: &MovieQuiz, correct: u32) -> DeckEvent {
+        quiz.get_results_event(&self.deck, correct)
+    }
+
+    #[cfg(feature = "movies")]
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn movie_metadata(&self, movie_ids: Vec<String>) -> Vec<MovieMetadata> {
+        self.deck.get_movie_metadata(movie_ids)
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn leeches(&self) -> Vec<CardSummary> {
+        self.deck.get_leeches()
+    }
+
+    /// See `Deck::get_leeches_page`
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn leeches_page(&self, cursor: Option<String>, page_size: u32) -> CardSummaryPage {
+        self.deck.get_leeches_page(cursor, page_size)
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn favorites(&self) -> Vec<String> {
+        self.deck.get_favorites()
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn lookup_notebook(&self) -> Vec<LookedUpWord> {
+        self.deck.get_lookup_notebook(self.timestamp_ms)
+    }
+
+    /// See `Deck::get_content_reports`
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn content_reports(&self, statuses: Vec<ContentReportStatus>) -> ContentReportHistory {
+        self.deck.get_content_reports(statuses)
+    }
+
+    /// See `Deck::search_sentences`
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn search_sentences(
+        &self,
+        query: String,
+        page: u32,
+        page_size: u32,
+    ) -> SentenceSearchResults {
+        self.deck.search_sentences(query, page, page_size)
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn audio_feedback(&self) -> Vec<ProviderAudioFeedback> {
+        self.deck.get_audio_feedback()
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn pronunciation_weaknesses(&self) -> Vec<PronunciationWeakness> {
+        self.deck.get_pronunciation_weaknesses()
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn pronunciation_coverage(&self) -> Vec<PronunciationCoverage> {
+        self.deck.get_pronunciation_coverage()
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn fatigue_report(&self, utc_offset_minutes: i32) -> FatigueReport {
+        self.deck.get_fatigue_report(utc_offset_minutes)
+    }
+
+    /// See `Deck::get_account_sharing_hint`
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn account_sharing_hint(&self, utc_offset_minutes: i32) -> Option<AccountSharingHint> {
+        self.deck
+            .get_account_sharing_hint(self.timestamp_ms, utc_offset_minutes)
+    }
+
+    /// See `Deck::export_activity_json`
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn activity_json(&self, start_ms: f64, end_ms: f64, utc_offset_minutes: i32) -> String {
+        self.deck
+            .export_activity_json(start_ms, end_ms, utc_offset_minutes)
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn activity_ical(&self) -> String {
+        self.deck.export_activity_ical()
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn weekly_digest(&self, week_start_ms: f64) -> WeeklyDigest {
+        self.deck.get_weekly_digest(week_start_ms)
+    }
+
+    /// See `Deck::get_session_struggles`
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn session_struggles(&self, session_start_ms: f64) -> Option<SessionStruggles> {
+        self.deck.get_session_struggles(session_start_ms)
+    }
+
+    /// See `Deck::get_retention_stats`
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn retention_stats(&self, window_days: u32, timestamp_ms: f64) -> RetentionStats {
+        self.deck.get_retention_stats(window_days, timestamp_ms)
+    }
+
+    /// See `Deck::get_frequency_drift`
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn frequency_drift(&self, min_factor: f64) -> Vec<CardFrequencyDrift> {
+        self.deck.get_frequency_drift(min_factor)
+    }
+}
+
+/// Grading methods return the event to pass to `DeckApi::add_event`
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+pub struct ChallengeApi {
+    deck: Rc<Deck>,
+    review_info: ReviewInfo,
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+impl ChallengeApi {
+    /// A review session over the cards of `deck` due at `timestamp_ms`
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen(constructor))]
+    pub fn new(
+        deck: &Deck,
+        banned_challenge_types: Vec<ChallengeRequirements>,
+        timestamp_ms: f64,
+    ) -> Self {
+        Self {
+            review_info: deck.get_review_info(banned_challenge_types, timestamp_ms),
+            deck: Rc::new(deck.clone()),
+        }
+    }
+
+    /// Like `new`, but cards due within `window_hours` are offered once the due cards run out
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn ahead(
+        deck: &Deck,
+        banned_challenge_types: Vec<ChallengeRequirements>,
+        timestamp_ms: f64,
+        window_hours: f64,
+    ) -> Self {
+        Self {
+            review_info: deck.get_review_info_ahead(
+                banned_challenge_types,
+                timestamp_ms,
+                window_hours,
+            ),
+            deck: Rc::new(deck.clone()),
+        }
+    }
+
+    /// A hands-free session over the listening cards of `deck` due at `timestamp_ms`, see
+    /// `Deck::get_hands_free_review_info`
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn hands_free(deck: &Deck, timestamp_ms: f64) -> Self {
+        Self {
+            review_info: deck.get_hands_free_review_info(timestamp_ms),
+            deck: Rc::new(deck.clone()),
+        }
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn next_challenge(&self) -> Option<Challenge<String>> {
+        self.review_info.get_next_challenge(&self.deck)
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn next_hands_free_challenge(&self) -> Option<HandsFreeChallenge> {
+        self.review_info.get_next_hands_free_challenge(&self.deck)
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn challenge_errors(&self) -> Vec<ChallengeErrorReport> {
+        self.review_info.get_challenge_errors()
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn sentence_generation_request(&self) -> Option<GenerateSentenceRequest> {
+        self.review_info.get_sentence_generation_request(&self.deck)
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn set_practice_favorites(&mut self, practice_favorites: bool) {
+        self.review_info.set_practice_favorites(practice_favorites);
+    }
+
+    /// Debug mode: sentence challenges carry an `explanation` of why their sentence was picked
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn set_explain_sentence_choices(&mut self, explain_sentence_choices: bool) {
+        self.review_info
+            .set_explain_sentence_choices(explain_sentence_choices);
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn set_sentence_length(&mut self, sentence_length: SentenceLength) {
+        self.review_info.set_sentence_length(sentence_length);
+    }
+
+    /// Applies the "not now" skips the frontend keeps for the session, see `SessionSkips`
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn apply_session_skips(&mut self, skips: &SessionSkips, timestamp_ms: f64) {
+        self.review_info
+            .apply_session_skips(&self.deck, skips, timestamp_ms);
+    }
+
+    /// "Not now": skips `card` in `skips` and in this session's queue
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn skip_card(
+        &mut self,
+        skips: &mut SessionSkips,
+        card: CardIndicator<String>,
+        timestamp_ms: f64,
+    ) {
+        skips.skip(card, timestamp_ms);
+        self.apply_session_skips(skips, timestamp_ms);
+    }
+
+    /// Starts the session with up to `limit` cards forgotten lately, see
+    /// `ReviewInfo::start_with_warmup`
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn start_with_warmup(
+        &mut self,
+        banned_challenge_types: Vec<ChallengeRequirements>,
+        limit: usize,
+        timestamp_ms: f64,
+    ) {
+        self.review_info
+            .start_with_warmup(&self.deck, banned_challenge_types, limit, timestamp_ms);
+    }
+
+    /// Limits the session to what fits in `minutes`, see `ReviewInfo::start_quick_session`
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn start_quick_session(&mut self, minutes: f64, timestamp_ms: f64) -> QuickSessionPlan {
+        self.review_info
+            .start_quick_session(&self.deck, minutes, timestamp_ms)
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen(getter))]
+    pub fn due_count(&self) -> usize {
+        self.review_info.due_count()
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen(getter))]
+    pub fn ahead_count(&self) -> usize {
+        self.review_info.ahead_count()
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen(getter))]
+    pub fn future_count(&self) -> usize {
+        self.review_info.future_count()
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn is_ahead(&self, card: CardIndicator<String>) -> bool {
+        self.review_info.is_ahead(&self.deck, card)
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn is_warmup(&self, card: CardIndicator<String>) -> bool {
+        self.review_info.is_warmup(&self.deck, card)
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn review_card(
+        &self,
+        reviewed: CardIndicator<String>,
+        rating: Rating,
+    ) -> Result<DeckEvent, ApiError> {
+        event_or(
+            self.deck.review_card(reviewed, rating),
+            "the card isn't in the deck",
+        )
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn hands_free_review(
+        &self,
+        reviewed: CardIndicator<String>,
+        sentence: Option<String>,
+        understood: bool,
+    ) -> Result<DeckEvent, ApiError> {
+        event_or(
+            self.deck.hands_free_review(reviewed, sentence, understood),
+            "the card isn't in the deck",
+        )
+    }
+
+    /// See `Deck::preview_review`
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn preview_review(
+        &self,
+        reviewed: CardIndicator<String>,
+        rating: Rating,
+        timestamp_ms: f64,
+    ) -> Result<ReviewPreview, ApiError> {
+        self.deck
+            .preview_review(reviewed, rating, timestamp_ms)
+            .ok_or_else(|| ApiError::invalid_event("the card isn't in the deck"))
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn translate_sentence_perfect(
+        &self,
+        words_tapped: Vec<Lexeme<String>>,
+        challenge_sentence: String,
+        favorite_practice: Option<bool>,
+    ) -> Result<DeckEvent, ApiError> {
+        event_or(
+            self.deck.translate_sentence_perfect(
+                words_tapped,
+                challenge_sentence,
+                favorite_practice,
+            ),
+            "the sentence couldn't be graded",
+        )
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn translate_generated_sentence_perfect(
+        &self,
+        words_tapped: Vec<Lexeme<String>>,
+        challenge_sentence: String,
+        sentence_lexemes: Vec<Lexeme<String>>,
+    ) -> Result<DeckEvent, ApiError> {
+        event_or(
+            self.deck.translate_generated_sentence_perfect(
+                words_tapped,
+                challenge_sentence,
+                sentence_lexemes,
+            ),
+            "the sentence couldn't be graded",
+        )
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn translate_sentence_wrong(
+        &self,
+        challenge_sentence: String,
+        submission: String,
+        words_remembered: Vec<Lexeme<String>>,
+        words_forgotten: Vec<Lexeme<String>>,
+        words_tapped: Vec<Lexeme<String>>,
+        favorite_practice: Option<bool>,
+    ) -> Result<DeckEvent, ApiError> {
+        event_or(
+            self.deck.translate_sentence_wrong(
+                challenge_sentence,
+                submission,
+                words_remembered,
+                words_forgotten,
+                words_tapped,
+                favorite_practice,
+            ),
+            "the sentence couldn't be graded",
+        )
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn transcribe_sentence(
+        &self,
+        challenge: Vec<transcription_challenge::PartGraded>,
+        level: Option<transcription_challenge::DictationLevel>,
+        input_mode: Option<transcription_challenge::InputMode>,
+    ) -> Result<DeckEvent, ApiError> {
+        event_or(
+            self.deck.transcribe_sentence(challenge, level, input_mode),
+            "the transcription couldn't be graded",
+        )
+    }
+
+    /// See `Deck::grade_challenge`
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn grade_challenge(
+        &self,
+        challenge: Challenge<String>,
+        outcome: ChallengeOutcome,
+    ) -> Result<DeckEvent, ApiError> {
+        event_or(
+            self.deck.grade_challenge(challenge, outcome),
+            "the outcome doesn't fit the challenge, or its card isn't in the deck",
+        )
+    }
+
+    /// See `Deck::disambiguate_heteronym`
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn disambiguate_heteronym(
+        &self,
+        answer: Heteronym<String>,
+        picked: Heteronym<String>,
+    ) -> Result<DeckEvent, ApiError> {
+        event_or(
+            self.deck.disambiguate_heteronym(answer, picked),
+            "the card isn't in the deck",
+        )
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn favorite_sentence(
+        &self,
+        sentence: String,
+        favorite: bool,
+    ) -> Result<DeckEvent, ApiError> {
+        event_or(
+            self.deck.favorite_sentence(sentence, favorite),
+            "the sentence is already (un)favorited",
+        )
+    }
+
+    /// See `Deck::repeat_sentence`
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn repeat_sentence(
+        &self,
+        sentence: String,
+        remembered: bool,
+    ) -> Result<DeckEvent, ApiError> {
+        event_or(
+            self.deck.repeat_sentence(sentence, remembered),
+            "the sentence isn't being repeated",
+        )
+    }
+
+    /// See `Deck::warmup_review`
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn warmup_review(
+        &self,
+        reviewed: CardIndicator<String>,
+        remembered: bool,
+    ) -> Result<DeckEvent, ApiError> {
+        event_or(
+            self.deck.warmup_review(reviewed, remembered),
+            "the card wasn't forgotten lately",
+        )
+    }
+
+    /// To record when the user opens a word's details, see `Deck::get_lookup_notebook`
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn look_up(&self, lexeme: Lexeme<String>, sentence: String) -> Result<DeckEvent, ApiError> {
+        event_or(
+            self.deck.look_up(lexeme, sentence),
+            "the word isn't in the language pack",
+        )
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn report_audio_feedback(&self, audio: AudioRequest, feedback: AudioFeedback) -> DeckEvent {
+        self.deck.report_audio_feedback(audio, feedback)
+    }
+}
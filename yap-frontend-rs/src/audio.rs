@@ -1,9 +1,12 @@
+#[cfg(feature = "simulation")]
+use crate::Deck;
 use crate::{AudioRequest, TtsRequest, persistent, utils::hit_ai_server};
 use base64::Engine;
-use language_utils::TtsProvider;
+use language_utils::{LANGUAGES, TtsProvider};
 use opfs::{DirectoryHandle as _, FileHandle as _, WritableFileStream as _};
+#[cfg(feature = "simulation")]
 use std::collections::BTreeSet;
-use wasm_bindgen::JsValue;
+use wasm_bindgen::prelude::*;
 use xxhash_rust::const_xxh3::xxh3_64 as const_xxh3;
 
 #[derive(Clone)]
@@ -28,14 +31,74 @@ impl AudioCache {
         Ok(Self { audio_dir })
     }
 
+    /// `<hash>.<provider>-<language>-v<voice version>.mp3`. The voice version means audio from a
+    /// replaced voice is never served, and the metadata lets `remove_outdated` recognize it without
+    /// knowing what it was for.
     pub fn get_cache_filename(request: &TtsRequest, provider: &TtsProvider) -> String {
+        format!(
+            "{cache_key}.{provider}-{language}-v{version}.mp3",
+            cache_key = Self::cache_key(request, provider),
+            provider = provider.slug(),
+            language = request.language.iso_639_3(),
+            version = provider.voice_version(request.language),
+        )
+    }
+
+    fn cache_key(request: &TtsRequest, provider: &TtsProvider) -> u64 {
         let cache_text = format!(
             "{provider:?}:{text}:{language}",
             text = request.text,
             language = request.language
         );
-        let cache_key = const_xxh3(cache_text.as_bytes());
-        format!("{cache_key}.mp3")
+        const_xxh3(cache_text.as_bytes())
+    }
+
+    /// Removes the audio for `request` made with older voices, which `get_cache_filename` no
+    /// longer points to. Files that don't exist are skipped.
+    async fn remove_older_versions(&self, request: &TtsRequest, provider: &TtsProvider) {
+        let cache_key = Self::cache_key(request, provider);
+        let older_filenames = std::iter::once(format!("{cache_key}.mp3")).chain(
+            (1..provider.voice_version(request.language)).map(|version| {
+                format!(
+                    "{cache_key}.{provider}-{language}-v{version}.mp3",
+                    provider = provider.slug(),
+                    language = request.language.iso_639_3(),
+                )
+            }),
+        );
+        let mut audio_dir = self.audio_dir.clone();
+        for filename in older_filenames {
+            let _ = audio_dir.remove_entry(&filename).await;
+        }
+    }
+
+    /// Removes every file made with an older voice than its provider now uses, returning how many
+    /// there were
+    pub async fn remove_outdated(&mut self) -> Result<usize, JsValue> {
+        use futures::StreamExt;
+
+        let outdated = {
+            let mut entries = self.audio_dir.entries().await.map_err(|e| {
+                JsValue::from_str(&format!("Failed to read audio directory: {e:?}"))
+            })?;
+
+            let mut files = Vec::new();
+            while let Some(Ok((filename, _))) = entries.next().await {
+                if is_outdated(&filename) {
+                    files.push(filename);
+                }
+            }
+            files
+        };
+
+        for filename in &outdated {
+            log::info!("Removing audio from an old voice: {filename}");
+            if let Err(e) = self.audio_dir.remove_entry(filename).await {
+                log::info!("Failed to remove audio file {filename}: {e:?}");
+            }
+        }
+
+        Ok(outdated.len())
     }
 
     pub async fn get_cached(
@@ -75,6 +138,9 @@ impl AudioCache {
                     }
                 }
             }
+        } else {
+            // It may have been cached with an older voice, which is about to be replaced
+            self.remove_older_versions(request, provider).await;
         }
         None
     }
@@ -129,16 +195,18 @@ impl AudioCache {
             return Ok(cached_bytes);
         }
 
-        let endpoint = match provider {
-            TtsProvider::Google => "/tts/google",
-            TtsProvider::ElevenLabs => "/tts",
+        // The server only synthesizes audio for signed-in users, see `anonymous`
+        let Some(access_token) = access_token else {
+            return Err(JsValue::from_str(
+                "Audio needs an account, use speak_on_device instead",
+            ));
         };
 
         let response = hit_ai_server(
             fetch_happen::Method::POST,
-            endpoint,
+            language_utils::backend_routes::tts_path(provider),
             Some(request),
-            access_token,
+            Some(access_token),
         )
         .await
         .map_err(|e| JsValue::from_str(&format!("Request error: {e:?}")))?;
@@ -165,6 +233,7 @@ impl AudioCache {
         Ok(bytes)
     }
 
+    #[cfg(feature = "simulation")]
     pub async fn cleanup_except(
         &mut self,
         keep_filenames: BTreeSet<String>,
@@ -200,6 +269,40 @@ impl AudioCache {
     }
 }
 
+/// Whether `filename` is audio from an older voice than its provider uses now. Files from before
+/// the cache recorded voices have no metadata, and count as outdated.
+fn is_outdated(filename: &str) -> bool {
+    let Some(stem) = filename.strip_suffix(".mp3") else {
+        return false;
+    };
+    let Some((_, metadata)) = stem.split_once('.') else {
+        return true;
+    };
+    let mut parts = metadata.split('-');
+    let (Some(provider), Some(language), Some(version), None) =
+        (parts.next(), parts.next(), parts.next(), parts.next())
+    else {
+        return true;
+    };
+    let Some(provider) = [TtsProvider::ElevenLabs, TtsProvider::Google]
+        .into_iter()
+        .find(|candidate| candidate.slug() == provider)
+    else {
+        return true;
+    };
+    let Some(language) = LANGUAGES
+        .iter()
+        .copied()
+        .find(|candidate| candidate.iso_639_3() == language)
+    else {
+        return true;
+    };
+    version
+        .strip_prefix('v')
+        .and_then(|version| version.parse::<u32>().ok())
+        .is_none_or(|version| version < provider.voice_version(language))
+}
+
 fn is_valid_mp3_data(bytes: &[u8]) -> bool {
     if bytes.len() < 2 {
         return false;
@@ -208,3 +311,86 @@ fn is_valid_mp3_data(bytes: &[u8]) -> bool {
     // Valid MP3 files either start with an ID3 tag or an MPEG frame sync (0xFFF)
     bytes.starts_with(b"ID3") || (bytes[0] == 0xFF && bytes[1] & 0xE0 == 0xE0)
 }
+
+/// The `AudioCache` as the deck sees it while prefetching challenge audio
+#[cfg(feature = "simulation")]
+#[derive(Clone)]
+struct ChallengeAudioStore {
+    audio_cache: AudioCache,
+    access_token: Option<String>,
+    abort_signal: Option<web_sys::AbortSignal>,
+}
+
+#[cfg(feature = "simulation")]
+impl yap_core::AudioStore for ChallengeAudioStore {
+    type Error = JsValue;
+
+    fn cache_filename(&self, request: &AudioRequest) -> String {
+        AudioCache::get_cache_filename(&request.request, &request.provider)
+    }
+
+    async fn fetch_and_cache(&self, request: &AudioRequest) -> Result<(), JsValue> {
+        self.audio_cache
+            .fetch_and_cache(request, self.access_token.as_ref())
+            .await
+            .map(|_| ())
+    }
+
+    async fn cleanup_except(&mut self, keep_filenames: BTreeSet<String>) -> Result<(), JsValue> {
+        self.audio_cache.cleanup_except(keep_filenames).await
+    }
+
+    async fn pause(&self) {
+        // Sleep for 1 second using JavaScript's setTimeout via JsFuture
+        let promise = js_sys::Promise::new(&mut |resolve, _| {
+            web_sys::window()
+                .unwrap()
+                .set_timeout_with_callback_and_timeout_and_arguments_0(&resolve, 1000)
+                .unwrap();
+        });
+        wasm_bindgen_futures::JsFuture::from(promise).await.unwrap();
+    }
+
+    fn cancelled(&self) -> bool {
+        self.abort_signal
+            .as_ref()
+            .is_some_and(|signal| signal.aborted())
+    }
+}
+
+/// Downloads the audio for the challenges the user is likely to see in the couple of days after
+/// `timestamp_ms`, and removes any other cached audio. Audio from voices that have since been
+/// replaced is removed up front, in case prefetching is cancelled before that cleanup.
+#[cfg(feature = "simulation")]
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+pub async fn cache_challenge_audio(
+    deck: &Deck,
+    access_token: Option<String>,
+    abort_signal: Option<web_sys::AbortSignal>,
+    timestamp_ms: f64,
+) {
+    // There's nothing to download without an account, and keeping the cached audio lets it play
+    if access_token.is_none() {
+        return;
+    }
+    let mut audio_cache = match AudioCache::new().await {
+        Ok(cache) => cache,
+        Err(e) => {
+            log::error!("Failed to create audio cache: {e:?}");
+            return;
+        }
+    };
+    // Replaced below if the challenges still need it
+    if let Err(e) = audio_cache.remove_outdated().await {
+        log::error!("Failed to remove outdated audio: {e:?}");
+    }
+    deck.cache_challenge_audio(
+        ChallengeAudioStore {
+            audio_cache,
+            access_token,
+            abort_signal,
+        },
+        timestamp_ms,
+    )
+    .await;
+}
@@ -0,0 +1,394 @@
+//! Grading translations and transcriptions with the LLM on the server, falling back to
+//! `heuristic_transcription_grade` when it can't be reached. Only built with the `ai-grading`
+//! feature, along with the queue of submissions waiting to be graded (`pending_grades`).
+
+use language_utils::autograde;
+use language_utils::backend_routes;
+use language_utils::local_grading::{AcceptedAnswers, grade_translation_locally};
+use language_utils::text_cleanup::normalize_for_grading;
+use language_utils::transcription_challenge;
+use language_utils::{Course, Language, Lexeme};
+use wasm_bindgen::prelude::*;
+
+use crate::{NativeTranslations, backend, language_pack};
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+pub async fn autograde_translation(
+    challenge_sentence: String,
+    user_sentence: String,
+    native_translations: Vec<String>,
+    primary_expression: Lexeme<String>,
+    lexemes: Vec<Lexeme<String>>,
+    access_token: Option<String>,
+    course: Course,
+    explanation_language: Option<Language>,
+    mistake_history: Option<autograde::MistakeDigest>,
+    other_native_translations: Option<Vec<NativeTranslations>>,
+) -> Result<autograde::AutoGradeTranslationResponse, JsValue> {
+    // Check if the user's translation matches any of the acceptable translations, in any of the
+    // languages they speak (see `Weapon::get_native_translations`)
+    let other_native_translations = other_native_translations.unwrap_or_default();
+    let is_perfect = std::iter::once((course.native_language, native_translations.as_slice()))
+        .chain(
+            other_native_translations
+                .iter()
+                .map(|other| (other.native_language, other.translations.as_slice())),
+        )
+        .any(|(language, translations)| {
+            let spelling_variants = language_pack::spelling_variants(language);
+            let normalized_user =
+                normalize_for_grading(&user_sentence, language, spelling_variants.as_deref());
+            translations.iter().any(|translation| {
+                normalize_for_grading(translation, language, spelling_variants.as_deref())
+                    == normalized_user
+            })
+        });
+
+    if is_perfect {
+        // Skip server call and return perfect response
+        return Ok(autograde::AutoGradeTranslationResponse {
+            primary_expression_status: autograde::Remembered::Remembered,
+            expressions_remembered: lexemes.clone(),
+            expressions_forgot: vec![],
+            encouragement: Some("Perfect! You translated it correctly!".to_string()),
+            explanation: None,
+            validation: transcription_challenge::GradeValidation::Valid,
+        });
+    }
+
+    // Answers from users without an account never go to the LLM, see `anonymous`
+    let Some(access_token) = access_token else {
+        return Ok(grade_anonymous_translation(
+            &user_sentence,
+            course,
+            &native_translations,
+            &other_native_translations,
+            primary_expression,
+            lexemes,
+        ));
+    };
+
+    let request = autograde::AutoGradeTranslationRequest {
+        challenge_sentence,
+        user_sentence: user_sentence.clone(),
+        primary_expression: primary_expression.clone(),
+        lexemes: lexemes.clone(),
+        course,
+        explanation_language,
+        mistake_history,
+        other_native_languages: other_native_translations
+            .iter()
+            .map(|other| other.native_language)
+            .collect(),
+    };
+
+    let response = autograde_translation_on_server(request, Some(&access_token)).await?;
+    if response.validation == transcription_challenge::GradeValidation::Invalid {
+        // The LLM's grade contradicts itself even after asking again, so fall back to comparing
+        // the answer with the known translations
+        return Ok(grade_anonymous_translation(
+            &user_sentence,
+            course,
+            &native_translations,
+            &other_native_translations,
+            primary_expression,
+            lexemes,
+        ));
+    }
+    Ok(response)
+}
+
+/// The part of `autograde_translation` that needs the network, shared with the retries in
+/// `pending_grades`
+pub(crate) async fn autograde_translation_on_server(
+    request: autograde::AutoGradeTranslationRequest,
+    access_token: Option<&String>,
+) -> Result<autograde::AutoGradeTranslationResponse, backend::BackendError> {
+    let mut response =
+        backend::call::<backend_routes::AutogradeTranslation>(&request, access_token).await?;
+    let primary_expression = request.primary_expression;
+
+    // make sure the primary expression is in the appropriate array:
+    if response.primary_expression_status == autograde::Remembered::Forgot
+        && !response.expressions_forgot.contains(&primary_expression)
+    {
+        response.expressions_forgot.push(primary_expression);
+    } else if response.primary_expression_status == autograde::Remembered::Remembered
+        && !response
+            .expressions_remembered
+            .contains(&primary_expression)
+    {
+        response.expressions_remembered.push(primary_expression);
+    }
+
+    log::info!("Autograde response: {response:#?}");
+
+    Ok(response)
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+pub async fn autograde_transcription(
+    submission: Vec<transcription_challenge::PartSubmitted>,
+    access_token: Option<String>,
+    course: Course,
+    explanation_language: Option<Language>,
+    mistake_history: Option<autograde::MistakeDigest>,
+) -> transcription_challenge::Grade {
+    if access_token.is_none() {
+        // Nothing went wrong, the LLM just isn't used without an account (see `anonymous`)
+        return transcription_challenge::Grade {
+            autograding_error: None,
+            ..heuristic_transcription_grade(submission, course)
+        };
+    }
+
+    let _autograde_error = match autograde_transcription_llm(
+        submission.clone(),
+        access_token,
+        course,
+        explanation_language,
+        mistake_history,
+    )
+    .await
+    {
+        Ok(grade) if grade.validation != transcription_challenge::GradeValidation::Invalid => {
+            return grade;
+        }
+        Ok(_) => Some(JsValue::from_str("The LLM returned malformed grades")),
+        Err(e) => Some(e),
+    };
+
+    heuristic_transcription_grade(submission, course)
+}
+
+/// Compares each word with what was said, for when the LLM can't grade a transcription
+pub(crate) fn heuristic_transcription_grade(
+    submission: Vec<transcription_challenge::PartSubmitted>,
+    course: Course,
+) -> transcription_challenge::Grade {
+    let spelling_variants = language_pack::spelling_variants(course.target_language);
+    let results = submission
+        .into_iter()
+        .map(|part| match part {
+            transcription_challenge::PartSubmitted::AskedToTranscribe { parts, submission } => {
+                let submitted_words = submission.split_whitespace().collect::<Vec<_>>();
+                if submitted_words.len() != parts.len() {
+                    return transcription_challenge::PartGraded::AskedToTranscribe {
+                        parts: parts
+                            .iter()
+                            .map(|part| transcription_challenge::PartGradedPart {
+                                heard: part.clone(),
+                                grade: transcription_challenge::WordGrade::Missed {},
+                            })
+                            .collect(),
+                        submission: submission.clone(),
+                    };
+                }
+
+                transcription_challenge::PartGraded::AskedToTranscribe {
+                    parts: parts
+                        .iter()
+                        .zip(submitted_words.iter())
+                        .map(|(part, &submission)| {
+                            let part_text = normalize_for_grading(
+                                &part.text,
+                                course.target_language,
+                                spelling_variants.as_deref(),
+                            )
+                            .trim()
+                            .to_string();
+                            let submission = normalize_for_grading(
+                                submission,
+                                course.target_language,
+                                spelling_variants.as_deref(),
+                            )
+                            .trim()
+                            .to_string();
+                            if part_text == submission {
+                                transcription_challenge::PartGradedPart {
+                                    heard: part.clone(),
+                                    grade: transcription_challenge::WordGrade::Perfect {
+                                        wrote: Some(submission.to_string()),
+                                    },
+                                }
+                            } else if remove_accents(&part_text) == remove_accents(&submission) {
+                                transcription_challenge::PartGradedPart {
+                                    heard: part.clone(),
+                                    grade: transcription_challenge::WordGrade::CorrectWithTypo {
+                                        wrote: Some(submission.to_string()),
+                                    },
+                                }
+                            // todo: check if word entered is in the set of homophones
+                            // and if so, grade is as correct PhoneticallyIdenticalButContextuallyIncorrect
+                            } else {
+                                transcription_challenge::PartGradedPart {
+                                    heard: part.clone(),
+                                    grade: transcription_challenge::WordGrade::Incorrect {
+                                        wrote: Some(submission.to_string()),
+                                    },
+                                }
+                            }
+                        })
+                        .collect(),
+                    submission: submission.clone(),
+                }
+            }
+            transcription_challenge::PartSubmitted::Provided { part } => {
+                transcription_challenge::PartGraded::Provided { part }
+            }
+        })
+        .collect();
+
+    transcription_challenge::Grade {
+        encouragement: None,
+        explanation: None,
+        results,
+        compare: Vec::new(),
+        autograding_error: Some("The LLM was not able to grade this transcription".to_string()),
+        validation: transcription_challenge::GradeValidation::Heuristic,
+    }
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+pub async fn autograde_transcription_llm(
+    submission: Vec<transcription_challenge::PartSubmitted>,
+    access_token: Option<String>,
+    course: Course,
+    explanation_language: Option<Language>,
+    mistake_history: Option<autograde::MistakeDigest>,
+) -> Result<transcription_challenge::Grade, JsValue> {
+    Ok(autograde_transcription_with_server(
+        submission,
+        access_token.as_ref(),
+        course,
+        explanation_language,
+        mistake_history,
+    )
+    .await?)
+}
+
+/// `autograde_transcription_llm`, shared with the retries in `pending_grades`
+pub(crate) async fn autograde_transcription_with_server(
+    submission: Vec<transcription_challenge::PartSubmitted>,
+    access_token: Option<&String>,
+    course: Course,
+    explanation_language: Option<Language>,
+    mistake_history: Option<autograde::MistakeDigest>,
+) -> Result<transcription_challenge::Grade, backend::BackendError> {
+    // Check if all answers are exactly correct (case-insensitive)
+    let spelling_variants = language_pack::spelling_variants(course.target_language);
+    let all_correct = submission.iter().all(|part| match part {
+        transcription_challenge::PartSubmitted::AskedToTranscribe { parts, submission } => {
+            let submission = normalize_for_grading(
+                submission.trim(),
+                course.target_language,
+                spelling_variants.as_deref(),
+            );
+            let parts = parts
+                .iter()
+                .map(|part| {
+                    format!(
+                        "{text}{whitespace}",
+                        text = normalize_for_grading(
+                            &part.text,
+                            course.target_language,
+                            spelling_variants.as_deref()
+                        ),
+                        whitespace = part.whitespace
+                    )
+                })
+                .collect::<Vec<_>>();
+            submission.trim() == parts.join("").trim()
+        }
+        transcription_challenge::PartSubmitted::Provided { .. } => true,
+    });
+    if all_correct {
+        // Skip server call and return perfect results
+        let results = submission
+            .into_iter()
+            .map(|part| match part {
+                transcription_challenge::PartSubmitted::AskedToTranscribe { parts, submission } => {
+                    let parts = parts
+                        .iter()
+                        .map(|part| transcription_challenge::PartGradedPart {
+                            heard: part.clone(),
+                            grade: transcription_challenge::WordGrade::Perfect {
+                                wrote: Some(part.text.clone()),
+                            },
+                        })
+                        .collect();
+                    transcription_challenge::PartGraded::AskedToTranscribe {
+                        parts,
+                        submission: submission.clone(),
+                    }
+                }
+                transcription_challenge::PartSubmitted::Provided { part } => {
+                    transcription_challenge::PartGraded::Provided { part }
+                }
+            })
+            .collect();
+
+        return Ok(transcription_challenge::Grade {
+            encouragement: Some("Perfect! You transcribed everything correctly!".to_string()),
+            explanation: None,
+            results,
+            compare: Vec::new(),
+            autograding_error: None,
+            validation: transcription_challenge::GradeValidation::Valid,
+        });
+    }
+
+    let request = autograde::AutoGradeTranscriptionRequest {
+        submission,
+        course,
+        explanation_language,
+        mistake_history,
+    };
+
+    backend::call::<backend_routes::AutogradeTranscription>(&request, access_token).await
+}
+
+fn remove_accents(s: &str) -> String {
+    use unicode_normalization::UnicodeNormalization;
+
+    s.nfd()
+        .filter(|c| !unicode_normalization::char::is_combining_mark(*c))
+        .collect()
+}
+
+/// `autograde_translation` for anonymous users, against the sentence's translations into each of
+/// the languages they speak. Also used when the LLM's grade can't be trusted.
+fn grade_anonymous_translation(
+    user_sentence: &str,
+    course: Course,
+    native_translations: &[String],
+    other_native_translations: &[NativeTranslations],
+    primary_expression: Lexeme<String>,
+    lexemes: Vec<Lexeme<String>>,
+) -> autograde::AutoGradeTranslationResponse {
+    let languages = std::iter::once((course.native_language, native_translations))
+        .chain(
+            other_native_translations
+                .iter()
+                .map(|other| (other.native_language, other.translations.as_slice())),
+        )
+        .map(|(language, translations)| {
+            (
+                language,
+                language_pack::spelling_variants(language),
+                translations,
+            )
+        })
+        .collect::<Vec<_>>();
+    let accepted = languages
+        .iter()
+        .map(
+            |(language, spelling_variants, translations)| AcceptedAnswers {
+                language: *language,
+                spelling_variants: spelling_variants.as_deref(),
+                translations,
+            },
+        )
+        .collect::<Vec<_>>();
+    grade_translation_locally(user_sentence, &accepted, primary_expression, lexemes)
+}
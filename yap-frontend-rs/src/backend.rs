@@ -0,0 +1,137 @@
+//! Typed calls to the yap-ai-backend's JSON routes, see `language_utils::backend_routes`. The
+//! routes that respond with audio or language packs still go through `hit_ai_server` directly.
+//!
+//! Errors come with an `ErrorEnvelope` saying whether to retry. `call` retries once itself if the
+//! wait is short, and longer waits are left to the caller (see `PendingGrade::failed`).
+
+use language_utils::backend_routes::{ErrorEnvelope, Route, RouteMethod, query_string};
+use wasm_bindgen::JsValue;
+
+use crate::utils::hit_ai_server;
+
+/// How long to wait before retrying a retryable error the backend didn't give a wait for
+#[cfg(target_arch = "wasm32")]
+const DEFAULT_RETRY_SECONDS: u64 = 1;
+/// `call` leaves errors with longer waits than this to the caller
+#[cfg(target_arch = "wasm32")]
+const MAX_INLINE_RETRY_SECONDS: u64 = 5;
+
+/// Why a call to the backend failed
+#[derive(Debug)]
+pub(crate) enum BackendError {
+    /// The backend couldn't be reached
+    Request(fetch_happen::Error),
+    /// The backend responded with an error status. Responses without an envelope (e.g. from a
+    /// proxy) get one from `ErrorEnvelope::from_status`.
+    Status {
+        status: u16,
+        envelope: ErrorEnvelope,
+    },
+    /// The response wasn't what the route returns
+    Parse(fetch_happen::Error),
+}
+
+impl std::fmt::Display for BackendError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BackendError::Request(e) => write!(f, "Request error: {e:?}"),
+            BackendError::Status { status, envelope } => {
+                write!(f, "HTTP error: {status} ({})", envelope.message)
+            }
+            BackendError::Parse(e) => write!(f, "Response parsing error: {e:?}"),
+        }
+    }
+}
+
+impl BackendError {
+    /// Whether the same call may succeed later
+    pub(crate) fn is_retryable(&self) -> bool {
+        match self {
+            BackendError::Request(_) => true,
+            BackendError::Status { envelope, .. } => envelope.retryable,
+            BackendError::Parse(_) => false,
+        }
+    }
+
+    /// How many seconds the backend asked us to wait before retrying
+    pub(crate) fn retry_after(&self) -> Option<u64> {
+        match self {
+            BackendError::Status { envelope, .. } => envelope.retry_after,
+            BackendError::Request(_) | BackendError::Parse(_) => None,
+        }
+    }
+}
+
+impl From<BackendError> for JsValue {
+    fn from(error: BackendError) -> Self {
+        JsValue::from_str(&error.to_string())
+    }
+}
+
+/// Calls `R` with `request`, retrying once if the backend says to retry soon. Logged-out calls send
+/// a dummy token, see `hit_ai_server`.
+pub(crate) async fn call<R: Route>(
+    request: &R::Request,
+    access_token: Option<&String>,
+) -> Result<R::Response, BackendError> {
+    let result = call_once::<R>(request, access_token).await;
+    #[cfg(target_arch = "wasm32")]
+    if let Err(error) = &result
+        && error.is_retryable()
+    {
+        let wait = error.retry_after().unwrap_or(DEFAULT_RETRY_SECONDS);
+        if wait <= MAX_INLINE_RETRY_SECONDS {
+            log::info!("Retrying {} in {wait}s: {error}", R::PATH);
+            crate::utils::sleep(wait * 1000).await;
+            return call_once::<R>(request, access_token).await;
+        }
+    }
+    result
+}
+
+async fn call_once<R: Route>(
+    request: &R::Request,
+    access_token: Option<&String>,
+) -> Result<R::Response, BackendError> {
+    let response = match R::METHOD {
+        RouteMethod::Get => {
+            hit_ai_server(
+                fetch_happen::Method::GET,
+                &format!("{}{}", R::PATH, query_string(request)),
+                None::<()>,
+                access_token,
+            )
+            .await
+        }
+        RouteMethod::Post => {
+            hit_ai_server(
+                fetch_happen::Method::POST,
+                R::PATH,
+                Some(request),
+                access_token,
+            )
+            .await
+        }
+        RouteMethod::Patch => {
+            hit_ai_server(
+                fetch_happen::Method::PATCH,
+                R::PATH,
+                Some(request),
+                access_token,
+            )
+            .await
+        }
+    }
+    .map_err(BackendError::Request)?;
+
+    if !response.ok() {
+        let status = response.status();
+        let envelope = response
+            .json()
+            .await
+            .unwrap_or_else(|_| ErrorEnvelope::from_status(status));
+        return Err(BackendError::Status { status, envelope });
+    }
+
+    response.json().await.map_err(BackendError::Parse)
+}
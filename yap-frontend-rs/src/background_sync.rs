@@ -0,0 +1,148 @@
+//! A minimal sync entry point for service workers (e.g. the Background Sync API's `sync` event).
+//!
+//! Unlike `Weapon`, this never loads language packs, imports logged-out data or writes the event
+//! logs. It reads the logs from OPFS, uploads whatever the server doesn't have yet, and leaves a
+//! report in OPFS for the main thread to pick up with `Weapon::take_background_sync_report`.
+
+use std::cell::RefCell;
+
+use chrono::{DateTime, Utc};
+use opfs::{DirectoryHandle as _, FileHandle as _, WritableFileStream as _, persistent};
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::prelude::*;
+use weapon::data_model::EventStore;
+
+use yap_core::{StreamId, sub_profiles};
+
+use crate::{directories, supabase};
+
+const REPORT_FILE_NAME: &str = "background-sync-report";
+
+/// What the last background sync did
+#[derive(Debug, Clone, Serialize, Deserialize, tsify::Tsify)]
+#[tsify(into_wasm_abi, from_wasm_abi)]
+pub struct BackgroundSyncReport {
+    pub finished_at: DateTime<Utc>,
+    pub uploaded_to_supabase: usize,
+    pub error: Option<String>,
+}
+
+/// Pushes any events that haven't been uploaded yet. The report is also saved to OPFS, so it
+/// doesn't matter whether the caller is still around to receive it.
+#[wasm_bindgen]
+pub async fn background_sync(
+    user_id: String,
+    access_token: String,
+) -> Result<BackgroundSyncReport, JsValue> {
+    #[allow(clippy::borrow_interior_mutable_const)]
+    *crate::LOGGER;
+
+    let user_id = Some(user_id);
+    let directories = directories::get_directories(&user_id).await?;
+
+    let result = push(&directories, user_id.as_ref().unwrap(), &access_token).await;
+    let report = BackgroundSyncReport {
+        finished_at: Utc::now(),
+        uploaded_to_supabase: *result.as_ref().unwrap_or(&0),
+        error: result
+            .err()
+            .map(|e| e.as_string().unwrap_or_else(|| format!("{e:?}"))),
+    };
+
+    let json = serde_json::to_vec(&report).map_err(|e| JsValue::from_str(&e.to_string()))?;
+    let mut file_handle = directories
+        .weapon_directory_handle
+        .get_file_handle_with_options(
+            REPORT_FILE_NAME,
+            &opfs::GetFileHandleOptions { create: true },
+        )
+        .await?;
+    let mut writable = file_handle
+        .create_writable_with_options(&opfs::CreateWritableOptions {
+            keep_existing_data: false,
+        })
+        .await?;
+    writable.write_at_cursor_pos(json).await?;
+    writable.close().await?;
+
+    Ok(report)
+}
+
+async fn push(
+    directories: &directories::Directories,
+    user_id: &str,
+    access_token: &str,
+) -> Result<usize, JsValue> {
+    let store = RefCell::new(crate::new_event_store());
+
+    EventStore::load_from_local_storage(
+        &store,
+        &directories.current_user_directory_handle,
+        StreamId::SUB_PROFILES.into(),
+        None,
+    )
+    .await?;
+    let sub_profile_ids = crate::sub_profiles_state(&store.borrow())
+        .list()
+        .into_iter()
+        .map(|sub_profile| Some(sub_profile.id));
+
+    for sub_profile in std::iter::once(None).chain(sub_profile_ids) {
+        for stream_id in [
+            sub_profiles::reviews_stream(sub_profile.as_deref()).into(),
+            sub_profiles::deck_selection_stream(sub_profile.as_deref()).into(),
+        ] {
+            EventStore::load_from_local_storage(
+                &store,
+                &directories.current_user_directory_handle,
+                stream_id,
+                None,
+            )
+            .await?;
+        }
+    }
+
+    EventStore::push_to_supabase(&store, access_token, supabase::supabase_config(), user_id).await
+}
+
+/// Reads the report left by the last `background_sync`, if there is one
+pub(crate) async fn read_report(
+    weapon_directory: &persistent::DirectoryHandle,
+) -> Result<Option<BackgroundSyncReport>, persistent::Error> {
+    let Ok(file_handle) = weapon_directory
+        .get_file_handle_with_options(
+            REPORT_FILE_NAME,
+            &opfs::GetFileHandleOptions { create: false },
+        )
+        .await
+    else {
+        return Ok(None);
+    };
+    let bytes = file_handle.read().await?;
+
+    Ok(serde_json::from_slice(&bytes)
+        .inspect_err(|e| log::error!("Background sync report was invalid: {e:?}"))
+        .ok())
+}
+
+/// Reads and removes the report left by the last `background_sync`, if there is one
+pub(crate) async fn take_report(
+    weapon_directory: &persistent::DirectoryHandle,
+) -> Result<Option<BackgroundSyncReport>, persistent::Error> {
+    let report = read_report(weapon_directory).await?;
+    // Invalid reports are removed too, so they aren't read again
+    let exists = weapon_directory
+        .get_file_handle_with_options(
+            REPORT_FILE_NAME,
+            &opfs::GetFileHandleOptions { create: false },
+        )
+        .await
+        .is_ok();
+    if exists {
+        weapon_directory
+            .clone()
+            .remove_entry(REPORT_FILE_NAME)
+            .await?;
+    }
+    Ok(report)
+}
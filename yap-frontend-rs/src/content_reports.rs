@@ -0,0 +1,37 @@
+//! Uploading the corrections recorded with `Deck::report_content`, and getting back what the
+//! backend made of them (see `language_utils::content_report`).
+
+use language_utils::backend_routes::{GetContentReports, SubmitContentReport};
+use language_utils::content_report::{
+    ContentReport, ContentReportStatus, SubmitContentReportRequest,
+};
+use wasm_bindgen::prelude::*;
+
+use crate::{Weapon, backend};
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+impl Weapon {
+    /// Uploads a report from a `ReportContent` event. Uploading it again is harmless, so retry
+    /// any report `Deck::get_content_reports` lists without a status.
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub async fn submit_content_report(
+        &self,
+        report: ContentReport,
+        access_token: Option<String>,
+    ) -> Result<ContentReportStatus, JsValue> {
+        Ok(backend::call::<SubmitContentReport>(
+            &SubmitContentReportRequest { report },
+            access_token.as_ref(),
+        )
+        .await?)
+    }
+
+    /// The status of every report the user uploaded, for `Deck::get_content_reports`
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub async fn get_content_report_statuses(
+        &self,
+        access_token: String,
+    ) -> Result<Vec<ContentReportStatus>, JsValue> {
+        Ok(backend::call::<GetContentReports>(&(), Some(&access_token)).await?)
+    }
+}
@@ -0,0 +1,205 @@
+//! Recording panics as `CrashReport`s. The panic hook saves a report with the panic message,
+//! backtrace and last log entries to OPFS, and if the user opted in to sharing them
+//! (`settings::SHARE_CRASH_REPORTS`), `Weapon::submit_crash_reports` uploads them the next time the
+//! app starts.
+
+use std::sync::Once;
+
+use chrono::Utc;
+use futures::StreamExt as _;
+use language_utils::backend_routes::SubmitCrashReport;
+use language_utils::crash_report::{CrashLogEntry, CrashReport, SubmitCrashReportRequest};
+use opfs::{DirectoryHandle as _, FileHandle as _, persistent};
+use wasm_bindgen::prelude::*;
+
+use crate::{Weapon, backend, diagnostics};
+
+/// How many log entries leading up to a panic are kept in its report
+const LOG_ENTRIES_PER_REPORT: usize = 100;
+
+/// Older reports are removed when there are more than this, so they don't pile up for users who
+/// haven't opted in
+const MAX_SAVED_REPORTS: usize = 10;
+
+/// Under the weapon directory, with a file per report
+const CRASH_REPORTS_DIRECTORY: &str = "crash-reports";
+
+/// Writes a file to `.weapon/crash-reports` (see `directories::get_directories`) without going
+/// back into wasm. The module aborts right after the panic hook returns, so a Rust future writing
+/// the report might never be polled.
+const SAVE_REPORT_JS: &str = r#"
+return navigator.storage.getDirectory()
+    .then(root => root.getDirectoryHandle(".weapon", { create: true }))
+    .then(weapon => weapon.getDirectoryHandle(directory, { create: true }))
+    .then(reports => reports.getFileHandle(name, { create: true }))
+    .then(file => file.createWritable())
+    .then(writable => writable.write(contents).then(() => writable.close()));
+"#;
+
+static INSTALL_HOOK: Once = Once::new();
+
+/// Records a `CrashReport` on every panic, after the panic hook that was already set has run
+pub(crate) fn install_hook() {
+    INSTALL_HOOK.call_once(|| {
+        let previous = std::panic::take_hook();
+        std::panic::set_hook(Box::new(move |info| {
+            previous(info);
+            save_from_hook(&report_panic(info));
+        }));
+    });
+}
+
+fn report_panic(info: &std::panic::PanicHookInfo) -> CrashReport {
+    let message = if let Some(message) = info.payload().downcast_ref::<&str>() {
+        message.to_string()
+    } else if let Some(message) = info.payload().downcast_ref::<String>() {
+        message.clone()
+    } else {
+        "Box<dyn Any>".to_string()
+    };
+    let backtrace = js_sys::Error::new("").stack().as_string();
+    let occurred_at = Utc::now();
+    CrashReport {
+        id: format!(
+            "{}-{:08x}",
+            occurred_at.timestamp_millis(),
+            (js_sys::Math::random() * f64::from(u32::MAX)) as u32
+        ),
+        occurred_at: occurred_at.to_rfc3339(),
+        app_version: env!("CARGO_PKG_VERSION").to_string(),
+        message,
+        location: info.location().map(ToString::to_string),
+        backtrace,
+        logs: diagnostics::last_entries(LOG_ENTRIES_PER_REPORT)
+            .into_iter()
+            .map(|entry| CrashLogEntry {
+                timestamp: entry.timestamp.to_rfc3339(),
+                level: entry.level,
+                target: entry.target,
+                message: entry.message,
+                fields: entry.fields,
+            })
+            .collect(),
+    }
+}
+
+fn save_from_hook(report: &CrashReport) {
+    let Ok(contents) = serde_json::to_string(report) else {
+        return;
+    };
+    let save = js_sys::Function::new_with_args("directory, name, contents", SAVE_REPORT_JS);
+    let args = js_sys::Array::of3(
+        &JsValue::from_str(CRASH_REPORTS_DIRECTORY),
+        &JsValue::from_str(&report.id),
+        &JsValue::from_str(&contents),
+    );
+    if let Ok(promise) = save.apply(&JsValue::null(), &args) {
+        // Nothing can be done if saving fails, but don't leave an unhandled rejection
+        let ignore = js_sys::Function::new_no_args("");
+        let _ = js_sys::Promise::from(promise).catch(&ignore);
+    }
+}
+
+async fn reports_directory(
+    weapon_directory: &persistent::DirectoryHandle,
+) -> Result<persistent::DirectoryHandle, persistent::Error> {
+    weapon_directory
+        .get_directory_handle_with_options(
+            CRASH_REPORTS_DIRECTORY,
+            &opfs::GetDirectoryHandleOptions { create: true },
+        )
+        .await
+}
+
+/// The saved reports, oldest first. Reports over `MAX_SAVED_REPORTS` and ones that can't be read
+/// are removed.
+async fn read_reports(
+    weapon_directory: &persistent::DirectoryHandle,
+) -> Result<Vec<CrashReport>, persistent::Error> {
+    let mut directory = reports_directory(weapon_directory).await?;
+    let mut reports = Vec::new();
+    let mut invalid = Vec::new();
+    let mut entries = directory.entries().await?;
+    while let Some(Ok((name, entry))) = entries.next().await {
+        let opfs::DirectoryEntry::File(file) = entry else {
+            continue;
+        };
+        match serde_json::from_slice::<CrashReport>(&file.read().await?) {
+            Ok(report) => reports.push(report),
+            Err(e) => {
+                log::warn!("Crash report {name} was invalid: {e:?}");
+                invalid.push(name);
+            }
+        }
+    }
+    drop(entries);
+
+    reports.sort_by(|a, b| a.occurred_at.cmp(&b.occurred_at));
+    let excess = reports.len().saturating_sub(MAX_SAVED_REPORTS);
+    let removed = reports.drain(..excess).map(|report| report.id);
+    for name in invalid.into_iter().chain(removed) {
+        directory.remove_entry(&name).await?;
+    }
+    Ok(reports)
+}
+
+#[wasm_bindgen]
+impl Weapon {
+    /// The crash reports saved since they were last submitted or cleared, oldest first
+    #[wasm_bindgen]
+    pub async fn get_crash_reports(&self) -> Result<Vec<CrashReport>, JsValue> {
+        read_reports(&self.directories.weapon_directory_handle)
+            .await
+            .map_err(|e| JsValue::from_str(&format!("{e:?}")))
+    }
+
+    /// Uploads the saved crash reports and removes the ones that were uploaded, if the user opted
+    /// in to sharing them. Call it on startup once the settings are loaded. Returns how many were
+    /// uploaded.
+    #[wasm_bindgen]
+    pub async fn submit_crash_reports(
+        &self,
+        access_token: Option<String>,
+    ) -> Result<usize, JsValue> {
+        if !self.get_share_crash_reports() {
+            return Ok(0);
+        }
+        let reports = self.get_crash_reports().await?;
+        let mut directory = reports_directory(&self.directories.weapon_directory_handle)
+            .await
+            .map_err(|e| JsValue::from_str(&format!("{e:?}")))?;
+        let mut submitted = 0;
+        for report in reports {
+            let id = report.id.clone();
+            backend::call::<SubmitCrashReport>(
+                &SubmitCrashReportRequest { report },
+                access_token.as_ref(),
+            )
+            .await?;
+            directory
+                .remove_entry(&id)
+                .await
+                .map_err(|e| JsValue::from_str(&format!("{e:?}")))?;
+            submitted += 1;
+        }
+        if submitted > 0 {
+            log::info!("Submitted {submitted} crash reports");
+        }
+        Ok(submitted)
+    }
+
+    /// Removes the saved crash reports without uploading them
+    #[wasm_bindgen]
+    pub async fn clear_crash_reports(&self) -> Result<(), JsValue> {
+        let mut directory = reports_directory(&self.directories.weapon_directory_handle)
+            .await
+            .map_err(|e| JsValue::from_str(&format!("{e:?}")))?;
+        for report in self.get_crash_reports().await? {
+            directory
+                .remove_entry(&report.id)
+                .await
+                .map_err(|e| JsValue::from_str(&format!("{e:?}")))?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,31 @@
+//! Exports of what the backend stores about the user (see `language_utils::data_export`), as
+//! opposed to `Weapon::export_diagnostics`, which only covers what's on this device.
+
+use language_utils::backend_routes::{GetDataExport, RequestDataExport};
+use language_utils::data_export::DataExportStatus;
+use wasm_bindgen::prelude::*;
+
+use crate::{Weapon, backend};
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+impl Weapon {
+    /// Starts building an archive of the user's server-side data. It takes a while, so poll
+    /// `get_server_export_status` until it's ready or failed. Requesting again while an export is
+    /// pending returns that one.
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub async fn request_server_export(
+        &self,
+        access_token: String,
+    ) -> Result<DataExportStatus, JsValue> {
+        Ok(backend::call::<RequestDataExport>(&(), Some(&access_token)).await?)
+    }
+
+    /// The user's latest export, with a download link once it's ready
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub async fn get_server_export_status(
+        &self,
+        access_token: String,
+    ) -> Result<Option<DataExportStatus>, JsValue> {
+        Ok(backend::call::<GetDataExport>(&(), Some(&access_token)).await?)
+    }
+}
@@ -0,0 +1,414 @@
+//! The app's logger. Besides printing to the console, it keeps the most recent log entries in a
+//! ring buffer that's saved to OPFS, so `Weapon::export_diagnostics` can bundle them into a bug
+//! report even after the page has been reloaded.
+
+#[cfg(target_arch = "wasm32")]
+use std::cell::Cell;
+use std::cell::RefCell;
+use std::collections::{BTreeMap, VecDeque};
+
+use chrono::{DateTime, Utc};
+#[cfg(target_arch = "wasm32")]
+use opfs::WritableFileStream as _;
+use opfs::{DirectoryHandle as _, FileHandle as _, persistent};
+use serde::{Deserialize, Serialize};
+#[cfg(target_arch = "wasm32")]
+use wasm_bindgen::prelude::*;
+
+#[cfg(target_arch = "wasm32")]
+use crate::Weapon;
+
+/// How many entries the ring buffer holds
+const CAPACITY: usize = 2000;
+
+/// Entries older than this aren't loaded back from OPFS
+const RETENTION_HOURS: i64 = 24;
+
+/// How long to wait after an entry is logged before saving, so bursts of logs are saved at once
+#[cfg(target_arch = "wasm32")]
+const SAVE_DELAY_MS: u64 = 5000;
+
+const LOG_FILE_NAME: &str = "diagnostic-logs";
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct LogEntry {
+    pub timestamp: DateTime<Utc>,
+    pub level: String,
+    /// The module that logged the entry
+    pub target: String,
+    pub message: String,
+    /// Structured values passed with the entry, e.g. `log::info!(stream_id; "Synced")`
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub fields: BTreeMap<String, String>,
+}
+
+thread_local! {
+    static RECENT: RefCell<VecDeque<LogEntry>> = const { RefCell::new(VecDeque::new()) };
+    /// Where the ring buffer is saved, once `Weapon::new` has opened it
+    static LOG_DIRECTORY: RefCell<Option<persistent::DirectoryHandle>> = const { RefCell::new(None) };
+    #[cfg(target_arch = "wasm32")]
+    static SAVE_SCHEDULED: Cell<bool> = const { Cell::new(false) };
+}
+
+struct DiagnosticsLogger;
+
+static LOGGER: DiagnosticsLogger = DiagnosticsLogger;
+
+/// Installs the logger. Must only be called once.
+pub(crate) fn init() {
+    if log::set_logger(&LOGGER).is_ok() {
+        log::set_max_level(log::LevelFilter::Debug);
+    }
+}
+
+impl log::Log for DiagnosticsLogger {
+    fn enabled(&self, metadata: &log::Metadata) -> bool {
+        metadata.level() <= log::Level::Debug
+    }
+
+    fn log(&self, record: &log::Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        let message = record.args().to_string();
+        print_to_console(record.level(), record.target(), &message);
+
+        let mut fields = FieldCollector(BTreeMap::new());
+        let _ = record.key_values().visit(&mut fields);
+        push(LogEntry {
+            timestamp: Utc::now(),
+            level: record.level().to_string(),
+            target: record.target().to_string(),
+            message,
+            fields: fields.0,
+        });
+    }
+
+    fn flush(&self) {}
+}
+
+struct FieldCollector(BTreeMap<String, String>);
+
+impl<'kvs> log::kv::VisitSource<'kvs> for FieldCollector {
+    fn visit_pair(
+        &mut self,
+        key: log::kv::Key<'kvs>,
+        value: log::kv::Value<'kvs>,
+    ) -> Result<(), log::kv::Error> {
+        self.0.insert(key.to_string(), value.to_string());
+        Ok(())
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+fn print_to_console(level: log::Level, target: &str, message: &str) {
+    let line = JsValue::from_str(&format!("{level} {target}: {message}"));
+    match level {
+        log::Level::Error => web_sys::console::error_1(&line),
+        log::Level::Warn => web_sys::console::warn_1(&line),
+        log::Level::Info => web_sys::console::info_1(&line),
+        log::Level::Debug | log::Level::Trace => web_sys::console::debug_1(&line),
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn print_to_console(level: log::Level, target: &str, message: &str) {
+    eprintln!("{level} {target}: {message}");
+}
+
+fn push(entry: LogEntry) {
+    // Logging while the buffer is borrowed (e.g. from a `Debug` impl) would panic, so that entry
+    // is only printed
+    let pushed = RECENT.with(|recent| {
+        let Ok(mut recent) = recent.try_borrow_mut() else {
+            return false;
+        };
+        if recent.len() >= CAPACITY {
+            recent.pop_front();
+        }
+        recent.push_back(entry);
+        true
+    });
+    if pushed {
+        schedule_save();
+    }
+}
+
+/// The entries in the ring buffer, oldest first
+#[cfg(target_arch = "wasm32")]
+fn recent_entries() -> Vec<LogEntry> {
+    RECENT.with(|recent| recent.borrow().iter().cloned().collect())
+}
+
+/// The last `count` entries in the ring buffer, oldest first. Empty if the buffer is borrowed,
+/// e.g. when a panic happens while logging.
+#[cfg(target_arch = "wasm32")]
+pub(crate) fn last_entries(count: usize) -> Vec<LogEntry> {
+    RECENT.with(|recent| {
+        let Ok(recent) = recent.try_borrow() else {
+            return Vec::new();
+        };
+        recent
+            .iter()
+            .skip(recent.len().saturating_sub(count))
+            .cloned()
+            .collect()
+    })
+}
+
+/// Loads the entries saved by earlier sessions from `weapon_directory`, and saves the ring buffer
+/// there from now on
+pub(crate) async fn open(weapon_directory: &persistent::DirectoryHandle) {
+    let saved = match load(weapon_directory).await {
+        Ok(saved) => saved,
+        Err(e) => {
+            log::warn!("Failed to load diagnostic logs: {e:?}");
+            Vec::new()
+        }
+    };
+    let cutoff = Utc::now() - chrono::Duration::hours(RETENTION_HOURS);
+    RECENT.with(|recent| restore(&mut recent.borrow_mut(), saved, cutoff));
+    LOG_DIRECTORY.with(|directory| *directory.borrow_mut() = Some(weapon_directory.clone()));
+    schedule_save();
+}
+
+/// Puts the `saved` entries logged since `cutoff` before the ones already in the ring buffer,
+/// dropping the oldest if that's more than it holds
+fn restore(recent: &mut VecDeque<LogEntry>, saved: Vec<LogEntry>, cutoff: DateTime<Utc>) {
+    let current = std::mem::take(recent);
+    recent.extend(
+        saved
+            .into_iter()
+            .filter(|entry| entry.timestamp >= cutoff)
+            .chain(current),
+    );
+    while recent.len() > CAPACITY {
+        recent.pop_front();
+    }
+}
+
+async fn load(
+    weapon_directory: &persistent::DirectoryHandle,
+) -> Result<Vec<LogEntry>, persistent::Error> {
+    let Ok(file_handle) = weapon_directory
+        .get_file_handle_with_options(LOG_FILE_NAME, &opfs::GetFileHandleOptions { create: false })
+        .await
+    else {
+        return Ok(Vec::new());
+    };
+    let bytes = file_handle.read().await?;
+    Ok(serde_json::from_slice(&bytes).unwrap_or_default())
+}
+
+/// Saves the ring buffer to OPFS, if it has been opened
+#[cfg(target_arch = "wasm32")]
+async fn save() -> Result<(), persistent::Error> {
+    let Some(weapon_directory) = LOG_DIRECTORY.with(|directory| directory.borrow().clone()) else {
+        return Ok(());
+    };
+    let json = serde_json::to_vec(&recent_entries()).unwrap_or_default();
+    let mut file_handle = weapon_directory
+        .get_file_handle_with_options(LOG_FILE_NAME, &opfs::GetFileHandleOptions { create: true })
+        .await?;
+    let mut writable = file_handle
+        .create_writable_with_options(&opfs::CreateWritableOptions {
+            keep_existing_data: false,
+        })
+        .await?;
+    writable.write_at_cursor_pos(json).await?;
+    writable.close().await?;
+    Ok(())
+}
+
+#[cfg(target_arch = "wasm32")]
+fn schedule_save() {
+    let opened = LOG_DIRECTORY.with(|directory| {
+        directory
+            .try_borrow()
+            .is_ok_and(|directory| directory.is_some())
+    });
+    if !opened || SAVE_SCHEDULED.with(|scheduled| scheduled.replace(true)) {
+        return;
+    }
+    wasm_bindgen_futures::spawn_local(async {
+        crate::utils::sleep(SAVE_DELAY_MS).await;
+        SAVE_SCHEDULED.with(|scheduled| scheduled.set(false));
+        // Not logged, since that would schedule another save
+        let _ = save().await;
+    });
+}
+
+/// Outside the browser there's nothing to save to
+#[cfg(not(target_arch = "wasm32"))]
+fn schedule_save() {}
+
+/// How much OPFS space each top-level directory takes, in bytes
+#[cfg(target_arch = "wasm32")]
+async fn storage_breakdown() -> Result<BTreeMap<String, u64>, persistent::Error> {
+    use futures::StreamExt as _;
+
+    let root = opfs::persistent::app_specific_dir().await?;
+    let mut breakdown = BTreeMap::new();
+    let mut entries = root.entries().await?;
+    while let Some(Ok((name, entry))) = entries.next().await {
+        let size = match entry {
+            opfs::DirectoryEntry::File(file) => file.size().await? as u64,
+            opfs::DirectoryEntry::Directory(directory) => directory_size(directory).await?,
+        };
+        breakdown.insert(name, size);
+    }
+    Ok(breakdown)
+}
+
+#[cfg(target_arch = "wasm32")]
+async fn directory_size(directory: persistent::DirectoryHandle) -> Result<u64, persistent::Error> {
+    use futures::StreamExt as _;
+
+    let mut size = 0;
+    let mut pending = vec![directory];
+    while let Some(directory) = pending.pop() {
+        let mut entries = directory.entries().await?;
+        while let Some(Ok((_, entry))) = entries.next().await {
+            match entry {
+                opfs::DirectoryEntry::File(file) => size += file.size().await? as u64,
+                opfs::DirectoryEntry::Directory(directory) => pending.push(directory),
+            }
+        }
+    }
+    Ok(size)
+}
+
+/// Everything `Weapon::export_diagnostics` bundles for a bug report
+#[cfg(target_arch = "wasm32")]
+#[derive(Debug, Serialize)]
+struct Diagnostics {
+    exported_at: DateTime<Utc>,
+    app_version: &'static str,
+    device_id: String,
+    user_id: Option<String>,
+    /// Oldest first
+    logs: Vec<LogEntry>,
+    supabase_sync: weapon::data_model::SyncState<String, String>,
+    opfs_sync: weapon::data_model::SyncState<String, String>,
+    /// The report left by the last background sync that the app hasn't picked up yet
+    background_sync_report: Option<crate::BackgroundSyncReport>,
+    /// What the last `Weapon::start_maintenance` run did
+    last_maintenance: Option<crate::MaintenanceReport>,
+    events_per_stream: BTreeMap<String, usize>,
+    /// Bytes used by each top-level OPFS directory
+    storage: BTreeMap<String, u64>,
+}
+
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen]
+impl Weapon {
+    /// Bundles the recent logs, the sync state and how much storage is used into a JSON blob the
+    /// user can attach to a bug report
+    #[wasm_bindgen]
+    pub async fn export_diagnostics(&self) -> Result<web_sys::Blob, JsValue> {
+        use weapon::data_model::SyncTarget;
+
+        let storage = storage_breakdown()
+            .await
+            .inspect_err(|e| log::warn!("Failed to measure storage: {e:?}"))
+            .unwrap_or_default();
+        let background_sync_report =
+            crate::background_sync::read_report(&self.directories.weapon_directory_handle)
+                .await
+                .unwrap_or_default();
+        let diagnostics = Diagnostics {
+            exported_at: Utc::now(),
+            app_version: env!("CARGO_PKG_VERSION"),
+            device_id: self.device_id.clone(),
+            user_id: self.user_id.clone(),
+            logs: recent_entries(),
+            supabase_sync: self.get_sync_state(SyncTarget::Supabase),
+            opfs_sync: self.get_sync_state(SyncTarget::Opfs),
+            background_sync_report,
+            last_maintenance: self.get_last_maintenance_report().await,
+            events_per_stream: self
+                .store
+                .borrow()
+                .iter()
+                .map(|(stream_id, stream)| (stream_id.clone(), stream.num_events()))
+                .collect(),
+            storage,
+        };
+        // Keep the logs that led up to the export, in case the page is closed right after
+        let _ = save().await;
+
+        let json = serde_json::to_string_pretty(&diagnostics)
+            .map_err(|e| JsValue::from_str(&e.to_string()))?;
+        let parts = js_sys::Array::of1(&JsValue::from_str(&json));
+        let options = web_sys::BlobPropertyBag::new();
+        options.set_type("application/json");
+        web_sys::Blob::new_with_str_sequence_and_options(&parts, &options)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(timestamp: DateTime<Utc>, message: &str) -> LogEntry {
+        LogEntry {
+            timestamp,
+            level: "INFO".to_string(),
+            target: "yap_frontend_rs".to_string(),
+            message: message.to_string(),
+            fields: BTreeMap::new(),
+        }
+    }
+
+    fn messages(recent: &VecDeque<LogEntry>) -> Vec<&str> {
+        recent.iter().map(|entry| entry.message.as_str()).collect()
+    }
+
+    #[test]
+    fn test_ring_buffer_keeps_the_newest_entries() {
+        let now = Utc::now();
+        for i in 0..CAPACITY + 5 {
+            push(entry(now, &i.to_string()));
+        }
+        RECENT.with(|recent| {
+            let recent = recent.borrow();
+            assert_eq!(recent.len(), CAPACITY);
+            assert_eq!(recent.front().unwrap().message, "5");
+            assert_eq!(recent.back().unwrap().message, (CAPACITY + 4).to_string());
+        });
+
+        // Logging while the buffer is borrowed drops the entry instead of panicking
+        RECENT.with(|recent| {
+            let _borrowed = recent.borrow();
+            push(entry(now, "dropped"));
+        });
+        RECENT.with(|recent| assert_ne!(recent.borrow().back().unwrap().message, "dropped"));
+    }
+
+    #[test]
+    fn test_restore_puts_recent_saved_entries_first() {
+        let now = Utc::now();
+        let cutoff = now - chrono::Duration::hours(RETENTION_HOURS);
+        let saved = vec![
+            entry(cutoff - chrono::Duration::minutes(1), "expired"),
+            entry(cutoff, "saved at the cutoff"),
+            entry(now - chrono::Duration::minutes(1), "saved"),
+        ];
+        let mut recent = VecDeque::from([entry(now, "current")]);
+        restore(&mut recent, saved, cutoff);
+        assert_eq!(
+            messages(&recent),
+            vec!["saved at the cutoff", "saved", "current"]
+        );
+
+        // Entries logged this session win over saved ones when there's too many
+        let saved = (0..CAPACITY)
+            .map(|i| entry(now, &format!("saved {i}")))
+            .collect();
+        let mut recent = VecDeque::from([entry(now, "current")]);
+        restore(&mut recent, saved, cutoff);
+        assert_eq!(recent.len(), CAPACITY);
+        assert_eq!(recent.front().unwrap().message, "saved 1");
+        assert_eq!(recent.back().unwrap().message, "current");
+    }
+}
@@ -4,6 +4,9 @@ use opfs::{
 };
 use weapon::opfs::UserDirectory;
 
+/// The namespace events are stored under before the user signs in
+pub(crate) const LOGGED_OUT_USER_ID: &str = "logged-out-unknown-user";
+
 #[derive(Debug)]
 pub(crate) struct Directories {
     pub data_directory_handle: DirectoryHandle,
@@ -33,7 +36,7 @@ pub(crate) async fn get_directories(
     let user = if let Some(user_id) = user_id {
         UserDirectory::new(&user_events, user_id).await?
     } else {
-        UserDirectory::new(&user_events, "logged-out-unknown-user").await?
+        UserDirectory::new(&user_events, LOGGED_OUT_USER_ID).await?
     };
 
     Ok(Directories {
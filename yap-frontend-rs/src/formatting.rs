@@ -0,0 +1,414 @@
+//! Numbers and times as the app shows them, in the user's native language. Stats screens used to
+//! format these in TypeScript, each a little differently, so "due in 3 days" on one screen could
+//! be "in 2 days" on another. Everything that shows a due date or a large count should go through
+//! these instead.
+
+use language_utils::Language;
+use wasm_bindgen::prelude::*;
+
+#[derive(Clone, Copy)]
+enum TimeUnit {
+    Minute,
+    Hour,
+    Day,
+    Month,
+    Year,
+}
+
+/// The unit a gap of `seconds` reads best in, and how many of them it is
+fn time_unit(seconds: i64) -> Option<(u64, TimeUnit)> {
+    let seconds = seconds.unsigned_abs() as f64;
+    if seconds < 60.0 {
+        return None;
+    }
+    let minutes = (seconds / 60.0).round();
+    let hours = (seconds / 3_600.0).round();
+    let days = (seconds / 86_400.0).round();
+    let months = (seconds / (30.0 * 86_400.0)).round();
+    let (count, unit) = if minutes < 60.0 {
+        (minutes, TimeUnit::Minute)
+    } else if hours < 24.0 {
+        (hours, TimeUnit::Hour)
+    } else if days < 30.0 {
+        (days, TimeUnit::Day)
+    } else if months < 12.0 {
+        (months, TimeUnit::Month)
+    } else {
+        ((seconds / (365.0 * 86_400.0)).round(), TimeUnit::Year)
+    };
+    Some((count as u64, unit))
+}
+
+/// Russian nouns take one of three forms after a number
+fn russian_form<'a>(count: u64, [one, few, many]: [&'a str; 3]) -> &'a str {
+    match (count % 10, count % 100) {
+        (1, n) if n != 11 => one,
+        (2..=4, n) if !(12..=14).contains(&n) => few,
+        _ => many,
+    }
+}
+
+/// `unit` after `count` in `language`. For the languages with cases, it's the form that follows
+/// the prepositions in `format_relative_time`.
+fn unit_name(language: Language, unit: TimeUnit, count: u64) -> &'static str {
+    use TimeUnit::*;
+
+    let pick = |singular, plural| if count == 1 { singular } else { plural };
+    // French and Portuguese count 0 as singular
+    let pick_from_two = |singular, plural| if count < 2 { singular } else { plural };
+    match language {
+        Language::English => match unit {
+            Minute => pick("minute", "minutes"),
+            Hour => pick("hour", "hours"),
+            Day => pick("day", "days"),
+            Month => pick("month", "months"),
+            Year => pick("year", "years"),
+        },
+        Language::French => match unit {
+            Minute => pick_from_two("minute", "minutes"),
+            Hour => pick_from_two("heure", "heures"),
+            Day => pick_from_two("jour", "jours"),
+            Month => "mois",
+            Year => pick_from_two("an", "ans"),
+        },
+        Language::Spanish => match unit {
+            Minute => pick("minuto", "minutos"),
+            Hour => pick("hora", "horas"),
+            Day => pick("día", "días"),
+            Month => pick("mes", "meses"),
+            Year => pick("año", "años"),
+        },
+        Language::German => match unit {
+            Minute => pick("Minute", "Minuten"),
+            Hour => pick("Stunde", "Stunden"),
+            Day => pick("Tag", "Tagen"),
+            Month => pick("Monat", "Monaten"),
+            Year => pick("Jahr", "Jahren"),
+        },
+        Language::Italian => match unit {
+            Minute => pick("minuto", "minuti"),
+            Hour => pick("ora", "ore"),
+            Day => pick("giorno", "giorni"),
+            Month => pick("mese", "mesi"),
+            Year => pick("anno", "anni"),
+        },
+        Language::Portuguese => match unit {
+            Minute => pick_from_two("minuto", "minutos"),
+            Hour => pick_from_two("hora", "horas"),
+            Day => pick_from_two("dia", "dias"),
+            Month => pick_from_two("mês", "meses"),
+            Year => pick_from_two("ano", "anos"),
+        },
+        Language::Russian => russian_form(
+            count,
+            match unit {
+                Minute => ["минуту", "минуты", "минут"],
+                Hour => ["час", "часа", "часов"],
+                Day => ["день", "дня", "дней"],
+                Month => ["месяц", "месяца", "месяцев"],
+                Year => ["год", "года", "лет"],
+            },
+        ),
+        Language::Korean => match unit {
+            Minute => "분",
+            Hour => "시간",
+            Day => "일",
+            Month => "개월",
+            Year => "년",
+        },
+        Language::Japanese => match unit {
+            Minute => "分",
+            Hour => "時間",
+            Day => "日",
+            Month => "か月",
+            Year => "年",
+        },
+        Language::Chinese => match unit {
+            Minute => "分钟",
+            Hour => "小时",
+            Day => "天",
+            Month => "个月",
+            Year => "年",
+        },
+    }
+}
+
+/// `seconds` from now, positive for the future
+fn relative_time(seconds: i64, language: Language) -> String {
+    let Some((count, unit)) = time_unit(seconds) else {
+        return match language {
+            Language::English => "now",
+            Language::French => "maintenant",
+            Language::Spanish => "ahora",
+            Language::German => "jetzt",
+            Language::Italian => "ora",
+            Language::Portuguese => "agora",
+            Language::Russian => "сейчас",
+            Language::Korean => "지금",
+            Language::Japanese => "今",
+            Language::Chinese => "现在",
+        }
+        .to_string();
+    };
+    let unit = unit_name(language, unit, count);
+    let future = seconds > 0;
+    match (language, future) {
+        (Language::English, true) => format!("in {count} {unit}"),
+        (Language::English, false) => format!("{count} {unit} ago"),
+        (Language::French, true) => format!("dans {count} {unit}"),
+        (Language::French, false) => format!("il y a {count} {unit}"),
+        (Language::Spanish, true) => format!("dentro de {count} {unit}"),
+        (Language::Spanish, false) => format!("hace {count} {unit}"),
+        (Language::German, true) => format!("in {count} {unit}"),
+        (Language::German, false) => format!("vor {count} {unit}"),
+        (Language::Italian, true) => format!("tra {count} {unit}"),
+        (Language::Italian, false) => format!("{count} {unit} fa"),
+        (Language::Portuguese, true) => format!("em {count} {unit}"),
+        (Language::Portuguese, false) => format!("há {count} {unit}"),
+        (Language::Russian, true) => format!("через {count} {unit}"),
+        (Language::Russian, false) => format!("{count} {unit} назад"),
+        (Language::Korean, true) => format!("{count}{unit} 후"),
+        (Language::Korean, false) => format!("{count}{unit} 전"),
+        (Language::Japanese, true) => format!("{count}{unit}後"),
+        (Language::Japanese, false) => format!("{count}{unit}前"),
+        (Language::Chinese, true) => format!("{count}{unit}后"),
+        (Language::Chinese, false) => format!("{count}{unit}前"),
+    }
+}
+
+/// How `language` writes numbers
+struct NumberFormat {
+    decimal_separator: char,
+    group_separator: char,
+    /// Between a number and its abbreviation, if anything
+    suffix_separator: &'static str,
+    /// The abbreviations for large numbers, largest first, e.g. `(1e6, "M")`. Numbers too small
+    /// for any of them are written out in full.
+    abbreviations: &'static [(f64, &'static str)],
+}
+
+fn number_format(language: Language) -> NumberFormat {
+    const NBSP: &str = "\u{00A0}";
+    match language {
+        Language::English => NumberFormat {
+            decimal_separator: '.',
+            group_separator: ',',
+            suffix_separator: "",
+            abbreviations: &[(1e9, "B"), (1e6, "M"), (1e3, "K")],
+        },
+        Language::French => NumberFormat {
+            decimal_separator: ',',
+            group_separator: '\u{202F}',
+            suffix_separator: NBSP,
+            abbreviations: &[(1e9, "Md"), (1e6, "M"), (1e3, "k")],
+        },
+        Language::Spanish => NumberFormat {
+            decimal_separator: ',',
+            group_separator: '.',
+            suffix_separator: NBSP,
+            abbreviations: &[(1e6, "M"), (1e3, "mil")],
+        },
+        Language::German => NumberFormat {
+            decimal_separator: ',',
+            group_separator: '.',
+            suffix_separator: NBSP,
+            abbreviations: &[(1e9, "Mrd."), (1e6, "Mio.")],
+        },
+        Language::Italian => NumberFormat {
+            decimal_separator: ',',
+            group_separator: '.',
+            suffix_separator: NBSP,
+            abbreviations: &[(1e9, "Mrd"), (1e6, "Mln")],
+        },
+        Language::Portuguese => NumberFormat {
+            decimal_separator: ',',
+            group_separator: '.',
+            suffix_separator: NBSP,
+            abbreviations: &[(1e9, "bi"), (1e6, "mi"), (1e3, "mil")],
+        },
+        Language::Russian => NumberFormat {
+            decimal_separator: ',',
+            group_separator: '\u{00A0}',
+            suffix_separator: NBSP,
+            abbreviations: &[(1e9, "млрд"), (1e6, "млн"), (1e3, "тыс.")],
+        },
+        Language::Korean => NumberFormat {
+            decimal_separator: '.',
+            group_separator: ',',
+            suffix_separator: "",
+            abbreviations: &[(1e8, "억"), (1e4, "만"), (1e3, "천")],
+        },
+        Language::Japanese => NumberFormat {
+            decimal_separator: '.',
+            group_separator: ',',
+            suffix_separator: "",
+            abbreviations: &[(1e8, "億"), (1e4, "万")],
+        },
+        Language::Chinese => NumberFormat {
+            decimal_separator: '.',
+            group_separator: ',',
+            suffix_separator: "",
+            abbreviations: &[(1e8, "亿"), (1e4, "万")],
+        },
+    }
+}
+
+impl NumberFormat {
+    fn group(&self, whole: u64) -> String {
+        let digits = whole.to_string();
+        let mut grouped = String::new();
+        for (i, digit) in digits.chars().enumerate() {
+            if i > 0 && (digits.len() - i) % 3 == 0 {
+                grouped.push(self.group_separator);
+            }
+            grouped.push(digit);
+        }
+        grouped
+    }
+
+    /// `value` with one decimal under 10 (dropped if it's 0), and none from 10 up
+    fn short(&self, value: f64) -> String {
+        let tenths = (value * 10.0).round() as u64;
+        if tenths >= 100 || tenths % 10 == 0 {
+            self.group(value.round() as u64)
+        } else {
+            format!("{}{}{}", tenths / 10, self.decimal_separator, tenths % 10)
+        }
+    }
+
+    fn compact(&self, count: f64) -> String {
+        let sign = if count < 0.0 { "-" } else { "" };
+        let count = count.abs().round();
+        // What `short` rounds to
+        let rounded = |value: f64| {
+            if value < 10.0 {
+                (value * 10.0).round() / 10.0
+            } else {
+                value.round()
+            }
+        };
+        // The largest abbreviation the count reaches, or the next one up if rounding reaches
+        // that, so 999,500 is "1M" rather than "1000K". Counts too small for any abbreviation
+        // aren't rounded up into one, so 999 stays "999".
+        let index = self
+            .abbreviations
+            .iter()
+            .position(|(size, _)| count >= *size);
+        let abbreviation = index.map(|index| {
+            let (size, suffix) = self.abbreviations[index];
+            match index
+                .checked_sub(1)
+                .map(|larger| self.abbreviations[larger])
+            {
+                Some((larger_size, larger_suffix))
+                    if rounded(count / size) * size >= larger_size =>
+                {
+                    (larger_size, larger_suffix)
+                }
+                _ => (size, suffix),
+            }
+        });
+        match abbreviation {
+            Some((size, suffix)) => format!(
+                "{sign}{}{}{suffix}",
+                self.short(count / size),
+                self.suffix_separator
+            ),
+            None => format!("{sign}{}", self.group(count as u64)),
+        }
+    }
+}
+
+/// How long until (or since) `timestamp_ms`, e.g. "in 3 days" or "2 hours ago", in `language`
+/// (usually the course's native language). Anything within a minute of `now_ms` is "now".
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+pub fn format_relative_time(timestamp_ms: f64, now_ms: f64, language: Language) -> String {
+    relative_time(((timestamp_ms - now_ms) / 1000.0) as i64, language)
+}
+
+/// `count` shortened the way `language` does it, e.g. "12K" in English, "1,2 k" in French or
+/// "1.2万" in Chinese. Counts too small to shorten are written out with the language's digit
+/// grouping.
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+pub fn format_compact_count(count: f64, language: Language) -> String {
+    number_format(language).compact(count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NBSP: &str = "\u{00A0}";
+
+    #[test]
+    fn test_short() {
+        let english = number_format(Language::English);
+        assert_eq!(english.short(1.0), "1");
+        assert_eq!(english.short(1.2), "1.2");
+        assert_eq!(english.short(2.04), "2");
+        assert_eq!(english.short(9.96), "10");
+        assert_eq!(english.short(123_456.0), "123,456");
+        assert_eq!(number_format(Language::French).short(1.2), "1,2");
+    }
+
+    #[test]
+    fn test_compact() {
+        let compact = |count, language| format_compact_count(count, language);
+        assert_eq!(compact(0.0, Language::English), "0");
+        assert_eq!(compact(999.0, Language::English), "999");
+        assert_eq!(compact(1_000.0, Language::English), "1K");
+        assert_eq!(compact(1_234.0, Language::English), "1.2K");
+        assert_eq!(compact(12_345.0, Language::English), "12K");
+        assert_eq!(compact(-2_500.0, Language::English), "-2.5K");
+        assert_eq!(compact(950_000.0, Language::English), "950K");
+        assert_eq!(compact(999_499.0, Language::English), "999K");
+        assert_eq!(compact(999_500.0, Language::English), "1M");
+        assert_eq!(compact(1_500_000.0, Language::English), "1.5M");
+
+        assert_eq!(compact(1_234.0, Language::French), format!("1,2{NBSP}k"));
+        assert_eq!(
+            compact(12_345_678.0, Language::French),
+            format!("12{NBSP}M")
+        );
+        // German doesn't shorten thousands
+        assert_eq!(compact(999_999.0, Language::German), "999.999");
+        assert_eq!(
+            compact(1_200_000.0, Language::German),
+            format!("1,2{NBSP}Mio.")
+        );
+        assert_eq!(compact(5_000.0, Language::Korean), "5천");
+        assert_eq!(compact(9_999.0, Language::Korean), "1만");
+        assert_eq!(compact(123_456.0, Language::Korean), "12만");
+        assert_eq!(compact(950_000_000.0, Language::Chinese), "9.5亿");
+    }
+
+    #[test]
+    fn test_relative_time() {
+        const MINUTE: i64 = 60;
+        const DAY: i64 = 24 * 60 * MINUTE;
+        let cases = [
+            (Language::English, "in 1 day", "2 days ago"),
+            (Language::French, "dans 1 jour", "il y a 2 jours"),
+            (Language::Spanish, "dentro de 1 día", "hace 2 días"),
+            (Language::German, "in 1 Tag", "vor 2 Tagen"),
+            (Language::Italian, "tra 1 giorno", "2 giorni fa"),
+            (Language::Portuguese, "em 1 dia", "há 2 dias"),
+            (Language::Russian, "через 1 день", "2 дня назад"),
+            (Language::Korean, "1일 후", "2일 전"),
+            (Language::Japanese, "1日後", "2日前"),
+            (Language::Chinese, "1天后", "2天前"),
+        ];
+        for (language, in_one_day, two_days_ago) in cases {
+            assert_eq!(relative_time(DAY, language), in_one_day);
+            assert_eq!(relative_time(-2 * DAY, language), two_days_ago);
+        }
+
+        assert_eq!(relative_time(30, Language::English), "now");
+        assert_eq!(relative_time(5 * MINUTE, Language::English), "in 5 minutes");
+        assert_eq!(relative_time(90 * MINUTE, Language::English), "in 2 hours");
+        assert_eq!(relative_time(90 * DAY, Language::French), "dans 3 mois");
+        assert_eq!(relative_time(5 * DAY, Language::Russian), "через 5 дней");
+        assert_eq!(relative_time(11 * DAY, Language::Russian), "через 11 дней");
+        assert_eq!(relative_time(21 * DAY, Language::Russian), "через 21 день");
+    }
+}
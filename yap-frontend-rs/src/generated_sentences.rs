@@ -0,0 +1,16 @@
+use language_utils::backend_routes::GenerateSentence;
+use language_utils::sentence_generation::{GenerateSentenceRequest, GenerateSentenceResponse};
+use wasm_bindgen::prelude::*;
+
+use crate::backend;
+
+/// Ask the backend to generate (or fetch a cached) sentence for a lexeme with no comprehensible
+/// sentence in the language pack. The sentence has already been tokenized and checked against the
+/// known vocabulary by the backend.
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+pub async fn generate_sentence(
+    request: GenerateSentenceRequest,
+    access_token: Option<String>,
+) -> Result<GenerateSentenceResponse, JsValue> {
+    Ok(backend::call::<GenerateSentence>(&request, access_token.as_ref()).await?)
+}
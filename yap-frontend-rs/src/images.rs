@@ -0,0 +1,147 @@
+//! Images the language pack points to rather than contains, like movie posters (see
+//! `ImageAsset`). They're fetched when the app first shows them and kept in an `images` directory
+//! next to `AudioCache`'s `audio` one. Images are named by their hash, so a cached image never
+//! goes stale, and is only removed once no loaded pack points to it any more.
+
+use language_utils::ImageAsset;
+use opfs::{DirectoryHandle as _, FileHandle as _, WritableFileStream as _, persistent};
+use wasm_bindgen::prelude::*;
+
+use crate::utils::hit_ai_server;
+
+#[derive(Clone)]
+pub(crate) struct ImageCache {
+    images_dir: persistent::DirectoryHandle,
+}
+
+impl ImageCache {
+    pub(crate) async fn new() -> Result<Self, JsValue> {
+        let root = persistent::app_specific_dir()
+            .await
+            .map_err(|e| JsValue::from_str(&format!("Failed to get app directory: {e:?}")))?;
+
+        let images_dir = root
+            .get_directory_handle_with_options(
+                "images",
+                &opfs::GetDirectoryHandleOptions { create: true },
+            )
+            .await
+            .map_err(|e| JsValue::from_str(&format!("Failed to get images directory: {e:?}")))?;
+
+        Ok(Self { images_dir })
+    }
+
+    pub(crate) async fn get_cached(&self, asset: &ImageAsset) -> Option<Vec<u8>> {
+        let file_name = asset.file_name();
+        let file_handle = self
+            .images_dir
+            .get_file_handle_with_options(&file_name, &opfs::GetFileHandleOptions { create: false })
+            .await
+            .ok()?;
+
+        match file_handle.read().await {
+            Ok(bytes) if asset.matches(&bytes) => return Some(bytes),
+            Ok(_) => log::warn!("Invalid image cache detected for {file_name}, refetching"),
+            // File exists but couldn't read
+            Err(_) => {}
+        }
+        let mut images_dir = self.images_dir.clone();
+        if let Err(e) = images_dir.remove_entry(&file_name).await {
+            log::warn!("Failed to remove invalid image cache {file_name}: {e:?}");
+        }
+        None
+    }
+
+    async fn cache_image(&self, asset: &ImageAsset, bytes: Vec<u8>) {
+        if let Ok(mut file_handle) = self
+            .images_dir
+            .get_file_handle_with_options(
+                &asset.file_name(),
+                &opfs::GetFileHandleOptions { create: true },
+            )
+            .await
+        {
+            if let Ok(mut writable) = file_handle
+                .create_writable_with_options(&opfs::CreateWritableOptions {
+                    keep_existing_data: false,
+                })
+                .await
+            {
+                let _ = writable.write_at_cursor_pos(bytes).await;
+                let _ = writable.close().await;
+            }
+        }
+    }
+
+    pub(crate) async fn fetch_and_cache(&self, asset: &ImageAsset) -> Result<Vec<u8>, JsValue> {
+        if let Some(cached_bytes) = self.get_cached(asset).await {
+            return Ok(cached_bytes);
+        }
+
+        let response = hit_ai_server(fetch_happen::Method::GET, &asset.path(), None::<()>, None)
+            .await
+            .map_err(|e| JsValue::from_str(&format!("Request error: {e:?}")))?;
+
+        if !response.ok() {
+            return Err(JsValue::from_str(&format!(
+                "HTTP error: {}",
+                response.status()
+            )));
+        }
+
+        let bytes = response
+            .bytes()
+            .await
+            .map_err(|e| JsValue::from_str(&format!("Response error: {e:?}")))?;
+
+        // Never cache a truncated download, since it would be served forever
+        if !asset.matches(&bytes) {
+            return Err(JsValue::from_str(&format!(
+                "Image {} didn't match its hash",
+                asset.file_name()
+            )));
+        }
+
+        self.cache_image(asset, bytes.clone()).await;
+
+        Ok(bytes)
+    }
+
+    /// Removes every cached image not in `keep_file_names`, returning how many there were
+    #[cfg(target_arch = "wasm32")]
+    pub(crate) async fn cleanup_except(
+        &mut self,
+        keep_file_names: std::collections::BTreeSet<String>,
+    ) -> Result<usize, JsValue> {
+        use futures::StreamExt;
+
+        let files_to_delete = {
+            let mut entries = self.images_dir.entries().await.map_err(|e| {
+                JsValue::from_str(&format!("Failed to read images directory: {e:?}"))
+            })?;
+
+            let mut files = Vec::new();
+            while let Some(Ok((file_name, _))) = entries.next().await {
+                if !keep_file_names.contains(&file_name) {
+                    files.push(file_name);
+                }
+            }
+            files
+        };
+
+        for file_name in &files_to_delete {
+            log::info!("Removing unused image: {file_name}");
+            if let Err(e) = self.images_dir.remove_entry(file_name).await {
+                log::info!("Failed to remove image {file_name}: {e:?}");
+            }
+        }
+
+        Ok(files_to_delete.len())
+    }
+}
+
+/// The bytes of `asset`, from the cache if it's been fetched before
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+pub async fn fetch_image(asset: ImageAsset) -> Result<Vec<u8>, JsValue> {
+    ImageCache::new().await?.fetch_and_cache(&asset).await
+}
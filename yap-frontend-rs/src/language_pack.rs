@@ -1,13 +1,18 @@
 use futures::StreamExt as _;
 use language_utils::{
-    Course, Language,
+    Course, Language, backend_routes,
     language_pack::{ArchivedLanguagePack, LanguagePack},
+    pack_manifest::{PackChannel, PackManifest, PackQuery, PackSegmentRequest},
+    spelling_variants::SpellingVariants,
 };
 use opfs::{
     DirectoryHandle as _, FileHandle as _, WritableFileStream as _,
     persistent::{self, DirectoryHandle},
 };
-use std::{collections::BTreeMap, sync::LazyLock};
+use std::{
+    collections::BTreeMap,
+    sync::{Arc, LazyLock, Mutex},
+};
 use xxhash_rust::const_xxh3::xxh3_64 as const_xxh3;
 
 use crate::utils::{self, hit_ai_server};
@@ -52,8 +57,215 @@ static LANGUAGE_DATA_HASHES: LazyLock<BTreeMap<Course, &'static str>> = LazyLock
     hashes
 });
 
-fn language_data_hash_for_course(course: Course) -> Option<&'static str> {
-    LANGUAGE_DATA_HASHES.get(&course).copied()
+/// Hashes for courses the backend advertised that this build doesn't know about
+static REMOTE_LANGUAGE_DATA_HASHES: LazyLock<Mutex<BTreeMap<Course, String>>> =
+    LazyLock::new(|| Mutex::new(BTreeMap::new()));
+
+/// Packs the user loaded with `Weapon::load_language_pack_from_bytes`. These take priority over
+/// every other hash, since the user asked for them explicitly.
+static SIDELOADED_LANGUAGE_DATA_HASHES: LazyLock<Mutex<BTreeMap<Course, String>>> =
+    LazyLock::new(|| Mutex::new(BTreeMap::new()));
+
+/// Where `SIDELOADED_LANGUAGE_DATA_HASHES` is kept between sessions, in the data directory
+const SIDELOADED_PACKS_FILE_NAME: &str = "sideloaded_packs.json";
+
+/// Hashes of the beta packs the backend advertised, used instead of the stable ones while the
+/// user is on `PackChannel::Beta`
+static BETA_LANGUAGE_DATA_HASHES: LazyLock<Mutex<BTreeMap<Course, String>>> =
+    LazyLock::new(|| Mutex::new(BTreeMap::new()));
+
+/// Which channel the current user gets packs from, see `Weapon::set_pack_channel`
+static PACK_CHANNEL: Mutex<PackChannel> = Mutex::new(PackChannel::Stable);
+
+/// Packs the current user is pinned to with `Weapon::pin_language_pack`, whatever their channel
+static PINNED_LANGUAGE_DATA_HASHES: LazyLock<Mutex<BTreeMap<Course, String>>> =
+    LazyLock::new(|| Mutex::new(BTreeMap::new()));
+
+/// Where `PACK_CHANNEL` and `PINNED_LANGUAGE_DATA_HASHES` are kept between sessions, in the
+/// user's directory
+const PACK_SETTINGS_FILE_NAME: &str = "pack_settings.json";
+
+#[derive(Debug, Default, serde::Serialize, serde::Deserialize)]
+struct PackSettings {
+    #[serde(default)]
+    channel: PackChannel,
+    #[serde(default)]
+    pinned: Vec<(Course, String)>,
+}
+
+/// Why a course's pack is the one it is
+#[derive(Clone, Copy, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize, tsify::Tsify)]
+#[tsify(into_wasm_abi, from_wasm_abi)]
+pub enum PackOrigin {
+    Sideloaded,
+    Pinned,
+    Channel(PackChannel),
+}
+
+impl PackOrigin {
+    /// How to ask the server for a pack from here
+    fn query(self, language_data_hash: &str) -> PackQuery {
+        match self {
+            PackOrigin::Pinned => PackQuery {
+                channel: PackChannel::Stable,
+                version: Some(language_data_hash.to_string()),
+            },
+            PackOrigin::Channel(channel) => PackQuery {
+                channel,
+                version: None,
+            },
+            // Never downloaded, see `download_and_cache_language_data`
+            PackOrigin::Sideloaded => PackQuery::default(),
+        }
+    }
+}
+
+/// The pack that was loaded for a course, see `Weapon::get_loaded_pack_info`
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize, tsify::Tsify)]
+#[tsify(into_wasm_abi, from_wasm_abi)]
+pub struct LoadedPackInfo {
+    pub course: Course,
+    /// The pack's hash, which `Weapon::pin_language_pack` takes to keep the user on it
+    pub version: String,
+    pub origin: PackOrigin,
+}
+
+static LOADED_PACKS: LazyLock<Mutex<BTreeMap<Course, LoadedPackInfo>>> =
+    LazyLock::new(|| Mutex::new(BTreeMap::new()));
+
+/// The spelling variants of each language in the packs loaded so far. Grading functions only get
+/// a `Course` from JS, so they look the variants up here rather than in the pack.
+static SPELLING_VARIANTS: LazyLock<Mutex<BTreeMap<Language, Arc<SpellingVariants>>>> =
+    LazyLock::new(|| Mutex::new(BTreeMap::new()));
+
+fn register_spelling_variants(course: Course, pack: &LanguagePack) {
+    let mut registered = SPELLING_VARIANTS.lock().unwrap();
+    for language in [course.target_language, course.native_language] {
+        if let Some(variants) = pack.spelling_variants(language) {
+            registered.insert(language, Arc::new(variants.clone()));
+        }
+    }
+}
+
+/// `None` if no loaded pack has spelling variants for `language`
+pub(crate) fn spelling_variants(language: Language) -> Option<Arc<SpellingVariants>> {
+    SPELLING_VARIANTS.lock().unwrap().get(&language).cloned()
+}
+
+pub(crate) fn has_language_data_hash(course: Course) -> bool {
+    language_data_hash_for_course(course).is_some()
+}
+
+/// Lets courses from the backend's course list be downloaded. Hashes compiled into this build
+/// take priority, since they're what the rest of the build was tested against.
+pub(crate) fn register_remote_language_data_hash(course: Course, hash: String) {
+    if LANGUAGE_DATA_HASHES.contains_key(&course) || hash.parse::<u64>().is_err() {
+        return;
+    }
+    REMOTE_LANGUAGE_DATA_HASHES
+        .lock()
+        .unwrap()
+        .insert(course, hash);
+}
+
+/// Lets users on the beta channel get `hash` for `course` instead of its stable pack
+pub(crate) fn register_beta_language_data_hash(course: Course, hash: String) {
+    if hash.parse::<u64>().is_err() {
+        return;
+    }
+    BETA_LANGUAGE_DATA_HASHES
+        .lock()
+        .unwrap()
+        .insert(course, hash);
+}
+
+pub(crate) fn pack_channel() -> PackChannel {
+    *PACK_CHANNEL.lock().unwrap()
+}
+
+pub(crate) fn loaded_pack_info(course: Course) -> Option<LoadedPackInfo> {
+    LOADED_PACKS.lock().unwrap().get(&course).cloned()
+}
+
+/// Switches the current user to `channel`, returning whether anything changed
+pub(crate) async fn set_pack_channel(
+    user_directory_handle: &DirectoryHandle,
+    channel: PackChannel,
+) -> Result<bool, persistent::Error> {
+    let previous = std::mem::replace(&mut *PACK_CHANNEL.lock().unwrap(), channel);
+    if previous == channel {
+        return Ok(false);
+    }
+    save_pack_settings(user_directory_handle).await?;
+    Ok(true)
+}
+
+/// Keeps the current user on the pack with hash `version` for `course`, or lets them follow
+/// their channel again if it's `None`
+pub(crate) async fn pin_language_pack(
+    user_directory_handle: &DirectoryHandle,
+    course: Course,
+    version: Option<String>,
+) -> Result<(), LanguageDataError> {
+    {
+        let mut pinned = PINNED_LANGUAGE_DATA_HASHES.lock().unwrap();
+        match version {
+            Some(version) => {
+                if version.parse::<u64>().is_err() {
+                    return Err(LanguageDataError::InvalidPack(format!(
+                        "{version} isn't a language pack version"
+                    )));
+                }
+                pinned.insert(course, version);
+            }
+            None => {
+                pinned.remove(&course);
+            }
+        }
+    }
+    save_pack_settings(user_directory_handle)
+        .await
+        .map_err(LanguageDataError::Persistent)
+}
+
+pub(crate) fn sideloaded_courses() -> Vec<Course> {
+    SIDELOADED_LANGUAGE_DATA_HASHES
+        .lock()
+        .unwrap()
+        .keys()
+        .copied()
+        .collect()
+}
+
+fn is_sideloaded(course: Course) -> bool {
+    SIDELOADED_LANGUAGE_DATA_HASHES
+        .lock()
+        .unwrap()
+        .contains_key(&course)
+}
+
+fn language_data_hash_for_course(course: Course) -> Option<(String, PackOrigin)> {
+    let hash_from =
+        |hashes: &Mutex<BTreeMap<Course, String>>| hashes.lock().unwrap().get(&course).cloned();
+    let channel = pack_channel();
+
+    let sideloaded = hash_from(&SIDELOADED_LANGUAGE_DATA_HASHES);
+    let pinned = hash_from(&PINNED_LANGUAGE_DATA_HASHES);
+    let beta = match channel {
+        PackChannel::Beta => hash_from(&BETA_LANGUAGE_DATA_HASHES),
+        PackChannel::Stable => None,
+    };
+    sideloaded
+        .map(|hash| (hash, PackOrigin::Sideloaded))
+        .or_else(|| pinned.map(|hash| (hash, PackOrigin::Pinned)))
+        .or_else(|| beta.map(|hash| (hash, PackOrigin::Channel(PackChannel::Beta))))
+        .or_else(|| {
+            LANGUAGE_DATA_HASHES
+                .get(&course)
+                .map(|hash| hash.to_string())
+                .or_else(|| hash_from(&REMOTE_LANGUAGE_DATA_HASHES))
+                .map(|hash| (hash, PackOrigin::Channel(PackChannel::Stable)))
+        })
 }
 
 fn course_directory_slug(course: Course) -> String {
@@ -64,10 +276,36 @@ fn course_directory_slug(course: Course) -> String {
     )
 }
 
+/// How far along a language pack download is
+#[derive(Clone, Copy, Debug, serde::Serialize, serde::Deserialize, tsify::Tsify)]
+#[tsify(into_wasm_abi, from_wasm_abi)]
+pub struct DownloadProgress {
+    pub downloaded_bytes: usize,
+    /// `None` if the server didn't say how big the pack is
+    pub total_bytes: Option<usize>,
+}
+
+/// Lets the caller follow and cancel a language pack download
+pub(crate) struct DownloadOptions<'a> {
+    pub on_progress: &'a dyn Fn(DownloadProgress),
+    /// Checked between segments, so cancelling takes effect once the current segment arrives
+    pub signal: Option<&'a web_sys::AbortSignal>,
+}
+
+impl DownloadOptions<'_> {
+    fn check_cancelled(&self) -> Result<(), LanguageDataError> {
+        if self.signal.is_some_and(|signal| signal.aborted()) {
+            return Err(LanguageDataError::Cancelled);
+        }
+        Ok(())
+    }
+}
+
 pub(crate) async fn get_language_pack(
     data_directory_handle: &DirectoryHandle,
     course: Course,
     set_loading_state: &impl Fn(&str),
+    download: &DownloadOptions<'_>,
 ) -> Result<LanguagePack, LanguageDataError> {
     let _perf_timer = utils::PerfTimer::new("get_language_pack");
     let course_directory = course_directory_slug(course);
@@ -79,8 +317,9 @@ pub(crate) async fn get_language_pack(
         .await
         .map_err(LanguageDataError::Persistent)?;
 
-    let language_data_hash = language_data_hash_for_course(course)
+    let (language_data_hash, origin) = language_data_hash_for_course(course)
         .ok_or(LanguageDataError::UnsupportedCourse(course))?;
+    let query = origin.query(&language_data_hash);
     log::info!(
         "expected language_data_hash for {:?}->{:?}: {language_data_hash}",
         course.native_language,
@@ -109,8 +348,10 @@ pub(crate) async fn get_language_pack(
             download_and_cache_language_data(
                 &mut language_directory,
                 course,
-                language_data_hash,
+                &language_data_hash,
+                &query,
                 set_loading_state,
+                download,
             )
             .await?
         } else {
@@ -125,8 +366,10 @@ pub(crate) async fn get_language_pack(
         download_and_cache_language_data(
             &mut language_directory,
             course,
-            language_data_hash,
+            &language_data_hash,
+            &query,
             set_loading_state,
+            download,
         )
         .await?
     };
@@ -147,8 +390,10 @@ pub(crate) async fn get_language_pack(
             let bytes = download_and_cache_language_data(
                 &mut language_directory,
                 course,
-                language_data_hash,
+                &language_data_hash,
+                &query,
                 set_loading_state,
+                download,
             )
             .await?;
             let archived = rkyv::access::<ArchivedLanguagePack, rkyv::rancor::Error>(&bytes[..])
@@ -166,9 +411,200 @@ pub(crate) async fn get_language_pack(
 
     drop(loading_perf_timer);
 
+    register_spelling_variants(course, &deserialized);
+    LOADED_PACKS.lock().unwrap().insert(
+        course,
+        LoadedPackInfo {
+            course,
+            version: language_data_hash,
+            origin,
+        },
+    );
+
     Ok(deserialized)
 }
 
+/// Registers the packs sideloaded in earlier sessions. Call this once on startup.
+pub(crate) async fn load_sideloaded_packs(
+    data_directory_handle: &DirectoryHandle,
+) -> Result<(), persistent::Error> {
+    let Ok(file_handle) = data_directory_handle
+        .get_file_handle_with_options(
+            SIDELOADED_PACKS_FILE_NAME,
+            &opfs::GetFileHandleOptions { create: false },
+        )
+        .await
+    else {
+        return Ok(());
+    };
+    let bytes = file_handle.read().await?;
+    let Ok(packs) = serde_json::from_slice::<Vec<(Course, String)>>(&bytes)
+        .inspect_err(|e| log::error!("Sideloaded pack list was invalid: {e:?}"))
+    else {
+        return Ok(());
+    };
+    SIDELOADED_LANGUAGE_DATA_HASHES
+        .lock()
+        .unwrap()
+        .extend(packs);
+    Ok(())
+}
+
+/// Restores the current user's pack channel and pins. Call this once on startup.
+pub(crate) async fn load_pack_settings(
+    user_directory_handle: &DirectoryHandle,
+) -> Result<(), persistent::Error> {
+    let Ok(file_handle) = user_directory_handle
+        .get_file_handle_with_options(
+            PACK_SETTINGS_FILE_NAME,
+            &opfs::GetFileHandleOptions { create: false },
+        )
+        .await
+    else {
+        return Ok(());
+    };
+    let bytes = file_handle.read().await?;
+    let Ok(settings) = serde_json::from_slice::<PackSettings>(&bytes)
+        .inspect_err(|e| log::error!("Pack settings were invalid: {e:?}"))
+    else {
+        return Ok(());
+    };
+    *PACK_CHANNEL.lock().unwrap() = settings.channel;
+    *PINNED_LANGUAGE_DATA_HASHES.lock().unwrap() = settings.pinned.into_iter().collect();
+    Ok(())
+}
+
+async fn save_pack_settings(
+    user_directory_handle: &DirectoryHandle,
+) -> Result<(), persistent::Error> {
+    let settings = PackSettings {
+        channel: pack_channel(),
+        pinned: PINNED_LANGUAGE_DATA_HASHES
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(course, hash)| (*course, hash.clone()))
+            .collect(),
+    };
+    let json = serde_json::to_vec(&settings).expect("pack settings always serialize");
+    write_json(user_directory_handle, PACK_SETTINGS_FILE_NAME, json).await
+}
+
+async fn write_json(
+    directory_handle: &DirectoryHandle,
+    file_name: &str,
+    json: Vec<u8>,
+) -> Result<(), persistent::Error> {
+    let mut file_handle = directory_handle
+        .get_file_handle_with_options(file_name, &opfs::GetFileHandleOptions { create: true })
+        .await?;
+    let mut writable = file_handle
+        .create_writable_with_options(&opfs::CreateWritableOptions {
+            keep_existing_data: false,
+        })
+        .await?;
+    writable.write_at_cursor_pos(json).await?;
+    writable.close().await?;
+    Ok(())
+}
+
+async fn save_sideloaded_packs(
+    data_directory_handle: &DirectoryHandle,
+) -> Result<(), persistent::Error> {
+    let packs: Vec<(Course, String)> = SIDELOADED_LANGUAGE_DATA_HASHES
+        .lock()
+        .unwrap()
+        .iter()
+        .map(|(course, hash)| (*course, hash.clone()))
+        .collect();
+    let json = serde_json::to_vec(&packs).expect("courses and hashes always serialize");
+    write_json(data_directory_handle, SIDELOADED_PACKS_FILE_NAME, json).await
+}
+
+/// Checks that a sideloaded pack is something the rest of the app can work with. rkyv has already
+/// checked that the bytes are a well-formed pack, so this only catches packs that are empty or
+/// labelled with a nonsensical course.
+fn validate_sideloaded_pack(course: Course, pack: &LanguagePack) -> Result<(), LanguageDataError> {
+    let problem = if course.native_language == course.target_language {
+        "the target and native languages are the same"
+    } else if pack.word_frequencies.is_empty() {
+        "the pack has no words"
+    } else if pack.sentences_to_literals.is_empty() {
+        "the pack has no sentences"
+    } else if pack.dictionary.is_empty() {
+        "the pack has no dictionary"
+    } else {
+        return Ok(());
+    };
+    Err(LanguageDataError::InvalidPack(problem.to_string()))
+}
+
+/// Validates a pack the user generated themselves, caches it in OPFS and registers its course, so
+/// `get_language_pack` finds it from now on (even after a reload) without hitting the server.
+pub(crate) async fn sideload_language_pack(
+    data_directory_handle: &DirectoryHandle,
+    course: Course,
+    bytes: Vec<u8>,
+) -> Result<LanguagePack, LanguageDataError> {
+    let archived = rkyv::access::<ArchivedLanguagePack, rkyv::rancor::Error>(&bytes[..])
+        .map_err(LanguageDataError::Rkyv)?;
+    let pack = rkyv::deserialize::<LanguagePack, rkyv::rancor::Error>(archived)
+        .map_err(LanguageDataError::Rkyv)?;
+    validate_sideloaded_pack(course, &pack)?;
+
+    let language_data_hash = const_xxh3(&bytes).to_string();
+    let language_directory = data_directory_handle
+        .get_directory_handle_with_options(
+            &course_directory_slug(course),
+            &opfs::GetDirectoryHandleOptions { create: true },
+        )
+        .await
+        .map_err(LanguageDataError::Persistent)?;
+    write_file(
+        &language_directory,
+        &format!("language_data_{language_data_hash}.rkyv"),
+        bytes,
+    )
+    .await?;
+
+    SIDELOADED_LANGUAGE_DATA_HASHES
+        .lock()
+        .unwrap()
+        .insert(course, language_data_hash);
+    save_sideloaded_packs(data_directory_handle)
+        .await
+        .map_err(LanguageDataError::Persistent)?;
+
+    register_spelling_variants(course, &pack);
+    Ok(pack)
+}
+
+async fn write_file(
+    language_directory_handle: &DirectoryHandle,
+    file_name: &str,
+    bytes: Vec<u8>,
+) -> Result<(), LanguageDataError> {
+    let mut language_data_file = language_directory_handle
+        .get_file_handle_with_options(file_name, &opfs::GetFileHandleOptions { create: true })
+        .await
+        .map_err(LanguageDataError::Persistent)?;
+    let mut writable = language_data_file
+        .create_writable_with_options(&opfs::CreateWritableOptions {
+            keep_existing_data: false,
+        })
+        .await
+        .map_err(LanguageDataError::Persistent)?;
+    writable
+        .write_at_cursor_pos(bytes)
+        .await
+        .map_err(LanguageDataError::Persistent)?;
+    writable
+        .close()
+        .await
+        .map_err(LanguageDataError::Persistent)?;
+    Ok(())
+}
+
 #[derive(Debug, thiserror::Error)]
 pub enum LanguageDataError {
     #[error("OPFS error: {0:?}")]
@@ -182,6 +618,24 @@ pub enum LanguageDataError {
 
     #[error("Unsupported course: {0:?}")]
     UnsupportedCourse(Course),
+
+    #[error("Invalid language pack: {0}")]
+    InvalidPack(String),
+
+    #[error("The sideloaded language pack for {0:?} is missing, load it again")]
+    SideloadedPackMissing(Course),
+
+    #[error("The language pack for {0:?} kept arriving corrupted")]
+    Corrupted(Course),
+
+    #[error("The language pack download was cancelled")]
+    Cancelled,
+
+    #[error("The server doesn't have the requested language pack for {0:?}")]
+    PackUnavailable(Course),
+
+    #[error("Couldn't fetch the language pack manifest: {0}")]
+    ManifestUnavailable(String),
 }
 
 impl From<LanguageDataError> for wasm_bindgen::JsValue {
@@ -199,34 +653,244 @@ impl From<LanguageDataError> for wasm_bindgen::JsValue {
             LanguageDataError::UnsupportedCourse(course) => {
                 wasm_bindgen::JsValue::from_str(&format!("Unsupported course: {course:?}"))
             }
+            error @ (LanguageDataError::InvalidPack(_)
+            | LanguageDataError::SideloadedPackMissing(_)
+            | LanguageDataError::Corrupted(_)
+            | LanguageDataError::Cancelled
+            | LanguageDataError::PackUnavailable(_)
+            | LanguageDataError::ManifestUnavailable(_)) => {
+                wasm_bindgen::JsValue::from_str(&error.to_string())
+            }
         }
     }
 }
 
-async fn download_and_cache_language_data(
-    language_directory_handle: &mut DirectoryHandle,
+/// How many times we fetch a segment that keeps arriving corrupted before giving up
+const MAX_SEGMENT_ATTEMPTS: usize = 3;
+
+async fn fetch_pack_manifest(course: Course, query: &PackQuery) -> Result<PackManifest, String> {
+    let response = hit_ai_server(
+        fetch_happen::Method::POST,
+        &format!(
+            "{}{}",
+            backend_routes::LANGUAGE_DATA_MANIFEST_PATH,
+            query.to_query_string()
+        ),
+        Some(course),
+        None,
+    )
+    .await
+    .map_err(|e| format!("Request error: {e:?}"))?;
+    if !response.ok() {
+        return Err(format!("Server returned {}", response.status()));
+    }
+    response
+        .json()
+        .await
+        .map_err(|e| format!("Response parsing error: {e:?}"))
+}
+
+/// The segments of an earlier, unfinished download of this pack that are still good
+async fn read_partial_download(
+    language_directory_handle: &DirectoryHandle,
+    partial_file_name: &str,
+    manifest: &PackManifest,
+) -> Vec<u8> {
+    let Ok(file_handle) = language_directory_handle
+        .get_file_handle_with_options(
+            partial_file_name,
+            &opfs::GetFileHandleOptions { create: false },
+        )
+        .await
+    else {
+        return Vec::new();
+    };
+    let Ok(mut bytes) = file_handle.read().await else {
+        return Vec::new();
+    };
+
+    let complete_segments = (0..manifest.segment_sha256.len())
+        .take_while(|&segment| {
+            bytes
+                .get(manifest.segment_range(segment))
+                .is_some_and(|segment_bytes| manifest.segment_matches(segment, segment_bytes))
+        })
+        .count();
+    bytes.truncate(manifest.segment_range(complete_segments).start);
+    bytes
+}
+
+/// Packs bigger than this are big enough to suggest downloading on Wi-Fi
+const WIFI_RECOMMENDED_BYTES: usize = 20 * 1024 * 1024;
+
+/// How much starting a course downloads, see `Weapon::get_course_download_info`
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize, tsify::Tsify)]
+#[tsify(into_wasm_abi, from_wasm_abi)]
+pub struct CourseDownloadInfo {
+    pub total_bytes: usize,
+    /// The size of each segment the pack is downloaded in, in order
+    pub segment_bytes: Vec<usize>,
+    pub wifi_recommended: bool,
+}
+
+impl CourseDownloadInfo {
+    fn for_manifest(manifest: &PackManifest) -> Self {
+        Self {
+            total_bytes: manifest.total_bytes,
+            segment_bytes: (0..manifest.segment_sha256.len())
+                .map(|segment| manifest.segment_range(segment).len())
+                .collect(),
+            wifi_recommended: manifest.total_bytes > WIFI_RECOMMENDED_BYTES,
+        }
+    }
+}
+
+/// The size of the pack `course` would be downloaded from, from its manifest alone. Sideloaded
+/// packs are never downloaded, so they take nothing.
+pub(crate) async fn course_download_info(
     course: Course,
-    language_data_hash: &'static str,
-    set_loading_state: &impl Fn(&str),
+) -> Result<CourseDownloadInfo, LanguageDataError> {
+    let (language_data_hash, origin) = language_data_hash_for_course(course)
+        .ok_or(LanguageDataError::UnsupportedCourse(course))?;
+    if origin == PackOrigin::Sideloaded {
+        return Ok(CourseDownloadInfo {
+            total_bytes: 0,
+            segment_bytes: Vec::new(),
+            wifi_recommended: false,
+        });
+    }
+
+    let manifest = fetch_pack_manifest(course, &origin.query(&language_data_hash))
+        .await
+        .map_err(LanguageDataError::ManifestUnavailable)?;
+    Ok(CourseDownloadInfo::for_manifest(&manifest))
+}
+
+/// Appends the segments `bytes` is missing, checking each against the manifest and refetching
+/// the ones that arrive corrupted (mobile networks sometimes truncate responses)
+async fn fetch_remaining_segments(
+    bytes: &mut Vec<u8>,
+    course: Course,
+    query: &PackQuery,
+    manifest: &PackManifest,
+    download: &DownloadOptions<'_>,
+) -> Result<(), LanguageDataError> {
+    let first_segment = bytes.len() / manifest.segment_bytes;
+    for segment in first_segment..manifest.segment_sha256.len() {
+        let mut attempts = 0;
+        loop {
+            download.check_cancelled()?;
+            attempts += 1;
+
+            let response = hit_ai_server(
+                fetch_happen::Method::POST,
+                &format!(
+                    "{}{}",
+                    backend_routes::LANGUAGE_DATA_SEGMENT_PATH,
+                    query.to_query_string()
+                ),
+                Some(PackSegmentRequest { course, segment }),
+                None,
+            )
+            .await
+            .map_err(LanguageDataError::AiServer)?;
+            let segment_bytes = if response.ok() {
+                response
+                    .bytes()
+                    .await
+                    .map_err(LanguageDataError::AiServer)?
+            } else {
+                log::warn!(
+                    "Server returned {} for language pack segment {segment}",
+                    response.status()
+                );
+                Vec::new()
+            };
+
+            if manifest.segment_matches(segment, &segment_bytes) {
+                bytes.extend_from_slice(&segment_bytes);
+                break;
+            }
+            if attempts >= MAX_SEGMENT_ATTEMPTS {
+                return Err(LanguageDataError::Corrupted(course));
+            }
+            log::warn!("Language pack segment {segment} arrived corrupted, refetching it");
+        }
+
+        (download.on_progress)(DownloadProgress {
+            downloaded_bytes: bytes.len(),
+            total_bytes: Some(manifest.total_bytes),
+        });
+    }
+    Ok(())
+}
+
+/// Downloads the pack one `PackManifest` segment at a time. If the download is cancelled or
+/// fails, the segments we already have are kept in OPFS and the next attempt resumes from there.
+async fn download_segments(
+    language_directory_handle: &DirectoryHandle,
+    course: Course,
+    language_data_hash: &str,
+    query: &PackQuery,
+    manifest: &PackManifest,
+    download: &DownloadOptions<'_>,
 ) -> Result<Vec<u8>, LanguageDataError> {
-    set_loading_state(&format!(
-        "Downloading {:?}->{:?} language data",
-        course.native_language, course.target_language
-    ));
-    log::info!(
-        "Language data cache miss for {:?}->{:?}, fetching from server...",
-        course.native_language,
-        course.target_language
-    );
+    let partial_file_name = format!("language_data_{language_data_hash}.partial");
+    let mut bytes =
+        read_partial_download(language_directory_handle, &partial_file_name, manifest).await;
+    if !bytes.is_empty() {
+        log::info!(
+            "Resuming language pack download at {} of {} bytes",
+            bytes.len(),
+            manifest.total_bytes
+        );
+    }
+    (download.on_progress)(DownloadProgress {
+        downloaded_bytes: bytes.len(),
+        total_bytes: Some(manifest.total_bytes),
+    });
+
+    if let Err(e) = fetch_remaining_segments(&mut bytes, course, query, manifest, download).await {
+        if !bytes.is_empty()
+            && let Err(save_error) =
+                write_file(language_directory_handle, &partial_file_name, bytes).await
+        {
+            log::warn!("Failed to save the partial language pack download: {save_error:?}");
+        }
+        return Err(e);
+    }
+
+    let mut language_directory_handle = language_directory_handle.clone();
+    let _ = language_directory_handle
+        .remove_entry(&partial_file_name)
+        .await;
+    Ok(bytes)
+}
+
+/// Downloads the pack in one request, for servers that don't have manifests
+async fn download_whole_pack(
+    course: Course,
+    query: &PackQuery,
+    download: &DownloadOptions<'_>,
+) -> Result<Vec<u8>, LanguageDataError> {
+    download.check_cancelled()?;
     let response = hit_ai_server(
         fetch_happen::Method::POST,
-        "/language-data",
+        &format!(
+            "{}{}",
+            backend_routes::LANGUAGE_DATA_PATH,
+            query.to_query_string()
+        ),
         Some(course),
         None,
     )
     .await
     .map_err(LanguageDataError::AiServer)?;
 
+    // e.g. a pinned version that was never uploaded
+    if response.status() == 404 {
+        return Err(LanguageDataError::PackUnavailable(course));
+    }
     if !response.ok() {
         log::info!("Server returned error: {}", response.status());
         panic!("Server returned error: {}", response.status());
@@ -235,6 +899,52 @@ async fn download_and_cache_language_data(
         .bytes()
         .await
         .map_err(LanguageDataError::AiServer)?;
+    (download.on_progress)(DownloadProgress {
+        downloaded_bytes: bytes.len(),
+        total_bytes: Some(bytes.len()),
+    });
+    Ok(bytes)
+}
+
+async fn download_and_cache_language_data(
+    language_directory_handle: &mut DirectoryHandle,
+    course: Course,
+    language_data_hash: &str,
+    query: &PackQuery,
+    set_loading_state: &impl Fn(&str),
+    download: &DownloadOptions<'_>,
+) -> Result<Vec<u8>, LanguageDataError> {
+    // The server has never heard of sideloaded packs
+    if is_sideloaded(course) {
+        return Err(LanguageDataError::SideloadedPackMissing(course));
+    }
+
+    set_loading_state(&format!(
+        "Downloading {:?}->{:?} language data",
+        course.native_language, course.target_language
+    ));
+    log::info!(
+        "Language data cache miss for {:?}->{:?}, fetching from server...",
+        course.native_language,
+        course.target_language
+    );
+    let bytes = match fetch_pack_manifest(course, query).await {
+        Ok(manifest) => {
+            download_segments(
+                language_directory_handle,
+                course,
+                language_data_hash,
+                query,
+                &manifest,
+                download,
+            )
+            .await?
+        }
+        Err(e) => {
+            log::warn!("Couldn't fetch the language pack manifest, downloading it whole: {e}");
+            download_whole_pack(course, query, download).await?
+        }
+    };
 
     set_loading_state("Verifying language data");
     let language_data_hash = {
@@ -250,27 +960,12 @@ async fn download_and_cache_language_data(
         }
         computed_hash
     };
-    let mut language_data_file = language_directory_handle
-        .get_file_handle_with_options(
-            &format!("language_data_{language_data_hash}.rkyv"),
-            &opfs::GetFileHandleOptions { create: true },
-        )
-        .await
-        .map_err(LanguageDataError::Persistent)?;
-    let mut writable = language_data_file
-        .create_writable_with_options(&opfs::CreateWritableOptions {
-            keep_existing_data: false,
-        })
-        .await
-        .map_err(LanguageDataError::Persistent)?;
-    writable
-        .write_at_cursor_pos(bytes.clone())
-        .await
-        .map_err(LanguageDataError::Persistent)?;
-    writable
-        .close()
-        .await
-        .map_err(LanguageDataError::Persistent)?;
+    write_file(
+        language_directory_handle,
+        &format!("language_data_{language_data_hash}.rkyv"),
+        bytes.clone(),
+    )
+    .await?;
 
     set_loading_state("Cleaning up old language data files");
     // Clean up old language data files
@@ -306,3 +1001,34 @@ async fn download_and_cache_language_data(
     log::info!("Language data successfully loaded and cached!");
     Ok(bytes)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use language_utils::pack_manifest::PACK_SEGMENT_BYTES;
+
+    fn manifest(total_bytes: usize) -> PackManifest {
+        PackManifest {
+            total_bytes,
+            segment_bytes: PACK_SEGMENT_BYTES,
+            segment_sha256: vec![String::new(); total_bytes.div_ceil(PACK_SEGMENT_BYTES)],
+        }
+    }
+
+    #[test]
+    fn test_download_info_comes_from_the_manifest() {
+        let small = CourseDownloadInfo::for_manifest(&manifest(PACK_SEGMENT_BYTES + 10));
+        assert_eq!(small.total_bytes, PACK_SEGMENT_BYTES + 10);
+        assert_eq!(small.segment_bytes, vec![PACK_SEGMENT_BYTES, 10]);
+        assert!(!small.wifi_recommended);
+
+        let at_limit = CourseDownloadInfo::for_manifest(&manifest(WIFI_RECOMMENDED_BYTES));
+        assert_eq!(
+            at_limit.segment_bytes.iter().sum::<usize>(),
+            WIFI_RECOMMENDED_BYTES
+        );
+        assert!(!at_limit.wifi_recommended);
+        let large = CourseDownloadInfo::for_manifest(&manifest(WIFI_RECOMMENDED_BYTES + 1));
+        assert!(large.wifi_recommended);
+    }
+}
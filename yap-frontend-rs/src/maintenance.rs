@@ -0,0 +1,463 @@
+//! Housekeeping the app used to have to ask for: snapshotting the deck (`save_deck_snapshot`),
+//! removing audio from old voices (`AudioCache::remove_outdated`), removing images no loaded pack
+//! points to any more (`ImageCache::cleanup_except`) and folding duplicate events out of the event
+//! logs (`repair_duplicate_events`). Once the app calls `Weapon::start_maintenance`,
+//! it runs about once a day, when the browser is idle and the device is charging or has battery to
+//! spare. Everything it does is local, so nothing is uploaded.
+//!
+//! What the last run did is saved to OPFS, so a reload doesn't run it again, and is included in
+//! `Weapon::export_diagnostics`.
+
+#[cfg(target_arch = "wasm32")]
+use std::cell::{Cell, RefCell};
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, Utc};
+#[cfg(target_arch = "wasm32")]
+use language_utils::Course;
+use serde::{Deserialize, Serialize};
+#[cfg(target_arch = "wasm32")]
+use wasm_bindgen::prelude::*;
+
+#[cfg(target_arch = "wasm32")]
+use crate::Weapon;
+
+/// How long after a run the next one is due
+const INTERVAL: chrono::Duration = chrono::Duration::hours(20);
+#[cfg(target_arch = "wasm32")]
+/// How often the scheduler checks whether a run is due
+const CHECK_INTERVAL_MS: i32 = 15 * 60 * 1000;
+#[cfg(target_arch = "wasm32")]
+/// How long to wait for the browser to be idle before running anyway
+const IDLE_TIMEOUT_MS: i32 = 60 * 1000;
+/// Below this, and not charging, runs wait for the device to be plugged in
+const MIN_BATTERY_LEVEL: f64 = 0.5;
+/// The deck is only snapshotted again once it has this many more reviews than the last snapshot
+const SNAPSHOT_AFTER_EVENTS: usize = 200;
+
+#[cfg(target_arch = "wasm32")]
+const REPORT_FILE_NAME: &str = "maintenance-report";
+
+/// What a maintenance run did
+#[derive(Clone, Debug, Serialize, Deserialize, tsify::Tsify)]
+#[tsify(into_wasm_abi, from_wasm_abi)]
+pub struct MaintenanceReport {
+    pub started_at: DateTime<Utc>,
+    pub finished_at: DateTime<Utc>,
+    /// How many reviews the new deck snapshot includes, or `None` if the deck wasn't snapshotted
+    pub snapshot_events: Option<usize>,
+    pub outdated_audio_removed: usize,
+    #[serde(default)]
+    pub unused_images_removed: usize,
+    /// How many duplicate events were folded out of each stream's event log
+    pub duplicates_removed: BTreeMap<String, usize>,
+    /// Steps that failed. The others still ran.
+    pub errors: Vec<String>,
+}
+
+/// The maintenance state shared by clones of a `Weapon`
+#[cfg(target_arch = "wasm32")]
+#[derive(Default)]
+pub(crate) struct Maintenance {
+    /// The course whose deck is snapshotted, see `Weapon::start_maintenance`
+    course: Cell<Option<Course>>,
+    scheduled: Cell<bool>,
+    /// Bumped whenever maintenance is started or stopped, so an old scheduler knows to end
+    generation: Cell<u32>,
+    /// `None` until it's been read from OPFS
+    last_report: RefCell<Option<MaintenanceReport>>,
+    running: Cell<bool>,
+}
+
+#[cfg(target_arch = "wasm32")]
+impl Maintenance {
+    pub(crate) fn last_report(&self) -> Option<MaintenanceReport> {
+        self.last_report.borrow().clone()
+    }
+}
+
+/// Whether a run is due at `now`, given the report of the last one
+fn is_due(last_report: Option<&MaintenanceReport>, now: DateTime<Utc>) -> bool {
+    last_report.is_none_or(|report| now - report.finished_at >= INTERVAL)
+}
+
+/// Why a run shouldn't start now, if there's a reason. `battery` is what `battery` returns.
+fn reason_to_wait_with(metered: bool, battery: Option<(bool, f64)>) -> Option<&'static str> {
+    // Data saving and cellular connections usually mean a phone away from its charger
+    if metered {
+        return Some("the connection is metered");
+    }
+    match battery {
+        Some((false, level)) if level < MIN_BATTERY_LEVEL => Some("the battery is low"),
+        _ => None,
+    }
+}
+
+/// Whether a deck with `num_events` reviews should be snapshotted again, when its last snapshot
+/// includes `snapshotted` of them
+fn snapshot_is_stale(num_events: usize, snapshotted: usize) -> bool {
+    num_events >= snapshotted + SNAPSHOT_AFTER_EVENTS
+}
+
+/// `navigator.getBattery()`, as `(charging, level)`, if the browser has it
+#[cfg(target_arch = "wasm32")]
+async fn battery() -> Option<(bool, f64)> {
+    let navigator =
+        js_sys::Reflect::get(&js_sys::global(), &JsValue::from_str("navigator")).ok()?;
+    let get_battery = js_sys::Reflect::get(&navigator, &JsValue::from_str("getBattery"))
+        .ok()?
+        .dyn_into::<js_sys::Function>()
+        .ok()?;
+    let promise = get_battery
+        .call0(&navigator)
+        .ok()?
+        .dyn_into::<js_sys::Promise>()
+        .ok()?;
+    let manager = wasm_bindgen_futures::JsFuture::from(promise).await.ok()?;
+    let get = |key: &str| js_sys::Reflect::get(&manager, &JsValue::from_str(key)).ok();
+    Some((get("charging")?.as_bool()?, get("level")?.as_f64()?))
+}
+
+/// `reason_to_wait_with` this device's connection and battery
+#[cfg(target_arch = "wasm32")]
+async fn reason_to_wait() -> Option<&'static str> {
+    if crate::network::is_metered_connection() {
+        return reason_to_wait_with(true, None);
+    }
+    reason_to_wait_with(false, battery().await)
+}
+
+#[cfg(target_arch = "wasm32")]
+/// Calls `global[name](resolve, argument)` and waits for it to resolve. `None` if `name` isn't a
+/// function here.
+async fn wait_for(name: &str, argument: JsValue) -> Option<()> {
+    let function = js_sys::Reflect::get(&js_sys::global(), &JsValue::from_str(name))
+        .ok()?
+        .dyn_into::<js_sys::Function>()
+        .ok()?;
+    let promise = js_sys::Promise::new(&mut |resolve, _| {
+        let _ = function.call2(&js_sys::global(), &resolve, &argument);
+    });
+    let _ = wasm_bindgen_futures::JsFuture::from(promise).await;
+    Some(())
+}
+
+#[cfg(target_arch = "wasm32")]
+/// Waits for `requestIdleCallback`, or for a while where there isn't one (e.g. Safari)
+async fn idle() {
+    let options = js_sys::Object::new();
+    let _ = js_sys::Reflect::set(
+        &options,
+        &JsValue::from_str("timeout"),
+        &JsValue::from(IDLE_TIMEOUT_MS),
+    );
+    if wait_for("requestIdleCallback", options.into())
+        .await
+        .is_none()
+    {
+        wait_for("setTimeout", JsValue::from(IDLE_TIMEOUT_MS)).await;
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen]
+impl Weapon {
+    /// Runs maintenance in the background from now on (see the `maintenance` module), snapshotting
+    /// the deck for `course`. Calling it again only changes the course.
+    #[wasm_bindgen]
+    pub fn start_maintenance(&self, course: Course) {
+        self.maintenance.course.set(Some(course));
+        if self.maintenance.scheduled.replace(true) {
+            return;
+        }
+        let generation = self.maintenance.generation.get() + 1;
+        self.maintenance.generation.set(generation);
+
+        let state = std::rc::Rc::downgrade(&self.state);
+        wasm_bindgen_futures::spawn_local(async move {
+            loop {
+                wait_for("setTimeout", JsValue::from(CHECK_INTERVAL_MS)).await;
+                idle().await;
+                // Ends once the app stops maintenance or drops the `Weapon`
+                let Some(state) = state.upgrade() else {
+                    break;
+                };
+                let weapon = Weapon { state };
+                if weapon.maintenance.generation.get() != generation {
+                    break;
+                }
+                weapon.maintain_if_due().await;
+            }
+        });
+    }
+
+    #[wasm_bindgen]
+    pub fn stop_maintenance(&self) {
+        if self.maintenance.scheduled.replace(false) {
+            let generation = self.maintenance.generation.get() + 1;
+            self.maintenance.generation.set(generation);
+        }
+    }
+
+    /// Runs maintenance now, whether or not it's due or the device is on battery. `None` if it's
+    /// already running.
+    #[wasm_bindgen]
+    pub async fn run_maintenance(&self) -> Option<MaintenanceReport> {
+        self.maintain().await
+    }
+
+    /// What the last maintenance run did, `None` if there hasn't been one
+    #[wasm_bindgen]
+    pub async fn get_last_maintenance_report(&self) -> Option<MaintenanceReport> {
+        self.load_maintenance_report().await;
+        self.maintenance.last_report()
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+impl Weapon {
+    async fn load_maintenance_report(&self) {
+        use opfs::{DirectoryHandle as _, FileHandle as _};
+
+        if self.maintenance.last_report.borrow().is_some() {
+            return;
+        }
+        let Ok(file_handle) = self
+            .directories
+            .weapon_directory_handle
+            .get_file_handle_with_options(
+                REPORT_FILE_NAME,
+                &opfs::GetFileHandleOptions { create: false },
+            )
+            .await
+        else {
+            return;
+        };
+        let report = match file_handle.read().await {
+            Ok(bytes) => serde_json::from_slice(&bytes)
+                .inspect_err(|e| log::error!("Maintenance report was invalid: {e:?}"))
+                .ok(),
+            Err(e) => {
+                log::warn!("Failed to read the maintenance report: {e:?}");
+                None
+            }
+        };
+        *self.maintenance.last_report.borrow_mut() = report;
+    }
+
+    async fn save_maintenance_report(&self, report: &MaintenanceReport) {
+        use opfs::{DirectoryHandle as _, WritableFileStream as _};
+
+        let result = async {
+            let json = serde_json::to_vec(report).expect("reports always serialize");
+            let mut file_handle = self
+                .directories
+                .weapon_directory_handle
+                .get_file_handle_with_options(
+                    REPORT_FILE_NAME,
+                    &opfs::GetFileHandleOptions { create: true },
+                )
+                .await?;
+            let mut writable = file_handle
+                .create_writable_with_options(&opfs::CreateWritableOptions {
+                    keep_existing_data: false,
+                })
+                .await?;
+            writable.write_at_cursor_pos(json).await?;
+            writable.close().await
+        }
+        .await;
+        if let Err(e) = result {
+            log::warn!("Failed to save the maintenance report: {e:?}");
+        }
+    }
+
+    async fn maintain_if_due(&self) {
+        self.load_maintenance_report().await;
+        if !is_due(self.maintenance.last_report().as_ref(), Utc::now()) {
+            return;
+        }
+        if let Some(reason) = reason_to_wait().await {
+            log::info!("Maintenance is due, but waiting because {reason}");
+            return;
+        }
+        self.maintain().await;
+    }
+
+    async fn maintain(&self) -> Option<MaintenanceReport> {
+        if self.maintenance.running.replace(true) {
+            return None;
+        }
+        let started_at = Utc::now();
+        let mut errors = Vec::new();
+        let mut error = |step: &str, e: JsValue| {
+            let message = format!(
+                "{step}: {}",
+                e.as_string().unwrap_or_else(|| format!("{e:?}"))
+            );
+            log::warn!("Maintenance step failed, {message}");
+            errors.push(message);
+        };
+
+        let snapshot_events = match self.snapshot_deck_if_stale().await {
+            Ok(snapshot_events) => snapshot_events,
+            Err(e) => {
+                error("snapshot", e);
+                None
+            }
+        };
+
+        let outdated_audio_removed = match crate::audio::AudioCache::new().await {
+            Ok(mut cache) => cache.remove_outdated().await,
+            Err(e) => Err(e),
+        }
+        .unwrap_or_else(|e| {
+            error("audio cleanup", e);
+            0
+        });
+
+        let unused_images_removed = self.remove_unused_images().await.unwrap_or_else(|e| {
+            error("image cleanup", e);
+            0
+        });
+
+        let mut duplicates_removed = BTreeMap::new();
+        let streams_with_duplicates: Vec<String> = {
+            let store = self.store.borrow();
+            store
+                .iter()
+                .map(|(stream_id, _)| stream_id.clone())
+                .filter(|stream_id| !store.duplicates(stream_id.clone()).is_empty())
+                .collect()
+        };
+        for stream_id in streams_with_duplicates {
+            match self.repair_duplicate_events(stream_id.clone()).await {
+                Ok(removed) => {
+                    duplicates_removed.insert(stream_id, removed.len());
+                }
+                Err(e) => error(&format!("folding {stream_id}"), e),
+            }
+        }
+
+        let report = MaintenanceReport {
+            started_at,
+            finished_at: Utc::now(),
+            snapshot_events,
+            outdated_audio_removed,
+            unused_images_removed,
+            duplicates_removed,
+            errors,
+        };
+        log::info!(
+            "Maintenance finished: snapshot of {:?} reviews, {} outdated audio files removed, \
+             {} unused images removed, duplicates removed {:?}, {} errors",
+            report.snapshot_events,
+            report.outdated_audio_removed,
+            report.unused_images_removed,
+            report.duplicates_removed,
+            report.errors.len()
+        );
+        self.save_maintenance_report(&report).await;
+        *self.maintenance.last_report.borrow_mut() = Some(report.clone());
+        self.maintenance.running.set(false);
+        Some(report)
+    }
+
+    /// Removes cached images that none of the loaded packs point to. Nothing is removed before a
+    /// pack has loaded, since then every image would look unused.
+    async fn remove_unused_images(&self) -> Result<usize, JsValue> {
+        let keep = {
+            let packs = self.language_pack.borrow();
+            if packs.is_empty() {
+                return Ok(0);
+            }
+            packs
+                .values()
+                .flat_map(|pack| pack.movies.values())
+                .flat_map(|movie| &movie.posters)
+                .map(|poster| poster.file_name())
+                .collect()
+        };
+        crate::images::ImageCache::new()
+            .await?
+            .cleanup_except(keep)
+            .await
+    }
+
+    /// Snapshots the deck if it has `SNAPSHOT_AFTER_EVENTS` more reviews than its last snapshot,
+    /// returning how many the new one includes
+    async fn snapshot_deck_if_stale(&self) -> Result<Option<usize>, JsValue> {
+        let Some(course) = self.maintenance.course.get() else {
+            return Ok(None);
+        };
+        let Some(pack) = self.language_pack.borrow().get(&course).cloned() else {
+            return Ok(None);
+        };
+        let stream_id = self.reviews_stream_id();
+        let num_events = self
+            .store
+            .borrow()
+            .get_raw(stream_id.clone())
+            .map_or(0, |stream| stream.num_events());
+        let snapshotted = self
+            .snapshots
+            .borrow()
+            .get(&stream_id)
+            .map_or(0, |snapshot| snapshot.num_events());
+        if !snapshot_is_stale(num_events, snapshotted) {
+            return Ok(None);
+        }
+
+        let language_pack = crate::FetchedLanguagePack { pack };
+        let snapshot_events = self
+            .save_deck_snapshot(&language_pack, course, None)
+            .await?;
+        Ok(Some(snapshot_events))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_runs_are_due_once_the_interval_has_passed() {
+        let now = Utc::now();
+        let finished_at = |ago: chrono::Duration| MaintenanceReport {
+            started_at: now - ago - chrono::Duration::minutes(1),
+            finished_at: now - ago,
+            snapshot_events: None,
+            outdated_audio_removed: 0,
+            unused_images_removed: 0,
+            duplicates_removed: BTreeMap::new(),
+            errors: Vec::new(),
+        };
+        assert!(is_due(None, now));
+        assert!(!is_due(Some(&finished_at(chrono::Duration::hours(1))), now));
+        assert!(is_due(Some(&finished_at(INTERVAL)), now));
+    }
+
+    #[test]
+    fn test_runs_wait_for_an_unmetered_connection_and_enough_battery() {
+        assert_eq!(
+            reason_to_wait_with(true, Some((true, 1.0))),
+            Some("the connection is metered")
+        );
+        assert_eq!(
+            reason_to_wait_with(false, Some((false, 0.2))),
+            Some("the battery is low")
+        );
+        // Charging, or a browser that doesn't say
+        assert_eq!(reason_to_wait_with(false, Some((true, 0.2))), None);
+        assert_eq!(reason_to_wait_with(false, Some((false, 0.8))), None);
+        assert_eq!(reason_to_wait_with(false, None), None);
+    }
+
+    #[test]
+    fn test_snapshots_wait_for_enough_new_reviews() {
+        assert!(!snapshot_is_stale(SNAPSHOT_AFTER_EVENTS - 1, 0));
+        assert!(snapshot_is_stale(SNAPSHOT_AFTER_EVENTS, 0));
+        assert!(!snapshot_is_stale(1000 + SNAPSHOT_AFTER_EVENTS - 1, 1000));
+        assert!(snapshot_is_stale(1000 + SNAPSHOT_AFTER_EVENTS, 1000));
+    }
+}
@@ -0,0 +1,100 @@
+//! Definitions and translations in every language a bilingual user speaks (see
+//! `DeckSelection::native_languages`), not just the course's native language. They come from the
+//! packs for the course's target language and each native language, so only the languages whose
+//! pack was loaded this session (see `Weapon::get_language_pack`) are available.
+
+use std::sync::Arc;
+
+use language_utils::language_pack::LanguagePack;
+use language_utils::{Course, DictionaryEntry, Heteronym, Language};
+use wasm_bindgen::prelude::*;
+
+use crate::Weapon;
+
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize, tsify::Tsify)]
+#[tsify(into_wasm_abi, from_wasm_abi)]
+pub struct NativeDictionaryEntry {
+    pub native_language: Language,
+    pub entry: DictionaryEntry,
+}
+
+/// A sentence's translations into one of the user's languages
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize, tsify::Tsify)]
+#[tsify(into_wasm_abi, from_wasm_abi)]
+pub struct NativeTranslations {
+    pub native_language: Language,
+    pub translations: Vec<String>,
+}
+
+impl Weapon {
+    /// The loaded packs from `target_language` to each of the user's languages, most preferred
+    /// first
+    fn native_language_packs(
+        &self,
+        target_language: Language,
+    ) -> Vec<(Language, Arc<LanguagePack>)> {
+        let packs = self.language_pack.borrow();
+        self.get_native_languages()
+            .into_iter()
+            .filter_map(|native_language| {
+                let course = Course {
+                    native_language,
+                    target_language,
+                };
+                Some((native_language, Arc::clone(packs.get(&course)?)))
+            })
+            .collect()
+    }
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+impl Weapon {
+    /// The languages the user speaks, most preferred first, see `DeckSelection::native_languages`.
+    /// Empty until they've picked a course.
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn get_native_languages(&self) -> Vec<Language> {
+        self.get_deck_selection_state()
+            .map(|selection| selection.native_languages())
+            .unwrap_or_default()
+    }
+
+    /// `heteronym`'s dictionary entry in each of the user's languages that has one
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn get_native_dictionary_entries(
+        &self,
+        target_language: Language,
+        heteronym: Heteronym<String>,
+    ) -> Vec<NativeDictionaryEntry> {
+        self.native_language_packs(target_language)
+            .into_iter()
+            .filter_map(|(native_language, pack)| {
+                let entry = pack.dictionary.get(&heteronym.get_interned(&pack.rodeo)?)?;
+                Some(NativeDictionaryEntry {
+                    native_language,
+                    entry: entry.clone(),
+                })
+            })
+            .collect()
+    }
+
+    /// `sentence`'s translations into each of the user's languages that has any. Pass the ones
+    /// other than the course's native language to `autograde_translation`, so the user can answer
+    /// in any of them.
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn get_native_translations(
+        &self,
+        target_language: Language,
+        sentence: String,
+    ) -> Vec<NativeTranslations> {
+        self.native_language_packs(target_language)
+            .into_iter()
+            .filter_map(|(native_language, pack)| {
+                let translations = pack.translations.get(&pack.rodeo.get(&sentence)?)?;
+                Some(NativeTranslations {
+                    native_language,
+                    translations: translations.iter().map(|t| t.to_string()).collect(),
+                })
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,429 @@
+//! Keeping syncing and audio prefetching cheap on metered connections. The app configures a
+//! `SyncThrottle` with `Weapon::set_sync_throttle`; work that doesn't fit it is queued as
+//! `DeferredWork` instead of running, and handed back to the app once the connection is
+//! unmetered again.
+
+use std::cell::{Cell, RefCell};
+use std::collections::BTreeSet;
+
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::prelude::*;
+use weapon::data_model::{EventStore, ListenerKey};
+use weapon::supabase::{SupabaseSyncPreview, SupabaseSyncResult};
+
+use crate::{Weapon, supabase};
+
+/// How much syncing is allowed to download, and what waits for an unmetered connection
+#[derive(Clone, Debug, PartialEq, tsify::Tsify, Serialize, Deserialize)]
+#[tsify(into_wasm_abi, from_wasm_abi)]
+pub struct SyncThrottle {
+    /// Syncs that would take the bytes synced since the app started over this are deferred.
+    /// `None` means there's no cap.
+    #[serde(default)]
+    pub max_bytes_per_session: Option<usize>,
+    /// On a metered connection, syncs that would download more than this are deferred. Local
+    /// events are still uploaded, so they aren't only on this device.
+    #[serde(default = "default_large_pull_bytes")]
+    pub large_pull_bytes: usize,
+    /// Whether to wait for an unmetered connection before prefetching challenge audio
+    #[serde(default = "default_true")]
+    pub defer_audio_prefetch: bool,
+}
+
+fn default_large_pull_bytes() -> usize {
+    256 * 1024
+}
+
+fn default_true() -> bool {
+    true
+}
+
+impl Default for SyncThrottle {
+    fn default() -> Self {
+        Self {
+            max_bytes_per_session: None,
+            large_pull_bytes: default_large_pull_bytes(),
+            defer_audio_prefetch: default_true(),
+        }
+    }
+}
+
+/// What a sync may do under the `SyncThrottle`, see `SyncThrottle::plan`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum SyncPlan {
+    Sync,
+    /// Defer the sync, but upload the local events now
+    UploadOnly,
+    Defer,
+}
+
+impl SyncThrottle {
+    /// What to do with a sync that would transfer what `preview` says, when `bytes_synced` have
+    /// been synced so far this session
+    fn plan(&self, preview: &SupabaseSyncPreview, bytes_synced: usize, metered: bool) -> SyncPlan {
+        let budget = self
+            .max_bytes_per_session
+            .map_or(usize::MAX, |max| max.saturating_sub(bytes_synced));
+        let bytes = preview.bytes_to_upload + preview.estimated_bytes_to_download;
+
+        if bytes <= budget
+            && !(metered && preview.estimated_bytes_to_download > self.large_pull_bytes)
+        {
+            SyncPlan::Sync
+        } else if preview.bytes_to_upload > 0 && preview.bytes_to_upload <= budget {
+            SyncPlan::UploadOnly
+        } else {
+            SyncPlan::Defer
+        }
+    }
+}
+
+/// Work that was skipped because of the `SyncThrottle`
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, tsify::Tsify, Serialize, Deserialize)]
+#[tsify(into_wasm_abi, from_wasm_abi)]
+#[serde(tag = "type")]
+pub enum DeferredWork {
+    /// A Supabase sync of `stream_id`, or of every stream if it's `None`
+    Sync {
+        stream_id: Option<String>,
+    },
+    AudioPrefetch,
+}
+
+/// The throttling state shared by clones of a `Weapon`
+#[derive(Default)]
+pub(crate) struct Throttling {
+    throttle: RefCell<SyncThrottle>,
+    /// Roughly how many bytes Supabase syncs have transferred since the app started
+    bytes_synced: Cell<usize>,
+    deferred: RefCell<BTreeSet<DeferredWork>>,
+    /// Called with the deferred work when the connection becomes unmetered
+    on_resume: RefCell<Option<js_sys::Function>>,
+    listening_for_connection_changes: Cell<bool>,
+}
+
+/// Whether the browser says the connection is metered: the user turned on data saving, or it's a
+/// cellular connection. Browsers without the Network Information API are assumed to be unmetered.
+pub fn is_metered_connection() -> bool {
+    #[cfg(target_arch = "wasm32")]
+    {
+        let Some(connection) = network_information() else {
+            return false;
+        };
+        let get = |key: &str| js_sys::Reflect::get(&connection, &JsValue::from_str(key)).ok();
+        get("saveData").and_then(|save_data| save_data.as_bool()) == Some(true)
+            || get("type").and_then(|kind| kind.as_string()).as_deref() == Some("cellular")
+    }
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        false
+    }
+}
+
+/// `navigator.connection`, if the browser has it
+#[cfg(target_arch = "wasm32")]
+fn network_information() -> Option<JsValue> {
+    let navigator = web_sys::window()?.navigator();
+    js_sys::Reflect::get(&navigator, &JsValue::from_str("connection"))
+        .ok()
+        .filter(|connection| connection.is_object())
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+impl Weapon {
+    /// Sets how syncing and audio prefetching are throttled. `on_resume` is called with the
+    /// `DeferredWork` queued in the meantime once the connection becomes unmetered, for the app to
+    /// run again (e.g. with a fresh access token).
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn set_sync_throttle(&self, throttle: SyncThrottle, on_resume: Option<js_sys::Function>) {
+        *self.throttling.throttle.borrow_mut() = throttle;
+        *self.throttling.on_resume.borrow_mut() = on_resume;
+        self.listen_for_connection_changes();
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn get_sync_throttle(&self) -> SyncThrottle {
+        self.throttling.throttle.borrow().clone()
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn is_metered_connection(&self) -> bool {
+        is_metered_connection()
+    }
+
+    /// Roughly how many bytes Supabase syncs have transferred since the app started
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn get_bytes_synced(&self) -> usize {
+        self.throttling.bytes_synced.get()
+    }
+
+    /// Removes and returns the deferred work, e.g. to run it without waiting for Wi-Fi
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub fn take_deferred_work(&self) -> Vec<DeferredWork> {
+        std::mem::take(&mut *self.throttling.deferred.borrow_mut())
+            .into_iter()
+            .collect()
+    }
+
+    /// `audio::cache_challenge_audio`, unless the throttle defers audio prefetching on this
+    /// connection. Returns whether the audio was prefetched.
+    #[cfg(target_arch = "wasm32")]
+    #[cfg(feature = "simulation")]
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub async fn cache_challenge_audio(
+        &self,
+        deck: &crate::Deck,
+        access_token: Option<String>,
+        abort_signal: Option<web_sys::AbortSignal>,
+        timestamp_ms: f64,
+    ) -> bool {
+        if self.throttling.throttle.borrow().defer_audio_prefetch && is_metered_connection() {
+            self.defer(DeferredWork::AudioPrefetch);
+            return false;
+        }
+        crate::audio::cache_challenge_audio(deck, access_token, abort_signal, timestamp_ms).await;
+        true
+    }
+}
+
+/// Posts `SummariesDirty` in the background, since the sync itself is already done
+fn mark_summaries_dirty(access_token: &str) {
+    #[cfg(target_arch = "wasm32")]
+    {
+        let access_token = access_token.to_string();
+        wasm_bindgen_futures::spawn_local(async move {
+            if let Err(e) = crate::backend::call::<language_utils::backend_routes::SummariesDirty>(
+                &(),
+                Some(&access_token),
+            )
+            .await
+            {
+                log::warn!("Failed to mark the backend's summaries dirty: {e}");
+            }
+        });
+    }
+    #[cfg(not(target_arch = "wasm32"))]
+    let _ = access_token;
+}
+
+impl Weapon {
+    fn defer(&self, work: DeferredWork) {
+        log::info!("Deferring {work:?} until the connection is unmetered");
+        self.throttling.deferred.borrow_mut().insert(work);
+    }
+
+    /// Keeps the clock baseline from the last sync for the next session, so its first sync can
+    /// send its clock as changes from it too
+    async fn save_clock_baseline(&self) {
+        if let Err(e) = EventStore::save_clock_baseline_to_local_storage(
+            &self.store,
+            &self.directories.current_user_directory_handle,
+        )
+        .await
+        {
+            log::warn!("Failed to save the clock baseline: {e:?}");
+        }
+    }
+
+    /// `EventStore::sync_with_supabase`, unless it doesn't fit the `SyncThrottle`. Then the sync is
+    /// deferred, and only the local events are uploaded if that fits. If any events moved, the
+    /// backend is told its summaries of the user are stale.
+    pub(crate) async fn throttled_sync_with_supabase(
+        &self,
+        access_token: &str,
+        user_id: &str,
+        stream_id: Option<String>,
+        modifier: Option<ListenerKey>,
+    ) -> Result<SupabaseSyncResult, JsValue> {
+        let result = self
+            .sync_within_throttle(access_token, user_id, stream_id, modifier)
+            .await?;
+        if result.uploaded_to_supabase + result.downloaded_from_supabase > 0 {
+            mark_summaries_dirty(access_token);
+        }
+        Ok(result)
+    }
+
+    async fn sync_within_throttle(
+        &self,
+        access_token: &str,
+        user_id: &str,
+        stream_id: Option<String>,
+        modifier: Option<ListenerKey>,
+    ) -> Result<SupabaseSyncResult, JsValue> {
+        if let Err(e) = EventStore::load_clock_baseline_from_local_storage(
+            &self.store,
+            &self.directories.current_user_directory_handle,
+        )
+        .await
+        {
+            log::warn!("Failed to load the clock baseline: {e:?}");
+        }
+
+        let throttle = self.throttling.throttle.borrow().clone();
+        let metered = is_metered_connection();
+        if throttle.max_bytes_per_session.is_none() && !metered {
+            let result = EventStore::sync_with_supabase(
+                &self.store,
+                access_token,
+                supabase::supabase_config(),
+                user_id,
+                stream_id,
+                modifier,
+            )
+            .await?;
+            self.save_clock_baseline().await;
+            return Ok(result);
+        }
+
+        let preview = EventStore::preview_sync_with_supabase(
+            &self.store,
+            access_token,
+            supabase::supabase_config(),
+            user_id,
+        )
+        .await?;
+        let plan = throttle.plan(&preview, self.throttling.bytes_synced.get(), metered);
+        if plan == SyncPlan::Sync {
+            let result = EventStore::sync_with_supabase(
+                &self.store,
+                access_token,
+                supabase::supabase_config(),
+                user_id,
+                stream_id,
+                modifier,
+            )
+            .await?;
+            self.save_clock_baseline().await;
+            self.add_bytes_synced(preview.bytes_to_upload + preview.estimated_bytes_to_download);
+            return Ok(result);
+        }
+
+        self.defer(DeferredWork::Sync { stream_id });
+        let mut uploaded_to_supabase = 0;
+        if plan == SyncPlan::UploadOnly {
+            uploaded_to_supabase = EventStore::push_to_supabase(
+                &self.store,
+                access_token,
+                supabase::supabase_config(),
+                user_id,
+            )
+            .await?;
+            self.add_bytes_synced(preview.bytes_to_upload);
+        }
+        Ok(SupabaseSyncResult {
+            uploaded_to_supabase,
+            downloaded_from_supabase: 0,
+        })
+    }
+
+    fn add_bytes_synced(&self, bytes: usize) {
+        let bytes_synced = &self.throttling.bytes_synced;
+        bytes_synced.set(bytes_synced.get().saturating_add(bytes));
+    }
+
+    /// Hands the deferred work to `on_resume` when the connection becomes unmetered. The listener
+    /// only holds a weak reference, so it doesn't keep the `Weapon` alive.
+    #[cfg(target_arch = "wasm32")]
+    fn listen_for_connection_changes(&self) {
+        use wasm_bindgen::JsCast as _;
+
+        if self
+            .throttling
+            .listening_for_connection_changes
+            .replace(true)
+        {
+            return;
+        }
+        let Some(connection) = network_information() else {
+            return;
+        };
+        let weapon = std::rc::Rc::downgrade(&self.state);
+        let on_change = Closure::<dyn FnMut()>::new(move || {
+            let Some(state) = weapon.upgrade() else {
+                return;
+            };
+            if is_metered_connection() {
+                return;
+            }
+            let weapon = Weapon { state };
+            let deferred = weapon.take_deferred_work();
+            if deferred.is_empty() {
+                return;
+            }
+            let on_resume = weapon.throttling.on_resume.borrow().clone();
+            match on_resume {
+                Some(on_resume) => {
+                    let deferred = serde_wasm_bindgen::to_value(&deferred).unwrap_or_default();
+                    let _ = on_resume.call1(&JsValue::null(), &deferred);
+                }
+                // Nobody to run it yet, so keep it for the next change
+                None => weapon.throttling.deferred.borrow_mut().extend(deferred),
+            }
+        });
+        let _ = connection
+            .unchecked_into::<web_sys::EventTarget>()
+            .add_event_listener_with_callback("change", on_change.as_ref().unchecked_ref());
+        // The listener lives as long as the page
+        on_change.forget();
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn listen_for_connection_changes(&self) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn preview(bytes_to_upload: usize, estimated_bytes_to_download: usize) -> SupabaseSyncPreview {
+        SupabaseSyncPreview {
+            events_to_upload: bytes_to_upload / 100,
+            bytes_to_upload,
+            events_to_download: estimated_bytes_to_download / 100,
+            estimated_bytes_to_download,
+        }
+    }
+
+    #[test]
+    fn test_sync_throttle_plan() {
+        let throttle = SyncThrottle::default();
+        let large_pull = preview(1_000, throttle.large_pull_bytes + 1);
+        assert_eq!(throttle.plan(&large_pull, 0, false), SyncPlan::Sync);
+        // On a metered connection a large pull waits, but the local events still go up
+        assert_eq!(throttle.plan(&large_pull, 0, true), SyncPlan::UploadOnly);
+        assert_eq!(
+            throttle.plan(&preview(0, throttle.large_pull_bytes + 1), 0, true),
+            SyncPlan::Defer
+        );
+        assert_eq!(
+            throttle.plan(&preview(1_000, 1_000), 0, true),
+            SyncPlan::Sync
+        );
+
+        // The session's budget counts what was already synced
+        let capped = SyncThrottle {
+            max_bytes_per_session: Some(10_000),
+            ..SyncThrottle::default()
+        };
+        assert_eq!(
+            capped.plan(&preview(1_000, 4_000), 5_000, false),
+            SyncPlan::Sync
+        );
+        assert_eq!(
+            capped.plan(&preview(1_000, 4_000), 6_000, false),
+            SyncPlan::UploadOnly
+        );
+        assert_eq!(
+            capped.plan(&preview(1_000, 4_000), 9_500, false),
+            SyncPlan::Defer
+        );
+    }
+
+    #[test]
+    fn test_sync_throttle_defaults_fill_in_missing_fields() {
+        let throttle: SyncThrottle = serde_json::from_str("{}").unwrap();
+        assert_eq!(throttle, SyncThrottle::default());
+        assert!(throttle.defer_audio_prefetch);
+    }
+}
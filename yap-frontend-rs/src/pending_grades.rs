@@ -0,0 +1,221 @@
+//! Autograde submissions that couldn't reach the server. Rather than losing the user's work, the
+//! frontend queues them with `Weapon::queue_pending_grade` and `Weapon::retry_pending_grades`
+//! sends them again, backing off after each failure (or for as long as the backend asks). The queue is kept in OPFS so it survives a
+//! reload, and once a submission is graded its event is added at the time the challenge was done.
+
+use chrono::{DateTime, Utc};
+use language_utils::local_grading::grade_translation_locally;
+use language_utils::{Lexeme, autograde, transcription_challenge};
+use opfs::{DirectoryHandle as _, FileHandle as _, WritableFileStream as _, persistent};
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::prelude::*;
+use yap_core::{DeckEvent, LanguageEvent, LanguageEventContent};
+
+use crate::backend::BackendError;
+
+const QUEUE_FILE_NAME: &str = "pending-grades";
+/// How long to wait before the first retry. Doubles after each failure.
+const INITIAL_BACKOFF_SECONDS: i64 = 30;
+/// Also how long to wait after an error the backend says won't go away by retrying, in case it's
+/// fixed on the backend in the meantime
+const MAX_BACKOFF_SECONDS: i64 = 60 * 60;
+
+/// A submission for the autograder, along with what's needed to turn its grade into an event
+#[derive(Clone, Debug, Serialize, Deserialize, tsify::Tsify)]
+#[tsify(into_wasm_abi, from_wasm_abi)]
+pub enum PendingGradeRequest {
+    Translation {
+        request: autograde::AutoGradeTranslationRequest,
+        words_tapped: Vec<Lexeme<String>>,
+        #[serde(default)]
+        favorite_practice: bool,
+    },
+    Transcription {
+        request: autograde::AutoGradeTranscriptionRequest,
+        level: Option<transcription_challenge::DictationLevel>,
+        input_mode: Option<transcription_challenge::InputMode>,
+    },
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub(crate) struct PendingGrade {
+    pub id: String,
+    /// When the challenge was done, which is also when its event is added
+    pub done_at: DateTime<Utc>,
+    /// The reviews stream of the sub-profile that did the challenge
+    pub stream_id: String,
+    pub request: PendingGradeRequest,
+    pub attempts: u32,
+    pub next_attempt_at: DateTime<Utc>,
+}
+
+impl PendingGrade {
+    pub fn new(request: PendingGradeRequest, done_at: DateTime<Utc>, stream_id: String) -> Self {
+        Self {
+            id: eyedee::get_uuid(),
+            done_at,
+            stream_id,
+            request,
+            attempts: 0,
+            next_attempt_at: Utc::now(),
+        }
+    }
+
+    pub fn failed(&mut self, now: DateTime<Utc>, error: &BackendError) {
+        self.attempts += 1;
+        let wait = if error.is_retryable() {
+            let retry_after = error.retry_after().map_or(0, |seconds| seconds as i64);
+            backoff_seconds(self.attempts).max(retry_after)
+        } else {
+            MAX_BACKOFF_SECONDS
+        };
+        self.next_attempt_at = now + chrono::Duration::seconds(wait);
+    }
+}
+
+fn backoff_seconds(attempts: u32) -> i64 {
+    INITIAL_BACKOFF_SECONDS
+        .saturating_mul(1 << attempts.saturating_sub(1).min(16))
+        .min(MAX_BACKOFF_SECONDS)
+}
+
+/// What `Weapon::retry_pending_grades` did
+#[derive(Clone, Debug, Serialize, Deserialize, tsify::Tsify)]
+#[tsify(into_wasm_abi, from_wasm_abi)]
+pub struct PendingGradesReport {
+    pub graded: usize,
+    pub remaining: usize,
+    /// When the earliest remaining submission should be retried, if there are any
+    pub next_retry_ms: Option<f64>,
+}
+
+impl PendingGradesReport {
+    pub(crate) fn new(graded: usize, queue: &[PendingGrade]) -> Self {
+        Self {
+            graded,
+            remaining: queue.len(),
+            next_retry_ms: queue
+                .iter()
+                .map(|pending| pending.next_attempt_at)
+                .min()
+                .map(|at| at.timestamp_millis() as f64),
+        }
+    }
+}
+
+/// Grades a queued submission, giving the event to add for it
+pub(crate) async fn grade(
+    request: &PendingGradeRequest,
+    access_token: Option<&String>,
+) -> Result<DeckEvent, BackendError> {
+    let (course, content) = match request.clone() {
+        PendingGradeRequest::Translation {
+            request,
+            words_tapped,
+            favorite_practice,
+        } => {
+            let course = request.course;
+            let challenge_sentence = request.challenge_sentence.clone();
+            let submission = request.user_sentence.clone();
+            let (primary_expression, lexemes) =
+                (request.primary_expression.clone(), request.lexemes.clone());
+            let response =
+                match crate::autograding::autograde_translation_on_server(request, access_token)
+                    .await?
+                {
+                    response
+                        if response.validation
+                            != transcription_challenge::GradeValidation::Invalid =>
+                    {
+                        response
+                    }
+                    // The translations aren't queued with the request, so all that can be said is
+                    // that the primary expression wasn't remembered
+                    _ => grade_translation_locally(&submission, &[], primary_expression, lexemes),
+                };
+            (
+                course,
+                LanguageEventContent::translation_wrong(
+                    challenge_sentence,
+                    submission,
+                    response.expressions_remembered,
+                    response.expressions_forgot,
+                    words_tapped,
+                    favorite_practice,
+                ),
+            )
+        }
+        PendingGradeRequest::Transcription {
+            request,
+            level,
+            input_mode,
+        } => {
+            let course = request.course;
+            let grade = match crate::autograding::autograde_transcription_with_server(
+                request.submission.clone(),
+                access_token,
+                course,
+                request.explanation_language,
+                request.mistake_history,
+            )
+            .await?
+            {
+                grade if grade.validation != transcription_challenge::GradeValidation::Invalid => {
+                    grade
+                }
+                // The server was reachable, so retrying wouldn't help
+                _ => crate::autograding::heuristic_transcription_grade(request.submission, course),
+            };
+            (
+                course,
+                LanguageEventContent::transcription(grade.results, level, input_mode),
+            )
+        }
+    };
+
+    Ok(DeckEvent::Language(LanguageEvent {
+        target_language: course.target_language,
+        native_language: course.native_language,
+        content,
+    }))
+}
+
+pub(crate) async fn load(
+    user_directory: &persistent::DirectoryHandle,
+) -> Result<Vec<PendingGrade>, persistent::Error> {
+    let Ok(file_handle) = user_directory
+        .get_file_handle_with_options(
+            QUEUE_FILE_NAME,
+            &opfs::GetFileHandleOptions { create: false },
+        )
+        .await
+    else {
+        return Ok(Vec::new());
+    };
+    let bytes = file_handle.read().await?;
+
+    Ok(serde_json::from_slice(&bytes)
+        .inspect_err(|e| log::error!("Pending grades were invalid: {e:?}"))
+        .unwrap_or_default())
+}
+
+pub(crate) async fn save(
+    user_directory: &persistent::DirectoryHandle,
+    queue: &[PendingGrade],
+) -> Result<(), JsValue> {
+    let json = serde_json::to_vec(queue).map_err(|e| JsValue::from_str(&e.to_string()))?;
+    let mut file_handle = user_directory
+        .get_file_handle_with_options(
+            QUEUE_FILE_NAME,
+            &opfs::GetFileHandleOptions { create: true },
+        )
+        .await?;
+    let mut writable = file_handle
+        .create_writable_with_options(&opfs::CreateWritableOptions {
+            keep_existing_data: false,
+        })
+        .await?;
+    writable.write_at_cursor_pos(json).await?;
+    writable.close().await?;
+    Ok(())
+}
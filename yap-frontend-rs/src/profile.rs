@@ -1,32 +1,24 @@
-use crate::utils::hit_ai_server;
+use crate::backend;
+use language_utils::backend_routes::{
+    CreateShareToken, Follow, GetFollowStatus, GetLanguageStats, GetProfile, GetShareTokens,
+    GetSharedDeck, GetSharedEvents, GetSharedStats, GetWebhook, RevokeShareToken, SetWebhook,
+    Unfollow, UpdateProfile,
+};
 use language_utils::profile::{
-    FollowRequest, FollowResponse, FollowStatus, Profile, UpdateProfileRequest,
-    UpdateProfileResponse, UserLanguageStats,
+    CreateShareTokenRequest, FollowRequest, GetProfileQuery, RevokeShareTokenRequest,
+    RevokeShareTokenResponse, ShareScope, ShareToken, SharedDeck, SharedEvents, SharedEventsQuery,
+    UpdateProfileRequest, UpdateProfileResponse, UserLanguageStats,
 };
+use language_utils::webhook::{SetWebhookRequest, Webhook};
 use wasm_bindgen::prelude::*;
 
 #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
 pub async fn get_profile_by_id(user_id: String) -> Result<JsValue, JsValue> {
-    let response = hit_ai_server(
-        fetch_happen::Method::GET,
-        &format!("/profile?id={user_id}"),
-        None::<()>,
-        None,
-    )
-    .await
-    .map_err(|e| JsValue::from_str(&format!("Request error: {e:?}")))?;
-
-    if !response.ok() {
-        return Err(JsValue::from_str(&format!(
-            "HTTP error: {}",
-            response.status()
-        )));
-    }
-
-    let profile: Profile = response
-        .json()
-        .await
-        .map_err(|e| JsValue::from_str(&format!("Response parsing error: {e:?}")))?;
+    let query = GetProfileQuery {
+        id: Some(user_id),
+        slug: None,
+    };
+    let profile = backend::call::<GetProfile>(&query, None).await?;
 
     serde_wasm_bindgen::to_value(&profile)
         .map_err(|e| JsValue::from_str(&format!("Serialization error: {e:?}")))
@@ -34,26 +26,11 @@ pub async fn get_profile_by_id(user_id: String) -> Result<JsValue, JsValue> {
 
 #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
 pub async fn get_profile_by_slug(slug: String) -> Result<JsValue, JsValue> {
-    let response = hit_ai_server(
-        fetch_happen::Method::GET,
-        &format!("/profile?slug={slug}"),
-        None::<()>,
-        None,
-    )
-    .await
-    .map_err(|e| JsValue::from_str(&format!("Request error: {e:?}")))?;
-
-    if !response.ok() {
-        return Err(JsValue::from_str(&format!(
-            "HTTP error: {}",
-            response.status()
-        )));
-    }
-
-    let profile: Profile = response
-        .json()
-        .await
-        .map_err(|e| JsValue::from_str(&format!("Response parsing error: {e:?}")))?;
+    let query = GetProfileQuery {
+        id: None,
+        slug: Some(slug),
+    };
+    let profile = backend::call::<GetProfile>(&query, None).await?;
 
     serde_wasm_bindgen::to_value(&profile)
         .map_err(|e| JsValue::from_str(&format!("Serialization error: {e:?}")))
@@ -67,52 +44,16 @@ pub async fn update_profile(
 ) -> Result<UpdateProfileResponse, JsValue> {
     let request = UpdateProfileRequest { display_name, bio };
 
-    let response = hit_ai_server(
-        fetch_happen::Method::PATCH,
-        "/profile",
-        Some(&request),
-        Some(&access_token),
-    )
-    .await
-    .map_err(|e| JsValue::from_str(&format!("Request error: {e:?}")))?;
-
-    if !response.ok() {
-        return Err(JsValue::from_str(&format!(
-            "HTTP error: {}",
-            response.status()
-        )));
-    }
-
-    let result: UpdateProfileResponse = response
-        .json()
-        .await
-        .map_err(|e| JsValue::from_str(&format!("Response parsing error: {e:?}")))?;
-
-    Ok(result)
+    Ok(backend::call::<UpdateProfile>(&request, Some(&access_token)).await?)
 }
 
 #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
 pub async fn get_user_language_stats_by_id(user_id: String) -> Result<JsValue, JsValue> {
-    let response = hit_ai_server(
-        fetch_happen::Method::GET,
-        &format!("/user-language-stats?id={user_id}"),
-        None::<()>,
-        None,
-    )
-    .await
-    .map_err(|e| JsValue::from_str(&format!("Request error: {e:?}")))?;
-
-    if !response.ok() {
-        return Err(JsValue::from_str(&format!(
-            "HTTP error: {}",
-            response.status()
-        )));
-    }
-
-    let stats: Vec<UserLanguageStats> = response
-        .json()
-        .await
-        .map_err(|e| JsValue::from_str(&format!("Response parsing error: {e:?}")))?;
+    let query = GetProfileQuery {
+        id: Some(user_id),
+        slug: None,
+    };
+    let stats = backend::call::<GetLanguageStats>(&query, None).await?;
 
     serde_wasm_bindgen::to_value(&stats)
         .map_err(|e| JsValue::from_str(&format!("Serialization error: {e:?}")))
@@ -120,26 +61,11 @@ pub async fn get_user_language_stats_by_id(user_id: String) -> Result<JsValue, J
 
 #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
 pub async fn get_user_language_stats_by_slug(slug: String) -> Result<JsValue, JsValue> {
-    let response = hit_ai_server(
-        fetch_happen::Method::GET,
-        &format!("/user-language-stats?slug={slug}"),
-        None::<()>,
-        None,
-    )
-    .await
-    .map_err(|e| JsValue::from_str(&format!("Request error: {e:?}")))?;
-
-    if !response.ok() {
-        return Err(JsValue::from_str(&format!(
-            "HTTP error: {}",
-            response.status()
-        )));
-    }
-
-    let stats: Vec<UserLanguageStats> = response
-        .json()
-        .await
-        .map_err(|e| JsValue::from_str(&format!("Response parsing error: {e:?}")))?;
+    let query = GetProfileQuery {
+        id: None,
+        slug: Some(slug),
+    };
+    let stats = backend::call::<GetLanguageStats>(&query, None).await?;
 
     serde_wasm_bindgen::to_value(&stats)
         .map_err(|e| JsValue::from_str(&format!("Serialization error: {e:?}")))
@@ -149,26 +75,7 @@ pub async fn get_user_language_stats_by_slug(slug: String) -> Result<JsValue, Js
 pub async fn follow_user(user_id: String, access_token: String) -> Result<JsValue, JsValue> {
     let request = FollowRequest { user_id };
 
-    let response = hit_ai_server(
-        fetch_happen::Method::POST,
-        "/follow",
-        Some(&request),
-        Some(&access_token),
-    )
-    .await
-    .map_err(|e| JsValue::from_str(&format!("Request error: {e:?}")))?;
-
-    if !response.ok() {
-        return Err(JsValue::from_str(&format!(
-            "HTTP error: {}",
-            response.status()
-        )));
-    }
-
-    let result: FollowResponse = response
-        .json()
-        .await
-        .map_err(|e| JsValue::from_str(&format!("Response parsing error: {e:?}")))?;
+    let result = backend::call::<Follow>(&request, Some(&access_token)).await?;
 
     serde_wasm_bindgen::to_value(&result)
         .map_err(|e| JsValue::from_str(&format!("Serialization error: {e:?}")))
@@ -178,26 +85,7 @@ pub async fn follow_user(user_id: String, access_token: String) -> Result<JsValu
 pub async fn unfollow_user(user_id: String, access_token: String) -> Result<JsValue, JsValue> {
     let request = FollowRequest { user_id };
 
-    let response = hit_ai_server(
-        fetch_happen::Method::POST,
-        "/unfollow",
-        Some(&request),
-        Some(&access_token),
-    )
-    .await
-    .map_err(|e| JsValue::from_str(&format!("Request error: {e:?}")))?;
-
-    if !response.ok() {
-        return Err(JsValue::from_str(&format!(
-            "HTTP error: {}",
-            response.status()
-        )));
-    }
-
-    let result: FollowResponse = response
-        .json()
-        .await
-        .map_err(|e| JsValue::from_str(&format!("Response parsing error: {e:?}")))?;
+    let result = backend::call::<Unfollow>(&request, Some(&access_token)).await?;
 
     serde_wasm_bindgen::to_value(&result)
         .map_err(|e| JsValue::from_str(&format!("Serialization error: {e:?}")))
@@ -210,34 +98,85 @@ pub async fn get_follow_status(
     access_token: String,
 ) -> Result<JsValue, JsValue> {
     let query = if let Some(id) = user_id {
-        format!("id={id}")
+        GetProfileQuery {
+            id: Some(id),
+            slug: None,
+        }
     } else if let Some(s) = slug {
-        format!("slug={s}")
+        GetProfileQuery {
+            id: None,
+            slug: Some(s),
+        }
     } else {
         return Err(JsValue::from_str("Either user_id or slug must be provided"));
     };
 
-    let response = hit_ai_server(
-        fetch_happen::Method::GET,
-        &format!("/follow-status?{query}"),
-        None::<()>,
-        Some(&access_token),
-    )
-    .await
-    .map_err(|e| JsValue::from_str(&format!("Request error: {e:?}")))?;
-
-    if !response.ok() {
-        return Err(JsValue::from_str(&format!(
-            "HTTP error: {}",
-            response.status()
-        )));
-    }
-
-    let status: FollowStatus = response
-        .json()
-        .await
-        .map_err(|e| JsValue::from_str(&format!("Response parsing error: {e:?}")))?;
+    let status = backend::call::<GetFollowStatus>(&query, Some(&access_token)).await?;
 
     serde_wasm_bindgen::to_value(&status)
         .map_err(|e| JsValue::from_str(&format!("Serialization error: {e:?}")))
 }
+
+/// The user's webhook for external automations, see `language_utils::webhook`
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+pub async fn get_webhook(access_token: String) -> Result<Option<Webhook>, JsValue> {
+    Ok(backend::call::<GetWebhook>(&(), Some(&access_token)).await?)
+}
+
+/// Sets up, changes or (with `url: None`) removes the user's webhook
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+pub async fn set_webhook(
+    request: SetWebhookRequest,
+    access_token: String,
+) -> Result<Option<Webhook>, JsValue> {
+    Ok(backend::call::<SetWebhook>(&request, Some(&access_token)).await?)
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+pub async fn get_share_tokens(access_token: String) -> Result<Vec<ShareToken>, JsValue> {
+    Ok(backend::call::<GetShareTokens>(&(), Some(&access_token)).await?)
+}
+
+/// A token letting whoever has it read the user's data, as far as `scope` allows. `label` is for
+/// telling tokens apart, e.g. who it was given to.
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+pub async fn create_share_token(
+    scope: ShareScope,
+    label: Option<String>,
+    access_token: String,
+) -> Result<ShareToken, JsValue> {
+    let request = CreateShareTokenRequest { scope, label };
+    Ok(backend::call::<CreateShareToken>(&request, Some(&access_token)).await?)
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+pub async fn revoke_share_token(
+    token: String,
+    access_token: String,
+) -> Result<RevokeShareTokenResponse, JsValue> {
+    let request = RevokeShareTokenRequest { token };
+    Ok(backend::call::<RevokeShareToken>(&request, Some(&access_token)).await?)
+}
+
+/// The language stats of whoever shared `token` with the viewer. Doesn't need a login: the share
+/// token is sent in place of one.
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+pub async fn get_shared_stats(token: String) -> Result<Vec<UserLanguageStats>, JsValue> {
+    Ok(backend::call::<GetSharedStats>(&(), Some(&token)).await?)
+}
+
+/// The words in the deck of whoever shared `token`, if it has `ShareScope::Deck`
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+pub async fn get_shared_deck(token: String) -> Result<SharedDeck, JsValue> {
+    Ok(backend::call::<GetSharedDeck>(&(), Some(&token)).await?)
+}
+
+/// A page of the review events of whoever shared `token`, if it has `ShareScope::FullHistory`
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+pub async fn get_shared_events(
+    token: String,
+    after_id: Option<i64>,
+) -> Result<SharedEvents, JsValue> {
+    let query = SharedEventsQuery { after_id };
+    Ok(backend::call::<GetSharedEvents>(&query, Some(&token)).await?)
+}
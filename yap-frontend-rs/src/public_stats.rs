@@ -0,0 +1,40 @@
+//! The user's public progress page, see `language_utils::public_stats`. The backend keeps the
+//! page up to date from the stats the app uploads when it syncs, so there's nothing to do here
+//! besides turning it on or off.
+
+use language_utils::backend_routes::{GetPublicStats, GetPublicStatsSettings, SetPublicStats};
+use language_utils::public_stats::{
+    GetPublicStatsQuery, PublicStats, PublicStatsSettings, SetPublicStatsRequest,
+};
+use wasm_bindgen::prelude::*;
+
+use crate::{Weapon, backend};
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+impl Weapon {
+    /// Publishes or unpublishes the user's progress page. Once it's on, the returned share id is
+    /// what goes in the link.
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub async fn set_public_stats(
+        &self,
+        enabled: bool,
+        access_token: String,
+    ) -> Result<PublicStatsSettings, JsValue> {
+        let request = SetPublicStatsRequest { enabled };
+        Ok(backend::call::<SetPublicStats>(&request, Some(&access_token)).await?)
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+    pub async fn get_public_stats_settings(
+        &self,
+        access_token: String,
+    ) -> Result<PublicStatsSettings, JsValue> {
+        Ok(backend::call::<GetPublicStatsSettings>(&(), Some(&access_token)).await?)
+    }
+}
+
+/// Someone's progress page, for rendering a share link. Doesn't need the viewer to be logged in.
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+pub async fn get_public_stats(share_id: String) -> Result<PublicStats, JsValue> {
+    Ok(backend::call::<GetPublicStats>(&GetPublicStatsQuery { share_id }, None).await?)
+}
@@ -0,0 +1,301 @@
+//! The user's own recordings of words, so they can compare how they say a word with its TTS audio.
+//! They're only kept on this device, in a `recordings` directory next to `AudioCache`'s `audio`
+//! one, with an index of them in `recordings.json`.
+
+use std::sync::{LazyLock, Mutex};
+
+use language_utils::{Heteronym, Language, Lexeme};
+use opfs::{DirectoryHandle as _, FileHandle as _, WritableFileStream as _, persistent};
+use wasm_bindgen::prelude::*;
+use xxhash_rust::const_xxh3::xxh3_64 as const_xxh3;
+use yap_core::{Deck, DictionaryEntryResolved};
+
+const INDEX_FILE_NAME: &str = "recordings.json";
+
+#[derive(Clone, Debug, PartialEq, serde::Serialize, serde::Deserialize, tsify::Tsify)]
+#[tsify(into_wasm_abi, from_wasm_abi)]
+pub struct Recording {
+    /// Pass this to `get_recording_audio` and `delete_recording`
+    pub id: String,
+    pub language: Language,
+    pub lexeme: Lexeme<String>,
+    pub recorded_at_ms: f64,
+    /// As the recording was made, e.g. "audio/webm;codecs=opus", so it can be played back
+    pub mime_type: String,
+}
+
+/// A word's dictionary entry, with what the user needs to compare their pronunciation with the
+/// TTS audio, see `get_word_detail`
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize, tsify::Tsify)]
+#[tsify(into_wasm_abi)]
+pub struct WordDetail {
+    pub entry: DictionaryEntryResolved,
+    /// The user's most recent recording of the word, if they've made one
+    pub latest_recording: Option<Recording>,
+}
+
+/// The index, once it's been read. It's kept in memory so `get_word_detail` doesn't have to wait
+/// for OPFS.
+static RECORDINGS: LazyLock<Mutex<Option<Vec<Recording>>>> = LazyLock::new(|| Mutex::new(None));
+
+struct RecordingStore {
+    recordings_dir: persistent::DirectoryHandle,
+}
+
+impl RecordingStore {
+    async fn new() -> Result<Self, persistent::Error> {
+        let root = persistent::app_specific_dir().await?;
+        let recordings_dir = root
+            .get_directory_handle_with_options(
+                "recordings",
+                &opfs::GetDirectoryHandleOptions { create: true },
+            )
+            .await?;
+        Ok(Self { recordings_dir })
+    }
+
+    fn audio_filename(id: &str) -> String {
+        format!("{id}.audio")
+    }
+
+    /// Reads the index the first time, and returns the one in memory after that
+    async fn index(&self) -> Result<Vec<Recording>, persistent::Error> {
+        let cached = RECORDINGS.lock().unwrap().clone();
+        if let Some(recordings) = cached {
+            return Ok(recordings);
+        }
+
+        let recordings = match self
+            .recordings_dir
+            .get_file_handle_with_options(
+                INDEX_FILE_NAME,
+                &opfs::GetFileHandleOptions { create: false },
+            )
+            .await
+        {
+            Ok(file_handle) => serde_json::from_slice(&file_handle.read().await?)
+                .inspect_err(|e| log::error!("Recordings index was invalid: {e:?}"))
+                .unwrap_or_default(),
+            Err(_) => Vec::new(),
+        };
+        *RECORDINGS.lock().unwrap() = Some(recordings.clone());
+        Ok(recordings)
+    }
+
+    async fn save_index(&self, recordings: Vec<Recording>) -> Result<(), persistent::Error> {
+        let json = serde_json::to_vec(&recordings).expect("recordings always serialize");
+        self.write(INDEX_FILE_NAME, json).await?;
+        *RECORDINGS.lock().unwrap() = Some(recordings);
+        Ok(())
+    }
+
+    async fn write(&self, filename: &str, bytes: Vec<u8>) -> Result<(), persistent::Error> {
+        let mut file_handle = self
+            .recordings_dir
+            .get_file_handle_with_options(filename, &opfs::GetFileHandleOptions { create: true })
+            .await?;
+        let mut writable = file_handle
+            .create_writable_with_options(&opfs::CreateWritableOptions {
+                keep_existing_data: false,
+            })
+            .await?;
+        writable.write_at_cursor_pos(bytes).await?;
+        writable.close().await?;
+        Ok(())
+    }
+
+    async fn save(&self, recording: Recording, audio: Vec<u8>) -> Result<(), persistent::Error> {
+        // The audio goes first, so the index never points at a file that isn't there
+        self.write(&Self::audio_filename(&recording.id), audio)
+            .await?;
+        let mut recordings = self.index().await?;
+        recordings.push(recording);
+        self.save_index(recordings).await
+    }
+
+    async fn audio(&self, id: &str) -> Result<Option<Vec<u8>>, persistent::Error> {
+        let Ok(file_handle) = self
+            .recordings_dir
+            .get_file_handle_with_options(
+                &Self::audio_filename(id),
+                &opfs::GetFileHandleOptions { create: false },
+            )
+            .await
+        else {
+            return Ok(None);
+        };
+        Ok(Some(file_handle.read().await?))
+    }
+
+    async fn delete(&self, id: &str) -> Result<(), persistent::Error> {
+        let mut recordings = self.index().await?;
+        recordings.retain(|recording| recording.id != id);
+        self.save_index(recordings).await?;
+        let mut recordings_dir = self.recordings_dir.clone();
+        if let Err(e) = recordings_dir.remove_entry(&Self::audio_filename(id)).await {
+            log::warn!("Failed to remove recording {id}: {e:?}");
+        }
+        Ok(())
+    }
+}
+
+/// Which of `recordings` `get_recordings` returns
+fn recordings_of(
+    recordings: &[Recording],
+    language: Language,
+    lexeme: Option<&Lexeme<String>>,
+) -> Vec<Recording> {
+    let mut recordings = recordings
+        .iter()
+        .filter(|recording| {
+            recording.language == language
+                && lexeme.is_none_or(|lexeme| &recording.lexeme == lexeme)
+        })
+        .cloned()
+        .collect::<Vec<_>>();
+    recordings.sort_by(|a, b| b.recorded_at_ms.total_cmp(&a.recorded_at_ms));
+    recordings
+}
+
+fn js_error(e: persistent::Error) -> JsValue {
+    JsValue::from_str(&format!("Recordings error: {e:?}"))
+}
+
+/// Reads the index into memory, so the first `get_word_detail` has the recordings
+pub(crate) async fn load_index() -> Result<(), persistent::Error> {
+    RecordingStore::new().await?.index().await.map(|_| ())
+}
+
+/// Saves a recording of the user saying `lexeme`, e.g. from a `MediaRecorder`
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+pub async fn save_recording(
+    language: Language,
+    lexeme: Lexeme<String>,
+    audio: Vec<u8>,
+    mime_type: String,
+    timestamp_ms: f64,
+) -> Result<Recording, JsValue> {
+    let id = format!(
+        "{:016x}",
+        const_xxh3(format!("{language:?}:{lexeme:?}:{timestamp_ms}").as_bytes())
+    );
+    let recording = Recording {
+        id,
+        language,
+        lexeme,
+        recorded_at_ms: timestamp_ms,
+        mime_type,
+    };
+    RecordingStore::new()
+        .await
+        .map_err(js_error)?
+        .save(recording.clone(), audio)
+        .await
+        .map_err(js_error)?;
+    Ok(recording)
+}
+
+/// The recordings of `lexeme`, or of every word if it's `None`, in `language`, newest first
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+pub async fn get_recordings(
+    language: Language,
+    lexeme: Option<Lexeme<String>>,
+) -> Result<Vec<Recording>, JsValue> {
+    let recordings = RecordingStore::new()
+        .await
+        .map_err(js_error)?
+        .index()
+        .await
+        .map_err(js_error)?;
+    Ok(recordings_of(&recordings, language, lexeme.as_ref()))
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+pub async fn get_recording_audio(id: String) -> Result<js_sys::Uint8Array, JsValue> {
+    let audio = RecordingStore::new()
+        .await
+        .map_err(js_error)?
+        .audio(&id)
+        .await
+        .map_err(js_error)?
+        .ok_or_else(|| JsValue::from_str(&format!("No recording {id}")))?;
+    Ok(js_sys::Uint8Array::from(&audio[..]))
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+pub async fn delete_recording(id: String) -> Result<(), JsValue> {
+    RecordingStore::new()
+        .await
+        .map_err(js_error)?
+        .delete(&id)
+        .await
+        .map_err(js_error)
+}
+
+/// `Deck::get_dictionary_entry`, with the user's latest recording of the word so the UI can offer
+/// to play it next to the TTS audio
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+pub fn get_word_detail(deck: &Deck, heteronym: Heteronym<String>) -> Option<WordDetail> {
+    let language = deck.get_target_language();
+    let lexeme = Lexeme::Heteronym(heteronym.clone());
+    let entry = deck.get_dictionary_entry(heteronym)?;
+    let latest_recording = RECORDINGS
+        .lock()
+        .unwrap()
+        .as_deref()
+        .and_then(|recordings| {
+            recordings_of(recordings, language, Some(&lexeme))
+                .into_iter()
+                .next()
+        });
+    Some(WordDetail {
+        entry,
+        latest_recording,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn recording(id: &str, language: Language, word: &str, recorded_at_ms: f64) -> Recording {
+        Recording {
+            id: id.to_string(),
+            language,
+            lexeme: Lexeme::Heteronym(Heteronym {
+                word: word.to_string(),
+                lemma: word.to_string(),
+                pos: language_utils::PartOfSpeech::Noun,
+            }),
+            recorded_at_ms,
+            mime_type: "audio/webm".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_recordings_of_a_word_are_newest_first() {
+        let recordings = vec![
+            recording("old chat", Language::French, "chat", 1.0),
+            recording("chien", Language::French, "chien", 2.0),
+            recording("new chat", Language::French, "chat", 3.0),
+            recording("spanish", Language::Spanish, "chat", 4.0),
+        ];
+        let ids = |recordings: Vec<Recording>| {
+            recordings
+                .into_iter()
+                .map(|recording| recording.id)
+                .collect::<Vec<_>>()
+        };
+
+        let chat = recordings[0].lexeme.clone();
+        assert_eq!(
+            ids(recordings_of(&recordings, Language::French, Some(&chat))),
+            vec!["new chat", "old chat"]
+        );
+        assert_eq!(
+            ids(recordings_of(&recordings, Language::French, None)),
+            vec!["new chat", "chien", "old chat"]
+        );
+        assert!(recordings_of(&recordings, Language::German, None).is_empty());
+    }
+}
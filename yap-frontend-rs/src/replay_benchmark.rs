@@ -0,0 +1,295 @@
+//! An instrumented replay of the deck, for comparing how long replaying takes on the main thread
+//! and in a worker, or in two builds, on real users' data. Replaying is what happens when there's
+//! no usable snapshot, so it's the slowest path to a deck.
+//!
+//! Allocations are counted by wrapping the global allocator. Counting is a relaxed atomic add
+//! per allocation, so it's left on in every build rather than needing a special one.
+
+#[cfg(target_arch = "wasm32")]
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+#[cfg(target_arch = "wasm32")]
+use language_utils::Course;
+use serde::Serialize;
+#[cfg(target_arch = "wasm32")]
+use wasm_bindgen::prelude::*;
+use weapon::PartialAppState;
+#[cfg(target_arch = "wasm32")]
+use weapon::data_model::EventType;
+use weapon::data_model::Timestamped;
+#[cfg(target_arch = "wasm32")]
+use yap_core::{Deck, DeckEvent};
+
+#[cfg(target_arch = "wasm32")]
+use crate::{FetchedLanguagePack, Weapon, initial_deck_state};
+
+/// Events are timed in chunks of this many, so slowdowns late in a long history stand out
+const EVENTS_PER_CHUNK: usize = 1000;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+static ALLOCATED_BYTES: AtomicUsize = AtomicUsize::new(0);
+
+#[cfg(target_arch = "wasm32")]
+struct CountingAllocator;
+
+#[cfg(target_arch = "wasm32")]
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        ALLOCATED_BYTES.fetch_add(layout.size(), Ordering::Relaxed);
+        unsafe { System.alloc(layout) }
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        ALLOCATED_BYTES.fetch_add(layout.size(), Ordering::Relaxed);
+        unsafe { System.alloc_zeroed(layout) }
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        ALLOCATED_BYTES.fetch_add(new_size, Ordering::Relaxed);
+        unsafe { System.realloc(ptr, layout, new_size) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        unsafe { System.dealloc(ptr, layout) }
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+/// `performance.now()`, which exists on the main thread and in workers alike
+#[cfg(target_arch = "wasm32")]
+fn now_ms() -> f64 {
+    js_sys::Reflect::get(&js_sys::global(), &JsValue::from_str("performance"))
+        .ok()
+        .and_then(|performance| performance.dyn_into::<web_sys::Performance>().ok())
+        .map_or_else(js_sys::Date::now, |performance| performance.now())
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn now_ms() -> f64 {
+    chrono::Utc::now().timestamp_micros() as f64 / 1000.0
+}
+
+#[derive(Clone, Copy, Debug, Serialize)]
+struct Measurement {
+    events: usize,
+    ms: f64,
+    allocations: usize,
+    /// Reallocations count their whole new size
+    allocated_bytes: usize,
+}
+
+fn measure<T>(events: usize, f: impl FnOnce() -> T) -> (T, Measurement) {
+    let allocations = ALLOCATIONS.load(Ordering::Relaxed);
+    let allocated_bytes = ALLOCATED_BYTES.load(Ordering::Relaxed);
+    let start = now_ms();
+    let result = f();
+    let measurement = Measurement {
+        events,
+        ms: now_ms() - start,
+        allocations: ALLOCATIONS.load(Ordering::Relaxed) - allocations,
+        allocated_bytes: ALLOCATED_BYTES.load(Ordering::Relaxed) - allocated_bytes,
+    };
+    (result, measurement)
+}
+
+/// What `replay` measured
+struct ReplayMeasurements {
+    skipped_events: usize,
+    chunks: Vec<Measurement>,
+    finalize: Measurement,
+    total: Measurement,
+}
+
+/// Applies `events` to `initial_state` the way a normal replay does, measuring each
+/// `EVENTS_PER_CHUNK` of them
+fn replay<A: PartialAppState>(
+    initial_state: A::Partial,
+    events: &[Timestamped<A::Event>],
+) -> (A, ReplayMeasurements) {
+    let mut chunks = Vec::new();
+    let mut skipped_events = 0;
+    let ((state, finalize), total) = measure(events.len(), || {
+        let mut partial = initial_state;
+        for chunk in events.chunks(EVENTS_PER_CHUNK) {
+            let (next, measurement) = measure(chunk.len(), || {
+                chunk.iter().fold(partial, |state, event| {
+                    match A::check_event(&state, event) {
+                        Ok(()) => A::process_event(state, event),
+                        Err(_) => {
+                            skipped_events += 1;
+                            state
+                        }
+                    }
+                })
+            });
+            partial = next;
+            chunks.push(measurement);
+        }
+        measure(0, || A::finalize(partial))
+    });
+    let measurements = ReplayMeasurements {
+        skipped_events,
+        chunks,
+        finalize,
+        total,
+    };
+    (state, measurements)
+}
+
+#[cfg(target_arch = "wasm32")]
+#[derive(Debug, Serialize)]
+struct ReplayBenchmark {
+    app_version: &'static str,
+    /// "main_thread" or "worker"
+    context: &'static str,
+    course: Course,
+    /// Events `check_event` refused, which are skipped like in a normal replay
+    skipped_events: usize,
+    /// Each `EVENTS_PER_CHUNK` events, in the order they were replayed
+    chunks: Vec<Measurement>,
+    /// Computing the deck's derived state once every event is applied
+    finalize: Measurement,
+    total: Measurement,
+}
+
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen]
+impl Weapon {
+    /// Replays the deck for `course` from the first review, timing it and counting allocations
+    /// along the way, and returns the measurements as JSON. The deck isn't kept, so this doesn't
+    /// change anything the app shows.
+    #[wasm_bindgen]
+    pub async fn benchmark_replay(
+        &self,
+        language_pack: &FetchedLanguagePack,
+        course: Course,
+    ) -> Result<String, JsValue> {
+        self.load_full_history(self.reviews_stream_id()).await?;
+        let store = self.store.borrow();
+        let sub_profile = self.sub_profile.borrow();
+        let initial_state =
+            initial_deck_state(&store, sub_profile.as_deref(), language_pack, course);
+        let events = store
+            .get::<EventType<DeckEvent>>(self.reviews_stream_id().into())
+            .map(|stream| {
+                stream
+                    .iter()
+                    .filter_map(|event| match &event.event {
+                        EventType::User(user_event) => Some(Timestamped {
+                            event: user_event.clone(),
+                            timestamp: event.timestamp,
+                            within_device_events_index: event.within_device_events_index,
+                        }),
+                        EventType::Meta(_) | EventType::Unknown(_) => None,
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_default();
+
+        let (
+            _,
+            ReplayMeasurements {
+                skipped_events,
+                chunks,
+                finalize,
+                total,
+            },
+        ) = replay::<Deck>(initial_state, &events);
+
+        let benchmark = ReplayBenchmark {
+            app_version: env!("CARGO_PKG_VERSION"),
+            context: if web_sys::window().is_some() {
+                "main_thread"
+            } else {
+                "worker"
+            },
+            course,
+            skipped_events,
+            chunks,
+            finalize,
+            total,
+        };
+        serde_json::to_string_pretty(&benchmark)
+            .map_err(|e| JsValue::from_str(&format!("Serialization error: {e:?}")))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use weapon::data_model::Event;
+
+    #[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, serde::Deserialize)]
+    struct Step(u32);
+
+    impl Event for Step {
+        fn to_json(&self) -> Result<serde_json::Value, serde_json::Error> {
+            serde_json::to_value(self)
+        }
+
+        fn from_json(json: &serde_json::Value) -> Result<Self, serde_json::Error> {
+            serde_json::from_value(json.clone())
+        }
+    }
+
+    /// Adds up steps, but refuses ones of 0
+    #[derive(Debug, PartialEq)]
+    struct Total(u32);
+
+    impl PartialAppState for Total {
+        type Event = Step;
+        type Partial = u32;
+
+        fn process_event(partial: u32, event: &Timestamped<Step>) -> u32 {
+            partial + event.event.0
+        }
+
+        fn check_event(_partial: &u32, event: &Timestamped<Step>) -> Result<(), String> {
+            if event.event.0 == 0 {
+                return Err("empty step".to_string());
+            }
+            Ok(())
+        }
+
+        fn finalize(partial: u32) -> Total {
+            Total(partial)
+        }
+    }
+
+    #[test]
+    fn test_replay_measures_each_chunk_and_skips_refused_events() {
+        let events = (0..2500)
+            .map(|index| Timestamped {
+                timestamp: chrono::DateTime::from_timestamp(index as i64, 0).unwrap(),
+                within_device_events_index: index,
+                event: Step(if index % 1000 == 999 { 0 } else { 1 }),
+            })
+            .collect::<Vec<_>>();
+
+        let (total, measurements) = replay::<Total>(0, &events);
+        assert_eq!(total, Total(2498));
+        assert_eq!(measurements.skipped_events, 2);
+        assert_eq!(
+            measurements
+                .chunks
+                .iter()
+                .map(|chunk| chunk.events)
+                .collect::<Vec<_>>(),
+            vec![1000, 1000, 500]
+        );
+        assert_eq!(measurements.finalize.events, 0);
+        assert_eq!(measurements.total.events, 2500);
+        assert!(measurements.total.ms >= 0.0);
+
+        let (total, measurements) = replay::<Total>(7, &[]);
+        assert_eq!(total, Total(7));
+        assert!(measurements.chunks.is_empty());
+    }
+}
@@ -0,0 +1,220 @@
+//! Opt-in scheduler telemetry (see `language_utils::scheduler_telemetry`). The histogram is the
+//! user's due reviews from `Deck::get_retention_stats`, by interval bucket and whether the card was
+//! remembered, with Laplace noise added to every count before it leaves the device.
+//!
+//! Adding or removing a single review changes one count by one, so noise with a scale of
+//! `1 / EPSILON` makes each upload `EPSILON`-differentially private for individual reviews.
+//! Fresh noise every upload would let the server average it away, so a device uploads at most
+//! once per `WINDOW_DAYS`, covering reviews that weren't in the previous upload.
+
+use chrono::{DateTime, Duration, Utc};
+use language_utils::backend_routes::SubmitSchedulerTelemetry;
+use language_utils::scheduler_telemetry::{NoisedIntervalCounts, SchedulerTelemetry};
+use opfs::{DirectoryHandle as _, FileHandle as _, WritableFileStream as _, persistent};
+use wasm_bindgen::prelude::*;
+use yap_core::{Deck, IntervalBucket, RetentionStats};
+
+use crate::{Weapon, backend};
+
+/// The privacy budget of each upload
+const EPSILON: f64 = 1.0;
+
+/// How many days of reviews an upload covers, and how long a device waits between uploads
+const WINDOW_DAYS: u32 = 30;
+
+/// Under the weapon directory, holding when this device last uploaded a histogram
+const SUBMITTED_FILE_NAME: &str = "scheduler-telemetry-submitted";
+
+/// A sample from the Laplace distribution centered on 0, given `uniform`, a sample from the
+/// uniform distribution on [0, 1)
+fn laplace_noise(scale: f64, uniform: f64) -> f64 {
+    let u = uniform - 0.5;
+    -scale * u.signum() * (1.0 - 2.0 * u.abs()).max(f64::MIN_POSITIVE).ln()
+}
+
+fn noised(count: u32, uniform: f64) -> u32 {
+    (f64::from(count) + laplace_noise(1.0 / EPSILON, uniform))
+        .round()
+        .max(0.0) as u32
+}
+
+fn max_interval_days(bucket: IntervalBucket) -> Option<u32> {
+    match bucket {
+        IntervalBucket::UpToADay => Some(1),
+        IntervalBucket::UpToAWeek => Some(7),
+        IntervalBucket::UpToAMonth => Some(30),
+        IntervalBucket::UpToThreeMonths => Some(90),
+        IntervalBucket::Longer => None,
+    }
+}
+
+/// `random` gives samples from the uniform distribution on [0, 1), like `Math.random`
+fn noised_histogram(stats: &RetentionStats, mut random: impl FnMut() -> f64) -> SchedulerTelemetry {
+    SchedulerTelemetry {
+        app_version: env!("CARGO_PKG_VERSION").to_string(),
+        epsilon: EPSILON,
+        window_days: stats.window_days,
+        buckets: stats
+            .by_interval
+            .iter()
+            .map(|bucket| NoisedIntervalCounts {
+                max_interval_days: max_interval_days(bucket.interval),
+                remembered: noised(bucket.retention.remembered, random()),
+                forgot: noised(
+                    bucket.retention.reviews - bucket.retention.remembered,
+                    random(),
+                ),
+            })
+            .collect(),
+    }
+}
+
+async fn last_submitted(
+    weapon_directory: &persistent::DirectoryHandle,
+) -> Result<Option<DateTime<Utc>>, persistent::Error> {
+    let Ok(file_handle) = weapon_directory
+        .get_file_handle_with_options(
+            SUBMITTED_FILE_NAME,
+            &opfs::GetFileHandleOptions { create: false },
+        )
+        .await
+    else {
+        return Ok(None);
+    };
+    let bytes = file_handle.read().await?;
+    Ok(serde_json::from_slice(&bytes).ok())
+}
+
+async fn save_submitted(
+    weapon_directory: &persistent::DirectoryHandle,
+    submitted_at: DateTime<Utc>,
+) -> Result<(), persistent::Error> {
+    let json = serde_json::to_vec(&submitted_at).expect("timestamps serialize");
+    let mut file_handle = weapon_directory
+        .get_file_handle_with_options(
+            SUBMITTED_FILE_NAME,
+            &opfs::GetFileHandleOptions { create: true },
+        )
+        .await?;
+    let mut writable = file_handle
+        .create_writable_with_options(&opfs::CreateWritableOptions {
+            keep_existing_data: false,
+        })
+        .await?;
+    writable.write_at_cursor_pos(json).await?;
+    writable.close().await?;
+    Ok(())
+}
+
+#[wasm_bindgen]
+impl Weapon {
+    /// Uploads a noised histogram of the due reviews in `deck` over the last `WINDOW_DAYS`, if the
+    /// user opted in (see `get_share_scheduler_telemetry`) and this device hasn't uploaded one in
+    /// that time. It's sent without an access token. Returns whether it was uploaded.
+    #[wasm_bindgen]
+    pub async fn submit_scheduler_telemetry(
+        &self,
+        deck: &Deck,
+        timestamp_ms: f64,
+    ) -> Result<bool, JsValue> {
+        if !self.get_share_scheduler_telemetry() {
+            return Ok(false);
+        }
+        let weapon_directory = &self.directories.weapon_directory_handle;
+        let now = DateTime::<Utc>::from_timestamp_millis(timestamp_ms as i64)
+            .ok_or_else(|| JsValue::from_str(&format!("Invalid timestamp {timestamp_ms}")))?;
+        let last_submitted = last_submitted(weapon_directory)
+            .await
+            .map_err(|e| JsValue::from_str(&format!("{e:?}")))?;
+        if last_submitted.is_some_and(|at| now - at < Duration::days(WINDOW_DAYS.into())) {
+            return Ok(false);
+        }
+
+        let telemetry = noised_histogram(
+            &deck.get_retention_stats(WINDOW_DAYS, timestamp_ms),
+            js_sys::Math::random,
+        );
+        backend::call::<SubmitSchedulerTelemetry>(&telemetry, None).await?;
+        save_submitted(weapon_directory, now)
+            .await
+            .map_err(|e| JsValue::from_str(&format!("{e:?}")))?;
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLES: u32 = 100_000;
+
+    /// Evenly spread samples standing in for `Math.random`
+    fn uniform_samples() -> impl Iterator<Item = f64> {
+        (0..SAMPLES).map(|i| (f64::from(i) + 0.5) / f64::from(SAMPLES))
+    }
+
+    #[test]
+    fn test_laplace_noise_bounds() {
+        let scale = 1.0 / EPSILON;
+        assert_eq!(laplace_noise(scale, 0.5), 0.0);
+        // Even the most extreme sample gives finite noise
+        let bound = -scale * f64::MIN_POSITIVE.ln();
+        for uniform in [0.0, 1.0 - f64::EPSILON] {
+            let noise = laplace_noise(scale, uniform);
+            assert!(noise.is_finite() && noise.abs() <= bound, "{noise}");
+        }
+
+        for uniform in uniform_samples() {
+            let noise = laplace_noise(scale, uniform);
+            assert!(noise.abs() <= bound);
+            assert!((noise + laplace_noise(scale, 1.0 - uniform)).abs() < 1e-9);
+        }
+        // Half of the Laplace distribution is within `scale * ln 2` of 0
+        let within = uniform_samples()
+            .filter(|uniform| laplace_noise(scale, *uniform).abs() <= scale * 2f64.ln())
+            .count();
+        assert!(
+            (within as f64 / f64::from(SAMPLES) - 0.5).abs() < 0.001,
+            "{within}"
+        );
+    }
+
+    #[test]
+    fn test_noised_counts_hide_the_exact_count() {
+        let outcomes = |count: u32| {
+            let mut outcomes = std::collections::BTreeMap::<u32, u32>::new();
+            for uniform in uniform_samples() {
+                *outcomes.entry(noised(count, uniform)).or_default() += 1;
+            }
+            outcomes
+        };
+        let ten = outcomes(10);
+        let eleven = outcomes(11);
+
+        // Most of the time the uploaded count isn't the real one
+        let exact = f64::from(ten[&10]) / f64::from(SAMPLES);
+        assert!(
+            (exact - (1.0 - (-0.5 * EPSILON).exp())).abs() < 0.001,
+            "{exact}"
+        );
+        assert!(exact < 0.5);
+
+        // And whatever was uploaded, a count one higher was about as likely to have given it,
+        // which is what makes an upload `EPSILON`-differentially private
+        for (value, times) in &ten {
+            let Some(times_eleven) = eleven.get(value) else {
+                assert!(*times < 1000, "{value} only comes from 10");
+                continue;
+            };
+            let ratio = f64::from(*times) / f64::from(*times_eleven);
+            if times.min(times_eleven) >= &1000 {
+                assert!(ratio <= EPSILON.exp() * 1.01, "{value}: {ratio}");
+                assert!(ratio >= (-EPSILON).exp() / 1.01, "{value}: {ratio}");
+            }
+        }
+
+        // Noise that would make a count negative rounds it up to 0
+        assert_eq!(noised(0, 0.0), 0);
+        assert_eq!(noised(0, 0.25), 0);
+    }
+}
@@ -0,0 +1,74 @@
+//! Preferences that follow the user across devices, kept in weapon's settings stream (see
+//! `weapon::settings`). They used to be in each device's localStorage.
+
+use std::collections::BTreeMap;
+
+use language_utils::Language;
+use weapon::settings::Setting;
+use yap_core::{ChallengeRequirements, ChallengeTypeSchedule, SentenceLength};
+
+/// Challenge types the user doesn't want, e.g. listening while on the bus
+pub const BANNED_CHALLENGE_TYPES: Setting<Vec<ChallengeRequirements>> =
+    Setting::new("banned_challenge_types");
+
+/// Challenge types banned only at certain times, on top of `BANNED_CHALLENGE_TYPES`, e.g. no
+/// speaking on weekday mornings
+pub const CHALLENGE_TYPE_SCHEDULES: Setting<Vec<ChallengeTypeSchedule>> =
+    Setting::new("challenge_type_schedules");
+
+/// Playback rate of challenge audio, where 1.0 is normal speed
+pub const AUDIO_SPEED: Setting<f64> = Setting::new("audio_speed");
+
+pub const GRADING_STRICTNESS: Setting<GradingStrictness> = Setting::new("grading_strictness");
+
+/// How long challenge sentences should be, see `ReviewInfo::set_sentence_length`
+pub const SENTENCE_LENGTH: Setting<SentenceLength> = Setting::new("sentence_length");
+
+/// By language, the spelling standard (one of the pack's spelling variants, e.g. "UK") to show
+/// sentences in and to have them spoken in, see `Weapon::respell`
+pub const PREFERRED_SPELLINGS: Setting<BTreeMap<Language, String>> =
+    Setting::new("preferred_spellings");
+
+/// Whether crash reports are uploaded, see `Weapon::submit_crash_reports`. Off unless the user opts
+/// in.
+pub const SHARE_CRASH_REPORTS: Setting<bool> = Setting::new("share_crash_reports");
+
+/// Whether noised histograms of how reviews went are uploaded, see
+/// `Weapon::submit_scheduler_telemetry`. Off unless the user opts in.
+pub const SHARE_SCHEDULER_TELEMETRY: Setting<bool> = Setting::new("share_scheduler_telemetry");
+
+/// How picky grading is about small mistakes like accents and typos
+#[derive(Clone, Copy, Debug, Default, serde::Serialize, serde::Deserialize, PartialEq, Eq)]
+#[cfg_attr(target_arch = "wasm32", derive(tsify::Tsify))]
+#[cfg_attr(target_arch = "wasm32", tsify(into_wasm_abi, from_wasm_abi))]
+pub enum GradingStrictness {
+    Lenient,
+    #[default]
+    Normal,
+    Strict,
+}
+
+/// Checks that `value` fits the setting `key`, if it's one the app knows about. Settings set from
+/// JS go through this, so a typo there can't break reading the setting on every device.
+pub(crate) fn validate(key: &str, value: &serde_json::Value) -> Result<(), String> {
+    fn check<T: serde::de::DeserializeOwned>(
+        setting: &Setting<T>,
+        value: &serde_json::Value,
+    ) -> Result<(), String> {
+        serde_json::from_value::<T>(value.clone())
+            .map(|_| ())
+            .map_err(|e| format!("Invalid value for setting {}: {e}", setting.key))
+    }
+
+    match key {
+        key if key == BANNED_CHALLENGE_TYPES.key => check(&BANNED_CHALLENGE_TYPES, value),
+        key if key == CHALLENGE_TYPE_SCHEDULES.key => check(&CHALLENGE_TYPE_SCHEDULES, value),
+        key if key == AUDIO_SPEED.key => check(&AUDIO_SPEED, value),
+        key if key == GRADING_STRICTNESS.key => check(&GRADING_STRICTNESS, value),
+        key if key == SENTENCE_LENGTH.key => check(&SENTENCE_LENGTH, value),
+        key if key == PREFERRED_SPELLINGS.key => check(&PREFERRED_SPELLINGS, value),
+        key if key == SHARE_CRASH_REPORTS.key => check(&SHARE_CRASH_REPORTS, value),
+        key if key == SHARE_SCHEDULER_TELEMETRY.key => check(&SHARE_SCHEDULER_TELEMETRY, value),
+        _ => Ok(()),
+    }
+}
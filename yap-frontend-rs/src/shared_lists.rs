@@ -0,0 +1,40 @@
+//! Sharing vocabulary lists between users, see `language_utils::shared_list`. Either encode the
+//! whole list into a code with `encode_shared_list`, or upload it with `share_list` for a short
+//! one. `Deck::import_shared_list` turns a list into cards on the recipient's deck.
+
+use base64::Engine;
+use language_utils::backend_routes::{GetSharedList, ShareList};
+use language_utils::shared_list::{GetSharedListQuery, ShareListRequest, SharedList};
+use wasm_bindgen::prelude::*;
+
+use crate::backend;
+
+/// A self-contained code for `list`, safe to put in a URL. Long lists make long codes, so prefer
+/// `share_list` when the user is logged in.
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+pub fn encode_shared_list(list: SharedList) -> Result<String, JsValue> {
+    let json = serde_json::to_vec(&list)
+        .map_err(|e| JsValue::from_str(&format!("Serialization error: {e:?}")))?;
+    Ok(base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(json))
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+pub fn decode_shared_list(code: String) -> Result<SharedList, JsValue> {
+    let json = base64::engine::general_purpose::URL_SAFE_NO_PAD
+        .decode(code.trim())
+        .map_err(|e| JsValue::from_str(&format!("Invalid list code: {e:?}")))?;
+    serde_json::from_slice(&json).map_err(|e| JsValue::from_str(&format!("Invalid list: {e:?}")))
+}
+
+/// Uploads `list` and returns the short code it can be fetched with
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+pub async fn share_list(list: SharedList, access_token: String) -> Result<String, JsValue> {
+    let response =
+        backend::call::<ShareList>(&ShareListRequest { list }, Some(&access_token)).await?;
+    Ok(response.code)
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+pub async fn get_shared_list(code: String) -> Result<SharedList, JsValue> {
+    Ok(backend::call::<GetSharedList>(&GetSharedListQuery { code }, None).await?)
+}